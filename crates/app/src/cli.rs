@@ -0,0 +1,765 @@
+//! Non-interactive commands for scripting against the library database.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use bookshelf_application::{
+    BootReader, CollectionFilter, LibraryQuery, TagMatchMode, book_author,
+    matches_collection_filter, matches_tag_filter, parse_reader_mode,
+};
+use bookshelf_core::{
+    Book, BookLabels, PageCount, ReaderMode, ReaderTextMode, ReadingStatus, decode_path,
+    display_path, encode_path,
+};
+use bookshelf_engine::{Engine, TextLayout};
+use bookshelf_storage::Storage;
+use serde::Serialize;
+
+const USAGE: &str = "\
+usage: app [--db PATH] [COMMAND]
+
+Without a command the library opens in the terminal UI.
+
+  --db PATH               use the library database at PATH instead of
+                          .bookshelf/bookshelf.db here (or $BOOKSHELF_DB when set);
+                          `--config PATH` works too
+
+commands:
+  list [--tag TAG]... [--all-tags] [--collection NAME | --no-collection]
+       [--favorites] [--status unread|reading|finished] [--json]
+                          list books as of the last library scan, optionally filtered
+  search QUERY [--json]   list books matching a library query (title, author, path,
+                          tag:, col:, fav:, author:, series:)
+  open PATH [--page N] [--mode image|text]
+                          open a book straight in the reader, at page N (1-based)
+                          or else where it was left; `--open PATH ...` works too
+  cat PATH (--pages N[-M] | --all) [--mode raw|wrap|reflow] [--trim-furniture]
+      [--separator SEP]   print a book's text page by page, each page followed by SEP
+                          (a form feed unless given; \\n, \\t and \\f are understood)
+  --export-labels FILE    write favorites, collections, tags, authors and series to a
+                          JSON file
+  --import-labels FILE    merge labels from a JSON file";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Command {
+    /// No arguments: run the terminal UI.
+    Tui,
+    List(ListOptions),
+    Search {
+        query: String,
+        json: bool,
+    },
+    Open {
+        path: String,
+        /// 1-based page; `None` resumes at the saved page.
+        page: Option<u32>,
+        mode: Option<ReaderMode>,
+    },
+    Cat(CatOptions),
+    ExportLabels {
+        file: String,
+    },
+    ImportLabels {
+        file: String,
+    },
+    Help,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ListOptions {
+    pub(crate) tags: Vec<String>,
+    pub(crate) tag_match_mode: TagMatchMode,
+    pub(crate) collection: CollectionFilter,
+    pub(crate) favorites_only: bool,
+    pub(crate) status: Option<ReadingStatus>,
+    pub(crate) json: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CatOptions {
+    pub(crate) path: String,
+    /// 1-based first and last page; `None` for the whole document.
+    pub(crate) pages: Option<(u32, u32)>,
+    pub(crate) mode: ReaderTextMode,
+    pub(crate) trim_furniture: bool,
+    pub(crate) separator: String,
+}
+
+impl ListOptions {
+    fn matches(&self, book: &Book, labels: &BookLabels) -> bool {
+        (!self.favorites_only || book.favorite)
+            && self.status.is_none_or(|status| status == book.status)
+            && matches_collection_filter(&self.collection, labels.collection.as_deref())
+            && matches_tag_filter(&self.tags, self.tag_match_mode, &labels.tags)
+    }
+}
+
+/// Takes a leading `--db PATH` (or `--config PATH`) off `args`: the database path and the
+/// arguments left for `parse_args`.
+pub(crate) fn split_db_flag(args: &[String]) -> anyhow::Result<(Option<String>, &[String])> {
+    match args {
+        [flag, path, rest @ ..] if flag == "--db" || flag == "--config" => {
+            Ok((Some(path.clone()), rest))
+        }
+        [flag] if flag == "--db" || flag == "--config" => {
+            usage_error(&format!("{flag} needs a path"))
+        }
+        [flag, rest @ ..] => match flag
+            .strip_prefix("--db=")
+            .or_else(|| flag.strip_prefix("--config="))
+        {
+            Some(path) => Ok((Some(path.to_string()), rest)),
+            None => Ok((None, args)),
+        },
+        [] => Ok((None, args)),
+    }
+}
+
+pub(crate) fn parse_args(args: &[String]) -> anyhow::Result<Command> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(Command::Tui);
+    };
+    match (command.as_str(), rest) {
+        ("list", rest) => parse_list(rest).map(Command::List),
+        ("search", rest) => {
+            let json = rest.iter().any(|arg| arg == "--json");
+            let words: Vec<&str> = rest
+                .iter()
+                .filter(|arg| *arg != "--json")
+                .map(String::as_str)
+                .collect();
+            if words.is_empty() {
+                usage_error("search needs a query")
+            } else {
+                Ok(Command::Search {
+                    query: words.join(" "),
+                    json,
+                })
+            }
+        }
+        ("open" | "--open", rest) => parse_open(rest),
+        ("cat", rest) => parse_cat(rest).map(Command::Cat),
+        ("--export-labels", [file]) => Ok(Command::ExportLabels { file: file.clone() }),
+        ("--import-labels", [file]) => Ok(Command::ImportLabels { file: file.clone() }),
+        ("help" | "--help" | "-h", []) => Ok(Command::Help),
+        _ => usage_error(&format!("unexpected arguments: {}", args.join(" "))),
+    }
+}
+
+fn parse_list(args: &[String]) -> anyhow::Result<ListOptions> {
+    let mut options = ListOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("{flag} needs a value\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--tag" => options.tags.push(value("--tag")?),
+            "--all-tags" => options.tag_match_mode = TagMatchMode::And,
+            "--collection" => {
+                options.collection = CollectionFilter::Selected(value("--collection")?)
+            }
+            "--no-collection" => options.collection = CollectionFilter::None,
+            "--favorites" => options.favorites_only = true,
+            "--status" => {
+                let status = value("--status")?;
+                options.status = Some(
+                    status
+                        .parse()
+                        .map_err(|err| anyhow::anyhow!("{err}: {status}"))?,
+                );
+            }
+            "--json" => options.json = true,
+            other => return usage_error(&format!("unknown list option: {other}")),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_open(args: &[String]) -> anyhow::Result<Command> {
+    let mut path = None;
+    let mut page = None;
+    let mut mode = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("{flag} needs a value\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--page" => {
+                let value = value("--page")?;
+                page = Some(
+                    value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|page| *page > 0)
+                        .ok_or_else(|| anyhow::anyhow!("not a page number: {value}"))?,
+                );
+            }
+            "--mode" => {
+                let value = value("--mode")?;
+                mode = Some(
+                    parse_reader_mode(&value)
+                        .ok_or_else(|| anyhow::anyhow!("unknown reader mode: {value}"))?,
+                );
+            }
+            other if path.is_none() && !other.starts_with("--") => path = Some(other.to_string()),
+            other => return usage_error(&format!("unexpected open argument: {other}")),
+        }
+    }
+    match path {
+        Some(path) => Ok(Command::Open { path, page, mode }),
+        None => usage_error("open needs a path"),
+    }
+}
+
+fn parse_cat(args: &[String]) -> anyhow::Result<CatOptions> {
+    let mut path = None;
+    let mut pages = None;
+    let mut all = false;
+    let mut mode = ReaderTextMode::Wrap;
+    let mut trim_furniture = false;
+    let mut separator = "\x0c".to_string();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("{flag} needs a value\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--pages" => {
+                let value = value("--pages")?;
+                pages = Some(
+                    parse_page_range(&value)
+                        .ok_or_else(|| anyhow::anyhow!("not a page range: {value}"))?,
+                );
+            }
+            "--all" => all = true,
+            "--mode" => {
+                let value = value("--mode")?;
+                mode = value
+                    .parse()
+                    .map_err(|err| anyhow::anyhow!("{err}: {value}"))?;
+            }
+            "--trim-furniture" => trim_furniture = true,
+            "--separator" => separator = unescape_separator(&value("--separator")?),
+            other if path.is_none() && !other.starts_with("--") => path = Some(other.to_string()),
+            other => return usage_error(&format!("unexpected cat argument: {other}")),
+        }
+    }
+    let Some(path) = path else {
+        return usage_error("cat needs a path");
+    };
+    if all == pages.is_some() {
+        return usage_error("cat needs one of --pages or --all");
+    }
+    if trim_furniture && mode == ReaderTextMode::Raw {
+        return usage_error("--trim-furniture does nothing with --mode raw");
+    }
+    Ok(CatOptions {
+        path,
+        pages,
+        mode,
+        trim_furniture,
+        separator,
+    })
+}
+
+/// `N` or `N-M`, 1-based and in order.
+fn parse_page_range(value: &str) -> Option<(u32, u32)> {
+    let page = |value: &str| value.trim().parse::<u32>().ok().filter(|page| *page > 0);
+    let (first, last) = match value.split_once('-') {
+        Some((first, last)) => (page(first)?, page(last)?),
+        None => (page(value)?, page(value)?),
+    };
+    (first <= last).then_some((first, last))
+}
+
+/// Separators are hard to type raw, so `\n`, `\t` and `\f` stand for their characters.
+fn unescape_separator(value: &str) -> String {
+    value
+        .replace("\\n", "\n")
+        .replace("\\t", "\t")
+        .replace("\\f", "\x0c")
+}
+
+fn usage_error<T>(message: &str) -> anyhow::Result<T> {
+    anyhow::bail!("{message}\n\n{USAGE}")
+}
+
+/// Runs a command that does not need the terminal UI.
+pub(crate) fn run(storage: &Storage, command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::List(options) => {
+            let books = query_books(storage, |book, labels| options.matches(book, labels))?;
+            print_books(storage, &books, options.json)?;
+        }
+        Command::Search { query, json } => {
+            let query = LibraryQuery::parse(&query);
+            let books = query_books(storage, |book, labels| query.matches(book, labels))?;
+            print_books(storage, &books, json)?;
+        }
+        Command::ExportLabels { file } => {
+            let count = storage.export_labels(&file)?;
+            println!("exported labels for {count} books to {file}");
+        }
+        Command::ImportLabels { file } => {
+            let report = storage.import_labels(&file)?;
+            println!(
+                "imported labels for {} books; skipped {} not in this library",
+                report.merged_books, report.skipped_books
+            );
+        }
+        Command::Help => println!("{USAGE}"),
+        Command::Cat(options) => cat(&std::env::current_dir()?, &options)?,
+        Command::Tui | Command::Open { .. } => {
+            anyhow::bail!("this command runs in the terminal UI")
+        }
+    }
+    Ok(())
+}
+
+/// Prints the text of `options.path` to stdout. Pages are read one at a time, so a long book
+/// does not sit in memory whole; text extraction needs no pdfium.
+pub(crate) fn cat(cwd: &Path, options: &CatOptions) -> anyhow::Result<()> {
+    let target = cwd.join(&options.path);
+    if !target.is_file() {
+        anyhow::bail!("no such book: {}", target.display());
+    }
+    let book = Book {
+        path: encode_path(&target.canonicalize().unwrap_or(target)),
+        title: String::new(),
+        last_opened: None,
+        favorite: false,
+        author: None,
+        status: ReadingStatus::Unread,
+        archived: false,
+    };
+    let engine = Engine::new();
+    let page_count = engine.page_count(&book)?;
+    let (first, last) = match options.pages {
+        Some((first, _)) if first > page_count => {
+            anyhow::bail!("page {first} is past the end; the book has {page_count} pages")
+        }
+        Some((first, last)) => (first, last.min(page_count)),
+        None => (1, page_count),
+    };
+    let furniture = if options.trim_furniture {
+        Some(engine.detect_page_furniture(&book)?)
+    } else {
+        None
+    };
+
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    let written = write_pages(
+        &mut out,
+        first - 1..last,
+        &options.separator,
+        |page_index| {
+            engine.render_page_text_for_reader(
+                &book,
+                page_index,
+                options.mode,
+                TextLayout::Stream,
+                furniture.as_ref(),
+            )
+        },
+    )
+    .and_then(|()| out.flush().map_err(anyhow::Error::from));
+    match written {
+        // The reader went away, as `head` does once it has enough.
+        Err(err)
+            if err
+                .downcast_ref::<io::Error>()
+                .is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe) =>
+        {
+            Ok(())
+        }
+        other => other,
+    }
+}
+
+/// Writes each page `render` gives back, followed by `separator`, flushing as it goes.
+fn write_pages(
+    out: &mut impl Write,
+    pages: std::ops::Range<u32>,
+    separator: &str,
+    mut render: impl FnMut(u32) -> anyhow::Result<String>,
+) -> anyhow::Result<()> {
+    for page_index in pages {
+        let text =
+            render(page_index).map_err(|err| anyhow::anyhow!("page {}: {err}", page_index + 1))?;
+        out.write_all(text.as_bytes())?;
+        if !text.is_empty() && !text.ends_with('\n') {
+            out.write_all(b"\n")?;
+        }
+        out.write_all(separator.as_bytes())?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// Stored books that pass `keep`, by title, with their labels.
+fn query_books(
+    storage: &Storage,
+    keep: impl Fn(&Book, &BookLabels) -> bool,
+) -> anyhow::Result<Vec<(Book, BookLabels)>> {
+    let mut labels_by_path = storage.list_labels_by_path()?;
+    let mut books: Vec<(Book, BookLabels)> = storage
+        .list_books()?
+        .into_iter()
+        .map(|book| {
+            let labels = labels_by_path.remove(&book.path).unwrap_or_default();
+            (book, labels)
+        })
+        .filter(|(book, labels)| keep(book, labels))
+        .collect();
+    books.sort_by_key(|(book, _)| book.title.to_lowercase());
+    Ok(books)
+}
+
+/// A book as printed by `list --json` and `search --json`.
+#[derive(Debug, Serialize)]
+struct BookSummary {
+    title: String,
+    path: String,
+    author: Option<String>,
+    tags: Vec<String>,
+    collection: Option<String>,
+    series: Option<String>,
+    series_number: Option<u32>,
+    favorite: bool,
+    status: ReadingStatus,
+    /// Last read page, 1-based.
+    last_page: Option<u32>,
+    /// Page (or chapter) count the library stored; left out when there is none or the file
+    /// has changed since it was taken.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pages: Option<u32>,
+}
+
+fn print_books(storage: &Storage, books: &[(Book, BookLabels)], json: bool) -> anyhow::Result<()> {
+    if !json {
+        for (book, _) in books {
+            println!("{}\t{}", display_path(&book.path), book.title);
+        }
+        return Ok(());
+    }
+
+    let progress_by_path = storage.list_progress()?;
+    // Only stored counts are printed; opening every book to count it is too slow for a listing.
+    let page_counts = storage.list_page_counts()?;
+    let summaries: Vec<BookSummary> = books
+        .iter()
+        .map(|(book, labels)| BookSummary {
+            title: book.title.clone(),
+            path: display_path(&book.path),
+            author: book_author(book, labels).map(str::to_string),
+            tags: labels.tags.clone(),
+            collection: labels.collection.clone(),
+            series: labels.series_name().map(str::to_string),
+            series_number: labels.series_number(),
+            favorite: book.favorite,
+            status: book.status,
+            last_page: progress_by_path.get(&book.path).map(|p| p.last_page),
+            pages: page_counts
+                .get(&book.path)
+                .and_then(|count| fresh_page_count(count, &book.path)),
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&summaries)?);
+    Ok(())
+}
+
+/// The stored `count` when it was taken from the file at `path` as it is now.
+fn fresh_page_count(count: &PageCount, path: &str) -> Option<u32> {
+    let metadata = std::fs::metadata(decode_path(path)).ok()?;
+    let modified_secs = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .and_then(|since| i64::try_from(since.as_secs()).ok())
+        .unwrap_or(0);
+    count
+        .is_fresh(metadata.len(), modified_secs)
+        .then_some(count.pages)
+}
+
+/// The boot reader for `path`, at `page` or else the saved page. Books outside the library
+/// open too, as long as the file exists.
+pub(crate) fn boot_reader(
+    storage: &Storage,
+    cwd: &Path,
+    path: &str,
+    page: Option<u32>,
+    mode: Option<ReaderMode>,
+) -> anyhow::Result<BootReader> {
+    let target = cwd.join(path);
+    let target = target.canonicalize().unwrap_or(target);
+    let stored = storage.list_books()?.into_iter().find(|book| {
+        let stored = decode_path(&book.path);
+        stored == target || stored.canonicalize().is_ok_and(|p| p == target)
+    });
+    let encoded = match stored {
+        Some(book) => book.path,
+        None if target.is_file() => encode_path(&target),
+        None => anyhow::bail!("no such book: {}", target.display()),
+    };
+    let page = match page {
+        Some(page) => page,
+        None => storage
+            .list_progress()?
+            .get(&encoded)
+            .map_or(1, |progress| progress.last_page),
+    };
+    Ok(BootReader {
+        path: encoded,
+        page_index: page.saturating_sub(1),
+        mode,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_commands_and_list_filters() -> anyhow::Result<()> {
+        assert_eq!(parse_args(&[])?, Command::Tui);
+        assert_eq!(
+            parse_args(&args(&["search", "borrow", "checker", "--json"]))?,
+            Command::Search {
+                query: "borrow checker".to_string(),
+                json: true
+            }
+        );
+        assert_eq!(
+            parse_args(&args(&["open", "books/a.pdf"]))?,
+            Command::Open {
+                path: "books/a.pdf".to_string(),
+                page: None,
+                mode: None,
+            }
+        );
+        assert_eq!(
+            parse_args(&args(&[
+                "--open",
+                "books/a.pdf",
+                "--page",
+                "12",
+                "--mode",
+                "image"
+            ]))?,
+            Command::Open {
+                path: "books/a.pdf".to_string(),
+                page: Some(12),
+                mode: Some(ReaderMode::Image),
+            }
+        );
+        assert_eq!(
+            parse_args(&args(&["--export-labels", "labels.json"]))?,
+            Command::ExportLabels {
+                file: "labels.json".to_string()
+            }
+        );
+        assert_eq!(
+            parse_args(&args(&[
+                "list",
+                "--tag",
+                "rust",
+                "--tag",
+                "os",
+                "--all-tags",
+                "--collection",
+                "work",
+                "--status",
+                "Reading",
+                "--json",
+            ]))?,
+            Command::List(ListOptions {
+                tags: vec!["rust".to_string(), "os".to_string()],
+                tag_match_mode: TagMatchMode::And,
+                collection: CollectionFilter::Selected("work".to_string()),
+                favorites_only: false,
+                status: Some(ReadingStatus::Reading),
+                json: true,
+            })
+        );
+
+        assert!(parse_args(&args(&["list", "--tag"])).is_err());
+        assert!(parse_args(&args(&["list", "--status", "skimmed"])).is_err());
+        assert!(parse_args(&args(&["list", "--bogus"])).is_err());
+        assert!(parse_args(&args(&["search"])).is_err());
+        assert!(parse_args(&args(&["open"])).is_err());
+        assert!(parse_args(&args(&["open", "a.pdf", "--page", "0"])).is_err());
+        assert!(parse_args(&args(&["open", "a.pdf", "--mode", "sepia"])).is_err());
+        assert!(parse_args(&args(&["open", "a.pdf", "b.pdf"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn db_flag_comes_before_the_command() -> anyhow::Result<()> {
+        let given = args(&["--db", "work.db", "list", "--json"]);
+        let (db, rest) = split_db_flag(&given)?;
+        assert_eq!(db.as_deref(), Some("work.db"));
+        assert_eq!(rest, &given[2..]);
+        let given = args(&["--config=/srv/home.db"]);
+        assert_eq!(
+            split_db_flag(&given)?,
+            (Some("/srv/home.db".to_string()), &[][..])
+        );
+        let given = args(&["search", "--db"]);
+        assert_eq!(split_db_flag(&given)?, (None, &given[..]));
+        assert!(split_db_flag(&args(&["--db"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_cat_ranges_and_separators() -> anyhow::Result<()> {
+        assert_eq!(
+            parse_args(&args(&[
+                "cat",
+                "a.pdf",
+                "--pages",
+                "1-20",
+                "--mode",
+                "reflow",
+                "--trim-furniture"
+            ]))?,
+            Command::Cat(CatOptions {
+                path: "a.pdf".to_string(),
+                pages: Some((1, 20)),
+                mode: ReaderTextMode::Reflow,
+                trim_furniture: true,
+                separator: "\x0c".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&[
+                "cat",
+                "--all",
+                "a.pdf",
+                "--separator",
+                "\\n---\\n"
+            ]))?,
+            Command::Cat(CatOptions {
+                path: "a.pdf".to_string(),
+                pages: None,
+                mode: ReaderTextMode::Wrap,
+                trim_furniture: false,
+                separator: "\n---\n".to_string(),
+            })
+        );
+        assert_eq!(parse_page_range("7"), Some((7, 7)));
+        assert_eq!(parse_page_range("3-2"), None);
+        assert_eq!(parse_page_range("0-2"), None);
+
+        assert!(parse_args(&args(&["cat", "a.pdf"])).is_err());
+        assert!(parse_args(&args(&["cat", "a.pdf", "--all", "--pages", "2"])).is_err());
+        assert!(parse_args(&args(&["cat", "--all"])).is_err());
+        assert!(parse_args(&args(&["cat", "a.pdf", "--all", "--mode", "sepia"])).is_err());
+        assert!(
+            parse_args(&args(&[
+                "cat",
+                "a.pdf",
+                "--all",
+                "--mode",
+                "raw",
+                "--trim-furniture"
+            ]))
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cat_writes_page_by_page_and_stops_at_a_failure() {
+        let mut out = Vec::new();
+        write_pages(&mut out, 0..2, "\x0c", |page| {
+            Ok(format!("page {}", page + 1))
+        })
+        .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "page 1\n\x0cpage 2\n\x0c");
+
+        let mut out = Vec::new();
+        let err = write_pages(&mut out, 0..3, "\x0c", |page| {
+            if page == 1 {
+                anyhow::bail!("bad xref")
+            }
+            Ok("text\n".to_string())
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "page 2: bad xref");
+        assert_eq!(String::from_utf8(out).unwrap(), "text\n\x0c");
+    }
+
+    #[test]
+    fn list_options_combine_filters() {
+        let book = Book {
+            path: "/library/rust.pdf".to_string(),
+            title: "Rust".to_string(),
+            last_opened: None,
+            favorite: true,
+            author: None,
+            status: ReadingStatus::Reading,
+            archived: false,
+        };
+        let labels = BookLabels {
+            tags: vec!["Rust".to_string(), "lang".to_string()],
+            collection: Some("Work".to_string()),
+            ..BookLabels::default()
+        };
+        let list = |extra: &[&str]| {
+            let mut all = vec!["list"];
+            all.extend_from_slice(extra);
+            match parse_args(&args(&all)) {
+                Ok(Command::List(options)) => options,
+                other => panic!("not a list command: {other:?}"),
+            }
+        };
+
+        assert!(list(&[]).matches(&book, &labels));
+        assert!(list(&["--tag", "rust", "--tag", "os"]).matches(&book, &labels));
+        assert!(!list(&["--tag", "rust", "--tag", "os", "--all-tags"]).matches(&book, &labels));
+        assert!(list(&["--collection", "work", "--favorites"]).matches(&book, &labels));
+        assert!(!list(&["--no-collection"]).matches(&book, &labels));
+        assert!(!list(&["--status", "finished"]).matches(&book, &labels));
+    }
+
+    #[test]
+    fn listings_print_stored_page_counts_only_while_fresh() -> anyhow::Result<()> {
+        let file = std::env::temp_dir().join(format!("bookshelf-cli-{}.pdf", std::process::id()));
+        fs::write(&file, b"%PDF-1.7")?;
+        let path = encode_path(&file);
+        let metadata = fs::metadata(&file)?;
+        let modified_secs = metadata
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let count = PageCount {
+            pages: 12,
+            size_bytes: metadata.len(),
+            modified_secs: i64::try_from(modified_secs)?,
+            kind: None,
+        };
+        assert_eq!(fresh_page_count(&count, &path), Some(12));
+
+        fs::write(&file, b"%PDF-1.7 changed")?;
+        assert_eq!(fresh_page_count(&count, &path), None);
+        fs::remove_file(&file)?;
+        assert_eq!(fresh_page_count(&count, &path), None);
+        Ok(())
+    }
+}
@@ -1,11 +1,22 @@
+mod cli;
+
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
+use std::rc::Rc;
 
 use anyhow::Context as _;
-use bookshelf_application::AppContext;
-use bookshelf_core::{Book, ScanScope, Settings, TagKind, encode_path};
-use bookshelf_storage::Storage;
-use bookshelf_ui::{Ui, UiExit};
+use bookshelf_application::{
+    AppContext, BookPathOp, BootReader, DirtyBatch, LabelCatalogOp, LibraryFilters, ScanMerge,
+    ScanMetadata, StoredBookData, merge_scan, resolve_db_path, scan_books,
+};
+use bookshelf_core::{Book, BookProgress, LibraryRoot, Settings, TagKind};
+use bookshelf_engine::Engine;
+use bookshelf_storage::{BACKUPS_KEPT, Storage, list_backups, restore_backup};
+use bookshelf_ui::{
+    AutosaveSink, BackupListSink, BackupSink, CleanupSink, ConflictCheck, LibrarySink,
+    ProgressSink, RestoreSink, SessionSink, SettingsExportSink, SettingsImportSink, Ui, UiExit,
+    UiOutcome,
+};
 
 fn main() {
     if let Err(err) = run() {
@@ -16,219 +27,665 @@ fn main() {
 
 fn run() -> anyhow::Result<()> {
     let cwd = std::env::current_dir().context("get cwd")?;
-    let cwd_str = cwd.to_string_lossy().to_string();
 
-    let db_dir = cwd.join(".bookshelf");
-    fs::create_dir_all(&db_dir).with_context(|| format!("create db dir {}", db_dir.display()))?;
-    let db_path = db_dir.join("bookshelf.db");
-    let storage = Storage::open(&db_path)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (db_flag, args) = cli::split_db_flag(&args)?;
+    let command = cli::parse_args(args)?;
+    // `cat` reads a file and nothing else; it leaves the library database alone.
+    if let cli::Command::Cat(options) = &command {
+        return cli::cat(&cwd, options);
+    }
+
+    let db_path = resolve_db_path(&cwd, db_flag.as_deref(), |var| std::env::var(var).ok());
+    if let Some(db_dir) = db_path.parent() {
+        fs::create_dir_all(db_dir)
+            .with_context(|| format!("create db dir {}", db_dir.display()))?;
+    }
+    let mut storage = Rc::new(Storage::open(&db_path)?);
+    let boot_reader = match command {
+        cli::Command::Tui => None,
+        cli::Command::Open { path, page, mode } => {
+            Some(cli::boot_reader(&storage, &cwd, &path, page, mode)?)
+        }
+        command => return cli::run(&storage, command),
+    };
+    let mut boot_reader = BootReader::resolve(boot_reader, |var| std::env::var(var).ok());
+    loop {
+        let outcome = run_ui(&storage, &cwd, &db_path, boot_reader.take())?;
+        match outcome.exit {
+            UiExit::Quit => {
+                let mut ctx = outcome.ctx;
+                return save_session(&storage, &mut ctx);
+            }
+            // Opening the restored database again brings an older backup's tables up to date.
+            UiExit::Reload => storage = Rc::new(Storage::open(&db_path)?),
+        }
+    }
+}
+
+/// Loads the session from `storage` and runs the UI on it until it exits.
+fn run_ui(
+    storage: &Rc<Storage>,
+    cwd: &Path,
+    db_path: &Path,
+    boot_reader: Option<BootReader>,
+) -> anyhow::Result<UiOutcome> {
+    let cwd_str = cwd.to_string_lossy().to_string();
     let mut settings = storage.load_settings()?;
 
     if settings.library_roots.is_empty() {
-        settings.library_roots.push(cwd_str.clone());
+        settings
+            .library_roots
+            .push(LibraryRoot::new(cwd_str.clone()));
         settings.normalize();
         storage.save_settings(&settings)?;
     }
 
-    sync_library(&storage, &settings, &cwd)?;
+    sync_library(storage, &settings, cwd)?;
     let books = storage.list_books()?;
     let progress_by_path = storage.list_progress()?;
+    let reader_state_by_path = storage.list_reader_states()?;
+    let reading_secs_by_path = storage.list_reading_time()?;
+    let page_counts_by_path = storage.list_page_counts()?;
+    let fingerprints_by_path = storage.list_fingerprints()?;
+    let added_at_by_path = storage.list_added_at()?;
     let labels_by_path = storage.list_labels_by_path()?;
+    let collection_positions = storage.list_collection_positions()?;
     let known_tags = storage.list_tag_names(TagKind::Tag)?;
     let known_collections = storage.list_tag_names(TagKind::Collection)?;
+    let known_authors = storage.list_tag_names(TagKind::Author)?;
+    let known_series = storage.list_tag_names(TagKind::Series)?;
     let bookmarks_by_path = storage.list_bookmarks_by_path()?;
     let notes_by_path = storage.list_notes_by_path()?;
+    let library_filters = LibraryFilters::from_json(&storage.load_library_filters_json()?);
+    let generation = storage.load_generation()?;
 
-    let mut ctx = AppContext::new(settings)
+    let ctx = AppContext::new(settings)
         .with_library(cwd_str, books)
         .with_progress(progress_by_path)
+        .with_reader_states(reader_state_by_path)
+        .with_reading_time(reading_secs_by_path)
+        .with_page_counts(page_counts_by_path)
+        .with_fingerprints(fingerprints_by_path)
+        .with_added_at(added_at_by_path)
         .with_labels(labels_by_path)
+        .with_collection_positions(collection_positions)
         .with_label_catalog(known_tags, known_collections)
+        .with_known_labels(TagKind::Author, known_authors)
+        .with_known_labels(TagKind::Series, known_series)
+        .with_library_filters(library_filters)
         .with_bookmarks(bookmarks_by_path)
-        .with_notes(notes_by_path);
-    loop {
-        let mut ui = Ui::new(ctx);
-        let outcome = ui.run()?;
-        ctx = outcome.ctx;
+        .with_notes(notes_by_path)
+        .with_db_generation(generation);
+    let progress_storage = Rc::clone(storage);
+    let progress_sink: ProgressSink = Box::new(move |path: &str, progress: &BookProgress| {
+        progress_storage.set_progress(path, progress)
+    });
+    let library_storage = Rc::clone(storage);
+    let library_sink: LibrarySink = Box::new(move |books: &[Book], complete: bool| {
+        sync_library_books(&library_storage, books, complete)
+    });
+    let cleanup_storage = Rc::clone(storage);
+    let cleanup_sink: CleanupSink = Box::new(move |keep: &[String]| cleanup_storage.cleanup(keep));
+    let session_storage = Rc::clone(storage);
+    let session_sink: SessionSink =
+        Box::new(move |ctx: &mut AppContext| save_session(&session_storage, ctx));
+    let autosave_storage = Rc::clone(storage);
+    let autosave_sink: AutosaveSink =
+        Box::new(move |ctx: &mut AppContext| autosave(&autosave_storage, ctx));
+    let settings_export_sink: SettingsExportSink =
+        Box::new(|file: &Path, settings: &Settings| Storage::export_settings(settings, file));
+    let import_storage = Rc::clone(storage);
+    let settings_import_sink: SettingsImportSink =
+        Box::new(move |file: &Path| import_storage.import_settings(file));
+    let backup_storage = Rc::clone(storage);
+    let backup_db_path = db_path.to_path_buf();
+    let backup_sink: BackupSink = Box::new(move |ctx: &mut AppContext| {
+        save_session(&backup_storage, ctx)?;
+        backup_storage.backup_next_to(&backup_db_path, BACKUPS_KEPT)
+    });
+    let list_db_path = db_path.to_path_buf();
+    let backup_list_sink: BackupListSink = Box::new(move || list_backups(&list_db_path));
+    let restore_db_path = db_path.to_path_buf();
+    let restore_sink: RestoreSink =
+        Box::new(move |backup: &Path| restore_backup(&restore_db_path, backup));
+    let conflict_storage = Rc::clone(storage);
+    let conflict_check: ConflictCheck = Box::new(move |ctx: &AppContext| {
+        Ok(conflict_storage.load_generation()? != ctx.db_generation)
+    });
+    let mut ui = Ui::new(ctx, boot_reader)
+        .with_progress_sink(progress_sink)
+        .with_library_sink(library_sink)
+        .with_cleanup_sink(cleanup_sink)
+        .with_session_sink(session_sink)
+        .with_autosave_sink(autosave_sink)
+        .with_settings_file_sinks(settings_export_sink, settings_import_sink)
+        .with_backup_sinks(backup_sink, backup_list_sink, restore_sink)
+        .with_db_path(db_path)
+        .with_conflict_check(conflict_check);
+    ui.run()
+}
+
+/// Writes settings and everything the session left dirty. Dirty sets are drained, so saving
+/// the same context again writes nothing twice; a failed write puts them back.
+///
+/// When another instance saved since this session loaded, its labels, bookmarks and notes are
+/// merged into the ones about to be written, and its settings stay if the user chose so.
+fn save_session(storage: &Storage, ctx: &mut AppContext) -> anyhow::Result<()> {
+    let conflict = storage.load_generation()? != ctx.db_generation;
+    if conflict {
+        ctx.merge_stored(&StoredBookData {
+            labels_by_path: storage.list_labels_by_path()?,
+            bookmarks_by_path: storage.list_bookmarks_by_path()?,
+            notes_by_path: storage.list_notes_by_path()?,
+        });
+    }
+    if !(conflict && ctx.keep_stored_settings) {
         storage.save_settings(&ctx.settings)?;
+        storage.save_library_filters_json(&ctx.library_filters().to_json())?;
+    }
 
-        let dirty_label_catalog_ops = std::mem::take(&mut ctx.dirty_label_catalog_ops);
-        for op in dirty_label_catalog_ops {
-            match op {
-                bookshelf_application::LabelCatalogOp::Create { kind, name } => {
-                    storage.create_tag(&name, kind)?;
-                }
-                bookshelf_application::LabelCatalogOp::Rename { kind, from, to } => {
-                    storage.rename_tag(&from, &to, kind)?;
-                }
-                bookshelf_application::LabelCatalogOp::Delete { kind, name } => {
-                    storage.delete_tag(&name, kind)?;
-                }
-            }
+    let book_path_ops = std::mem::take(&mut ctx.dirty_book_path_ops);
+    let label_catalog_ops = std::mem::take(&mut ctx.dirty_label_catalog_ops);
+    let batch = ctx.take_dirty_batch(usize::MAX);
+    let written = storage.write_batch(|storage| {
+        write_path_and_catalog_ops(storage, &book_path_ops, &label_catalog_ops)?;
+        write_dirty_batch(storage, ctx, &batch)?;
+        storage.bump_generation()
+    });
+    match written {
+        Ok(generation) => {
+            ctx.mark_saved(generation);
+            Ok(())
+        }
+        Err(err) => {
+            ctx.dirty_book_path_ops.splice(0..0, book_path_ops);
+            ctx.dirty_label_catalog_ops.splice(0..0, label_catalog_ops);
+            ctx.restore_dirty_batch(batch);
+            Err(err)
         }
+    }
+}
 
-        let dirty_favorite_paths = std::mem::take(&mut ctx.dirty_favorite_paths);
-        for path in dirty_favorite_paths {
-            if let Some(book) = ctx.books.iter().find(|b| b.path == path) {
-                storage.set_favorite(&book.path, book.favorite)?;
+/// Most book changes one autosave writes; the rest wait for the next one, so a save never
+/// holds up the event loop for long.
+const AUTOSAVE_MAX_CHANGES: usize = 200;
+
+/// Writes part of what the session left dirty while the UI runs, in one transaction, and
+/// returns how many changes it wrote. Settings wait for the save on quit. A failed write puts
+/// the changes back for the next try.
+fn autosave(storage: &Storage, ctx: &mut AppContext) -> anyhow::Result<usize> {
+    let conflict = storage.load_generation()? != ctx.db_generation;
+    if conflict {
+        ctx.merge_stored(&StoredBookData {
+            labels_by_path: storage.list_labels_by_path()?,
+            bookmarks_by_path: storage.list_bookmarks_by_path()?,
+            notes_by_path: storage.list_notes_by_path()?,
+        });
+    }
+    let book_path_ops = std::mem::take(&mut ctx.dirty_book_path_ops);
+    let label_catalog_ops = std::mem::take(&mut ctx.dirty_label_catalog_ops);
+    let ops = book_path_ops.len() + label_catalog_ops.len();
+    let batch = ctx.take_dirty_batch(AUTOSAVE_MAX_CHANGES.saturating_sub(ops));
+    let written = storage.write_batch(|storage| {
+        write_path_and_catalog_ops(storage, &book_path_ops, &label_catalog_ops)?;
+        write_dirty_batch(storage, ctx, &batch)?;
+        storage.bump_generation()
+    });
+    match written {
+        Ok(generation) => {
+            // After a conflict the generation stays behind, so later saves keep merging and
+            // the save on quit still asks about the other instance's settings.
+            if !conflict {
+                ctx.db_generation = generation;
             }
+            ctx.mark_batch_saved(&batch);
+            Ok(ops + batch.len())
         }
+        Err(err) => {
+            ctx.dirty_book_path_ops.splice(0..0, book_path_ops);
+            ctx.dirty_label_catalog_ops.splice(0..0, label_catalog_ops);
+            ctx.restore_dirty_batch(batch);
+            Err(err)
+        }
+    }
+}
 
-        for (path, last_page) in ctx.progress_by_path.iter() {
-            storage.set_progress(path, *last_page)?;
+/// Applies relinks and removals, then label catalog edits, in the order they were made.
+fn write_path_and_catalog_ops(
+    storage: &Storage,
+    book_path_ops: &[BookPathOp],
+    label_catalog_ops: &[LabelCatalogOp],
+) -> anyhow::Result<()> {
+    for op in book_path_ops {
+        match op {
+            BookPathOp::Relink { from, to, title } => {
+                storage.rename_book_path(from, to, title)?;
+            }
+            BookPathOp::Remove { path } => {
+                storage.delete_book_by_path(path)?;
+            }
         }
-        for (path, opened_at) in ctx.opened_at_by_path.iter() {
-            storage.set_last_opened(path, *opened_at)?;
+    }
+    for op in label_catalog_ops {
+        match op {
+            LabelCatalogOp::Create { kind, name } => {
+                storage.create_tag(name, *kind)?;
+            }
+            LabelCatalogOp::Rename { kind, from, to } => {
+                storage.rename_tag(from, to, *kind)?;
+            }
+            LabelCatalogOp::Delete { kind, name } => {
+                storage.delete_tag(name, *kind)?;
+            }
         }
-        ctx.opened_at_by_path.clear();
+    }
+    Ok(())
+}
 
-        let dirty_label_paths = std::mem::take(&mut ctx.dirty_label_paths);
-        for path in dirty_label_paths {
-            let labels = ctx.labels_by_path.get(&path).cloned().unwrap_or_default();
-            storage.save_labels(&path, &labels)?;
+/// Writes the current value of each book change in `batch`.
+fn write_dirty_batch(
+    storage: &Storage,
+    ctx: &AppContext,
+    batch: &DirtyBatch,
+) -> anyhow::Result<()> {
+    let book = |path: &str| ctx.books.iter().find(|b| b.path == path);
+    for path in &batch.favorite_paths {
+        if let Some(book) = book(path) {
+            storage.set_favorite(&book.path, book.favorite)?;
         }
-
-        let dirty_bookmark_paths = std::mem::take(&mut ctx.dirty_bookmark_paths);
-        for path in dirty_bookmark_paths {
-            let bookmarks = ctx
-                .bookmarks_by_path
-                .get(&path)
-                .cloned()
-                .unwrap_or_default();
-            storage.replace_bookmarks(&path, &bookmarks)?;
+    }
+    for path in &batch.status_paths {
+        if let Some(book) = book(path) {
+            storage.set_status(&book.path, book.status)?;
         }
-        let dirty_note_paths = std::mem::take(&mut ctx.dirty_note_paths);
-        for path in dirty_note_paths {
-            let notes = ctx.notes_by_path.get(&path).cloned().unwrap_or_default();
-            storage.replace_notes(&path, &notes)?;
+    }
+    for path in &batch.archived_paths {
+        if let Some(book) = book(path) {
+            storage.set_archived(&book.path, book.archived)?;
+        }
+    }
+    for path in &batch.metadata_paths {
+        if let Some(book) = book(path) {
+            storage.set_book_metadata(&book.path, &book.title, book.author.as_deref())?;
         }
+    }
 
-        match outcome.exit {
-            UiExit::Quit => break,
-            UiExit::Rescan => {
-                sync_library(&storage, &ctx.settings, &cwd)?;
-                let books = storage.list_books()?;
-                let progress_by_path = storage.list_progress()?;
-                let labels_by_path = storage.list_labels_by_path()?;
-                let known_tags = storage.list_tag_names(TagKind::Tag)?;
-                let known_collections = storage.list_tag_names(TagKind::Collection)?;
-                let bookmarks_by_path = storage.list_bookmarks_by_path()?;
-                let notes_by_path = storage.list_notes_by_path()?;
-                let cwd_str = ctx.cwd.clone();
-                ctx = ctx
-                    .with_library(cwd_str, books)
-                    .with_progress(progress_by_path)
-                    .with_labels(labels_by_path)
-                    .with_label_catalog(known_tags, known_collections)
-                    .with_bookmarks(bookmarks_by_path)
-                    .with_notes(notes_by_path);
-            }
+    for path in &batch.progress_paths {
+        if let Some(progress) = ctx.progress_by_path.get(path) {
+            storage.set_progress(path, progress)?;
+        }
+    }
+    for path in &batch.reader_state_paths {
+        if let Some(state) = ctx.reader_state_by_path.get(path) {
+            storage.set_reader_state(path, state)?;
+        }
+    }
+    for path in &batch.reading_time_paths {
+        if let Some(secs) = ctx.reading_secs_by_path.get(path) {
+            storage.set_reading_time(path, *secs)?;
         }
     }
+    for path in &batch.page_count_paths {
+        if let Some(count) = ctx.page_counts_by_path.get(path) {
+            storage.set_page_count(path, count)?;
+        }
+    }
+    for path in &batch.fingerprint_paths {
+        if let Some(fingerprint) = ctx.fingerprints_by_path.get(path) {
+            storage.set_fingerprint(path, fingerprint)?;
+        }
+    }
+    for (path, opened_at) in &batch.opened_at {
+        storage.set_last_opened(path, *opened_at)?;
+    }
 
+    for path in &batch.label_paths {
+        let labels = ctx.labels_by_path.get(path).cloned().unwrap_or_default();
+        storage.save_labels(path, &labels)?;
+    }
+    for path in &batch.collection_position_paths {
+        let position = ctx.collection_positions_by_path.get(path).copied();
+        storage.set_collection_position(path, position)?;
+    }
+
+    for path in &batch.bookmark_paths {
+        let bookmarks = ctx.bookmarks_by_path.get(path).cloned().unwrap_or_default();
+        storage.replace_bookmarks(path, &bookmarks)?;
+    }
+    for path in &batch.note_paths {
+        let notes = ctx.notes_by_path.get(path).cloned().unwrap_or_default();
+        storage.replace_notes(path, &notes)?;
+    }
     Ok(())
 }
 
+/// Scans the library roots at startup and merges what they hold into storage.
 fn sync_library(storage: &Storage, settings: &Settings, cwd: &Path) -> anyhow::Result<()> {
-    let scanned = scan_pdfs(settings, cwd)?;
-    let mut scanned_set = std::collections::HashSet::new();
-    for book in scanned {
-        scanned_set.insert(book.path.clone());
-        storage.upsert_book(&book)?;
-    }
+    // Only books new to the library are opened for their metadata.
+    let metadata = ScanMetadata {
+        known_paths: storage.list_books()?.into_iter().map(|b| b.path).collect(),
+        read: |book| Engine::new().document_metadata(book).ok(),
+        // This walk blocks startup, so fingerprints are left to scans from the library.
+        fingerprinted: Default::default(),
+    };
+    let scanned = scan_books(settings, cwd, Some(metadata))?;
+    sync_library_books(storage, &scanned, true)?;
+    Ok(())
+}
 
-    let existing = storage.list_books()?;
-    for book in existing {
-        if !scanned_set.contains(&book.path) {
-            storage.delete_book_by_path(&book.path)?;
-        }
+/// Merges scanned books into the stored ones: new books are added, known ones only get their
+/// derived fields refreshed. Books no longer found are kept so the library can flag them as
+/// missing and offer a relink; pruning them goes through the session's path ops.
+fn sync_library_books(
+    storage: &Storage,
+    scanned: &[Book],
+    complete: bool,
+) -> anyhow::Result<ScanMerge> {
+    let merge = merge_scan(&storage.list_books()?, scanned, complete);
+    for book in &merge.added {
+        storage.upsert_book(book)?;
     }
-
-    Ok(())
+    for book in &merge.updated {
+        storage.set_book_metadata(&book.path, &book.title, book.author.as_deref())?;
+    }
+    Ok(merge)
 }
 
-fn scan_pdfs(settings: &Settings, cwd: &Path) -> anyhow::Result<Vec<Book>> {
-    let mut found = std::collections::BTreeMap::<String, Book>::new();
+#[cfg(test)]
+mod tests {
+    use bookshelf_core::{BookLabels, Note, ReadingStatus};
+    use bookshelf_ui::save_all_changes;
+
+    use super::*;
+
+    #[test]
+    fn sessions_sharing_a_database_merge_labels_and_notes() -> anyhow::Result<()> {
+        let db_path =
+            std::env::temp_dir().join(format!("bookshelf-shared-{}.db", std::process::id()));
+        let _ = fs::remove_file(&db_path);
+        let storage = Storage::open(&db_path)?;
+        let path = "/library/a.pdf".to_string();
+        storage.upsert_book(&Book {
+            path: path.clone(),
+            title: "a".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        })?;
+        storage.save_labels(
+            &path,
+            &BookLabels {
+                tags: vec!["rust".to_string()],
+                collection: None,
+                ..BookLabels::default()
+            },
+        )?;
+        let note = |body: &str| Note {
+            page: 1,
+            body: body.to_string(),
+        };
+        storage.replace_notes(&path, &[note("kept"), note("removed by first")])?;
 
-    for root in &settings.library_roots {
-        let root_path = PathBuf::from(root);
-        let root_path = if root_path.is_absolute() {
-            root_path
-        } else {
-            cwd.join(root_path)
+        let load = |storage: &Storage| -> anyhow::Result<AppContext> {
+            Ok(AppContext::new(Settings::default())
+                .with_labels(storage.list_labels_by_path()?)
+                .with_notes(storage.list_notes_by_path()?)
+                .with_db_generation(storage.load_generation()?))
         };
+        let mut first = load(&storage)?;
+        let mut second = load(&storage)?;
 
-        if root_path.is_file() {
-            if is_pdf(&root_path) {
-                add_book(&mut found, &root_path)?;
-            }
-            continue;
-        }
+        first.set_book_labels(
+            &path,
+            BookLabels {
+                tags: vec!["rust".to_string(), "async".to_string()],
+                collection: None,
+                ..BookLabels::default()
+            },
+        );
+        first
+            .notes_by_path
+            .insert(path.clone(), vec![note("kept"), note("from first")]);
+        first.dirty_note_paths.insert(path.clone());
+        first.settings.watch_library = false;
+        save_session(&storage, &mut first)?;
 
-        if !root_path.is_dir() {
-            continue;
-        }
+        second.set_book_labels(
+            &path,
+            BookLabels {
+                tags: vec!["rust".to_string(), "db".to_string()],
+                collection: None,
+                ..BookLabels::default()
+            },
+        );
+        second
+            .notes_by_path
+            .entry(path.clone())
+            .or_default()
+            .push(note("from second"));
+        second.dirty_note_paths.insert(path.clone());
+        second.keep_stored_settings = true;
+        save_session(&storage, &mut second)?;
 
-        match settings.scan_scope {
-            ScanScope::Direct => {
-                for entry in fs::read_dir(&root_path)
-                    .with_context(|| format!("read dir {}", root_path.display()))?
-                {
-                    let entry = entry?;
-                    let path = entry.path();
-                    if path.is_file() && is_pdf(&path) {
-                        add_book(&mut found, &path)?;
-                    }
-                }
-            }
-            ScanScope::Recursive => {
-                let mut stack = vec![root_path];
-                while let Some(dir) = stack.pop() {
-                    for entry in
-                        fs::read_dir(&dir).with_context(|| format!("read dir {}", dir.display()))?
-                    {
-                        let entry = entry?;
-                        let path = entry.path();
-                        if path.is_dir() {
-                            stack.push(path);
-                        } else if path.is_file() && is_pdf(&path) {
-                            add_book(&mut found, &path)?;
-                        }
-                    }
-                }
-            }
+        let labels = storage.list_labels_by_path()?;
+        assert_eq!(
+            labels.get(&path).map(|l| l.tags.clone()),
+            Some(vec![
+                "async".to_string(),
+                "db".to_string(),
+                "rust".to_string()
+            ])
+        );
+        let bodies: Vec<String> = storage.list_notes_by_path()?[&path]
+            .iter()
+            .map(|n| n.body.clone())
+            .collect();
+        assert_eq!(bodies, vec!["from first", "from second", "kept"]);
+        assert!(!storage.load_settings()?.watch_library);
+        assert_eq!(second.db_generation, storage.load_generation()?);
+        drop(storage);
+        let _ = fs::remove_file(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn notes_added_before_a_panic_are_saved() -> anyhow::Result<()> {
+        let db_path =
+            std::env::temp_dir().join(format!("bookshelf-panic-{}.db", std::process::id()));
+        let _ = fs::remove_file(&db_path);
+        let storage = Rc::new(Storage::open(&db_path)?);
+        let book = Book {
+            path: "/library/a.pdf".to_string(),
+            title: "a".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        storage.upsert_book(&book)?;
+
+        let settings = Settings {
+            watch_library: false,
+            ..Settings::default()
+        };
+        let mut ctx = AppContext::new(settings).with_library(String::new(), vec![book.clone()]);
+        let notes = vec![Note {
+            page: 3,
+            body: "written just before the crash".to_string(),
+        }];
+        ctx.notes_by_path.insert(book.path.clone(), notes.clone());
+        ctx.dirty_note_paths.insert(book.path.clone());
+
+        let session_storage = Rc::clone(&storage);
+        let mut ui = Ui::new(ctx, None).with_session_sink(Box::new(move |ctx: &mut AppContext| {
+            save_session(&session_storage, ctx)
+        }));
+        let result = ui.run_guarded(|_| panic!("mid-session"));
+
+        let err = result.expect_err("the panic is reported");
+        assert!(err.to_string().contains("mid-session"), "{err:#}");
+        assert_eq!(storage.list_notes_by_path()?.get(&book.path), Some(&notes));
+        drop(storage);
+        let _ = fs::remove_file(&db_path);
+        Ok(())
+    }
+
+    #[test]
+    fn autosave_writes_capped_batches_until_nothing_is_left() -> anyhow::Result<()> {
+        let storage = Storage::open(":memory:")?;
+        let books: Vec<Book> = (0..AUTOSAVE_MAX_CHANGES + 5)
+            .map(|idx| scanned(&format!("/library/{idx}.pdf"), "b", None))
+            .collect();
+        for book in &books {
+            storage.upsert_book(book)?;
+        }
+        let mut ctx = AppContext::new(Settings::default()).with_library(String::new(), books);
+        for book in &ctx.books {
+            let notes = vec![Note {
+                page: 1,
+                body: book.path.clone(),
+            }];
+            ctx.notes_by_path.insert(book.path.clone(), notes);
+            ctx.dirty_note_paths.insert(book.path.clone());
         }
+        ctx.settings.watch_library = false;
+
+        assert_eq!(autosave(&storage, &mut ctx)?, AUTOSAVE_MAX_CHANGES);
+        assert_eq!(storage.list_notes_by_path()?.len(), AUTOSAVE_MAX_CHANGES);
+        assert!(ctx.has_unsaved_changes());
+        assert_eq!(ctx.db_generation, storage.load_generation()?);
+        assert_eq!(autosave(&storage, &mut ctx)?, 5);
+        assert_eq!(
+            storage.list_notes_by_path()?.len(),
+            AUTOSAVE_MAX_CHANGES + 5
+        );
+        assert!(!ctx.has_unsaved_changes());
+        // Settings wait for the save on quit.
+        assert!(storage.load_settings()?.watch_library);
+
+        // Another instance saving in between is merged, not overwritten.
+        let path = ctx.books[0].path.clone();
+        storage.bump_generation()?;
+        storage.replace_notes(
+            &path,
+            &[
+                ctx.notes_by_path[&path][0].clone(),
+                Note {
+                    page: 9,
+                    body: "theirs".to_string(),
+                },
+            ],
+        )?;
+        ctx.notes_by_path.get_mut(&path).unwrap().push(Note {
+            page: 2,
+            body: "ours".to_string(),
+        });
+        ctx.dirty_note_paths.insert(path.clone());
+        assert_eq!(autosave(&storage, &mut ctx)?, 1);
+        assert_eq!(storage.list_notes_by_path()?[&path].len(), 3);
+        assert_ne!(ctx.db_generation, storage.load_generation()?);
+        Ok(())
     }
 
-    Ok(found.into_values().collect())
-}
+    #[test]
+    fn save_now_writes_every_autosave_batch() -> anyhow::Result<()> {
+        let storage = Storage::open(":memory:")?;
+        let count = 2 * AUTOSAVE_MAX_CHANGES + 3;
+        let books: Vec<Book> = (0..count)
+            .map(|idx| scanned(&format!("/library/{idx}.pdf"), "b", None))
+            .collect();
+        for book in &books {
+            storage.upsert_book(book)?;
+        }
+        let mut ctx = AppContext::new(Settings::default()).with_library(String::new(), books);
+        for book in &mut ctx.books {
+            book.favorite = true;
+            ctx.dirty_favorite_paths.insert(book.path.clone());
+        }
 
-fn is_pdf(path: &Path) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.eq_ignore_ascii_case("pdf"))
-        .unwrap_or(false)
-}
+        let mut sink = |ctx: &mut AppContext| autosave(&storage, ctx);
+        assert_eq!(save_all_changes(&mut sink, &mut ctx)?, count);
+        assert!(!ctx.has_unsaved_changes());
+        assert!(storage.list_books()?.iter().all(|book| book.favorite));
+        Ok(())
+    }
 
-fn add_book(out: &mut std::collections::BTreeMap<String, Book>, path: &Path) -> anyhow::Result<()> {
-    let normalized = match fs::canonicalize(path) {
-        Ok(p) => p,
-        Err(_) => path.to_path_buf(),
-    };
-    let path_str = encode_path(&normalized);
-    let title = normalized
-        .file_stem()
-        .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_else(|| "untitled".to_string());
-
-    out.insert(
-        path_str.clone(),
+    fn scanned(path: &str, title: &str, author: Option<&str>) -> Book {
         Book {
-            path: path_str,
-            title,
+            path: path.to_string(),
+            title: title.to_string(),
             last_opened: None,
             favorite: false,
-        },
-    );
-    Ok(())
+            author: author.map(str::to_string),
+            status: ReadingStatus::Unread,
+            archived: false,
+        }
+    }
+
+    #[test]
+    fn rescans_keep_stored_fields_and_add_new_books() -> anyhow::Result<()> {
+        let storage = Storage::open(":memory:")?;
+        storage.upsert_book(&scanned("/library/a.pdf", "a", None))?;
+        storage.set_book_metadata("/library/a.pdf", "My Edited Title", Some("Old"))?;
+        storage.set_favorite("/library/a.pdf", true)?;
+        storage.set_last_opened("/library/a.pdf", 42)?;
+        storage.upsert_book(&scanned("/library/b.pdf", "b", None))?;
+        let added_before = storage.list_added_at()?;
+
+        let merge = sync_library_books(
+            &storage,
+            &[
+                scanned("/library/a.pdf", "a", Some("Le Guin")),
+                scanned("/library/b.pdf", "b", None),
+                scanned("/library/c.pdf", "c", Some("Austen")),
+            ],
+            true,
+        )?;
+        assert_eq!(
+            merge.report(),
+            bookshelf_application::ScanReport {
+                added: 1,
+                updated: 1,
+                missing: 0,
+                unchanged: 1,
+            }
+        );
+
+        let books = storage.list_books()?;
+        let a = books.iter().find(|b| b.path == "/library/a.pdf").unwrap();
+        assert_eq!(a.title, "My Edited Title");
+        assert_eq!(a.author.as_deref(), Some("Le Guin"));
+        assert!(a.favorite);
+        assert_eq!(a.last_opened, Some(42));
+        let c = books.iter().find(|b| b.path == "/library/c.pdf").unwrap();
+        assert_eq!(c.author.as_deref(), Some("Austen"));
+
+        let added_at = storage.list_added_at()?;
+        assert_eq!(added_at["/library/a.pdf"], added_before["/library/a.pdf"]);
+        assert!(added_at["/library/c.pdf"] >= added_before["/library/a.pdf"]);
+        Ok(())
+    }
+
+    #[test]
+    fn rescans_keep_books_they_did_not_find() -> anyhow::Result<()> {
+        let storage = Storage::open(":memory:")?;
+        storage.upsert_book(&scanned("/library/a.pdf", "a", None))?;
+        storage.upsert_book(&scanned("/library/gone.pdf", "gone", None))?;
+        let found = [scanned("/library/a.pdf", "a", None)];
+
+        let merge = sync_library_books(&storage, &found, true)?;
+        assert_eq!(merge.missing, vec!["/library/gone.pdf".to_string()]);
+        assert_eq!(merge.unchanged, 1);
+        assert_eq!(storage.list_books()?.len(), 2);
+
+        // A cancelled scan cannot tell what is missing.
+        let merge = sync_library_books(&storage, &found, false)?;
+        assert!(merge.missing.is_empty());
+        assert_eq!(storage.list_books()?.len(), 2);
+        Ok(())
+    }
 }
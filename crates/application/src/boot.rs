@@ -0,0 +1,137 @@
+use bookshelf_core::ReaderMode;
+
+/// Set (to anything but `0`) to boot straight into the reader; kept for launchers that still
+/// pass the book through the environment.
+pub const BOOT_READER_ENV: &str = "BOOKSHELF_BOOT_READER";
+/// Stored path of the book to boot into.
+pub const BOOT_READER_PATH_ENV: &str = "BOOKSHELF_BOOT_READER_PATH";
+/// 0-based page to boot at.
+pub const BOOT_READER_PAGE_INDEX_ENV: &str = "BOOKSHELF_BOOT_READER_PAGE_INDEX";
+/// `image` or `text`.
+pub const BOOT_READER_MODE_ENV: &str = "BOOKSHELF_BOOT_READER_MODE";
+
+/// Every variable read by [`BootReader::from_vars`], for launchers that must not pass them on.
+pub const BOOT_READER_ENV_VARS: [&str; 4] = [
+    BOOT_READER_ENV,
+    BOOT_READER_PATH_ENV,
+    BOOT_READER_PAGE_INDEX_ENV,
+    BOOT_READER_MODE_ENV,
+];
+
+/// A book the UI opens in the reader before showing the library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootReader {
+    /// Stored (encoded) path of the book.
+    pub path: String,
+    pub page_index: u32,
+    /// Reader mode to switch to; `None` keeps the configured one.
+    pub mode: Option<ReaderMode>,
+}
+
+impl BootReader {
+    /// The book named on the command line, or else the one in the boot-reader environment
+    /// variables looked up through `var`.
+    pub fn resolve(args: Option<Self>, var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        args.or_else(|| Self::from_vars(var))
+    }
+
+    /// Reads the boot-reader environment variables through `var`. Returns `None` unless the
+    /// flag and the path are both set; a page that does not parse is page 0.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let enabled = var(BOOT_READER_ENV).is_some_and(|v| !v.trim().is_empty() && v.trim() != "0");
+        if !enabled {
+            return None;
+        }
+        let path = var(BOOT_READER_PATH_ENV)?;
+        let page_index = var(BOOT_READER_PAGE_INDEX_ENV)
+            .and_then(|s| s.trim().parse::<u32>().ok())
+            .unwrap_or(0);
+        let mode = var(BOOT_READER_MODE_ENV).and_then(|mode| parse_reader_mode(&mode));
+        Some(Self {
+            path,
+            page_index,
+            mode,
+        })
+    }
+}
+
+/// `image` or `text`, ignoring case and surrounding whitespace.
+pub fn parse_reader_mode(mode: &str) -> Option<ReaderMode> {
+    let mode = mode.trim();
+    if mode.eq_ignore_ascii_case("image") {
+        Some(ReaderMode::Image)
+    } else if mode.eq_ignore_ascii_case("text") {
+        Some(ReaderMode::Text)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn env(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn arguments_win_over_the_environment() {
+        let from_env = env(&[
+            (BOOT_READER_ENV, "1"),
+            (BOOT_READER_PATH_ENV, "/env.pdf"),
+            (BOOT_READER_PAGE_INDEX_ENV, "7"),
+            (BOOT_READER_MODE_ENV, "Image"),
+        ]);
+        let from_args = BootReader {
+            path: "/args.pdf".to_string(),
+            page_index: 2,
+            mode: None,
+        };
+        assert_eq!(
+            BootReader::resolve(Some(from_args.clone()), &from_env),
+            Some(from_args)
+        );
+        assert_eq!(
+            BootReader::resolve(None, &from_env),
+            Some(BootReader {
+                path: "/env.pdf".to_string(),
+                page_index: 7,
+                mode: Some(ReaderMode::Image),
+            })
+        );
+    }
+
+    #[test]
+    fn environment_needs_the_flag_and_a_path() {
+        let path_only = env(&[(BOOT_READER_PATH_ENV, "/a.pdf")]);
+        assert_eq!(BootReader::resolve(None, path_only), None);
+        let disabled = env(&[(BOOT_READER_ENV, "0"), (BOOT_READER_PATH_ENV, "/a.pdf")]);
+        assert_eq!(BootReader::resolve(None, disabled), None);
+        assert_eq!(
+            BootReader::resolve(None, env(&[(BOOT_READER_ENV, "1")])),
+            None
+        );
+
+        let bad_page = env(&[
+            (BOOT_READER_ENV, "yes"),
+            (BOOT_READER_PATH_ENV, "/a.pdf"),
+            (BOOT_READER_PAGE_INDEX_ENV, "ten"),
+            (BOOT_READER_MODE_ENV, "sepia"),
+        ]);
+        assert_eq!(
+            BootReader::resolve(None, bad_page),
+            Some(BootReader {
+                path: "/a.pdf".to_string(),
+                page_index: 0,
+                mode: None,
+            })
+        );
+    }
+}
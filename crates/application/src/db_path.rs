@@ -0,0 +1,49 @@
+use std::path::{Path, PathBuf};
+
+/// Path of the library database, for when `--db` is not given.
+pub const DB_PATH_ENV: &str = "BOOKSHELF_DB";
+
+/// The library database: the path given with `--db`, else the one in `DB_PATH_ENV` looked up
+/// through `var`, else `.bookshelf/bookshelf.db` under `cwd`. Relative paths start at `cwd`.
+pub fn resolve_db_path(
+    cwd: &Path,
+    flag: Option<&str>,
+    var: impl Fn(&str) -> Option<String>,
+) -> PathBuf {
+    let chosen = flag
+        .map(str::to_string)
+        .or_else(|| var(DB_PATH_ENV))
+        .filter(|path| !path.trim().is_empty());
+    match chosen {
+        Some(path) => cwd.join(path.trim()),
+        None => cwd.join(".bookshelf").join("bookshelf.db"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flag_wins_over_the_environment_and_the_default() {
+        let cwd = Path::new("/home/me/books");
+        let env =
+            |value: &'static str| move |var: &str| (var == DB_PATH_ENV).then(|| value.to_string());
+        assert_eq!(
+            resolve_db_path(cwd, None, |_| None),
+            PathBuf::from("/home/me/books/.bookshelf/bookshelf.db")
+        );
+        assert_eq!(
+            resolve_db_path(cwd, None, env("/srv/work.db")),
+            PathBuf::from("/srv/work.db")
+        );
+        assert_eq!(
+            resolve_db_path(cwd, Some("personal.db"), env("/srv/work.db")),
+            PathBuf::from("/home/me/books/personal.db")
+        );
+        assert_eq!(
+            resolve_db_path(cwd, None, env("  ")),
+            PathBuf::from("/home/me/books/.bookshelf/bookshelf.db")
+        );
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::UNIX_EPOCH;
+
+use bookshelf_core::{FileFingerprint, decode_path};
+use sha2::{Digest, Sha256};
+
+use crate::scan::Wake;
+
+/// Bytes read from each end of a file for its quick and tail hashes.
+pub const QUICK_HASH_BYTES: u64 = 64 * 1024;
+/// A background pass reports progress after this many books.
+const DUPLICATE_PROGRESS_EVERY_BOOKS: usize = 32;
+
+/// Outcome of a duplicate pass over the library.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DuplicateReport {
+    /// Paths whose files have the same content. Each group is sorted and holds two or more
+    /// paths.
+    pub groups: Vec<Vec<String>>,
+    /// Books left out because their file could not be read.
+    pub unreadable: usize,
+}
+
+/// What happens to the other copies once their data has moved to the kept one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateDisposal {
+    /// Hidden from the library, so rescans don't bring them back.
+    Archive,
+    /// Dropped from the library; the files stay on disk.
+    Remove,
+}
+
+/// What a background duplicate pass sends back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DuplicateEvent {
+    /// `done` of `total` books fingerprinted.
+    Progress { done: usize, total: usize },
+    /// The pass is over. `report` is `None` when it was cancelled; `fingerprints` are the ones
+    /// taken or completed either way, for the library to keep.
+    Finished {
+        report: Option<DuplicateReport>,
+        fingerprints: Vec<(String, FileFingerprint)>,
+    },
+}
+
+/// A duplicate pass running on a background thread, so hashing a large or slow library
+/// does not hold up the UI.
+pub struct DuplicateScan {
+    events: Receiver<DuplicateEvent>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl DuplicateScan {
+    /// Starts a pass over `paths`, reusing `fingerprints` that are still fresh. `wake`, when
+    /// set, is called after each event is sent.
+    pub fn start(
+        paths: Vec<String>,
+        mut fingerprints: HashMap<String, FileFingerprint>,
+        wake: Option<Wake>,
+    ) -> Self {
+        let (tx, events) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = Arc::clone(&cancel);
+        let send = move |event: DuplicateEvent| {
+            let _ = tx.send(event);
+            if let Some(wake) = &wake {
+                wake();
+            }
+        };
+        let spawned = thread::Builder::new()
+            .name("bookshelf-duplicates".to_string())
+            .spawn({
+                let send = send.clone();
+                move || {
+                    let total = paths.len();
+                    let mut dirty = HashSet::new();
+                    let mut reported = None;
+                    let report = find_duplicates(&paths, &mut fingerprints, &mut dirty, |done| {
+                        if done % DUPLICATE_PROGRESS_EVERY_BOOKS == 0 && reported != Some(done) {
+                            reported = Some(done);
+                            send(DuplicateEvent::Progress { done, total });
+                        }
+                        !worker_cancel.load(Ordering::Relaxed)
+                    });
+                    let mut fingerprints: Vec<(String, FileFingerprint)> = fingerprints
+                        .into_iter()
+                        .filter(|(path, _)| dirty.contains(path))
+                        .collect();
+                    fingerprints.sort_by(|a, b| a.0.cmp(&b.0));
+                    send(DuplicateEvent::Finished {
+                        report,
+                        fingerprints,
+                    });
+                }
+            });
+        if spawned.is_err() {
+            // Without a thread there is nothing to report but that the pass ended.
+            send(DuplicateEvent::Finished {
+                report: None,
+                fingerprints: Vec::new(),
+            });
+        }
+        Self { events, cancel }
+    }
+
+    pub fn try_recv(&self) -> Option<DuplicateEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Asks the pass to stop; a `Finished` event without a report still follows.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Groups `paths` by file content. Files are first matched on size and the hash of their
+/// first 64 KiB; only files that collide there are hashed in full. Fingerprints still fresh in
+/// `fingerprints` are reused, and paths whose fingerprint was (re)taken are added to `dirty`.
+/// `progress` is called with the number of books fingerprinted so far; when it returns
+/// `false` the pass stops and returns `None`.
+pub(crate) fn find_duplicates(
+    paths: &[String],
+    fingerprints: &mut HashMap<String, FileFingerprint>,
+    dirty: &mut HashSet<String>,
+    mut progress: impl FnMut(usize) -> bool,
+) -> Option<DuplicateReport> {
+    let mut report = DuplicateReport::default();
+    let mut by_quick_hash: BTreeMap<(u64, String), Vec<String>> = BTreeMap::new();
+    for (done, path) in paths.iter().enumerate() {
+        if !progress(done) {
+            return None;
+        }
+        let known = fingerprints.get(path);
+        let fingerprint = match fingerprint_file(&decode_path(path), known) {
+            Ok(fingerprint) => fingerprint,
+            Err(_) => {
+                report.unreadable += 1;
+                continue;
+            }
+        };
+        let key = (fingerprint.size_bytes, fingerprint.quick_hash.clone());
+        if fingerprints.get(path) != Some(&fingerprint) {
+            fingerprints.insert(path.clone(), fingerprint);
+            dirty.insert(path.clone());
+        }
+        by_quick_hash.entry(key).or_default().push(path.clone());
+    }
+
+    for candidates in by_quick_hash.into_values().filter(|paths| paths.len() > 1) {
+        if !progress(paths.len()) {
+            return None;
+        }
+        let mut by_full_hash: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for path in candidates {
+            let Some(fingerprint) = fingerprints.get_mut(&path) else {
+                continue;
+            };
+            if fingerprint.full_hash.is_none() {
+                // A file no longer than the quick read was hashed whole already.
+                let full_hash = if fingerprint.size_bytes <= QUICK_HASH_BYTES {
+                    Ok(fingerprint.quick_hash.clone())
+                } else {
+                    File::open(decode_path(&path)).and_then(hash_reader)
+                };
+                match full_hash {
+                    Ok(hash) => {
+                        fingerprint.full_hash = Some(hash);
+                        dirty.insert(path.clone());
+                    }
+                    Err(_) => {
+                        report.unreadable += 1;
+                        continue;
+                    }
+                }
+            }
+            let hash = fingerprint.full_hash.clone().unwrap_or_default();
+            by_full_hash.entry(hash).or_default().push(path);
+        }
+        report
+            .groups
+            .extend(by_full_hash.into_values().filter(|paths| paths.len() > 1));
+    }
+
+    for group in &mut report.groups {
+        group.sort();
+    }
+    report.groups.sort();
+    Some(report)
+}
+
+/// Fingerprints the file at `path`, reusing `known` when the file hasn't changed since.
+pub(crate) fn fingerprint_file(
+    path: &Path,
+    known: Option<&FileFingerprint>,
+) -> io::Result<FileFingerprint> {
+    let metadata = std::fs::metadata(path)?;
+    let size_bytes = metadata.len();
+    let modified_secs = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| {
+            i64::try_from(since.as_secs()).unwrap_or(i64::MAX)
+        });
+    if let Some(known) = known.filter(|known| known.is_fresh(size_bytes, modified_secs)) {
+        if known.tail_hash.is_some() {
+            return Ok(known.clone());
+        }
+        return Ok(FileFingerprint {
+            tail_hash: Some(tail_hash(path, size_bytes, &known.quick_hash)?),
+            ..known.clone()
+        });
+    }
+    let quick_hash = hash_reader(File::open(path)?.take(QUICK_HASH_BYTES))?;
+    Ok(FileFingerprint {
+        size_bytes,
+        modified_secs,
+        tail_hash: Some(tail_hash(path, size_bytes, &quick_hash)?),
+        quick_hash,
+        full_hash: None,
+    })
+}
+
+/// Hash of the last `QUICK_HASH_BYTES` of a file of `size_bytes`; a file no longer than that
+/// was hashed whole for `quick_hash` already.
+fn tail_hash(path: &Path, size_bytes: u64, quick_hash: &str) -> io::Result<String> {
+    if size_bytes <= QUICK_HASH_BYTES {
+        return Ok(quick_hash.to_string());
+    }
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(size_bytes - QUICK_HASH_BYTES))?;
+    hash_reader(file.take(QUICK_HASH_BYTES))
+}
+
+fn hash_reader(mut reader: impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn make_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "bookshelf-dupes-{name}-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn groups_identical_files_and_full_hashes_only_collisions() -> anyhow::Result<()> {
+        let dir = make_dir("groups");
+        let head = vec![7u8; QUICK_HASH_BYTES as usize];
+        let mut book = head.clone();
+        book.extend_from_slice(b"the rest of the book");
+        let mut errata = head.clone();
+        errata.extend_from_slice(b"the rest of the errata");
+        fs::write(dir.join("a.pdf"), &book)?;
+        fs::write(dir.join("b.pdf"), &book)?;
+        // Same size and first 64 KiB as the book, different ending.
+        fs::write(dir.join("c.pdf"), &errata[..book.len()])?;
+        fs::write(dir.join("d.pdf"), b"small")?;
+        fs::write(dir.join("e.pdf"), b"small")?;
+        fs::write(dir.join("f.pdf"), b"other")?;
+
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        let mut paths: Vec<String> = ["a.pdf", "b.pdf", "c.pdf", "d.pdf", "e.pdf", "f.pdf"]
+            .into_iter()
+            .map(path)
+            .collect();
+        paths.push(path("missing.pdf"));
+
+        let mut fingerprints = HashMap::new();
+        let mut dirty = HashSet::new();
+        let report = find_duplicates(&paths, &mut fingerprints, &mut dirty, |_| true)
+            .expect("not cancelled");
+        assert_eq!(
+            report.groups,
+            vec![
+                vec![path("a.pdf"), path("b.pdf")],
+                vec![path("d.pdf"), path("e.pdf")],
+            ]
+        );
+        assert_eq!(report.unreadable, 1);
+        assert_eq!(dirty.len(), 6);
+        assert!(fingerprints[&path("c.pdf")].full_hash.is_some());
+        assert!(fingerprints[&path("f.pdf")].full_hash.is_none());
+
+        // Unchanged files are not hashed again.
+        dirty.clear();
+        let again = find_duplicates(&paths, &mut fingerprints, &mut dirty, |_| true);
+        assert_eq!(again, Some(report));
+        assert!(dirty.is_empty());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn background_pass_reports_progress_then_the_report() -> anyhow::Result<()> {
+        let dir = make_dir("background");
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        let paths: Vec<String> = ["a.pdf", "b.pdf", "c.pdf"].into_iter().map(path).collect();
+        for (path, body) in paths.iter().zip(["same", "same", "other"]) {
+            fs::write(path, body)?;
+        }
+
+        let scan = DuplicateScan::start(paths.clone(), HashMap::new(), None);
+        let mut events = Vec::new();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while !matches!(events.last(), Some(DuplicateEvent::Finished { .. }))
+            && std::time::Instant::now() < deadline
+        {
+            match scan.try_recv() {
+                Some(event) => events.push(event),
+                None => thread::sleep(std::time::Duration::from_millis(5)),
+            }
+        }
+        fs::remove_dir_all(&dir)?;
+
+        assert_eq!(events[0], DuplicateEvent::Progress { done: 0, total: 3 });
+        let Some(DuplicateEvent::Finished {
+            report,
+            fingerprints,
+        }) = events.last()
+        else {
+            panic!("no Finished event: {events:?}");
+        };
+        assert_eq!(
+            report.as_ref().map(|report| report.groups.clone()),
+            Some(vec![vec![path("a.pdf"), path("b.pdf")]])
+        );
+        let fingerprinted: Vec<&String> = fingerprints.iter().map(|(path, _)| path).collect();
+        assert_eq!(fingerprinted, paths.iter().collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn a_stopped_pass_keeps_the_fingerprints_it_took() -> anyhow::Result<()> {
+        let dir = make_dir("stopped");
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        let paths: Vec<String> = ["a.pdf", "b.pdf"].into_iter().map(path).collect();
+        for path in &paths {
+            fs::write(path, "same")?;
+        }
+
+        let mut fingerprints = HashMap::new();
+        let mut dirty = HashSet::new();
+        let report = find_duplicates(&paths, &mut fingerprints, &mut dirty, |done| done < 1);
+        fs::remove_dir_all(&dir)?;
+
+        assert_eq!(report, None);
+        assert_eq!(dirty, HashSet::from([path("a.pdf")]));
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprints_cover_length_head_and_tail_only() -> anyhow::Result<()> {
+        let dir = make_dir("edges");
+        let edge = QUICK_HASH_BYTES as usize;
+        let book = vec![1u8; edge * 3];
+        let mut middle_changed = book.clone();
+        middle_changed[edge + 10] = 2;
+        let mut tail_changed = book.clone();
+        tail_changed[edge * 3 - 1] = 2;
+        let write = |name: &str, bytes: &[u8]| -> anyhow::Result<FileFingerprint> {
+            let path = dir.join(name);
+            fs::write(&path, bytes)?;
+            Ok(fingerprint_file(&path, None)?)
+        };
+        let edges = |fingerprint: FileFingerprint| {
+            (
+                fingerprint.size_bytes,
+                fingerprint.quick_hash,
+                fingerprint.tail_hash,
+            )
+        };
+        let original = write("a.pdf", &book)?;
+        let moved = write("b.pdf", &book)?;
+        let middle = write("c.pdf", &middle_changed)?;
+        let tail = write("d.pdf", &tail_changed)?;
+        let short = write("e.pdf", &book[..edge * 3 - 1])?;
+        let small = write("f.pdf", b"small")?;
+
+        // A fingerprint from before tails were hashed gets its tail, and keeps the rest.
+        let untailed = FileFingerprint {
+            tail_hash: None,
+            full_hash: Some("kept".to_string()),
+            ..original.clone()
+        };
+        let tailed = fingerprint_file(&dir.join("a.pdf"), Some(&untailed))?;
+        fs::remove_dir_all(&dir)?;
+
+        assert_eq!(tailed.tail_hash, original.tail_hash);
+        assert_eq!(tailed.full_hash.as_deref(), Some("kept"));
+        assert_eq!(small.tail_hash.as_ref(), Some(&small.quick_hash));
+        let original = edges(original);
+        assert_eq!(edges(moved), original);
+        // Edits between the two ends go unnoticed; full hashes settle duplicates.
+        assert_eq!(edges(middle), original);
+        assert_ne!(edges(tail), original);
+        assert_ne!(edges(short), original);
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+
+use bookshelf_core::TagKind;
+
+use crate::{CollectionFilter, TagMatchMode};
+
+/// Library filters kept between sessions. Stored as JSON; fields missing from older copies
+/// take their defaults and a copy that does not parse is ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LibraryFilters {
+    pub query: String,
+    pub favorites_only: bool,
+    pub collection: CollectionFilter,
+    pub tags: Vec<String>,
+    pub tag_match_mode: TagMatchMode,
+    pub author: Option<String>,
+    pub series: Option<String>,
+}
+
+impl LibraryFilters {
+    pub fn from_json(json: &str) -> Self {
+        serde_json::from_str(json).unwrap_or_default()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Drops labels that are no longer in the label catalog, and spells the rest the way the
+    /// catalog does. An author filter is kept as is: it also matches authors read from the
+    /// documents, which the catalog does not list.
+    pub(crate) fn retain_known<'a>(&mut self, catalog: impl Fn(TagKind) -> &'a Vec<String>) {
+        let known = |kind: TagKind, name: &str| {
+            catalog(kind)
+                .iter()
+                .find(|known| known.eq_ignore_ascii_case(name.trim()))
+                .cloned()
+        };
+        self.tags = self
+            .tags
+            .iter()
+            .filter_map(|tag| known(TagKind::Tag, tag))
+            .collect();
+        if let CollectionFilter::Selected(name) = &self.collection {
+            self.collection = match known(TagKind::Collection, name) {
+                Some(name) => CollectionFilter::Selected(name),
+                None => CollectionFilter::Any,
+            };
+        }
+        self.series = self
+            .series
+            .as_deref()
+            .and_then(|name| known(TagKind::Series, name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_roundtrip_tolerates_old_and_broken_copies() {
+        let filters = LibraryFilters {
+            query: "author:knuth".to_string(),
+            favorites_only: true,
+            collection: CollectionFilter::Selected("Work".to_string()),
+            tags: vec!["rust".to_string()],
+            tag_match_mode: TagMatchMode::And,
+            author: Some("Knuth".to_string()),
+            series: Some("TAOCP".to_string()),
+        };
+        assert_eq!(LibraryFilters::from_json(&filters.to_json()), filters);
+        assert_eq!(
+            LibraryFilters::from_json(r#"{"favorites_only":true}"#),
+            LibraryFilters {
+                favorites_only: true,
+                ..LibraryFilters::default()
+            }
+        );
+        assert_eq!(
+            LibraryFilters::from_json("not json"),
+            LibraryFilters::default()
+        );
+    }
+
+    #[test]
+    fn unknown_labels_are_dropped() {
+        let tags = vec!["rust".to_string()];
+        let collections = vec!["work".to_string()];
+        let series = vec!["Dune".to_string()];
+        let none = Vec::new();
+        let catalog = |kind: TagKind| match kind {
+            TagKind::Tag => &tags,
+            TagKind::Collection => &collections,
+            TagKind::Series => &series,
+            TagKind::Author => &none,
+        };
+
+        let mut filters = LibraryFilters {
+            collection: CollectionFilter::Selected("gone".to_string()),
+            tags: vec!["RUST".to_string(), "deleted".to_string()],
+            series: Some("Foundation".to_string()),
+            author: Some("Herbert".to_string()),
+            ..LibraryFilters::default()
+        };
+        filters.retain_known(catalog);
+        assert_eq!(filters.tags, vec!["rust".to_string()]);
+        assert_eq!(filters.collection, CollectionFilter::Any);
+        assert_eq!(filters.series, None);
+        assert_eq!(filters.author, Some("Herbert".to_string()));
+
+        let mut filters = LibraryFilters {
+            collection: CollectionFilter::Selected("WORK".to_string()),
+            series: Some("dune".to_string()),
+            ..LibraryFilters::default()
+        };
+        filters.retain_known(catalog);
+        assert_eq!(
+            filters.collection,
+            CollectionFilter::Selected("work".to_string())
+        );
+        assert_eq!(filters.series, Some("Dune".to_string()));
+    }
+}
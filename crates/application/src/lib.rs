@@ -1,32 +1,161 @@
 //! Application orchestration layer for Bookshelf.
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::collections::HashSet;
 
-use bookshelf_core::{Book, BookLabels, Bookmark, Note, Progress, Settings, TagKind};
+use bookshelf_core::{
+    Book, BookKind, BookLabels, BookProgress, Bookmark, CleanupReport, DocumentMetadata,
+    FileFingerprint, Note, PageCount, Progress, ReaderViewState, ReadingStatus, Settings, TagKind,
+};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+mod boot;
+mod db_path;
+mod duplicates;
+mod filters;
+mod merge;
+mod moved;
+mod query;
+mod rescan;
+mod scan;
+mod tag_tree;
+mod watch;
+
+pub use boot::{
+    BOOT_READER_ENV, BOOT_READER_ENV_VARS, BOOT_READER_MODE_ENV, BOOT_READER_PAGE_INDEX_ENV,
+    BOOT_READER_PATH_ENV, BootReader, parse_reader_mode,
+};
+pub use db_path::{DB_PATH_ENV, resolve_db_path};
+pub use duplicates::{
+    DuplicateDisposal, DuplicateEvent, DuplicateReport, DuplicateScan, QUICK_HASH_BYTES,
+};
+pub use filters::LibraryFilters;
+pub use merge::{SessionBase, StoredBookData};
+pub use moved::MovedBook;
+pub use query::LibraryQuery;
+pub use rescan::{ScanMerge, ScanReport, merge_scan};
+pub use scan::{
+    LibraryScan, MetadataReader, ScanEvent, ScanMetadata, Wake, is_book_file, resolve_root,
+    scan_books,
+};
+pub use tag_tree::{
+    TagTreeRow, complete_tag_namespace, is_tag_namespace, tag_matches, tag_namespaces, tag_tree,
+};
+pub use watch::LibraryWatcher;
+
+/// Number of books kept in the recently-opened history.
+pub const RECENT_BOOKS_LIMIT: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TagMatchMode {
     And,
+    #[default]
     Or,
 }
 
-impl Default for TagMatchMode {
-    fn default() -> Self {
-        Self::Or
-    }
-}
-
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CollectionFilter {
+    #[default]
     Any,
     None,
     Selected(String),
 }
 
-impl Default for CollectionFilter {
-    fn default() -> Self {
-        Self::Any
+/// Whether a book in `book_collection` passes the collection filter. Names ignore case.
+pub fn matches_collection_filter(filter: &CollectionFilter, book_collection: Option<&str>) -> bool {
+    match filter {
+        CollectionFilter::Any => true,
+        CollectionFilter::None => book_collection.is_none(),
+        CollectionFilter::Selected(wanted) => book_collection
+            .as_ref()
+            .is_some_and(|c| c.eq_ignore_ascii_case(wanted)),
+    }
+}
+
+/// The author a book is listed under: the one set by hand, else the document's.
+pub fn book_author<'a>(book: &'a Book, labels: &'a BookLabels) -> Option<&'a str> {
+    labels.author.as_deref().or(book.author.as_deref())
+}
+
+/// Whether a book with `value` passes a single-name filter such as the author or series one.
+/// Names ignore case; no filter matches every book.
+pub fn matches_name_filter(filter: Option<&str>, value: Option<&str>) -> bool {
+    filter.is_none_or(|wanted| value.is_some_and(|value| value.eq_ignore_ascii_case(wanted)))
+}
+
+/// Whether a book carrying `book_tags` has all (`And`) or any (`Or`) of the selected tags. A
+/// selected namespace such as `lang/` is had by any tag under it. No selected tags match every
+/// book.
+pub fn matches_tag_filter(selected: &[String], mode: TagMatchMode, book_tags: &[String]) -> bool {
+    if selected.is_empty() {
+        return true;
+    }
+    if book_tags.is_empty() {
+        return false;
+    }
+
+    match mode {
+        TagMatchMode::And => selected.iter().all(|t| {
+            let t = t.trim();
+            !t.is_empty() && book_tags.iter().any(|bt| tag_matches(t, bt))
+        }),
+        TagMatchMode::Or => selected.iter().any(|t| {
+            let t = t.trim();
+            !t.is_empty() && book_tags.iter().any(|bt| tag_matches(t, bt))
+        }),
+    }
+}
+
+/// Books whose unsaved changes were taken out of an `AppContext` to be written in one go.
+/// Each list names the books with that kind of change; values are read from the context when
+/// written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirtyBatch {
+    pub bookmark_paths: Vec<String>,
+    pub note_paths: Vec<String>,
+    pub label_paths: Vec<String>,
+    pub progress_paths: Vec<String>,
+    pub reader_state_paths: Vec<String>,
+    pub reading_time_paths: Vec<String>,
+    pub favorite_paths: Vec<String>,
+    pub status_paths: Vec<String>,
+    pub archived_paths: Vec<String>,
+    pub collection_position_paths: Vec<String>,
+    pub metadata_paths: Vec<String>,
+    pub page_count_paths: Vec<String>,
+    pub fingerprint_paths: Vec<String>,
+    pub opened_at: Vec<(String, i64)>,
+}
+
+impl DirtyBatch {
+    /// Changes in the batch, one per book and kind.
+    pub fn len(&self) -> usize {
+        [
+            &self.bookmark_paths,
+            &self.note_paths,
+            &self.label_paths,
+            &self.progress_paths,
+            &self.reader_state_paths,
+            &self.reading_time_paths,
+            &self.favorite_paths,
+            &self.status_paths,
+            &self.archived_paths,
+            &self.collection_position_paths,
+            &self.metadata_paths,
+            &self.page_count_paths,
+            &self.fingerprint_paths,
+        ]
+        .iter()
+        .map(|paths| paths.len())
+        .sum::<usize>()
+            + self.opened_at.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 }
 
@@ -38,47 +167,127 @@ pub struct AppContext {
     pub selected: usize,
     pub library_query: String,
     pub favorites_only: bool,
+    /// Lists archived books along with the rest; they are hidden otherwise.
+    pub include_archived: bool,
+    /// Only books with this reading status are listed.
+    pub status_filter: Option<ReadingStatus>,
+    /// Only books added to the library in the last this many days are listed.
+    pub added_within_days: Option<u32>,
+    /// Only books found to be of this kind are listed; books not looked at yet are not.
+    pub kind_filter: Option<BookKind>,
     pub collection_filter: CollectionFilter,
+    /// Lists the selected collection as a reading list, in its stored order.
+    pub collection_view: bool,
     pub tag_filters: Vec<String>,
     pub tag_match_mode: TagMatchMode,
-    pub progress_by_path: HashMap<String, u32>,
+    /// Only books by this author (set by hand, else from the document) are listed.
+    pub author_filter: Option<String>,
+    /// Only books in this series are listed, in series order.
+    pub series_filter: Option<String>,
+    pub progress_by_path: HashMap<String, BookProgress>,
+    pub reader_state_by_path: HashMap<String, ReaderViewState>,
+    /// Active reading time per book, in seconds.
+    pub reading_secs_by_path: HashMap<String, u64>,
+    /// Page counts remembered from earlier sessions, so browsing needn't open every book.
+    pub page_counts_by_path: HashMap<String, PageCount>,
+    /// Content hashes from earlier duplicate passes and scans, so unchanged files aren't read
+    /// again and books whose file moved can be found.
+    pub fingerprints_by_path: HashMap<String, FileFingerprint>,
     pub opened_at_by_path: HashMap<String, i64>,
+    /// Recently opened books, most recent first. Seeded from `Book::last_opened`.
+    pub recent_paths: Vec<String>,
+    pub added_at_by_path: HashMap<String, i64>,
+    /// Books added after this time are badged new. Starts at `Settings::last_scan_at`; a
+    /// complete scan moves it to the scan before, so the books that scan found count as new.
+    pub new_since: Option<i64>,
     pub labels_by_path: HashMap<String, BookLabels>,
+    /// Place of each book in its collection's reading list; books without one come last.
+    pub collection_positions_by_path: HashMap<String, u32>,
     pub bookmarks_by_path: HashMap<String, Vec<Bookmark>>,
     pub notes_by_path: HashMap<String, Vec<Note>>,
     pub dirty_favorite_paths: HashSet<String>,
+    pub dirty_status_paths: HashSet<String>,
+    pub dirty_archived_paths: HashSet<String>,
+    /// Books whose title or author was refreshed from document metadata.
+    pub dirty_metadata_paths: HashSet<String>,
+    pub dirty_progress_paths: HashSet<String>,
+    pub dirty_reader_state_paths: HashSet<String>,
+    pub dirty_reading_time_paths: HashSet<String>,
+    pub dirty_page_count_paths: HashSet<String>,
+    pub dirty_fingerprint_paths: HashSet<String>,
     pub dirty_label_paths: HashSet<String>,
+    pub dirty_collection_position_paths: HashSet<String>,
     pub known_tags: Vec<String>,
     pub known_collections: Vec<String>,
+    pub known_authors: Vec<String>,
+    pub known_series: Vec<String>,
     pub dirty_label_catalog_ops: Vec<LabelCatalogOp>,
     pub dirty_bookmark_paths: HashSet<String>,
     pub dirty_note_paths: HashSet<String>,
+    pub dirty_book_path_ops: Vec<BookPathOp>,
+    /// Save generation of the database when this session loaded or last saved it. Another
+    /// instance saving in between moves the stored one on.
+    pub db_generation: i64,
+    /// Chosen on a save conflict: leave the settings the other instance saved in place.
+    pub keep_stored_settings: bool,
+    /// Labels, bookmarks and notes as of `db_generation`, the base for `merge_stored`.
+    pub session_base: SessionBase,
 }
 
 impl AppContext {
     pub fn new(settings: Settings) -> Self {
         Self {
+            new_since: settings.last_scan_at,
             settings,
             cwd: String::new(),
             books: Vec::new(),
             selected: 0,
             library_query: String::new(),
             favorites_only: false,
+            include_archived: false,
+            status_filter: None,
+            added_within_days: None,
+            kind_filter: None,
             collection_filter: CollectionFilter::Any,
+            collection_view: false,
             tag_filters: Vec::new(),
             tag_match_mode: TagMatchMode::Or,
+            author_filter: None,
+            series_filter: None,
             progress_by_path: HashMap::new(),
+            reader_state_by_path: HashMap::new(),
+            reading_secs_by_path: HashMap::new(),
+            page_counts_by_path: HashMap::new(),
+            fingerprints_by_path: HashMap::new(),
             opened_at_by_path: HashMap::new(),
+            recent_paths: Vec::new(),
+            added_at_by_path: HashMap::new(),
             labels_by_path: HashMap::new(),
+            collection_positions_by_path: HashMap::new(),
             bookmarks_by_path: HashMap::new(),
             notes_by_path: HashMap::new(),
             dirty_favorite_paths: HashSet::new(),
+            dirty_status_paths: HashSet::new(),
+            dirty_archived_paths: HashSet::new(),
+            dirty_metadata_paths: HashSet::new(),
+            dirty_progress_paths: HashSet::new(),
+            dirty_reader_state_paths: HashSet::new(),
+            dirty_reading_time_paths: HashSet::new(),
+            dirty_page_count_paths: HashSet::new(),
+            dirty_fingerprint_paths: HashSet::new(),
             dirty_label_paths: HashSet::new(),
+            dirty_collection_position_paths: HashSet::new(),
             known_tags: Vec::new(),
             known_collections: Vec::new(),
+            known_authors: Vec::new(),
+            known_series: Vec::new(),
             dirty_label_catalog_ops: Vec::new(),
             dirty_bookmark_paths: HashSet::new(),
             dirty_note_paths: HashSet::new(),
+            dirty_book_path_ops: Vec::new(),
+            db_generation: 0,
+            keep_stored_settings: false,
+            session_base: SessionBase::default(),
         }
     }
 
@@ -86,19 +295,311 @@ impl AppContext {
         self.cwd = cwd;
         self.books = books;
         self.selected = self.selected.min(self.books.len().saturating_sub(1));
+
+        let mut opened: Vec<(i64, &str)> = self
+            .books
+            .iter()
+            .filter_map(|b| Some((b.last_opened?, b.path.as_str())))
+            .collect();
+        opened.sort_by(|a, b| b.cmp(a));
+        self.recent_paths = opened
+            .into_iter()
+            .take(RECENT_BOOKS_LIMIT)
+            .map(|(_, path)| path.to_string())
+            .collect();
         self
     }
 
-    pub fn with_progress(mut self, progress_by_path: HashMap<String, u32>) -> Self {
+    /// Stamps `last_opened` (persisted on the next save) and moves the book to the front of
+    /// the history.
+    pub fn record_book_opened(&mut self, path: &str, opened_at: i64) {
+        if let Some(book) = self.books.iter_mut().find(|b| b.path == path) {
+            book.last_opened = Some(opened_at);
+        }
+        self.opened_at_by_path.insert(path.to_string(), opened_at);
+        self.recent_paths.retain(|p| p != path);
+        self.recent_paths.insert(0, path.to_string());
+        self.recent_paths.truncate(RECENT_BOOKS_LIMIT);
+    }
+
+    /// Sets a book's reading status (persisted on exit). Returns `false` when nothing changed.
+    pub fn set_book_status(&mut self, path: &str, status: ReadingStatus) -> bool {
+        let Some(book) = self.books.iter_mut().find(|b| b.path == path) else {
+            return false;
+        };
+        if book.status == status {
+            return false;
+        }
+        book.status = status;
+        self.dirty_status_paths.insert(path.to_string());
+        true
+    }
+
+    /// Archives or restores a book (persisted on exit). Returns `false` when nothing changed.
+    pub fn set_book_archived(&mut self, path: &str, archived: bool) -> bool {
+        let Some(book) = self.books.iter_mut().find(|b| b.path == path) else {
+            return false;
+        };
+        if book.archived == archived {
+            return false;
+        }
+        book.archived = archived;
+        self.dirty_archived_paths.insert(path.to_string());
+        true
+    }
+
+    /// Whether a book belongs in the library list given the archive filter.
+    pub fn is_listed(&self, book: &Book) -> bool {
+        self.include_archived || !book.archived
+    }
+
+    /// Takes title and author from freshly read document metadata. Returns `false` when
+    /// nothing changed; without a usable metadata title the current title stays.
+    pub fn refresh_book_metadata(&mut self, path: &str, metadata: &DocumentMetadata) -> bool {
+        let Some(book) = self.books.iter_mut().find(|b| b.path == path) else {
+            return false;
+        };
+        let before = (book.title.clone(), book.author.clone());
+        book.apply_metadata(metadata);
+        if (&book.title, &book.author) == (&before.0, &before.1) {
+            return false;
+        }
+        self.dirty_metadata_paths.insert(path.to_string());
+        true
+    }
+
+    pub fn add_reading_time(&mut self, path: &str, secs: u64) {
+        if secs == 0 {
+            return;
+        }
+        let total = self
+            .reading_secs_by_path
+            .entry(path.to_string())
+            .or_default();
+        *total = total.saturating_add(secs);
+        self.dirty_reading_time_paths.insert(path.to_string());
+    }
+
+    /// Remembers a freshly counted page count (persisted on exit).
+    pub fn set_page_count(&mut self, path: &str, count: PageCount) {
+        if self.page_counts_by_path.get(path) == Some(&count) {
+            return;
+        }
+        self.page_counts_by_path.insert(path.to_string(), count);
+        self.dirty_page_count_paths.insert(path.to_string());
+    }
+
+    pub fn with_progress(mut self, progress_by_path: HashMap<String, BookProgress>) -> Self {
         self.progress_by_path = progress_by_path;
         self
     }
 
+    pub fn with_reader_states(
+        mut self,
+        reader_state_by_path: HashMap<String, ReaderViewState>,
+    ) -> Self {
+        self.reader_state_by_path = reader_state_by_path;
+        self
+    }
+
+    pub fn with_reading_time(mut self, reading_secs_by_path: HashMap<String, u64>) -> Self {
+        self.reading_secs_by_path = reading_secs_by_path;
+        self
+    }
+
+    pub fn with_page_counts(mut self, page_counts_by_path: HashMap<String, PageCount>) -> Self {
+        self.page_counts_by_path = page_counts_by_path;
+        self
+    }
+
+    pub fn with_fingerprints(
+        mut self,
+        fingerprints_by_path: HashMap<String, FileFingerprint>,
+    ) -> Self {
+        self.fingerprints_by_path = fingerprints_by_path;
+        self
+    }
+
+    pub fn set_fingerprint(&mut self, path: &str, fingerprint: FileFingerprint) {
+        if self.fingerprints_by_path.get(path) == Some(&fingerprint) {
+            return;
+        }
+        self.fingerprints_by_path
+            .insert(path.to_string(), fingerprint);
+        self.dirty_fingerprint_paths.insert(path.to_string());
+    }
+
+    pub fn with_added_at(mut self, added_at_by_path: HashMap<String, i64>) -> Self {
+        self.added_at_by_path = added_at_by_path;
+        self
+    }
+
+    /// Notes when a book found this session joined the library; storage sets its own time
+    /// when the book is saved.
+    pub fn record_book_added(&mut self, path: &str, now: i64) {
+        self.added_at_by_path.entry(path.to_string()).or_insert(now);
+    }
+
+    /// A library scan walked every root; the books added since the scan before it are new.
+    pub fn record_complete_scan(&mut self, now: i64) {
+        self.new_since = self.settings.last_scan_at;
+        self.settings.last_scan_at = Some(now);
+    }
+
+    /// Added after `new_since`. Nothing is new before the first complete scan.
+    pub fn is_new(&self, path: &str) -> bool {
+        self.new_since.is_some_and(|since| {
+            self.added_at_by_path
+                .get(path)
+                .is_some_and(|added| *added > since)
+        })
+    }
+
+    /// Whether a book passes the recently-added filter at `now`.
+    pub fn added_within_filter(&self, path: &str, now: i64) -> bool {
+        self.added_within_days.is_none_or(|days| {
+            self.added_at_by_path
+                .get(path)
+                .is_some_and(|added| now.saturating_sub(*added) <= i64::from(days) * 24 * 60 * 60)
+        })
+    }
+
+    /// What the book's file holds, once it has been looked at.
+    pub fn book_kind(&self, path: &str) -> Option<BookKind> {
+        self.page_counts_by_path
+            .get(path)
+            .and_then(|count| count.kind)
+    }
+
+    /// Whether a book passes the kind filter.
+    pub fn kind_filter_matches(&self, path: &str) -> bool {
+        self.kind_filter
+            .is_none_or(|kind| self.book_kind(path) == Some(kind))
+    }
+
     pub fn with_labels(mut self, labels_by_path: HashMap<String, BookLabels>) -> Self {
+        self.session_base.labels_by_path = labels_by_path.clone();
         self.labels_by_path = labels_by_path;
         self
     }
 
+    pub fn with_collection_positions(mut self, positions: HashMap<String, u32>) -> Self {
+        self.collection_positions_by_path = positions;
+        self
+    }
+
+    /// Replaces a book's labels (persisted on exit). A book that changes collection loses its
+    /// place in the old collection's reading list.
+    pub fn set_book_labels(&mut self, path: &str, mut labels: BookLabels) {
+        labels.normalize();
+        let old_collection = self
+            .labels_by_path
+            .get(path)
+            .and_then(|labels| labels.collection.as_deref());
+        let same_collection = match (old_collection, labels.collection.as_deref()) {
+            (Some(old), Some(new)) => old.eq_ignore_ascii_case(new),
+            (old, new) => old == new,
+        };
+        if !same_collection && self.collection_positions_by_path.remove(path).is_some() {
+            self.dirty_collection_position_paths
+                .insert(path.to_string());
+        }
+        if labels.is_empty() {
+            self.labels_by_path.remove(path);
+        } else {
+            self.labels_by_path.insert(path.to_string(), labels);
+        }
+        self.dirty_label_paths.insert(path.to_string());
+    }
+
+    /// Reading-list order: books with a stored place by that place, then the rest by title.
+    pub fn cmp_collection_order(&self, a: &Book, b: &Book) -> Ordering {
+        let position = |book: &Book| self.collection_positions_by_path.get(&book.path).copied();
+        let by_position = match (position(a), position(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        by_position.then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+    }
+
+    /// Series order: numbered books by number, then the rest by title.
+    pub fn cmp_series_order(&self, a: &Book, b: &Book) -> Ordering {
+        let number = |book: &Book| {
+            self.labels_by_path
+                .get(&book.path)
+                .and_then(BookLabels::series_number)
+        };
+        let by_number = match (number(a), number(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        by_number.then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+    }
+
+    /// Indices of the books in `collection`, in reading-list order.
+    pub fn collection_reading_order(&self, collection: &str) -> Vec<usize> {
+        let mut indices: Vec<usize> = self
+            .books
+            .iter()
+            .enumerate()
+            .filter(|(_, book)| {
+                self.labels_by_path
+                    .get(&book.path)
+                    .and_then(|labels| labels.collection.as_deref())
+                    .is_some_and(|name| name.eq_ignore_ascii_case(collection))
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        indices.sort_by(|&a, &b| self.cmp_collection_order(&self.books[a], &self.books[b]));
+        indices
+    }
+
+    /// Moves a book one place earlier or later in its collection's reading list; the whole list
+    /// is numbered so its order is kept (persisted on exit). Returns `false` when the book is in
+    /// no collection or already at that end.
+    pub fn move_in_collection(&mut self, path: &str, earlier: bool) -> bool {
+        let Some(collection) = self
+            .labels_by_path
+            .get(path)
+            .and_then(|labels| labels.collection.clone())
+        else {
+            return false;
+        };
+        let mut order: Vec<String> = self
+            .collection_reading_order(&collection)
+            .into_iter()
+            .map(|idx| self.books[idx].path.clone())
+            .collect();
+        let Some(from) = order.iter().position(|p| p == path) else {
+            return false;
+        };
+        let to = if earlier {
+            from.checked_sub(1)
+        } else {
+            Some(from + 1).filter(|&to| to < order.len())
+        };
+        let Some(to) = to else {
+            return false;
+        };
+        order.swap(from, to);
+
+        for (position, path) in order.into_iter().enumerate() {
+            let position = u32::try_from(position).unwrap_or(u32::MAX);
+            if self
+                .collection_positions_by_path
+                .insert(path.clone(), position)
+                != Some(position)
+            {
+                self.dirty_collection_position_paths.insert(path);
+            }
+        }
+        true
+    }
+
     pub fn with_label_catalog(mut self, tags: Vec<String>, collections: Vec<String>) -> Self {
         self.known_tags = tags;
         self.known_collections = collections;
@@ -106,32 +607,89 @@ impl AppContext {
         self
     }
 
+    /// Sets the catalog of one label kind, e.g. the known authors.
+    pub fn with_known_labels(mut self, kind: TagKind, names: Vec<String>) -> Self {
+        *self.known_labels_mut(kind) = names;
+        self.normalize_label_catalog();
+        self
+    }
+
+    /// Restores saved library filters. Call after the label catalog is set: labels missing
+    /// from it are dropped.
+    pub fn with_library_filters(mut self, mut filters: LibraryFilters) -> Self {
+        filters.retain_known(|kind| self.known_labels(kind));
+        self.library_query = filters.query;
+        self.favorites_only = filters.favorites_only;
+        self.collection_filter = filters.collection;
+        self.tag_filters = filters.tags;
+        self.tag_match_mode = filters.tag_match_mode;
+        self.author_filter = filters.author;
+        self.series_filter = filters.series;
+        self
+    }
+
+    /// The library filters to keep for the next session.
+    pub fn library_filters(&self) -> LibraryFilters {
+        LibraryFilters {
+            query: self.library_query.clone(),
+            favorites_only: self.favorites_only,
+            collection: self.collection_filter.clone(),
+            tags: self.tag_filters.clone(),
+            tag_match_mode: self.tag_match_mode,
+            author: self.author_filter.clone(),
+            series: self.series_filter.clone(),
+        }
+    }
+
+    /// The catalog of one label kind.
+    pub fn known_labels(&self, kind: TagKind) -> &Vec<String> {
+        match kind {
+            TagKind::Tag => &self.known_tags,
+            TagKind::Collection => &self.known_collections,
+            TagKind::Author => &self.known_authors,
+            TagKind::Series => &self.known_series,
+        }
+    }
+
+    pub fn known_labels_mut(&mut self, kind: TagKind) -> &mut Vec<String> {
+        match kind {
+            TagKind::Tag => &mut self.known_tags,
+            TagKind::Collection => &mut self.known_collections,
+            TagKind::Author => &mut self.known_authors,
+            TagKind::Series => &mut self.known_series,
+        }
+    }
+
     pub fn normalize_label_catalog(&mut self) {
-        normalize_label_names(&mut self.known_tags);
-        normalize_label_names(&mut self.known_collections);
+        for kind in TagKind::ALL {
+            normalize_label_names(self.known_labels_mut(kind));
+        }
     }
 
     pub fn ensure_known_labels(&mut self, labels: &BookLabels) {
+        let mut names: Vec<(TagKind, &str)> = labels
+            .tags
+            .iter()
+            .map(|tag| (TagKind::Tag, tag.as_str()))
+            .collect();
+        names.extend(
+            [
+                (TagKind::Collection, labels.collection.as_deref()),
+                (TagKind::Author, labels.author.as_deref()),
+                (TagKind::Series, labels.series_name()),
+            ]
+            .into_iter()
+            .filter_map(|(kind, name)| Some((kind, name?))),
+        );
         let mut changed = false;
-        for tag in &labels.tags {
-            let name = tag.trim();
+        for (kind, name) in names {
+            let name = name.trim();
             if name.is_empty() {
                 continue;
             }
-            if !self.known_tags.iter().any(|t| t.eq_ignore_ascii_case(name)) {
-                self.known_tags.push(name.to_string());
-                changed = true;
-            }
-        }
-        if let Some(collection) = labels.collection.as_deref() {
-            let name = collection.trim();
-            if !name.is_empty()
-                && !self
-                    .known_collections
-                    .iter()
-                    .any(|c| c.eq_ignore_ascii_case(name))
-            {
-                self.known_collections.push(name.to_string());
+            let known = self.known_labels_mut(kind);
+            if !known.iter().any(|k| k.eq_ignore_ascii_case(name)) {
+                known.push(name.to_string());
                 changed = true;
             }
         }
@@ -141,14 +699,602 @@ impl AppContext {
     }
 
     pub fn with_bookmarks(mut self, bookmarks_by_path: HashMap<String, Vec<Bookmark>>) -> Self {
+        self.session_base.bookmarks_by_path = bookmarks_by_path.clone();
         self.bookmarks_by_path = bookmarks_by_path;
         self
     }
 
     pub fn with_notes(mut self, notes_by_path: HashMap<String, Vec<Note>>) -> Self {
+        self.session_base.notes_by_path = notes_by_path.clone();
         self.notes_by_path = notes_by_path;
         self
     }
+
+    pub fn with_db_generation(mut self, generation: i64) -> Self {
+        self.db_generation = generation;
+        self
+    }
+
+    /// Merges what another instance saved since this session loaded into the labels,
+    /// bookmarks and notes about to be written, so neither side's additions or removals are
+    /// lost. Books this session did not change are not written and keep the stored data.
+    pub fn merge_stored(&mut self, stored: &StoredBookData) {
+        for path in &self.dirty_label_paths {
+            let base = self.session_base.labels_by_path.get(path);
+            let theirs = stored.labels_by_path.get(path);
+            let ours = self.labels_by_path.get(path);
+            let merged = merge::merge_labels(
+                base.unwrap_or(&BookLabels::default()),
+                ours.unwrap_or(&BookLabels::default()),
+                theirs.unwrap_or(&BookLabels::default()),
+            );
+            if merged.is_empty() {
+                self.labels_by_path.remove(path);
+            } else {
+                self.labels_by_path.insert(path.clone(), merged);
+            }
+        }
+        for path in &self.dirty_bookmark_paths {
+            let merged = merge::merge_entries(
+                self.session_base
+                    .bookmarks_by_path
+                    .get(path)
+                    .map_or(&[], Vec::as_slice),
+                self.bookmarks_by_path.get(path).map_or(&[], Vec::as_slice),
+                stored
+                    .bookmarks_by_path
+                    .get(path)
+                    .map_or(&[], Vec::as_slice),
+                |a, b| a == b,
+            );
+            self.bookmarks_by_path.insert(path.clone(), merged);
+        }
+        for path in &self.dirty_note_paths {
+            let merged = merge::merge_entries(
+                self.session_base
+                    .notes_by_path
+                    .get(path)
+                    .map_or(&[], Vec::as_slice),
+                self.notes_by_path.get(path).map_or(&[], Vec::as_slice),
+                stored.notes_by_path.get(path).map_or(&[], Vec::as_slice),
+                |a, b| a == b,
+            );
+            self.notes_by_path.insert(path.clone(), merged);
+        }
+    }
+
+    /// Records a save at `generation`: later merges start from what was just written.
+    pub fn mark_saved(&mut self, generation: i64) {
+        self.db_generation = generation;
+        self.session_base = SessionBase {
+            labels_by_path: self.labels_by_path.clone(),
+            bookmarks_by_path: self.bookmarks_by_path.clone(),
+            notes_by_path: self.notes_by_path.clone(),
+        };
+    }
+
+    /// Whether anything is left to write: changed books, relinks and removals, or label
+    /// catalog edits.
+    pub fn has_unsaved_changes(&self) -> bool {
+        !self.dirty_book_path_ops.is_empty()
+            || !self.dirty_label_catalog_ops.is_empty()
+            || !self.opened_at_by_path.is_empty()
+            || [
+                &self.dirty_bookmark_paths,
+                &self.dirty_note_paths,
+                &self.dirty_label_paths,
+                &self.dirty_progress_paths,
+                &self.dirty_reader_state_paths,
+                &self.dirty_reading_time_paths,
+                &self.dirty_favorite_paths,
+                &self.dirty_status_paths,
+                &self.dirty_archived_paths,
+                &self.dirty_collection_position_paths,
+                &self.dirty_metadata_paths,
+                &self.dirty_page_count_paths,
+                &self.dirty_fingerprint_paths,
+            ]
+            .iter()
+            .any(|dirty| !dirty.is_empty())
+    }
+
+    /// Whether the user changed something that is not saved yet: labels, notes, bookmarks,
+    /// favorites, statuses, archiving, collection order, relinks and removals, or label catalog
+    /// edits. Progress, reading time and what scans found are saved too but do not count.
+    pub fn has_unsaved_edits(&self) -> bool {
+        !self.dirty_book_path_ops.is_empty()
+            || !self.dirty_label_catalog_ops.is_empty()
+            || [
+                &self.dirty_bookmark_paths,
+                &self.dirty_note_paths,
+                &self.dirty_label_paths,
+                &self.dirty_favorite_paths,
+                &self.dirty_status_paths,
+                &self.dirty_archived_paths,
+                &self.dirty_collection_position_paths,
+            ]
+            .iter()
+            .any(|dirty| !dirty.is_empty())
+    }
+
+    /// Takes up to `limit` book changes out of the dirty sets, bookmarks, notes, labels and
+    /// progress first. What is left stays dirty for the next batch.
+    pub fn take_dirty_batch(&mut self, limit: usize) -> DirtyBatch {
+        let mut budget = limit;
+        let mut batch = DirtyBatch::default();
+        for (dirty, taken) in self.dirty_sets_with(&mut batch) {
+            *taken = take_paths(dirty, &mut budget);
+        }
+        let opened: Vec<String> = self
+            .opened_at_by_path
+            .keys()
+            .take(budget)
+            .cloned()
+            .collect();
+        for path in opened {
+            if let Some(opened_at) = self.opened_at_by_path.remove(&path) {
+                batch.opened_at.push((path, opened_at));
+            }
+        }
+        batch
+    }
+
+    /// Puts back a batch that failed to save, so the next save writes it again.
+    pub fn restore_dirty_batch(&mut self, mut batch: DirtyBatch) {
+        for (path, opened_at) in std::mem::take(&mut batch.opened_at) {
+            // An open recorded since the batch was taken is newer.
+            self.opened_at_by_path.entry(path).or_insert(opened_at);
+        }
+        for (dirty, taken) in self.dirty_sets_with(&mut batch) {
+            dirty.extend(taken.drain(..));
+        }
+    }
+
+    /// Records that `batch` was written: later merges of its books start from what was
+    /// written. Books still dirty keep their old base.
+    pub fn mark_batch_saved(&mut self, batch: &DirtyBatch) {
+        for path in &batch.label_paths {
+            match self.labels_by_path.get(path) {
+                Some(labels) => {
+                    self.session_base
+                        .labels_by_path
+                        .insert(path.clone(), labels.clone());
+                }
+                None => {
+                    self.session_base.labels_by_path.remove(path);
+                }
+            }
+        }
+        for path in &batch.bookmark_paths {
+            let bookmarks = self
+                .bookmarks_by_path
+                .get(path)
+                .cloned()
+                .unwrap_or_default();
+            self.session_base
+                .bookmarks_by_path
+                .insert(path.clone(), bookmarks);
+        }
+        for path in &batch.note_paths {
+            let notes = self.notes_by_path.get(path).cloned().unwrap_or_default();
+            self.session_base.notes_by_path.insert(path.clone(), notes);
+        }
+    }
+
+    /// Each dirty set beside the list of `batch` it is taken into, in the order batches fill.
+    fn dirty_sets_with<'a>(
+        &'a mut self,
+        batch: &'a mut DirtyBatch,
+    ) -> [(&'a mut HashSet<String>, &'a mut Vec<String>); 13] {
+        [
+            (&mut self.dirty_bookmark_paths, &mut batch.bookmark_paths),
+            (&mut self.dirty_note_paths, &mut batch.note_paths),
+            (&mut self.dirty_label_paths, &mut batch.label_paths),
+            (&mut self.dirty_progress_paths, &mut batch.progress_paths),
+            (
+                &mut self.dirty_reader_state_paths,
+                &mut batch.reader_state_paths,
+            ),
+            (
+                &mut self.dirty_reading_time_paths,
+                &mut batch.reading_time_paths,
+            ),
+            (&mut self.dirty_favorite_paths, &mut batch.favorite_paths),
+            (&mut self.dirty_status_paths, &mut batch.status_paths),
+            (&mut self.dirty_archived_paths, &mut batch.archived_paths),
+            (
+                &mut self.dirty_collection_position_paths,
+                &mut batch.collection_position_paths,
+            ),
+            (&mut self.dirty_metadata_paths, &mut batch.metadata_paths),
+            (
+                &mut self.dirty_page_count_paths,
+                &mut batch.page_count_paths,
+            ),
+            (
+                &mut self.dirty_fingerprint_paths,
+                &mut batch.fingerprint_paths,
+            ),
+        ]
+    }
+
+    /// Points a book at a new file, moving its per-book data and pending changes along.
+    /// A different book already at `to` is dropped.
+    pub fn relink_book(&mut self, from: &str, to: &str, title: String) {
+        if from == to || !self.books.iter().any(|b| b.path == from) {
+            return;
+        }
+        let selected_path = self.books.get(self.selected).map(|b| b.path.clone());
+        if self.books.iter().any(|b| b.path == to) {
+            self.forget_book(to);
+        }
+        if let Some(book) = self.books.iter_mut().find(|b| b.path == from) {
+            book.path = to.to_string();
+            book.title = title.clone();
+        }
+
+        move_path_key(&mut self.progress_by_path, from, to);
+        move_path_key(&mut self.reader_state_by_path, from, to);
+        move_path_key(&mut self.reading_secs_by_path, from, to);
+        // The new file is counted afresh; storage drops the old count on relink too.
+        self.page_counts_by_path.remove(from);
+        self.page_counts_by_path.remove(to);
+        self.dirty_page_count_paths.remove(from);
+        self.dirty_page_count_paths.remove(to);
+        self.fingerprints_by_path.remove(from);
+        self.fingerprints_by_path.remove(to);
+        self.dirty_fingerprint_paths.remove(from);
+        self.dirty_fingerprint_paths.remove(to);
+        move_path_key(&mut self.opened_at_by_path, from, to);
+        move_path_key(&mut self.added_at_by_path, from, to);
+        move_path_key(&mut self.labels_by_path, from, to);
+        move_path_key(&mut self.collection_positions_by_path, from, to);
+        move_path_key(&mut self.bookmarks_by_path, from, to);
+        move_path_key(&mut self.notes_by_path, from, to);
+        self.recent_paths.retain(|p| p != to);
+        for path in &mut self.recent_paths {
+            if path == from {
+                *path = to.to_string();
+            }
+        }
+        for dirty in [
+            &mut self.dirty_favorite_paths,
+            &mut self.dirty_status_paths,
+            &mut self.dirty_archived_paths,
+            &mut self.dirty_metadata_paths,
+            &mut self.dirty_progress_paths,
+            &mut self.dirty_reader_state_paths,
+            &mut self.dirty_reading_time_paths,
+            &mut self.dirty_label_paths,
+            &mut self.dirty_collection_position_paths,
+            &mut self.dirty_bookmark_paths,
+            &mut self.dirty_note_paths,
+        ] {
+            dirty.remove(to);
+            if dirty.remove(from) {
+                dirty.insert(to.to_string());
+            }
+        }
+
+        let selected_path = selected_path.map(|p| if p == from { to.to_string() } else { p });
+        self.reselect(selected_path);
+        self.dirty_book_path_ops.push(BookPathOp::Relink {
+            from: from.to_string(),
+            to: to.to_string(),
+            title,
+        });
+    }
+
+    /// Relinks `missing` books to `found` files that have the same size and edge hashes. A
+    /// found file with progress, bookmarks, notes or labels of its own is left alone. The book
+    /// keeps its title.
+    pub fn relink_moved_books(&mut self, missing: &[String], found: &[String]) -> Vec<MovedBook> {
+        let found: Vec<String> = found
+            .iter()
+            .filter(|path| self.carried_data(path).is_empty())
+            .cloned()
+            .collect();
+        let pairs = moved::moved_books(missing, &found, &self.fingerprints_by_path);
+        let mut moved = Vec::new();
+        for (from, to) in pairs {
+            let Some(title) = self
+                .books
+                .iter()
+                .find(|b| b.path == from)
+                .map(|b| b.title.clone())
+            else {
+                continue;
+            };
+            let fingerprint = self.fingerprints_by_path.get(&to).cloned();
+            let carried = self.carried_data(&from);
+            self.relink_book(&from, &to, title.clone());
+            // Same file, so its fingerprint still holds after the relink.
+            if let Some(fingerprint) = fingerprint {
+                self.set_fingerprint(&to, fingerprint);
+            }
+            moved.push(MovedBook {
+                title,
+                from,
+                to,
+                carried,
+            });
+        }
+        moved
+    }
+
+    /// The reader's data kept for `path`, in words.
+    fn carried_data(&self, path: &str) -> Vec<String> {
+        let count = |n: usize, what: &str| match n {
+            0 => None,
+            1 => Some(format!("1 {what}")),
+            n => Some(format!("{n} {what}s")),
+        };
+        let mut carried = Vec::new();
+        if self.progress_by_path.contains_key(path) {
+            carried.push("progress".to_string());
+        }
+        carried.extend(count(
+            self.bookmarks_by_path.get(path).map_or(0, Vec::len),
+            "bookmark",
+        ));
+        carried.extend(count(
+            self.notes_by_path.get(path).map_or(0, Vec::len),
+            "note",
+        ));
+        if self
+            .labels_by_path
+            .get(path)
+            .is_some_and(|labels| !labels.is_empty())
+        {
+            carried.push("labels".to_string());
+        }
+        carried
+    }
+
+    /// Drops a book and everything keyed by its path.
+    pub fn remove_book(&mut self, path: &str) {
+        if !self.books.iter().any(|b| b.path == path) {
+            return;
+        }
+        let selected_path = self.books.get(self.selected).map(|b| b.path.clone());
+        self.forget_book(path);
+        self.reselect(selected_path);
+        self.dirty_book_path_ops.push(BookPathOp::Remove {
+            path: path.to_string(),
+        });
+    }
+
+    /// Starts looking for books whose files have the same content, on a background thread.
+    /// Only files that are new or have changed since the last pass are hashed; the
+    /// fingerprints the pass sends back go through `set_fingerprint` (persisted on exit).
+    pub fn start_duplicate_scan(&self, wake: Option<Wake>) -> DuplicateScan {
+        let paths: Vec<String> = self.books.iter().map(|b| b.path.clone()).collect();
+        DuplicateScan::start(paths, self.fingerprints_by_path.clone(), wake)
+    }
+
+    /// Folds copies of one book into `keep`: the furthest progress, every bookmark and note,
+    /// the union of tags, and reading time all move to it, and it becomes a favorite if any
+    /// copy was. The copies are then archived or removed (persisted on exit). Returns how many
+    /// copies were merged.
+    pub fn merge_duplicates(
+        &mut self,
+        keep: &str,
+        others: &[String],
+        disposal: DuplicateDisposal,
+    ) -> usize {
+        if !self.books.iter().any(|b| b.path == keep) {
+            return 0;
+        }
+        let others: Vec<String> = others
+            .iter()
+            .filter(|path| *path != keep && self.books.iter().any(|b| &b.path == *path))
+            .cloned()
+            .collect();
+        for other in &others {
+            if let Some(progress) = self.progress_by_path.get(other).copied()
+                && self
+                    .progress_by_path
+                    .get(keep)
+                    .is_none_or(|kept| kept.last_page < progress.last_page)
+            {
+                self.progress_by_path.insert(keep.to_string(), progress);
+                self.dirty_progress_paths.insert(keep.to_string());
+            }
+            if !self.reader_state_by_path.contains_key(keep)
+                && let Some(state) = self.reader_state_by_path.get(other).copied()
+            {
+                self.reader_state_by_path.insert(keep.to_string(), state);
+                self.dirty_reader_state_paths.insert(keep.to_string());
+            }
+            if let Some(secs) = self.reading_secs_by_path.get(other).copied() {
+                self.add_reading_time(keep, secs);
+                self.reading_secs_by_path.insert(other.clone(), 0);
+                self.dirty_reading_time_paths.insert(other.clone());
+            }
+
+            if let Some(bookmarks) = self.bookmarks_by_path.remove(other) {
+                let kept = self.bookmarks_by_path.entry(keep.to_string()).or_default();
+                for bookmark in bookmarks {
+                    if !kept.iter().any(|b| b.page == bookmark.page) {
+                        kept.push(bookmark);
+                    }
+                }
+                kept.sort_by_key(|b| b.page);
+                self.dirty_bookmark_paths.insert(keep.to_string());
+                self.dirty_bookmark_paths.insert(other.clone());
+            }
+            if let Some(notes) = self.notes_by_path.remove(other) {
+                let kept = self.notes_by_path.entry(keep.to_string()).or_default();
+                for note in notes {
+                    if !kept.contains(&note) {
+                        kept.push(note);
+                    }
+                }
+                kept.sort_by_key(|n| (n.page, n.body.clone()));
+                self.dirty_note_paths.insert(keep.to_string());
+                self.dirty_note_paths.insert(other.clone());
+            }
+            if let Some(labels) = self.labels_by_path.get(other).cloned() {
+                let mut merged = self.labels_by_path.get(keep).cloned().unwrap_or_default();
+                merged.tags.extend(labels.tags);
+                if merged.collection.is_none() {
+                    merged.collection = labels.collection;
+                }
+                if merged.author.is_none() {
+                    merged.author = labels.author;
+                }
+                if merged.series.is_none() {
+                    merged.series = labels.series;
+                }
+                self.set_book_labels(keep, merged);
+                self.set_book_labels(other, BookLabels::default());
+            }
+
+            let Some(copy) = self.books.iter().find(|b| &b.path == other).cloned() else {
+                continue;
+            };
+            if let Some(book) = self.books.iter_mut().find(|b| b.path == keep)
+                && copy.favorite
+                && !book.favorite
+            {
+                book.favorite = true;
+                self.dirty_favorite_paths.insert(keep.to_string());
+            }
+            let status = self
+                .books
+                .iter()
+                .find(|b| b.path == keep)
+                .map(|b| b.status)
+                .unwrap_or_default();
+            if status_rank(copy.status) > status_rank(status) {
+                self.set_book_status(keep, copy.status);
+            }
+        }
+
+        for other in &others {
+            match disposal {
+                DuplicateDisposal::Archive => {
+                    self.set_book_archived(other, true);
+                }
+                DuplicateDisposal::Remove => self.remove_book(other),
+            }
+        }
+        others.len()
+    }
+
+    /// Stored paths a database cleanup must keep: sources of relinks not yet written.
+    pub fn cleanup_keep_paths(&self) -> Vec<String> {
+        self.dirty_book_path_ops
+            .iter()
+            .filter_map(|op| match op {
+                BookPathOp::Relink { from, .. } => Some(from.clone()),
+                BookPathOp::Remove { .. } => None,
+            })
+            .collect()
+    }
+
+    /// Drops what a database cleanup deleted. Removed labels still held by an unsaved book stay
+    /// in the catalog; saving the book recreates them.
+    pub fn apply_cleanup(&mut self, report: &CleanupReport) {
+        let selected_path = self.books.get(self.selected).map(|b| b.path.clone());
+        for path in &report.removed_books {
+            self.forget_book(path);
+        }
+        self.reselect(selected_path);
+
+        let in_use = |kind: TagKind, name: &str| {
+            self.labels_by_path
+                .values()
+                .any(|labels| labels.carries(kind, name))
+        };
+        let removed: Vec<(TagKind, String)> = report
+            .removed_tags
+            .iter()
+            .filter(|(kind, name)| !in_use(*kind, name))
+            .cloned()
+            .collect();
+        for (kind, name) in removed {
+            self.known_labels_mut(kind)
+                .retain(|n| !n.eq_ignore_ascii_case(&name));
+        }
+    }
+
+    /// Takes in what a finished scan merged into the stored library, and with `prune` removes
+    /// the books it did not find. Books already relinked to a new path are no longer missing,
+    /// so the returned report leaves them out.
+    pub fn apply_scan_merge(&mut self, merge: &ScanMerge, prune: bool) -> ScanReport {
+        for updated in &merge.updated {
+            if let Some(book) = self.books.iter_mut().find(|b| b.path == updated.path) {
+                book.author = updated.author.clone();
+            }
+        }
+        let missing: Vec<&String> = merge
+            .missing
+            .iter()
+            .filter(|path| self.books.iter().any(|b| &b.path == *path))
+            .collect();
+        if prune {
+            for path in &missing {
+                self.remove_book(path);
+            }
+        }
+        ScanReport {
+            missing: missing.len(),
+            ..merge.report()
+        }
+    }
+
+    fn forget_book(&mut self, path: &str) {
+        self.books.retain(|b| b.path != path);
+        self.recent_paths.retain(|p| p != path);
+        self.progress_by_path.remove(path);
+        self.reader_state_by_path.remove(path);
+        self.reading_secs_by_path.remove(path);
+        self.page_counts_by_path.remove(path);
+        self.fingerprints_by_path.remove(path);
+        self.opened_at_by_path.remove(path);
+        self.added_at_by_path.remove(path);
+        self.labels_by_path.remove(path);
+        self.collection_positions_by_path.remove(path);
+        self.bookmarks_by_path.remove(path);
+        self.notes_by_path.remove(path);
+        self.dirty_favorite_paths.remove(path);
+        self.dirty_status_paths.remove(path);
+        self.dirty_archived_paths.remove(path);
+        self.dirty_metadata_paths.remove(path);
+        self.dirty_progress_paths.remove(path);
+        self.dirty_reader_state_paths.remove(path);
+        self.dirty_reading_time_paths.remove(path);
+        self.dirty_page_count_paths.remove(path);
+        self.dirty_fingerprint_paths.remove(path);
+        self.dirty_label_paths.remove(path);
+        self.dirty_collection_position_paths.remove(path);
+        self.dirty_bookmark_paths.remove(path);
+        self.dirty_note_paths.remove(path);
+    }
+
+    fn reselect(&mut self, path: Option<String>) {
+        if let Some(idx) = path.and_then(|p| self.books.iter().position(|b| b.path == p)) {
+            self.selected = idx;
+        }
+        self.selected = self.selected.min(self.books.len().saturating_sub(1));
+    }
+}
+
+/// How far along a status is; a merge keeps the furthest.
+fn status_rank(status: ReadingStatus) -> u8 {
+    match status {
+        ReadingStatus::Unread => 0,
+        ReadingStatus::Reading => 1,
+        ReadingStatus::Finished => 2,
+    }
+}
+
+fn move_path_key<V>(map: &mut HashMap<String, V>, from: &str, to: &str) {
+    map.remove(to);
+    if let Some(value) = map.remove(from) {
+        map.insert(to.to_string(), value);
+    }
 }
 
 fn normalize_label_names(values: &mut Vec<String>) {
@@ -178,6 +1324,19 @@ pub enum LabelCatalogOp {
     },
 }
 
+/// Library rows to move or drop in storage, applied before other per-path changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BookPathOp {
+    Relink {
+        from: String,
+        to: String,
+        title: String,
+    },
+    Remove {
+        path: String,
+    },
+}
+
 #[derive(Debug, Default)]
 pub struct ProgressTracker;
 
@@ -187,6 +1346,20 @@ impl ProgressTracker {
     }
 }
 
+/// Takes up to `budget` paths out of `dirty`, counting them off the budget.
+fn take_paths(dirty: &mut HashSet<String>, budget: &mut usize) -> Vec<String> {
+    if dirty.len() <= *budget {
+        *budget -= dirty.len();
+        return std::mem::take(dirty).into_iter().collect();
+    }
+    let taken: Vec<String> = dirty.iter().take(*budget).cloned().collect();
+    for path in &taken {
+        dirty.remove(path);
+    }
+    *budget = 0;
+    taken
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -200,4 +1373,565 @@ mod tests {
         };
         assert_eq!(tracker.percent(&progress), 25.0);
     }
+
+    fn book(path: &str) -> Book {
+        Book {
+            path: path.to_string(),
+            title: path.trim_start_matches('/').to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        }
+    }
+
+    #[test]
+    fn new_badges_and_added_filter_follow_scans() {
+        const DAY: i64 = 24 * 60 * 60;
+        let settings = Settings {
+            last_scan_at: Some(100 * DAY),
+            ..Settings::default()
+        };
+        let mut ctx = AppContext::new(settings)
+            .with_library(String::new(), vec![book("/old"), book("/synced")])
+            .with_added_at(HashMap::from([
+                ("/old".to_string(), 10 * DAY),
+                ("/synced".to_string(), 101 * DAY),
+            ]));
+        assert!(!ctx.is_new("/old"));
+        assert!(ctx.is_new("/synced"));
+
+        ctx.record_book_added("/scanned", 105 * DAY);
+        ctx.record_book_added("/old", 105 * DAY);
+        ctx.record_complete_scan(105 * DAY);
+        assert_eq!(ctx.settings.last_scan_at, Some(105 * DAY));
+        assert!(ctx.is_new("/scanned") && ctx.is_new("/synced"));
+        assert!(!ctx.is_new("/old"));
+        ctx.record_complete_scan(106 * DAY);
+        assert!(!ctx.is_new("/scanned"));
+
+        let now = 106 * DAY;
+        assert!(ctx.added_within_filter("/old", now));
+        ctx.added_within_days = Some(7);
+        assert!(ctx.added_within_filter("/synced", now));
+        assert!(!ctx.added_within_filter("/old", now));
+        assert!(!ctx.added_within_filter("/unknown", now));
+        ctx.added_within_days = Some(1);
+        assert!(ctx.added_within_filter("/scanned", now));
+        assert!(!ctx.added_within_filter("/synced", now));
+    }
+
+    #[test]
+    fn kind_filter_lists_books_found_to_be_that_kind() {
+        let mut ctx = AppContext::new(Settings::default());
+        let count = |kind| PageCount {
+            pages: 1,
+            size_bytes: 1,
+            modified_secs: 1,
+            kind,
+        };
+        ctx.set_page_count("/scan", count(Some(BookKind::ScannedPdf)));
+        ctx.set_page_count("/text", count(Some(BookKind::TextPdf)));
+        ctx.set_page_count("/old", count(None));
+        assert!(
+            ["/scan", "/text", "/old", "/new"]
+                .iter()
+                .all(|path| ctx.kind_filter_matches(path))
+        );
+
+        ctx.kind_filter = Some(BookKind::ScannedPdf);
+        assert!(ctx.kind_filter_matches("/scan"));
+        assert!(!ctx.kind_filter_matches("/text"));
+        assert!(!ctx.kind_filter_matches("/old"));
+        assert!(!ctx.kind_filter_matches("/new"));
+    }
+
+    #[test]
+    fn dirty_batches_take_annotations_first_and_restore_on_failure() {
+        let note = |body: &str| Note {
+            page: 1,
+            body: body.to_string(),
+        };
+        let mut ctx = AppContext::new(Settings::default())
+            .with_library(String::new(), vec![book("/a"), book("/b")])
+            .with_notes(HashMap::from([("/a".to_string(), vec![note("old")])]));
+        ctx.notes_by_path
+            .insert("/a".to_string(), vec![note("old"), note("new")]);
+        ctx.dirty_note_paths.insert("/a".to_string());
+        ctx.dirty_favorite_paths.insert("/b".to_string());
+        ctx.dirty_progress_paths.insert("/b".to_string());
+        ctx.record_book_opened("/b", 7);
+        assert!(ctx.has_unsaved_changes());
+        assert!(ctx.has_unsaved_edits());
+
+        let batch = ctx.take_dirty_batch(2);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.note_paths, vec!["/a"]);
+        assert_eq!(batch.progress_paths, vec!["/b"]);
+        assert!(ctx.dirty_note_paths.is_empty());
+        assert!(ctx.dirty_favorite_paths.contains("/b"));
+
+        ctx.restore_dirty_batch(batch);
+        assert!(ctx.dirty_note_paths.contains("/a"));
+        assert!(ctx.dirty_progress_paths.contains("/b"));
+
+        let batch = ctx.take_dirty_batch(usize::MAX);
+        assert_eq!(batch.len(), 4);
+        assert_eq!(batch.opened_at, vec![("/b".to_string(), 7)]);
+        assert!(!ctx.has_unsaved_changes());
+        ctx.mark_batch_saved(&batch);
+        assert_eq!(ctx.session_base.notes_by_path["/a"].len(), 2);
+    }
+
+    #[test]
+    fn relink_book_moves_per_book_data() {
+        let mut ctx = AppContext::new(Settings::default())
+            .with_library(String::new(), vec![book("/a"), book("/b"), book("/c")])
+            .with_progress(HashMap::from([
+                ("/b".to_string(), BookProgress::at_page(5)),
+                ("/c".to_string(), BookProgress::at_page(9)),
+            ]))
+            .with_bookmarks(HashMap::from([(
+                "/b".to_string(),
+                vec![Bookmark {
+                    page: 2,
+                    label: String::new(),
+                    view: None,
+                }],
+            )]));
+        ctx.selected = 1;
+        ctx.dirty_progress_paths.insert("/b".to_string());
+        ctx.add_reading_time("/b", 30);
+        ctx.add_reading_time("/b", 45);
+        let count = PageCount {
+            pages: 10,
+            size_bytes: 100,
+            modified_secs: 1,
+            kind: None,
+        };
+        ctx.set_page_count("/b", count);
+        ctx.set_page_count("/c", count);
+        assert!(ctx.dirty_page_count_paths.contains("/b"));
+
+        ctx.relink_book("/b", "/c", "c2".to_string());
+
+        let paths: Vec<&str> = ctx.books.iter().map(|b| b.path.as_str()).collect();
+        assert_eq!(paths, vec!["/a", "/c"]);
+        assert_eq!(ctx.books[ctx.selected].title, "c2");
+        assert_eq!(
+            ctx.progress_by_path.get("/c"),
+            Some(&BookProgress::at_page(5))
+        );
+        assert!(!ctx.progress_by_path.contains_key("/b"));
+        assert!(ctx.bookmarks_by_path.contains_key("/c"));
+        assert!(ctx.dirty_progress_paths.contains("/c"));
+        assert_eq!(ctx.reading_secs_by_path.get("/c"), Some(&75));
+        assert!(ctx.dirty_reading_time_paths.contains("/c"));
+        assert!(ctx.page_counts_by_path.is_empty());
+        assert!(ctx.dirty_page_count_paths.is_empty());
+        assert_eq!(
+            ctx.dirty_book_path_ops,
+            vec![BookPathOp::Relink {
+                from: "/b".to_string(),
+                to: "/c".to_string(),
+                title: "c2".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn moved_books_are_relinked_by_fingerprint() {
+        let fingerprints = |pairs: &[(&str, &str)]| -> HashMap<String, FileFingerprint> {
+            pairs
+                .iter()
+                .map(|(path, hash)| {
+                    let fingerprint = FileFingerprint {
+                        size_bytes: 100,
+                        modified_secs: 1,
+                        quick_hash: hash.to_string(),
+                        tail_hash: Some(hash.to_string()),
+                        full_hash: None,
+                    };
+                    (path.to_string(), fingerprint)
+                })
+                .collect()
+        };
+        let mut ctx = AppContext::new(Settings::default())
+            .with_library(
+                String::new(),
+                vec![
+                    book("/old/a"),
+                    book("/old/b"),
+                    book("/new/a"),
+                    book("/new/b"),
+                ],
+            )
+            .with_progress(HashMap::from([
+                ("/old/a".to_string(), BookProgress::at_page(5)),
+                // Already read at its new path, so it is not taken over.
+                ("/new/b".to_string(), BookProgress::at_page(1)),
+            ]))
+            .with_notes(HashMap::from([(
+                "/old/a".to_string(),
+                vec![
+                    Note {
+                        page: 1,
+                        body: "one".to_string(),
+                    },
+                    Note {
+                        page: 2,
+                        body: "two".to_string(),
+                    },
+                ],
+            )]))
+            .with_fingerprints(fingerprints(&[
+                ("/old/a", "A"),
+                ("/old/b", "B"),
+                ("/new/a", "A"),
+                ("/new/b", "B"),
+            ]));
+        ctx.books[0].title = "Dune".to_string();
+
+        let moved = ctx.relink_moved_books(
+            &["/old/a".to_string(), "/old/b".to_string()],
+            &["/new/a".to_string(), "/new/b".to_string()],
+        );
+
+        assert_eq!(
+            moved,
+            vec![MovedBook {
+                title: "Dune".to_string(),
+                from: "/old/a".to_string(),
+                to: "/new/a".to_string(),
+                carried: vec!["progress".to_string(), "2 notes".to_string()],
+            }]
+        );
+        let paths: Vec<&str> = ctx.books.iter().map(|b| b.path.as_str()).collect();
+        assert_eq!(paths, vec!["/new/a", "/old/b", "/new/b"]);
+        assert_eq!(ctx.books[0].title, "Dune");
+        assert_eq!(
+            ctx.progress_by_path.get("/new/a"),
+            Some(&BookProgress::at_page(5))
+        );
+        assert_eq!(
+            ctx.fingerprints_by_path,
+            fingerprints(&[("/new/a", "A"), ("/old/b", "B"), ("/new/b", "B")])
+        );
+        assert!(ctx.dirty_fingerprint_paths.contains("/new/a"));
+    }
+
+    #[test]
+    fn collection_reading_order_moves_and_forgets_places() {
+        let in_collection = |name: &str| BookLabels {
+            tags: Vec::new(),
+            collection: Some(name.to_string()),
+            ..BookLabels::default()
+        };
+        let mut ctx = AppContext::new(Settings::default())
+            .with_library(
+                String::new(),
+                vec![book("/d"), book("/c"), book("/b"), book("/a"), book("/x")],
+            )
+            .with_labels(HashMap::from([
+                ("/a".to_string(), in_collection("Queue")),
+                ("/b".to_string(), in_collection("queue")),
+                ("/c".to_string(), in_collection("Queue")),
+                ("/d".to_string(), in_collection("Queue")),
+                ("/x".to_string(), in_collection("Other")),
+            ]))
+            .with_collection_positions(HashMap::from([("/c".to_string(), 0)]));
+        let order = |ctx: &AppContext| -> Vec<String> {
+            ctx.collection_reading_order("Queue")
+                .into_iter()
+                .map(|idx| ctx.books[idx].path.clone())
+                .collect()
+        };
+
+        // Unordered books follow the ordered ones, by title.
+        assert_eq!(order(&ctx), vec!["/c", "/a", "/b", "/d"]);
+
+        assert!(!ctx.move_in_collection("/c", true));
+        assert!(!ctx.move_in_collection("/missing", false));
+        assert!(ctx.dirty_collection_position_paths.is_empty());
+
+        assert!(ctx.move_in_collection("/b", true));
+        assert_eq!(order(&ctx), vec!["/c", "/b", "/a", "/d"]);
+        assert_eq!(
+            ctx.dirty_collection_position_paths,
+            HashSet::from(["/a".to_string(), "/b".to_string(), "/d".to_string()])
+        );
+        assert!(!ctx.move_in_collection("/d", false));
+
+        ctx.dirty_collection_position_paths.clear();
+        ctx.set_book_labels("/b", in_collection("Other"));
+        assert_eq!(order(&ctx), vec!["/c", "/a", "/d"]);
+        assert!(!ctx.collection_positions_by_path.contains_key("/b"));
+        assert!(ctx.dirty_collection_position_paths.contains("/b"));
+        assert!(ctx.dirty_label_paths.contains("/b"));
+
+        ctx.dirty_collection_position_paths.clear();
+        ctx.set_book_labels("/a", in_collection("QUEUE"));
+        assert!(ctx.dirty_collection_position_paths.is_empty());
+    }
+
+    #[test]
+    fn set_book_status_marks_changes_dirty() {
+        let mut ctx = AppContext::new(Settings::default())
+            .with_library(String::new(), vec![book("/a"), book("/b")]);
+
+        assert!(!ctx.set_book_status("/a", ReadingStatus::Unread));
+        assert!(!ctx.set_book_status("/missing", ReadingStatus::Reading));
+        assert!(ctx.dirty_status_paths.is_empty());
+
+        assert!(ctx.set_book_status("/a", ReadingStatus::Finished));
+        assert_eq!(ctx.books[0].status, ReadingStatus::Finished);
+        assert!(ctx.dirty_status_paths.contains("/a"));
+
+        ctx.relink_book("/a", "/c", "c".to_string());
+        assert!(ctx.dirty_status_paths.contains("/c"));
+        ctx.remove_book("/c");
+        assert!(ctx.dirty_status_paths.is_empty());
+    }
+
+    #[test]
+    fn archived_books_are_hidden_unless_included() {
+        let mut ctx = AppContext::new(Settings::default())
+            .with_library(String::new(), vec![book("/a"), book("/b")]);
+
+        assert!(!ctx.set_book_archived("/a", false));
+        assert!(!ctx.set_book_archived("/missing", true));
+        assert!(ctx.set_book_archived("/a", true));
+        assert!(ctx.dirty_archived_paths.contains("/a"));
+        assert!(!ctx.is_listed(&ctx.books[0]));
+        assert!(ctx.is_listed(&ctx.books[1]));
+
+        ctx.include_archived = true;
+        assert!(ctx.is_listed(&ctx.books[0]));
+
+        ctx.relink_book("/a", "/c", "c".to_string());
+        assert!(ctx.books[0].archived);
+        assert!(ctx.dirty_archived_paths.contains("/c"));
+        ctx.remove_book("/c");
+        assert!(ctx.dirty_archived_paths.is_empty());
+    }
+
+    #[test]
+    fn merging_duplicates_keeps_the_furthest_progress_and_every_annotation() {
+        let mut copy = book("/copy");
+        copy.favorite = true;
+        copy.status = ReadingStatus::Reading;
+        let mut ctx = AppContext::new(Settings::default())
+            .with_library(String::new(), vec![book("/kept"), copy, book("/other")])
+            .with_progress(HashMap::from([
+                ("/kept".to_string(), BookProgress::at_page(4)),
+                ("/copy".to_string(), BookProgress::at_page(40)),
+            ]))
+            .with_reading_time(HashMap::from([
+                ("/kept".to_string(), 60),
+                ("/copy".to_string(), 90),
+            ]))
+            .with_bookmarks(HashMap::from([
+                (
+                    "/kept".to_string(),
+                    vec![Bookmark {
+                        page: 9,
+                        label: "kept".to_string(),
+                        view: None,
+                    }],
+                ),
+                (
+                    "/copy".to_string(),
+                    vec![
+                        Bookmark {
+                            page: 9,
+                            label: "copy".to_string(),
+                            view: None,
+                        },
+                        Bookmark {
+                            page: 2,
+                            label: String::new(),
+                            view: None,
+                        },
+                    ],
+                ),
+            ]))
+            .with_notes(HashMap::from([(
+                "/copy".to_string(),
+                vec![Note {
+                    page: 3,
+                    body: "look".to_string(),
+                }],
+            )]))
+            .with_labels(HashMap::from([
+                (
+                    "/kept".to_string(),
+                    BookLabels {
+                        tags: vec!["rust".to_string()],
+                        collection: None,
+                        ..BookLabels::default()
+                    },
+                ),
+                (
+                    "/copy".to_string(),
+                    BookLabels {
+                        tags: vec!["os".to_string()],
+                        collection: Some("Work".to_string()),
+                        ..BookLabels::default()
+                    },
+                ),
+            ]));
+
+        let merged = ctx.merge_duplicates(
+            "/kept",
+            &[
+                "/copy".to_string(),
+                "/kept".to_string(),
+                "/gone".to_string(),
+            ],
+            DuplicateDisposal::Archive,
+        );
+        assert_eq!(merged, 1);
+        assert_eq!(ctx.progress_by_path["/kept"].last_page, 40);
+        assert_eq!(ctx.reading_secs_by_path["/kept"], 150);
+        let pages: Vec<(u32, &str)> = ctx.bookmarks_by_path["/kept"]
+            .iter()
+            .map(|b| (b.page, b.label.as_str()))
+            .collect();
+        assert_eq!(pages, vec![(2, ""), (9, "kept")]);
+        assert_eq!(ctx.notes_by_path["/kept"].len(), 1);
+        assert_eq!(ctx.labels_by_path["/kept"].tags, vec!["os", "rust"]);
+        assert_eq!(
+            ctx.labels_by_path["/kept"].collection.as_deref(),
+            Some("Work")
+        );
+        assert!(!ctx.labels_by_path.contains_key("/copy"));
+        assert!(ctx.books[0].favorite);
+        assert_eq!(ctx.books[0].status, ReadingStatus::Reading);
+        assert!(ctx.books[1].archived);
+        assert!(ctx.dirty_bookmark_paths.contains("/copy"));
+
+        assert_eq!(
+            ctx.merge_duplicates("/kept", &["/other".to_string()], DuplicateDisposal::Remove),
+            1
+        );
+        assert!(ctx.books.iter().all(|b| b.path != "/other"));
+    }
+
+    #[test]
+    fn recent_history_is_seeded_and_deduped() {
+        let mut a = book("/a");
+        a.last_opened = Some(10);
+        let mut c = book("/c");
+        c.last_opened = Some(30);
+        let mut ctx = AppContext::new(Settings::default())
+            .with_library(String::new(), vec![a, book("/b"), c]);
+        assert_eq!(ctx.recent_paths, vec!["/c".to_string(), "/a".to_string()]);
+
+        ctx.record_book_opened("/a", 40);
+        ctx.record_book_opened("/b", 50);
+        assert_eq!(
+            ctx.recent_paths,
+            vec!["/b".to_string(), "/a".to_string(), "/c".to_string()]
+        );
+        assert_eq!(ctx.books[0].last_opened, Some(40));
+        assert_eq!(ctx.opened_at_by_path.get("/b"), Some(&50));
+
+        for i in 0..RECENT_BOOKS_LIMIT {
+            ctx.record_book_opened(&format!("/x{i}"), 60 + i as i64);
+        }
+        assert_eq!(ctx.recent_paths.len(), RECENT_BOOKS_LIMIT);
+        assert_eq!(ctx.recent_paths[0], format!("/x{}", RECENT_BOOKS_LIMIT - 1));
+    }
+
+    #[test]
+    fn remove_book_drops_entries_and_clamps_selection() {
+        let mut ctx = AppContext::new(Settings::default())
+            .with_library(String::new(), vec![book("/a"), book("/b")])
+            .with_progress(HashMap::from([(
+                "/b".to_string(),
+                BookProgress::at_page(3),
+            )]));
+        ctx.selected = 1;
+        ctx.dirty_progress_paths.insert("/b".to_string());
+
+        ctx.remove_book("/b");
+
+        assert_eq!(ctx.books.len(), 1);
+        assert_eq!(ctx.selected, 0);
+        assert!(ctx.progress_by_path.is_empty());
+        assert!(ctx.dirty_progress_paths.is_empty());
+        assert_eq!(
+            ctx.dirty_book_path_ops,
+            vec![BookPathOp::Remove {
+                path: "/b".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn apply_cleanup_forgets_books_and_unused_labels() {
+        let mut ctx = AppContext::new(Settings::default())
+            .with_library(String::new(), vec![book("/a"), book("/b"), book("/c")])
+            .with_labels(HashMap::from([(
+                "/c".to_string(),
+                BookLabels {
+                    tags: vec!["draft".to_string()],
+                    collection: None,
+                    ..BookLabels::default()
+                },
+            )]))
+            .with_label_catalog(
+                vec!["draft".to_string(), "old".to_string()],
+                vec!["Work".to_string()],
+            );
+        ctx.selected = 2;
+        ctx.relink_book("/a", "/z", "z".to_string());
+        assert_eq!(ctx.cleanup_keep_paths(), vec!["/a".to_string()]);
+
+        ctx.apply_cleanup(&CleanupReport {
+            removed_books: vec!["/b".to_string()],
+            removed_tags: vec![
+                (TagKind::Tag, "draft".to_string()),
+                (TagKind::Tag, "old".to_string()),
+                (TagKind::Collection, "Work".to_string()),
+            ],
+            ..CleanupReport::default()
+        });
+
+        let paths: Vec<&str> = ctx.books.iter().map(|b| b.path.as_str()).collect();
+        assert_eq!(paths, vec!["/z", "/c"]);
+        assert_eq!(ctx.books[ctx.selected].path, "/c");
+        assert_eq!(ctx.known_tags, vec!["draft".to_string()]);
+        assert!(ctx.known_collections.is_empty());
+    }
+
+    #[test]
+    fn apply_scan_merge_prunes_only_books_still_missing() {
+        let mut ctx = AppContext::new(Settings::default())
+            .with_library(String::new(), vec![book("/a"), book("/b"), book("/c")]);
+        ctx.relink_book("/a", "/z", "z".to_string());
+        let merge = ScanMerge {
+            updated: vec![Book {
+                author: Some("Le Guin".to_string()),
+                ..book("/c")
+            }],
+            missing: vec!["/a".to_string(), "/b".to_string()],
+            unchanged: 1,
+            ..ScanMerge::default()
+        };
+
+        let report = ctx.apply_scan_merge(&merge, false);
+        assert_eq!(report.missing, 1);
+        assert_eq!(report.updated, 1);
+        assert_eq!(ctx.books.len(), 3);
+        assert_eq!(ctx.books[2].author.as_deref(), Some("Le Guin"));
+
+        ctx.apply_scan_merge(&merge, true);
+        let paths: Vec<&str> = ctx.books.iter().map(|b| b.path.as_str()).collect();
+        assert_eq!(paths, vec!["/z", "/c"]);
+        assert!(ctx.dirty_book_path_ops.contains(&BookPathOp::Remove {
+            path: "/b".to_string()
+        }));
+    }
 }
@@ -0,0 +1,108 @@
+//! Merging a session's per-book changes with what another instance saved to the same database
+//! while it ran.
+
+use std::collections::HashMap;
+
+use bookshelf_core::{BookLabels, Bookmark, Note};
+
+/// Per-book data as last loaded from or saved to the database: the common ancestor when
+/// another instance's changes are merged in.
+#[derive(Debug, Clone, Default)]
+pub struct SessionBase {
+    pub labels_by_path: HashMap<String, BookLabels>,
+    pub bookmarks_by_path: HashMap<String, Vec<Bookmark>>,
+    pub notes_by_path: HashMap<String, Vec<Note>>,
+}
+
+/// What another instance left in the database, for the books this session is about to write.
+#[derive(Debug, Clone, Default)]
+pub struct StoredBookData {
+    pub labels_by_path: HashMap<String, BookLabels>,
+    pub bookmarks_by_path: HashMap<String, Vec<Bookmark>>,
+    pub notes_by_path: HashMap<String, Vec<Note>>,
+}
+
+/// Three-way merge of two edits of `base`: our entries, plus the ones they added, minus the
+/// ones they removed. Our order comes first; their additions follow.
+pub(crate) fn merge_entries<T: Clone>(
+    base: &[T],
+    ours: &[T],
+    theirs: &[T],
+    same: impl Fn(&T, &T) -> bool,
+) -> Vec<T> {
+    let contains = |list: &[T], item: &T| list.iter().any(|other| same(other, item));
+    let mut merged: Vec<T> = ours
+        .iter()
+        .filter(|item| !contains(base, item) || contains(theirs, item))
+        .cloned()
+        .collect();
+    for item in theirs {
+        if !contains(base, item) && !contains(&merged, item) {
+            merged.push(item.clone());
+        }
+    }
+    merged
+}
+
+/// Tags merge entry by entry. The collection, author and series are ours when this session
+/// changed them, theirs otherwise.
+pub(crate) fn merge_labels(
+    base: &BookLabels,
+    ours: &BookLabels,
+    theirs: &BookLabels,
+) -> BookLabels {
+    fn changed<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> T {
+        if ours == base {
+            theirs.clone()
+        } else {
+            ours.clone()
+        }
+    }
+    let mut merged = BookLabels {
+        tags: merge_entries(&base.tags, &ours.tags, &theirs.tags, |a, b| {
+            a.eq_ignore_ascii_case(b)
+        }),
+        collection: changed(&base.collection, &ours.collection, &theirs.collection),
+        author: changed(&base.author, &ours.author, &theirs.author),
+        series: changed(&base.series, &ours.series, &theirs.series),
+    };
+    merged.normalize();
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(tags: &[&str], collection: Option<&str>) -> BookLabels {
+        BookLabels {
+            tags: tags.iter().map(ToString::to_string).collect(),
+            collection: collection.map(ToString::to_string),
+            ..BookLabels::default()
+        }
+    }
+
+    #[test]
+    fn entries_keep_both_sides_additions_and_removals() {
+        let base = [1, 2, 3];
+        // We removed 2 and added 4; they removed 3 and added 5.
+        let merged = merge_entries(&base, &[1, 3, 4], &[1, 2, 5], |a, b| a == b);
+        assert_eq!(merged, vec![1, 4, 5]);
+        // Both added the same entry: it appears once.
+        assert_eq!(merge_entries(&[], &[7], &[7], |a, b| a == b), vec![7]);
+    }
+
+    #[test]
+    fn labels_union_tags_and_keep_the_changed_collection() {
+        let base = labels(&["rust"], Some("work"));
+        let ours = labels(&["rust", "async"], Some("work"));
+        let theirs = labels(&["RUST", "db"], Some("reading"));
+        assert_eq!(
+            merge_labels(&base, &ours, &theirs),
+            labels(&["async", "db", "rust"], Some("reading"))
+        );
+
+        let ours = labels(&[], None);
+        assert_eq!(merge_labels(&base, &ours, &theirs), labels(&["db"], None));
+    }
+}
@@ -0,0 +1,121 @@
+//! Books whose file moved, found again by the fingerprint of the file's bytes.
+
+use std::collections::{BTreeMap, HashMap};
+
+use bookshelf_core::FileFingerprint;
+
+/// A book relinked to the path its file moved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovedBook {
+    pub title: String,
+    pub from: String,
+    pub to: String,
+    /// What came along, e.g. `progress` or `3 notes`; empty when the book had no data.
+    pub carried: Vec<String>,
+}
+
+/// Pairs `missing` books with `found` files of the same size, quick hash and tail hash, as
+/// `(from, to)` sorted by `from`. Fingerprints without a tail hash are passed over. A
+/// fingerprint shared by two missing books or two found files is skipped, as there is no
+/// telling which copy went where.
+pub(crate) fn moved_books(
+    missing: &[String],
+    found: &[String],
+    fingerprints: &HashMap<String, FileFingerprint>,
+) -> Vec<(String, String)> {
+    let edges = |path: &String| {
+        fingerprints.get(path).and_then(|fingerprint| {
+            let tail_hash = fingerprint.tail_hash.as_deref()?;
+            Some((
+                fingerprint.size_bytes,
+                fingerprint.quick_hash.as_str(),
+                tail_hash,
+            ))
+        })
+    };
+    let mut by_edges: BTreeMap<_, (Vec<&String>, Vec<&String>)> = BTreeMap::new();
+    for path in missing {
+        if let Some(key) = edges(path) {
+            by_edges.entry(key).or_default().0.push(path);
+        }
+    }
+    for path in found {
+        if let Some((_, found)) = edges(path).and_then(|key| by_edges.get_mut(&key)) {
+            found.push(path);
+        }
+    }
+    let mut pairs: Vec<(String, String)> = by_edges
+        .into_values()
+        .filter_map(
+            |(missing, found)| match (missing.as_slice(), found.as_slice()) {
+                ([from], [to]) if from != to => Some((from.to_string(), to.to_string())),
+                _ => None,
+            },
+        )
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_unambiguous_fingerprints_pair_up() {
+        let paths =
+            |paths: &[&str]| -> Vec<String> { paths.iter().map(|p| p.to_string()).collect() };
+        let fingerprints: HashMap<String, FileFingerprint> = [
+            ("/old/a.pdf", "A"),
+            ("/new/a.pdf", "A"),
+            // Two missing copies of one file.
+            ("/old/b1.pdf", "B"),
+            ("/old/b2.pdf", "B"),
+            ("/new/b.pdf", "B"),
+            // Found twice.
+            ("/old/c.pdf", "C"),
+            ("/new/c1.pdf", "C"),
+            ("/new/c2.pdf", "C"),
+            ("/new/d.pdf", "D"),
+            // Same head, different tail.
+            ("/old/f.pdf", "F"),
+            ("/new/f.pdf", "F2"),
+        ]
+        .into_iter()
+        .map(|(path, hash)| {
+            let fingerprint = FileFingerprint {
+                size_bytes: 100,
+                modified_secs: 1,
+                quick_hash: hash[..1].to_string(),
+                tail_hash: Some(hash.to_string()),
+                full_hash: None,
+            };
+            (path.to_string(), fingerprint)
+        })
+        .collect();
+
+        let pairs = moved_books(
+            &paths(&[
+                "/old/a.pdf",
+                "/old/b1.pdf",
+                "/old/b2.pdf",
+                "/old/c.pdf",
+                "/old/e.pdf",
+                "/old/f.pdf",
+            ]),
+            &paths(&[
+                "/new/a.pdf",
+                "/new/b.pdf",
+                "/new/c1.pdf",
+                "/new/c2.pdf",
+                "/new/d.pdf",
+                "/new/f.pdf",
+            ]),
+            &fingerprints,
+        );
+        assert_eq!(
+            pairs,
+            vec![("/old/a.pdf".to_string(), "/new/a.pdf".to_string())]
+        );
+    }
+}
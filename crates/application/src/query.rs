@@ -0,0 +1,284 @@
+use std::fmt;
+
+use bookshelf_core::{Book, BookLabels, display_path};
+
+use crate::book_author;
+
+/// A parsed library filter query.
+///
+/// `tag:rust col:work fav:yes author:knuth kernel` keeps books tagged `rust`, in the `work`
+/// collection, marked favorite, by an author matching `knuth`, with `kernel` in the title, author
+/// or path; `series:dune` keeps books in a series matching `dune`. Values and bare words may be
+/// quoted to include
+/// spaces (`tag:"data science"`). Unknown prefixes and empty or invalid values are plain text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LibraryQuery {
+    /// Words and phrases that must all appear in the title, author or path.
+    pub text: Vec<String>,
+    /// Tags the book must all carry.
+    pub tags: Vec<String>,
+    /// Collections the book may be in; any one matches.
+    pub collections: Vec<String>,
+    /// Words and phrases that must all appear in the author.
+    pub authors: Vec<String>,
+    /// Words and phrases that must all appear in the series name.
+    pub series: Vec<String>,
+    pub favorite: Option<bool>,
+}
+
+impl LibraryQuery {
+    pub fn parse(input: &str) -> Self {
+        let mut query = Self::default();
+        for token in tokenize(input) {
+            let field = token.split_once(':').and_then(|(prefix, value)| {
+                let value = value.trim();
+                if value.is_empty() {
+                    return None;
+                }
+                match prefix.to_ascii_lowercase().as_str() {
+                    "tag" | "tags" => Some(QueryField::Tag(value.to_string())),
+                    "col" | "collection" => Some(QueryField::Collection(value.to_string())),
+                    "fav" | "favorite" => parse_bool(value).map(QueryField::Favorite),
+                    "author" | "by" => Some(QueryField::Author(value.to_string())),
+                    "series" => Some(QueryField::Series(value.to_string())),
+                    _ => None,
+                }
+            });
+            match field {
+                Some(QueryField::Tag(tag)) => query.tags.push(tag),
+                Some(QueryField::Collection(name)) => query.collections.push(name),
+                Some(QueryField::Favorite(favorite)) => query.favorite = Some(favorite),
+                Some(QueryField::Author(author)) => query.authors.push(author),
+                Some(QueryField::Series(name)) => query.series.push(name),
+                None => query.text.push(token),
+            }
+        }
+        query
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+            && self.tags.is_empty()
+            && self.collections.is_empty()
+            && self.authors.is_empty()
+            && self.series.is_empty()
+            && self.favorite.is_none()
+    }
+
+    pub fn matches(&self, book: &Book, labels: &BookLabels) -> bool {
+        if self
+            .favorite
+            .is_some_and(|favorite| favorite != book.favorite)
+        {
+            return false;
+        }
+        if !self.collections.is_empty()
+            && !labels.collection.as_deref().is_some_and(|collection| {
+                self.collections
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(collection))
+            })
+        {
+            return false;
+        }
+        if !self.tags.iter().all(|tag| {
+            labels
+                .tags
+                .iter()
+                .any(|have| have.eq_ignore_ascii_case(tag))
+        }) {
+            return false;
+        }
+        let author = book_author(book, labels).unwrap_or_default().to_lowercase();
+        if !self
+            .authors
+            .iter()
+            .all(|term| author.contains(&term.to_lowercase()))
+        {
+            return false;
+        }
+        let series = labels.series_name().unwrap_or_default().to_lowercase();
+        if !self
+            .series
+            .iter()
+            .all(|term| series.contains(&term.to_lowercase()))
+        {
+            return false;
+        }
+        if self.text.is_empty() {
+            return true;
+        }
+
+        let title = book.title.to_ascii_lowercase();
+        let path = display_path(&book.path).to_ascii_lowercase();
+        self.text.iter().all(|term| {
+            let term = term.to_ascii_lowercase();
+            title.contains(&term) || author.contains(&term) || path.contains(&term)
+        })
+    }
+}
+
+/// Renders the understood form, e.g. `tag:rust col:work author:knuth fav:yes text:kernel`.
+impl fmt::Display for LibraryQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        parts.extend(self.tags.iter().map(|tag| format!("tag:{}", quoted(tag))));
+        parts.extend(
+            self.collections
+                .iter()
+                .map(|name| format!("col:{}", quoted(name))),
+        );
+        parts.extend(
+            self.authors
+                .iter()
+                .map(|author| format!("author:{}", quoted(author))),
+        );
+        parts.extend(
+            self.series
+                .iter()
+                .map(|name| format!("series:{}", quoted(name))),
+        );
+        if let Some(favorite) = self.favorite {
+            parts.push(format!("fav:{}", if favorite { "yes" } else { "no" }));
+        }
+        parts.extend(
+            self.text
+                .iter()
+                .map(|term| format!("text:{}", quoted(term))),
+        );
+        f.write_str(&parts.join(" "))
+    }
+}
+
+enum QueryField {
+    Tag(String),
+    Collection(String),
+    Favorite(bool),
+    Author(String),
+    Series(String),
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" | "y" | "true" | "1" | "on" => Some(true),
+        "no" | "n" | "false" | "0" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Splits on whitespace outside double quotes; quotes are dropped and may close anywhere.
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for ch in input.chars() {
+        match ch {
+            '"' => in_quotes = !in_quotes,
+            ch if ch.is_whitespace() && !in_quotes => tokens.push(std::mem::take(&mut current)),
+            ch => current.push(ch),
+        }
+    }
+    tokens.push(current);
+    tokens.retain(|token| !token.trim().is_empty());
+    tokens
+}
+
+fn quoted(value: &str) -> String {
+    if value.chars().any(char::is_whitespace) {
+        format!("\"{value}\"")
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bookshelf_core::ReadingStatus;
+
+    use super::*;
+
+    fn book(title: &str, favorite: bool) -> Book {
+        Book {
+            path: format!("/library/{title}.pdf"),
+            title: title.to_string(),
+            last_opened: None,
+            favorite,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        }
+    }
+
+    fn labels(tags: &[&str], collection: Option<&str>) -> BookLabels {
+        BookLabels {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            collection: collection.map(str::to_string),
+            ..BookLabels::default()
+        }
+    }
+
+    #[test]
+    fn parse_splits_fields_phrases_and_unknown_prefixes() {
+        let query =
+            LibraryQuery::parse(r#"tag:rust col:"side projects" fav:yes "os kernel" foo:bar tag:"#);
+        assert_eq!(query.tags, vec!["rust".to_string()]);
+        assert_eq!(query.collections, vec!["side projects".to_string()]);
+        assert_eq!(query.favorite, Some(true));
+        assert_eq!(
+            query.text,
+            vec![
+                "os kernel".to_string(),
+                "foo:bar".to_string(),
+                "tag:".to_string()
+            ]
+        );
+        assert_eq!(
+            query.to_string(),
+            r#"tag:rust col:"side projects" fav:yes text:"os kernel" text:foo:bar text:tag:"#
+        );
+        assert_eq!(
+            LibraryQuery::parse("fav:maybe").text,
+            vec!["fav:maybe".to_string()]
+        );
+        assert!(LibraryQuery::parse("  \"\" ").is_empty());
+    }
+
+    #[test]
+    fn matches_combines_all_terms() {
+        let query = LibraryQuery::parse("tag:Rust col:work fav:yes kernel");
+        let labelled = labels(&["rust", "os"], Some("Work"));
+        assert!(query.matches(&book("Linux Kernel Dev", true), &labelled));
+        assert!(!query.matches(&book("Linux Kernel Dev", false), &labelled));
+        assert!(!query.matches(&book("Compilers", true), &labelled));
+        assert!(!query.matches(
+            &book("Linux Kernel Dev", true),
+            &labels(&["os"], Some("work"))
+        ));
+        assert!(!query.matches(&book("Linux Kernel Dev", true), &labels(&["rust"], None)));
+
+        let mut by_knuth = book("Concrete Mathematics", false);
+        by_knuth.author = Some("Donald E. Knuth".to_string());
+        let author = LibraryQuery::parse("author:knuth concrete");
+        assert!(author.matches(&by_knuth, &BookLabels::default()));
+        assert!(!author.matches(&book("Concrete Mathematics", false), &BookLabels::default()));
+        assert!(LibraryQuery::parse("donald").matches(&by_knuth, &BookLabels::default()));
+
+        let by_hand = BookLabels {
+            author: Some("Ursula K. Le Guin".to_string()),
+            series: Some(("Earthsea".to_string(), Some(1))),
+            ..BookLabels::default()
+        };
+        let untitled = book("A Wizard of Earthsea", false);
+        assert!(LibraryQuery::parse("author:guin series:earth").matches(&untitled, &by_hand));
+        assert!(!LibraryQuery::parse("series:earth").matches(&untitled, &BookLabels::default()));
+        assert_eq!(
+            LibraryQuery::parse(r#"series:"hainish cycle""#).to_string(),
+            r#"series:"hainish cycle""#
+        );
+
+        let words = LibraryQuery::parse("kernel linux");
+        assert!(words.matches(&book("Linux Kernel Dev", false), &BookLabels::default()));
+        let phrase = LibraryQuery::parse("\"kernel linux\"");
+        assert!(!phrase.matches(&book("Linux Kernel Dev", false), &BookLabels::default()));
+    }
+}
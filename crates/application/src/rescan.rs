@@ -0,0 +1,151 @@
+//! Merging a finished scan into the stored library.
+//!
+//! A scan only derives a path, a title from the file name or the document, and the document's
+//! author. Books already stored keep their title, favorite flag, status and timestamps, since
+//! those may have been set by hand; only the author, which always comes from the document, is
+//! refreshed. Books the scan did not find stay until the user prunes them.
+
+use std::collections::{HashMap, HashSet};
+
+use bookshelf_core::Book;
+
+/// What a rescan did to the library, shown as a notice once it finishes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanReport {
+    pub added: usize,
+    pub updated: usize,
+    /// Stored books a complete scan did not find; removed from the library when pruned.
+    pub missing: usize,
+    pub unchanged: usize,
+}
+
+/// How a scan changes the stored library.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanMerge {
+    /// Books new to the library, in scan order.
+    pub added: Vec<Book>,
+    /// Stored books with their derived fields refreshed, in stored order.
+    pub updated: Vec<Book>,
+    /// Paths of stored books the scan did not find, in stored order. Empty unless the scan
+    /// was complete.
+    pub missing: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl ScanMerge {
+    pub fn report(&self) -> ScanReport {
+        ScanReport {
+            added: self.added.len(),
+            updated: self.updated.len(),
+            missing: self.missing.len(),
+            unchanged: self.unchanged,
+        }
+    }
+}
+
+/// Merges the books a scan `found` into the `stored` ones. An incomplete scan (cancelled or
+/// failed) adds and updates what it reached but reports nothing missing.
+pub fn merge_scan(stored: &[Book], found: &[Book], complete: bool) -> ScanMerge {
+    let mut found_by_path: HashMap<&str, &Book> = HashMap::new();
+    for book in found {
+        found_by_path.entry(book.path.as_str()).or_insert(book);
+    }
+
+    let mut merge = ScanMerge::default();
+    for book in stored {
+        match found_by_path.get(book.path.as_str()) {
+            Some(scanned) => match refreshed(book, scanned) {
+                Some(updated) => merge.updated.push(updated),
+                None => merge.unchanged += 1,
+            },
+            None if complete => merge.missing.push(book.path.clone()),
+            None => {}
+        }
+    }
+
+    let stored_paths: HashSet<&str> = stored.iter().map(|b| b.path.as_str()).collect();
+    let mut added_paths = HashSet::new();
+    for book in found {
+        if !stored_paths.contains(book.path.as_str()) && added_paths.insert(book.path.as_str()) {
+            merge.added.push(book.clone());
+        }
+    }
+    merge
+}
+
+/// The stored book with the scan's derived fields, or `None` when they match. A scan that
+/// read no author leaves the stored one.
+fn refreshed(stored: &Book, scanned: &Book) -> Option<Book> {
+    let author = scanned.author.as_ref()?;
+    if stored.author.as_ref() == Some(author) {
+        return None;
+    }
+    Some(Book {
+        author: Some(author.clone()),
+        ..stored.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bookshelf_core::ReadingStatus;
+
+    use super::*;
+
+    fn book(path: &str, title: &str, author: Option<&str>) -> Book {
+        Book {
+            path: path.to_string(),
+            title: title.to_string(),
+            last_opened: None,
+            favorite: false,
+            author: author.map(str::to_string),
+            status: ReadingStatus::Unread,
+            archived: false,
+        }
+    }
+
+    #[test]
+    fn stored_books_keep_their_own_fields() {
+        let mut edited = book("/a.pdf", "My Title", Some("Old"));
+        edited.favorite = true;
+        edited.last_opened = Some(7);
+        let stored = vec![edited.clone(), book("/b.pdf", "b", None)];
+        let found = vec![
+            book("/b.pdf", "b", None),
+            book("/a.pdf", "a", Some("New")),
+            book("/c.pdf", "c", None),
+        ];
+
+        let merge = merge_scan(&stored, &found, true);
+        assert_eq!(
+            merge.updated,
+            vec![Book {
+                author: Some("New".to_string()),
+                ..edited
+            }]
+        );
+        assert_eq!(merge.added, vec![book("/c.pdf", "c", None)]);
+        assert_eq!(
+            merge.report(),
+            ScanReport {
+                added: 1,
+                updated: 1,
+                missing: 0,
+                unchanged: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn only_complete_scans_report_missing_books() {
+        let stored = vec![book("/a.pdf", "a", None), book("/b.pdf", "b", None)];
+        let found = vec![book("/b.pdf", "b", None), book("/b.pdf", "b", None)];
+
+        let merge = merge_scan(&stored, &found, true);
+        assert_eq!(merge.missing, vec!["/a.pdf".to_string()]);
+        assert_eq!(merge.unchanged, 1);
+        assert!(merge.added.is_empty());
+
+        assert!(merge_scan(&stored, &found, false).missing.is_empty());
+    }
+}
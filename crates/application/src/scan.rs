@@ -0,0 +1,863 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use bookshelf_core::{
+    Book, DocumentMetadata, FileFingerprint, ReadingStatus, ScanScope, Settings, encode_path,
+};
+
+use crate::duplicates::fingerprint_file;
+
+const BOOK_EXTENSIONS: &[&str] = &["pdf", "epub", "cbz"];
+
+/// Books are sent to the UI in batches of this size (or sooner, see `SCAN_PROGRESS_EVERY_FILES`).
+const SCAN_BATCH_BOOKS: usize = 64;
+const SCAN_PROGRESS_EVERY_FILES: usize = 256;
+/// Files queued from the walk to the workers, and results from the workers to the batching;
+/// both queues block when full, so a huge tree does not pile up in memory.
+const SCAN_QUEUE_FILES: usize = 1024;
+/// Most worker threads one scan starts.
+const SCAN_WORKERS_MAX: usize = 8;
+/// Bytes read from each end of a PDF to check its header and trailer.
+const PDF_CHECK_BYTES: u64 = 1024;
+
+/// Reads a book's document metadata; `None` when it has none or cannot be read.
+pub type MetadataReader = fn(&Book) -> Option<DocumentMetadata>;
+
+/// Called from a background thread once it has sent something, so an event loop blocked
+/// waiting for input picks it up at once instead of on its next tick.
+pub type Wake = Arc<dyn Fn() + Send + Sync>;
+
+/// Metadata lookup during a scan. Books outside `known_paths` take their title and author from
+/// `read`; known books keep what the library already has.
+#[derive(Debug, Clone)]
+pub struct ScanMetadata {
+    pub known_paths: HashSet<String>,
+    pub read: MetadataReader,
+    /// Books that already have a fingerprint with a tail hash. Background scans fingerprint
+    /// the others when `Settings.fingerprint_books` is on.
+    pub fingerprinted: HashSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanEvent {
+    /// Newly discovered books, file fingerprints taken for them as `(path, fingerprint)`,
+    /// books whose file is broken as `(path, error)`, folders and entries the walk could not
+    /// read as `(path, error)`, and the running count of files examined so far. Broken books
+    /// are among `books` too. Books come in the order the workers finish them.
+    Batch {
+        books: Vec<Book>,
+        fingerprints: Vec<(String, FileFingerprint)>,
+        broken: Vec<(String, String)>,
+        skipped: Vec<(String, String)>,
+        files_seen: usize,
+    },
+    /// The walk ended; `error` is set when it could not run at all.
+    Finished {
+        files_seen: usize,
+        error: Option<String>,
+    },
+}
+
+/// A library walk running on a background thread.
+pub struct LibraryScan {
+    events: Receiver<ScanEvent>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl LibraryScan {
+    /// Starts the walk. `wake`, when set, is called after each event is sent.
+    pub fn start(
+        settings: &Settings,
+        cwd: &Path,
+        metadata: Option<ScanMetadata>,
+        wake: Option<Wake>,
+    ) -> Self {
+        let (tx, events) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let roots = resolve_roots(settings, cwd);
+        let scope = settings.scan_scope;
+        let fingerprint = settings.fingerprint_books;
+        let worker_cancel = Arc::clone(&cancel);
+        let spawned = thread::Builder::new()
+            .name("bookshelf-library-scan".to_string())
+            .spawn({
+                let tx = tx.clone();
+                let wake = wake.clone();
+                move || {
+                    let job = FileJob {
+                        metadata,
+                        fingerprint,
+                    };
+                    let mut sink = BatchSink::new(tx.clone(), &worker_cancel);
+                    sink.wake = wake.clone();
+                    scan_roots(&roots, scope, scan_workers(), &job, &mut sink);
+                    let files_seen = sink.files_seen;
+                    sink.flush();
+                    let _ = tx.send(ScanEvent::Finished {
+                        files_seen,
+                        error: None,
+                    });
+                    if let Some(wake) = &wake {
+                        wake();
+                    }
+                }
+            });
+        if let Err(err) = spawned {
+            // Sent from this thread, which is the one that would be woken.
+            let _ = tx.send(ScanEvent::Finished {
+                files_seen: 0,
+                error: Some(format!("start scan thread: {err}")),
+            });
+        }
+        Self { events, cancel }
+    }
+
+    pub fn try_recv(&self) -> Option<ScanEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Asks the walk and its workers to stop; a `Finished` event still follows.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+/// Walks all library roots, returning when it is done. Books are not fingerprinted, so the
+/// walk never reads more than metadata and the ends of PDFs. Folders it cannot read are left
+/// out; the books come sorted by path.
+pub fn scan_books(
+    settings: &Settings,
+    cwd: &Path,
+    metadata: Option<ScanMetadata>,
+) -> anyhow::Result<Vec<Book>> {
+    let (tx, rx) = mpsc::channel();
+    let cancel = AtomicBool::new(false);
+    let job = FileJob {
+        metadata,
+        fingerprint: false,
+    };
+    let mut sink = BatchSink::new(tx, &cancel);
+    scan_roots(
+        &resolve_roots(settings, cwd),
+        settings.scan_scope,
+        scan_workers(),
+        &job,
+        &mut sink,
+    );
+    sink.flush();
+    drop(sink);
+
+    let mut found = BTreeMap::<String, Book>::new();
+    for event in rx {
+        if let ScanEvent::Batch { books, .. } = event {
+            for book in books {
+                found.insert(book.path.clone(), book);
+            }
+        }
+    }
+    Ok(found.into_values().collect())
+}
+
+pub fn is_book_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            BOOK_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+        .unwrap_or(false)
+}
+
+/// The enabled library roots, relative ones taken from `cwd`.
+pub(crate) fn resolve_roots(settings: &Settings, cwd: &Path) -> Vec<PathBuf> {
+    settings
+        .enabled_roots()
+        .map(|root| resolve_root(root, cwd))
+        .collect()
+}
+
+pub fn resolve_root(root: &str, cwd: &Path) -> PathBuf {
+    let root_path = PathBuf::from(root);
+    if root_path.is_absolute() {
+        root_path
+    } else {
+        cwd.join(root_path)
+    }
+}
+
+/// Worker threads for a scan: one per core, up to `SCAN_WORKERS_MAX`.
+fn scan_workers() -> usize {
+    thread::available_parallelism()
+        .map_or(1, |cores| cores.get())
+        .min(SCAN_WORKERS_MAX)
+}
+
+/// What the workers and the walk pass to the batching.
+enum Scanned {
+    /// A file; `Some` when it is a book.
+    File(Option<ScannedBook>),
+    /// A folder or entry the walk could not read, and why.
+    Skipped(String, String),
+}
+
+struct ScannedBook {
+    book: Book,
+    fingerprint: Option<FileFingerprint>,
+    problem: Option<String>,
+}
+
+/// The per-file work, shared by the workers.
+struct FileJob {
+    metadata: Option<ScanMetadata>,
+    /// Take file fingerprints of books not in `ScanMetadata::fingerprinted`.
+    fingerprint: bool,
+}
+
+impl FileJob {
+    /// `None` when `path` is not a file; `Some(None)` for files that are not books.
+    fn file(&self, path: &Path) -> Option<Option<ScannedBook>> {
+        if !path.is_file() {
+            return None;
+        }
+        if !is_book_file(path) {
+            return Some(None);
+        }
+        let mut book = book_for_path(path);
+        // Broken files are listed but never parsed, and a half-downloaded file would change
+        // its fingerprint once complete.
+        let problem = pdf_problem(path);
+        let mut fingerprint = None;
+        if problem.is_none() {
+            if let Some(lookup) = &self.metadata
+                && !lookup.known_paths.contains(&book.path)
+                && let Some(metadata) = (lookup.read)(&book)
+            {
+                book.apply_metadata(&metadata);
+            }
+            if self.fingerprint
+                && let Some(lookup) = &self.metadata
+                && !lookup.fingerprinted.contains(&book.path)
+            {
+                fingerprint = fingerprint_file(path, None).ok();
+            }
+        }
+        Some(Some(ScannedBook {
+            book,
+            fingerprint,
+            problem,
+        }))
+    }
+}
+
+struct BatchSink<'a> {
+    tx: Sender<ScanEvent>,
+    cancel: &'a AtomicBool,
+    wake: Option<Wake>,
+    pending: Vec<Book>,
+    pending_fingerprints: Vec<(String, FileFingerprint)>,
+    pending_broken: Vec<(String, String)>,
+    pending_skipped: Vec<(String, String)>,
+    files_seen: usize,
+    files_at_last_send: usize,
+}
+
+impl<'a> BatchSink<'a> {
+    fn new(tx: Sender<ScanEvent>, cancel: &'a AtomicBool) -> Self {
+        Self {
+            tx,
+            cancel,
+            wake: None,
+            pending: Vec::new(),
+            pending_fingerprints: Vec::new(),
+            pending_broken: Vec::new(),
+            pending_skipped: Vec::new(),
+            files_seen: 0,
+            files_at_last_send: 0,
+        }
+    }
+
+    /// Returns `false` once the scan was cancelled or the receiver is gone.
+    fn scanned(&mut self, scanned: Scanned) -> bool {
+        match scanned {
+            Scanned::File(book) => {
+                self.files_seen += 1;
+                if let Some(ScannedBook {
+                    book,
+                    fingerprint,
+                    problem,
+                }) = book
+                {
+                    if let Some(problem) = problem {
+                        self.pending_broken.push((book.path.clone(), problem));
+                    }
+                    if let Some(fingerprint) = fingerprint {
+                        self.pending_fingerprints
+                            .push((book.path.clone(), fingerprint));
+                    }
+                    self.pending.push(book);
+                }
+            }
+            Scanned::Skipped(path, error) => self.pending_skipped.push((path, error)),
+        }
+        if self.pending.len() >= SCAN_BATCH_BOOKS
+            || self.files_seen - self.files_at_last_send >= SCAN_PROGRESS_EVERY_FILES
+        {
+            return self.flush();
+        }
+        !self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn flush(&mut self) -> bool {
+        self.files_at_last_send = self.files_seen;
+        let event = ScanEvent::Batch {
+            books: std::mem::take(&mut self.pending),
+            fingerprints: std::mem::take(&mut self.pending_fingerprints),
+            broken: std::mem::take(&mut self.pending_broken),
+            skipped: std::mem::take(&mut self.pending_skipped),
+            files_seen: self.files_seen,
+        };
+        let sent = self.tx.send(event).is_ok();
+        if let Some(wake) = &self.wake {
+            wake();
+        }
+        sent && !self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+/// Scans `roots` with `workers` threads: this thread batches what they find while one more
+/// walks the folders and queues the files for them.
+fn scan_roots(
+    roots: &[PathBuf],
+    scope: ScanScope,
+    workers: usize,
+    job: &FileJob,
+    sink: &mut BatchSink<'_>,
+) {
+    let cancel = sink.cancel;
+    let (file_tx, file_rx) = mpsc::sync_channel::<PathBuf>(SCAN_QUEUE_FILES);
+    let (scanned_tx, scanned_rx) = mpsc::sync_channel::<Scanned>(SCAN_QUEUE_FILES);
+    let file_rx = Mutex::new(file_rx);
+    thread::scope(|threads| {
+        for _ in 0..workers.max(1) {
+            let scanned_tx = scanned_tx.clone();
+            let file_rx = &file_rx;
+            threads.spawn(move || {
+                // The lock is held only while waiting for the next file.
+                let next = || file_rx.lock().ok()?.recv().ok();
+                while let Some(path) = next() {
+                    // Once cancelled the queue is drained without work, so the walk is never
+                    // left blocked on a full queue.
+                    if cancel.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    if let Some(scanned) = job.file(&path) {
+                        let _ = scanned_tx.send(Scanned::File(scanned));
+                    }
+                }
+            });
+        }
+        threads.spawn(move || walk_roots(roots, scope, cancel, &file_tx, &scanned_tx));
+
+        let stopped = scanned_rx.iter().any(|scanned| !sink.scanned(scanned));
+        if stopped {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        // Dropped before the threads are joined, so a worker blocked sending returns.
+        drop(scanned_rx);
+    });
+}
+
+/// Queues every file under `roots` for the workers. Folders and entries that cannot be read
+/// are reported and passed over.
+fn walk_roots(
+    roots: &[PathBuf],
+    scope: ScanScope,
+    cancel: &AtomicBool,
+    files: &SyncSender<PathBuf>,
+    scanned: &SyncSender<Scanned>,
+) {
+    let skip = |path: &Path, error: String| {
+        scanned
+            .send(Scanned::Skipped(encode_path(path), error))
+            .is_ok()
+    };
+    for root_path in roots {
+        if root_path.is_file() {
+            if files.send(root_path.clone()).is_err() {
+                return;
+            }
+            continue;
+        }
+
+        if !root_path.is_dir() {
+            continue;
+        }
+
+        let mut stack = vec![root_path.clone()];
+        while let Some(dir) = stack.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    if !skip(&dir, format!("read dir: {err}")) {
+                        return;
+                    }
+                    continue;
+                }
+            };
+            for entry in entries {
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        if !skip(&dir, format!("read entry: {err}")) {
+                            return;
+                        }
+                        continue;
+                    }
+                };
+                // The entry's type comes with the listing; only links need a stat here.
+                let path = entry.path();
+                let is_dir = match entry.file_type() {
+                    Ok(kind) if kind.is_symlink() => path.is_dir(),
+                    Ok(kind) => kind.is_dir(),
+                    Err(_) => path.is_dir(),
+                };
+                if is_dir {
+                    if scope == ScanScope::Recursive {
+                        stack.push(path);
+                    }
+                } else if files.send(path).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Why a PDF cannot be opened, judged from its first and last `PDF_CHECK_BYTES` alone: it must
+/// have the `%PDF-` header and end with a `startxref` offset and `%%EOF`, which
+/// half-downloaded files lack. Other formats are not checked.
+fn pdf_problem(path: &Path) -> Option<String> {
+    let is_pdf = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"));
+    if !is_pdf {
+        return None;
+    }
+    let (head, tail) = match read_ends(path, PDF_CHECK_BYTES) {
+        Ok(ends) => ends,
+        Err(err) => return Some(format!("cannot read: {err}")),
+    };
+    if find(&head, b"%PDF-").is_none() {
+        return Some("not a PDF: no %PDF- header".to_string());
+    }
+    let Some(at) = tail
+        .windows(b"startxref".len())
+        .rposition(|window| window == b"startxref")
+    else {
+        return Some("truncated: no startxref trailer".to_string());
+    };
+    let rest = &tail[at + b"startxref".len()..];
+    let digits = rest
+        .iter()
+        .skip_while(|byte| byte.is_ascii_whitespace())
+        .take_while(|byte| byte.is_ascii_digit())
+        .count();
+    if digits == 0 {
+        return Some("truncated: startxref has no offset".to_string());
+    }
+    if find(rest, b"%%EOF").is_none() {
+        return Some("truncated: no %%EOF marker".to_string());
+    }
+    None
+}
+
+/// Up to `len` bytes from the start and from the end of the file; they overlap when it is
+/// shorter than `2 * len`.
+fn read_ends(path: &Path, len: u64) -> std::io::Result<(Vec<u8>, Vec<u8>)> {
+    let mut file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut head = Vec::new();
+    (&mut file).take(len).read_to_end(&mut head)?;
+    file.seek(SeekFrom::Start(size.saturating_sub(len)))?;
+    let mut tail = Vec::new();
+    file.take(len).read_to_end(&mut tail)?;
+    Ok((head, tail))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn book_for_path(path: &Path) -> Book {
+    let normalized = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let path_str = encode_path(&normalized);
+    let title = normalized
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "untitled".to_string());
+    Book {
+        path: path_str,
+        title,
+        last_opened: None,
+        favorite: false,
+        author: None,
+        status: ReadingStatus::Unread,
+        archived: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bookshelf_core::LibraryRoot;
+
+    use super::*;
+
+    /// Passes the header and trailer check; nothing in these tests parses it.
+    const PDF_SKELETON: &[u8] = b"%PDF-1.4\n1 0 obj\n<< >>\nendobj\nstartxref\n9\n%%EOF\n";
+
+    fn make_library(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "bookshelf-scan-{name}-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.pdf"), PDF_SKELETON).unwrap();
+        fs::write(root.join("notes.txt"), b"").unwrap();
+        fs::write(root.join("sub").join("b.EPUB"), b"").unwrap();
+        fs::write(root.join("sub").join("c.cbz"), b"").unwrap();
+        root
+    }
+
+    fn settings_for(root: &Path, scan_scope: ScanScope) -> Settings {
+        Settings {
+            library_roots: vec![LibraryRoot::new(root.to_string_lossy())],
+            scan_scope,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn scan_books_respects_scope() -> anyhow::Result<()> {
+        let root = make_library("scope");
+        let titles = |scope| -> anyhow::Result<Vec<String>> {
+            let books = scan_books(&settings_for(&root, scope), &root, None)?;
+            Ok(books.into_iter().map(|b| b.title).collect())
+        };
+        let direct = titles(ScanScope::Direct);
+        let recursive = titles(ScanScope::Recursive);
+        let mut disabled = settings_for(&root, ScanScope::Recursive);
+        disabled.library_roots[0].enabled = false;
+        let disabled = scan_books(&disabled, &root, None);
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(direct?, vec!["a".to_string()]);
+        assert!(disabled?.is_empty());
+        let mut recursive = recursive?;
+        recursive.sort();
+        assert_eq!(
+            recursive,
+            vec!["a".to_string(), "b".to_string(), "c".to_string()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn scan_reads_metadata_for_new_books_only() -> anyhow::Result<()> {
+        let root = make_library("metadata");
+        let known = book_for_path(&root.join("a.pdf")).path;
+        let metadata = ScanMetadata {
+            known_paths: HashSet::from([known]),
+            read: |book| {
+                Some(DocumentMetadata {
+                    title: Some(format!("{} (metadata)", book.title)),
+                    author: Some("A. Author".to_string()),
+                    year: None,
+                })
+            },
+            fingerprinted: HashSet::new(),
+        };
+        let books = scan_books(
+            &settings_for(&root, ScanScope::Recursive),
+            &root,
+            Some(metadata),
+        );
+        let _ = fs::remove_dir_all(&root);
+
+        let mut books: Vec<(String, Option<String>)> =
+            books?.into_iter().map(|b| (b.title, b.author)).collect();
+        books.sort();
+        assert_eq!(
+            books,
+            vec![
+                ("a".to_string(), None),
+                ("b (metadata)".to_string(), Some("A. Author".to_string())),
+                ("c (metadata)".to_string(), Some("A. Author".to_string())),
+            ]
+        );
+        Ok(())
+    }
+
+    /// Everything a background scan sent, batches put together.
+    #[derive(Debug, Default)]
+    struct ScanRun {
+        books: Vec<Book>,
+        fingerprints: Vec<(String, FileFingerprint)>,
+        broken: Vec<(String, String)>,
+        skipped: Vec<(String, String)>,
+        files_seen: usize,
+    }
+
+    fn run_scan(scan: LibraryScan) -> ScanRun {
+        let mut run = ScanRun::default();
+        loop {
+            match scan.events.recv_timeout(std::time::Duration::from_secs(10)) {
+                Ok(ScanEvent::Batch {
+                    books,
+                    fingerprints,
+                    broken,
+                    skipped,
+                    ..
+                }) => {
+                    run.books.extend(books);
+                    run.fingerprints.extend(fingerprints);
+                    run.broken.extend(broken);
+                    run.skipped.extend(skipped);
+                }
+                Ok(ScanEvent::Finished { files_seen, error }) => {
+                    assert_eq!(error, None);
+                    run.files_seen = files_seen;
+                    return run;
+                }
+                Err(err) => panic!("scan did not finish: {err}"),
+            }
+        }
+    }
+
+    #[test]
+    fn background_scan_streams_batches_then_finishes() {
+        let root = make_library("background");
+        let fingerprinted = book_for_path(&root.join("a.pdf")).path;
+        let metadata = ScanMetadata {
+            known_paths: HashSet::new(),
+            read: |_| None,
+            fingerprinted: HashSet::from([fingerprinted.clone()]),
+        };
+        let mut settings = settings_for(&root, ScanScope::Recursive);
+        let wakes = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let wake: Wake = {
+            let wakes = Arc::clone(&wakes);
+            Arc::new(move || {
+                wakes.fetch_add(1, Ordering::Relaxed);
+            })
+        };
+        let run = run_scan(LibraryScan::start(
+            &settings,
+            &root,
+            Some(metadata.clone()),
+            Some(wake),
+        ));
+        settings.fingerprint_books = false;
+        let skipped = run_scan(LibraryScan::start(&settings, &root, Some(metadata), None));
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(run.files_seen, 4);
+        assert_eq!(run.books.len(), 3);
+        assert!(run.broken.is_empty());
+        // Books already fingerprinted are not read again; the other two files are both empty.
+        let fingerprints = run.fingerprints;
+        assert_eq!(fingerprints.len(), 2);
+        assert!(fingerprints.iter().all(|(path, _)| *path != fingerprinted));
+        assert_eq!(fingerprints[0].1.quick_hash, fingerprints[1].1.quick_hash);
+        assert!(fingerprints[0].1.tail_hash.is_some());
+        assert!(skipped.fingerprints.is_empty());
+        // The batches woke the loop before `Finished` was sent.
+        assert!(wakes.load(Ordering::Relaxed) >= 1);
+    }
+
+    #[test]
+    fn broken_pdfs_are_flagged_without_being_read() {
+        let root = make_library("broken");
+        let half = &PDF_SKELETON[..PDF_SKELETON.len() / 2];
+        fs::write(root.join("half.pdf"), half).unwrap();
+        fs::write(root.join("page.pdf"), b"<html>not found</html>").unwrap();
+        let metadata = ScanMetadata {
+            known_paths: HashSet::new(),
+            read: |book| {
+                Some(DocumentMetadata {
+                    title: Some(format!("{} (metadata)", book.title)),
+                    author: None,
+                    year: None,
+                })
+            },
+            fingerprinted: HashSet::new(),
+        };
+        let settings = settings_for(&root, ScanScope::Direct);
+        let run = run_scan(LibraryScan::start(&settings, &root, Some(metadata), None));
+        let _ = fs::remove_dir_all(&root);
+
+        let mut titles: Vec<String> = run.books.into_iter().map(|b| b.title).collect();
+        titles.sort();
+        assert_eq!(titles, vec!["a (metadata)", "half", "page"]);
+        let mut broken: Vec<String> = run
+            .broken
+            .into_iter()
+            .map(|(path, problem)| {
+                let name = Path::new(&path).file_name().unwrap().to_string_lossy();
+                format!("{name}: {problem}")
+            })
+            .collect();
+        broken.sort();
+        assert_eq!(
+            broken,
+            vec![
+                "half.pdf: truncated: no startxref trailer",
+                "page.pdf: not a PDF: no %PDF- header",
+            ]
+        );
+        assert_eq!(run.fingerprints.len(), 1);
+    }
+
+    /// Scans `root` on `workers` threads, returning the titles found, sorted, and the count
+    /// of files seen.
+    fn scan_titles(root: &Path, workers: usize, cancel: &AtomicBool) -> (Vec<String>, usize) {
+        let (tx, rx) = mpsc::channel();
+        let job = FileJob {
+            metadata: None,
+            fingerprint: false,
+        };
+        let mut sink = BatchSink::new(tx, cancel);
+        scan_roots(
+            &[root.to_path_buf()],
+            ScanScope::Recursive,
+            workers,
+            &job,
+            &mut sink,
+        );
+        sink.flush();
+        let files_seen = sink.files_seen;
+        drop(sink);
+        let mut titles: Vec<String> = rx
+            .into_iter()
+            .flat_map(|event| match event {
+                ScanEvent::Batch { books, .. } => books,
+                ScanEvent::Finished { .. } => Vec::new(),
+            })
+            .map(|book| book.title)
+            .collect();
+        titles.sort();
+        (titles, files_seen)
+    }
+
+    /// A tree of `dirs` folders holding `per_dir` PDFs and one other file each.
+    fn make_tree(name: &str, dirs: usize, per_dir: usize) -> PathBuf {
+        let root = make_library(name);
+        for dir in 0..dirs {
+            let dir_path = root.join(format!("d{dir:03}"));
+            fs::create_dir_all(&dir_path).unwrap();
+            for file in 0..per_dir {
+                fs::write(
+                    dir_path.join(format!("{dir:03}-{file:03}.pdf")),
+                    PDF_SKELETON,
+                )
+                .unwrap();
+            }
+            fs::write(dir_path.join("cover.jpg"), b"").unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn workers_find_the_same_books_as_one_thread() {
+        let root = make_tree("workers", 12, 20);
+        let never = AtomicBool::new(false);
+        let serial = scan_titles(&root, 1, &never);
+        let parallel = scan_titles(&root, 4, &never);
+        // A scan cancelled before it starts still returns instead of waiting on its queues.
+        let cancelled = AtomicBool::new(true);
+        let (stopped, _) = scan_titles(&root, 4, &cancelled);
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(serial.0.len(), 3 + 12 * 20);
+        assert_eq!(serial.1, 4 + 12 * 21);
+        assert_eq!(parallel, serial);
+        assert!(stopped.len() < serial.0.len());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_folders_are_reported_and_passed_over() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = make_library("unreadable");
+        let locked = root.join("locked");
+        fs::create_dir_all(&locked).unwrap();
+        fs::write(locked.join("d.pdf"), PDF_SKELETON).unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // Root reads any folder, so there is nothing to skip when the tests run as root.
+        let readable = fs::read_dir(&locked).is_ok();
+        let settings = settings_for(&root, ScanScope::Recursive);
+        let run = run_scan(LibraryScan::start(&settings, &root, None, None));
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        let _ = fs::remove_dir_all(&root);
+
+        if readable {
+            assert_eq!(run.books.len(), 4);
+            assert!(run.skipped.is_empty());
+        } else {
+            assert_eq!(run.books.len(), 3);
+            assert_eq!(run.skipped.len(), 1);
+            assert!(run.skipped[0].0.ends_with("locked"));
+            assert!(run.skipped[0].1.starts_with("read dir:"));
+        }
+    }
+
+    /// Checks that 4 workers scan a large synthetic tree at least twice as fast as one. It
+    /// times real work, so it runs on request, in release, on a machine with 4 cores or more:
+    /// `cargo test -p application --release scan_speedup -- --ignored`.
+    #[test]
+    #[ignore]
+    fn scan_speedup_on_a_synthetic_tree() {
+        let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+        assert!(cores >= 4, "needs 4 cores, found {cores}");
+        let root = make_tree("speedup", 100, 100);
+        let never = AtomicBool::new(false);
+        // The first scan fills the page cache; the best of three runs is timed.
+        let (titles, _) = scan_titles(&root, 1, &never);
+        assert_eq!(titles.len(), 100 * 100);
+        let time = |workers| {
+            (0..3)
+                .map(|_| {
+                    let started = std::time::Instant::now();
+                    scan_titles(&root, workers, &never);
+                    started.elapsed()
+                })
+                .min()
+                .unwrap()
+        };
+        let one = time(1);
+        let four = time(4);
+        let _ = fs::remove_dir_all(&root);
+        assert!(four * 2 <= one, "1 worker: {one:?}, 4 workers: {four:?}");
+    }
+}
@@ -0,0 +1,203 @@
+//! Tags named like `lang/rust` sit in namespaces: `lang/` holds every tag that starts with it,
+//! and selecting it in the tag filter matches all of them. Tags without a slash stand alone.
+
+use std::collections::HashSet;
+
+/// Whether `filter` is a namespace such as `lang/` rather than a single tag.
+pub fn is_tag_namespace(filter: &str) -> bool {
+    filter.ends_with('/')
+}
+
+/// Whether a book tagged `tag` passes the selected `filter`: the same tag ignoring case, or a
+/// tag anywhere under a selected namespace.
+pub fn tag_matches(filter: &str, tag: &str) -> bool {
+    if is_tag_namespace(filter) {
+        tag.len() > filter.len()
+            && tag.is_char_boundary(filter.len())
+            && tag[..filter.len()].eq_ignore_ascii_case(filter)
+    } else {
+        tag.eq_ignore_ascii_case(filter)
+    }
+}
+
+/// The namespaces `tag` sits in, outermost first: `a/b/c` is in `a/` and `a/b/`.
+pub fn tag_namespaces(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices('/')
+        .map(move |(idx, _)| &tag[..=idx])
+        .filter(move |namespace| namespace.len() < tag.len() && namespace.len() > 1)
+}
+
+/// One row of the tag tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagTreeRow {
+    /// The tag, or the namespace with its trailing slash; what selecting the row filters by.
+    pub filter: String,
+    /// The last part of the name, as shown indented under its namespace.
+    pub label: String,
+    /// Namespaces above the row.
+    pub depth: usize,
+}
+
+impl TagTreeRow {
+    pub fn is_namespace(&self) -> bool {
+        is_tag_namespace(&self.filter)
+    }
+}
+
+/// Rows for `tags` in tree order: each namespace comes before what it holds, and rows under a
+/// namespace in `collapsed` (lowercase) are left out. Tags without a slash come out as they
+/// are, in name order.
+pub fn tag_tree(tags: &[String], collapsed: &HashSet<String>) -> Vec<TagTreeRow> {
+    let mut filters: Vec<&str> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            continue;
+        }
+        filters.extend(tag_namespaces(tag));
+        filters.push(tag);
+    }
+    // Splitting on the slash sorts a namespace (`lang/` is `["lang", ""]`) after a flat tag of
+    // the same name and before everything under it.
+    let key = |filter: &str| -> Vec<String> {
+        filter
+            .to_ascii_lowercase()
+            .split('/')
+            .map(str::to_string)
+            .collect()
+    };
+    filters.sort_by_cached_key(|filter| key(filter));
+    filters.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+
+    filters
+        .into_iter()
+        .filter(|filter| {
+            !tag_namespaces(filter)
+                .any(|namespace| collapsed.contains(&namespace.to_ascii_lowercase()))
+        })
+        .map(|filter| {
+            let parent_len = tag_namespaces(filter).last().map_or(0, str::len);
+            TagTreeRow {
+                filter: filter.to_string(),
+                label: filter[parent_len..].to_string(),
+                depth: tag_namespaces(filter).count(),
+            }
+        })
+        .collect()
+}
+
+/// The longest namespace start that every namespace beginning with `typed` shares, when it
+/// adds something to what was typed: with `lang/rust` and `lang/go`, `la` completes to `lang/`.
+pub fn complete_tag_namespace(typed: &str, tags: &[String]) -> Option<String> {
+    let typed = typed.trim();
+    if typed.is_empty() {
+        return None;
+    }
+    let mut common: Option<&str> = None;
+    for namespace in tags.iter().flat_map(|tag| tag_namespaces(tag.trim())) {
+        if namespace.len() <= typed.len()
+            || !namespace.is_char_boundary(typed.len())
+            || !namespace[..typed.len()].eq_ignore_ascii_case(typed)
+        {
+            continue;
+        }
+        common = Some(match common {
+            None => namespace,
+            Some(common) => {
+                let shared = common
+                    .char_indices()
+                    .zip(namespace.chars())
+                    .find(|((_, a), b)| !a.eq_ignore_ascii_case(b))
+                    .map_or(common.len().min(namespace.len()), |((idx, _), _)| idx);
+                &common[..shared]
+            }
+        });
+    }
+    common
+        .filter(|common| common.len() > typed.len())
+        .map(|common| format!("{typed}{}", &common[typed.len()..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn namespaces_match_every_tag_under_them() {
+        assert!(tag_matches("lang/", "Lang/Rust"));
+        assert!(tag_matches("lang/", "lang/sys/c"));
+        assert!(!tag_matches("lang/", "lang"));
+        assert!(!tag_matches("lang/", "language/rust"));
+        assert!(tag_matches("Rust", "rust"));
+        assert!(!tag_matches("lang", "lang/rust"));
+        assert_eq!(
+            tag_namespaces("a/b/c").collect::<Vec<_>>(),
+            vec!["a/", "a/b/"]
+        );
+        assert_eq!(tag_namespaces("/x").count(), 0);
+    }
+
+    #[test]
+    fn tree_groups_tags_under_their_namespaces() {
+        let all = tags(&["topic/networking", "lang/rust", "lang", "draft", "lang/go"]);
+        let rows: Vec<(String, String, usize)> = tag_tree(&all, &HashSet::new())
+            .into_iter()
+            .map(|row| (row.filter, row.label, row.depth))
+            .collect();
+        let expected = [
+            ("draft", "draft", 0),
+            ("lang", "lang", 0),
+            ("lang/", "lang/", 0),
+            ("lang/go", "go", 1),
+            ("lang/rust", "rust", 1),
+            ("topic/", "topic/", 0),
+            ("topic/networking", "networking", 1),
+        ];
+        assert_eq!(
+            rows,
+            expected.map(|(filter, label, depth)| (filter.to_string(), label.to_string(), depth))
+        );
+
+        let collapsed = HashSet::from(["lang/".to_string()]);
+        let shown: Vec<String> = tag_tree(&all, &collapsed)
+            .into_iter()
+            .map(|row| row.filter)
+            .collect();
+        assert_eq!(
+            shown,
+            vec!["draft", "lang", "lang/", "topic/", "topic/networking"]
+        );
+
+        // Flat tags come out as they are.
+        let flat = tags(&["b", "A"]);
+        let rows = tag_tree(&flat, &HashSet::new());
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|row| row.depth == 0 && !row.is_namespace()));
+        assert_eq!(rows[0].label, "A");
+    }
+
+    #[test]
+    fn typing_completes_the_shared_namespace() {
+        let all = tags(&["lang/rust", "lang/go", "lang/sys/c", "law/tax", "topic"]);
+        assert_eq!(complete_tag_namespace("la", &all), None);
+        assert_eq!(
+            complete_tag_namespace("lan", &all),
+            Some("lang/".to_string())
+        );
+        assert_eq!(
+            complete_tag_namespace("Lang/s", &all),
+            Some("Lang/sys/".to_string())
+        );
+        assert_eq!(
+            complete_tag_namespace("lang/", &all),
+            Some("lang/sys/".to_string())
+        );
+        assert_eq!(complete_tag_namespace("lang/r", &all), None);
+        assert_eq!(complete_tag_namespace("top", &all), None);
+        assert_eq!(complete_tag_namespace("", &all), None);
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use anyhow::Context as _;
+use bookshelf_core::{ScanScope, Settings};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use crate::scan::{Wake, is_book_file, resolve_roots};
+
+/// Quiet time after the last change before a burst (e.g. copying a folder of books) is reported.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(1500);
+
+/// Watches the library roots and reports when book files appear, change or go away.
+///
+/// Events are only collected when polled; a burst of changes is reported once, after
+/// `WATCH_DEBOUNCE` without further changes. `settles_at` says when to poll next.
+pub struct LibraryWatcher {
+    // Dropping the watcher stops it.
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    roots: Vec<PathBuf>,
+    scope: ScanScope,
+    debounce: Debounce,
+}
+
+impl LibraryWatcher {
+    /// Watches every existing root, recursively only for a recursive scan scope. `wake`, when
+    /// set, is called from the watcher's thread for every file event.
+    pub fn start(settings: &Settings, cwd: &Path, wake: Option<Wake>) -> anyhow::Result<Self> {
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+            if let Some(wake) = &wake {
+                wake();
+            }
+        })
+        .context("create file watcher")?;
+        let roots: Vec<PathBuf> = resolve_roots(settings, cwd)
+            .into_iter()
+            .filter(|root| root.is_dir())
+            .collect();
+        let mode = match settings.scan_scope {
+            ScanScope::Direct => RecursiveMode::NonRecursive,
+            ScanScope::Recursive => RecursiveMode::Recursive,
+        };
+        for root in &roots {
+            watcher
+                .watch(root, mode)
+                .with_context(|| format!("watch {}", root.display()))?;
+        }
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            roots,
+            scope: settings.scan_scope,
+            debounce: Debounce::default(),
+        })
+    }
+
+    /// Drains pending events. Returns `true` once per settled burst of library changes.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        while let Ok(event) = self.events.try_recv() {
+            // Watcher errors (e.g. a queue overflow) may hide changes; rescan to be safe.
+            let relevant = match event {
+                Ok(event) => {
+                    !matches!(event.kind, EventKind::Access(_))
+                        && event
+                            .paths
+                            .iter()
+                            .any(|path| is_library_change(path, &self.roots, self.scope))
+                }
+                Err(_) => true,
+            };
+            if relevant {
+                self.debounce.record(now);
+            }
+        }
+        self.debounce.settled(now)
+    }
+
+    /// When the burst of changes seen so far will have settled, if there is one.
+    pub fn settles_at(&self) -> Option<Instant> {
+        self.debounce.last_change.map(|at| at + WATCH_DEBOUNCE)
+    }
+}
+
+/// Whether a changed path can affect the library: a book file the scan scope covers, or (for
+/// recursive scans) a directory that may have carried books in or out.
+fn is_library_change(path: &Path, roots: &[PathBuf], scope: ScanScope) -> bool {
+    roots.iter().any(|root| match scope {
+        ScanScope::Direct => path.parent() == Some(root.as_path()) && is_book_file(path),
+        // Removed directories can no longer be checked on disk; any extensionless path counts.
+        ScanScope::Recursive => {
+            path.starts_with(root) && (is_book_file(path) || path.extension().is_none())
+        }
+    })
+}
+
+#[derive(Debug, Default)]
+struct Debounce {
+    last_change: Option<Instant>,
+}
+
+impl Debounce {
+    fn record(&mut self, now: Instant) {
+        self.last_change = Some(now);
+    }
+
+    fn settled(&mut self, now: Instant) -> bool {
+        match self.last_change {
+            Some(at) if now.duration_since(at) >= WATCH_DEBOUNCE => {
+                self.last_change = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn library_changes_follow_scan_scope() {
+        let roots = vec![PathBuf::from("/books")];
+        let direct = |path: &str| is_library_change(Path::new(path), &roots, ScanScope::Direct);
+        let recursive =
+            |path: &str| is_library_change(Path::new(path), &roots, ScanScope::Recursive);
+
+        assert!(direct("/books/new.PDF"));
+        assert!(!direct("/books/sub/new.pdf"));
+        assert!(!direct("/books/notes.txt"));
+        assert!(!direct("/elsewhere/new.pdf"));
+
+        assert!(recursive("/books/sub/new.epub"));
+        assert!(recursive("/books/moved-folder"));
+        assert!(!recursive("/books/sub/.new.pdf.part"));
+        assert!(!recursive("/elsewhere/new.pdf"));
+    }
+
+    #[test]
+    fn debounce_reports_a_burst_once_after_it_settles() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut debounce = Debounce::default();
+
+        assert!(!debounce.settled(at(0)));
+        for ms in (0..5_000).step_by(100) {
+            debounce.record(at(ms));
+            assert!(!debounce.settled(at(ms + 50)));
+        }
+        assert!(!debounce.settled(at(4_900 + 1_000)));
+        assert!(debounce.settled(at(4_900 + 1_500)));
+        assert!(!debounce.settled(at(10_000)));
+    }
+}
@@ -1,5 +1,6 @@
 //! Core domain types for Bookshelf.
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
@@ -99,16 +100,135 @@ pub struct Settings {
     pub reader_text_mode: ReaderTextMode,
     #[serde(default = "default_reader_trim_headers_footers")]
     pub reader_trim_headers_footers: bool,
+    /// Read two-column PDF pages column by column in text mode instead of in content order.
+    pub reader_detect_columns: bool,
+    /// Widest line of wrapped and reflowed text, centered in the reader; 0 uses the full width.
+    pub reader_text_width: u16,
+    /// Spacing of wrapped and reflowed text in tenths of a line: 10 is single, 20 double.
+    /// Wider spacing shows fewer lines per screen on a tall terminal.
+    pub reader_line_spacing: u16,
+    /// Pages sampled, spread across the book, when looking for running headers and footers.
+    pub furniture_sample_pages: u32,
+    /// Share of the sampled pages a line must repeat on to be trimmed as a header or footer.
+    pub furniture_min_fraction: f32,
+    /// Invert page colors in image mode (light text on a dark page).
+    pub reader_invert_colors: bool,
     pub kitty_image_quality: KittyImageQuality,
+    /// Filter that shrinks rendered pages to what the terminal is sent; `Auto` follows
+    /// `kitty_image_quality`.
+    pub downscale_filter: DownscaleFilter,
+    /// How much memory image mode may spend on cached page images.
+    pub image_cache_limit: ImageCacheLimit,
+    /// Send small page images whatever the quality, for terminals reached over a slow link
+    /// such as SSH.
+    pub remote_mode: bool,
+    /// Trim blank page margins in image mode.
+    pub auto_crop: AutoCrop,
+    /// Command that opens a book outside the app; `%f` is the file, `%p` the 1-based page.
+    /// Empty means the platform default (`xdg-open`/`open`).
+    pub external_viewer: String,
+    /// Command that opens a terminal with graphics for the reader fallback; `%e` is this
+    /// executable with the reader's arguments, `%f` the book, `%p` the 1-based page. Empty tries
+    /// kitty, wezterm, ghostty and foot in turn.
+    pub graphics_terminal: String,
+    /// How often the event loop wakes while waiting for input, in milliseconds. Shorter pans
+    /// images more smoothly; longer saves battery. After a while without input it wakes far
+    /// less often either way.
+    pub tick_rate_ms: u32,
+    /// Seconds between saves of unsaved bookmarks, notes, labels and progress while the app
+    /// runs; 0 saves only on quit.
+    pub autosave_secs: u32,
     pub theme: Theme,
     pub scan_scope: ScanScope,
-    pub library_roots: Vec<String>,
+    /// Rescan when files change under the library roots. Off for mounts where watching misbehaves.
+    pub watch_library: bool,
+    /// Fingerprint books found by a library scan, so a book whose file moved keeps its data.
+    /// Off for slow storage, where reading every new file during a scan costs too much.
+    pub fingerprint_books: bool,
+    /// When the last complete library scan finished, in unix seconds.
+    pub last_scan_at: Option<i64>,
+    /// Show reading progress and note/bookmark counts after each book in the library list.
+    pub library_decorations: bool,
+    /// Reopen the most recently read book at its saved page when the app starts.
+    pub resume_last_book: bool,
+    /// Show a column beside reader pages marking the position in the book, with ticks for
+    /// bookmarks and chapter starts.
+    pub reader_minimap: bool,
+    /// Show the library as a grid of covers instead of a list of titles.
+    pub library_grid: bool,
+    /// Take mouse events from the terminal: clicks select rows, the wheel scrolls. Off keeps the
+    /// terminal's own selection and copy.
+    pub mouse_capture: bool,
+    /// Ask before quitting from the library while edits (labels, notes, bookmarks, ...) are still
+    /// unsaved.
+    pub confirm_quit: bool,
+    pub sort_mode: SortMode,
+    pub library_roots: Vec<LibraryRoot>,
+    /// Key binding overrides for the UI: action name → key specs (e.g. `"next_item": ["j"]`).
+    pub key_bindings: BTreeMap<String, Vec<String>>,
 }
 
+/// Choices the settings panel offers for `Settings::furniture_sample_pages`.
+pub const FURNITURE_SAMPLE_PAGE_CHOICES: [u32; 4] = [4, 8, 16, 32];
+/// Choices the settings panel offers for `Settings::furniture_min_fraction`.
+pub const FURNITURE_MIN_FRACTION_CHOICES: [f32; 4] = [0.5, 0.6, 0.75, 0.9];
+/// Choices the settings panel offers for `Settings::tick_rate_ms`.
+pub const TICK_RATE_MS_CHOICES: [u32; 4] = [50, 100, 250, 500];
+/// Choices the settings panel offers for `Settings::autosave_secs`; 0 is off.
+pub const AUTOSAVE_SECS_CHOICES: [u32; 5] = [0, 15, 30, 60, 300];
+/// Narrowest `Settings::reader_text_width` other than full width.
+pub const READER_TEXT_WIDTH_MIN: u16 = 30;
+/// Columns the reader's width keys add or take away.
+pub const READER_TEXT_WIDTH_STEP: u16 = 10;
+/// Choices the reader's zoom keys step `Settings::reader_line_spacing` through in text mode.
+pub const READER_LINE_SPACING_CHOICES: [u16; 3] = [10, 15, 20];
+
 fn default_reader_trim_headers_footers() -> bool {
     true
 }
 
+/// A folder scanned for books. Disabled roots stay in the list but are not scanned or watched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "StoredLibraryRoot")]
+pub struct LibraryRoot {
+    pub path: String,
+    pub enabled: bool,
+}
+
+impl LibraryRoot {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            enabled: true,
+        }
+    }
+}
+
+/// Older settings stored each root as a bare path.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredLibraryRoot {
+    Path(String),
+    Root {
+        path: String,
+        #[serde(default = "default_root_enabled")]
+        enabled: bool,
+    },
+}
+
+fn default_root_enabled() -> bool {
+    true
+}
+
+impl From<StoredLibraryRoot> for LibraryRoot {
+    fn from(stored: StoredLibraryRoot) -> Self {
+        match stored {
+            StoredLibraryRoot::Path(path) => Self::new(path),
+            StoredLibraryRoot::Root { path, enabled } => Self { path, enabled },
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ReaderMode {
@@ -124,6 +244,39 @@ pub enum ReaderTextMode {
     Reflow,
 }
 
+/// How image mode sizes a page at 100% zoom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFit {
+    /// The whole page fits the viewport.
+    Page,
+    /// The page fills the viewport width and scrolls vertically.
+    Width,
+    /// The page fills the viewport height and pans horizontally.
+    Height,
+}
+
+/// How far image mode turns page images, clockwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageRotation {
+    #[default]
+    Upright,
+    Clockwise,
+    UpsideDown,
+    CounterClockwise,
+}
+
+/// Where the reader is with a book; books nobody marked are unread.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadingStatus {
+    #[default]
+    Unread,
+    Reading,
+    Finished,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Theme {
@@ -137,6 +290,63 @@ pub enum KittyImageQuality {
     Fast,
     Balanced,
     Sharp,
+    /// Budgets follow the reader area in pixels; see `ImageViewport`.
+    Auto,
+}
+
+/// `Auto` sends this many pixels per pixel of the reader area, in halves.
+const AUTO_SUPERSAMPLE_HALVES: u64 = 3;
+/// `Auto` renders pages this many times larger than it sends, leaving room to zoom and pan
+/// like the presets do.
+const AUTO_RENDER_HEADROOM: u64 = 4;
+
+/// The area image mode draws pages in: the terminal's cell size in pixels, as the image
+/// protocol reports it, and the area's size in cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageViewport {
+    pub cell_px: (u16, u16),
+    pub cells: (u16, u16),
+}
+
+impl ImageViewport {
+    pub fn pixels(&self) -> u64 {
+        u64::from(self.cell_px.0)
+            * u64::from(self.cell_px.1)
+            * u64::from(self.cells.0)
+            * u64::from(self.cells.1)
+    }
+}
+
+/// Memory ceiling for the page images image mode keeps for flipping back and forth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageCacheLimit {
+    Small,
+    Medium,
+    Large,
+}
+
+/// Resampling filter for shrinking page images, from fastest to sharpest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownscaleFilter {
+    /// Picked by `KittyImageQuality`; see `DownscaleFilter::for_quality`.
+    Auto,
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Lanczos3,
+}
+
+/// How hard image mode trims near-white margins around page content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoCrop {
+    Off,
+    /// Only pure white counts as margin.
+    Light,
+    /// Light gray scanner shadow and specks count as margin too.
+    Aggressive,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -146,6 +356,15 @@ pub enum ScanScope {
     Recursive,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortMode {
+    Title,
+    LastOpened,
+    Added,
+    Size,
+}
+
 impl ReaderMode {
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -165,6 +384,88 @@ impl ReaderTextMode {
     }
 }
 
+impl ImageFit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageFit::Page => "page",
+            ImageFit::Width => "width",
+            ImageFit::Height => "height",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ImageFit::Page => ImageFit::Width,
+            ImageFit::Width => ImageFit::Height,
+            ImageFit::Height => ImageFit::Page,
+        }
+    }
+}
+
+impl PageRotation {
+    pub fn degrees(self) -> u16 {
+        match self {
+            PageRotation::Upright => 0,
+            PageRotation::Clockwise => 90,
+            PageRotation::UpsideDown => 180,
+            PageRotation::CounterClockwise => 270,
+        }
+    }
+
+    /// Nearest quarter turn at or below `degrees`, counting negative angles counterclockwise.
+    pub fn from_degrees(degrees: i64) -> Self {
+        match degrees.rem_euclid(360) / 90 {
+            1 => PageRotation::Clockwise,
+            2 => PageRotation::UpsideDown,
+            3 => PageRotation::CounterClockwise,
+            _ => PageRotation::Upright,
+        }
+    }
+
+    pub fn clockwise(self) -> Self {
+        Self::from_degrees(i64::from(self.degrees()) + 90)
+    }
+
+    pub fn counter_clockwise(self) -> Self {
+        Self::from_degrees(i64::from(self.degrees()) - 90)
+    }
+
+    /// Whether the page's width and height trade places.
+    pub fn is_sideways(self) -> bool {
+        matches!(
+            self,
+            PageRotation::Clockwise | PageRotation::CounterClockwise
+        )
+    }
+}
+
+impl ReadingStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReadingStatus::Unread => "unread",
+            ReadingStatus::Reading => "reading",
+            ReadingStatus::Finished => "finished",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ReadingStatus::Unread => ReadingStatus::Reading,
+            ReadingStatus::Reading => ReadingStatus::Finished,
+            ReadingStatus::Finished => ReadingStatus::Unread,
+        }
+    }
+
+    /// One-cell marker for library rows.
+    pub fn glyph(&self) -> char {
+        match self {
+            ReadingStatus::Unread => '·',
+            ReadingStatus::Reading => '◐',
+            ReadingStatus::Finished => '✓',
+        }
+    }
+}
+
 impl Theme {
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -180,38 +481,244 @@ impl KittyImageQuality {
             KittyImageQuality::Fast => "fast",
             KittyImageQuality::Balanced => "balanced",
             KittyImageQuality::Sharp => "sharp",
+            KittyImageQuality::Auto => "auto",
         }
     }
 
-    pub fn max_transmit_pixels(&self) -> u64 {
+    /// Largest image sent per frame. `Auto` sends the `viewport` supersampled, within the
+    /// Fast and Sharp budgets, and what Balanced sends before the viewport is measured.
+    pub fn max_transmit_pixels(&self, viewport: Option<ImageViewport>) -> u64 {
         match self {
             KittyImageQuality::Fast => 750_000,
             KittyImageQuality::Balanced => 1_250_000,
             KittyImageQuality::Sharp => 2_500_000,
+            KittyImageQuality::Auto => self.auto_budget(viewport, 1, Self::max_transmit_pixels),
+        }
+    }
+
+    /// Transmit cap for sixel, whose payloads are several times larger than kitty's.
+    pub fn max_sixel_pixels(&self, viewport: Option<ImageViewport>) -> u64 {
+        match self {
+            KittyImageQuality::Fast => 300_000,
+            KittyImageQuality::Balanced => 500_000,
+            KittyImageQuality::Sharp => 1_000_000,
+            // The presets send sixel 2/5 of their kitty budget.
+            KittyImageQuality::Auto => self.max_transmit_pixels(viewport) * 2 / 5,
         }
     }
 
-    pub fn max_render_pixels(&self) -> u64 {
+    pub fn max_render_pixels(&self, viewport: Option<ImageViewport>) -> u64 {
         match self {
             KittyImageQuality::Fast => 4_000_000,
             KittyImageQuality::Balanced => 8_000_000,
             KittyImageQuality::Sharp => 12_000_000,
+            KittyImageQuality::Auto => {
+                self.auto_budget(viewport, AUTO_RENDER_HEADROOM, Self::max_render_pixels)
+            }
         }
     }
 
+    /// `Auto`'s budget: `viewport` supersampled `times` over, clamped to what `budget` gives
+    /// Fast and Sharp.
+    fn auto_budget(
+        &self,
+        viewport: Option<ImageViewport>,
+        times: u64,
+        budget: fn(&Self, Option<ImageViewport>) -> u64,
+    ) -> u64 {
+        let Some(viewport) = viewport else {
+            return budget(&KittyImageQuality::Balanced, None);
+        };
+        (viewport.pixels() * AUTO_SUPERSAMPLE_HALVES / 2)
+            .saturating_mul(times)
+            .clamp(
+                budget(&KittyImageQuality::Fast, None),
+                budget(&KittyImageQuality::Sharp, None),
+            )
+    }
+
     pub fn next(&self) -> Self {
         match self {
             KittyImageQuality::Fast => KittyImageQuality::Balanced,
             KittyImageQuality::Balanced => KittyImageQuality::Sharp,
-            KittyImageQuality::Sharp => KittyImageQuality::Fast,
+            KittyImageQuality::Sharp => KittyImageQuality::Auto,
+            KittyImageQuality::Auto => KittyImageQuality::Fast,
         }
     }
 
     pub fn prev(&self) -> Self {
         match self {
-            KittyImageQuality::Fast => KittyImageQuality::Sharp,
+            KittyImageQuality::Fast => KittyImageQuality::Auto,
             KittyImageQuality::Balanced => KittyImageQuality::Fast,
             KittyImageQuality::Sharp => KittyImageQuality::Balanced,
+            KittyImageQuality::Auto => KittyImageQuality::Sharp,
+        }
+    }
+}
+
+impl ImageCacheLimit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageCacheLimit::Small => "small",
+            ImageCacheLimit::Medium => "medium",
+            ImageCacheLimit::Large => "large",
+        }
+    }
+
+    /// Total bytes of decoded pixels the cache may hold. The page on screen is kept even
+    /// when it alone is larger.
+    pub fn max_bytes(&self) -> usize {
+        match self {
+            ImageCacheLimit::Small => 64 << 20,
+            ImageCacheLimit::Medium => 256 << 20,
+            ImageCacheLimit::Large => 1 << 30,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ImageCacheLimit::Small => ImageCacheLimit::Medium,
+            ImageCacheLimit::Medium => ImageCacheLimit::Large,
+            ImageCacheLimit::Large => ImageCacheLimit::Small,
+        }
+    }
+
+    pub fn prev(&self) -> Self {
+        match self {
+            ImageCacheLimit::Small => ImageCacheLimit::Large,
+            ImageCacheLimit::Medium => ImageCacheLimit::Small,
+            ImageCacheLimit::Large => ImageCacheLimit::Medium,
+        }
+    }
+}
+
+impl std::fmt::Display for ImageCacheLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ImageCacheLimit {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "small" => Ok(ImageCacheLimit::Small),
+            "medium" => Ok(ImageCacheLimit::Medium),
+            "large" => Ok(ImageCacheLimit::Large),
+            _ => Err("unknown image cache limit"),
+        }
+    }
+}
+
+impl AutoCrop {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AutoCrop::Off => "off",
+            AutoCrop::Light => "light",
+            AutoCrop::Aggressive => "aggressive",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            AutoCrop::Off => AutoCrop::Light,
+            AutoCrop::Light => AutoCrop::Aggressive,
+            AutoCrop::Aggressive => AutoCrop::Off,
+        }
+    }
+
+    pub fn prev(&self) -> Self {
+        match self {
+            AutoCrop::Off => AutoCrop::Aggressive,
+            AutoCrop::Light => AutoCrop::Off,
+            AutoCrop::Aggressive => AutoCrop::Light,
+        }
+    }
+}
+
+impl DownscaleFilter {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DownscaleFilter::Auto => "auto",
+            DownscaleFilter::Nearest => "nearest",
+            DownscaleFilter::Triangle => "triangle",
+            DownscaleFilter::CatmullRom => "catmullrom",
+            DownscaleFilter::Lanczos3 => "lanczos3",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            DownscaleFilter::Auto => DownscaleFilter::Nearest,
+            DownscaleFilter::Nearest => DownscaleFilter::Triangle,
+            DownscaleFilter::Triangle => DownscaleFilter::CatmullRom,
+            DownscaleFilter::CatmullRom => DownscaleFilter::Lanczos3,
+            DownscaleFilter::Lanczos3 => DownscaleFilter::Auto,
+        }
+    }
+
+    pub fn prev(&self) -> Self {
+        match self {
+            DownscaleFilter::Auto => DownscaleFilter::Lanczos3,
+            DownscaleFilter::Nearest => DownscaleFilter::Auto,
+            DownscaleFilter::Triangle => DownscaleFilter::Nearest,
+            DownscaleFilter::CatmullRom => DownscaleFilter::Triangle,
+            DownscaleFilter::Lanczos3 => DownscaleFilter::CatmullRom,
+        }
+    }
+
+    /// The filter to use at `quality`: `Auto` trades speed for sharpness the way the quality
+    /// does (Fast blurs a little with triangle, Sharp keeps small text crisp with lanczos3);
+    /// any other filter is used as chosen.
+    pub fn for_quality(self, quality: KittyImageQuality) -> Self {
+        match (self, quality) {
+            (DownscaleFilter::Auto, KittyImageQuality::Fast) => DownscaleFilter::Triangle,
+            (DownscaleFilter::Auto, KittyImageQuality::Balanced | KittyImageQuality::Auto) => {
+                DownscaleFilter::CatmullRom
+            }
+            (DownscaleFilter::Auto, KittyImageQuality::Sharp) => DownscaleFilter::Lanczos3,
+            (filter, _) => filter,
+        }
+    }
+}
+
+impl std::fmt::Display for DownscaleFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for DownscaleFilter {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(DownscaleFilter::Auto),
+            "nearest" => Ok(DownscaleFilter::Nearest),
+            "triangle" => Ok(DownscaleFilter::Triangle),
+            "catmullrom" => Ok(DownscaleFilter::CatmullRom),
+            "lanczos3" => Ok(DownscaleFilter::Lanczos3),
+            _ => Err("unknown downscale filter"),
+        }
+    }
+}
+
+impl std::fmt::Display for AutoCrop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for AutoCrop {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(AutoCrop::Off),
+            "light" => Ok(AutoCrop::Light),
+            "aggressive" => Ok(AutoCrop::Aggressive),
+            _ => Err("unknown auto crop"),
         }
     }
 }
@@ -228,6 +735,24 @@ impl std::fmt::Display for ReaderTextMode {
     }
 }
 
+impl std::fmt::Display for ImageFit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::fmt::Display for PageRotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}°", self.degrees())
+    }
+}
+
+impl std::fmt::Display for ReadingStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl std::fmt::Display for Theme {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(self.as_str())
@@ -265,6 +790,32 @@ impl std::str::FromStr for ReaderTextMode {
     }
 }
 
+impl std::str::FromStr for ImageFit {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "page" => Ok(ImageFit::Page),
+            "width" => Ok(ImageFit::Width),
+            "height" => Ok(ImageFit::Height),
+            _ => Err("unknown image fit"),
+        }
+    }
+}
+
+impl std::str::FromStr for ReadingStatus {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "unread" => Ok(ReadingStatus::Unread),
+            "reading" => Ok(ReadingStatus::Reading),
+            "finished" => Ok(ReadingStatus::Finished),
+            _ => Err("unknown reading status"),
+        }
+    }
+}
+
 impl std::str::FromStr for Theme {
     type Err = &'static str;
 
@@ -285,6 +836,7 @@ impl std::str::FromStr for KittyImageQuality {
             "fast" => Ok(KittyImageQuality::Fast),
             "balanced" => Ok(KittyImageQuality::Balanced),
             "sharp" => Ok(KittyImageQuality::Sharp),
+            "auto" => Ok(KittyImageQuality::Auto),
             _ => Err("unknown kitty image quality"),
         }
     }
@@ -317,30 +869,150 @@ impl std::str::FromStr for ScanScope {
     }
 }
 
+impl SortMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortMode::Title => "title",
+            SortMode::LastOpened => "last_opened",
+            SortMode::Added => "added",
+            SortMode::Size => "size",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortMode::Title => "title",
+            SortMode::LastOpened => "recently opened",
+            SortMode::Added => "recently added",
+            SortMode::Size => "size",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            SortMode::Title => SortMode::LastOpened,
+            SortMode::LastOpened => SortMode::Added,
+            SortMode::Added => SortMode::Size,
+            SortMode::Size => SortMode::Title,
+        }
+    }
+}
+
+impl std::fmt::Display for SortMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for SortMode {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "title" => Ok(SortMode::Title),
+            "last_opened" => Ok(SortMode::LastOpened),
+            "added" => Ok(SortMode::Added),
+            "size" => Ok(SortMode::Size),
+            _ => Err("unknown sort mode"),
+        }
+    }
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Self {
             reader_mode: ReaderMode::Text,
             reader_text_mode: ReaderTextMode::Reflow,
             reader_trim_headers_footers: true,
+            reader_detect_columns: true,
+            reader_text_width: 0,
+            reader_line_spacing: 10,
+            furniture_sample_pages: 8,
+            furniture_min_fraction: 0.6,
+            reader_invert_colors: false,
             kitty_image_quality: KittyImageQuality::Balanced,
+            downscale_filter: DownscaleFilter::Auto,
+            image_cache_limit: ImageCacheLimit::Medium,
+            remote_mode: false,
+            auto_crop: AutoCrop::Off,
+            external_viewer: String::new(),
+            graphics_terminal: String::new(),
+            tick_rate_ms: 250,
+            autosave_secs: 30,
             theme: Theme::Dark,
             scan_scope: ScanScope::Recursive,
+            watch_library: true,
+            fingerprint_books: true,
+            last_scan_at: None,
+            library_decorations: true,
+            resume_last_book: false,
+            mouse_capture: true,
+            confirm_quit: true,
+            reader_minimap: true,
+            library_grid: false,
+            sort_mode: SortMode::Title,
             library_roots: Vec::new(),
+            key_bindings: BTreeMap::new(),
         }
     }
 }
 
+/// The choice after (or before) `current`, wrapping around. A value between two choices
+/// moves to the nearer one in that direction.
+fn cycle_choice<T: PartialOrd + Copy>(choices: &[T], current: T, forward: bool) -> T {
+    if forward {
+        choices
+            .iter()
+            .copied()
+            .find(|choice| *choice > current)
+            .unwrap_or(choices[0])
+    } else {
+        choices
+            .iter()
+            .rev()
+            .copied()
+            .find(|choice| *choice < current)
+            .unwrap_or(choices[choices.len() - 1])
+    }
+}
+
 impl Settings {
     pub fn normalize(&mut self) {
-        self.library_roots = self
-            .library_roots
+        for root in &mut self.library_roots {
+            root.path = root.path.trim().to_string();
+        }
+        self.library_roots.retain(|root| !root.path.is_empty());
+        self.library_roots.sort_by(|a, b| a.path.cmp(&b.path));
+        // A path listed twice is scanned when either copy is enabled.
+        self.library_roots.dedup_by(|dup, kept| {
+            let same = dup.path == kept.path;
+            if same {
+                kept.enabled |= dup.enabled;
+            }
+            same
+        });
+        self.external_viewer = self.external_viewer.trim().to_string();
+        self.graphics_terminal = self.graphics_terminal.trim().to_string();
+        self.furniture_sample_pages = self.furniture_sample_pages.clamp(2, 64);
+        self.tick_rate_ms = self.tick_rate_ms.clamp(16, 1000);
+        self.autosave_secs = self.autosave_secs.min(3600);
+        if self.reader_text_width != 0 {
+            self.reader_text_width = self.reader_text_width.max(READER_TEXT_WIDTH_MIN);
+        }
+        self.reader_line_spacing = self.reader_line_spacing.clamp(10, 20);
+        self.furniture_min_fraction = if self.furniture_min_fraction.is_finite() {
+            self.furniture_min_fraction.clamp(0.3, 1.0)
+        } else {
+            0.6
+        };
+    }
+
+    /// Paths of the roots that are scanned and watched.
+    pub fn enabled_roots(&self) -> impl Iterator<Item = &str> {
+        self.library_roots
             .iter()
-            .map(|root| root.trim().to_string())
-            .filter(|root| !root.is_empty())
-            .collect();
-        self.library_roots.sort();
-        self.library_roots.dedup();
+            .filter(|root| root.enabled)
+            .map(|root| root.path.as_str())
     }
 
     pub fn cycle_reader_mode(&mut self) {
@@ -362,6 +1034,73 @@ impl Settings {
         self.reader_trim_headers_footers = !self.reader_trim_headers_footers;
     }
 
+    pub fn toggle_reader_detect_columns(&mut self) {
+        self.reader_detect_columns = !self.reader_detect_columns;
+    }
+
+    /// Widens or narrows the text column by `READER_TEXT_WIDTH_STEP`, starting from `full`
+    /// columns when it uses the full width. Reaching `full` goes back to full width.
+    pub fn step_reader_text_width(&mut self, wider: bool, full: u16) {
+        let current = match self.reader_text_width {
+            0 => full,
+            width => width.min(full),
+        };
+        let next = if wider {
+            current.saturating_add(READER_TEXT_WIDTH_STEP)
+        } else {
+            current
+                .saturating_sub(READER_TEXT_WIDTH_STEP)
+                .max(READER_TEXT_WIDTH_MIN)
+        };
+        self.reader_text_width = if next >= full { 0 } else { next };
+    }
+
+    /// Steps the text line spacing to the next wider or tighter of
+    /// `READER_LINE_SPACING_CHOICES`, stopping at either end.
+    pub fn step_reader_line_spacing(&mut self, wider: bool) {
+        let current = self.reader_line_spacing;
+        self.reader_line_spacing = if wider {
+            READER_LINE_SPACING_CHOICES
+                .into_iter()
+                .find(|spacing| *spacing > current)
+                .unwrap_or(current)
+        } else {
+            READER_LINE_SPACING_CHOICES
+                .into_iter()
+                .rev()
+                .find(|spacing| *spacing < current)
+                .unwrap_or(current)
+        };
+    }
+
+    pub fn toggle_reader_invert_colors(&mut self) {
+        self.reader_invert_colors = !self.reader_invert_colors;
+    }
+
+    pub fn cycle_furniture_sample_pages(&mut self, forward: bool) {
+        self.furniture_sample_pages = cycle_choice(
+            &FURNITURE_SAMPLE_PAGE_CHOICES,
+            self.furniture_sample_pages,
+            forward,
+        );
+    }
+
+    pub fn cycle_tick_rate(&mut self, forward: bool) {
+        self.tick_rate_ms = cycle_choice(&TICK_RATE_MS_CHOICES, self.tick_rate_ms, forward);
+    }
+
+    pub fn cycle_autosave(&mut self, forward: bool) {
+        self.autosave_secs = cycle_choice(&AUTOSAVE_SECS_CHOICES, self.autosave_secs, forward);
+    }
+
+    pub fn cycle_furniture_min_fraction(&mut self, forward: bool) {
+        self.furniture_min_fraction = cycle_choice(
+            &FURNITURE_MIN_FRACTION_CHOICES,
+            self.furniture_min_fraction,
+            forward,
+        );
+    }
+
     pub fn cycle_kitty_image_quality_next(&mut self) {
         self.kitty_image_quality = self.kitty_image_quality.next();
     }
@@ -370,6 +1109,30 @@ impl Settings {
         self.kitty_image_quality = self.kitty_image_quality.prev();
     }
 
+    pub fn cycle_image_cache_limit_next(&mut self) {
+        self.image_cache_limit = self.image_cache_limit.next();
+    }
+
+    pub fn cycle_image_cache_limit_prev(&mut self) {
+        self.image_cache_limit = self.image_cache_limit.prev();
+    }
+
+    pub fn cycle_downscale_filter_next(&mut self) {
+        self.downscale_filter = self.downscale_filter.next();
+    }
+
+    pub fn cycle_downscale_filter_prev(&mut self) {
+        self.downscale_filter = self.downscale_filter.prev();
+    }
+
+    pub fn cycle_auto_crop_next(&mut self) {
+        self.auto_crop = self.auto_crop.next();
+    }
+
+    pub fn cycle_auto_crop_prev(&mut self) {
+        self.auto_crop = self.auto_crop.prev();
+    }
+
     pub fn cycle_theme(&mut self) {
         self.theme = match self.theme {
             Theme::Dark => Theme::Light,
@@ -377,12 +1140,52 @@ impl Settings {
         };
     }
 
+    pub fn toggle_watch_library(&mut self) {
+        self.watch_library = !self.watch_library;
+    }
+
+    pub fn toggle_fingerprint_books(&mut self) {
+        self.fingerprint_books = !self.fingerprint_books;
+    }
+
+    pub fn toggle_library_decorations(&mut self) {
+        self.library_decorations = !self.library_decorations;
+    }
+
+    pub fn toggle_resume_last_book(&mut self) {
+        self.resume_last_book = !self.resume_last_book;
+    }
+
+    pub fn toggle_mouse_capture(&mut self) {
+        self.mouse_capture = !self.mouse_capture;
+    }
+
+    pub fn toggle_confirm_quit(&mut self) {
+        self.confirm_quit = !self.confirm_quit;
+    }
+
+    pub fn toggle_reader_minimap(&mut self) {
+        self.reader_minimap = !self.reader_minimap;
+    }
+
+    pub fn toggle_library_grid(&mut self) {
+        self.library_grid = !self.library_grid;
+    }
+
+    pub fn toggle_remote_mode(&mut self) {
+        self.remote_mode = !self.remote_mode;
+    }
+
     pub fn cycle_scan_scope(&mut self) {
         self.scan_scope = match self.scan_scope {
             ScanScope::Direct => ScanScope::Recursive,
             ScanScope::Recursive => ScanScope::Direct,
         };
     }
+
+    pub fn cycle_sort_mode(&mut self) {
+        self.sort_mode = self.sort_mode.next();
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -392,19 +1195,66 @@ pub struct Book {
     pub last_opened: Option<i64>,
     #[serde(default)]
     pub favorite: bool,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub status: ReadingStatus,
+    /// Hidden from the library unless archived books are included; rescans keep the flag.
+    #[serde(default)]
+    pub archived: bool,
+}
+
+impl Book {
+    /// Takes the metadata title (when there is a usable one) and author.
+    pub fn apply_metadata(&mut self, metadata: &DocumentMetadata) {
+        if let Some(title) = &metadata.title {
+            self.title = title.clone();
+        }
+        self.author = metadata.author.clone();
+    }
+}
+
+/// Title, author and year read from a document's own metadata; unusable values are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Publication year, or the year the file was made when that is all there is.
+    pub year: Option<i32>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TagKind {
     Tag,
     Collection,
+    Author,
+    Series,
 }
 
 impl TagKind {
+    pub const ALL: [TagKind; 4] = [
+        TagKind::Collection,
+        TagKind::Tag,
+        TagKind::Author,
+        TagKind::Series,
+    ];
+
     pub fn as_str(self) -> &'static str {
         match self {
             TagKind::Tag => "tag",
             TagKind::Collection => "collection",
+            TagKind::Author => "author",
+            TagKind::Series => "series",
+        }
+    }
+
+    /// Capitalized name, for titles and prompts.
+    pub fn title(self) -> &'static str {
+        match self {
+            TagKind::Tag => "Tag",
+            TagKind::Collection => "Collection",
+            TagKind::Author => "Author",
+            TagKind::Series => "Series",
         }
     }
 }
@@ -422,6 +1272,8 @@ impl std::str::FromStr for TagKind {
         match s.trim().to_ascii_lowercase().as_str() {
             "tag" => Ok(TagKind::Tag),
             "collection" => Ok(TagKind::Collection),
+            "author" => Ok(TagKind::Author),
+            "series" => Ok(TagKind::Series),
             _ => Err("unknown TagKind"),
         }
     }
@@ -433,6 +1285,12 @@ pub struct BookLabels {
     pub tags: Vec<String>,
     #[serde(default)]
     pub collection: Option<String>,
+    /// Author set by hand; shown and searched in place of the one read from the document.
+    #[serde(default)]
+    pub author: Option<String>,
+    /// Series name and the book's number in it.
+    #[serde(default)]
+    pub series: Option<(String, Option<u32>)>,
 }
 
 impl BookLabels {
@@ -444,14 +1302,107 @@ impl BookLabels {
             .filter(|t| !t.is_empty())
             .map(ToString::to_string)
             .collect();
-        self.tags
-            .sort_by(|a, b| a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase()));
+        self.tags.sort_by_key(|a| a.to_ascii_lowercase());
         self.tags.dedup();
 
         self.collection = self.collection.as_ref().map(|c| c.trim().to_string());
         if self.collection.as_deref().is_some_and(|c| c.is_empty()) {
             self.collection = None;
         }
+
+        self.author = self
+            .author
+            .as_deref()
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .map(ToString::to_string);
+        self.series = self
+            .series
+            .take()
+            .map(|(name, number)| (name.trim().to_string(), number))
+            .filter(|(name, _)| !name.is_empty());
+    }
+
+    /// No tags, collection, author or series.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+            && self.collection.is_none()
+            && self.author.is_none()
+            && self.series.is_none()
+    }
+
+    pub fn series_name(&self) -> Option<&str> {
+        self.series.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    pub fn series_number(&self) -> Option<u32> {
+        self.series.as_ref().and_then(|(_, number)| *number)
+    }
+
+    /// Whether the book carries the catalog label `name` of `kind`, ignoring case.
+    pub fn carries(&self, kind: TagKind, name: &str) -> bool {
+        let single = match kind {
+            TagKind::Tag => return self.tags.iter().any(|t| t.eq_ignore_ascii_case(name)),
+            TagKind::Collection => self.collection.as_deref(),
+            TagKind::Author => self.author.as_deref(),
+            TagKind::Series => self.series_name(),
+        };
+        single.is_some_and(|value| value.eq_ignore_ascii_case(name))
+    }
+
+    /// Renames the catalog label `from` of `kind` to `to`; a series keeps its number.
+    pub fn rename_label(&mut self, kind: TagKind, from: &str, to: &str) {
+        if !self.carries(kind, from) {
+            return;
+        }
+        match kind {
+            TagKind::Tag => {
+                for tag in &mut self.tags {
+                    if tag.eq_ignore_ascii_case(from) {
+                        *tag = to.to_string();
+                    }
+                }
+            }
+            TagKind::Collection => self.collection = Some(to.to_string()),
+            TagKind::Author => self.author = Some(to.to_string()),
+            TagKind::Series => {
+                if let Some((name, _)) = &mut self.series {
+                    *name = to.to_string();
+                }
+            }
+        }
+    }
+
+    /// Takes the catalog label `name` of `kind` off the book.
+    pub fn remove_label(&mut self, kind: TagKind, name: &str) {
+        if !self.carries(kind, name) {
+            return;
+        }
+        match kind {
+            TagKind::Tag => self.tags.retain(|t| !t.eq_ignore_ascii_case(name)),
+            TagKind::Collection => self.collection = None,
+            TagKind::Author => self.author = None,
+            TagKind::Series => self.series = None,
+        }
+    }
+}
+
+/// `Name #3`, or just `Name` without a number.
+pub fn format_series(name: &str, number: Option<u32>) -> String {
+    match number {
+        Some(number) => format!("{name} #{number}"),
+        None => name.to_string(),
+    }
+}
+
+/// Reads `Name #3` or `Name`. The number is `None` when missing or not a number; the name is
+/// trimmed and may be empty (`#3` alone).
+pub fn parse_series(input: &str) -> (String, Option<u32>) {
+    match input.rsplit_once('#') {
+        Some((name, number)) if number.trim().parse::<u32>().is_ok() => {
+            (name.trim().to_string(), number.trim().parse().ok())
+        }
+        _ => (input.trim().to_string(), None),
     }
 }
 
@@ -460,6 +1411,23 @@ pub struct Bookmark {
     pub page: u32,
     #[serde(default)]
     pub label: String,
+    /// Where in the page the bookmark was made; `None` opens the page at its top, as
+    /// bookmarks made before this was kept do.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub view: Option<BookmarkView>,
+}
+
+/// How a bookmarked page was being viewed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookmarkView {
+    /// Text mode, scrolled this many lines of text down, not counting line spacing.
+    Text { scroll: u16 },
+    /// Image mode at this zoom, panned this far into the zoomed render, in pixels.
+    Image {
+        zoom_percent: u16,
+        pan_px: (u32, u32),
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -475,6 +1443,182 @@ pub struct TocItem {
     pub depth: usize,
 }
 
+/// Per-book reader view, restored when the book is reopened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReaderViewState {
+    pub reader_mode: ReaderMode,
+    pub reader_text_mode: ReaderTextMode,
+    pub image_zoom_percent: u16,
+    #[serde(default = "default_image_fit")]
+    pub image_fit: ImageFit,
+    /// Turn applied to every page of the book in image mode, for sideways or upside-down scans.
+    #[serde(default)]
+    pub rotation: PageRotation,
+}
+
+fn default_image_fit() -> ImageFit {
+    ImageFit::Page
+}
+
+/// Saved reading position: the page plus where the reader was within it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookProgress {
+    /// 1-based page.
+    pub last_page: u32,
+    /// Text-mode scroll offset in lines of text, not counting line spacing; `None` for
+    /// positions saved before it was tracked.
+    pub scroll: Option<u16>,
+    /// Image-mode pan offset in pixels of the zoomed page render.
+    pub pan_px: Option<(u32, u32)>,
+}
+
+impl BookProgress {
+    pub fn at_page(last_page: u32) -> Self {
+        Self {
+            last_page,
+            scroll: None,
+            pan_px: None,
+        }
+    }
+}
+
+/// What a book's file holds. PDFs without text on their first pages are taken to be scans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BookKind {
+    TextPdf,
+    ScannedPdf,
+    Epub,
+    Comic,
+}
+
+impl BookKind {
+    pub const ALL: [BookKind; 4] = [
+        BookKind::TextPdf,
+        BookKind::ScannedPdf,
+        BookKind::Epub,
+        BookKind::Comic,
+    ];
+
+    /// The stored name, also shown as the library row badge.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookKind::TextPdf => "pdf",
+            BookKind::ScannedPdf => "scan",
+            BookKind::Epub => "epub",
+            BookKind::Comic => "comic",
+        }
+    }
+
+    /// For the details pane.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            BookKind::TextPdf => "PDF with text",
+            BookKind::ScannedPdf => "scanned PDF (no text)",
+            BookKind::Epub => "EPUB",
+            BookKind::Comic => "comic archive",
+        }
+    }
+}
+
+impl std::fmt::Display for BookKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for BookKind {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        BookKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str().eq_ignore_ascii_case(value.trim()))
+            .ok_or("unknown book kind")
+    }
+}
+
+/// Page count read from a book's file, with the file's size and modification time when it was
+/// counted. A file that has changed since is counted again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageCount {
+    pub pages: u32,
+    pub size_bytes: u64,
+    /// Seconds since the Unix epoch.
+    pub modified_secs: i64,
+    /// Found along with the count; `None` for counts taken before kinds were kept, and when the
+    /// kind could not be told.
+    pub kind: Option<BookKind>,
+}
+
+impl PageCount {
+    /// Whether the count was taken from the file as it is now.
+    pub fn is_fresh(&self, size_bytes: u64, modified_secs: i64) -> bool {
+        self.size_bytes == size_bytes && self.modified_secs == modified_secs
+    }
+}
+
+/// Content hashes of a book's file, used to spot the same document under different paths:
+/// duplicates by content, and books whose file moved. Taken at the file's size and
+/// modification time; a file that has changed is hashed again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFingerprint {
+    pub size_bytes: u64,
+    /// Seconds since the Unix epoch.
+    pub modified_secs: i64,
+    /// Hex SHA-256 of the first 64 KiB.
+    pub quick_hash: String,
+    /// Hex SHA-256 of the last 64 KiB; the quick hash again for files no longer than that.
+    /// `None` for fingerprints taken before tails were hashed.
+    pub tail_hash: Option<String>,
+    /// Hex SHA-256 of the whole file; only taken once another file shares the quick hash.
+    pub full_hash: Option<String>,
+}
+
+impl FileFingerprint {
+    /// Whether the hashes were taken from the file as it is now.
+    pub fn is_fresh(&self, size_bytes: u64, modified_secs: i64) -> bool {
+        self.size_bytes == size_bytes && self.modified_secs == modified_secs
+    }
+}
+
+/// Outcome of a database cleanup.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CleanupReport {
+    /// Books removed because their file no longer exists.
+    pub removed_books: Vec<String>,
+    /// Progress, reader state, bookmark, note and label rows left behind by deleted books.
+    pub orphaned_rows: usize,
+    /// Tags and collections no book used.
+    pub removed_tags: Vec<(TagKind, String)>,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+impl CleanupReport {
+    pub fn freed_bytes(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
+
+/// Outcome of importing a labels file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelImportReport {
+    /// Books in the file that are in the local library and were merged.
+    pub merged_books: usize,
+    /// Books in the file that are not in the local library.
+    pub skipped_books: usize,
+}
+
+/// Outcome of importing a settings file.
+#[derive(Debug, Clone)]
+pub struct SettingsImport {
+    /// The settings now in effect: the file's, with defaults for fields it left out or held
+    /// values that did not parse.
+    pub settings: Settings,
+    /// Fields the file held values for that did not parse, by name.
+    pub rejected: Vec<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Progress {
     pub current_page: u32,
@@ -542,6 +1686,55 @@ mod tests {
         assert!("nope".parse::<ReaderTextMode>().is_err());
     }
 
+    #[test]
+    fn image_fit_parses_and_cycles() {
+        assert_eq!(" Width ".parse::<ImageFit>().unwrap(), ImageFit::Width);
+        assert!("nope".parse::<ImageFit>().is_err());
+        let mut fit = ImageFit::Page;
+        for _ in 0..3 {
+            fit = fit.next();
+            assert_eq!(fit.as_str().parse::<ImageFit>().unwrap(), fit);
+        }
+        assert_eq!(fit, ImageFit::Page);
+    }
+
+    #[test]
+    fn page_rotation_turns_both_ways() {
+        let mut rotation = PageRotation::Upright;
+        for expected in [90, 180, 270, 0] {
+            rotation = rotation.clockwise();
+            assert_eq!(rotation.degrees(), expected);
+            assert_eq!(PageRotation::from_degrees(i64::from(expected)), rotation);
+        }
+        assert_eq!(
+            PageRotation::Upright.counter_clockwise(),
+            PageRotation::CounterClockwise
+        );
+        assert_eq!(
+            PageRotation::from_degrees(-90),
+            PageRotation::CounterClockwise
+        );
+        assert_eq!(PageRotation::from_degrees(450), PageRotation::Clockwise);
+        assert!(PageRotation::Clockwise.is_sideways());
+        assert!(!PageRotation::UpsideDown.is_sideways());
+        assert_eq!(PageRotation::UpsideDown.to_string(), "180°");
+    }
+
+    #[test]
+    fn reading_status_parses_and_cycles() {
+        assert_eq!(
+            " Finished ".parse::<ReadingStatus>().unwrap(),
+            ReadingStatus::Finished
+        );
+        assert!("done".parse::<ReadingStatus>().is_err());
+        let mut status = ReadingStatus::default();
+        for _ in 0..3 {
+            status = status.next();
+            assert_eq!(status.as_str().parse::<ReadingStatus>().unwrap(), status);
+        }
+        assert_eq!(status, ReadingStatus::Unread);
+    }
+
     #[test]
     fn scan_scope_parses_strings() {
         assert_eq!("direct".parse::<ScanScope>().unwrap(), ScanScope::Direct);
@@ -552,6 +1745,31 @@ mod tests {
         assert!("nope".parse::<ScanScope>().is_err());
     }
 
+    #[test]
+    fn sort_mode_parses_strings() {
+        assert_eq!("title".parse::<SortMode>().unwrap(), SortMode::Title);
+        assert_eq!(
+            " Last_Opened ".parse::<SortMode>().unwrap(),
+            SortMode::LastOpened
+        );
+        assert_eq!("size".parse::<SortMode>().unwrap(), SortMode::Size);
+        assert!("nope".parse::<SortMode>().is_err());
+    }
+
+    #[test]
+    fn sort_mode_cycles_through_all_modes() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.sort_mode, SortMode::Title);
+        settings.cycle_sort_mode();
+        assert_eq!(settings.sort_mode, SortMode::LastOpened);
+        settings.cycle_sort_mode();
+        assert_eq!(settings.sort_mode, SortMode::Added);
+        settings.cycle_sort_mode();
+        assert_eq!(settings.sort_mode, SortMode::Size);
+        settings.cycle_sort_mode();
+        assert_eq!(settings.sort_mode, SortMode::Title);
+    }
+
     #[test]
     fn theme_parses_strings() {
         assert_eq!("dark".parse::<Theme>().unwrap(), Theme::Dark);
@@ -565,21 +1783,161 @@ mod tests {
             reader_mode: ReaderMode::Text,
             reader_text_mode: ReaderTextMode::Reflow,
             reader_trim_headers_footers: true,
+            reader_detect_columns: true,
+            reader_text_width: 0,
+            reader_line_spacing: 10,
+            furniture_sample_pages: 8,
+            furniture_min_fraction: 0.6,
+            reader_invert_colors: false,
             kitty_image_quality: KittyImageQuality::Balanced,
+            downscale_filter: DownscaleFilter::Auto,
+            image_cache_limit: ImageCacheLimit::Medium,
+            remote_mode: false,
+            auto_crop: AutoCrop::Off,
+            external_viewer: String::new(),
+            graphics_terminal: String::new(),
+            tick_rate_ms: 250,
+            autosave_secs: 30,
             theme: Theme::Dark,
             scan_scope: ScanScope::Direct,
+            watch_library: true,
+            fingerprint_books: true,
+            last_scan_at: None,
+            library_decorations: true,
+            resume_last_book: false,
+            mouse_capture: true,
+            confirm_quit: true,
+            reader_minimap: true,
+            library_grid: false,
+            sort_mode: SortMode::Title,
             library_roots: vec![
-                " ".to_string(),
-                "/a".to_string(),
-                "/a".to_string(),
-                " /b ".to_string(),
+                LibraryRoot::new(" "),
+                LibraryRoot {
+                    path: "/a".to_string(),
+                    enabled: false,
+                },
+                LibraryRoot::new("/a"),
+                LibraryRoot {
+                    path: " /b ".to_string(),
+                    enabled: false,
+                },
             ],
+            key_bindings: BTreeMap::new(),
         };
         settings.normalize();
         assert_eq!(
             settings.library_roots,
-            vec!["/a".to_string(), "/b".to_string()]
+            vec![
+                LibraryRoot::new("/a"),
+                LibraryRoot {
+                    path: "/b".to_string(),
+                    enabled: false,
+                },
+            ]
         );
+        assert_eq!(settings.enabled_roots().collect::<Vec<_>>(), vec!["/a"]);
+    }
+
+    #[test]
+    fn furniture_thresholds_cycle_through_choices() {
+        let mut settings = Settings {
+            furniture_sample_pages: 1000,
+            furniture_min_fraction: f32::NAN,
+            ..Settings::default()
+        };
+        settings.normalize();
+        assert_eq!(settings.furniture_sample_pages, 64);
+        assert_eq!(settings.furniture_min_fraction, 0.6);
+
+        settings.cycle_furniture_sample_pages(true);
+        assert_eq!(settings.furniture_sample_pages, 4);
+        settings.furniture_sample_pages = 10;
+        settings.cycle_furniture_sample_pages(false);
+        assert_eq!(settings.furniture_sample_pages, 8);
+        settings.cycle_furniture_min_fraction(true);
+        assert_eq!(settings.furniture_min_fraction, 0.75);
+        settings.furniture_min_fraction = 0.5;
+        settings.cycle_furniture_min_fraction(false);
+        assert_eq!(settings.furniture_min_fraction, 0.9);
+    }
+
+    #[test]
+    fn tick_rate_is_clamped_and_cycles() {
+        let mut settings = Settings {
+            tick_rate_ms: 0,
+            ..Settings::default()
+        };
+        settings.normalize();
+        assert_eq!(settings.tick_rate_ms, 16);
+        settings.cycle_tick_rate(true);
+        assert_eq!(settings.tick_rate_ms, 50);
+        settings.tick_rate_ms = 250;
+        settings.cycle_tick_rate(true);
+        assert_eq!(settings.tick_rate_ms, 500);
+        settings.cycle_tick_rate(false);
+        assert_eq!(settings.tick_rate_ms, 250);
+    }
+
+    #[test]
+    fn autosave_interval_cycles_through_off() {
+        let mut settings = Settings {
+            autosave_secs: 90_000,
+            ..Settings::default()
+        };
+        settings.normalize();
+        assert_eq!(settings.autosave_secs, 3600);
+        settings.cycle_autosave(true);
+        assert_eq!(settings.autosave_secs, 0);
+        settings.cycle_autosave(false);
+        assert_eq!(settings.autosave_secs, 300);
+        settings.autosave_secs = 30;
+        settings.cycle_autosave(true);
+        assert_eq!(settings.autosave_secs, 60);
+    }
+
+    #[test]
+    fn reader_line_spacing_steps_through_the_choices() {
+        let mut settings = Settings::default();
+        settings.step_reader_line_spacing(false);
+        assert_eq!(settings.reader_line_spacing, 10);
+        settings.step_reader_line_spacing(true);
+        assert_eq!(settings.reader_line_spacing, 15);
+        settings.step_reader_line_spacing(true);
+        settings.step_reader_line_spacing(true);
+        assert_eq!(settings.reader_line_spacing, 20);
+        settings.step_reader_line_spacing(false);
+        assert_eq!(settings.reader_line_spacing, 15);
+
+        // A spacing between the choices steps to its neighbours.
+        settings.reader_line_spacing = 12;
+        settings.step_reader_line_spacing(true);
+        assert_eq!(settings.reader_line_spacing, 15);
+        settings.reader_line_spacing = 40;
+        settings.normalize();
+        assert_eq!(settings.reader_line_spacing, 20);
+    }
+
+    #[test]
+    fn reader_text_width_steps_between_min_and_full() {
+        let mut settings = Settings::default();
+        settings.step_reader_text_width(false, 120);
+        assert_eq!(settings.reader_text_width, 110);
+        settings.step_reader_text_width(true, 120);
+        assert_eq!(settings.reader_text_width, 0);
+
+        settings.reader_text_width = 35;
+        settings.step_reader_text_width(false, 120);
+        assert_eq!(settings.reader_text_width, READER_TEXT_WIDTH_MIN);
+        // A window narrower than the setting widens from what is shown.
+        settings.reader_text_width = 90;
+        settings.step_reader_text_width(true, 60);
+        assert_eq!(settings.reader_text_width, 0);
+        settings.step_reader_text_width(false, 25);
+        assert_eq!(settings.reader_text_width, 0);
+
+        settings.reader_text_width = 5;
+        settings.normalize();
+        assert_eq!(settings.reader_text_width, READER_TEXT_WIDTH_MIN);
     }
 
     #[test]
@@ -593,6 +1951,92 @@ mod tests {
             KittyImageQuality::Sharp
         );
         assert!("nope".parse::<KittyImageQuality>().is_err());
+        assert_eq!(
+            "auto".parse::<KittyImageQuality>().unwrap(),
+            KittyImageQuality::Auto
+        );
+    }
+
+    #[test]
+    fn downscale_filter_defaults_follow_the_quality() {
+        assert_eq!(
+            DownscaleFilter::Auto.for_quality(KittyImageQuality::Fast),
+            DownscaleFilter::Triangle
+        );
+        assert_eq!(
+            DownscaleFilter::Auto.for_quality(KittyImageQuality::Sharp),
+            DownscaleFilter::Lanczos3
+        );
+        assert_eq!(
+            DownscaleFilter::Nearest.for_quality(KittyImageQuality::Sharp),
+            DownscaleFilter::Nearest
+        );
+        let mut filter = DownscaleFilter::Auto;
+        for _ in 0..5 {
+            filter = filter.next();
+            assert_eq!(filter.as_str().parse::<DownscaleFilter>(), Ok(filter));
+            assert_eq!(filter.next().prev(), filter);
+        }
+        assert_eq!(filter, DownscaleFilter::Auto);
+        assert!("bicubic".parse::<DownscaleFilter>().is_err());
+    }
+
+    #[test]
+    fn auto_image_quality_follows_the_viewport() {
+        let auto = KittyImageQuality::Auto;
+        let viewport = |cell_px, cells| {
+            Some(ImageViewport {
+                cell_px,
+                cells: (cells, cells / 3),
+            })
+        };
+        // 90x30 cells of 10x20 px: 540k px on screen.
+        let small = viewport((10, 20), 90);
+        assert_eq!(auto.max_transmit_pixels(small), 810_000);
+        assert_eq!(auto.max_sixel_pixels(small), 324_000);
+        assert_eq!(auto.max_render_pixels(small), 4_000_000);
+        // Tiny cells and areas get the Fast floor, HiDPI ones the Sharp ceiling.
+        let tiny = viewport((4, 8), 30);
+        assert_eq!(auto.max_transmit_pixels(tiny), 750_000);
+        let large = viewport((20, 40), 300);
+        assert_eq!(auto.max_transmit_pixels(large), 2_500_000);
+        assert_eq!(auto.max_render_pixels(large), 12_000_000);
+        assert_eq!(auto.max_transmit_pixels(None), 1_250_000);
+        // The presets ignore the viewport.
+        assert_eq!(
+            KittyImageQuality::Fast.max_transmit_pixels(large),
+            KittyImageQuality::Fast.max_transmit_pixels(None)
+        );
+        assert_eq!(KittyImageQuality::Sharp.next(), auto);
+        assert_eq!(auto.next(), KittyImageQuality::Fast);
+    }
+
+    #[test]
+    fn series_parses_name_and_number() {
+        assert_eq!(
+            parse_series(" Discworld #3 "),
+            ("Discworld".to_string(), Some(3))
+        );
+        assert_eq!(parse_series("Dune"), ("Dune".to_string(), None));
+        assert_eq!(parse_series("#12"), (String::new(), Some(12)));
+        assert_eq!(parse_series("C# Notes"), ("C# Notes".to_string(), None));
+        assert_eq!(format_series("Discworld", Some(3)), "Discworld #3");
+
+        let mut labels = BookLabels {
+            author: Some("  ".to_string()),
+            series: Some((" Dune ".to_string(), Some(1))),
+            ..BookLabels::default()
+        };
+        labels.normalize();
+        assert_eq!(labels.author, None);
+        assert_eq!(labels.series_name(), Some("Dune"));
+        assert!(!labels.is_empty());
+
+        labels.rename_label(TagKind::Series, "DUNE", "Dune Saga");
+        assert_eq!(labels.series, Some(("Dune Saga".to_string(), Some(1))));
+        assert!(labels.carries(TagKind::Series, "dune saga"));
+        labels.remove_label(TagKind::Series, "Dune Saga");
+        assert!(labels.is_empty());
     }
 
     #[test]
@@ -0,0 +1,166 @@
+//! Annotations other readers left on PDF pages: highlights, sticky notes and the like.
+
+use std::path::Path;
+
+use pdf::file::FileOptions;
+use pdf::object::{Page, Resolve};
+
+use crate::columns::TextRun;
+use crate::links::{LinkRect, text_in_rect};
+
+/// What an annotation is, from its `/Subtype`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PdfAnnotationKind {
+    Highlight,
+    Underline,
+    StrikeOut,
+    Squiggly,
+    /// A sticky note (`/Text`).
+    Note,
+    FreeText,
+    Ink,
+    /// Any other subtype, by name.
+    Other(String),
+}
+
+impl PdfAnnotationKind {
+    fn from_subtype(subtype: &str) -> Self {
+        match subtype {
+            "Highlight" => PdfAnnotationKind::Highlight,
+            "Underline" => PdfAnnotationKind::Underline,
+            "StrikeOut" => PdfAnnotationKind::StrikeOut,
+            "Squiggly" => PdfAnnotationKind::Squiggly,
+            "Text" => PdfAnnotationKind::Note,
+            "FreeText" => PdfAnnotationKind::FreeText,
+            "Ink" => PdfAnnotationKind::Ink,
+            other => PdfAnnotationKind::Other(other.to_string()),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            PdfAnnotationKind::Highlight => "highlight",
+            PdfAnnotationKind::Underline => "underline",
+            PdfAnnotationKind::StrikeOut => "strikeout",
+            PdfAnnotationKind::Squiggly => "squiggly",
+            PdfAnnotationKind::Note => "note",
+            PdfAnnotationKind::FreeText => "text box",
+            PdfAnnotationKind::Ink => "ink",
+            PdfAnnotationKind::Other(name) => name,
+        }
+    }
+
+    /// Markup over text, whose words say what was marked when the annotation has no comment.
+    fn marks_text(&self) -> bool {
+        matches!(
+            self,
+            PdfAnnotationKind::Highlight
+                | PdfAnnotationKind::Underline
+                | PdfAnnotationKind::StrikeOut
+                | PdfAnnotationKind::Squiggly
+        )
+    }
+}
+
+/// An annotation on a PDF page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfAnnotation {
+    /// 1-based, like `PageLink::target_page`.
+    pub page: u32,
+    pub kind: PdfAnnotationKind,
+    /// The annotation's `/Contents` on one line, else the words under a highlight or other
+    /// text markup; empty when there is neither.
+    pub contents: String,
+    pub rect: LinkRect,
+}
+
+/// Subtypes that are not someone's annotation: links are listed as links, popups repeat the
+/// annotation they belong to, and widgets are form fields.
+const SKIPPED_SUBTYPES: &[&str] = &["Link", "Popup", "Widget"];
+
+/// Annotations on one PDF page, top to bottom.
+pub(crate) fn pdf_page_annotations(
+    path: &Path,
+    page_index: u32,
+) -> anyhow::Result<Vec<PdfAnnotation>> {
+    let file = FileOptions::cached().open(path)?;
+    let resolver = file.resolver();
+    let page = file.get_page(page_index)?;
+    annotations_on(&page, page_index.saturating_add(1), &resolver)
+}
+
+/// Annotations in the whole PDF, by page and top to bottom on each.
+pub(crate) fn pdf_annotations(path: &Path) -> anyhow::Result<Vec<PdfAnnotation>> {
+    let file = FileOptions::cached().open(path)?;
+    let resolver = file.resolver();
+    let mut out = Vec::new();
+    for (idx, page) in file.pages().enumerate() {
+        let page_number = u32::try_from(idx + 1).unwrap_or(u32::MAX);
+        let page = page?;
+        out.extend(annotations_on(&page, page_number, &resolver)?);
+    }
+    Ok(out)
+}
+
+fn annotations_on(
+    page: &Page,
+    page_number: u32,
+    resolver: &impl Resolve,
+) -> anyhow::Result<Vec<PdfAnnotation>> {
+    let annots = page.annotations.load(resolver)?;
+    let mut text: Option<(String, Vec<TextRun>)> = None;
+    let mut out = Vec::new();
+    for annot in annots.iter() {
+        let subtype = annot.subtype.as_str();
+        if SKIPPED_SUBTYPES.contains(&subtype) {
+            continue;
+        }
+        let Some(rect) = annot.rect else {
+            continue;
+        };
+        let rect = LinkRect {
+            left: rect.left.min(rect.right),
+            bottom: rect.bottom.min(rect.top),
+            right: rect.left.max(rect.right),
+            top: rect.bottom.max(rect.top),
+        };
+        let kind = PdfAnnotationKind::from_subtype(subtype);
+        let contents = annot
+            .contents
+            .as_ref()
+            .map(|contents| one_line(&contents.to_string_lossy()))
+            .filter(|contents| !contents.is_empty());
+        let contents = match contents {
+            Some(contents) => contents,
+            None if kind.marks_text() => {
+                let (page_text, runs) = text.get_or_insert_with(|| {
+                    crate::page_text_runs(page, resolver).unwrap_or_default()
+                });
+                text_in_rect(page_text, runs, rect)
+                    .map(|words| one_line(&words))
+                    .unwrap_or_default()
+            }
+            None => String::new(),
+        };
+        out.push(PdfAnnotation {
+            page: page_number,
+            kind,
+            contents,
+            rect,
+        });
+    }
+    out.sort_by(|a, b| {
+        b.rect
+            .top
+            .total_cmp(&a.rect.top)
+            .then_with(|| a.rect.left.total_cmp(&b.rect.left))
+    });
+    Ok(out)
+}
+
+fn one_line(text: &str) -> String {
+    crate::sanitize_extracted_text(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
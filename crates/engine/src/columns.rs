@@ -0,0 +1,213 @@
+use pdf::content::Matrix;
+
+/// Average glyph advance as a fraction of the font size. Column detection only needs to know
+/// roughly where a run ends, so fonts are not loaded for their real widths.
+pub(crate) const AVG_GLYPH_WIDTH: f32 = 0.45;
+/// Pages with fewer text runs than this are never split into columns.
+const MIN_COLUMN_RUNS: usize = 6;
+/// Each column needs at least this many lines.
+const MIN_COLUMN_LINES: usize = 3;
+/// Runs that cross the gutter, such as a title set across the page, as a fraction of all runs.
+const MAX_STRADDLING_FRACTION: f32 = 0.1;
+/// The gutter is looked for between these fractions of the width covered by text.
+const GUTTER_BAND: (f32, f32) = (0.3, 0.7);
+/// The columns must share at least this fraction of the shorter column's height.
+const MIN_VERTICAL_OVERLAP: f32 = 0.5;
+/// Baselines closer than this (in text space units) are the same line.
+pub(crate) const SAME_LINE_TOLERANCE: f32 = 1.0;
+
+/// Order in which the text of a PDF page is read out. EPUB chapters have no layout and
+/// ignore it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TextLayout {
+    /// Content-stream order.
+    #[default]
+    Stream,
+    /// The left column, then the right one, on pages that are clearly set in two columns;
+    /// content-stream order on every other page.
+    Columns,
+}
+
+/// The text line matrix (`Tlm`). Each line starts at its origin.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LineMatrix {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+    f: f32,
+}
+
+impl Default for LineMatrix {
+    fn default() -> Self {
+        Self {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            e: 0.0,
+            f: 0.0,
+        }
+    }
+}
+
+impl LineMatrix {
+    /// `Tm`.
+    pub(crate) fn set(&mut self, matrix: &Matrix) {
+        *self = Self {
+            a: matrix.a,
+            b: matrix.b,
+            c: matrix.c,
+            d: matrix.d,
+            e: matrix.e,
+            f: matrix.f,
+        };
+    }
+
+    /// `Td`, and `T*` with the leading negated.
+    pub(crate) fn translate(&mut self, tx: f32, ty: f32) {
+        self.e += tx * self.a + ty * self.c;
+        self.f += tx * self.b + ty * self.d;
+    }
+
+    pub(crate) fn origin(&self) -> (f32, f32) {
+        (self.e, self.f)
+    }
+
+    /// How much the matrix scales text horizontally.
+    pub(crate) fn scale(&self) -> f32 {
+        self.a.hypot(self.b)
+    }
+}
+
+/// Text drawn from one line origin: `out[start..]` up to the next run's start, where `out`
+/// is the page text in content-stream order.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TextRun {
+    pub(crate) start: usize,
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    /// Font size in text space, after the line matrix.
+    pub(crate) font_size: f32,
+}
+
+struct Piece<'a> {
+    text: &'a str,
+    x: f32,
+    /// Estimated end of the run.
+    x_end: f32,
+    y: f32,
+}
+
+/// Rebuilds `out` column by column when its runs fall into two columns side by side with a
+/// clear gutter between them. `None` keeps the content-stream order.
+pub(crate) fn two_column_text(out: &str, runs: &[TextRun]) -> Option<String> {
+    let pieces: Vec<Piece> = runs
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, run)| {
+            let end = runs.get(idx + 1).map_or(out.len(), |next| next.start);
+            let text = out.get(run.start..end)?.trim_matches('\n');
+            let chars = text.trim().chars().count();
+            (chars > 0).then_some(Piece {
+                text,
+                x: run.x,
+                x_end: run.x + chars as f32 * run.font_size * AVG_GLYPH_WIDTH,
+                y: run.y,
+            })
+        })
+        .collect();
+    let gutter = find_gutter(&pieces)?;
+    let (left, right): (Vec<&Piece>, Vec<&Piece>) =
+        pieces.iter().partition(|piece| piece.x < gutter);
+
+    let mut text = join_lines(&left);
+    text.push('\n');
+    text.push_str(&join_lines(&right));
+    Some(text)
+}
+
+/// The x where the right column starts, if the page has two columns.
+fn find_gutter(pieces: &[Piece]) -> Option<f32> {
+    if pieces.len() < MIN_COLUMN_RUNS {
+        return None;
+    }
+    let left = pieces
+        .iter()
+        .map(|piece| piece.x)
+        .fold(f32::INFINITY, f32::min);
+    let right = pieces
+        .iter()
+        .map(|piece| piece.x_end)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let width = right - left;
+    if width <= 0.0 {
+        return None;
+    }
+    let band = (left + width * GUTTER_BAND.0)..=(left + width * GUTTER_BAND.1);
+    let max_straddling = (pieces.len() as f32 * MAX_STRADDLING_FRACTION) as usize;
+
+    let mut candidates: Vec<f32> = pieces
+        .iter()
+        .map(|piece| piece.x)
+        .filter(|x| band.contains(x))
+        .collect();
+    candidates.sort_by(f32::total_cmp);
+    candidates.dedup();
+    candidates.into_iter().find(|&gutter| {
+        let straddling = pieces
+            .iter()
+            .filter(|piece| piece.x < gutter && piece.x_end > gutter)
+            .count();
+        straddling <= max_straddling && columns_side_by_side(pieces, gutter)
+    })
+}
+
+/// Whether both sides of `gutter` hold enough lines and share most of their height, so a
+/// right-aligned block or a lone indented line is not taken for a column.
+fn columns_side_by_side(pieces: &[Piece], gutter: f32) -> bool {
+    let span = |right: bool| {
+        let mut ys: Vec<f32> = pieces
+            .iter()
+            .filter(|piece| (piece.x >= gutter) == right)
+            .map(|piece| piece.y)
+            .collect();
+        ys.sort_by(f32::total_cmp);
+        ys.dedup_by(|a, b| (*a - *b).abs() < SAME_LINE_TOLERANCE);
+        (ys.len(), ys.first().copied(), ys.last().copied())
+    };
+    let (left_lines, Some(left_low), Some(left_high)) = span(false) else {
+        return false;
+    };
+    let (right_lines, Some(right_low), Some(right_high)) = span(true) else {
+        return false;
+    };
+    if left_lines < MIN_COLUMN_LINES || right_lines < MIN_COLUMN_LINES {
+        return false;
+    }
+    let overlap = left_high.min(right_high) - left_low.max(right_low);
+    let shorter = (left_high - left_low).min(right_high - right_low);
+    overlap > 0.0 && overlap >= shorter * MIN_VERTICAL_OVERLAP
+}
+
+/// Runs in content order, with a line break wherever the baseline changes.
+fn join_lines(pieces: &[&Piece]) -> String {
+    let mut text = String::new();
+    let mut last_y: Option<f32> = None;
+    for piece in pieces {
+        match last_y {
+            Some(y) if (piece.y - y).abs() < SAME_LINE_TOLERANCE => text.push_str(piece.text),
+            Some(_) => {
+                // Drop the spaces that set the other column apart.
+                text.truncate(text.trim_end_matches(' ').len());
+                text.push('\n');
+                text.push_str(piece.text.trim_start());
+            }
+            None => text.push_str(piece.text.trim_start()),
+        }
+        last_y = Some(piece.y);
+    }
+    text.truncate(text.trim_end_matches(' ').len());
+    text
+}
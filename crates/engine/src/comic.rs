@@ -0,0 +1,253 @@
+//! Comic book archives (CBZ): a zip of page images, read as an image-only book.
+
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{Cursor, Read as _};
+use std::path::Path;
+
+use anyhow::Context as _;
+use bookshelf_core::DocumentMetadata;
+use image::DynamicImage;
+
+use crate::epub::element_text;
+
+/// Entry extensions read as pages.
+const PAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp"];
+
+pub(crate) fn is_comic(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("cbz"))
+        .unwrap_or(false)
+}
+
+#[derive(Debug)]
+pub(crate) struct ComicArchive {
+    archive: zip::ZipArchive<File>,
+    /// Archive paths of the page images, in natural order.
+    pages: Vec<String>,
+}
+
+impl ComicArchive {
+    pub(crate) fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path).with_context(|| format!("open cbz {}", path.display()))?;
+        let archive = zip::ZipArchive::new(file)
+            .with_context(|| format!("read cbz archive {}", path.display()))?;
+        let mut pages: Vec<String> = archive
+            .file_names()
+            .filter(|name| is_page_entry(name))
+            .map(str::to_string)
+            .collect();
+        if pages.is_empty() {
+            anyhow::bail!("cbz has no page images");
+        }
+        pages.sort_by(|a, b| natural_cmp(a, b));
+        Ok(Self { archive, pages })
+    }
+
+    pub(crate) fn page_count(&self) -> u32 {
+        u32::try_from(self.pages.len()).unwrap_or(u32::MAX)
+    }
+
+    /// Archive path of a page's image.
+    pub(crate) fn page_name(&self, page_index: u32) -> anyhow::Result<&str> {
+        self.pages
+            .get(page_index as usize)
+            .map(String::as_str)
+            .with_context(|| format!("page {page_index} out of range"))
+    }
+
+    pub(crate) fn page_image(&mut self, page_index: u32) -> anyhow::Result<DynamicImage> {
+        let name = self.page_name(page_index)?.to_string();
+        let bytes = self.read_entry(&name)?;
+        image::ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()?
+            .decode()
+            .with_context(|| format!("decode cbz page {name}"))
+    }
+
+    /// Pixel size of a page's image, read from its header.
+    pub(crate) fn page_size(&mut self, page_index: u32) -> anyhow::Result<(u32, u32)> {
+        let name = self.page_name(page_index)?.to_string();
+        let bytes = self.read_entry(&name)?;
+        image::ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()?
+            .into_dimensions()
+            .with_context(|| format!("read cbz page size {name}"))
+    }
+
+    /// Title, writer and year from `ComicInfo.xml`, when the archive has one.
+    pub(crate) fn metadata(&mut self) -> DocumentMetadata {
+        let name = self
+            .archive
+            .file_names()
+            .find(|name| name.eq_ignore_ascii_case("ComicInfo.xml"))
+            .map(str::to_string);
+        let Some(info) = name.and_then(|name| self.read_entry(&name).ok()) else {
+            return DocumentMetadata::default();
+        };
+        let info = String::from_utf8_lossy(&info);
+        DocumentMetadata {
+            title: element_text(&info, "Title"),
+            author: element_text(&info, "Writer"),
+            year: element_text(&info, "Year").and_then(|year| crate::metadata_year(&year)),
+        }
+    }
+
+    fn read_entry(&mut self, name: &str) -> anyhow::Result<Vec<u8>> {
+        let mut entry = self
+            .archive
+            .by_name(name)
+            .with_context(|| format!("cbz entry {name}"))?;
+        let mut bytes = Vec::new();
+        entry
+            .read_to_end(&mut bytes)
+            .with_context(|| format!("read cbz entry {name}"))?;
+        Ok(bytes)
+    }
+}
+
+/// Image files, leaving out directories and the hidden files archivers leave behind
+/// (`__MACOSX/`, `._page.jpg`, `.thumbs/`).
+fn is_page_entry(name: &str) -> bool {
+    if name.ends_with('/')
+        || name
+            .split('/')
+            .any(|part| part.starts_with('.') || part == "__MACOSX")
+    {
+        return false;
+    }
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            PAGE_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+/// Orders names the way people number pages: `page2` before `page10`, ignoring case.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                // Compare by value: strip leading zeros, then shorter is smaller.
+                let (xs, ys) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = xs.len().cmp(&ys.len()).then_with(|| xs.cmp(ys));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut number = String::new();
+    while let Some(ch) = chars.next_if(char::is_ascii_digit) {
+        number.push(ch);
+    }
+    number
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::*;
+
+    fn png(width: u32, height: u32) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        DynamicImage::new_rgb8(width, height)
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)?;
+        Ok(bytes)
+    }
+
+    fn write_test_cbz(path: &Path) -> anyhow::Result<()> {
+        let file = File::create(path)?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        let entries = [
+            ("ComicInfo.xml", b"<ComicInfo><Title>Night Shift</Title><Writer>A. Writer</Writer><Year>2021</Year></ComicInfo>".to_vec()),
+            ("pages/page10.png", png(30, 40)?),
+            ("pages/page2.png", png(20, 40)?),
+            ("pages/Page1.png", png(10, 40)?),
+            ("__MACOSX/pages/._page2.png", b"resource fork".to_vec()),
+            ("pages/notes.txt", b"not a page".to_vec()),
+        ];
+        for (name, body) in entries {
+            zip.start_file(name, options)?;
+            zip.write_all(&body)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    #[test]
+    fn pages_are_images_in_natural_order() -> anyhow::Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("bookshelf-cbz-test-{}.cbz", std::process::id()));
+        write_test_cbz(&path)?;
+        let result = (|| {
+            let mut comic = ComicArchive::open(&path)?;
+            assert_eq!(comic.page_count(), 3);
+            assert_eq!(comic.page_name(0)?, "pages/Page1.png");
+            assert_eq!(comic.page_name(2)?, "pages/page10.png");
+            assert!(comic.page_name(3).is_err());
+            assert_eq!(comic.page_size(1)?, (20, 40));
+            assert_eq!(comic.page_image(2)?.width(), 30);
+            assert_eq!(
+                comic.metadata(),
+                DocumentMetadata {
+                    title: Some("Night Shift".to_string()),
+                    author: Some("A. Writer".to_string()),
+                    year: Some(2021),
+                }
+            );
+            anyhow::Ok(())
+        })();
+        std::fs::remove_file(&path)?;
+        result
+    }
+
+    #[test]
+    fn natural_order_compares_numbers_by_value() {
+        let mut names = vec![
+            "p10.jpg",
+            "p9.jpg",
+            "P1.jpg",
+            "p09b.jpg",
+            "p010.jpg",
+            "cover.jpg",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec![
+                "cover.jpg",
+                "P1.jpg",
+                "p9.jpg",
+                "p09b.jpg",
+                "p10.jpg",
+                "p010.jpg"
+            ]
+        );
+    }
+}
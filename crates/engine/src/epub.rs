@@ -0,0 +1,665 @@
+//! Minimal EPUB backend: spine chapters are exposed as reader "pages".
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read as _;
+use std::path::Path;
+
+use anyhow::Context as _;
+use bookshelf_core::{DocumentMetadata, TocItem};
+
+pub(crate) fn is_epub(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("epub"))
+        .unwrap_or(false)
+}
+
+#[derive(Debug)]
+pub(crate) struct EpubDocument {
+    archive: zip::ZipArchive<File>,
+    /// Archive paths of the spine documents, in reading order.
+    chapters: Vec<String>,
+    toc: Vec<TocItem>,
+}
+
+/// Title and first creator from the package document, as written, and the year of the
+/// first `dc:date`.
+pub(crate) fn read_metadata(path: &Path) -> anyhow::Result<DocumentMetadata> {
+    let (_, _, opf) = open_package(path)?;
+    Ok(DocumentMetadata {
+        title: element_text(&opf, "title"),
+        author: element_text(&opf, "creator"),
+        year: element_text(&opf, "date").and_then(|date| crate::metadata_year(&date)),
+    })
+}
+
+/// Opens the archive and reads the package document; returns it with its archive path.
+fn open_package(path: &Path) -> anyhow::Result<(zip::ZipArchive<File>, String, String)> {
+    let file = File::open(path).with_context(|| format!("open epub {}", path.display()))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .with_context(|| format!("read epub archive {}", path.display()))?;
+
+    let container = read_entry(&mut archive, "META-INF/container.xml")?;
+    let opf_path = xml_tags(&container)
+        .find(|tag| tag.name == "rootfile")
+        .and_then(|tag| tag.attr("full-path"))
+        .context("epub container has no rootfile")?;
+    let opf = read_entry(&mut archive, &opf_path)?;
+    Ok((archive, opf_path, opf))
+}
+
+impl EpubDocument {
+    pub(crate) fn open(path: &Path) -> anyhow::Result<Self> {
+        let (archive, opf_path, opf) = open_package(path)?;
+        let opf_dir = parent_dir(&opf_path);
+
+        let mut manifest: HashMap<String, ManifestItem> = HashMap::new();
+        let mut spine_ids: Vec<String> = Vec::new();
+        let mut ncx_id: Option<String> = None;
+        for tag in xml_tags(&opf) {
+            match tag.name.as_str() {
+                "item" => {
+                    let (Some(id), Some(href)) = (tag.attr("id"), tag.attr("href")) else {
+                        continue;
+                    };
+                    manifest.insert(
+                        id,
+                        ManifestItem {
+                            path: join_archive_path(&opf_dir, &href),
+                            media_type: tag.attr("media-type").unwrap_or_default(),
+                            is_nav: tag
+                                .attr("properties")
+                                .is_some_and(|p| p.split_whitespace().any(|p| p == "nav")),
+                        },
+                    );
+                }
+                "spine" => ncx_id = tag.attr("toc"),
+                "itemref" => {
+                    if tag.attr("linear").as_deref() == Some("no") {
+                        continue;
+                    }
+                    if let Some(idref) = tag.attr("idref") {
+                        spine_ids.push(idref);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let chapters: Vec<String> = spine_ids
+            .iter()
+            .filter_map(|id| manifest.get(id))
+            .map(|item| item.path.clone())
+            .collect();
+        if chapters.is_empty() {
+            anyhow::bail!("epub spine is empty");
+        }
+
+        let mut doc = Self {
+            archive,
+            chapters,
+            toc: Vec::new(),
+        };
+
+        let nav = manifest.values().find(|item| item.is_nav).cloned();
+        let ncx = ncx_id
+            .as_ref()
+            .and_then(|id| manifest.get(id))
+            .or_else(|| {
+                manifest
+                    .values()
+                    .find(|item| item.media_type == "application/x-dtbncx+xml")
+            })
+            .cloned();
+        if let Some(nav) = nav
+            && let Ok(xml) = read_entry(&mut doc.archive, &nav.path)
+        {
+            let base_dir = parent_dir(&nav.path);
+            doc.toc = doc.parse_nav_toc(&xml, &base_dir, true);
+            if doc.toc.is_empty() {
+                doc.toc = doc.parse_nav_toc(&xml, &base_dir, false);
+            }
+        }
+        if doc.toc.is_empty()
+            && let Some(ncx) = ncx
+            && let Ok(xml) = read_entry(&mut doc.archive, &ncx.path)
+        {
+            doc.toc = doc.parse_ncx_toc(&xml, &parent_dir(&ncx.path));
+        }
+
+        Ok(doc)
+    }
+
+    pub(crate) fn chapter_count(&self) -> u32 {
+        u32::try_from(self.chapters.len()).unwrap_or(u32::MAX)
+    }
+
+    pub(crate) fn toc(&self) -> Vec<TocItem> {
+        self.toc.clone()
+    }
+
+    pub(crate) fn chapter_text(&mut self, index: u32) -> anyhow::Result<String> {
+        let path = self
+            .chapters
+            .get(index as usize)
+            .cloned()
+            .with_context(|| format!("epub chapter {index} out of range"))?;
+        let html = read_entry(&mut self.archive, &path)?;
+        Ok(html_to_text(&html))
+    }
+
+    fn chapter_page_for_href(&self, base_dir: &str, href: &str) -> Option<u32> {
+        let target = href.split('#').next().unwrap_or_default();
+        let target = join_archive_path(base_dir, target);
+        self.chapters
+            .iter()
+            .position(|chapter| *chapter == target)
+            .and_then(|idx| u32::try_from(idx).ok())
+            .map(|idx| idx.saturating_add(1))
+    }
+
+    /// Reads the EPUB 3 navigation document. With `toc_only`, only the
+    /// `epub:type="toc"` nav is used (skipping landmarks/page lists).
+    fn parse_nav_toc(&self, xml: &str, base_dir: &str, toc_only: bool) -> Vec<TocItem> {
+        let mut out = Vec::new();
+        let mut nav_depth = 0usize;
+        let mut list_depth = 0usize;
+        let mut pending: Option<(Option<u32>, usize, usize)> = None;
+
+        for tag in xml_tags(xml) {
+            match (tag.name.as_str(), tag.closing) {
+                ("nav", false) => {
+                    if nav_depth > 0 {
+                        nav_depth += 1;
+                    } else if !toc_only
+                        || tag
+                            .attr("type")
+                            .is_some_and(|t| t.split_whitespace().any(|t| t == "toc"))
+                    {
+                        nav_depth = 1;
+                        list_depth = 0;
+                    }
+                }
+                ("nav", true) if nav_depth > 0 => {
+                    nav_depth -= 1;
+                    if nav_depth == 0 && !out.is_empty() {
+                        break;
+                    }
+                }
+                _ if nav_depth == 0 => {}
+                ("ol", false) => list_depth += 1,
+                ("ol", true) => list_depth = list_depth.saturating_sub(1),
+                ("a", false) => {
+                    let page = tag
+                        .attr("href")
+                        .and_then(|href| self.chapter_page_for_href(base_dir, &href));
+                    pending = Some((page, list_depth.saturating_sub(1), tag.end));
+                }
+                ("a", true) => {
+                    if let Some((page, depth, start)) = pending.take() {
+                        let title = html_to_text(&xml[start..tag.start]);
+                        if !title.is_empty() {
+                            out.push(TocItem { title, page, depth });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        out
+    }
+
+    fn parse_ncx_toc(&self, xml: &str, base_dir: &str) -> Vec<TocItem> {
+        let mut out = Vec::new();
+        let mut depth = 0usize;
+        let mut title: Option<String> = None;
+        let mut text_start: Option<usize> = None;
+
+        for tag in xml_tags(xml) {
+            match (tag.name.as_str(), tag.closing) {
+                ("navPoint", false) => {
+                    depth += 1;
+                    title = None;
+                }
+                ("navPoint", true) => depth = depth.saturating_sub(1),
+                ("text", false) => text_start = Some(tag.end),
+                ("text", true) => {
+                    if let Some(start) = text_start.take() {
+                        title = Some(decode_entities(xml[start..tag.start].trim()));
+                    }
+                }
+                ("content", _) => {
+                    let Some(title) = title.take() else {
+                        continue;
+                    };
+                    let page = tag
+                        .attr("src")
+                        .and_then(|src| self.chapter_page_for_href(base_dir, &src));
+                    out.push(TocItem {
+                        title,
+                        page,
+                        depth: depth.saturating_sub(1),
+                    });
+                }
+                _ => {}
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ManifestItem {
+    path: String,
+    media_type: String,
+    is_nav: bool,
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<File>, name: &str) -> anyhow::Result<String> {
+    let mut entry = archive
+        .by_name(name)
+        .with_context(|| format!("epub entry {name}"))?;
+    let mut bytes = Vec::new();
+    entry
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("read epub entry {name}"))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn parent_dir(path: &str) -> String {
+    match path.rfind('/') {
+        Some(idx) => path[..idx].to_string(),
+        None => String::new(),
+    }
+}
+
+fn join_archive_path(base_dir: &str, href: &str) -> String {
+    let href = percent_decode(href);
+    let mut parts: Vec<&str> = if href.starts_with('/') {
+        Vec::new()
+    } else {
+        base_dir.split('/').filter(|p| !p.is_empty()).collect()
+    };
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = s.get(i + 1..i + 3)
+            && let Ok(b) = u8::from_str_radix(hex, 16)
+        {
+            out.push(b);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[derive(Debug, Clone)]
+struct XmlTag<'a> {
+    /// Local name without namespace prefix (`opf:item` -> `item`).
+    name: String,
+    raw_attrs: &'a str,
+    closing: bool,
+    /// Byte offset of `<`.
+    start: usize,
+    /// Byte offset just past `>`.
+    end: usize,
+}
+
+impl XmlTag<'_> {
+    fn attr(&self, key: &str) -> Option<String> {
+        let mut rest = self.raw_attrs;
+        while let Some(eq) = rest.find('=') {
+            let name = rest[..eq].trim();
+            let name = name.rsplit(char::is_whitespace).next().unwrap_or(name);
+            let after = rest[eq + 1..].trim_start();
+            let quote = after.chars().next()?;
+            if quote != '"' && quote != '\'' {
+                return None;
+            }
+            let value_end = after[1..].find(quote)? + 1;
+            let value = &after[1..value_end];
+            let local = name.rsplit(':').next().unwrap_or(name);
+            if name == key || local == key {
+                return Some(decode_entities(value));
+            }
+            rest = &after[value_end + 1..];
+        }
+        None
+    }
+}
+
+fn xml_tags(xml: &str) -> impl Iterator<Item = XmlTag<'_>> {
+    let mut pos = 0usize;
+    std::iter::from_fn(move || {
+        loop {
+            let start = pos + xml[pos..].find('<')?;
+            let rest = &xml[start..];
+            if rest.starts_with("<!--") {
+                pos = start + rest.find("-->").map(|i| i + 3).unwrap_or(rest.len());
+                continue;
+            }
+            let end = start + rest.find('>')? + 1;
+            pos = end;
+            let inner = &xml[start + 1..end - 1];
+            if inner.starts_with('?') || inner.starts_with('!') {
+                continue;
+            }
+            let closing = inner.starts_with('/');
+            let inner = inner.trim_start_matches('/').trim_end_matches('/');
+            let name_end = inner
+                .find(|c: char| c.is_whitespace())
+                .unwrap_or(inner.len());
+            let full_name = &inner[..name_end];
+            let name = full_name.rsplit(':').next().unwrap_or(full_name);
+            return Some(XmlTag {
+                name: name.to_string(),
+                raw_attrs: &inner[name_end..],
+                closing,
+                start,
+                end,
+            });
+        }
+    })
+}
+
+/// Text of the first `name` element (local name). List items inside it, as in XMP's
+/// `<rdf:Alt>`/`<rdf:Seq>`, are joined with ", ".
+pub(crate) fn element_text(xml: &str, name: &str) -> Option<String> {
+    let mut tags = xml_tags(xml);
+    let open = tags.find(|tag| tag.name == name && !tag.closing)?;
+    let close = tags.find(|tag| tag.name == name && tag.closing)?;
+    let inner = &xml[open.end..close.start];
+
+    let mut items = Vec::new();
+    let mut item_start = None;
+    for tag in xml_tags(inner) {
+        match (tag.name.as_str(), tag.closing) {
+            ("li", false) => item_start = Some(tag.end),
+            ("li", true) => {
+                if let Some(start) = item_start.take() {
+                    items.push(inner[start..tag.start].trim());
+                }
+            }
+            _ => {}
+        }
+    }
+    let text = if items.is_empty() {
+        decode_entities(inner.trim())
+    } else {
+        decode_entities(&items.join(", "))
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+/// Converts an XHTML chapter to plain text with blank lines between blocks,
+/// so the reader's wrap/reflow modes treat paragraphs as they do for PDFs.
+fn html_to_text(html: &str) -> String {
+    let body_start = xml_tags(html)
+        .find(|tag| tag.name.eq_ignore_ascii_case("body") && !tag.closing)
+        .map(|tag| tag.end)
+        .unwrap_or(0);
+    let html = &html[body_start..];
+
+    let mut out = String::new();
+    let mut skip_until: Option<String> = None;
+    let mut pos = 0usize;
+    for tag in xml_tags(html) {
+        if skip_until.is_none() {
+            append_html_text(&mut out, &html[pos..tag.start]);
+        }
+        pos = tag.end;
+
+        let name = tag.name.to_ascii_lowercase();
+        if let Some(skip) = skip_until.as_deref() {
+            if tag.closing && name == skip {
+                skip_until = None;
+            }
+            continue;
+        }
+        match name.as_str() {
+            "script" | "style" | "head" if !tag.closing => skip_until = Some(name.clone()),
+            "br" => out.push('\n'),
+            "p" | "div" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "li" | "blockquote"
+            | "section" | "tr" | "pre" | "hr" | "figure" | "dt" | "dd" => {
+                push_paragraph_break(&mut out)
+            }
+            _ => {}
+        }
+    }
+    if skip_until.is_none() {
+        append_html_text(&mut out, &html[pos..]);
+    }
+
+    let mut text = String::new();
+    for line in out.lines() {
+        text.push_str(line.trim());
+        text.push('\n');
+    }
+    while text.contains("\n\n\n") {
+        text = text.replace("\n\n\n", "\n\n");
+    }
+    text.trim().to_string()
+}
+
+fn append_html_text(out: &mut String, raw: &str) {
+    let decoded = decode_entities(raw);
+    for (i, word) in decoded.split_whitespace().enumerate() {
+        // Text split by inline tags (`<em>hot</em>.`) joins without a space.
+        let separated = i > 0 || decoded.starts_with(char::is_whitespace);
+        if separated && !out.is_empty() && !out.ends_with(['\n', ' ']) {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    if decoded.ends_with(char::is_whitespace) && !out.is_empty() && !out.ends_with(['\n', ' ']) {
+        out.push(' ');
+    }
+}
+
+fn push_paragraph_break(out: &mut String) {
+    let trimmed_len = out.trim_end_matches(' ').len();
+    out.truncate(trimmed_len);
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push_str(if out.ends_with('\n') { "\n" } else { "\n\n" });
+    }
+}
+
+fn decode_entities(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        let after = &rest[amp..];
+        let Some(semi) = after.find(';').filter(|idx| *idx <= 10) else {
+            out.push('&');
+            rest = &after[1..];
+            continue;
+        };
+        let entity = &after[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(ch) => {
+                out.push(ch);
+                rest = &after[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &after[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::*;
+
+    fn write_test_epub(path: &Path) -> anyhow::Result<()> {
+        let file = File::create(path)?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        let entries = [
+            (
+                "META-INF/container.xml",
+                r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                r#"<package><metadata><dc:title>Fish &amp; Chips</dc:title>
+<dc:creator>A. Author</dc:creator><dc:creator>B. Author</dc:creator><dc:date>2019-05-01</dc:date>
+</metadata><manifest>
+<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+<item id="c1" href="text/ch1.xhtml" media-type="application/xhtml+xml"/>
+<item id="c2" href="text/ch2.xhtml" media-type="application/xhtml+xml"/>
+</manifest><spine><itemref idref="c1"/><itemref idref="c2"/></spine></package>"#,
+            ),
+            (
+                "OEBPS/nav.xhtml",
+                r#"<html><body><nav epub:type="toc"><ol>
+<li><a href="text/ch1.xhtml">One</a><ol><li><a href="text/ch1.xhtml#s1">One.A</a></li></ol></li>
+<li><a href="text/ch2.xhtml">Two</a></li></ol></nav></body></html>"#,
+            ),
+            (
+                "OEBPS/text/ch1.xhtml",
+                "<html><body><p>First chapter.</p></body></html>",
+            ),
+            (
+                "OEBPS/text/ch2.xhtml",
+                "<html><body><p>Second chapter.</p></body></html>",
+            ),
+        ];
+        for (name, body) in entries {
+            zip.start_file(name, options)?;
+            zip.write_all(body.as_bytes())?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    #[test]
+    fn opens_epub_chapters_and_nav_toc() -> anyhow::Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("bookshelf-epub-test-{}.epub", std::process::id()));
+        write_test_epub(&path)?;
+        let result = (|| {
+            assert_eq!(
+                read_metadata(&path)?,
+                DocumentMetadata {
+                    title: Some("Fish & Chips".to_string()),
+                    author: Some("A. Author".to_string()),
+                    year: Some(2019),
+                }
+            );
+            let mut doc = EpubDocument::open(&path)?;
+            assert_eq!(doc.chapter_count(), 2);
+            assert_eq!(doc.chapter_text(1)?, "Second chapter.");
+            let toc = doc.toc();
+            let summary: Vec<(&str, Option<u32>, usize)> = toc
+                .iter()
+                .map(|item| (item.title.as_str(), item.page, item.depth))
+                .collect();
+            assert_eq!(
+                summary,
+                vec![
+                    ("One", Some(1), 0),
+                    ("One.A", Some(1), 1),
+                    ("Two", Some(2), 0)
+                ]
+            );
+            anyhow::Ok(())
+        })();
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    #[test]
+    fn html_to_text_separates_blocks_and_decodes_entities() {
+        let html = r#"<?xml version="1.0"?>
+<html><head><title>Ignored</title><style>p { x: y }</style></head>
+<body><h1>Chapter&#160;One</h1>
+<p>Fish &amp; chips,
+   served <em>hot</em>.</p><p>Second<br/>line</p></body></html>"#;
+        assert_eq!(
+            html_to_text(html),
+            "Chapter One\n\nFish & chips, served hot.\n\nSecond\nline"
+        );
+    }
+
+    #[test]
+    fn join_archive_path_resolves_relative_hrefs() {
+        assert_eq!(
+            join_archive_path("OEBPS/text", "../images/a%20b.png"),
+            "OEBPS/images/a b.png"
+        );
+        assert_eq!(join_archive_path("", "ch1.xhtml"), "ch1.xhtml");
+    }
+
+    #[test]
+    fn element_text_joins_xmp_list_items() {
+        let xmp = r#"<x:xmpmeta><rdf:Description>
+<dc:title><rdf:Alt><rdf:li xml:lang="x-default">Operating Systems</rdf:li></rdf:Alt></dc:title>
+<dc:creator><rdf:Seq><rdf:li>R. Arpaci</rdf:li><rdf:li>A. Arpaci</rdf:li></rdf:Seq></dc:creator>
+</rdf:Description></x:xmpmeta>"#;
+        assert_eq!(
+            element_text(xmp, "title").as_deref(),
+            Some("Operating Systems")
+        );
+        assert_eq!(
+            element_text(xmp, "creator").as_deref(),
+            Some("R. Arpaci, A. Arpaci")
+        );
+        assert_eq!(element_text(xmp, "subject"), None);
+    }
+
+    #[test]
+    fn xml_tag_attributes_ignore_namespace_prefix() {
+        let tag =
+            xml_tags(r#"<opf:item id="c1" href='ch1.xhtml' media-type="application/xhtml+xml"/>"#)
+                .next()
+                .unwrap();
+        assert_eq!(tag.name, "item");
+        assert_eq!(tag.attr("id").as_deref(), Some("c1"));
+        assert_eq!(tag.attr("href").as_deref(), Some("ch1.xhtml"));
+        assert_eq!(tag.attr("missing"), None);
+    }
+}
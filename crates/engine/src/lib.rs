@@ -1,25 +1,54 @@
 //! PDF engine wrapper.
 
-use std::cell::{Ref, RefCell};
 use std::collections::{HashMap, HashSet};
 use std::fmt::Write as _;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
 use anyhow::Context as _;
-use bookshelf_core::{Book, ReaderMode, ReaderTextMode, TocItem};
+use bookshelf_core::{Book, BookKind, DocumentMetadata, ReaderMode, ReaderTextMode, TocItem};
 use pdf::content::{Op, TextDrawAdjusted};
 use pdf::file::FileOptions;
 use pdf::font::ToUnicodeMap;
-use pdf::object::{
-    Action, Dest, MaybeNamedDest, OutlineItem, PageTree, PagesNode, PlainRef, RcRef, Resolve,
-    Resources,
-};
-use pdf::primitive::{Name, PdfString, Primitive};
+use pdf::object::{OutlineItem, Page, RcRef, Resolve, Resources};
+use pdf::primitive::{Name, PdfString};
 use pdfium_render::prelude::{PdfBitmapFormat, PdfRenderConfig, Pdfium};
 
+mod annotations;
+mod columns;
+mod comic;
+mod epub;
+mod links;
+mod page_labels;
+mod tables;
+mod text_cache;
+
+pub use annotations::{PdfAnnotation, PdfAnnotationKind};
+pub use columns::TextLayout;
+pub use links::{LinkRect, PageLink};
+pub use tables::tabular_lines;
+
+use columns::{AVG_GLYPH_WIDTH, LineMatrix, SAME_LINE_TOLERANCE, TextRun};
+use text_cache::{FileStamp, PageTextCache};
+
+/// Reads books for the app. It is `Send + Sync`: one engine can be shared by the UI and
+/// background threads, and Pdfium calls from several threads take turns (pdfium-render's
+/// `sync` feature), since the library itself is not thread-safe.
 #[derive(Debug, Default)]
 pub struct Engine {
-    pdfium: RefCell<PdfiumState>,
+    pdfium: Mutex<PdfiumState>,
+    /// Shared by the reader, furniture detection, in-book search and page dumps, which read
+    /// the same pages again and again.
+    page_text: Mutex<PageTextCache>,
+    /// Files `page_count` failed on, with the error, until they change on disk; browsing past
+    /// a corrupt file must not parse it again on every selection.
+    page_count_errors: Mutex<HashMap<PathBuf, (FileStamp, String)>>,
+}
+
+/// Locks `mutex` even if a thread panicked while holding it: the engine's state is caches and
+/// the Pdfium binding, which a panic leaves usable.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
 }
 
 #[derive(Debug, Clone, Default)]
@@ -29,11 +58,16 @@ pub struct PageFurniture {
 }
 
 const PAGE_FURNITURE_SAMPLE_PAGES: u32 = 8;
+/// Pages `book_kind` looks for text on; the first is often a scanned cover.
+const BOOK_KIND_SAMPLE_PAGES: u32 = 3;
 const PAGE_FURNITURE_TOP_K: usize = 3;
 const PAGE_FURNITURE_BOTTOM_K: usize = 3;
 const PAGE_FURNITURE_MIN_FRACTION: f32 = 0.6;
 
 const TJ_INSERT_SPACE_THRESHOLD: f32 = -200.0;
+/// A run that starts this many average glyphs past where the text before it on the line
+/// ends is another cell or column, and is set apart by spaces.
+const CELL_GAP_GLYPHS: f32 = 2.0;
 
 impl PageFurniture {
     pub fn is_empty(&self) -> bool {
@@ -41,11 +75,43 @@ impl PageFurniture {
     }
 }
 
+/// How running headers and footers are told apart from the text of a page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FurnitureConfig {
+    /// Pages read, spread from the first page to the last.
+    pub sample_pages: u32,
+    /// Share of the sampled pages a line must repeat on to be furniture.
+    pub min_fraction: f32,
+}
+
+impl Default for FurnitureConfig {
+    fn default() -> Self {
+        Self {
+            sample_pages: PAGE_FURNITURE_SAMPLE_PAGES,
+            min_fraction: PAGE_FURNITURE_MIN_FRACTION,
+        }
+    }
+}
+
+/// Lines trimming page furniture cuts from one page, in page order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FurnitureCut {
+    pub header: Vec<String>,
+    pub footer: Vec<String>,
+}
+
+impl FurnitureCut {
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty() && self.footer.is_empty()
+    }
+}
+
 #[derive(Debug, Default)]
 enum PdfiumState {
     #[default]
     Uninitialized,
-    Available(Pdfium),
+    /// Shared so a render does not hold the state's lock while it runs.
+    Available(Arc<Pdfium>),
     Unavailable(String),
 }
 
@@ -59,77 +125,65 @@ impl Engine {
         Ok(())
     }
 
-    pub fn page_count(&self, book: &Book) -> anyhow::Result<u32> {
-        let path = bookshelf_core::decode_path(&book.path);
-        let file = FileOptions::cached().open(path)?;
-        Ok(file.num_pages())
+    /// Forgets an earlier failure to load Pdfium and looks for it again, so a library
+    /// installed while the app runs is picked up.
+    pub fn recheck_pdfium(&self) -> anyhow::Result<()> {
+        {
+            let mut state = lock(&self.pdfium);
+            if matches!(*state, PdfiumState::Unavailable(_)) {
+                *state = PdfiumState::Uninitialized;
+            }
+        }
+        self.check_pdfium()
     }
 
-    pub fn toc(&self, book: &Book) -> anyhow::Result<Vec<TocItem>> {
-        let path = bookshelf_core::decode_path(&book.path);
-        let file = FileOptions::cached().open(&path)?;
-        let resolver = file.resolver();
-        let catalog = file.get_root();
+    /// PDFs are drawn by Pdfium; EPUBs have no image mode and comic pages are images already.
+    pub fn renders_with_pdfium(&self, book: &Book) -> bool {
+        !self.is_epub(book) && !self.is_image_only(book)
+    }
 
-        let mut dest_pages_by_name: HashMap<String, PlainRef> = HashMap::new();
-        if let Some(ref names) = catalog.names
-            && let Some(ref dests) = names.dests
+    pub fn page_count(&self, book: &Book) -> anyhow::Result<u32> {
+        let path = bookshelf_core::decode_path(&book.path);
+        let stamp = FileStamp::read(&path);
+        if let Some(stamp) = stamp
+            && let Some((failed_at, err)) = lock(&self.page_count_errors).get(&path)
+            && *failed_at == stamp
         {
-            dests.walk(&resolver, &mut |key: &PdfString, val: &Option<Dest>| {
-                if let Some(Dest {
-                    page: Some(page), ..
-                }) = val
-                {
-                    dest_pages_by_name.insert(key.to_string_lossy(), page.get_inner());
-                }
-            })?;
+            anyhow::bail!("{err}");
         }
-
-        let mut pages_by_ref: HashMap<PlainRef, usize> = HashMap::new();
-        fn add_tree(
-            r: &impl Resolve,
-            pages: &mut HashMap<PlainRef, usize>,
-            tree: &PageTree,
-            current_page: &mut usize,
-        ) {
-            for &node_ref in &tree.kids {
-                let node = match r.get(node_ref) {
-                    Ok(n) => n,
-                    Err(_) => continue,
-                };
-                match *node {
-                    PagesNode::Tree(ref tree) => add_tree(r, pages, tree, current_page),
-                    PagesNode::Leaf(ref _page) => {
-                        pages.insert(node_ref.get_inner(), *current_page);
-                        *current_page += 1;
-                    }
-                }
+        // Parser errors nest; the innermost one says what is wrong with the file.
+        let result = count_pages(&path).map_err(|err| anyhow::anyhow!("{}", err.root_cause()));
+        let mut errors = lock(&self.page_count_errors);
+        match (&result, stamp) {
+            (Err(err), Some(stamp)) => {
+                errors.insert(path, (stamp, err.to_string()));
+            }
+            _ => {
+                errors.remove(&path);
             }
         }
-        add_tree(&resolver, &mut pages_by_ref, &catalog.pages, &mut 0);
+        result
+    }
 
-        fn page_index_to_page_number(page_index: usize) -> Option<u32> {
-            u32::try_from(page_index).ok().map(|n| n.saturating_add(1))
+    pub fn toc(&self, book: &Book) -> anyhow::Result<Vec<TocItem>> {
+        let path = bookshelf_core::decode_path(&book.path);
+        if epub::is_epub(&path) {
+            return Ok(epub::EpubDocument::open(&path)?.toc());
         }
+        if comic::is_comic(&path) {
+            return Ok(Vec::new());
+        }
+        let file = FileOptions::cached().open(&path)?;
+        let resolver = file.resolver();
+        let catalog = file.get_root();
 
-        let page_for_ref = |r: PlainRef| -> Option<u32> {
-            pages_by_ref
-                .get(&r)
-                .copied()
-                .and_then(page_index_to_page_number)
-        };
-
-        let page_for_name = |name: &str| -> Option<u32> {
-            let page_ref = dest_pages_by_name.get(name).copied()?;
-            page_for_ref(page_ref)
-        };
+        let dests = links::DestPages::load(catalog, &resolver)?;
 
         fn walk_outline(
             r: &impl Resolve,
             mut node: RcRef<OutlineItem>,
             depth: usize,
-            page_for_name: &impl Fn(&str) -> Option<u32>,
-            page_for_ref: &impl Fn(PlainRef) -> Option<u32>,
+            dests: &links::DestPages,
             out: &mut Vec<TocItem>,
         ) {
             loop {
@@ -139,41 +193,18 @@ impl Engine {
                     .map(|t| t.to_string_lossy())
                     .unwrap_or_else(|| "(untitled)".to_string());
 
-                let mut page: Option<u32> = None;
-                if let Some(ref dest) = node.dest {
-                    match dest {
-                        Primitive::String(s) => {
-                            page = page_for_name(&s.to_string_lossy());
-                        }
-                        Primitive::Array(a) => {
-                            if let Some(Primitive::Reference(r)) = a.first() {
-                                page = page_for_ref(*r);
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-
-                if page.is_none()
-                    && let Some(Action::Goto(dest)) = node.action.clone()
-                {
-                    match dest {
-                        MaybeNamedDest::Named(s) => {
-                            page = page_for_name(&s.to_string_lossy());
-                        }
-                        MaybeNamedDest::Direct(Dest { page: Some(p), .. }) => {
-                            page = page_for_ref(p.get_inner());
-                        }
-                        _ => {}
-                    }
-                }
+                let page = node
+                    .dest
+                    .as_ref()
+                    .and_then(|dest| dests.page_for_dest(dest, r))
+                    .or_else(|| dests.page_for_action(node.action.as_ref()?));
 
                 out.push(TocItem { title, page, depth });
 
                 if let Some(entry_ref) = node.first
                     && let Ok(entry) = r.get(entry_ref)
                 {
-                    walk_outline(r, entry, depth + 1, page_for_name, page_for_ref, out);
+                    walk_outline(r, entry, depth + 1, dests, out);
                 }
 
                 if let Some(entry_ref) = node.next
@@ -192,28 +223,80 @@ impl Engine {
             && let Some(entry_ref) = outlines.first
         {
             let entry = resolver.get(entry_ref)?;
-            walk_outline(&resolver, entry, 0, &page_for_name, &page_for_ref, &mut out);
+            walk_outline(&resolver, entry, 0, &dests, &mut out);
         }
         Ok(out)
     }
 
+    /// Printed page labels from a PDF's /PageLabels tree, one per page ("xii", "A-3", "57").
+    /// `None` for EPUBs and comics, for labels that are just the page numbers, and for label
+    /// trees that do not parse.
+    pub fn page_labels(&self, book: &Book) -> Option<Vec<String>> {
+        let path = bookshelf_core::decode_path(&book.path);
+        if epub::is_epub(&path) || comic::is_comic(&path) {
+            return None;
+        }
+        page_labels::pdf_page_labels(&path)
+    }
+
+    /// Links on a PDF page that lead to another page of the same file, top to bottom. EPUB
+    /// chapters and comic pages have none.
+    pub fn page_links(&self, book: &Book, page_index: u32) -> anyhow::Result<Vec<PageLink>> {
+        let path = bookshelf_core::decode_path(&book.path);
+        if epub::is_epub(&path) || comic::is_comic(&path) {
+            return Ok(Vec::new());
+        }
+        links::pdf_page_links(&path, page_index)
+    }
+
+    /// Highlights, notes and other annotations on a PDF page, top to bottom. Links are left
+    /// to `page_links`; EPUB chapters and comic pages have no annotations.
+    pub fn page_annotations(
+        &self,
+        book: &Book,
+        page_index: u32,
+    ) -> anyhow::Result<Vec<PdfAnnotation>> {
+        let path = bookshelf_core::decode_path(&book.path);
+        if epub::is_epub(&path) || comic::is_comic(&path) {
+            return Ok(Vec::new());
+        }
+        annotations::pdf_page_annotations(&path, page_index)
+    }
+
+    /// Annotations on every page of a PDF, page by page.
+    pub fn annotations(&self, book: &Book) -> anyhow::Result<Vec<PdfAnnotation>> {
+        let path = bookshelf_core::decode_path(&book.path);
+        if epub::is_epub(&path) || comic::is_comic(&path) {
+            return Ok(Vec::new());
+        }
+        annotations::pdf_annotations(&path)
+    }
+
+    /// Plain text of a page (an EPUB chapter) in content-stream order.
     pub fn render_page_text(&self, book: &Book, page_index: u32) -> anyhow::Result<String> {
+        self.render_page_text_in(book, page_index, TextLayout::Stream)
+    }
+
+    /// Plain text of a page (an EPUB chapter) read out in `layout`. Cached per file, page and
+    /// layout until the file changes on disk.
+    pub fn render_page_text_in(
+        &self,
+        book: &Book,
+        page_index: u32,
+        layout: TextLayout,
+    ) -> anyhow::Result<String> {
         let path = bookshelf_core::decode_path(&book.path);
-        let file = FileOptions::cached().open(path)?;
-        let resolver = file.resolver();
-        let page = file.get_page(page_index)?;
-        let resources = page.resources()?;
-        let Some(content) = &page.contents else {
-            return Ok("no text found".to_string());
-        };
-        let ops = content.operations(&resolver)?;
-        let text = ops_to_text(&ops, &resolver, resources);
-        let text = text.trim().to_string();
-        if text.is_empty() {
-            Ok("no text found".to_string())
-        } else {
-            Ok(text)
+        let stamp = FileStamp::read(&path);
+        if let Some(stamp) = stamp
+            && let Some(text) = lock(&self.page_text).get(&path, page_index, layout, stamp)
+        {
+            return Ok(text);
+        }
+        let text = extract_page_text(&path, page_index, layout)?;
+        if let Some(stamp) = stamp {
+            lock(&self.page_text).insert(&path, page_index, layout, stamp, text.clone());
         }
+        Ok(text)
     }
 
     pub fn render_page_text_for_reader(
@@ -221,9 +304,10 @@ impl Engine {
         book: &Book,
         page_index: u32,
         text_mode: ReaderTextMode,
+        layout: TextLayout,
         furniture: Option<&PageFurniture>,
     ) -> anyhow::Result<String> {
-        let raw = self.render_page_text(book, page_index)?;
+        let raw = self.render_page_text_in(book, page_index, layout)?;
         let trimmed = if text_mode == ReaderTextMode::Raw {
             raw
         } else if let Some(furniture) = furniture
@@ -240,18 +324,75 @@ impl Engine {
         })
     }
 
+    /// Whether trimming `furniture` removes any line from the page read out in `layout`.
+    pub fn page_furniture_trimmed(
+        &self,
+        book: &Book,
+        page_index: u32,
+        layout: TextLayout,
+        furniture: &PageFurniture,
+    ) -> anyhow::Result<bool> {
+        if furniture.is_empty() {
+            return Ok(false);
+        }
+        let raw = self.render_page_text_in(book, page_index, layout)?;
+        Ok(trim_page_furniture(&raw, furniture) != raw)
+    }
+
+    /// The lines trimming `furniture` cuts from the page read out in `layout`.
+    pub fn page_furniture_cut(
+        &self,
+        book: &Book,
+        page_index: u32,
+        layout: TextLayout,
+        furniture: &PageFurniture,
+    ) -> anyhow::Result<FurnitureCut> {
+        if furniture.is_empty() {
+            return Ok(FurnitureCut::default());
+        }
+        let raw = self.render_page_text_in(book, page_index, layout)?;
+        Ok(page_furniture_cut(&raw, furniture))
+    }
+
+    /// Finds the first case-insensitive match of `query` on one page.
+    pub fn search_page(
+        &self,
+        book: &Book,
+        page_index: u32,
+        query: &str,
+    ) -> anyhow::Result<Option<SearchHit>> {
+        if query.trim().is_empty() {
+            return Ok(None);
+        }
+        let text = self.render_page_text(book, page_index)?;
+        Ok(
+            match_snippet(&text, query).map(|(snippet, match_start, match_end)| SearchHit {
+                page: page_index.saturating_add(1),
+                snippet,
+                match_start,
+                match_end,
+            }),
+        )
+    }
+
     pub fn detect_page_furniture(&self, book: &Book) -> anyhow::Result<PageFurniture> {
-        let total_pages = self
-            .page_count(book)
-            .ok()
-            .unwrap_or(PAGE_FURNITURE_SAMPLE_PAGES);
-        let sample_pages = total_pages.min(PAGE_FURNITURE_SAMPLE_PAGES).max(1);
+        self.detect_page_furniture_with(book, FurnitureConfig::default())
+    }
+
+    /// Lines repeated at the top or bottom of enough sampled pages to be running headers or
+    /// footers.
+    pub fn detect_page_furniture_with(
+        &self,
+        book: &Book,
+        config: FurnitureConfig,
+    ) -> anyhow::Result<PageFurniture> {
+        let total_pages = self.page_count(book).ok().unwrap_or(config.sample_pages);
 
         let mut sampled_pages = 0u32;
         let mut header_counts: HashMap<String, u32> = HashMap::new();
         let mut footer_counts: HashMap<String, u32> = HashMap::new();
 
-        for page_index in 0..sample_pages {
+        for page_index in furniture_sample_pages(total_pages, config.sample_pages) {
             let text = match self.render_page_text(book, page_index) {
                 Ok(t) => t,
                 Err(_) => continue,
@@ -273,7 +414,7 @@ impl Engine {
             return Ok(PageFurniture::default());
         }
 
-        let min_repeats = ((sampled_pages as f32) * PAGE_FURNITURE_MIN_FRACTION).ceil() as u32;
+        let min_repeats = ((sampled_pages as f32) * config.min_fraction).ceil() as u32;
         let min_repeats = min_repeats.max(2);
 
         let header_lines = header_counts
@@ -293,6 +434,21 @@ impl Engine {
 
     pub fn debug_page_text(&self, book: &Book, page_index: u32) -> anyhow::Result<String> {
         let path = bookshelf_core::decode_path(&book.path);
+        if epub::is_epub(&path) {
+            let text = epub::EpubDocument::open(&path)?.chapter_text(page_index)?;
+            return Ok(format!(
+                "file: {}\nchapter_index: {page_index}\n\n{text}\n",
+                path.display()
+            ));
+        }
+        if comic::is_comic(&path) {
+            let comic = comic::ComicArchive::open(&path)?;
+            return Ok(format!(
+                "file: {}\npage_index: {page_index}\nimage: {}\n",
+                path.display(),
+                comic.page_name(page_index)?
+            ));
+        }
         let file = FileOptions::cached().open(&path)?;
         let resolver = file.resolver();
         let page = file.get_page(page_index)?;
@@ -399,19 +555,21 @@ impl Engine {
         Ok(out)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn render_page_for_reader(
         &self,
         book: &Book,
         page_index: u32,
         mode: ReaderMode,
         text_mode: ReaderTextMode,
+        layout: TextLayout,
         furniture: Option<&PageFurniture>,
         _viewport_width_chars: u16,
         _viewport_height_chars: u16,
     ) -> anyhow::Result<String> {
         match mode {
             ReaderMode::Text => {
-                self.render_page_text_for_reader(book, page_index, text_mode, furniture)
+                self.render_page_text_for_reader(book, page_index, text_mode, layout, furniture)
             }
             ReaderMode::Image => {
                 anyhow::bail!("image mode is rendered in the UI (ratatui-image), not as text")
@@ -426,12 +584,31 @@ impl Engine {
         target_width: i32,
         max_height: i32,
     ) -> anyhow::Result<RgbaBitmap> {
+        if self.is_epub(book) {
+            anyhow::bail!("image mode is not supported for EPUB; press m for text mode");
+        }
+        let path = bookshelf_core::decode_path(&book.path);
+        if comic::is_comic(&path) {
+            let image = comic::ComicArchive::open(&path)?.page_image(page_index)?;
+            let image = image.resize(
+                target_width.max(1) as u32,
+                max_height.max(1) as u32,
+                image::imageops::FilterType::Triangle,
+            );
+            let pixels = image.into_rgba8();
+            let (width, height) = (pixels.width() as usize, pixels.height() as usize);
+            return Ok(RgbaBitmap {
+                width,
+                height,
+                stride: width.saturating_mul(4),
+                pixels: pixels.into_raw(),
+            });
+        }
         if self.pdfium_disabled() {
             anyhow::bail!("pdfium disabled via BOOKSHELF_DISABLE_PDFIUM");
         }
 
         let pdfium = self.pdfium()?;
-        let path = bookshelf_core::decode_path(&book.path);
         let document = pdfium
             .load_pdf_from_file(&path, None)
             .map_err(|err| anyhow::anyhow!(err))?;
@@ -461,11 +638,7 @@ impl Engine {
         let height = bitmap.height().max(0) as usize;
         let src_pixels = bitmap.as_raw_bytes();
 
-        let src_stride = if height == 0 {
-            0
-        } else {
-            src_pixels.len() / height
-        };
+        let src_stride = src_pixels.len().checked_div(height).unwrap_or(0);
 
         let mut pixels = Vec::with_capacity(width.saturating_mul(height).saturating_mul(4));
         for y in 0..height {
@@ -490,6 +663,14 @@ impl Engine {
 
     pub fn page_size_points(&self, book: &Book, page_index: u32) -> anyhow::Result<(f32, f32)> {
         let path = bookshelf_core::decode_path(&book.path);
+        if epub::is_epub(&path) {
+            anyhow::bail!("EPUB chapters have no fixed page size");
+        }
+        if comic::is_comic(&path) {
+            // One point per pixel keeps the page's aspect ratio, which is all callers use.
+            let (width, height) = comic::ComicArchive::open(&path)?.page_size(page_index)?;
+            return Ok((width.max(1) as f32, height.max(1) as f32));
+        }
         let file = FileOptions::cached()
             .open(&path)
             .with_context(|| format!("open pdf for page size: {}", path.display()))?;
@@ -506,36 +687,95 @@ impl Engine {
         Ok((width, height))
     }
 
-    fn pdfium(&self) -> anyhow::Result<Ref<'_, Pdfium>> {
-        let init_error = {
-            let mut state = self.pdfium.borrow_mut();
-            match &*state {
-                PdfiumState::Available(_) => None,
-                PdfiumState::Unavailable(err) => Some(err.clone()),
-                PdfiumState::Uninitialized => match bind_pdfium() {
-                    Ok(pdfium) => {
-                        *state = PdfiumState::Available(pdfium);
-                        None
-                    }
-                    Err(err) => {
-                        let msg = err.to_string();
-                        *state = PdfiumState::Unavailable(msg.clone());
-                        Some(msg)
-                    }
-                },
+    /// Title, author and year from the PDF Info dictionary (falling back to XMP), the EPUB
+    /// package or a comic's `ComicInfo.xml`. A PDF's year is the one it was created in.
+    /// Placeholder titles such as "Microsoft Word - doc1" come back as `None`.
+    pub fn document_metadata(&self, book: &Book) -> anyhow::Result<DocumentMetadata> {
+        let path = bookshelf_core::decode_path(&book.path);
+        let raw = if epub::is_epub(&path) {
+            epub::read_metadata(&path)?
+        } else if comic::is_comic(&path) {
+            comic::ComicArchive::open(&path)?.metadata()
+        } else {
+            let file = FileOptions::cached().open(&path)?;
+            let mut raw = DocumentMetadata::default();
+            if let Some(info) = &file.trailer.info_dict {
+                raw.title = info.title.as_ref().map(|t| t.to_string_lossy());
+                raw.author = info.author.as_ref().map(|a| a.to_string_lossy());
+                raw.year = info.creation_date.as_ref().map(|d| i32::from(d.year));
             }
+            if raw.title.is_none() || raw.author.is_none() || raw.year.is_none() {
+                let resolver = file.resolver();
+                let xmp = file
+                    .get_root()
+                    .metadata
+                    .and_then(|stream| resolver.get(stream).ok())
+                    .and_then(|stream| pdf::object::Stream::data(&stream, &resolver).ok());
+                if let Some(xmp) = xmp {
+                    let xmp = String::from_utf8_lossy(&xmp);
+                    raw.title = raw.title.or_else(|| epub::element_text(&xmp, "title"));
+                    raw.author = raw.author.or_else(|| epub::element_text(&xmp, "creator"));
+                    raw.year = raw.year.or_else(|| {
+                        epub::element_text(&xmp, "CreateDate")
+                            .or_else(|| epub::element_text(&xmp, "date"))
+                            .and_then(|date| metadata_year(&date))
+                    });
+                }
+            }
+            raw
         };
+        Ok(DocumentMetadata {
+            title: raw.title.as_deref().and_then(clean_metadata_title),
+            author: raw.author.as_deref().and_then(clean_metadata_author),
+            year: raw.year.filter(|year| (1000..=9999).contains(year)),
+        })
+    }
+
+    /// EPUB books are text-only: chapters map to pages and image mode is unsupported.
+    pub fn is_epub(&self, book: &Book) -> bool {
+        epub::is_epub(&bookshelf_core::decode_path(&book.path))
+    }
 
-        if let Some(err) = init_error {
-            return Err(anyhow::anyhow!(err));
+    /// Comic archives (CBZ) are image-only: each page is one image and there is no text.
+    pub fn is_image_only(&self, book: &Book) -> bool {
+        comic::is_comic(&bookshelf_core::decode_path(&book.path))
+    }
+
+    /// What `book` holds. A PDF is a scan when none of its first `BOOK_KIND_SAMPLE_PAGES`
+    /// pages has text; reading them is quick next to opening the book.
+    pub fn book_kind(&self, book: &Book) -> anyhow::Result<BookKind> {
+        if self.is_epub(book) {
+            return Ok(BookKind::Epub);
         }
+        if self.is_image_only(book) {
+            return Ok(BookKind::Comic);
+        }
+        let pages = self.page_count(book)?.min(BOOK_KIND_SAMPLE_PAGES);
+        let has_text = (0..pages).any(|page_index| {
+            self.render_page_text(book, page_index).is_ok_and(|text| {
+                let text = text.trim();
+                !text.is_empty() && !text.eq_ignore_ascii_case("no text found")
+            })
+        });
+        Ok(if has_text {
+            BookKind::TextPdf
+        } else {
+            BookKind::ScannedPdf
+        })
+    }
 
-        let state = self.pdfium.borrow();
+    /// The Pdfium binding, loaded on first use. A failure to load is remembered until
+    /// `recheck_pdfium`.
+    fn pdfium(&self) -> anyhow::Result<Arc<Pdfium>> {
+        let mut state = lock(&self.pdfium);
+        if matches!(*state, PdfiumState::Uninitialized) {
+            *state = match bind_pdfium() {
+                Ok(pdfium) => PdfiumState::Available(Arc::new(pdfium)),
+                Err(err) => PdfiumState::Unavailable(err.to_string()),
+            };
+        }
         match &*state {
-            PdfiumState::Available(_) => Ok(Ref::map(state, |state| match state {
-                PdfiumState::Available(pdfium) => pdfium,
-                _ => unreachable!("pdfium state checked above"),
-            })),
+            PdfiumState::Available(pdfium) => Ok(Arc::clone(pdfium)),
             PdfiumState::Unavailable(err) => Err(anyhow::anyhow!(err.clone())),
             PdfiumState::Uninitialized => unreachable!("pdfium state initialized above"),
         }
@@ -548,7 +788,19 @@ impl Engine {
     }
 }
 
-#[derive(Debug, Clone)]
+/// A page containing a search match. `match_start..match_end` is a byte range in `snippet`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    pub page: u32,
+    pub snippet: String,
+    pub match_start: usize,
+    pub match_end: usize,
+}
+
+const SEARCH_SNIPPET_BEFORE_CHARS: usize = 30;
+const SEARCH_SNIPPET_AFTER_CHARS: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RgbaBitmap {
     pub width: usize,
     pub height: usize,
@@ -556,29 +808,23 @@ pub struct RgbaBitmap {
     pub pixels: Vec<u8>,
 }
 
-fn bind_pdfium() -> anyhow::Result<Pdfium> {
-    let mut candidates: Vec<PathBuf> = Vec::new();
+/// File name of the Pdfium library on this platform, e.g. `libpdfium.so`.
+pub fn pdfium_library_name() -> String {
+    Pdfium::pdfium_platform_library_name()
+        .to_string_lossy()
+        .to_string()
+}
 
+/// Library paths tried, in order, before the system library search path. When
+/// `BOOKSHELF_PDFIUM_LIB_PATH` is set it is the only place looked at.
+pub fn pdfium_search_paths() -> Vec<PathBuf> {
     if let Ok(path) = std::env::var("BOOKSHELF_PDFIUM_LIB_PATH") {
-        let path = PathBuf::from(path);
-        let bindings = Pdfium::bind_to_library(&path)
-            .map_err(|err| anyhow::anyhow!(err))
-            .map_err(|err| {
-                anyhow::anyhow!(
-                    "{err}\n\nFailed to load Pdfium from BOOKSHELF_PDFIUM_LIB_PATH={}.",
-                    path.display()
-                )
-            })?;
-        return Ok(Pdfium::new(bindings));
+        return vec![PathBuf::from(path)];
     }
 
+    let mut candidates: Vec<PathBuf> = Vec::new();
     if let Some(path) = option_env!("BOOKSHELF_PDFIUM_LIB_PATH") {
-        let path = PathBuf::from(path);
-        if path.is_file()
-            && let Ok(bindings) = Pdfium::bind_to_library(&path)
-        {
-            return Ok(Pdfium::new(bindings));
-        }
+        candidates.push(PathBuf::from(path));
     }
 
     if let Ok(dir) = std::env::var("BOOKSHELF_PDFIUM_DIR") {
@@ -597,8 +843,27 @@ fn bind_pdfium() -> anyhow::Result<Pdfium> {
         ".pdfium",
     )));
     candidates.push(Pdfium::pdfium_platform_library_name_at_path(Path::new(".")));
+    candidates
+}
+
+fn bind_pdfium() -> anyhow::Result<Pdfium> {
+    if let Ok(path) = std::env::var("BOOKSHELF_PDFIUM_LIB_PATH") {
+        let path = PathBuf::from(path);
+        let bindings = Pdfium::bind_to_library(&path)
+            .map_err(|err| anyhow::anyhow!(err))
+            .map_err(|err| {
+                anyhow::anyhow!(
+                    "{err}\n\nFailed to load Pdfium from BOOKSHELF_PDFIUM_LIB_PATH={}.",
+                    path.display()
+                )
+            })?;
+        return Ok(Pdfium::new(bindings));
+    }
 
-    for path in candidates {
+    for path in pdfium_search_paths() {
+        if !path.is_file() {
+            continue;
+        }
         if let Ok(bindings) = Pdfium::bind_to_library(&path) {
             return Ok(Pdfium::new(bindings));
         }
@@ -607,16 +872,82 @@ fn bind_pdfium() -> anyhow::Result<Pdfium> {
     let bindings = Pdfium::bind_to_system_library()
         .map_err(|err| anyhow::anyhow!(err))
         .map_err(|err| {
-            let lib_name = Pdfium::pdfium_platform_library_name();
             anyhow::anyhow!(
                 "{err}\n\nPdfium library not found.\n- Install it system-wide, or\n- Place {} next to the executable.\n",
-                lib_name.to_string_lossy()
+                pdfium_library_name()
             )
         })?;
 
     Ok(Pdfium::new(bindings))
 }
 
+/// Authoring tools put these before the file name of untitled documents.
+const PLACEHOLDER_TITLE_PREFIXES: &[&str] = &[
+    "microsoft word - ",
+    "microsoft powerpoint - ",
+    "microsoft excel - ",
+];
+/// Default names, alone or followed by a number or separator (`Document1`, `untitled-2`).
+const PLACEHOLDER_TITLE_WORDS: &[&str] = &["untitled", "document", "doc", "title", "slide"];
+const PLACEHOLDER_TITLE_EXTENSIONS: &[&str] = &[
+    ".doc", ".docx", ".dvi", ".indd", ".odt", ".pdf", ".ppt", ".pptx", ".ps", ".qxd", ".rtf",
+    ".tex", ".txt",
+];
+const PLACEHOLDER_AUTHORS: &[&str] = &[
+    "administrator",
+    "admin",
+    "author",
+    "owner",
+    "unknown",
+    "user",
+];
+
+fn clean_metadata_title(raw: &str) -> Option<String> {
+    let title = collapse_metadata_text(raw)?;
+    let lower = title.to_lowercase();
+    let placeholder = PLACEHOLDER_TITLE_PREFIXES
+        .iter()
+        .any(|prefix| lower.starts_with(prefix))
+        || PLACEHOLDER_TITLE_WORDS.iter().any(|word| {
+            lower.strip_prefix(word).is_some_and(|rest| {
+                rest.chars()
+                    .next()
+                    .is_none_or(|c| !c.is_alphabetic() && !c.is_whitespace())
+            })
+        })
+        || PLACEHOLDER_TITLE_EXTENSIONS
+            .iter()
+            .any(|ext| lower.ends_with(ext))
+        || title.chars().filter(|c| c.is_alphabetic()).count() < 2;
+    (!placeholder).then_some(title)
+}
+
+fn clean_metadata_author(raw: &str) -> Option<String> {
+    let author = collapse_metadata_text(raw)?;
+    let lower = author.to_lowercase();
+    (!PLACEHOLDER_AUTHORS.contains(&lower.as_str())).then_some(author)
+}
+
+/// The year a metadata date starts with: `2019`, `2019-05-01` or `2019-05-01T10:00:00Z`.
+fn metadata_year(raw: &str) -> Option<i32> {
+    let raw = raw.trim();
+    let digits = raw.bytes().take_while(u8::is_ascii_digit).count();
+    if digits != 4 {
+        return None;
+    }
+    raw[..4].parse().ok()
+}
+
+/// Drops control characters and collapses whitespace; `None` when nothing is left.
+fn collapse_metadata_text(raw: &str) -> Option<String> {
+    let text = raw
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
 fn dump_text_op(
     out: &mut String,
     idx: usize,
@@ -656,17 +987,122 @@ fn dump_text_op(
     Ok(())
 }
 
-fn ops_to_text(ops: &[Op], resolver: &impl Resolve, resources: &Resources) -> String {
+/// Reads a page's text straight from the file; "no text found" stands in for an empty page
+/// and for every page of a comic.
+fn count_pages(path: &Path) -> anyhow::Result<u32> {
+    if epub::is_epub(path) {
+        return Ok(epub::EpubDocument::open(path)?.chapter_count());
+    }
+    if comic::is_comic(path) {
+        return Ok(comic::ComicArchive::open(path)?.page_count());
+    }
+    let file = FileOptions::cached().open(path)?;
+    Ok(file.num_pages())
+}
+
+fn extract_page_text(path: &Path, page_index: u32, layout: TextLayout) -> anyhow::Result<String> {
+    if comic::is_comic(path) {
+        comic::ComicArchive::open(path)?.page_name(page_index)?;
+        return Ok("no text found".to_string());
+    }
+    if epub::is_epub(path) {
+        let text = epub::EpubDocument::open(path)?.chapter_text(page_index)?;
+        return Ok(if text.is_empty() {
+            "no text found".to_string()
+        } else {
+            text
+        });
+    }
+    let file = FileOptions::cached().open(path)?;
+    let resolver = file.resolver();
+    let page = file.get_page(page_index)?;
+    let resources = page.resources()?;
+    let Some(content) = &page.contents else {
+        return Ok("no text found".to_string());
+    };
+    let ops = content.operations(&resolver)?;
+    let text = ops_to_text(&ops, &resolver, resources, layout);
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        Ok("no text found".to_string())
+    } else {
+        Ok(text)
+    }
+}
+
+/// A page's text in content-stream order with the runs it was drawn in; empty for a page
+/// without content.
+pub(crate) fn page_text_runs(
+    page: &Page,
+    resolver: &impl Resolve,
+) -> anyhow::Result<(String, Vec<TextRun>)> {
+    let Some(content) = &page.contents else {
+        return Ok(Default::default());
+    };
+    let ops = content.operations(resolver)?;
+    Ok(ops_to_runs(&ops, resolver, page.resources()?))
+}
+
+fn ops_to_text(
+    ops: &[Op],
+    resolver: &impl Resolve,
+    resources: &Resources,
+    layout: TextLayout,
+) -> String {
+    let (out, runs) = ops_to_runs(ops, resolver, resources);
+    match layout {
+        TextLayout::Columns if !tables::has_wide_table(&out) => {
+            columns::two_column_text(&out, &runs).unwrap_or(out)
+        }
+        TextLayout::Columns | TextLayout::Stream => out,
+    }
+}
+
+fn ops_to_runs(
+    ops: &[Op],
+    resolver: &impl Resolve,
+    resources: &Resources,
+) -> (String, Vec<TextRun>) {
     let mut tounicode_cache: HashMap<Name, Option<ToUnicodeMap>> = HashMap::new();
     let mut current_font: Option<Name> = None;
+    let mut font_size = 0.0;
     let mut pending_space = false;
+    let mut line = LineMatrix::default();
+    let mut leading = 0.0;
+    // Where each line's text starts in `out`, for reordering by column.
+    let mut runs: Vec<TextRun> = Vec::new();
 
     let mut out = String::new();
 
+    // Where the line being written started on the page.
+    let mut line_start_x = 0.0;
+
     for op in ops {
+        if matches!(op, Op::TextDraw { .. } | Op::TextDrawAdjusted { .. }) {
+            let (x, y) = line.origin();
+            if runs.last().is_none_or(|run| run.x != x || run.y != y) {
+                let glyph = font_size * line.scale() * AVG_GLYPH_WIDTH;
+                if out.is_empty() || out.ends_with('\n') {
+                    line_start_x = x;
+                } else if let Some(gap) = cell_gap(&out, runs.last(), x, y, line_start_x, glyph) {
+                    out.extend(std::iter::repeat_n(' ', gap));
+                    pending_space = false;
+                }
+                runs.push(TextRun {
+                    start: out.len(),
+                    x,
+                    y,
+                    font_size: font_size * line.scale(),
+                });
+            }
+        }
         match op {
-            Op::TextFont { name, .. } => {
+            Op::BeginText => line = LineMatrix::default(),
+            Op::SetTextMatrix { matrix } => line.set(matrix),
+            Op::Leading { leading: value } => leading = *value,
+            Op::TextFont { name, size } => {
                 current_font = Some(name.clone());
+                font_size = *size;
             }
             Op::TextDraw { text } => {
                 let s = decode_pdf_string(
@@ -700,10 +1136,12 @@ fn ops_to_text(ops: &[Op], resolver: &impl Resolve, resources: &Resources) -> St
                 }
             }
             Op::TextNewline => {
+                line.translate(0.0, -leading);
                 out.push('\n');
                 pending_space = false;
             }
             Op::MoveTextPosition { translation } => {
+                line.translate(translation.x, translation.y);
                 if translation.y < 0.0 {
                     out.push('\n');
                     pending_space = false;
@@ -713,7 +1151,32 @@ fn ops_to_text(ops: &[Op], resolver: &impl Resolve, resources: &Resources) -> St
         }
     }
 
-    out
+    (out, runs)
+}
+
+/// Spaces to put before a run drawn at `(x, y)` when it starts a new cell on the line `out`
+/// ends with: enough to reach the run's column counted from `line_start_x`, so cells that
+/// share an x on the page line up, and never fewer than two.
+fn cell_gap(
+    out: &str,
+    previous: Option<&TextRun>,
+    x: f32,
+    y: f32,
+    line_start_x: f32,
+    glyph: f32,
+) -> Option<usize> {
+    let previous = previous?;
+    if glyph <= 0.0 || (y - previous.y).abs() > SAME_LINE_TOLERANCE {
+        return None;
+    }
+    let drawn = out.get(previous.start..)?.chars().count() as f32;
+    let previous_end = previous.x + drawn * previous.font_size * AVG_GLYPH_WIDTH;
+    if x - previous_end < CELL_GAP_GLYPHS * glyph {
+        return None;
+    }
+    let column = out.rsplit('\n').next().unwrap_or(out).chars().count();
+    let target = ((x - line_start_x) / glyph).round().max(0.0) as usize;
+    Some(target.saturating_sub(column).max(2))
 }
 
 fn append_text_piece(out: &mut String, s: &str, pending_space: &mut bool) {
@@ -838,6 +1301,71 @@ fn decode_bytes(bytes: &[u8], width: usize, map: &ToUnicodeMap) -> (String, usiz
     (out, matches, total)
 }
 
+/// The first case-insensitive match of `query` in `text`, as the in-book search shows it: a
+/// snippet around the match with whitespace runs made single spaces, and the match's byte
+/// range in the snippet.
+pub fn match_snippet(text: &str, query: &str) -> Option<(String, usize, usize)> {
+    let query = query.trim();
+    if query.is_empty() {
+        return None;
+    }
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let (start, end) = find_case_insensitive(&text, query)?;
+    Some(make_search_snippet(&text, start, end))
+}
+
+/// Returns the byte range of the first case-insensitive occurrence of `needle`.
+fn find_case_insensitive(haystack: &str, needle: &str) -> Option<(usize, usize)> {
+    let needle: Vec<char> = needle.chars().flat_map(char::to_lowercase).collect();
+    if needle.is_empty() {
+        return None;
+    }
+    for (start, _) in haystack.char_indices() {
+        let mut matched = 0usize;
+        for (offset, ch) in haystack[start..].char_indices() {
+            let mut lower = ch.to_lowercase();
+            let ok = lower.all(|c| {
+                let hit = needle.get(matched) == Some(&c);
+                matched += 1;
+                hit
+            });
+            if !ok {
+                break;
+            }
+            if matched == needle.len() {
+                return Some((start, start + offset + ch.len_utf8()));
+            }
+        }
+    }
+    None
+}
+
+fn make_search_snippet(text: &str, start: usize, end: usize) -> (String, usize, usize) {
+    let snippet_start = text[..start]
+        .char_indices()
+        .rev()
+        .nth(SEARCH_SNIPPET_BEFORE_CHARS.saturating_sub(1))
+        .map(|(idx, _)| idx)
+        .unwrap_or(0);
+    let snippet_end = text[end..]
+        .char_indices()
+        .nth(SEARCH_SNIPPET_AFTER_CHARS)
+        .map(|(idx, _)| end + idx)
+        .unwrap_or(text.len());
+
+    let mut snippet = String::new();
+    if snippet_start > 0 {
+        snippet.push('…');
+    }
+    let match_start = snippet.len() + (start - snippet_start);
+    let match_end = match_start + (end - start);
+    snippet.push_str(&text[snippet_start..snippet_end]);
+    if snippet_end < text.len() {
+        snippet.push('…');
+    }
+    (snippet, match_start, match_end)
+}
+
 fn sanitize_extracted_text(s: &str) -> String {
     let mut out = String::with_capacity(s.len());
     for ch in s.chars() {
@@ -907,19 +1435,16 @@ fn take_bottom_boundary_lines(text: &str, k: usize) -> Vec<String> {
     out
 }
 
-fn trim_page_furniture(text: &str, furniture: &PageFurniture) -> String {
+/// Indices of the header and footer lines of `text` that are furniture. None are returned
+/// when cutting them would leave the page blank.
+fn furniture_line_indices(text: &str, furniture: &PageFurniture) -> (Vec<usize>, Vec<usize>) {
     let trimmed = text.trim();
     if trimmed.is_empty() || trimmed.eq_ignore_ascii_case("no text found") {
-        return text.to_string();
+        return (Vec::new(), Vec::new());
     }
 
     let lines: Vec<&str> = text.lines().collect();
-    if lines.is_empty() {
-        return text.to_string();
-    }
-
-    let mut to_remove: HashSet<usize> = HashSet::new();
-
+    let mut header = Vec::new();
     let mut header_seen = 0usize;
     for (idx, line) in lines.iter().enumerate() {
         let normalized = normalize_line_for_reflow(line);
@@ -931,10 +1456,11 @@ fn trim_page_furniture(text: &str, furniture: &PageFurniture) -> String {
             break;
         }
         if furniture.header_lines.contains(&normalized) {
-            to_remove.insert(idx);
+            header.push(idx);
         }
     }
 
+    let mut footer = Vec::new();
     let mut footer_seen = 0usize;
     for (rev_idx, line) in lines.iter().rev().enumerate() {
         let normalized = normalize_line_for_reflow(line);
@@ -946,27 +1472,64 @@ fn trim_page_furniture(text: &str, furniture: &PageFurniture) -> String {
             break;
         }
         let idx = lines.len().saturating_sub(1 + rev_idx);
-        if furniture.footer_lines.contains(&normalized) {
-            to_remove.insert(idx);
+        if furniture.footer_lines.contains(&normalized) && !header.contains(&idx) {
+            footer.push(idx);
         }
     }
+    footer.reverse();
 
-    if to_remove.is_empty() {
-        return text.to_string();
+    let blanks_page = lines.iter().enumerate().all(|(idx, line)| {
+        line.trim().is_empty() || header.contains(&idx) || footer.contains(&idx)
+    });
+    if blanks_page {
+        return (Vec::new(), Vec::new());
     }
+    (header, footer)
+}
 
-    let out = lines
-        .into_iter()
+fn trim_page_furniture(text: &str, furniture: &PageFurniture) -> String {
+    let (header, footer) = furniture_line_indices(text, furniture);
+    if header.is_empty() && footer.is_empty() {
+        return text.to_string();
+    }
+    text.lines()
         .enumerate()
-        .filter_map(|(idx, line)| (!to_remove.contains(&idx)).then_some(line))
+        .filter_map(|(idx, line)| {
+            (!header.contains(&idx) && !footer.contains(&idx)).then_some(line)
+        })
         .collect::<Vec<_>>()
-        .join("\n");
+        .join("\n")
+}
 
-    if out.trim().is_empty() {
-        text.to_string()
-    } else {
-        out
+fn page_furniture_cut(text: &str, furniture: &PageFurniture) -> FurnitureCut {
+    let lines: Vec<&str> = text.lines().collect();
+    let (header, footer) = furniture_line_indices(text, furniture);
+    let pick = |indices: Vec<usize>| {
+        indices
+            .into_iter()
+            .map(|idx| lines[idx].trim().to_string())
+            .collect()
+    };
+    FurnitureCut {
+        header: pick(header),
+        footer: pick(footer),
+    }
+}
+
+/// `wanted` page indices spread evenly from the first page to the last, so front matter does
+/// not make up the whole sample.
+fn furniture_sample_pages(total: u32, wanted: u32) -> Vec<u32> {
+    let wanted = wanted.max(1);
+    if total <= wanted {
+        return (0..total).collect();
     }
+    if wanted == 1 {
+        return vec![0];
+    }
+    let last = u64::from(total - 1);
+    (0..u64::from(wanted))
+        .map(|n| (n * last / u64::from(wanted - 1)) as u32)
+        .collect()
 }
 
 fn reflow_reader_text(raw: &str) -> String {
@@ -988,13 +1551,29 @@ fn reflow_reader_text(raw: &str) -> String {
         .unwrap_or(0);
 
     let short_threshold = (typical_len as f32 * 0.6).round() as usize;
+    let compounds = mid_line_compounds(&lines);
+    let tabular = tabular_lines(&lines);
 
     let mut out = String::new();
     let mut paragraph = String::new();
     let mut prev_len = 0usize;
     let mut prev_blank = true;
+    let mut in_table = false;
 
-    for raw_line in lines {
+    for (raw_line, tabular) in lines.into_iter().zip(tabular) {
+        // Table rows keep their line and their spacing, as a block of their own.
+        if tabular {
+            flush_paragraph(&mut out, &mut paragraph, &mut prev_blank);
+            if !out.is_empty() {
+                out.push_str(if in_table { "\n" } else { "\n\n" });
+            }
+            out.push_str(raw_line.trim_end());
+            in_table = true;
+            prev_len = 0;
+            prev_blank = true;
+            continue;
+        }
+        in_table = false;
         let had_indent = raw_line.starts_with("  ") || raw_line.starts_with('\t');
         let line = normalize_line_for_reflow(raw_line);
         if line.is_empty() {
@@ -1015,7 +1594,7 @@ fn reflow_reader_text(raw: &str) -> String {
             }
         }
 
-        append_reflow_line(&mut paragraph, &line);
+        append_reflow_line(&mut paragraph, &line, &compounds);
         prev_len = line.len();
         prev_blank = false;
     }
@@ -1028,6 +1607,8 @@ fn normalize_line_for_reflow(line: &str) -> String {
     let mut out = String::with_capacity(line.len());
     let mut last_was_space = false;
 
+    let line = line.trim_end();
+    let soft_break = line.ends_with('\u{00AD}');
     for ch in line.chars() {
         if ch == '\u{00AD}' {
             continue;
@@ -1045,7 +1626,12 @@ fn normalize_line_for_reflow(line: &str) -> String {
         last_was_space = false;
     }
 
-    out.trim().to_string()
+    let mut out = out.trim().to_string();
+    if soft_break && !out.is_empty() {
+        // A soft hyphen only shows where a word was broken; keep the break point for joining.
+        out.push('\u{00AD}');
+    }
+    out
 }
 
 fn flush_paragraph(out: &mut String, paragraph: &mut String, prev_blank: &mut bool) {
@@ -1066,36 +1652,97 @@ fn flush_paragraph(out: &mut String, paragraph: &mut String, prev_blank: &mut bo
     *prev_blank = true;
 }
 
-fn append_reflow_line(paragraph: &mut String, line: &str) {
+/// Lowercased hyphenated words (`well-known`) written mid-line on the page. A line break inside
+/// one of these keeps its hyphen instead of being joined as a broken word.
+fn mid_line_compounds(lines: &[&str]) -> HashSet<String> {
+    let mut compounds = HashSet::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        // The last word may itself be broken at the line end.
+        for word in words.iter().take(words.len().saturating_sub(1)) {
+            let word = word.trim_matches(|ch: char| !ch.is_alphanumeric());
+            let mut parts = word.split('-');
+            let all_letters = parts
+                .clone()
+                .all(|part| !part.is_empty() && part.chars().all(char::is_alphabetic));
+            if parts.nth(1).is_some() && all_letters {
+                compounds.insert(word.to_lowercase());
+            }
+        }
+    }
+    compounds
+}
+
+fn append_reflow_line(paragraph: &mut String, line: &str, compounds: &HashSet<String>) {
     if paragraph.is_empty() {
         paragraph.push_str(line);
         return;
     }
 
-    if paragraph.ends_with('-') && should_dehyphenate(paragraph, line) {
+    if paragraph.ends_with('\u{00AD}') {
         paragraph.pop();
         paragraph.push_str(line);
         return;
     }
 
+    match line_end_hyphen(paragraph, line, compounds) {
+        HyphenJoin::Drop => {
+            paragraph.pop();
+            paragraph.push_str(line);
+            return;
+        }
+        HyphenJoin::Keep => {
+            paragraph.push_str(line);
+            return;
+        }
+        HyphenJoin::Space => {}
+    }
+
     if !paragraph.ends_with(' ') {
         paragraph.push(' ');
     }
     paragraph.push_str(line);
 }
 
-fn should_dehyphenate(paragraph: &str, next: &str) -> bool {
-    if paragraph.ends_with("--") {
-        return false;
-    }
+enum HyphenJoin {
+    /// A word broken across lines: drop the hyphen and join (`informa-` + `tion`).
+    Drop,
+    /// Join without a space, keeping the dash (`well-` + `known`, `1998-` + `2003`,
+    /// `word—` + `next`).
+    Keep,
+    /// Not a break inside a word; join with a space.
+    Space,
+}
 
-    let prev = paragraph
-        .chars()
-        .rev()
-        .nth(1)
-        .is_some_and(|ch| ch.is_alphabetic());
-    let next = next.chars().next().is_some_and(|ch| ch.is_alphabetic());
-    prev && next
+fn line_end_hyphen(paragraph: &str, next: &str, compounds: &HashSet<String>) -> HyphenJoin {
+    let mut tail = paragraph.chars().rev();
+    let (Some(dash), Some(before)) = (tail.next(), tail.next()) else {
+        return HyphenJoin::Space;
+    };
+    let Some(first) = next.chars().next() else {
+        return HyphenJoin::Space;
+    };
+
+    match dash {
+        // Em and en dashes set closed up against the previous word continue the sentence.
+        '—' | '–' if !before.is_whitespace() => HyphenJoin::Keep,
+        '-' | '\u{2010}' if before == '-' => HyphenJoin::Space,
+        '-' | '\u{2010}' if before.is_ascii_digit() && first.is_ascii_digit() => HyphenJoin::Keep,
+        '-' | '\u{2010}' if before.is_alphabetic() && first.is_lowercase() => {
+            let head = paragraph[..paragraph.len() - dash.len_utf8()]
+                .rsplit(|ch: char| ch.is_whitespace())
+                .next()
+                .unwrap_or_default()
+                .trim_start_matches(|ch: char| !ch.is_alphanumeric());
+            let rest: String = next.chars().take_while(|ch| ch.is_alphabetic()).collect();
+            if compounds.contains(&format!("{head}-{rest}").to_lowercase()) {
+                HyphenJoin::Keep
+            } else {
+                HyphenJoin::Drop
+            }
+        }
+        _ => HyphenJoin::Space,
+    }
 }
 
 fn is_bullet_start(line: &str) -> bool {
@@ -1138,6 +1785,47 @@ mod tests {
     use pdf::object::NoResolve;
     use std::path::Path;
 
+    #[test]
+    fn metadata_titles_skip_placeholders() {
+        assert_eq!(
+            clean_metadata_title("  Operating\u{0}Systems:\n Three Easy Pieces ").as_deref(),
+            Some("Operating Systems: Three Easy Pieces")
+        );
+        assert_eq!(clean_metadata_title("Microsoft Word - doc1"), None);
+        assert_eq!(clean_metadata_title("untitled-final-v3"), None);
+        assert_eq!(clean_metadata_title("Document1"), None);
+        assert_eq!(
+            clean_metadata_title("Untitled Heroes").as_deref(),
+            Some("Untitled Heroes")
+        );
+        assert_eq!(clean_metadata_title("thesis_draft.docx"), None);
+        assert_eq!(clean_metadata_title("  "), None);
+        assert_eq!(clean_metadata_title("12-3"), None);
+        assert_eq!(clean_metadata_author("Administrator"), None);
+        assert_eq!(
+            clean_metadata_author(" Remzi  Arpaci-Dusseau ").as_deref(),
+            Some("Remzi Arpaci-Dusseau")
+        );
+    }
+
+    #[test]
+    fn find_case_insensitive_returns_byte_range() {
+        assert_eq!(find_case_insensitive("Hello World", "world"), Some((6, 11)));
+        assert_eq!(find_case_insensitive("Ünïcode text", "ünï"), Some((0, 5)));
+        assert_eq!(find_case_insensitive("abc", "abcd"), None);
+        assert_eq!(find_case_insensitive("abc", ""), None);
+    }
+
+    #[test]
+    fn search_snippet_marks_match_inside_context() {
+        let text = format!("{} needle {}", "a".repeat(40), "b".repeat(80));
+        let (start, end) = find_case_insensitive(&text, "NEEDLE").unwrap();
+        let (snippet, match_start, match_end) = make_search_snippet(&text, start, end);
+        assert!(snippet.starts_with('…'));
+        assert!(snippet.ends_with('…'));
+        assert_eq!(&snippet[match_start..match_end], "needle");
+    }
+
     fn empty_resources() -> Resources {
         Resources {
             graphics_states: HashMap::new(),
@@ -1167,6 +1855,28 @@ mod tests {
         assert_eq!(reflow_reader_text(input), "microscopic");
     }
 
+    #[test]
+    fn reflow_dehyphenation_spares_compounds_dashes_and_ranges() {
+        // Genuine compound seen mid-line keeps its hyphen when broken at a line end.
+        let input =
+            "a well-known result that is well-\nknown to all and the informa-\ntion flows\n";
+        assert_eq!(
+            reflow_reader_text(input),
+            "a well-known result that is well-known to all and the information flows"
+        );
+        // Capitalized continuations are separate words, not a broken one.
+        assert_eq!(reflow_reader_text("pre-\nWar\n"), "pre- War");
+        assert_eq!(
+            reflow_reader_text("the years 1998-\n2003 saw\n"),
+            "the years 1998-2003 saw"
+        );
+        assert_eq!(reflow_reader_text("since 1998-\nnow\n"), "since 1998- now");
+        assert_eq!(reflow_reader_text("it was—\nalmost\n"), "it was—almost");
+        assert_eq!(reflow_reader_text("it was —\nalmost\n"), "it was — almost");
+        assert_eq!(reflow_reader_text("a --\nb\n"), "a -- b");
+        assert_eq!(reflow_reader_text("infor\u{00AD}\nmation\n"), "information");
+    }
+
     #[test]
     fn reflow_breaks_on_short_line_then_caps() {
         let input = "This is a longer line with words\nShort.\nNext Paragraph starts here\n";
@@ -1194,6 +1904,31 @@ mod tests {
 
         let input = "Header\n\nFooter";
         assert_eq!(trim_page_furniture(input, &furniture), input);
+        assert!(page_furniture_cut(input, &furniture).is_empty());
+    }
+
+    #[test]
+    fn furniture_cut_lists_the_trimmed_lines() {
+        let mut furniture = PageFurniture::default();
+        furniture.header_lines.insert("Bookshelf".to_string());
+        furniture.footer_lines.insert("Page 3".to_string());
+
+        let input = "  Bookshelf\nAn epigraph\n\nHello world\nPage 3";
+        assert_eq!(
+            page_furniture_cut(input, &furniture),
+            FurnitureCut {
+                header: vec!["Bookshelf".to_string()],
+                footer: vec!["Page 3".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn furniture_samples_spread_across_the_book() {
+        assert_eq!(furniture_sample_pages(100, 5), vec![0, 24, 49, 74, 99]);
+        assert_eq!(furniture_sample_pages(3, 8), vec![0, 1, 2]);
+        assert_eq!(furniture_sample_pages(10, 1), vec![0]);
+        assert!(furniture_sample_pages(0, 8).is_empty());
     }
 
     #[test]
@@ -1223,7 +1958,10 @@ mod tests {
                 text: PdfString::from("t"),
             },
         ];
-        assert_eq!(ops_to_text(&ops, &NoResolve, &resources), "Mat");
+        assert_eq!(
+            ops_to_text(&ops, &NoResolve, &resources, TextLayout::Stream),
+            "Mat"
+        );
     }
 
     #[test]
@@ -1236,7 +1974,580 @@ mod tests {
                 TextDrawAdjusted::Text(PdfString::from("world")),
             ],
         }];
-        assert_eq!(ops_to_text(&ops, &NoResolve, &resources), "Hello world");
+        assert_eq!(
+            ops_to_text(&ops, &NoResolve, &resources, TextLayout::Stream),
+            "Hello world"
+        );
+    }
+
+    /// A two-column page whose content stream alternates between the columns line by line,
+    /// under a title set at the left margin.
+    const TWO_COLUMN_STREAM: &str = "BT /F1 10 Tf 12 TL
+72 740 Td (Reading Order in Two Column Layouts) Tj
+0 -24 Td (Left one: papers often set their body text in) Tj
+244 0 Td (Right one: the right column continues where) Tj
+-244 -12 Td (Left two: two narrow columns, and extraction) Tj
+244 0 Td (Right two: the left column ends, so reading) Tj
+-244 -12 Td (Left three: in stream order mixes the lines) Tj
+244 0 Td (Right three: it second keeps the sentences.) Tj
+-244 -12 Td (Left four: of both columns into one mess.) Tj
+244 0 Td (Right four: whole from top to bottom again.) Tj
+ET";
+
+    fn stream_text(stream: &str, layout: TextLayout) -> anyhow::Result<String> {
+        let ops = pdf::content::parse_ops(stream.as_bytes(), &NoResolve)?;
+        Ok(ops_to_text(&ops, &NoResolve, &empty_resources(), layout))
+    }
+
+    #[test]
+    fn columns_layout_reads_the_left_column_first() -> anyhow::Result<()> {
+        assert_eq!(
+            stream_text(TWO_COLUMN_STREAM, TextLayout::Columns)?,
+            "Reading Order in Two Column Layouts
+Left one: papers often set their body text in
+Left two: two narrow columns, and extraction
+Left three: in stream order mixes the lines
+Left four: of both columns into one mess.
+Right one: the right column continues where
+Right two: the left column ends, so reading
+Right three: it second keeps the sentences.
+Right four: whole from top to bottom again."
+        );
+        // Content-stream order sets each pair of lines side by side, the columns aligned.
+        let stream = stream_text(TWO_COLUMN_STREAM, TextLayout::Stream)?;
+        let right: Vec<Option<usize>> = stream.lines().skip(1).map(|l| l.find("Right")).collect();
+        assert_eq!(right, vec![Some(54); 4], "{stream}");
+        Ok(())
+    }
+
+    /// A prose line over a price table whose cells are placed with `Td`, as typesetters
+    /// draw tables, and a closing sentence.
+    pub(crate) const TABLE_STREAM: &str = "BT /F1 10 Tf 12 TL
+72 740 Td (The prices below held for the whole season.) Tj
+0 -12 Td (Fruit) Tj 90 0 Td (Qty) Tj 45 0 Td (Price) Tj
+-135 -12 Td (Apples) Tj 90 0 Td (12) Tj 45 0 Td (3.50) Tj
+-135 -12 Td (Pears) Tj 90 0 Td (4) Tj 45 0 Td (12.00) Tj
+-135 -12 Td (Prices went up) Tj
+T* (the next spring.) Tj
+ET";
+
+    #[test]
+    fn table_cells_keep_their_columns_in_every_text_mode() -> anyhow::Result<()> {
+        let raw = stream_text(TABLE_STREAM, TextLayout::Stream)?;
+        let rows: Vec<&str> = raw.lines().skip(1).take(3).collect();
+        assert_eq!(
+            rows,
+            vec![
+                "Fruit               Qty       Price",
+                "Apples              12        3.50",
+                "Pears               4         12.00",
+            ],
+            "{raw}"
+        );
+        assert_eq!(stream_text(TABLE_STREAM, TextLayout::Columns)?, raw);
+        assert_eq!(
+            reflow_reader_text(&raw),
+            "The prices below held for the whole season.
+
+Fruit               Qty       Price
+Apples              12        3.50
+Pears               4         12.00
+
+Prices went up the next spring."
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn single_column_pages_keep_stream_order() -> anyhow::Result<()> {
+        // A centered heading and an indented paragraph start well inside the page, but the
+        // body lines run across it, so there is no gutter.
+        let page = "BT /F1 10 Tf 12 TL
+1 0 0 1 250 740 Tm (Chapter Two) Tj
+1 0 0 1 90 716 Tm (An indented first line of a paragraph that runs across the whole page) Tj
+T* (and continues on the next line at the left margin of the one and only column) Tj
+T* (with more of the same text so that every line covers the middle of the page) Tj
+T* (where a gutter between two columns would otherwise have to be found to split) Tj
+1 0 0 1 400 680 Tm (- a pull quote) Tj
+1 0 0 1 72 668 Tm (The last line of the page, back at the margin once more and just as long too.) Tj
+ET";
+        assert_eq!(
+            stream_text(page, TextLayout::Columns)?,
+            stream_text(page, TextLayout::Stream)?
+        );
+        Ok(())
+    }
+
+    /// Writes an uncompressed PDF with one page per entry; lines are separated by `\n`.
+    fn write_test_pdf(path: &Path, pages: &[String]) -> anyhow::Result<()> {
+        write_test_pdf_with_annots(path, pages, &[])
+    }
+
+    /// Like `write_test_pdf`; `annots[idx]` is the `/Annots` array body of page `idx`. Page
+    /// `idx` is object `3 + 2 * idx`.
+    fn write_test_pdf_with_annots(
+        path: &Path,
+        pages: &[String],
+        annots: &[&str],
+    ) -> anyhow::Result<()> {
+        write_test_pdf_with_catalog(path, pages, annots, "")
+    }
+
+    /// Like `write_test_pdf_with_annots`, with `catalog` added to the catalog dictionary.
+    fn write_test_pdf_with_catalog(
+        path: &Path,
+        pages: &[String],
+        annots: &[&str],
+        catalog: &str,
+    ) -> anyhow::Result<()> {
+        write_test_pdf_with_objects(path, pages, annots, catalog, &[], "")
+    }
+
+    /// Like `write_test_pdf_with_catalog`, with `objects` numbered from `4 + 2 * pages.len()`
+    /// and `trailer` added to the trailer dictionary.
+    fn write_test_pdf_with_objects(
+        path: &Path,
+        pages: &[String],
+        annots: &[&str],
+        catalog: &str,
+        extra_objects: &[String],
+        trailer: &str,
+    ) -> anyhow::Result<()> {
+        let page_count = pages.len();
+        let font_id = 3 + 2 * page_count;
+        let mut objects = vec![
+            format!("<< /Type /Catalog /Pages 2 0 R{catalog} >>"),
+            format!(
+                "<< /Type /Pages /Count {page_count} /Kids [{}] >>",
+                (0..page_count)
+                    .map(|idx| format!("{} 0 R", 3 + 2 * idx))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+        ];
+        for (idx, text) in pages.iter().enumerate() {
+            let lines = text
+                .lines()
+                .map(|line| format!("({line}) Tj"))
+                .collect::<Vec<_>>()
+                .join(" T* ");
+            let stream = format!("BT /F1 11 Tf 14 TL 72 760 Td {lines} ET");
+            let annots = annots
+                .get(idx)
+                .map(|annots| format!(" /Annots [{annots}]"))
+                .unwrap_or_default();
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] \
+                 /Resources << /Font << /F1 {font_id} 0 R >> >> /Contents {} 0 R{annots} >>",
+                4 + 2 * idx
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{stream}\nendstream",
+                stream.len()
+            ));
+        }
+        objects.push("<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string());
+        objects.extend_from_slice(extra_objects);
+
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (idx, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.push_str(&format!("{} 0 obj\n{object}\nendobj\n", idx + 1));
+        }
+        let xref_at = pdf.len();
+        pdf.push_str(&format!(
+            "xref\n0 {}\n0000000000 65535 f \n",
+            objects.len() + 1
+        ));
+        for offset in offsets {
+            pdf.push_str(&format!("{offset:010} 00000 n \n"));
+        }
+        pdf.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R{trailer} >>\nstartxref\n{xref_at}\n%%EOF\n",
+            objects.len() + 1
+        ));
+        std::fs::write(path, pdf)?;
+        Ok(())
+    }
+
+    fn test_pdf_book(name: &str, pages: &[String]) -> anyhow::Result<Book> {
+        let dir = std::env::temp_dir().join(format!("bookshelf-engine-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(name);
+        write_test_pdf(&path, pages)?;
+        Ok(Book {
+            path: bookshelf_core::encode_path(&path),
+            title: name.to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: bookshelf_core::ReadingStatus::Unread,
+            archived: false,
+        })
+    }
+
+    #[test]
+    fn page_links_resolve_internal_targets_top_to_bottom() -> anyhow::Result<()> {
+        let pages = [
+            "See chapter two\nthen the index".to_string(),
+            "Chapter two".to_string(),
+            "Index".to_string(),
+        ];
+        let annots = [concat!(
+            "<< /Type /Annot /Subtype /Link /Rect [72 743 160 756] ",
+            "/A << /S /GoTo /D [7 0 R /Fit] >> >> ",
+            "<< /Type /Annot /Subtype /Link /Rect [90 757 160 770] /Dest [5 0 R /Fit] >> ",
+            "<< /Type /Annot /Subtype /Link /Rect [72 700 160 712] ",
+            "/A << /S /URI /URI (https://example.com) >> >> ",
+            "<< /Type /Annot /Subtype /Link /Rect [300 700 400 712] ",
+            "/Contents (Back to start) /Dest [3 0 R /Fit] >>",
+        )];
+        let dir = std::env::temp_dir().join(format!("bookshelf-engine-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("links.pdf");
+        write_test_pdf_with_annots(&path, &pages, &annots)?;
+        let book = Book {
+            path: bookshelf_core::encode_path(&path),
+            title: "links".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: bookshelf_core::ReadingStatus::Unread,
+            archived: false,
+        };
+
+        let engine = Engine::new();
+        let links = engine
+            .page_links(&book, 0)?
+            .into_iter()
+            .map(|link| (link.target_page, link.label))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            links,
+            vec![
+                (2, "chapter two".to_string()),
+                (3, "then the index".to_string()),
+                (1, "Back to start".to_string()),
+            ]
+        );
+        assert!(engine.page_links(&book, 1)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn annotations_list_comments_and_marked_text() -> anyhow::Result<()> {
+        let pages = [
+            "See chapter two\nthen the index".to_string(),
+            "Chapter two".to_string(),
+            "Index".to_string(),
+        ];
+        let annots = [
+            concat!(
+                "<< /Type /Annot /Subtype /Highlight /Rect [90 757 160 770] >> ",
+                "<< /Type /Annot /Subtype /Link /Rect [72 743 160 756] /Dest [5 0 R /Fit] >> ",
+                "<< /Type /Annot /Subtype /Text /Rect [300 780 320 800] ",
+                "/Contents (Check\r  this\\001 claim) >>",
+            ),
+            "",
+            "<< /Type /Annot /Subtype /Square /Rect [72 700 160 712] >>",
+        ];
+        let dir = std::env::temp_dir().join(format!("bookshelf-engine-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("annotations.pdf");
+        write_test_pdf_with_annots(&path, &pages, &annots)?;
+        let book = Book {
+            path: bookshelf_core::encode_path(&path),
+            title: "annotations".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: bookshelf_core::ReadingStatus::Unread,
+            archived: false,
+        };
+
+        let engine = Engine::new();
+        let summary = |annotations: Vec<PdfAnnotation>| {
+            annotations
+                .into_iter()
+                .map(|annotation| (annotation.page, annotation.kind, annotation.contents))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            summary(engine.page_annotations(&book, 0)?),
+            vec![
+                (1, PdfAnnotationKind::Note, "Check this claim".to_string()),
+                (1, PdfAnnotationKind::Highlight, "chapter two".to_string()),
+            ]
+        );
+        assert!(engine.page_annotations(&book, 1)?.is_empty());
+        assert_eq!(
+            summary(engine.annotations(&book)?),
+            vec![
+                (1, PdfAnnotationKind::Note, "Check this claim".to_string()),
+                (1, PdfAnnotationKind::Highlight, "chapter two".to_string()),
+                (
+                    3,
+                    PdfAnnotationKind::Other("Square".to_string()),
+                    String::new()
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn page_labels_follow_the_catalog_tree() -> anyhow::Result<()> {
+        let pages: Vec<String> = (1..=4).map(|page| format!("Page {page}")).collect();
+        let book = test_pdf_book("plain-labels.pdf", &pages)?;
+        let engine = Engine::new();
+        assert_eq!(engine.page_labels(&book), None);
+
+        let path = bookshelf_core::decode_path(&book.path);
+        write_test_pdf_with_catalog(
+            &path,
+            &pages,
+            &[],
+            " /PageLabels << /Nums [0 << /S /r >> 2 << /S /D /St 1 >>] >>",
+        )?;
+        assert_eq!(
+            engine.page_labels(&book),
+            Some(["i", "ii", "1", "2"].map(String::from).to_vec())
+        );
+
+        // A label tree that does not parse leaves the book unlabeled.
+        write_test_pdf_with_catalog(&path, &pages, &[], " /PageLabels << /Nums [0 (x)] >>")?;
+        assert_eq!(engine.page_labels(&book), None);
+        Ok(())
+    }
+
+    #[test]
+    fn pdf_metadata_years_come_from_the_info_dictionary_or_xmp() -> anyhow::Result<()> {
+        let pages = vec!["Page".to_string()];
+        let engine = Engine::new();
+        let book = test_pdf_book("info-year.pdf", &pages)?;
+        let path = bookshelf_core::decode_path(&book.path);
+        // One page, so the first extra object is 6.
+        let info = "<< /Title (Dune) /Author (Frank Herbert) /CreationDate (D:19650801120000Z) >>";
+        write_test_pdf_with_objects(&path, &pages, &[], "", &[info.to_string()], " /Info 6 0 R")?;
+        assert_eq!(
+            engine.document_metadata(&book)?,
+            DocumentMetadata {
+                title: Some("Dune".to_string()),
+                author: Some("Frank Herbert".to_string()),
+                year: Some(1965),
+            }
+        );
+
+        let book = test_pdf_book("xmp-year.pdf", &pages)?;
+        let path = bookshelf_core::decode_path(&book.path);
+        let xmp = "<x:xmpmeta><rdf:Description>\
+                   <xmp:CreateDate>2008-03-01T10:00:00Z</xmp:CreateDate>\
+                   </rdf:Description></x:xmpmeta>";
+        let objects = [
+            "<< /Title (Dune) >>".to_string(),
+            format!(
+                "<< /Type /Metadata /Subtype /XML /Length {} >>\nstream\n{xmp}\nendstream",
+                xmp.len()
+            ),
+        ];
+        write_test_pdf_with_objects(
+            &path,
+            &pages,
+            &[],
+            " /Metadata 7 0 R",
+            &objects,
+            " /Info 6 0 R",
+        )?;
+        assert_eq!(engine.document_metadata(&book)?.year, Some(2008));
+
+        assert_eq!(metadata_year(" 2019-05-01 "), Some(2019));
+        assert_eq!(metadata_year("May 2019"), None);
+        assert_eq!(metadata_year("20190501"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn page_count_failures_are_cached_until_the_file_changes() -> anyhow::Result<()> {
+        let pages = vec!["One".to_string(), "Two".to_string()];
+        let book = test_pdf_book("truncated.pdf", &pages)?;
+        let path = bookshelf_core::decode_path(&book.path);
+        let whole = std::fs::read(&path)?;
+        std::fs::write(&path, &whole[..whole.len() / 2])?;
+        let engine = Engine::new();
+
+        let first = engine.page_count(&book).unwrap_err().to_string();
+        assert_eq!(lock(&engine.page_count_errors).len(), 1);
+        assert_eq!(engine.page_count(&book).unwrap_err().to_string(), first);
+
+        // The download finished: the file is counted again.
+        std::fs::write(&path, &whole)?;
+        assert_eq!(engine.page_count(&book)?, 2);
+        assert!(lock(&engine.page_count_errors).is_empty());
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn page_text_is_cached_until_the_file_changes() -> anyhow::Result<()> {
+        let pages = |body: &str| -> Vec<String> {
+            (1..=3)
+                .map(|n| format!("Running Head\n{body} {n}\nPage {n}"))
+                .collect()
+        };
+        let book = test_pdf_book("cache.pdf", &pages("First edition"))?;
+        let engine = Engine::new();
+
+        assert_eq!(
+            engine.render_page_text(&book, 1)?,
+            "Running Head\nFirst edition 2\nPage 2"
+        );
+        assert!(!engine.detect_page_furniture(&book)?.is_empty());
+        assert_eq!(lock(&engine.page_text).len(), 3);
+
+        write_test_pdf(
+            &bookshelf_core::decode_path(&book.path),
+            &pages("Revised second edition"),
+        )?;
+        assert_eq!(
+            engine.render_page_text(&book, 1)?,
+            "Running Head\nRevised second edition 2\nPage 2"
+        );
+        assert_eq!(lock(&engine.page_text).len(), 1);
+
+        std::fs::remove_file(bookshelf_core::decode_path(&book.path))?;
+        Ok(())
+    }
+
+    /// Furniture detection on a warm cache reads every sampled page from it instead of parsing.
+    #[test]
+    fn page_text_cache_serves_repeat_furniture_detection() -> anyhow::Result<()> {
+        let body = (0..40)
+            .map(|line| format!("Body line {line} with some ordinary words to extract"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let pages: Vec<String> = (1..=PAGE_FURNITURE_SAMPLE_PAGES)
+            .map(|n| format!("Chapter Header\n{body}\nPage {n}"))
+            .collect();
+        let book = test_pdf_book("bench.pdf", &pages)?;
+        let engine = Engine::new();
+
+        let cold = engine.detect_page_furniture(&book)?;
+        let cached = lock(&engine.page_text).len();
+        assert!(cached > 0);
+        assert_eq!(lock(&engine.page_text).hits(), 0);
+
+        let warm = engine.detect_page_furniture(&book)?;
+        assert_eq!(cold.header_lines, warm.header_lines);
+        assert!(cold.header_lines.contains("Chapter Header"));
+        assert_eq!(lock(&engine.page_text).len(), cached);
+        assert_eq!(lock(&engine.page_text).hits(), cached);
+
+        std::fs::remove_file(bookshelf_core::decode_path(&book.path))?;
+        Ok(())
+    }
+
+    #[test]
+    fn book_kind_tells_scans_from_text() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("bookshelf-engine-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let engine = Engine::new();
+        let kind = |name: &str, pages: &[&str]| -> anyhow::Result<BookKind> {
+            let path = dir.join(name);
+            let pages: Vec<String> = pages.iter().map(|page| page.to_string()).collect();
+            write_test_pdf(&path, &pages)?;
+            let book = Book {
+                path: bookshelf_core::encode_path(&path),
+                title: name.to_string(),
+                last_opened: None,
+                favorite: false,
+                author: None,
+                status: bookshelf_core::ReadingStatus::Unread,
+                archived: false,
+            };
+            let kind = engine.book_kind(&book);
+            std::fs::remove_file(&path)?;
+            kind
+        };
+
+        // A cover without text does not make a scan.
+        assert_eq!(
+            kind("covered.pdf", &["", "Chapter one"])?,
+            BookKind::TextPdf
+        );
+        assert_eq!(kind("scan.pdf", &["", "", ""])?, BookKind::ScannedPdf);
+        // Only the first pages are read.
+        assert_eq!(
+            kind("late.pdf", &["", "", "", "Appendix"])?,
+            BookKind::ScannedPdf
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn one_engine_serves_four_threads() -> anyhow::Result<()> {
+        fn shareable<T: Send + Sync>() {}
+        shareable::<Engine>();
+
+        let pages: Vec<String> = (1..=6)
+            .map(|page| format!("Page {page} heading\nThe text of page {page} goes on here"))
+            .collect();
+        let dir = std::env::temp_dir().join(format!("bookshelf-engine-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("threads.pdf");
+        write_test_pdf(&path, &pages)?;
+        let book = Book {
+            path: bookshelf_core::encode_path(&path),
+            title: "threads".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: bookshelf_core::ReadingStatus::Unread,
+            archived: false,
+        };
+
+        // What one thread reads is what every thread must read. Without Pdfium the bitmaps
+        // are the same error each time.
+        let engine = Engine::new();
+        let bitmap = |page| {
+            engine
+                .render_page_bitmap_rgba(&book, page, 120, 160)
+                .map_err(|err| err.to_string())
+        };
+        let texts = (0..6)
+            .map(|page| engine.render_page_text(&book, page))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let bitmaps: Vec<_> = (0..6).map(bitmap).collect();
+        let toc = engine.toc(&book)?;
+
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4u32)
+                .map(|worker| {
+                    let (engine, book, texts, bitmaps, toc) =
+                        (&engine, &book, &texts, &bitmaps, &toc);
+                    scope.spawn(move || -> anyhow::Result<()> {
+                        for round in 0..20u32 {
+                            let page = (worker + round) % 6;
+                            assert_eq!(engine.page_count(book)?, 6);
+                            assert_eq!(engine.render_page_text(book, page)?, texts[page as usize]);
+                            let rendered = engine
+                                .render_page_bitmap_rgba(book, page, 120, 160)
+                                .map_err(|err| err.to_string());
+                            assert_eq!(rendered, bitmaps[page as usize]);
+                            assert_eq!(&engine.toc(book)?, toc);
+                        }
+                        Ok(())
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .try_for_each(|worker| worker.join().expect("worker thread panicked"))
+        })?;
+
+        std::fs::remove_file(&path)?;
+        Ok(())
     }
 
     #[cfg(unix)]
@@ -1273,6 +2584,9 @@ mod tests {
                     .unwrap_or_else(|| "untitled".to_string()),
                 last_opened: None,
                 favorite: false,
+                author: None,
+                status: bookshelf_core::ReadingStatus::Unread,
+                archived: false,
             };
 
             let pages = engine.page_count(&book)?;
@@ -0,0 +1,251 @@
+//! Internal links on PDF pages, and the page lookup they share with outline entries.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use pdf::file::FileOptions;
+use pdf::object::{
+    Action, Catalog, Dest, MaybeNamedDest, Object, PageTree, PagesNode, PlainRef, Resolve,
+};
+use pdf::primitive::{PdfString, Primitive};
+
+use crate::columns::{AVG_GLYPH_WIDTH, TextRun};
+
+/// Area of a link or annotation in PDF user space: points from the bottom-left corner of the page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkRect {
+    pub left: f32,
+    pub bottom: f32,
+    pub right: f32,
+    pub top: f32,
+}
+
+/// A link to another page of the same document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLink {
+    pub rect: LinkRect,
+    /// 1-based, like `TocItem::page`.
+    pub target_page: u32,
+    /// The text under the link, else the annotation's contents, else "Go to page N".
+    pub label: String,
+}
+
+/// Resolves link and outline destinations to 1-based page numbers.
+pub(crate) struct DestPages {
+    pages_by_ref: HashMap<PlainRef, u32>,
+    dest_pages_by_name: HashMap<String, PlainRef>,
+}
+
+impl DestPages {
+    pub(crate) fn load(catalog: &Catalog, resolver: &impl Resolve) -> anyhow::Result<Self> {
+        let mut dest_pages_by_name: HashMap<String, PlainRef> = HashMap::new();
+        if let Some(ref names) = catalog.names
+            && let Some(ref dests) = names.dests
+        {
+            dests.walk(resolver, &mut |key: &PdfString, val: &Option<Dest>| {
+                if let Some(Dest {
+                    page: Some(page), ..
+                }) = val
+                {
+                    dest_pages_by_name.insert(key.to_string_lossy(), page.get_inner());
+                }
+            })?;
+        }
+
+        fn add_tree(
+            r: &impl Resolve,
+            pages: &mut HashMap<PlainRef, u32>,
+            tree: &PageTree,
+            current_page: &mut u32,
+        ) {
+            for &node_ref in &tree.kids {
+                let node = match r.get(node_ref) {
+                    Ok(n) => n,
+                    Err(_) => continue,
+                };
+                match *node {
+                    PagesNode::Tree(ref tree) => add_tree(r, pages, tree, current_page),
+                    PagesNode::Leaf(ref _page) => {
+                        *current_page = current_page.saturating_add(1);
+                        pages.insert(node_ref.get_inner(), *current_page);
+                    }
+                }
+            }
+        }
+        let mut pages_by_ref = HashMap::new();
+        add_tree(resolver, &mut pages_by_ref, &catalog.pages, &mut 0);
+
+        Ok(Self {
+            pages_by_ref,
+            dest_pages_by_name,
+        })
+    }
+
+    pub(crate) fn page_for_ref(&self, r: PlainRef) -> Option<u32> {
+        self.pages_by_ref.get(&r).copied()
+    }
+
+    pub(crate) fn page_for_name(&self, name: &str) -> Option<u32> {
+        self.page_for_ref(*self.dest_pages_by_name.get(name)?)
+    }
+
+    /// A `/Dest` entry: a named destination, or an array starting with the page.
+    pub(crate) fn page_for_dest(&self, dest: &Primitive, resolver: &impl Resolve) -> Option<u32> {
+        match dest {
+            Primitive::String(s) => self.page_for_name(&s.to_string_lossy()),
+            Primitive::Name(name) => self.page_for_name(name.as_str()),
+            Primitive::Array(a) => match a.first() {
+                Some(Primitive::Reference(r)) => self.page_for_ref(*r),
+                _ => None,
+            },
+            Primitive::Reference(r) => {
+                let dest = resolver.resolve(*r).ok()?;
+                (!matches!(dest, Primitive::Reference(_)))
+                    .then(|| self.page_for_dest(&dest, resolver))
+                    .flatten()
+            }
+            _ => None,
+        }
+    }
+
+    /// A `GoTo` action; other actions (URIs, launches, other files) have no page here.
+    pub(crate) fn page_for_action(&self, action: &Action) -> Option<u32> {
+        match action {
+            Action::Goto(MaybeNamedDest::Named(s)) => self.page_for_name(&s.to_string_lossy()),
+            Action::Goto(MaybeNamedDest::Direct(Dest { page: Some(p), .. })) => {
+                self.page_for_ref(p.get_inner())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Links from one PDF page to pages of the same file, top to bottom.
+pub(crate) fn pdf_page_links(path: &Path, page_index: u32) -> anyhow::Result<Vec<PageLink>> {
+    let file = FileOptions::cached().open(path)?;
+    let resolver = file.resolver();
+    let page = file.get_page(page_index)?;
+    let annots = page.annotations.load(&resolver)?;
+    if annots.is_empty() {
+        return Ok(Vec::new());
+    }
+    let dests = DestPages::load(file.get_root(), &resolver)?;
+
+    let mut text: Option<(String, Vec<TextRun>)> = None;
+    let mut out = Vec::new();
+    for annot in annots.iter() {
+        if annot.subtype.as_str() != "Link" {
+            continue;
+        }
+        let Some(rect) = annot.rect else {
+            continue;
+        };
+        let target_page = annot
+            .other
+            .get("Dest")
+            .and_then(|dest| dests.page_for_dest(dest, &resolver))
+            .or_else(|| {
+                let action = Action::from_primitive(annot.other.get("A")?.clone(), &resolver);
+                dests.page_for_action(&action.ok()?)
+            });
+        let Some(target_page) = target_page else {
+            continue;
+        };
+        let rect = LinkRect {
+            left: rect.left.min(rect.right),
+            bottom: rect.bottom.min(rect.top),
+            right: rect.left.max(rect.right),
+            top: rect.bottom.max(rect.top),
+        };
+
+        let (page_text, runs) =
+            text.get_or_insert_with(|| crate::page_text_runs(&page, &resolver).unwrap_or_default());
+        let label = text_in_rect(page_text, runs, rect)
+            .or_else(|| {
+                let contents = annot.contents.as_ref()?.to_string_lossy();
+                let contents = contents.split_whitespace().collect::<Vec<_>>().join(" ");
+                (!contents.is_empty()).then_some(contents)
+            })
+            .unwrap_or_else(|| format!("Go to page {target_page}"));
+        out.push(PageLink {
+            rect,
+            target_page,
+            label,
+        });
+    }
+    out.sort_by(|a, b| {
+        b.rect
+            .top
+            .total_cmp(&a.rect.top)
+            .then_with(|| a.rect.left.total_cmp(&b.rect.left))
+    });
+    Ok(out)
+}
+
+/// Words drawn inside `rect`. Glyph widths are estimated, so a word counts when its middle
+/// falls inside.
+pub(crate) fn text_in_rect(text: &str, runs: &[TextRun], rect: LinkRect) -> Option<String> {
+    let mut words: Vec<String> = Vec::new();
+    for (idx, run) in runs.iter().enumerate() {
+        // Baselines sit a little above the bottom of the link box, below descenders.
+        let slack = run.font_size * 0.3;
+        if run.y < rect.bottom - slack || run.y > rect.top {
+            continue;
+        }
+        let end = runs.get(idx + 1).map_or(text.len(), |next| next.start);
+        let Some(run_text) = text.get(run.start..end) else {
+            continue;
+        };
+        let advance = run.font_size * AVG_GLYPH_WIDTH;
+        let mut word_start = None;
+        for (pos, ch) in run_text.chars().chain([' ']).enumerate() {
+            match (ch.is_whitespace(), word_start) {
+                (false, None) => word_start = Some(pos),
+                (true, Some(start)) => {
+                    let middle = run.x + advance * (start + pos) as f32 / 2.0;
+                    if middle >= rect.left && middle <= rect.right {
+                        words.push(run_text.chars().skip(start).take(pos - start).collect());
+                    }
+                    word_start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_in_rect_keeps_the_words_under_the_link() {
+        // Two lines at 11pt: "See chapter two" at y=760, "then the index" at y=746.
+        let text = "See chapter two\nthen the index";
+        let run = |start, y| TextRun {
+            start,
+            x: 72.0,
+            y,
+            font_size: 11.0,
+        };
+        let runs = [run(0, 760.0), run(16, 746.0)];
+        // Each glyph is estimated at 4.95pt, so "chapter" spans 91.8..126.5.
+        let rect = LinkRect {
+            left: 90.0,
+            bottom: 757.0,
+            right: 150.0,
+            top: 770.0,
+        };
+        assert_eq!(
+            text_in_rect(text, &runs, rect).as_deref(),
+            Some("chapter two")
+        );
+        let below = LinkRect {
+            bottom: 700.0,
+            top: 720.0,
+            ..rect
+        };
+        assert_eq!(text_in_rect(text, &runs, below), None);
+    }
+}
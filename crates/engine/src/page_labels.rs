@@ -0,0 +1,210 @@
+//! Printed page numbers from a PDF's /PageLabels number tree.
+//!
+//! Each entry starts a range at a 0-based page index: a numbering style, a prefix and a first
+//! number. Books often number their front matter i, ii, iii and restart at 1 for the body.
+
+use std::path::Path;
+
+use pdf::file::FileOptions;
+use pdf::object::{Counter, PageLabel};
+
+/// How a range numbers its pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LabelStyle {
+    Arabic,
+    RomanLower,
+    RomanUpper,
+    AlphaLower,
+    AlphaUpper,
+}
+
+/// One /PageLabels entry: pages from `first_page` (0-based) on are `prefix` followed by the
+/// number in `style`, counting from `start`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LabelRange {
+    first_page: usize,
+    style: Option<LabelStyle>,
+    prefix: String,
+    start: usize,
+}
+
+/// Labels of the PDF's pages, or `None` when it has no label tree, the tree does not parse,
+/// or its labels are just the page numbers.
+pub(crate) fn pdf_page_labels(path: &Path) -> Option<Vec<String>> {
+    let file = FileOptions::cached().open(path).ok()?;
+    let resolver = file.resolver();
+    let tree = file.get_root().page_labels.as_ref()?;
+    let mut ranges = Vec::new();
+    tree.walk(&resolver, &mut |first_page: i32, label: &PageLabel| {
+        if let Ok(first_page) = usize::try_from(first_page) {
+            ranges.push(LabelRange {
+                first_page,
+                style: label.style.as_ref().map(label_style),
+                prefix: label
+                    .prefix
+                    .as_ref()
+                    .map(|prefix| prefix.to_string_lossy())
+                    .unwrap_or_default(),
+                start: label.start.unwrap_or(1).max(1),
+            });
+        }
+    })
+    .ok()?;
+    labels_for(ranges, file.num_pages() as usize)
+}
+
+/// `pdf` names the lowercase styles (`/r`, `/a`) "Upper" and the uppercase ones "Lower"; the
+/// style letters are what the PDF means.
+fn label_style(counter: &Counter) -> LabelStyle {
+    match counter {
+        Counter::Arabic => LabelStyle::Arabic,
+        Counter::RomanUpper => LabelStyle::RomanLower,
+        Counter::RomanLower => LabelStyle::RomanUpper,
+        Counter::AlphaUpper => LabelStyle::AlphaLower,
+        Counter::AlphaLower => LabelStyle::AlphaUpper,
+    }
+}
+
+/// The label of each of `page_count` pages. Pages before the first range, or whose label
+/// comes out empty, keep their 1-based number.
+fn labels_for(mut ranges: Vec<LabelRange>, page_count: usize) -> Option<Vec<String>> {
+    ranges.sort_by_key(|range| range.first_page);
+    let mut labels: Vec<String> = (1..=page_count).map(|page| page.to_string()).collect();
+    for (idx, range) in ranges.iter().enumerate() {
+        let end = ranges
+            .get(idx + 1)
+            .map_or(page_count, |next| next.first_page.min(page_count));
+        let pages = labels.iter_mut().take(end).skip(range.first_page);
+        for (offset, page_label) in pages.enumerate() {
+            let number = range.start.saturating_add(offset);
+            let label = match range.style {
+                Some(style) => format!("{}{}", range.prefix, format_number(number, style)),
+                None => range.prefix.clone(),
+            };
+            if !label.is_empty() {
+                *page_label = label;
+            }
+        }
+    }
+    let plain = labels
+        .iter()
+        .enumerate()
+        .all(|(idx, label)| *label == (idx + 1).to_string());
+    (!plain).then_some(labels)
+}
+
+/// Numbers too large to spell as numerals or letters, as a broken `/St` can ask for, are
+/// written in digits.
+const MAX_SPELLED_NUMBER: usize = 4999;
+
+fn format_number(number: usize, style: LabelStyle) -> String {
+    match style {
+        _ if number > MAX_SPELLED_NUMBER => number.to_string(),
+        LabelStyle::Arabic => number.to_string(),
+        LabelStyle::RomanLower => roman(number).to_lowercase(),
+        LabelStyle::RomanUpper => roman(number),
+        // a..z, then aa..zz, and so on.
+        LabelStyle::AlphaLower | LabelStyle::AlphaUpper => {
+            let base = if style == LabelStyle::AlphaLower {
+                b'a'
+            } else {
+                b'A'
+            };
+            let letter = char::from(base + ((number - 1) % 26) as u8);
+            letter.to_string().repeat((number - 1) / 26 + 1)
+        }
+    }
+}
+
+fn roman(mut number: usize) -> String {
+    const NUMERALS: &[(usize, &str)] = &[
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut out = String::new();
+    for (value, numeral) in NUMERALS {
+        while number >= *value {
+            out.push_str(numeral);
+            number -= value;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(
+        first_page: usize,
+        style: Option<LabelStyle>,
+        prefix: &str,
+        start: usize,
+    ) -> LabelRange {
+        LabelRange {
+            first_page,
+            style,
+            prefix: prefix.to_string(),
+            start,
+        }
+    }
+
+    #[test]
+    fn front_matter_restarts_the_numbering() {
+        let labels = labels_for(
+            vec![
+                range(3, Some(LabelStyle::Arabic), "", 1),
+                range(0, Some(LabelStyle::RomanLower), "", 1),
+                range(5, Some(LabelStyle::AlphaUpper), "A-", 26),
+            ],
+            7,
+        );
+        assert_eq!(
+            labels,
+            Some(
+                ["i", "ii", "iii", "1", "2", "A-Z", "A-AA"]
+                    .map(String::from)
+                    .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn plain_numbering_is_no_labels() {
+        assert_eq!(
+            labels_for(vec![range(0, Some(LabelStyle::Arabic), "", 1)], 3),
+            None
+        );
+        assert_eq!(labels_for(Vec::new(), 3), None);
+        // Pages before the first range and empty labels keep their number.
+        assert_eq!(
+            labels_for(
+                vec![
+                    range(1, None, "", 1),
+                    range(2, Some(LabelStyle::RomanUpper), "", 4)
+                ],
+                3
+            ),
+            Some(["1", "2", "IV"].map(String::from).to_vec())
+        );
+    }
+
+    #[test]
+    fn roman_numerals() {
+        assert_eq!(roman(1994), "MCMXCIV");
+        assert_eq!(format_number(14, LabelStyle::RomanLower), "xiv");
+        assert_eq!(format_number(3, LabelStyle::AlphaLower), "c");
+        assert_eq!(format_number(1_000_000, LabelStyle::RomanUpper), "1000000");
+    }
+}
@@ -0,0 +1,97 @@
+//! Tables in extracted text. Cells drawn apart on the page come out separated by runs of
+//! spaces (see `ops_to_runs`), so the rows of a table share the columns where their cells
+//! start. Reflow and wrapping pass such lines through as they are.
+
+/// Spaces that separate two cells; a single space is just a word break.
+const CELL_GAP: usize = 2;
+/// Columns two rows' cell starts may differ by and still line up.
+const CELL_SLACK: usize = 1;
+
+/// Columns (in chars) where a cell starts after a gap of `CELL_GAP` spaces or a tab; the
+/// line's own indent does not count.
+fn cell_starts(line: &str) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut seen_text = false;
+    let mut gap = 0;
+    for (col, ch) in line.chars().enumerate() {
+        if ch == '\t' {
+            gap = CELL_GAP;
+        } else if ch == ' ' {
+            gap += 1;
+        } else {
+            if seen_text && gap >= CELL_GAP {
+                starts.push(col);
+            }
+            seen_text = true;
+            gap = 0;
+        }
+    }
+    starts
+}
+
+fn share_a_column(a: &[usize], b: &[usize]) -> bool {
+    a.iter()
+        .any(|a| b.iter().any(|b| a.abs_diff(*b) <= CELL_SLACK))
+}
+
+/// Which of `lines` belong to a table: two or more lines in a row, each split into cells and
+/// with a cell starting in the same column as one in the line before it.
+pub fn tabular_lines(lines: &[&str]) -> Vec<bool> {
+    let starts: Vec<Vec<usize>> = lines.iter().map(|line| cell_starts(line)).collect();
+    let mut tabular = vec![false; lines.len()];
+    let mut group_start = 0;
+    for idx in 1..=lines.len() {
+        let continues = idx < lines.len()
+            && !starts[idx].is_empty()
+            && share_a_column(&starts[idx - 1], &starts[idx]);
+        if continues {
+            continue;
+        }
+        if idx - group_start >= 2 {
+            tabular[group_start..idx].fill(true);
+        }
+        group_start = idx;
+    }
+    tabular
+}
+
+/// Whether `text` holds a table of three or more columns. Column detection leaves such pages
+/// alone: a page set in two columns has at most two cells on a line, and splitting a wider
+/// table would tear its rows apart.
+pub(crate) fn has_wide_table(text: &str) -> bool {
+    let lines: Vec<&str> = text.lines().collect();
+    tabular_lines(&lines)
+        .into_iter()
+        .zip(&lines)
+        .any(|(tabular, line)| tabular && cell_starts(line).len() >= 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_sharing_cell_columns_are_a_table() {
+        let lines = [
+            "Prices rose over the year, as the table shows.",
+            "Item      Qty   Price",
+            "Apples    12    3.50",
+            "Pears     4     12.00",
+            "A sentence  with a double space.",
+            "Code:\tnot a table",
+        ];
+        assert_eq!(
+            tabular_lines(&lines),
+            vec![false, true, true, true, false, false]
+        );
+        assert_eq!(cell_starts("    indented  once"), vec![14]);
+        assert_eq!(cell_starts("a\tb"), vec![2]);
+        // Rows whose cells do not line up are not a table.
+        assert_eq!(tabular_lines(&["a  b", "ccccccc  d"]), vec![false, false]);
+        assert!(tabular_lines(&[]).is_empty());
+        assert!(has_wide_table(&lines.join("\n")));
+        assert!(!has_wide_table(
+            "left one     right one\nleft two     right two"
+        ));
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::TextLayout;
+
+/// Pages of extracted text an `Engine` keeps; the least recently used page goes first.
+pub(crate) const PAGE_TEXT_CACHE_CAPACITY: usize = 512;
+
+/// Identifies one version of a file on disk. A different modification time or size means the
+/// file changed and its cached pages are stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl FileStamp {
+    /// `None` when the file cannot be inspected; such reads are not cached.
+    pub(crate) fn read(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Extracted page text keyed by file, 0-based page and layout.
+#[derive(Debug, Default)]
+pub(crate) struct PageTextCache {
+    entries: HashMap<(PathBuf, u32, TextLayout), CachedPage>,
+    /// Use counter; an entry's `last_used` orders evictions.
+    clock: u64,
+    /// Lookups answered from the cache, so tests can tell a hit from a fresh extraction.
+    #[cfg(test)]
+    hits: usize,
+}
+
+#[derive(Debug)]
+struct CachedPage {
+    stamp: FileStamp,
+    text: String,
+    last_used: u64,
+}
+
+impl PageTextCache {
+    /// Cached text for a page of the file as it is at `stamp`. A changed file drops every
+    /// page cached for it.
+    pub(crate) fn get(
+        &mut self,
+        path: &Path,
+        page_index: u32,
+        layout: TextLayout,
+        stamp: FileStamp,
+    ) -> Option<String> {
+        let key = (path.to_path_buf(), page_index, layout);
+        let cached_stamp = self.entries.get(&key)?.stamp;
+        if cached_stamp != stamp {
+            self.entries.retain(|(p, _, _), _| p != path);
+            return None;
+        }
+        self.clock += 1;
+        let entry = self.entries.get_mut(&key)?;
+        entry.last_used = self.clock;
+        #[cfg(test)]
+        {
+            self.hits += 1;
+        }
+        Some(entry.text.clone())
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        path: &Path,
+        page_index: u32,
+        layout: TextLayout,
+        stamp: FileStamp,
+        text: String,
+    ) {
+        self.clock += 1;
+        self.entries.insert(
+            (path.to_path_buf(), page_index, layout),
+            CachedPage {
+                stamp,
+                text,
+                last_used: self.clock,
+            },
+        );
+        if self.entries.len() > PAGE_TEXT_CACHE_CAPACITY
+            && let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, page)| page.last_used)
+                .map(|(key, _)| key.clone())
+        {
+            self.entries.remove(&oldest);
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn hits(&self) -> usize {
+        self.hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn stamp(secs: u64, len: u64) -> FileStamp {
+        FileStamp {
+            modified: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+            len,
+        }
+    }
+
+    #[test]
+    fn changed_files_drop_their_pages() {
+        let mut cache = PageTextCache::default();
+        let book = Path::new("/books/a.pdf");
+        let other = Path::new("/books/b.pdf");
+        cache.insert(book, 0, TextLayout::Stream, stamp(1, 10), "one".to_string());
+        cache.insert(book, 1, TextLayout::Stream, stamp(1, 10), "two".to_string());
+        cache.insert(
+            other,
+            0,
+            TextLayout::Stream,
+            stamp(1, 10),
+            "other".to_string(),
+        );
+
+        assert_eq!(
+            cache
+                .get(book, 1, TextLayout::Stream, stamp(1, 10))
+                .as_deref(),
+            Some("two")
+        );
+        assert_eq!(cache.get(book, 5, TextLayout::Stream, stamp(1, 10)), None);
+        assert_eq!(cache.get(book, 0, TextLayout::Stream, stamp(2, 10)), None);
+        assert_eq!(cache.get(book, 1, TextLayout::Stream, stamp(1, 10)), None);
+        assert_eq!(
+            cache
+                .get(other, 0, TextLayout::Stream, stamp(1, 10))
+                .as_deref(),
+            Some("other")
+        );
+        assert_eq!(cache.get(other, 0, TextLayout::Stream, stamp(1, 11)), None);
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_page() {
+        let mut cache = PageTextCache::default();
+        let book = Path::new("/books/a.pdf");
+        let capacity = u32::try_from(PAGE_TEXT_CACHE_CAPACITY).unwrap_or(u32::MAX);
+        for page in 0..capacity {
+            cache.insert(
+                book,
+                page,
+                TextLayout::Stream,
+                stamp(1, 10),
+                page.to_string(),
+            );
+        }
+        assert!(
+            cache
+                .get(book, 0, TextLayout::Stream, stamp(1, 10))
+                .is_some()
+        );
+
+        cache.insert(
+            book,
+            capacity,
+            TextLayout::Stream,
+            stamp(1, 10),
+            "new".to_string(),
+        );
+        assert_eq!(cache.len(), PAGE_TEXT_CACHE_CAPACITY);
+        assert!(
+            cache
+                .get(book, 0, TextLayout::Stream, stamp(1, 10))
+                .is_some()
+        );
+        assert!(
+            cache
+                .get(book, 1, TextLayout::Stream, stamp(1, 10))
+                .is_none()
+        );
+        assert!(
+            cache
+                .get(book, capacity, TextLayout::Stream, stamp(1, 10))
+                .is_some()
+        );
+    }
+}
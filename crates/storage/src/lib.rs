@@ -1,13 +1,14 @@
 //! Sqlite-backed persistence.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::Context as _;
 use bookshelf_core::{
-    Book, BookLabels, Bookmark, KittyImageQuality, Note, ReaderMode, ReaderTextMode, ScanScope,
-    Settings, TagKind, Theme,
+    Book, BookLabels, BookProgress, Bookmark, BookmarkView, CleanupReport, FileFingerprint,
+    ImageFit, LabelImportReport, Note, PageCount, PageRotation, ReaderMode, ReaderTextMode,
+    ReaderViewState, ReadingStatus, Settings, SettingsImport, TagKind, decode_path,
 };
-use rusqlite::{Connection, OptionalExtension as _};
+use rusqlite::{Connection, OpenFlags, OptionalExtension as _};
 
 #[derive(Debug)]
 pub struct Storage {
@@ -18,6 +19,10 @@ impl Storage {
     pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
         let conn = Connection::open(path.as_ref())
             .with_context(|| format!("open sqlite db at {}", path.as_ref().display()))?;
+        // A second instance on the same database waits for the other's write instead of
+        // failing with "database is locked".
+        conn.busy_timeout(std::time::Duration::from_secs(5))
+            .context("set sqlite busy timeout")?;
         let storage = Self { conn };
         storage.migrate()?;
         Ok(storage)
@@ -36,10 +41,37 @@ impl Storage {
                 reader_mode TEXT NOT NULL DEFAULT 'text',
                 reader_text_mode TEXT NOT NULL DEFAULT 'reflow',
                 reader_trim_headers_footers INTEGER NOT NULL DEFAULT 1,
+                reader_detect_columns INTEGER NOT NULL DEFAULT 1,
+                reader_invert_colors INTEGER NOT NULL DEFAULT 0,
                 kitty_image_quality TEXT NOT NULL DEFAULT 'balanced',
+                downscale_filter TEXT NOT NULL DEFAULT 'auto',
+                image_cache_limit TEXT NOT NULL DEFAULT 'medium',
+                remote_mode INTEGER NOT NULL DEFAULT 0,
+                furniture_sample_pages INTEGER NOT NULL DEFAULT 8,
+                furniture_min_fraction REAL NOT NULL DEFAULT 0.6,
+                auto_crop TEXT NOT NULL DEFAULT 'off',
+                external_viewer TEXT NOT NULL DEFAULT '',
                 theme TEXT NOT NULL DEFAULT 'dark',
                 scan_scope TEXT NOT NULL DEFAULT 'recursive',
-                library_roots_json TEXT NOT NULL DEFAULT '[]'
+                watch_library INTEGER NOT NULL DEFAULT 1,
+                fingerprint_books INTEGER NOT NULL DEFAULT 1,
+                last_scan_at INTEGER,
+                library_decorations INTEGER NOT NULL DEFAULT 1,
+                resume_last_book INTEGER NOT NULL DEFAULT 0,
+                mouse_capture INTEGER NOT NULL DEFAULT 1,
+                confirm_quit INTEGER NOT NULL DEFAULT 1,
+                reader_minimap INTEGER NOT NULL DEFAULT 1,
+                reader_text_width INTEGER NOT NULL DEFAULT 0,
+                reader_line_spacing INTEGER NOT NULL DEFAULT 10,
+                graphics_terminal TEXT NOT NULL DEFAULT '',
+                tick_rate_ms INTEGER NOT NULL DEFAULT 250,
+                library_grid INTEGER NOT NULL DEFAULT 0,
+                autosave_secs INTEGER NOT NULL DEFAULT 30,
+                sort_mode TEXT NOT NULL DEFAULT 'title',
+                library_roots_json TEXT NOT NULL DEFAULT '[]',
+                key_bindings_json TEXT NOT NULL DEFAULT '{}',
+                library_filters_json TEXT NOT NULL DEFAULT '{}',
+                generation INTEGER NOT NULL DEFAULT 0
             );
             "#,
         )?;
@@ -56,12 +88,43 @@ impl Storage {
                 title TEXT NOT NULL,
                 added_at INTEGER NOT NULL DEFAULT (unixepoch()),
                 last_opened INTEGER,
-                favorite INTEGER NOT NULL DEFAULT 0
+                favorite INTEGER NOT NULL DEFAULT 0,
+                author TEXT,
+                status TEXT,
+                archived INTEGER NOT NULL DEFAULT 0,
+                page_count INTEGER,
+                page_count_size INTEGER,
+                page_count_mtime INTEGER,
+                kind TEXT,
+                file_size INTEGER,
+                file_mtime INTEGER,
+                quick_hash TEXT,
+                full_hash TEXT,
+                fingerprint TEXT
             );
 
             CREATE TABLE IF NOT EXISTS book_progress (
                 path TEXT PRIMARY KEY REFERENCES books(path) ON DELETE CASCADE,
                 last_page INTEGER NOT NULL,
+                scroll INTEGER,
+                pan_x INTEGER,
+                pan_y INTEGER,
+                updated_at INTEGER NOT NULL DEFAULT (unixepoch())
+            );
+
+            CREATE TABLE IF NOT EXISTS book_reader_state (
+                path TEXT PRIMARY KEY REFERENCES books(path) ON DELETE CASCADE,
+                reader_mode TEXT NOT NULL,
+                reader_text_mode TEXT NOT NULL,
+                image_zoom_percent INTEGER NOT NULL DEFAULT 100,
+                image_fit TEXT NOT NULL DEFAULT 'page',
+                rotation INTEGER NOT NULL DEFAULT 0,
+                updated_at INTEGER NOT NULL DEFAULT (unixepoch())
+            );
+
+            CREATE TABLE IF NOT EXISTS book_reading_time (
+                path TEXT PRIMARY KEY REFERENCES books(path) ON DELETE CASCADE,
+                seconds INTEGER NOT NULL DEFAULT 0,
                 updated_at INTEGER NOT NULL DEFAULT (unixepoch())
             );
 
@@ -89,8 +152,14 @@ impl Storage {
             CREATE TABLE IF NOT EXISTS book_tags (
                 path TEXT NOT NULL REFERENCES books(path) ON DELETE CASCADE,
                 tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+                number INTEGER,
                 PRIMARY KEY (path, tag_id)
             );
+
+            CREATE TABLE IF NOT EXISTS collection_positions (
+                path TEXT PRIMARY KEY REFERENCES books(path) ON DELETE CASCADE,
+                position INTEGER NOT NULL
+            );
             "#,
         )?;
 
@@ -112,7 +181,6 @@ impl Storage {
             [],
         )?;
 
-
         match self.conn.execute(
             "ALTER TABLE settings ADD COLUMN reader_mode TEXT NOT NULL DEFAULT 'text'",
             [],
@@ -152,6 +220,32 @@ impl Storage {
             }
         }
 
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN reader_invert_colors INTEGER NOT NULL DEFAULT 0",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.reader_invert_colors column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN watch_library INTEGER NOT NULL DEFAULT 1",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.watch_library column");
+                }
+            }
+        }
+
         match self.conn.execute(
             "ALTER TABLE settings ADD COLUMN kitty_image_quality TEXT NOT NULL DEFAULT 'balanced'",
             [],
@@ -195,205 +289,956 @@ impl Storage {
         }
 
         match self.conn.execute(
-            "ALTER TABLE books ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE settings ADD COLUMN sort_mode TEXT NOT NULL DEFAULT 'title'",
             [],
         ) {
             Ok(_) => {}
             Err(err) => {
                 let msg = err.to_string();
                 if !msg.contains("duplicate column name") {
-                    return Err(err).context("add books.favorite column");
+                    return Err(err).context("add settings.sort_mode column");
                 }
             }
         }
 
-        Ok(())
-    }
-
-    pub fn load_settings(&self) -> anyhow::Result<Settings> {
-        let row = self
-            .conn
-            .query_row(
-                "SELECT reader_mode, reader_text_mode, reader_trim_headers_footers, kitty_image_quality, theme, scan_scope, library_roots_json FROM settings WHERE id = 1",
-                [],
-                |row| {
-                    let reader_mode: String = row.get(0)?;
-                    let reader_text_mode: String = row.get(1)?;
-                    let reader_trim_headers_footers: i64 = row.get(2)?;
-                    let kitty_image_quality: String = row.get(3)?;
-                    let theme: String = row.get(4)?;
-                    let scan_scope: String = row.get(5)?;
-                    let library_roots_json: String = row.get(6)?;
-                    Ok((
-                        reader_mode,
-                        reader_text_mode,
-                        reader_trim_headers_footers,
-                        kitty_image_quality,
-                        theme,
-                        scan_scope,
-                        library_roots_json,
-                    ))
-                },
-            )
-            .optional()?;
-
-        let (
-            reader_mode,
-            reader_text_mode,
-            reader_trim_headers_footers,
-            kitty_image_quality,
-            theme,
-            scan_scope,
-            library_roots_json,
-        ) = match row {
-            Some(value) => value,
-            None => (
-                "text".to_string(),
-                "reflow".to_string(),
-                1,
-                "balanced".to_string(),
-                "dark".to_string(),
-                "recursive".to_string(),
-                "[]".to_string(),
-            ),
-        };
-
-        let reader_mode = reader_mode
-            .parse::<ReaderMode>()
-            .unwrap_or(ReaderMode::Text);
-        let reader_text_mode = reader_text_mode
-            .parse::<ReaderTextMode>()
-            .unwrap_or(ReaderTextMode::Reflow);
-        let kitty_image_quality = kitty_image_quality
-            .parse::<KittyImageQuality>()
-            .unwrap_or(KittyImageQuality::Balanced);
-        let theme = theme.parse::<Theme>().unwrap_or(Theme::Dark);
-        let reader_trim_headers_footers = reader_trim_headers_footers != 0;
-        let scan_scope = scan_scope
-            .parse::<ScanScope>()
-            .unwrap_or(ScanScope::Recursive);
-        let library_roots: Vec<String> =
-            serde_json::from_str(&library_roots_json).unwrap_or_else(|_| Vec::new());
-
-        let mut settings = Settings {
-            reader_mode,
-            reader_text_mode,
-            reader_trim_headers_footers,
-            kitty_image_quality,
-            theme,
-            scan_scope,
-            library_roots,
-        };
-        settings.normalize();
-        Ok(settings)
-    }
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN key_bindings_json TEXT NOT NULL DEFAULT '{}'",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.key_bindings_json column");
+                }
+            }
+        }
 
-    pub fn save_settings(&self, settings: &Settings) -> anyhow::Result<()> {
-        let mut settings = settings.clone();
-        settings.normalize();
-        let library_roots_json = serde_json::to_string(&settings.library_roots)?;
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN auto_crop TEXT NOT NULL DEFAULT 'off'",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.auto_crop column");
+                }
+            }
+        }
 
-        self.conn.execute(
-            "UPDATE settings SET reader_mode = ?, reader_text_mode = ?, reader_trim_headers_footers = ?, kitty_image_quality = ?, theme = ?, scan_scope = ?, library_roots_json = ? WHERE id = 1",
-            (
-                settings.reader_mode.as_str(),
-                settings.reader_text_mode.as_str(),
-                i64::from(settings.reader_trim_headers_footers),
-                settings.kitty_image_quality.as_str(),
-                settings.theme.as_str(),
-                settings.scan_scope.as_str(),
-                library_roots_json,
-            ),
-        )?;
-        Ok(())
-    }
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN image_cache_limit TEXT NOT NULL DEFAULT 'medium'",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.image_cache_limit column");
+                }
+            }
+        }
 
-    pub fn upsert_book(&self, book: &Book) -> anyhow::Result<()> {
-        self.conn.execute(
-            r#"
-            INSERT INTO books (path, title) VALUES (?, ?)
-            ON CONFLICT(path) DO UPDATE SET title = excluded.title
-            "#,
-            (&book.path, &book.title),
-        )?;
-        Ok(())
-    }
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN external_viewer TEXT NOT NULL DEFAULT ''",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.external_viewer column");
+                }
+            }
+        }
 
-    pub fn list_books(&self) -> anyhow::Result<Vec<Book>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT path, title, last_opened, favorite FROM books ORDER BY title COLLATE NOCASE",
-        )?;
-        let rows = stmt.query_map([], |row| {
-            let favorite: i64 = row.get(3)?;
-            Ok(Book {
-                path: row.get(0)?,
-                title: row.get(1)?,
-                last_opened: row.get(2)?,
-                favorite: favorite != 0,
-            })
-        })?;
-        Ok(rows.collect::<Result<Vec<_>, _>>()?)
-    }
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN library_decorations INTEGER NOT NULL DEFAULT 1",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.library_decorations column");
+                }
+            }
+        }
 
-    pub fn set_last_opened(&self, path: &str, last_opened: i64) -> anyhow::Result<()> {
-        self.conn.execute(
-            "UPDATE books SET last_opened = ? WHERE path = ?",
-            (last_opened, path),
-        )?;
-        Ok(())
-    }
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN reader_detect_columns INTEGER NOT NULL DEFAULT 1",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.reader_detect_columns column");
+                }
+            }
+        }
 
-    pub fn set_favorite(&self, path: &str, favorite: bool) -> anyhow::Result<()> {
-        self.conn.execute(
-            "UPDATE books SET favorite = ? WHERE path = ?",
-            (i64::from(favorite), path),
-        )?;
-        Ok(())
-    }
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN resume_last_book INTEGER NOT NULL DEFAULT 0",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.resume_last_book column");
+                }
+            }
+        }
 
-    pub fn list_labels_by_path(
-        &self,
-    ) -> anyhow::Result<std::collections::HashMap<String, BookLabels>> {
-        let mut stmt = self.conn.prepare(
-            r#"
-            SELECT bt.path, t.name, t.kind
-            FROM book_tags bt
-            JOIN tags t ON t.id = bt.tag_id
-            ORDER BY bt.path, t.kind, t.name COLLATE NOCASE
-            "#,
-        )?;
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN remote_mode INTEGER NOT NULL DEFAULT 0",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.remote_mode column");
+                }
+            }
+        }
 
-        let rows = stmt.query_map([], |row| {
-            let path: String = row.get(0)?;
-            let name: String = row.get(1)?;
-            let kind: String = row.get(2)?;
-            Ok((path, name, kind))
-        })?;
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN fingerprint_books INTEGER NOT NULL DEFAULT 1",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.fingerprint_books column");
+                }
+            }
+        }
 
-        let mut out: std::collections::HashMap<String, BookLabels> =
-            std::collections::HashMap::new();
-        for row in rows {
-            let (path, name, kind) = row?;
-            let kind = kind.parse::<TagKind>().unwrap_or(TagKind::Tag);
-            let entry = out.entry(path).or_default();
-            match kind {
-                TagKind::Tag => entry.tags.push(name),
-                TagKind::Collection => {
-                    if entry.collection.is_none() {
-                        entry.collection = Some(name);
-                    }
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN furniture_sample_pages INTEGER NOT NULL DEFAULT 8",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.furniture_sample_pages column");
                 }
             }
         }
 
-        for labels in out.values_mut() {
-            labels.normalize();
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN furniture_min_fraction REAL NOT NULL DEFAULT 0.6",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.furniture_min_fraction column");
+                }
+            }
         }
 
-        Ok(out)
-    }
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN library_filters_json TEXT NOT NULL DEFAULT '{}'",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.library_filters_json column");
+                }
+            }
+        }
 
-    pub fn list_tag_names(&self, kind: TagKind) -> anyhow::Result<Vec<String>> {
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN generation INTEGER NOT NULL DEFAULT 0",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.generation column");
+                }
+            }
+        }
+
+        match self
+            .conn
+            .execute("ALTER TABLE settings ADD COLUMN last_scan_at INTEGER", [])
+        {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.last_scan_at column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN mouse_capture INTEGER NOT NULL DEFAULT 1",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.mouse_capture column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN reader_minimap INTEGER NOT NULL DEFAULT 1",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.reader_minimap column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN reader_text_width INTEGER NOT NULL DEFAULT 0",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.reader_text_width column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN graphics_terminal TEXT NOT NULL DEFAULT ''",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.graphics_terminal column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN tick_rate_ms INTEGER NOT NULL DEFAULT 250",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.tick_rate_ms column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN library_grid INTEGER NOT NULL DEFAULT 0",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.library_grid column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN autosave_secs INTEGER NOT NULL DEFAULT 30",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.autosave_secs column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN downscale_filter TEXT NOT NULL DEFAULT 'auto'",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.downscale_filter column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN reader_line_spacing INTEGER NOT NULL DEFAULT 10",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.reader_line_spacing column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE settings ADD COLUMN confirm_quit INTEGER NOT NULL DEFAULT 1",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add settings.confirm_quit column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE books ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add books.favorite column");
+                }
+            }
+        }
+
+        match self
+            .conn
+            .execute("ALTER TABLE books ADD COLUMN author TEXT", [])
+        {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add books.author column");
+                }
+            }
+        }
+
+        match self
+            .conn
+            .execute("ALTER TABLE books ADD COLUMN status TEXT", [])
+        {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add books.status column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE book_reader_state ADD COLUMN image_fit TEXT NOT NULL DEFAULT 'page'",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add book_reader_state.image_fit column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE book_reader_state ADD COLUMN rotation INTEGER NOT NULL DEFAULT 0",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add book_reader_state.rotation column");
+                }
+            }
+        }
+
+        match self.conn.execute(
+            "ALTER TABLE books ADD COLUMN archived INTEGER NOT NULL DEFAULT 0",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add books.archived column");
+                }
+            }
+        }
+
+        // Cached page count and the file stamp it was counted at; NULL until first counted.
+        for column in ["page_count", "page_count_size", "page_count_mtime"] {
+            match self.conn.execute(
+                &format!("ALTER TABLE books ADD COLUMN {column} INTEGER"),
+                [],
+            ) {
+                Ok(_) => {}
+                Err(err) => {
+                    let msg = err.to_string();
+                    if !msg.contains("duplicate column name") {
+                        return Err(err).with_context(|| format!("add books.{column} column"));
+                    }
+                }
+            }
+        }
+
+        // What the file holds, found along with the page count; NULL until then.
+        match self
+            .conn
+            .execute("ALTER TABLE books ADD COLUMN kind TEXT", [])
+        {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add books.kind column");
+                }
+            }
+        }
+
+        // Content hashes from the last duplicate pass or scan and the file stamp they were taken
+        // at.
+        for (column, kind) in [
+            ("file_size", "INTEGER"),
+            ("file_mtime", "INTEGER"),
+            ("quick_hash", "TEXT"),
+            ("tail_hash", "TEXT"),
+            ("full_hash", "TEXT"),
+        ] {
+            match self
+                .conn
+                .execute(&format!("ALTER TABLE books ADD COLUMN {column} {kind}"), [])
+            {
+                Ok(_) => {}
+                Err(err) => {
+                    let msg = err.to_string();
+                    if !msg.contains("duplicate column name") {
+                        return Err(err).with_context(|| format!("add books.{column} column"));
+                    }
+                }
+            }
+        }
+
+        // Position within the page; rows saved before these columns keep NULL.
+        for column in ["scroll", "pan_x", "pan_y"] {
+            match self.conn.execute(
+                &format!("ALTER TABLE book_progress ADD COLUMN {column} INTEGER"),
+                [],
+            ) {
+                Ok(_) => {}
+                Err(err) => {
+                    let msg = err.to_string();
+                    if !msg.contains("duplicate column name") {
+                        return Err(err)
+                            .with_context(|| format!("add book_progress.{column} column"));
+                    }
+                }
+            }
+        }
+
+        // Where in the page a bookmark was made; bookmarks from before keep NULL and open at
+        // the page top.
+        for column in ["scroll", "zoom_percent", "pan_x", "pan_y"] {
+            match self.conn.execute(
+                &format!("ALTER TABLE bookmarks ADD COLUMN {column} INTEGER"),
+                [],
+            ) {
+                Ok(_) => {}
+                Err(err) => {
+                    let msg = err.to_string();
+                    if !msg.contains("duplicate column name") {
+                        return Err(err).with_context(|| format!("add bookmarks.{column} column"));
+                    }
+                }
+            }
+        }
+
+        // The book's number in a series; only set on series rows.
+        match self
+            .conn
+            .execute("ALTER TABLE book_tags ADD COLUMN number INTEGER", [])
+        {
+            Ok(_) => {}
+            Err(err) => {
+                let msg = err.to_string();
+                if !msg.contains("duplicate column name") {
+                    return Err(err).context("add book_tags.number column");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn load_settings(&self) -> anyhow::Result<Settings> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT reader_mode, reader_text_mode, reader_trim_headers_footers, reader_invert_colors, kitty_image_quality, theme, scan_scope, watch_library, sort_mode, library_roots_json, key_bindings_json, auto_crop, external_viewer, library_decorations, reader_detect_columns, resume_last_book, image_cache_limit, remote_mode, furniture_sample_pages, furniture_min_fraction, fingerprint_books, last_scan_at, mouse_capture, reader_minimap, reader_text_width, graphics_terminal, tick_rate_ms, library_grid, autosave_secs, downscale_filter, reader_line_spacing, confirm_quit FROM settings WHERE id = 1",
+                [],
+                |row| {
+                    let flag = |idx: usize| -> rusqlite::Result<bool> {
+                        Ok(row.get::<_, i64>(idx)? != 0)
+                    };
+                    let json = |idx: usize| -> rusqlite::Result<serde_json::Value> {
+                        let json: String = row.get(idx)?;
+                        Ok(serde_json::from_str(&json).unwrap_or_default())
+                    };
+                    Ok(SettingsRow {
+                        reader_mode: row.get(0)?,
+                        reader_text_mode: row.get(1)?,
+                        reader_trim_headers_footers: flag(2)?,
+                        reader_invert_colors: flag(3)?,
+                        kitty_image_quality: row.get(4)?,
+                        theme: row.get(5)?,
+                        scan_scope: row.get(6)?,
+                        watch_library: flag(7)?,
+                        sort_mode: row.get(8)?,
+                        library_roots: json(9)?,
+                        key_bindings: json(10)?,
+                        auto_crop: row.get(11)?,
+                        external_viewer: row.get(12)?,
+                        library_decorations: flag(13)?,
+                        reader_detect_columns: flag(14)?,
+                        resume_last_book: flag(15)?,
+                        image_cache_limit: row.get(16)?,
+                        remote_mode: flag(17)?,
+                        furniture_sample_pages: row.get(18)?,
+                        furniture_min_fraction: row.get(19)?,
+                        fingerprint_books: flag(20)?,
+                        last_scan_at: row.get(21)?,
+                        mouse_capture: flag(22)?,
+                        reader_minimap: flag(23)?,
+                        reader_text_width: row.get(24)?,
+                        graphics_terminal: row.get(25)?,
+                        tick_rate_ms: row.get(26)?,
+                        library_grid: flag(27)?,
+                        autosave_secs: row.get(28)?,
+                        downscale_filter: row.get(29)?,
+                        reader_line_spacing: row.get(30)?,
+                        confirm_quit: flag(31)?,
+                    })
+                },
+            )
+            .optional()?;
+        let row = row.unwrap_or_else(|| SettingsRow::from(&Settings::default()));
+        Ok(row.into_settings().0)
+    }
+
+    pub fn save_settings(&self, settings: &Settings) -> anyhow::Result<()> {
+        let mut settings = settings.clone();
+        settings.normalize();
+        let library_roots_json = serde_json::to_string(&settings.library_roots)?;
+        let key_bindings_json = serde_json::to_string(&settings.key_bindings)?;
+
+        self.conn.execute(
+            "UPDATE settings SET reader_mode = ?, reader_text_mode = ?, reader_trim_headers_footers = ?, reader_invert_colors = ?, kitty_image_quality = ?, theme = ?, scan_scope = ?, watch_library = ?, sort_mode = ?, library_roots_json = ?, key_bindings_json = ?, auto_crop = ?, external_viewer = ?, library_decorations = ?, reader_detect_columns = ?, resume_last_book = ?, image_cache_limit = ?, remote_mode = ?, furniture_sample_pages = ?, furniture_min_fraction = ?, fingerprint_books = ?, last_scan_at = ?, mouse_capture = ?, reader_minimap = ?, reader_text_width = ?, graphics_terminal = ?, tick_rate_ms = ?, library_grid = ?, autosave_secs = ?, downscale_filter = ?, reader_line_spacing = ?, confirm_quit = ? WHERE id = 1",
+            rusqlite::params![
+                settings.reader_mode.as_str(),
+                settings.reader_text_mode.as_str(),
+                i64::from(settings.reader_trim_headers_footers),
+                i64::from(settings.reader_invert_colors),
+                settings.kitty_image_quality.as_str(),
+                settings.theme.as_str(),
+                settings.scan_scope.as_str(),
+                i64::from(settings.watch_library),
+                settings.sort_mode.as_str(),
+                library_roots_json,
+                key_bindings_json,
+                settings.auto_crop.as_str(),
+                settings.external_viewer.as_str(),
+                i64::from(settings.library_decorations),
+                i64::from(settings.reader_detect_columns),
+                i64::from(settings.resume_last_book),
+                settings.image_cache_limit.as_str(),
+                i64::from(settings.remote_mode),
+                settings.furniture_sample_pages,
+                f64::from(settings.furniture_min_fraction),
+                i64::from(settings.fingerprint_books),
+                settings.last_scan_at,
+                i64::from(settings.mouse_capture),
+                i64::from(settings.reader_minimap),
+                settings.reader_text_width,
+                settings.graphics_terminal.as_str(),
+                settings.tick_rate_ms,
+                i64::from(settings.library_grid),
+                settings.autosave_secs,
+                settings.downscale_filter.as_str(),
+                settings.reader_line_spacing,
+                i64::from(settings.confirm_quit),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Library filters from the last session, as JSON written by the application layer; `{}`
+    /// until the first save.
+    pub fn load_library_filters_json(&self) -> anyhow::Result<String> {
+        let json = self
+            .conn
+            .query_row(
+                "SELECT library_filters_json FROM settings WHERE id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(json.unwrap_or_else(|| "{}".to_string()))
+    }
+
+    pub fn save_library_filters_json(&self, json: &str) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE settings SET library_filters_json = ? WHERE id = 1",
+            [json],
+        )?;
+        Ok(())
+    }
+
+    /// Counter bumped by every session save, so an instance can tell that another one saved
+    /// since it loaded.
+    pub fn load_generation(&self) -> anyhow::Result<i64> {
+        let generation = self
+            .conn
+            .query_row("SELECT generation FROM settings WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(generation.unwrap_or(0))
+    }
+
+    /// Runs `write` in one transaction, so a batch of writes costs one commit and lands all
+    /// together or not at all.
+    pub fn write_batch<T>(
+        &self,
+        write: impl FnOnce(&Self) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let tx = self.conn.unchecked_transaction()?;
+        let out = write(self)?;
+        tx.commit()?;
+        Ok(out)
+    }
+
+    /// Runs `write` in a transaction of its own, or as part of a `write_batch` already open:
+    /// SQLite transactions do not nest.
+    fn atomically(
+        &self,
+        write: impl FnOnce(&Connection) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        if !self.conn.is_autocommit() {
+            return write(&self.conn);
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        write(&tx)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Marks a session save; returns the new generation.
+    pub fn bump_generation(&self) -> anyhow::Result<i64> {
+        self.conn.execute(
+            "UPDATE settings SET generation = generation + 1 WHERE id = 1",
+            [],
+        )?;
+        self.load_generation()
+    }
+
+    /// Adds a book. Known books keep their stored title and author; `set_book_metadata`
+    /// replaces them.
+    pub fn upsert_book(&self, book: &Book) -> anyhow::Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO books (path, title, author) VALUES (?, ?, ?)
+            ON CONFLICT(path) DO NOTHING
+            "#,
+            (&book.path, &book.title, &book.author),
+        )?;
+        Ok(())
+    }
+
+    pub fn set_book_metadata(
+        &self,
+        path: &str,
+        title: &str,
+        author: Option<&str>,
+    ) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE books SET title = ?, author = ? WHERE path = ?",
+            (title, author, path),
+        )?;
+        Ok(())
+    }
+
+    pub fn list_books(&self) -> anyhow::Result<Vec<Book>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, title, last_opened, favorite, author, status, archived FROM books ORDER BY title COLLATE NOCASE",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let favorite: i64 = row.get(3)?;
+            let status: Option<String> = row.get(5)?;
+            let archived: i64 = row.get(6)?;
+            Ok(Book {
+                path: row.get(0)?,
+                title: row.get(1)?,
+                last_opened: row.get(2)?,
+                favorite: favorite != 0,
+                author: row.get(4)?,
+                status: status.and_then(|s| s.parse().ok()).unwrap_or_default(),
+                archived: archived != 0,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Cached page counts of books that have been counted.
+    pub fn list_page_counts(&self) -> anyhow::Result<std::collections::HashMap<String, PageCount>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT path, page_count, page_count_size, page_count_mtime, kind FROM books
+            WHERE page_count IS NOT NULL
+                AND page_count_size IS NOT NULL
+                AND page_count_mtime IS NOT NULL
+            "#,
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let path: String = row.get(0)?;
+            let pages: i64 = row.get(1)?;
+            let size_bytes: i64 = row.get(2)?;
+            let modified_secs: i64 = row.get(3)?;
+            let kind: Option<String> = row.get(4)?;
+            Ok((path, pages, size_bytes, modified_secs, kind))
+        })?;
+
+        let mut out = std::collections::HashMap::new();
+        for row in rows {
+            let (path, pages, size_bytes, modified_secs, kind) = row?;
+            let (Ok(pages), Ok(size_bytes)) = (u32::try_from(pages), u64::try_from(size_bytes))
+            else {
+                continue;
+            };
+            out.insert(
+                path,
+                PageCount {
+                    pages,
+                    size_bytes,
+                    modified_secs,
+                    kind: kind.and_then(|kind| kind.parse().ok()),
+                },
+            );
+        }
+        Ok(out)
+    }
+
+    pub fn set_page_count(&self, path: &str, count: &PageCount) -> anyhow::Result<()> {
+        self.conn.execute(
+            r#"
+            UPDATE books SET page_count = ?, page_count_size = ?, page_count_mtime = ?, kind = ?
+            WHERE path = ?
+            "#,
+            (
+                i64::from(count.pages),
+                i64::try_from(count.size_bytes).unwrap_or(i64::MAX),
+                count.modified_secs,
+                count.kind.map(|kind| kind.as_str()),
+                path,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Content hashes of books that have been through a duplicate pass or a scan.
+    pub fn list_fingerprints(
+        &self,
+    ) -> anyhow::Result<std::collections::HashMap<String, FileFingerprint>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT path, file_size, file_mtime, quick_hash, tail_hash, full_hash FROM books
+            WHERE file_size IS NOT NULL
+                AND file_mtime IS NOT NULL
+                AND quick_hash IS NOT NULL
+            "#,
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let path: String = row.get(0)?;
+            let size_bytes: i64 = row.get(1)?;
+            let modified_secs: i64 = row.get(2)?;
+            let quick_hash: String = row.get(3)?;
+            let tail_hash: Option<String> = row.get(4)?;
+            let full_hash: Option<String> = row.get(5)?;
+            Ok((
+                path,
+                size_bytes,
+                modified_secs,
+                quick_hash,
+                tail_hash,
+                full_hash,
+            ))
+        })?;
+
+        let mut out = std::collections::HashMap::new();
+        for row in rows {
+            let (path, size_bytes, modified_secs, quick_hash, tail_hash, full_hash) = row?;
+            let Ok(size_bytes) = u64::try_from(size_bytes) else {
+                continue;
+            };
+            out.insert(
+                path,
+                FileFingerprint {
+                    size_bytes,
+                    modified_secs,
+                    quick_hash,
+                    tail_hash,
+                    full_hash,
+                },
+            );
+        }
+        Ok(out)
+    }
+
+    pub fn set_fingerprint(&self, path: &str, fingerprint: &FileFingerprint) -> anyhow::Result<()> {
+        self.conn.execute(
+            r#"
+            UPDATE books
+            SET file_size = ?, file_mtime = ?, quick_hash = ?, tail_hash = ?, full_hash = ?
+            WHERE path = ?
+            "#,
+            (
+                i64::try_from(fingerprint.size_bytes).unwrap_or(i64::MAX),
+                fingerprint.modified_secs,
+                &fingerprint.quick_hash,
+                fingerprint.tail_hash.as_deref(),
+                fingerprint.full_hash.as_deref(),
+                path,
+            ),
+        )?;
+        Ok(())
+    }
+
+    pub fn list_added_at(&self) -> anyhow::Result<std::collections::HashMap<String, i64>> {
+        let mut stmt = self.conn.prepare("SELECT path, added_at FROM books")?;
+        let rows = stmt.query_map([], |row| {
+            let path: String = row.get(0)?;
+            let added_at: i64 = row.get(1)?;
+            Ok((path, added_at))
+        })?;
+
+        let mut out = std::collections::HashMap::new();
+        for row in rows {
+            let (path, added_at) = row?;
+            out.insert(path, added_at);
+        }
+        Ok(out)
+    }
+
+    pub fn set_last_opened(&self, path: &str, last_opened: i64) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE books SET last_opened = ? WHERE path = ?",
+            (last_opened, path),
+        )?;
+        Ok(())
+    }
+
+    pub fn set_favorite(&self, path: &str, favorite: bool) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE books SET favorite = ? WHERE path = ?",
+            (i64::from(favorite), path),
+        )?;
+        Ok(())
+    }
+
+    pub fn set_archived(&self, path: &str, archived: bool) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE books SET archived = ? WHERE path = ?",
+            (i64::from(archived), path),
+        )?;
+        Ok(())
+    }
+
+    /// Stores a book's reading status; unread is kept as NULL.
+    pub fn set_status(&self, path: &str, status: ReadingStatus) -> anyhow::Result<()> {
+        let status = (status != ReadingStatus::Unread).then(|| status.as_str());
+        self.conn
+            .execute("UPDATE books SET status = ? WHERE path = ?", (status, path))?;
+        Ok(())
+    }
+
+    pub fn list_labels_by_path(
+        &self,
+    ) -> anyhow::Result<std::collections::HashMap<String, BookLabels>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT bt.path, t.name, t.kind, bt.number
+            FROM book_tags bt
+            JOIN tags t ON t.id = bt.tag_id
+            ORDER BY bt.path, t.kind, t.name COLLATE NOCASE
+            "#,
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            let path: String = row.get(0)?;
+            let name: String = row.get(1)?;
+            let kind: String = row.get(2)?;
+            let number: Option<u32> = row.get(3)?;
+            Ok((path, name, kind, number))
+        })?;
+
+        let mut out: std::collections::HashMap<String, BookLabels> =
+            std::collections::HashMap::new();
+        for row in rows {
+            let (path, name, kind, number) = row?;
+            let kind = kind.parse::<TagKind>().unwrap_or(TagKind::Tag);
+            let entry = out.entry(path).or_default();
+            match kind {
+                TagKind::Tag => entry.tags.push(name),
+                TagKind::Collection => {
+                    if entry.collection.is_none() {
+                        entry.collection = Some(name);
+                    }
+                }
+                TagKind::Author => {
+                    if entry.author.is_none() {
+                        entry.author = Some(name);
+                    }
+                }
+                TagKind::Series => {
+                    if entry.series.is_none() {
+                        entry.series = Some((name, number));
+                    }
+                }
+            }
+        }
+
+        for labels in out.values_mut() {
+            labels.normalize();
+        }
+
+        Ok(out)
+    }
+
+    pub fn list_tag_names(&self, kind: TagKind) -> anyhow::Result<Vec<String>> {
         let mut stmt = self
             .conn
             .prepare("SELECT name FROM tags WHERE kind = ? ORDER BY name COLLATE NOCASE")?;
@@ -449,45 +1294,239 @@ impl Storage {
     }
 
     pub fn save_labels(&self, path: &str, labels: &BookLabels) -> anyhow::Result<()> {
-        let mut labels = labels.clone();
-        labels.normalize();
+        self.atomically(|conn| write_labels(conn, path, labels))
+    }
 
-        let tx = self.conn.unchecked_transaction()?;
-        tx.execute("DELETE FROM book_tags WHERE path = ?", [path])?;
+    /// Writes favorites, collections, tags, authors, series and the label catalog to a JSON
+    /// file keyed by book path. Returns the number of books written; books without labels or
+    /// favorite are left out.
+    pub fn export_labels(&self, file: impl AsRef<Path>) -> anyhow::Result<usize> {
+        let file = file.as_ref();
+        let mut books = std::collections::BTreeMap::new();
+        let mut labels_by_path = self.list_labels_by_path()?;
+        for book in self.list_books()? {
+            let labels = labels_by_path.remove(&book.path).unwrap_or_default();
+            if !book.favorite && labels.is_empty() {
+                continue;
+            }
+            books.insert(
+                book.path,
+                ExportedBookLabels {
+                    favorite: book.favorite,
+                    series_number: labels.series_number(),
+                    series: labels.series.map(|(name, _)| name),
+                    collection: labels.collection,
+                    tags: labels.tags,
+                    author: labels.author,
+                },
+            );
+        }
+        let document = LabelsDocument {
+            version: LABELS_DOCUMENT_VERSION,
+            tags: self.list_tag_names(TagKind::Tag)?,
+            collections: self.list_tag_names(TagKind::Collection)?,
+            authors: self.list_tag_names(TagKind::Author)?,
+            series: self.list_tag_names(TagKind::Series)?,
+            books,
+        };
+        let count = document.books.len();
+        let json = serde_json::to_string_pretty(&document)?;
+        std::fs::write(file, json).with_context(|| format!("write {}", file.display()))?;
+        Ok(count)
+    }
 
-        if let Some(collection) = labels.collection.as_deref() {
-            let id = get_or_create_tag_id(&tx, collection, TagKind::Collection)
-                .context("get/create collection tag")?;
-            tx.execute(
-                "INSERT OR IGNORE INTO book_tags (path, tag_id) VALUES (?, ?)",
-                (path, id),
-            )?;
+    /// Merges a file written by `export_labels` into the library in one transaction. Tags are
+    /// added to the book's own, an imported collection, author or series replaces the local one,
+    /// and favorites are only ever set. Books not in the local library are skipped and counted.
+    pub fn import_labels(&self, file: impl AsRef<Path>) -> anyhow::Result<LabelImportReport> {
+        let file = file.as_ref();
+        let json =
+            std::fs::read_to_string(file).with_context(|| format!("read {}", file.display()))?;
+        let document: LabelsDocument = serde_json::from_str(&json)
+            .with_context(|| format!("parse labels file {}", file.display()))?;
+        if document.version > LABELS_DOCUMENT_VERSION {
+            anyhow::bail!(
+                "labels file version {} is newer than supported ({LABELS_DOCUMENT_VERSION})",
+                document.version
+            );
         }
 
-        for tag in &labels.tags {
-            let id = get_or_create_tag_id(&tx, tag, TagKind::Tag).context("get/create tag")?;
-            tx.execute(
-                "INSERT OR IGNORE INTO book_tags (path, tag_id) VALUES (?, ?)",
-                (path, id),
-            )?;
+        let known_paths: std::collections::HashSet<String> =
+            self.list_books()?.into_iter().map(|b| b.path).collect();
+        let mut labels_by_path = self.list_labels_by_path()?;
+        let mut tag_names = self.list_tag_names(TagKind::Tag)?;
+        let mut collection_names = self.list_tag_names(TagKind::Collection)?;
+        let mut author_names = self.list_tag_names(TagKind::Author)?;
+        let mut series_names = self.list_tag_names(TagKind::Series)?;
+        let mut report = LabelImportReport::default();
+
+        let tx = self.conn.unchecked_transaction()?;
+        for name in &document.tags {
+            let name = local_label_name(&mut tag_names, name);
+            get_or_create_tag_id(&tx, &name, TagKind::Tag).context("get/create tag")?;
+        }
+        for name in &document.collections {
+            let name = local_label_name(&mut collection_names, name);
+            get_or_create_tag_id(&tx, &name, TagKind::Collection)
+                .context("get/create collection tag")?;
+        }
+        for name in &document.authors {
+            let name = local_label_name(&mut author_names, name);
+            get_or_create_tag_id(&tx, &name, TagKind::Author).context("get/create author tag")?;
+        }
+        for name in &document.series {
+            let name = local_label_name(&mut series_names, name);
+            get_or_create_tag_id(&tx, &name, TagKind::Series).context("get/create series tag")?;
         }
 
+        for (path, imported) in &document.books {
+            if !known_paths.contains(path) {
+                report.skipped_books += 1;
+                continue;
+            }
+            let mut labels = labels_by_path.remove(path).unwrap_or_default();
+            for tag in &imported.tags {
+                let tag = local_label_name(&mut tag_names, tag);
+                if !tag.is_empty() && !labels.tags.contains(&tag) {
+                    labels.tags.push(tag);
+                }
+            }
+            if let Some(collection) = imported.collection.as_deref()
+                && !collection.trim().is_empty()
+            {
+                labels.collection = Some(local_label_name(&mut collection_names, collection));
+            }
+            if let Some(author) = imported.author.as_deref()
+                && !author.trim().is_empty()
+            {
+                labels.author = Some(local_label_name(&mut author_names, author));
+            }
+            if let Some(series) = imported.series.as_deref()
+                && !series.trim().is_empty()
+            {
+                labels.series = Some((
+                    local_label_name(&mut series_names, series),
+                    imported.series_number,
+                ));
+            }
+            write_labels(&tx, path, &labels)?;
+            if imported.favorite {
+                tx.execute("UPDATE books SET favorite = 1 WHERE path = ?", [path])?;
+            }
+            report.merged_books += 1;
+        }
         tx.commit()?;
+        Ok(report)
+    }
+
+    /// Writes `settings` to a JSON file, leaving out when this library was last scanned. The
+    /// database is not touched, so a session can export settings it has not saved.
+    pub fn export_settings(settings: &Settings, file: impl AsRef<Path>) -> anyhow::Result<()> {
+        let file = file.as_ref();
+        let mut document = serde_json::Map::new();
+        document.insert("version".to_string(), SETTINGS_DOCUMENT_VERSION.into());
+        let serde_json::Value::Object(fields) = serde_json::to_value(SettingsRow::from(settings))?
+        else {
+            anyhow::bail!("settings did not serialize to an object");
+        };
+        document.extend(fields);
+        let json = serde_json::to_string_pretty(&document)?;
+        std::fs::write(file, json).with_context(|| format!("write {}", file.display()))?;
         Ok(())
     }
 
+    /// Replaces the stored settings with those in a file written by `export_settings`. Each
+    /// field is read as the database reads its own: a value that does not parse gets the
+    /// default and is named in the report, and fields the file leaves out get the default.
+    pub fn import_settings(&self, file: impl AsRef<Path>) -> anyhow::Result<SettingsImport> {
+        let file = file.as_ref();
+        let json =
+            std::fs::read_to_string(file).with_context(|| format!("read {}", file.display()))?;
+        let document: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&json)
+            .with_context(|| format!("parse settings file {}", file.display()))?;
+        let version = document
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(0);
+        if version > u64::from(SETTINGS_DOCUMENT_VERSION) {
+            anyhow::bail!(
+                "settings file version {version} is newer than supported ({SETTINGS_DOCUMENT_VERSION})"
+            );
+        }
+
+        let mut row = SettingsRow::from(&Settings::default());
+        row.last_scan_at = self.load_settings()?.last_scan_at;
+        let mut rejected = Vec::new();
+        row.merge_document(&document, &mut rejected);
+        let (settings, unparsed) = row.into_settings();
+        rejected.extend(unparsed);
+        self.save_settings(&settings)?;
+        Ok(SettingsImport {
+            settings,
+            rejected: rejected.into_iter().map(str::to_string).collect(),
+        })
+    }
+
+    /// Copies the whole database to `path` with sqlite's backup API.
+    pub fn backup_to(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        self.conn
+            .backup(rusqlite::MAIN_DB, path, None)
+            .with_context(|| format!("back up database to {}", path.display()))
+    }
+
+    /// Backs the database at `db_path` up to a timestamped file next to it, then removes all
+    /// but the newest `keep` backups. Returns the new backup.
+    pub fn backup_next_to(&self, db_path: &Path, keep: usize) -> anyhow::Result<PathBuf> {
+        let stamp: String = self.conn.query_row(
+            "SELECT strftime('%Y%m%d-%H%M%S', 'now', 'localtime')",
+            [],
+            |row| row.get(0),
+        )?;
+        let mut path = backup_path(db_path, &stamp);
+        // A second backup within the same second gets a counter after the time.
+        let mut count = 1;
+        while path.exists() {
+            count += 1;
+            path = backup_path(db_path, &format!("{stamp}-{count}"));
+        }
+        self.backup_to(&path)?;
+        for stale in list_backups(db_path)?.into_iter().skip(keep.max(1)) {
+            std::fs::remove_file(&stale)
+                .with_context(|| format!("remove old backup {}", stale.display()))?;
+        }
+        Ok(path)
+    }
+
     pub fn list_bookmarks_by_path(
         &self,
     ) -> anyhow::Result<std::collections::HashMap<String, Vec<Bookmark>>> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT path, page, label FROM bookmarks ORDER BY path, page, label")?;
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT path, page, label, scroll, zoom_percent, pan_x, pan_y
+            FROM bookmarks ORDER BY path, page, label
+            "#,
+        )?;
         let rows = stmt.query_map([], |row| {
             let path: String = row.get(0)?;
             let page: i64 = row.get(1)?;
             let label: String = row.get(2)?;
+            let scroll: Option<i64> = row.get(3)?;
+            let zoom_percent: Option<i64> = row.get(4)?;
+            let pan_x: Option<i64> = row.get(5)?;
+            let pan_y: Option<i64> = row.get(6)?;
             let page = u32::try_from(page).unwrap_or(1).max(1);
-            Ok((path, Bookmark { page, label }))
+            let text_view = scroll
+                .and_then(|scroll| u16::try_from(scroll).ok())
+                .map(|scroll| BookmarkView::Text { scroll });
+            let image_view = || {
+                Some(BookmarkView::Image {
+                    zoom_percent: u16::try_from(zoom_percent?).ok()?,
+                    pan_px: (u32::try_from(pan_x?).ok()?, u32::try_from(pan_y?).ok()?),
+                })
+            };
+            let view = text_view.or_else(image_view);
+            Ok((path, Bookmark { page, label, view }))
         })?;
 
         let mut out: std::collections::HashMap<String, Vec<Bookmark>> =
@@ -500,17 +1539,44 @@ impl Storage {
     }
 
     pub fn replace_bookmarks(&self, path: &str, bookmarks: &[Bookmark]) -> anyhow::Result<()> {
-        let tx = self.conn.unchecked_transaction()?;
-        tx.execute("DELETE FROM bookmarks WHERE path = ?", [path])?;
-        for bookmark in bookmarks {
-            let page = bookmark.page.max(1) as i64;
-            tx.execute(
-                "INSERT OR IGNORE INTO bookmarks (path, page, label) VALUES (?, ?, ?)",
-                (path, page, bookmark.label.as_str()),
-            )?;
-        }
-        tx.commit()?;
-        Ok(())
+        self.atomically(|conn| {
+            conn.execute("DELETE FROM bookmarks WHERE path = ?", [path])?;
+            for bookmark in bookmarks {
+                let page = bookmark.page.max(1) as i64;
+                let (scroll, zoom_percent, pan_x, pan_y) = match bookmark.view {
+                    None => (None, None, None, None),
+                    Some(BookmarkView::Text { scroll }) => {
+                        (Some(i64::from(scroll)), None, None, None)
+                    }
+                    Some(BookmarkView::Image {
+                        zoom_percent,
+                        pan_px: (x, y),
+                    }) => (
+                        None,
+                        Some(i64::from(zoom_percent)),
+                        Some(i64::from(x)),
+                        Some(i64::from(y)),
+                    ),
+                };
+                conn.execute(
+                    r#"
+                    INSERT OR IGNORE INTO bookmarks
+                        (path, page, label, scroll, zoom_percent, pan_x, pan_y)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                    (
+                        path,
+                        page,
+                        bookmark.label.as_str(),
+                        scroll,
+                        zoom_percent,
+                        pan_x,
+                        pan_y,
+                    ),
+                )?;
+            }
+            Ok(())
+        })
     }
 
     pub fn list_notes_by_path(
@@ -527,72 +1593,878 @@ impl Storage {
             Ok((path, Note { page, body }))
         })?;
 
-        let mut out: std::collections::HashMap<String, Vec<Note>> =
-            std::collections::HashMap::new();
-        for row in rows {
-            let (path, note) = row?;
-            out.entry(path).or_default().push(note);
+        let mut out: std::collections::HashMap<String, Vec<Note>> =
+            std::collections::HashMap::new();
+        for row in rows {
+            let (path, note) = row?;
+            out.entry(path).or_default().push(note);
+        }
+        Ok(out)
+    }
+
+    /// Writes a book's full note list, so edited or deleted notes leave no old rows behind.
+    pub fn replace_notes(&self, path: &str, notes: &[Note]) -> anyhow::Result<()> {
+        self.atomically(|conn| {
+            conn.execute("DELETE FROM notes WHERE path = ?", [path])?;
+            for note in notes {
+                let page = note.page.max(1) as i64;
+                conn.execute(
+                    "INSERT OR IGNORE INTO notes (path, page, body) VALUES (?, ?, ?)",
+                    (path, page, note.body.as_str()),
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    pub fn delete_book_by_path(&self, path: &str) -> anyhow::Result<()> {
+        self.conn
+            .execute("DELETE FROM books WHERE path = ?", [path])?;
+        Ok(())
+    }
+
+    /// Moves a book row to a new path, carrying over progress, reader state, reading time,
+    /// bookmarks, notes, labels and its place in its collection. An existing row at `to` is
+    /// replaced.
+    pub fn rename_book_path(&self, from: &str, to: &str, title: &str) -> anyhow::Result<()> {
+        if from == to {
+            return Ok(());
+        }
+
+        self.atomically(|conn| {
+            conn.execute("DELETE FROM books WHERE path = ?", [to])?;
+            let inserted = conn.execute(
+                r#"
+                INSERT INTO books (path, title, added_at, last_opened, favorite, author, status, archived)
+                SELECT ?, ?, added_at, last_opened, favorite, author, status, archived
+                FROM books WHERE path = ?
+                "#,
+                (to, title, from),
+            )?;
+            if inserted == 0 {
+                anyhow::bail!("book not found: {from}");
+            }
+            for table in [
+                "book_progress",
+                "book_reader_state",
+                "book_reading_time",
+                "bookmarks",
+                "notes",
+                "book_tags",
+                "collection_positions",
+            ] {
+                conn.execute(
+                    &format!("UPDATE {table} SET path = ? WHERE path = ?"),
+                    (to, from),
+                )
+                .with_context(|| format!("move {table} rows"))?;
+            }
+            conn.execute("DELETE FROM books WHERE path = ?", [from])?;
+            Ok(())
+        })
+    }
+
+    /// Deletes books whose file is gone (except `keep`), rows left behind by deleted books and
+    /// tags no book uses, then compacts the database. The deletes commit together or not at all.
+    pub fn cleanup(&self, keep: &[String]) -> anyhow::Result<CleanupReport> {
+        let bytes_before = self.database_size_bytes()?;
+
+        let tx = self.conn.unchecked_transaction()?;
+        let paths = {
+            let mut stmt = tx.prepare("SELECT path FROM books ORDER BY path")?;
+            stmt.query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        let removed_books: Vec<String> = paths
+            .into_iter()
+            .filter(|path| !keep.contains(path) && !decode_path(path).exists())
+            .collect();
+        for path in &removed_books {
+            tx.execute("DELETE FROM books WHERE path = ?", [path])
+                .with_context(|| format!("delete book {path}"))?;
+        }
+
+        let mut orphaned_rows = 0;
+        for table in [
+            "book_progress",
+            "book_reader_state",
+            "book_reading_time",
+            "bookmarks",
+            "notes",
+            "book_tags",
+            "collection_positions",
+        ] {
+            orphaned_rows += tx
+                .execute(
+                    &format!("DELETE FROM {table} WHERE path NOT IN (SELECT path FROM books)"),
+                    [],
+                )
+                .with_context(|| format!("delete orphaned {table} rows"))?;
+        }
+        orphaned_rows += tx.execute(
+            "DELETE FROM book_tags WHERE tag_id NOT IN (SELECT id FROM tags)",
+            [],
+        )?;
+
+        let removed_tags = {
+            let mut stmt = tx.prepare(
+                "SELECT kind, name FROM tags WHERE id NOT IN (SELECT tag_id FROM book_tags) ORDER BY kind, name",
+            )?;
+            stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter_map(|(kind, name)| Some((kind.parse::<TagKind>().ok()?, name)))
+            .collect::<Vec<_>>()
+        };
+        tx.execute(
+            "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM book_tags)",
+            [],
+        )?;
+        tx.commit()?;
+
+        // VACUUM cannot run inside a transaction; an interrupted one leaves the data intact.
+        self.conn
+            .execute_batch("VACUUM")
+            .context("vacuum database")?;
+        Ok(CleanupReport {
+            removed_books,
+            orphaned_rows,
+            removed_tags,
+            bytes_before,
+            bytes_after: self.database_size_bytes()?,
+        })
+    }
+
+    fn database_size_bytes(&self) -> anyhow::Result<u64> {
+        let page_count: i64 = self
+            .conn
+            .pragma_query_value(None, "page_count", |row| row.get(0))?;
+        let page_size: i64 = self
+            .conn
+            .pragma_query_value(None, "page_size", |row| row.get(0))?;
+        Ok(u64::try_from(page_count.saturating_mul(page_size)).unwrap_or(0))
+    }
+
+    pub fn list_progress(&self) -> anyhow::Result<std::collections::HashMap<String, BookProgress>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, last_page, scroll, pan_x, pan_y FROM book_progress")?;
+        let rows = stmt.query_map([], |row| {
+            let path: String = row.get(0)?;
+            let last_page: i64 = row.get(1)?;
+            let scroll: Option<i64> = row.get(2)?;
+            let pan_x: Option<i64> = row.get(3)?;
+            let pan_y: Option<i64> = row.get(4)?;
+            Ok((path, last_page, scroll, pan_x, pan_y))
+        })?;
+
+        let mut out = std::collections::HashMap::new();
+        for row in rows {
+            let (path, last_page, scroll, pan_x, pan_y) = row?;
+            let progress = BookProgress {
+                last_page: u32::try_from(last_page).unwrap_or(1).max(1),
+                scroll: scroll.and_then(|s| u16::try_from(s).ok()),
+                pan_px: pan_x
+                    .zip(pan_y)
+                    .and_then(|(x, y)| Some((u32::try_from(x).ok()?, u32::try_from(y).ok()?))),
+            };
+            out.insert(path, progress);
+        }
+        Ok(out)
+    }
+
+    pub fn set_progress(&self, path: &str, progress: &BookProgress) -> anyhow::Result<()> {
+        let last_page = progress.last_page.max(1) as i64;
+        let (pan_x, pan_y) = progress
+            .pan_px
+            .map(|(x, y)| (i64::from(x), i64::from(y)))
+            .unzip();
+        self.conn.execute(
+            r#"
+            INSERT INTO book_progress (path, last_page, scroll, pan_x, pan_y, updated_at)
+            VALUES (?, ?, ?, ?, ?, unixepoch())
+            ON CONFLICT(path) DO UPDATE SET
+                last_page = excluded.last_page,
+                scroll = excluded.scroll,
+                pan_x = excluded.pan_x,
+                pan_y = excluded.pan_y,
+                updated_at = excluded.updated_at
+            "#,
+            (
+                path,
+                last_page,
+                progress.scroll.map(i64::from),
+                pan_x,
+                pan_y,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Total active reading time per book, in seconds.
+    pub fn list_reading_time(&self) -> anyhow::Result<std::collections::HashMap<String, u64>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, seconds FROM book_reading_time")?;
+        let rows = stmt.query_map([], |row| {
+            let path: String = row.get(0)?;
+            let seconds: i64 = row.get(1)?;
+            Ok((path, seconds))
+        })?;
+
+        let mut out = std::collections::HashMap::new();
+        for row in rows {
+            let (path, seconds) = row?;
+            out.insert(path, u64::try_from(seconds).unwrap_or(0));
+        }
+        Ok(out)
+    }
+
+    pub fn set_reading_time(&self, path: &str, seconds: u64) -> anyhow::Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO book_reading_time (path, seconds, updated_at)
+            VALUES (?, ?, unixepoch())
+            ON CONFLICT(path) DO UPDATE SET
+                seconds = excluded.seconds,
+                updated_at = excluded.updated_at
+            "#,
+            (path, i64::try_from(seconds).unwrap_or(i64::MAX)),
+        )?;
+        Ok(())
+    }
+
+    /// Each book's place in its collection's reading list; unordered books have no row.
+    pub fn list_collection_positions(
+        &self,
+    ) -> anyhow::Result<std::collections::HashMap<String, u32>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, position FROM collection_positions")?;
+        let rows = stmt.query_map([], |row| {
+            let path: String = row.get(0)?;
+            let position: i64 = row.get(1)?;
+            Ok((path, position))
+        })?;
+
+        let mut out = std::collections::HashMap::new();
+        for row in rows {
+            let (path, position) = row?;
+            if let Ok(position) = u32::try_from(position) {
+                out.insert(path, position);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Stores a book's place in its collection's reading list; `None` removes it.
+    pub fn set_collection_position(&self, path: &str, position: Option<u32>) -> anyhow::Result<()> {
+        match position {
+            Some(position) => self.conn.execute(
+                r#"
+                INSERT INTO collection_positions (path, position)
+                VALUES (?, ?)
+                ON CONFLICT(path) DO UPDATE SET position = excluded.position
+                "#,
+                (path, position),
+            )?,
+            None => self
+                .conn
+                .execute("DELETE FROM collection_positions WHERE path = ?", [path])?,
+        };
+        Ok(())
+    }
+
+    pub fn list_reader_states(
+        &self,
+    ) -> anyhow::Result<std::collections::HashMap<String, ReaderViewState>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, reader_mode, reader_text_mode, image_zoom_percent, image_fit, rotation FROM book_reader_state",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let path: String = row.get(0)?;
+            let reader_mode: String = row.get(1)?;
+            let reader_text_mode: String = row.get(2)?;
+            let image_zoom_percent: i64 = row.get(3)?;
+            let image_fit: String = row.get(4)?;
+            let rotation: i64 = row.get(5)?;
+            Ok((
+                path,
+                reader_mode,
+                reader_text_mode,
+                image_zoom_percent,
+                image_fit,
+                rotation,
+            ))
+        })?;
+
+        let mut out = std::collections::HashMap::new();
+        for row in rows {
+            let (path, reader_mode, reader_text_mode, image_zoom_percent, image_fit, rotation) =
+                row?;
+            let state = ReaderViewState {
+                reader_mode: reader_mode
+                    .parse::<ReaderMode>()
+                    .unwrap_or(ReaderMode::Text),
+                reader_text_mode: reader_text_mode
+                    .parse::<ReaderTextMode>()
+                    .unwrap_or(ReaderTextMode::Reflow),
+                image_zoom_percent: u16::try_from(image_zoom_percent).unwrap_or(100),
+                image_fit: image_fit.parse::<ImageFit>().unwrap_or(ImageFit::Page),
+                rotation: PageRotation::from_degrees(rotation),
+            };
+            out.insert(path, state);
+        }
+        Ok(out)
+    }
+
+    pub fn set_reader_state(&self, path: &str, state: &ReaderViewState) -> anyhow::Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO book_reader_state (path, reader_mode, reader_text_mode, image_zoom_percent, image_fit, rotation, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, unixepoch())
+            ON CONFLICT(path) DO UPDATE SET
+                reader_mode = excluded.reader_mode,
+                reader_text_mode = excluded.reader_text_mode,
+                image_zoom_percent = excluded.image_zoom_percent,
+                image_fit = excluded.image_fit,
+                rotation = excluded.rotation,
+                updated_at = excluded.updated_at
+            "#,
+            (
+                path,
+                state.reader_mode.as_str(),
+                state.reader_text_mode.as_str(),
+                i64::from(state.image_zoom_percent),
+                state.image_fit.as_str(),
+                i64::from(state.rotation.degrees()),
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+/// Replaces a book's collection, tags, author and series.
+fn write_labels(tx: &Connection, path: &str, labels: &BookLabels) -> anyhow::Result<()> {
+    let mut labels = labels.clone();
+    labels.normalize();
+
+    tx.execute("DELETE FROM book_tags WHERE path = ?", [path])?;
+
+    if let Some(collection) = labels.collection.as_deref() {
+        let id = get_or_create_tag_id(tx, collection, TagKind::Collection)
+            .context("get/create collection tag")?;
+        tx.execute(
+            "INSERT OR IGNORE INTO book_tags (path, tag_id) VALUES (?, ?)",
+            (path, id),
+        )?;
+    }
+
+    for tag in &labels.tags {
+        let id = get_or_create_tag_id(tx, tag, TagKind::Tag).context("get/create tag")?;
+        tx.execute(
+            "INSERT OR IGNORE INTO book_tags (path, tag_id) VALUES (?, ?)",
+            (path, id),
+        )?;
+    }
+
+    if let Some(author) = labels.author.as_deref() {
+        let id =
+            get_or_create_tag_id(tx, author, TagKind::Author).context("get/create author tag")?;
+        tx.execute(
+            "INSERT OR IGNORE INTO book_tags (path, tag_id) VALUES (?, ?)",
+            (path, id),
+        )?;
+    }
+
+    if let Some((series, number)) = &labels.series {
+        let id =
+            get_or_create_tag_id(tx, series, TagKind::Series).context("get/create series tag")?;
+        tx.execute(
+            "INSERT OR IGNORE INTO book_tags (path, tag_id, number) VALUES (?, ?, ?)",
+            (path, id, number),
+        )?;
+    }
+    Ok(())
+}
+
+/// The local spelling of a label name that differs only in case, else `name` (trimmed), which
+/// is then remembered as local.
+fn local_label_name(names: &mut Vec<String>, name: &str) -> String {
+    let name = name.trim();
+    if let Some(local) = names.iter().find(|n| n.eq_ignore_ascii_case(name)) {
+        return local.clone();
+    }
+    if !name.is_empty() {
+        names.push(name.to_string());
+    }
+    name.to_string()
+}
+
+/// Backups `Storage::backup_next_to` keeps next to the database when the app makes one.
+pub const BACKUPS_KEPT: usize = 5;
+
+/// Tables every bookshelf database has had; a file without them is not one.
+const BACKUP_REQUIRED_TABLES: &[&str] =
+    &["settings", "books", "book_progress", "bookmarks", "notes"];
+
+/// What comes before and after the time in a backup's file name: `bookshelf.db` is backed up
+/// as `bookshelf-backup-<time>.db` in the same folder.
+fn backup_name_parts(db_path: &Path) -> (String, String) {
+    let stem = db_path
+        .file_stem()
+        .map_or_else(|| "bookshelf".into(), |stem| stem.to_string_lossy());
+    let suffix = db_path
+        .extension()
+        .map_or_else(String::new, |ext| format!(".{}", ext.to_string_lossy()));
+    (format!("{stem}-backup-"), suffix)
+}
+
+fn backup_path(db_path: &Path, stamp: &str) -> PathBuf {
+    let (prefix, suffix) = backup_name_parts(db_path);
+    db_path.with_file_name(format!("{prefix}{stamp}{suffix}"))
+}
+
+/// Backups of the database at `db_path` made by `Storage::backup_next_to`, newest first.
+pub fn list_backups(db_path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let (prefix, suffix) = backup_name_parts(db_path);
+    let dir = match db_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("list {}", dir.display())),
+    };
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(stamp) = name
+            .strip_prefix(prefix.as_str())
+            .and_then(|rest| rest.strip_suffix(suffix.as_str()))
+            .filter(|stamp| !stamp.is_empty())
+        {
+            backups.push((stamp.to_string(), entry.path()));
         }
-        Ok(out)
     }
+    // Stamps sort by time, and one with a counter after the one without.
+    backups.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
 
-    pub fn replace_notes(&self, path: &str, notes: &[Note]) -> anyhow::Result<()> {
-        let tx = self.conn.unchecked_transaction()?;
-        tx.execute("DELETE FROM notes WHERE path = ?", [path])?;
-        for note in notes {
-            let page = note.page.max(1) as i64;
-            tx.execute(
-                "INSERT OR IGNORE INTO notes (path, page, body) VALUES (?, ?, ?)",
-                (path, page, note.body.as_str()),
-            )?;
+/// Checks that `path` is an intact sqlite database holding bookshelf's tables.
+pub fn check_backup(path: &Path) -> anyhow::Result<()> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("open {}", path.display()))?;
+    let check: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .with_context(|| format!("{} is not a sqlite database", path.display()))?;
+    anyhow::ensure!(check == "ok", "{} is damaged: {check}", path.display());
+    let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type = 'table'")?;
+    let tables = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<std::collections::HashSet<_>, _>>()?;
+    let missing: Vec<&str> = BACKUP_REQUIRED_TABLES
+        .iter()
+        .copied()
+        .filter(|table| !tables.contains(*table))
+        .collect();
+    anyhow::ensure!(
+        missing.is_empty(),
+        "{} is not a bookshelf database (no {} table)",
+        path.display(),
+        missing.join(", ")
+    );
+    Ok(())
+}
+
+/// Replaces the database at `db_path` with the backup at `backup`, once `check_backup` passes.
+/// Connections already open on the database see the restored contents; open `Storage` on it
+/// again to bring an older backup's tables up to date.
+pub fn restore_backup(db_path: &Path, backup: &Path) -> anyhow::Result<()> {
+    check_backup(backup)?;
+    let mut conn = Connection::open(db_path)
+        .with_context(|| format!("open sqlite db at {}", db_path.display()))?;
+    conn.busy_timeout(std::time::Duration::from_secs(5))
+        .context("set sqlite busy timeout")?;
+    conn.restore(
+        rusqlite::MAIN_DB,
+        backup,
+        None::<fn(rusqlite::backup::Progress)>,
+    )
+    .with_context(|| format!("restore {}", backup.display()))
+}
+
+const LABELS_DOCUMENT_VERSION: u32 = 1;
+
+/// Labels file written by `export_labels`.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct LabelsDocument {
+    version: u32,
+    /// Label catalog, including labels no book uses.
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    collections: Vec<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    series: Vec<String>,
+    /// Stored book path → labels.
+    #[serde(default)]
+    books: std::collections::BTreeMap<String, ExportedBookLabels>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct ExportedBookLabels {
+    #[serde(default)]
+    favorite: bool,
+    #[serde(default)]
+    collection: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    series: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    series_number: Option<u32>,
+}
+
+const SETTINGS_DOCUMENT_VERSION: u32 = 1;
+
+/// Settings as the `settings` row and a settings file hold them: choices by name, roots and
+/// key bindings as JSON. Both are read through `into_settings`.
+#[derive(Debug, Clone, serde::Serialize)]
+struct SettingsRow {
+    reader_mode: String,
+    reader_text_mode: String,
+    reader_trim_headers_footers: bool,
+    reader_invert_colors: bool,
+    kitty_image_quality: String,
+    theme: String,
+    scan_scope: String,
+    watch_library: bool,
+    sort_mode: String,
+    library_roots: serde_json::Value,
+    key_bindings: serde_json::Value,
+    auto_crop: String,
+    external_viewer: String,
+    library_decorations: bool,
+    reader_detect_columns: bool,
+    resume_last_book: bool,
+    image_cache_limit: String,
+    remote_mode: bool,
+    furniture_sample_pages: u32,
+    furniture_min_fraction: f64,
+    fingerprint_books: bool,
+    /// When this database last scanned; another machine's scan says nothing about it.
+    #[serde(skip)]
+    last_scan_at: Option<i64>,
+    mouse_capture: bool,
+    confirm_quit: bool,
+    reader_minimap: bool,
+    reader_text_width: u16,
+    graphics_terminal: String,
+    tick_rate_ms: u32,
+    library_grid: bool,
+    autosave_secs: u32,
+    downscale_filter: String,
+    reader_line_spacing: u16,
+}
+
+impl From<&Settings> for SettingsRow {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            reader_mode: settings.reader_mode.as_str().to_string(),
+            reader_text_mode: settings.reader_text_mode.as_str().to_string(),
+            reader_trim_headers_footers: settings.reader_trim_headers_footers,
+            reader_invert_colors: settings.reader_invert_colors,
+            kitty_image_quality: settings.kitty_image_quality.as_str().to_string(),
+            theme: settings.theme.as_str().to_string(),
+            scan_scope: settings.scan_scope.as_str().to_string(),
+            watch_library: settings.watch_library,
+            sort_mode: settings.sort_mode.as_str().to_string(),
+            library_roots: serde_json::to_value(&settings.library_roots).unwrap_or_default(),
+            key_bindings: serde_json::to_value(&settings.key_bindings).unwrap_or_default(),
+            auto_crop: settings.auto_crop.as_str().to_string(),
+            external_viewer: settings.external_viewer.clone(),
+            library_decorations: settings.library_decorations,
+            reader_detect_columns: settings.reader_detect_columns,
+            resume_last_book: settings.resume_last_book,
+            image_cache_limit: settings.image_cache_limit.as_str().to_string(),
+            remote_mode: settings.remote_mode,
+            furniture_sample_pages: settings.furniture_sample_pages,
+            furniture_min_fraction: f64::from(settings.furniture_min_fraction),
+            fingerprint_books: settings.fingerprint_books,
+            last_scan_at: settings.last_scan_at,
+            mouse_capture: settings.mouse_capture,
+            confirm_quit: settings.confirm_quit,
+            reader_minimap: settings.reader_minimap,
+            reader_text_width: settings.reader_text_width,
+            graphics_terminal: settings.graphics_terminal.clone(),
+            tick_rate_ms: settings.tick_rate_ms,
+            library_grid: settings.library_grid,
+            autosave_secs: settings.autosave_secs,
+            downscale_filter: settings.downscale_filter.as_str().to_string(),
+            reader_line_spacing: settings.reader_line_spacing,
         }
-        tx.commit()?;
-        Ok(())
     }
+}
 
-    pub fn delete_book_by_path(&self, path: &str) -> anyhow::Result<()> {
-        self.conn
-            .execute("DELETE FROM books WHERE path = ?", [path])?;
-        Ok(())
+impl SettingsRow {
+    /// Replaces each field the settings file `doc` holds. Values of the wrong type leave the
+    /// field as it was and are named in `rejected`.
+    fn merge_document(
+        &mut self,
+        doc: &serde_json::Map<String, serde_json::Value>,
+        rejected: &mut Vec<&'static str>,
+    ) {
+        fn take<T: serde::de::DeserializeOwned>(
+            doc: &serde_json::Map<String, serde_json::Value>,
+            field: &'static str,
+            value: &mut T,
+            rejected: &mut Vec<&'static str>,
+        ) {
+            if let Some(raw) = doc.get(field) {
+                match T::deserialize(raw) {
+                    Ok(parsed) => *value = parsed,
+                    Err(_) => rejected.push(field),
+                }
+            }
+        }
+        take(doc, "reader_mode", &mut self.reader_mode, rejected);
+        take(
+            doc,
+            "reader_text_mode",
+            &mut self.reader_text_mode,
+            rejected,
+        );
+        take(
+            doc,
+            "reader_trim_headers_footers",
+            &mut self.reader_trim_headers_footers,
+            rejected,
+        );
+        take(
+            doc,
+            "reader_invert_colors",
+            &mut self.reader_invert_colors,
+            rejected,
+        );
+        take(
+            doc,
+            "kitty_image_quality",
+            &mut self.kitty_image_quality,
+            rejected,
+        );
+        take(doc, "theme", &mut self.theme, rejected);
+        take(doc, "scan_scope", &mut self.scan_scope, rejected);
+        take(doc, "watch_library", &mut self.watch_library, rejected);
+        take(doc, "sort_mode", &mut self.sort_mode, rejected);
+        take(doc, "library_roots", &mut self.library_roots, rejected);
+        take(doc, "key_bindings", &mut self.key_bindings, rejected);
+        take(doc, "auto_crop", &mut self.auto_crop, rejected);
+        take(doc, "external_viewer", &mut self.external_viewer, rejected);
+        take(
+            doc,
+            "library_decorations",
+            &mut self.library_decorations,
+            rejected,
+        );
+        take(
+            doc,
+            "reader_detect_columns",
+            &mut self.reader_detect_columns,
+            rejected,
+        );
+        take(
+            doc,
+            "resume_last_book",
+            &mut self.resume_last_book,
+            rejected,
+        );
+        take(
+            doc,
+            "image_cache_limit",
+            &mut self.image_cache_limit,
+            rejected,
+        );
+        take(doc, "remote_mode", &mut self.remote_mode, rejected);
+        take(
+            doc,
+            "furniture_sample_pages",
+            &mut self.furniture_sample_pages,
+            rejected,
+        );
+        take(
+            doc,
+            "furniture_min_fraction",
+            &mut self.furniture_min_fraction,
+            rejected,
+        );
+        take(
+            doc,
+            "fingerprint_books",
+            &mut self.fingerprint_books,
+            rejected,
+        );
+        take(doc, "mouse_capture", &mut self.mouse_capture, rejected);
+        take(doc, "confirm_quit", &mut self.confirm_quit, rejected);
+        take(doc, "reader_minimap", &mut self.reader_minimap, rejected);
+        take(
+            doc,
+            "reader_text_width",
+            &mut self.reader_text_width,
+            rejected,
+        );
+        take(
+            doc,
+            "graphics_terminal",
+            &mut self.graphics_terminal,
+            rejected,
+        );
+        take(doc, "tick_rate_ms", &mut self.tick_rate_ms, rejected);
+        take(doc, "library_grid", &mut self.library_grid, rejected);
+        take(doc, "autosave_secs", &mut self.autosave_secs, rejected);
+        take(
+            doc,
+            "downscale_filter",
+            &mut self.downscale_filter,
+            rejected,
+        );
+        take(
+            doc,
+            "reader_line_spacing",
+            &mut self.reader_line_spacing,
+            rejected,
+        );
     }
 
-    pub fn list_progress(&self) -> anyhow::Result<std::collections::HashMap<String, u32>> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT path, last_page FROM book_progress")?;
-        let rows = stmt.query_map([], |row| {
-            let path: String = row.get(0)?;
-            let last_page: i64 = row.get(1)?;
-            let last_page = u32::try_from(last_page).unwrap_or(1).max(1);
-            Ok((path, last_page))
-        })?;
-
-        let mut out = std::collections::HashMap::new();
-        for row in rows {
-            let (path, last_page) = row?;
-            out.insert(path, last_page);
+    /// The settings the row describes. A choice that does not parse, or roots or key bindings
+    /// that do not, get the default and are named in the returned list.
+    fn into_settings(self) -> (Settings, Vec<&'static str>) {
+        fn parse<T: std::str::FromStr>(
+            value: &str,
+            default: T,
+            field: &'static str,
+            rejected: &mut Vec<&'static str>,
+        ) -> T {
+            value.parse().unwrap_or_else(|_| {
+                rejected.push(field);
+                default
+            })
+        }
+        fn from_json<T: serde::de::DeserializeOwned>(
+            value: serde_json::Value,
+            default: T,
+            field: &'static str,
+            rejected: &mut Vec<&'static str>,
+        ) -> T {
+            serde_json::from_value(value).unwrap_or_else(|_| {
+                rejected.push(field);
+                default
+            })
         }
-        Ok(out)
-    }
 
-    pub fn set_progress(&self, path: &str, last_page: u32) -> anyhow::Result<()> {
-        let last_page = last_page.max(1) as i64;
-        self.conn.execute(
-            r#"
-            INSERT INTO book_progress (path, last_page, updated_at) VALUES (?, ?, unixepoch())
-            ON CONFLICT(path) DO UPDATE SET last_page = excluded.last_page, updated_at = excluded.updated_at
-            "#,
-            (path, last_page),
-        )?;
-        Ok(())
+        let defaults = Settings::default();
+        let mut rejected = Vec::new();
+        let rejected_ref = &mut rejected;
+        let mut settings = Settings {
+            reader_mode: parse(
+                &self.reader_mode,
+                defaults.reader_mode,
+                "reader_mode",
+                rejected_ref,
+            ),
+            reader_text_mode: parse(
+                &self.reader_text_mode,
+                defaults.reader_text_mode,
+                "reader_text_mode",
+                rejected_ref,
+            ),
+            reader_trim_headers_footers: self.reader_trim_headers_footers,
+            reader_detect_columns: self.reader_detect_columns,
+            furniture_sample_pages: self.furniture_sample_pages,
+            furniture_min_fraction: self.furniture_min_fraction as f32,
+            reader_invert_colors: self.reader_invert_colors,
+            kitty_image_quality: parse(
+                &self.kitty_image_quality,
+                defaults.kitty_image_quality,
+                "kitty_image_quality",
+                rejected_ref,
+            ),
+            downscale_filter: parse(
+                &self.downscale_filter,
+                defaults.downscale_filter,
+                "downscale_filter",
+                rejected_ref,
+            ),
+            image_cache_limit: parse(
+                &self.image_cache_limit,
+                defaults.image_cache_limit,
+                "image_cache_limit",
+                rejected_ref,
+            ),
+            remote_mode: self.remote_mode,
+            auto_crop: parse(
+                &self.auto_crop,
+                defaults.auto_crop,
+                "auto_crop",
+                rejected_ref,
+            ),
+            external_viewer: self.external_viewer,
+            theme: parse(&self.theme, defaults.theme, "theme", rejected_ref),
+            scan_scope: parse(
+                &self.scan_scope,
+                defaults.scan_scope,
+                "scan_scope",
+                rejected_ref,
+            ),
+            watch_library: self.watch_library,
+            fingerprint_books: self.fingerprint_books,
+            last_scan_at: self.last_scan_at,
+            library_decorations: self.library_decorations,
+            resume_last_book: self.resume_last_book,
+            mouse_capture: self.mouse_capture,
+            confirm_quit: self.confirm_quit,
+            reader_minimap: self.reader_minimap,
+            reader_text_width: self.reader_text_width,
+            reader_line_spacing: self.reader_line_spacing,
+            graphics_terminal: self.graphics_terminal,
+            tick_rate_ms: self.tick_rate_ms,
+            library_grid: self.library_grid,
+            autosave_secs: self.autosave_secs,
+            sort_mode: parse(
+                &self.sort_mode,
+                defaults.sort_mode,
+                "sort_mode",
+                rejected_ref,
+            ),
+            // Older rows hold bare paths; `LibraryRoot` reads those as enabled roots.
+            library_roots: from_json(
+                self.library_roots,
+                defaults.library_roots,
+                "library_roots",
+                rejected_ref,
+            ),
+            key_bindings: from_json(
+                self.key_bindings,
+                defaults.key_bindings,
+                "key_bindings",
+                rejected_ref,
+            ),
+        };
+        settings.normalize();
+        (settings, rejected)
     }
 }
 
-fn get_or_create_tag_id(
-    tx: &rusqlite::Transaction<'_>,
-    name: &str,
-    kind: TagKind,
-) -> anyhow::Result<i64> {
+fn get_or_create_tag_id(tx: &Connection, name: &str, kind: TagKind) -> anyhow::Result<i64> {
     let name = name.trim();
     tx.execute(
         "INSERT INTO tags (name, kind) VALUES (?, ?) ON CONFLICT(name, kind) DO NOTHING",
@@ -610,6 +2482,11 @@ fn get_or_create_tag_id(
 
 #[cfg(test)]
 mod tests {
+    use bookshelf_core::{
+        AutoCrop, BookKind, DownscaleFilter, ImageCacheLimit, KittyImageQuality, LibraryRoot,
+        ScanScope, SortMode, Theme,
+    };
+
     use super::*;
 
     fn open_in_memory() -> anyhow::Result<Storage> {
@@ -626,151 +2503,639 @@ mod tests {
         settings.reader_mode = ReaderMode::Image;
         settings.reader_text_mode = ReaderTextMode::Raw;
         settings.reader_trim_headers_footers = false;
+        settings.reader_detect_columns = false;
+        settings.reader_invert_colors = true;
         settings.kitty_image_quality = KittyImageQuality::Sharp;
+        settings.downscale_filter = DownscaleFilter::Nearest;
+        settings.image_cache_limit = ImageCacheLimit::Large;
+        settings.remote_mode = true;
+        settings.furniture_sample_pages = 16;
+        settings.furniture_min_fraction = 0.75;
+        settings.auto_crop = AutoCrop::Aggressive;
+        settings.external_viewer = "zathura --page=%p %f ".to_string();
         settings.scan_scope = ScanScope::Direct;
-        settings.library_roots = vec!["/tmp".to_string()];
+        settings.watch_library = false;
+        settings.fingerprint_books = false;
+        settings.last_scan_at = Some(1_700_000_000);
+        settings.library_decorations = false;
+        settings.resume_last_book = true;
+        settings.mouse_capture = false;
+        settings.confirm_quit = false;
+        settings.reader_minimap = false;
+        settings.reader_text_width = 72;
+        settings.reader_line_spacing = 15;
+        settings.graphics_terminal = "wezterm start -- %e".to_string();
+        settings.tick_rate_ms = 100;
+        settings.library_grid = true;
+        settings.autosave_secs = 0;
+        settings.sort_mode = SortMode::Size;
+        settings.library_roots = vec![
+            LibraryRoot {
+                path: "/mnt/books".to_string(),
+                enabled: false,
+            },
+            LibraryRoot::new("/tmp"),
+        ];
+        settings
+            .key_bindings
+            .insert("next_item".to_string(), vec!["j".to_string()]);
         storage.save_settings(&settings)?;
 
         let settings2 = storage.load_settings()?;
         assert_eq!(settings2.reader_mode, ReaderMode::Image);
         assert_eq!(settings2.reader_text_mode, ReaderTextMode::Raw);
         assert!(!settings2.reader_trim_headers_footers);
+        assert!(!settings2.reader_detect_columns);
+        assert!(settings2.reader_invert_colors);
         assert_eq!(settings2.kitty_image_quality, KittyImageQuality::Sharp);
+        assert_eq!(settings2.image_cache_limit, ImageCacheLimit::Large);
+        assert!(settings2.remote_mode);
+        assert_eq!(settings2.furniture_sample_pages, 16);
+        assert_eq!(settings2.furniture_min_fraction, 0.75);
+        assert_eq!(settings2.auto_crop, AutoCrop::Aggressive);
+        assert_eq!(settings2.downscale_filter, DownscaleFilter::Nearest);
+        assert_eq!(settings2.external_viewer, "zathura --page=%p %f");
         assert_eq!(settings2.scan_scope, ScanScope::Direct);
-        assert_eq!(settings2.library_roots, vec!["/tmp".to_string()]);
+        assert!(!settings2.watch_library);
+        assert!(!settings2.fingerprint_books);
+        assert_eq!(settings2.last_scan_at, Some(1_700_000_000));
+        assert!(!settings2.library_decorations);
+        assert!(settings2.resume_last_book);
+        assert!(!settings2.mouse_capture);
+        assert!(!settings2.confirm_quit);
+        assert!(!settings2.reader_minimap);
+        assert_eq!(settings2.reader_text_width, 72);
+        assert_eq!(settings2.reader_line_spacing, 15);
+        assert_eq!(settings2.graphics_terminal, "wezterm start -- %e");
+        assert_eq!(settings2.tick_rate_ms, 100);
+        assert!(settings2.library_grid);
+        assert_eq!(settings2.autosave_secs, 0);
+        assert_eq!(settings2.sort_mode, SortMode::Size);
+        assert_eq!(settings2.library_roots, settings.library_roots);
+        assert_eq!(settings2.key_bindings, settings.key_bindings);
+
+        // Roots saved as bare paths by older versions load as enabled roots.
+        storage.conn.execute(
+            "UPDATE settings SET library_roots_json = '[\"/old\"]' WHERE id = 1",
+            [],
+        )?;
+        assert_eq!(
+            storage.load_settings()?.library_roots,
+            vec![LibraryRoot::new("/old")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn library_filters_json_roundtrip() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        assert_eq!(storage.load_library_filters_json()?, "{}");
+        storage.save_library_filters_json(r#"{"favorites_only":true}"#)?;
+        storage.save_settings(&storage.load_settings()?)?;
+        assert_eq!(
+            storage.load_library_filters_json()?,
+            r#"{"favorites_only":true}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn generation_counts_saves() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        assert_eq!(storage.load_generation()?, 0);
+        assert_eq!(storage.bump_generation()?, 1);
+        assert_eq!(storage.bump_generation()?, 2);
+        assert_eq!(storage.load_generation()?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn book_roundtrip() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let book = Book {
+            path: "/a/b.pdf".to_string(),
+            title: "b".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        storage.upsert_book(&book)?;
+        let books = storage.list_books()?;
+        assert_eq!(books, vec![book]);
+        Ok(())
+    }
+
+    #[test]
+    fn rescan_keeps_title_until_metadata_is_set() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let mut book = Book {
+            path: "/a/untitled-final-v3.pdf".to_string(),
+            title: "Operating Systems".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: Some("A. Author".to_string()),
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        storage.upsert_book(&book)?;
+
+        let rescanned = Book {
+            title: "untitled-final-v3".to_string(),
+            author: None,
+            status: ReadingStatus::Unread,
+            ..book.clone()
+        };
+        storage.upsert_book(&rescanned)?;
+        assert_eq!(storage.list_books()?, vec![book.clone()]);
+
+        storage.set_book_metadata(&book.path, "OSTEP", None)?;
+        book.title = "OSTEP".to_string();
+        book.author = None;
+        assert_eq!(storage.list_books()?, vec![book]);
+        Ok(())
+    }
+
+    #[test]
+    fn added_at_is_listed_per_book() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let book = Book {
+            path: "/a/b.pdf".to_string(),
+            title: "b".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        storage.upsert_book(&book)?;
+        storage.conn.execute(
+            "UPDATE books SET added_at = 42 WHERE path = ?",
+            [&book.path],
+        )?;
+
+        let added_at = storage.list_added_at()?;
+        assert_eq!(added_at.get(&book.path).copied(), Some(42));
+        Ok(())
+    }
+
+    #[test]
+    fn favorite_roundtrip() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let mut book = Book {
+            path: "/a/b.pdf".to_string(),
+            title: "b".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        storage.upsert_book(&book)?;
+        storage.set_favorite(&book.path, true)?;
+
+        book.favorite = true;
+        let books = storage.list_books()?;
+        assert_eq!(books, vec![book]);
+        Ok(())
+    }
+
+    #[test]
+    fn archived_books_stay_archived_across_rescans() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let mut book = Book {
+            path: "/a/b.pdf".to_string(),
+            title: "b".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        storage.upsert_book(&book)?;
+        storage.set_archived(&book.path, true)?;
+
+        // A rescan upserts the file again as a fresh, unarchived book.
+        storage.upsert_book(&book)?;
+        book.archived = true;
+        assert_eq!(storage.list_books()?, vec![book.clone()]);
+
+        storage.rename_book_path(&book.path, "/a/c.pdf", "c")?;
+        let books = storage.list_books()?;
+        assert_eq!(books.len(), 1);
+        assert!(books[0].archived);
+
+        storage.set_archived("/a/c.pdf", false)?;
+        assert!(!storage.list_books()?[0].archived);
+        Ok(())
+    }
+
+    #[test]
+    fn status_roundtrip() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let mut book = Book {
+            path: "/a/b.pdf".to_string(),
+            title: "b".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        storage.upsert_book(&book)?;
+        storage.set_status(&book.path, ReadingStatus::Finished)?;
+        book.status = ReadingStatus::Finished;
+        assert_eq!(storage.list_books()?, vec![book.clone()]);
+
+        storage.set_status(&book.path, ReadingStatus::Unread)?;
+        let stored: Option<String> = storage.conn.query_row(
+            "SELECT status FROM books WHERE path = ?",
+            [&book.path],
+            |row| row.get(0),
+        )?;
+        assert_eq!(stored, None);
+        book.status = ReadingStatus::Unread;
+        assert_eq!(storage.list_books()?, vec![book]);
+        Ok(())
+    }
+
+    #[test]
+    fn labels_roundtrip_and_cascade() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let book = Book {
+            path: "/a/b.pdf".to_string(),
+            title: "b".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        storage.upsert_book(&book)?;
+
+        let saved = BookLabels {
+            tags: vec!["rust".to_string(), "tui".to_string()],
+            collection: Some("work".to_string()),
+            author: Some("Ferris".to_string()),
+            series: Some(("Crab Tales".to_string(), Some(2))),
+        };
+        storage.save_labels(&book.path, &saved)?;
+
+        let labels = storage.list_labels_by_path()?;
+        assert_eq!(labels.get(&book.path).cloned(), Some(saved));
+        assert_eq!(
+            storage.list_tag_names(TagKind::Series)?,
+            vec!["Crab Tales".to_string()]
+        );
+
+        storage.delete_book_by_path(&book.path)?;
+        assert!(storage.list_labels_by_path()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn tag_catalog_rename_and_delete_updates_labels() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let book = Book {
+            path: "/a/b.pdf".to_string(),
+            title: "b".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        storage.upsert_book(&book)?;
+
+        storage.save_labels(
+            &book.path,
+            &BookLabels {
+                tags: vec!["rust".to_string()],
+                collection: Some("work".to_string()),
+                series: Some(("Dune".to_string(), Some(3))),
+                ..BookLabels::default()
+            },
+        )?;
+
+        storage.create_tag("unused", TagKind::Tag)?;
+        assert_eq!(
+            storage.list_tag_names(TagKind::Tag)?,
+            vec!["rust".to_string(), "unused".to_string()]
+        );
+        assert_eq!(
+            storage.list_tag_names(TagKind::Collection)?,
+            vec!["work".to_string()]
+        );
+
+        storage.rename_tag("rust", "systems", TagKind::Tag)?;
+        storage.rename_tag("Dune", "Dune Chronicles", TagKind::Series)?;
+        let labels = storage.list_labels_by_path()?;
+        assert_eq!(
+            labels.get(&book.path).cloned(),
+            Some(BookLabels {
+                tags: vec!["systems".to_string()],
+                collection: Some("work".to_string()),
+                series: Some(("Dune Chronicles".to_string(), Some(3))),
+                ..BookLabels::default()
+            })
+        );
+
+        storage.delete_tag("Dune Chronicles", TagKind::Series)?;
+        storage.delete_tag("work", TagKind::Collection)?;
+        storage.delete_tag("systems", TagKind::Tag)?;
+        let labels = storage.list_labels_by_path()?;
+        assert_eq!(labels.get(&book.path).cloned(), None);
         Ok(())
     }
 
     #[test]
-    fn book_roundtrip() -> anyhow::Result<()> {
+    fn progress_roundtrip() -> anyhow::Result<()> {
         let storage = open_in_memory()?;
         let book = Book {
             path: "/a/b.pdf".to_string(),
             title: "b".to_string(),
             last_opened: None,
             favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
         };
         storage.upsert_book(&book)?;
-        let books = storage.list_books()?;
-        assert_eq!(books, vec![book]);
+
+        storage.set_progress(&book.path, &BookProgress::at_page(3))?;
+        let progress = storage.list_progress()?;
+        assert_eq!(
+            progress.get(&book.path).copied(),
+            Some(BookProgress::at_page(3))
+        );
+
+        let within_page = BookProgress {
+            last_page: 4,
+            scroll: Some(12),
+            pan_px: Some((40, 800)),
+        };
+        storage.set_progress(&book.path, &within_page)?;
+        assert_eq!(
+            storage.list_progress()?.get(&book.path).copied(),
+            Some(within_page)
+        );
+
+        storage.delete_book_by_path(&book.path)?;
+        let progress = storage.list_progress()?;
+        assert!(progress.is_empty());
         Ok(())
     }
 
     #[test]
-    fn favorite_roundtrip() -> anyhow::Result<()> {
+    fn page_count_roundtrip() -> anyhow::Result<()> {
         let storage = open_in_memory()?;
-        let mut book = Book {
+        let book = Book {
             path: "/a/b.pdf".to_string(),
             title: "b".to_string(),
             last_opened: None,
             favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
         };
         storage.upsert_book(&book)?;
-        storage.set_favorite(&book.path, true)?;
+        assert!(storage.list_page_counts()?.is_empty());
 
-        book.favorite = true;
-        let books = storage.list_books()?;
-        assert_eq!(books, vec![book]);
+        let mut count = PageCount {
+            pages: 312,
+            size_bytes: 4_096_000,
+            modified_secs: 1_700_000_000,
+            kind: None,
+        };
+        storage.set_page_count(&book.path, &count)?;
+        storage.set_page_count("/not/in/library.pdf", &count)?;
+        let counts = storage.list_page_counts()?;
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts.get(&book.path), Some(&count));
+        count.kind = Some(BookKind::ScannedPdf);
+        storage.set_page_count(&book.path, &count)?;
+        assert_eq!(storage.list_page_counts()?.get(&book.path), Some(&count));
+
+        // A relinked book points at another file, so its count is dropped.
+        storage.rename_book_path(&book.path, "/a/c.pdf", "c")?;
+        assert!(storage.list_page_counts()?.is_empty());
         Ok(())
     }
 
     #[test]
-    fn labels_roundtrip_and_cascade() -> anyhow::Result<()> {
+    fn fingerprint_roundtrip() -> anyhow::Result<()> {
         let storage = open_in_memory()?;
         let book = Book {
             path: "/a/b.pdf".to_string(),
             title: "b".to_string(),
             last_opened: None,
             favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
         };
         storage.upsert_book(&book)?;
+        assert!(storage.list_fingerprints()?.is_empty());
+
+        let mut fingerprint = FileFingerprint {
+            size_bytes: 4_096_000,
+            modified_secs: 1_700_000_000,
+            quick_hash: "ab12".to_string(),
+            tail_hash: None,
+            full_hash: None,
+        };
+        storage.set_fingerprint(&book.path, &fingerprint)?;
+        assert_eq!(
+            storage.list_fingerprints()?.get(&book.path),
+            Some(&fingerprint)
+        );
+        fingerprint.tail_hash = Some("ef56".to_string());
+        fingerprint.full_hash = Some("cd34".to_string());
+        storage.set_fingerprint(&book.path, &fingerprint)?;
+        assert_eq!(
+            storage.list_fingerprints()?.get(&book.path),
+            Some(&fingerprint)
+        );
 
-        storage.save_labels(
-            &book.path,
-            &BookLabels {
-                tags: vec!["rust".to_string(), "tui".to_string()],
-                collection: Some("work".to_string()),
-            },
+        // A relinked book points at another file, so its hashes are dropped.
+        storage.rename_book_path(&book.path, "/a/c.pdf", "c")?;
+        assert!(storage.list_fingerprints()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn progress_rows_from_before_position_columns_still_load() -> anyhow::Result<()> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch(
+            r#"
+            CREATE TABLE books (path TEXT PRIMARY KEY, title TEXT NOT NULL);
+            CREATE TABLE book_progress (
+                path TEXT PRIMARY KEY REFERENCES books(path) ON DELETE CASCADE,
+                last_page INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL DEFAULT (unixepoch())
+            );
+            INSERT INTO books (path, title) VALUES ('/a.pdf', 'a');
+            INSERT INTO book_progress (path, last_page) VALUES ('/a.pdf', 5);
+            "#,
         )?;
+        let storage = Storage { conn };
+        storage.migrate()?;
 
-        let labels = storage.list_labels_by_path()?;
         assert_eq!(
-            labels.get(&book.path).cloned(),
-            Some(BookLabels {
-                tags: vec!["rust".to_string(), "tui".to_string()],
-                collection: Some("work".to_string())
-            })
+            storage.list_progress()?.get("/a.pdf").copied(),
+            Some(BookProgress::at_page(5))
         );
+        Ok(())
+    }
 
-        storage.delete_book_by_path(&book.path)?;
-        assert!(storage.list_labels_by_path()?.is_empty());
+    #[test]
+    fn bookmark_views_roundtrip_and_old_rows_open_at_the_top() -> anyhow::Result<()> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch(
+            r#"
+            CREATE TABLE books (path TEXT PRIMARY KEY, title TEXT NOT NULL);
+            CREATE TABLE bookmarks (
+                path TEXT NOT NULL REFERENCES books(path) ON DELETE CASCADE,
+                page INTEGER NOT NULL,
+                label TEXT NOT NULL DEFAULT '',
+                PRIMARY KEY (path, page, label)
+            );
+            INSERT INTO books (path, title) VALUES ('/a.pdf', 'a');
+            INSERT INTO bookmarks (path, page, label) VALUES ('/a.pdf', 4, 'old');
+            "#,
+        )?;
+        let storage = Storage { conn };
+        storage.migrate()?;
+        assert_eq!(storage.list_bookmarks_by_path()?["/a.pdf"][0].view, None);
+
+        let bookmarks = vec![
+            Bookmark {
+                page: 2,
+                label: "table".to_string(),
+                view: Some(BookmarkView::Text { scroll: 40 }),
+            },
+            Bookmark {
+                page: 3,
+                label: "figure".to_string(),
+                view: Some(BookmarkView::Image {
+                    zoom_percent: 175,
+                    pan_px: (120, 900),
+                }),
+            },
+            Bookmark {
+                page: 4,
+                label: "old".to_string(),
+                view: None,
+            },
+        ];
+        storage.replace_bookmarks("/a.pdf", &bookmarks)?;
+        assert_eq!(storage.list_bookmarks_by_path()?["/a.pdf"], bookmarks);
         Ok(())
     }
 
     #[test]
-    fn tag_catalog_rename_and_delete_updates_labels() -> anyhow::Result<()> {
+    fn reader_state_roundtrip_and_cascade() -> anyhow::Result<()> {
         let storage = open_in_memory()?;
         let book = Book {
             path: "/a/b.pdf".to_string(),
             title: "b".to_string(),
             last_opened: None,
             favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
         };
         storage.upsert_book(&book)?;
 
-        storage.save_labels(
+        let state = ReaderViewState {
+            reader_mode: ReaderMode::Image,
+            reader_text_mode: ReaderTextMode::Wrap,
+            image_zoom_percent: 150,
+            image_fit: ImageFit::Page,
+            rotation: PageRotation::Upright,
+        };
+        storage.set_reader_state(&book.path, &state)?;
+        storage.set_reader_state(
             &book.path,
-            &BookLabels {
-                tags: vec!["rust".to_string()],
-                collection: Some("work".to_string()),
+            &ReaderViewState {
+                image_zoom_percent: 175,
+                image_fit: ImageFit::Width,
+                rotation: PageRotation::CounterClockwise,
+                ..state
             },
         )?;
-
-        storage.create_tag("unused", TagKind::Tag)?;
-        assert_eq!(
-            storage.list_tag_names(TagKind::Tag)?,
-            vec!["rust".to_string(), "unused".to_string()]
-        );
+        let states = storage.list_reader_states()?;
         assert_eq!(
-            storage.list_tag_names(TagKind::Collection)?,
-            vec!["work".to_string()]
+            states.get(&book.path).copied(),
+            Some(ReaderViewState {
+                image_zoom_percent: 175,
+                image_fit: ImageFit::Width,
+                rotation: PageRotation::CounterClockwise,
+                ..state
+            })
         );
 
-        storage.rename_tag("rust", "systems", TagKind::Tag)?;
-        let labels = storage.list_labels_by_path()?;
+        storage.delete_book_by_path(&book.path)?;
+        assert!(storage.list_reader_states()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn reading_time_roundtrip_and_cascade() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let book = Book {
+            path: "/a/b.pdf".to_string(),
+            title: "b".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        storage.upsert_book(&book)?;
+
+        storage.set_reading_time(&book.path, 90)?;
+        storage.set_reading_time(&book.path, 15_120)?;
         assert_eq!(
-            labels.get(&book.path).cloned(),
-            Some(BookLabels {
-                tags: vec!["systems".to_string()],
-                collection: Some("work".to_string())
-            })
+            storage.list_reading_time()?.get(&book.path).copied(),
+            Some(15_120)
         );
 
-        storage.delete_tag("work", TagKind::Collection)?;
-        storage.delete_tag("systems", TagKind::Tag)?;
-        let labels = storage.list_labels_by_path()?;
-        assert_eq!(labels.get(&book.path).cloned(), None);
+        storage.delete_book_by_path(&book.path)?;
+        assert!(storage.list_reading_time()?.is_empty());
         Ok(())
     }
 
     #[test]
-    fn progress_roundtrip() -> anyhow::Result<()> {
+    fn collection_positions_roundtrip_and_cascade() -> anyhow::Result<()> {
         let storage = open_in_memory()?;
         let book = Book {
             path: "/a/b.pdf".to_string(),
             title: "b".to_string(),
             last_opened: None,
             favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
         };
         storage.upsert_book(&book)?;
 
-        storage.set_progress(&book.path, 3)?;
-        let progress = storage.list_progress()?;
-        assert_eq!(progress.get(&book.path).copied(), Some(3));
+        storage.set_collection_position(&book.path, Some(2))?;
+        storage.set_collection_position(&book.path, Some(0))?;
+        assert_eq!(
+            storage
+                .list_collection_positions()?
+                .get(&book.path)
+                .copied(),
+            Some(0)
+        );
+        storage.set_collection_position(&book.path, None)?;
+        assert!(storage.list_collection_positions()?.is_empty());
 
+        storage.set_collection_position(&book.path, Some(1))?;
         storage.delete_book_by_path(&book.path)?;
-        let progress = storage.list_progress()?;
-        assert!(progress.is_empty());
+        assert!(storage.list_collection_positions()?.is_empty());
         Ok(())
     }
 
@@ -782,6 +3147,9 @@ mod tests {
             title: "b".to_string(),
             last_opened: None,
             favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
         };
         storage.upsert_book(&book)?;
 
@@ -790,6 +3158,7 @@ mod tests {
             &[Bookmark {
                 page: 2,
                 label: "start".to_string(),
+                view: None,
             }],
         )?;
         storage.replace_notes(
@@ -804,7 +3173,8 @@ mod tests {
             storage.list_bookmarks_by_path()?.get(&book.path).cloned(),
             Some(vec![Bookmark {
                 page: 2,
-                label: "start".to_string()
+                label: "start".to_string(),
+                view: None,
             }])
         );
         assert_eq!(
@@ -820,4 +3190,418 @@ mod tests {
         assert!(storage.list_notes_by_path()?.is_empty());
         Ok(())
     }
+
+    #[test]
+    fn edited_notes_replace_their_old_row() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let path = "/a/b.pdf";
+        storage.upsert_book(&Book {
+            path: path.to_string(),
+            title: "b".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        })?;
+        let note = |page: u32, body: &str| Note {
+            page,
+            body: body.to_string(),
+        };
+        storage.replace_notes(path, &[note(2, "teh proof"), note(5, "lemma")])?;
+
+        let edited = vec![note(2, "the proof\nsee also p. 9"), note(5, "lemma")];
+        storage.replace_notes(path, &edited)?;
+
+        assert_eq!(storage.list_notes_by_path()?[path], edited);
+        Ok(())
+    }
+
+    #[test]
+    fn write_batch_lands_whole_or_not_at_all() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let path = "/a/b.pdf";
+        storage.upsert_book(&Book {
+            path: path.to_string(),
+            title: "b".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        })?;
+        let note = Note {
+            page: 3,
+            body: "kept".to_string(),
+        };
+
+        let failed: anyhow::Result<()> = storage.write_batch(|storage| {
+            storage.replace_notes(path, std::slice::from_ref(&note))?;
+            storage.set_favorite(path, true)?;
+            anyhow::bail!("disk full")
+        });
+        assert!(failed.is_err());
+        assert!(storage.list_notes_by_path()?.is_empty());
+        assert!(!storage.list_books()?[0].favorite);
+
+        let generation = storage.write_batch(|storage| {
+            storage.replace_notes(path, std::slice::from_ref(&note))?;
+            storage.save_labels(
+                path,
+                &BookLabels {
+                    tags: vec!["proofs".to_string()],
+                    ..BookLabels::default()
+                },
+            )?;
+            storage.bump_generation()
+        })?;
+        assert_eq!(generation, 1);
+        assert_eq!(storage.list_notes_by_path()?[path], vec![note]);
+        assert_eq!(storage.list_labels_by_path()?[path].tags, vec!["proofs"]);
+        // Outside a batch, writes still commit on their own.
+        storage.replace_notes(path, &[])?;
+        assert!(storage.list_notes_by_path()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn rename_book_path_carries_over_book_data() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let old = Book {
+            path: "/old/b.pdf".to_string(),
+            title: "b".to_string(),
+            last_opened: Some(42),
+            favorite: true,
+            author: None,
+            status: ReadingStatus::Reading,
+            archived: false,
+        };
+        let stale = Book {
+            path: "/new/b2.pdf".to_string(),
+            title: "stale".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        storage.upsert_book(&old)?;
+        storage.upsert_book(&stale)?;
+        storage.set_progress(&stale.path, &BookProgress::at_page(99))?;
+
+        storage.set_last_opened(&old.path, 42)?;
+        storage.set_favorite(&old.path, true)?;
+        storage.set_status(&old.path, ReadingStatus::Reading)?;
+        storage.set_progress(&old.path, &BookProgress::at_page(7))?;
+        storage.set_reading_time(&old.path, 600)?;
+        storage.replace_bookmarks(
+            &old.path,
+            &[Bookmark {
+                page: 3,
+                label: "ch1".to_string(),
+                view: None,
+            }],
+        )?;
+        storage.replace_notes(
+            &old.path,
+            &[Note {
+                page: 4,
+                body: "remember".to_string(),
+            }],
+        )?;
+        storage.save_labels(
+            &old.path,
+            &BookLabels {
+                tags: vec!["math".to_string()],
+                collection: Some("Uni".to_string()),
+                ..BookLabels::default()
+            },
+        )?;
+        storage.set_collection_position(&old.path, Some(3))?;
+
+        storage.rename_book_path(&old.path, &stale.path, "b2")?;
+
+        let books = storage.list_books()?;
+        assert_eq!(
+            books,
+            vec![Book {
+                path: stale.path.clone(),
+                title: "b2".to_string(),
+                last_opened: Some(42),
+                favorite: true,
+                author: None,
+                status: ReadingStatus::Reading,
+                archived: false,
+            }]
+        );
+        assert_eq!(
+            storage
+                .list_progress()?
+                .get(&stale.path)
+                .map(|p| p.last_page),
+            Some(7)
+        );
+        assert_eq!(storage.list_reading_time()?[&stale.path], 600);
+        assert_eq!(storage.list_bookmarks_by_path()?[&stale.path].len(), 1);
+        assert_eq!(storage.list_notes_by_path()?[&stale.path].len(), 1);
+        let labels = storage.list_labels_by_path()?;
+        assert_eq!(labels[&stale.path].collection.as_deref(), Some("Uni"));
+        assert!(!labels.contains_key(&old.path));
+        assert_eq!(
+            storage.list_collection_positions()?,
+            std::collections::HashMap::from([(stale.path.clone(), 3)])
+        );
+
+        assert!(
+            storage
+                .rename_book_path("/missing.pdf", "/x.pdf", "x")
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn labels_export_and_merge_import() -> anyhow::Result<()> {
+        let book = |path: &str| Book {
+            path: path.to_string(),
+            title: path.trim_start_matches('/').to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        let labels = |collection: &str, tags: &[&str]| BookLabels {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            collection: Some(collection.to_string()),
+            ..BookLabels::default()
+        };
+
+        let source = open_in_memory()?;
+        for path in ["/a.pdf", "/b.pdf", "/z.pdf"] {
+            source.upsert_book(&book(path))?;
+        }
+        source.set_favorite("/a.pdf", true)?;
+        let in_series = |labels: BookLabels| BookLabels {
+            series: Some(("OS Notes".to_string(), Some(2))),
+            ..labels
+        };
+        source.save_labels("/a.pdf", &in_series(labels("Work", &["rust", "os"])))?;
+        source.save_labels("/z.pdf", &labels("Work", &["rust"]))?;
+        source.create_tag("unused", TagKind::Tag)?;
+
+        let file =
+            std::env::temp_dir().join(format!("bookshelf-labels-{}.json", std::process::id()));
+        assert_eq!(source.export_labels(&file)?, 2);
+
+        let target = open_in_memory()?;
+        for path in ["/a.pdf", "/c.pdf"] {
+            target.upsert_book(&book(path))?;
+        }
+        target.save_labels("/a.pdf", &labels("Home", &["Rust", "kernel"]))?;
+        target.save_labels("/c.pdf", &labels("Home", &["misc"]))?;
+
+        let report = target.import_labels(&file)?;
+        std::fs::remove_file(&file)?;
+        assert_eq!(
+            report,
+            LabelImportReport {
+                merged_books: 1,
+                skipped_books: 1,
+            }
+        );
+
+        let merged = target.list_labels_by_path()?;
+        assert_eq!(
+            merged.get("/a.pdf"),
+            Some(&in_series(labels("Work", &["kernel", "os", "Rust"])))
+        );
+        assert_eq!(merged.get("/c.pdf"), Some(&labels("Home", &["misc"])));
+        let books = target.list_books()?;
+        assert!(books.iter().any(|b| b.path == "/a.pdf" && b.favorite));
+        assert_eq!(
+            target.list_tag_names(TagKind::Tag)?,
+            vec!["kernel", "misc", "os", "Rust", "unused"]
+        );
+        assert_eq!(
+            target.list_tag_names(TagKind::Collection)?,
+            vec!["Home", "Work"]
+        );
+        assert_eq!(target.list_tag_names(TagKind::Series)?, vec!["OS Notes"]);
+        Ok(())
+    }
+
+    #[test]
+    fn backups_rotate_and_restore_only_bookshelf_databases() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("bookshelf-backups-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let db_path = dir.join("bookshelf.db");
+        let storage = Storage::open(&db_path)?;
+        let book = |path: &str| Book {
+            path: path.to_string(),
+            title: "t".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        storage.upsert_book(&book("/books/a.pdf"))?;
+
+        let first = storage.backup_next_to(&db_path, 2)?;
+        let second = storage.backup_next_to(&db_path, 2)?;
+        let third = storage.backup_next_to(&db_path, 2)?;
+        assert_eq!(list_backups(&db_path)?, vec![third.clone(), second]);
+        assert!(!first.exists());
+        let name = third.file_name().unwrap_or_default().to_string_lossy();
+        assert!(name.starts_with("bookshelf-backup-") && name.ends_with(".db"));
+
+        storage.upsert_book(&book("/books/b.pdf"))?;
+        let garbage = dir.join("notes.txt");
+        std::fs::write(&garbage, "not a database")?;
+        assert!(restore_backup(&db_path, &garbage).is_err());
+        let other = dir.join("other.db");
+        Connection::open(&other)?.execute_batch("CREATE TABLE books (path TEXT);")?;
+        let err = restore_backup(&db_path, &other).expect_err("not a bookshelf database");
+        assert!(
+            err.to_string().contains("not a bookshelf database"),
+            "{err}"
+        );
+        assert_eq!(storage.list_books()?.len(), 2);
+
+        restore_backup(&db_path, &third)?;
+        let paths: Vec<String> = storage.list_books()?.into_iter().map(|b| b.path).collect();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(paths, vec!["/books/a.pdf".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn settings_export_and_import_reject_bad_fields() -> anyhow::Result<()> {
+        let source = open_in_memory()?;
+        let mut settings = source.load_settings()?;
+        settings.reader_mode = ReaderMode::Image;
+        settings.theme = Theme::Light;
+        settings.tick_rate_ms = 100;
+        settings.last_scan_at = Some(1_700_000_000);
+        settings.library_roots = vec![LibraryRoot::new("/mnt/books")];
+
+        let file =
+            std::env::temp_dir().join(format!("bookshelf-settings-{}.json", std::process::id()));
+        Storage::export_settings(&settings, &file)?;
+        let mut document: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&file)?)?;
+        assert_eq!(document["version"], 1);
+        assert_eq!(document["theme"], "light");
+        assert!(document.get("last_scan_at").is_none());
+        document["theme"] = "neon".into();
+        document["tick_rate_ms"] = "fast".into();
+        document
+            .as_object_mut()
+            .expect("settings object")
+            .remove("sort_mode");
+        std::fs::write(&file, document.to_string())?;
+
+        let target = open_in_memory()?;
+        let mut local = target.load_settings()?;
+        local.sort_mode = SortMode::Size;
+        local.last_scan_at = Some(42);
+        target.save_settings(&local)?;
+        let import = target.import_settings(&file)?;
+        std::fs::remove_file(&file)?;
+
+        assert_eq!(import.rejected, vec!["tick_rate_ms", "theme"]);
+        let stored = target.load_settings()?;
+        assert_eq!(stored.reader_mode, ReaderMode::Image);
+        assert_eq!(stored.theme, Theme::Dark);
+        assert_eq!(stored.tick_rate_ms, Settings::default().tick_rate_ms);
+        assert_eq!(stored.sort_mode, SortMode::Title);
+        assert_eq!(stored.last_scan_at, Some(42));
+        assert_eq!(stored.library_roots, vec![LibraryRoot::new("/mnt/books")]);
+        assert_eq!(import.settings.reader_mode, stored.reader_mode);
+
+        std::fs::write(&file, r#"{"version": 99}"#)?;
+        assert!(target.import_settings(&file).is_err());
+        std::fs::remove_file(&file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn cleanup_removes_missing_books_orphans_and_unused_tags() -> anyhow::Result<()> {
+        let storage = open_in_memory()?;
+        let dir = std::env::temp_dir().join(format!("bookshelf-cleanup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let present_path = dir.join("present.pdf");
+        std::fs::write(&present_path, b"")?;
+
+        let book = |path: &str| Book {
+            path: path.to_string(),
+            title: "t".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        let present = book(&present_path.to_string_lossy());
+        let gone = book("/definitely/missing/gone.pdf");
+        let kept = book("/definitely/missing/relinking.pdf");
+        for b in [&present, &gone, &kept] {
+            storage.upsert_book(b)?;
+        }
+        storage.set_progress(&gone.path, &BookProgress::at_page(3))?;
+        storage.replace_notes(
+            &gone.path,
+            &[Note {
+                page: 1,
+                body: "old".to_string(),
+            }],
+        )?;
+        storage.save_labels(
+            &gone.path,
+            &BookLabels {
+                tags: vec!["stale".to_string()],
+                collection: None,
+                ..BookLabels::default()
+            },
+        )?;
+        storage.save_labels(
+            &present.path,
+            &BookLabels {
+                tags: vec!["live".to_string()],
+                collection: None,
+                ..BookLabels::default()
+            },
+        )?;
+        storage.create_tag("unused", TagKind::Collection)?;
+        // A row left behind from before foreign keys were enforced.
+        storage.conn.execute_batch("PRAGMA foreign_keys=OFF;")?;
+        storage.set_progress("/never/a/book.pdf", &BookProgress::at_page(9))?;
+        storage.conn.execute_batch("PRAGMA foreign_keys=ON;")?;
+
+        let report = storage.cleanup(std::slice::from_ref(&kept.path))?;
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(report.removed_books, vec![gone.path.clone()]);
+        assert_eq!(report.orphaned_rows, 1);
+        assert_eq!(
+            report.removed_tags,
+            vec![
+                (TagKind::Collection, "unused".to_string()),
+                (TagKind::Tag, "stale".to_string()),
+            ]
+        );
+        let mut paths: Vec<String> = storage.list_books()?.into_iter().map(|b| b.path).collect();
+        paths.sort();
+        let mut expected = vec![kept.path.clone(), present.path.clone()];
+        expected.sort();
+        assert_eq!(paths, expected);
+        assert!(storage.list_progress()?.is_empty());
+        assert!(storage.list_notes_by_path()?.is_empty());
+        assert_eq!(
+            storage.list_tag_names(TagKind::Tag)?,
+            vec!["live".to_string()]
+        );
+        Ok(())
+    }
 }
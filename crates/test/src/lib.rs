@@ -1,16 +1,44 @@
 //! Test helpers and fixtures.
 
-use bookshelf_core::{KittyImageQuality, ReaderMode, ReaderTextMode, ScanScope, Settings, Theme};
+use bookshelf_core::{
+    AutoCrop, DownscaleFilter, ImageCacheLimit, KittyImageQuality, ReaderMode, ReaderTextMode,
+    ScanScope, Settings, SortMode, Theme,
+};
 
 pub fn make_settings() -> Settings {
     Settings {
         reader_mode: ReaderMode::Text,
         reader_text_mode: ReaderTextMode::Reflow,
         reader_trim_headers_footers: true,
+        reader_detect_columns: true,
+        reader_text_width: 0,
+        reader_line_spacing: 10,
+        reader_invert_colors: false,
         kitty_image_quality: KittyImageQuality::Balanced,
+        downscale_filter: DownscaleFilter::Auto,
+        image_cache_limit: ImageCacheLimit::Medium,
+        remote_mode: false,
+        furniture_sample_pages: 8,
+        furniture_min_fraction: 0.6,
+        auto_crop: AutoCrop::Off,
+        external_viewer: String::new(),
+        graphics_terminal: String::new(),
+        tick_rate_ms: 250,
         theme: Theme::Dark,
         scan_scope: ScanScope::Recursive,
+        watch_library: true,
+        fingerprint_books: true,
+        last_scan_at: None,
+        library_decorations: true,
+        resume_last_book: false,
+        mouse_capture: true,
+        confirm_quit: true,
+        reader_minimap: true,
+        library_grid: false,
+        autosave_secs: 30,
+        sort_mode: SortMode::Title,
         library_roots: Vec::new(),
+        key_bindings: Default::default(),
     }
 }
 
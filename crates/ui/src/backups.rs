@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+/// The Settings panel's list of database backups to restore from.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BackupsPanel {
+    pub(crate) open: bool,
+    pub(crate) selected: usize,
+    /// Newest first.
+    pub(crate) backups: Vec<PathBuf>,
+    /// Set while asking whether to replace the library with the selected backup.
+    pub(crate) confirm: bool,
+    pub(crate) error: Option<String>,
+}
+
+impl BackupsPanel {
+    pub(crate) fn show(&mut self, backups: anyhow::Result<Vec<PathBuf>>) {
+        *self = BackupsPanel {
+            open: true,
+            ..BackupsPanel::default()
+        };
+        match backups {
+            Ok(backups) if backups.is_empty() => {
+                self.error = Some("No backups yet; use Backup database first".to_string());
+            }
+            Ok(backups) => self.backups = backups,
+            Err(err) => self.error = Some(format!("{err:#}")),
+        }
+    }
+
+    pub(crate) fn selected_backup(&self) -> Option<&Path> {
+        self.backups.get(self.selected).map(PathBuf::as_path)
+    }
+
+    /// Asks before restoring; does nothing without a backup to restore.
+    pub(crate) fn ask_to_restore(&mut self) {
+        self.confirm = self.selected_backup().is_some();
+    }
+}
+
+/// A backup as one list row: its file name.
+pub(crate) fn backup_label(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restoring_asks_only_with_a_backup_selected() {
+        let mut panel = BackupsPanel::default();
+        panel.show(Ok(vec![
+            PathBuf::from("/db/bookshelf-backup-20261016-101500.db"),
+            PathBuf::from("/db/bookshelf-backup-20261015-090000.db"),
+        ]));
+        assert!(panel.open && panel.error.is_none());
+        panel.selected = 1;
+        panel.ask_to_restore();
+        assert!(panel.confirm);
+        assert_eq!(
+            panel.selected_backup().map(backup_label).as_deref(),
+            Some("bookshelf-backup-20261015-090000.db")
+        );
+
+        panel.show(Ok(Vec::new()));
+        assert!(!panel.confirm);
+        panel.ask_to_restore();
+        assert!(!panel.confirm);
+        assert!(panel.error.is_some());
+
+        panel.show(Err(anyhow::anyhow!("permission denied")));
+        assert_eq!(panel.error.as_deref(), Some("permission denied"));
+    }
+}
@@ -0,0 +1,37 @@
+use std::io::{self, Write};
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+
+/// Puts `text` on the system clipboard through the terminal (OSC 52), so it also works over SSH.
+/// Inside tmux the sequence is wrapped for passthrough, like the kitty graphics.
+pub(crate) fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let in_tmux = std::env::var_os("TMUX").is_some();
+    let mut stdout = io::stdout();
+    stdout.write_all(osc52_sequence(text, in_tmux).as_bytes())?;
+    stdout.flush()
+}
+
+pub(crate) fn osc52_sequence(text: &str, in_tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    if in_tmux {
+        // tmux forwards DCS passthrough payloads with every ESC doubled.
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn osc52_encodes_and_wraps_for_tmux() {
+        assert_eq!(osc52_sequence("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(
+            osc52_sequence("hi", true),
+            "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\"
+        );
+    }
+}
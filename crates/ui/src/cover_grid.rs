@@ -0,0 +1,216 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use image::DynamicImage;
+
+use ratatui_image::protocol::Protocol as ImageProtocol;
+
+/// Columns one grid cell takes, the gap to its neighbour included.
+pub(crate) const GRID_CELL_WIDTH: u16 = 16;
+/// Rows one cell takes with covers: the cover, then the caption.
+pub(crate) const GRID_COVER_CELL_HEIGHT: u16 = 12;
+/// Rows one cell takes as a boxed title card, for terminals without images.
+pub(crate) const GRID_CARD_CELL_HEIGHT: u16 = 6;
+/// Caption rows under a cover.
+pub(crate) const GRID_CAPTION_ROWS: u16 = 2;
+const GRID_COVER_CACHE_MAX: usize = 256;
+
+/// An arrow key in the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GridMove {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Cells that fit side by side in `width` columns; always at least one.
+pub(crate) fn grid_columns(width: u16) -> usize {
+    usize::from(width / GRID_CELL_WIDTH).max(1)
+}
+
+/// Where `mv` takes the cursor at `pos` in a grid of `len` cells, `columns` wide; `None` at
+/// an edge. Left and Right stay in their row. Down from above a short last row lands on the
+/// last cell.
+pub(crate) fn grid_move(pos: usize, len: usize, columns: usize, mv: GridMove) -> Option<usize> {
+    let columns = columns.max(1);
+    if pos >= len {
+        return None;
+    }
+    match mv {
+        GridMove::Left => (!pos.is_multiple_of(columns)).then(|| pos - 1),
+        GridMove::Right => (pos % columns + 1 < columns && pos + 1 < len).then_some(pos + 1),
+        GridMove::Up => pos.checked_sub(columns),
+        GridMove::Down => {
+            (pos / columns < (len - 1) / columns).then(|| (pos + columns).min(len - 1))
+        }
+    }
+}
+
+/// First row to draw so `selected_row` is among the `rows_shown`, scrolling as little as
+/// possible from `first_row`.
+pub(crate) fn grid_first_row(first_row: usize, selected_row: usize, rows_shown: usize) -> usize {
+    let rows_shown = rows_shown.max(1);
+    if selected_row < first_row {
+        selected_row
+    } else if selected_row >= first_row + rows_shown {
+        selected_row + 1 - rows_shown
+    } else {
+        first_row
+    }
+}
+
+/// Covers for the library grid: first-page renders by book path.
+#[derive(Default)]
+pub(crate) struct CoverGrid {
+    /// Cells per row in the last frame; Up and Down move this far.
+    pub(crate) columns: usize,
+    /// Row of cells at the top of the last frame.
+    pub(crate) first_row: usize,
+    /// `None` marks a book whose first page failed to render.
+    images: HashMap<String, Option<Arc<DynamicImage>>>,
+    /// Renders asked of the worker and not back yet.
+    pending: HashSet<String>,
+    /// Protocols for the covers on screen, with the cell size they fit.
+    protocols: HashMap<String, ((u16, u16), ImageProtocol)>,
+    /// Books on screen in the last frame; their covers are kept over the others.
+    shown: HashSet<String>,
+}
+
+impl CoverGrid {
+    pub(crate) fn image(&self, path: &str) -> Option<Option<Arc<DynamicImage>>> {
+        self.images.get(path).cloned()
+    }
+
+    /// Starts a frame showing `paths`. Returns the covers still to render when they differ
+    /// from those already asked for; the worker drops the rest of its earlier list.
+    pub(crate) fn show(&mut self, paths: &[&str]) -> Option<Vec<String>> {
+        self.shown = paths.iter().map(|path| path.to_string()).collect();
+        self.protocols.retain(|path, _| self.shown.contains(path));
+        let missing: HashSet<String> = paths
+            .iter()
+            .filter(|path| !self.images.contains_key(**path))
+            .map(|path| path.to_string())
+            .collect();
+        if missing == self.pending {
+            return None;
+        }
+        let wanted = paths
+            .iter()
+            .filter(|path| missing.contains(**path))
+            .map(|path| path.to_string())
+            .collect();
+        self.pending = missing;
+        Some(wanted)
+    }
+
+    /// Whether covers on screen are still rendering.
+    pub(crate) fn is_waiting(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Takes a finished cover. Returns `true` when it is on screen.
+    pub(crate) fn insert(&mut self, path: String, image: Option<DynamicImage>) -> bool {
+        self.pending.remove(&path);
+        self.protocols.remove(&path);
+        let shown = self.shown.contains(&path);
+        self.images.insert(path, image.map(Arc::new));
+        while self.images.len() > GRID_COVER_CACHE_MAX {
+            let Some(evict) = self
+                .images
+                .keys()
+                .find(|path| !self.shown.contains(*path))
+                .cloned()
+            else {
+                break;
+            };
+            self.images.remove(&evict);
+        }
+        shown
+    }
+
+    /// The protocol for `path` sized for `size` cells, built from its cover when missing or
+    /// built for another size.
+    pub(crate) fn protocol(
+        &mut self,
+        path: &str,
+        size: (u16, u16),
+        build: impl FnOnce(&DynamicImage) -> Option<ImageProtocol>,
+    ) -> Option<&ImageProtocol> {
+        let stale = self
+            .protocols
+            .get(path)
+            .is_none_or(|(built_for, _)| *built_for != size);
+        if stale {
+            match self
+                .images
+                .get(path)
+                .and_then(Option::as_ref)
+                .and_then(|image| build(image))
+            {
+                Some(protocol) => {
+                    self.protocols.insert(path.to_string(), (size, protocol));
+                }
+                None => {
+                    self.protocols.remove(path);
+                }
+            }
+        }
+        self.protocols.get(path).map(|(_, protocol)| protocol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrows_move_in_two_dimensions() {
+        // 0 1 2
+        // 3 4 5
+        // 6 7
+        assert_eq!(grid_move(4, 8, 3, GridMove::Left), Some(3));
+        assert_eq!(grid_move(3, 8, 3, GridMove::Left), None);
+        assert_eq!(grid_move(4, 8, 3, GridMove::Right), Some(5));
+        assert_eq!(grid_move(5, 8, 3, GridMove::Right), None);
+        assert_eq!(grid_move(7, 8, 3, GridMove::Right), None);
+        assert_eq!(grid_move(4, 8, 3, GridMove::Up), Some(1));
+        assert_eq!(grid_move(1, 8, 3, GridMove::Up), None);
+        assert_eq!(grid_move(4, 8, 3, GridMove::Down), Some(7));
+        assert_eq!(grid_move(5, 8, 3, GridMove::Down), Some(7));
+        assert_eq!(grid_move(7, 8, 3, GridMove::Down), None);
+        assert_eq!(grid_move(0, 0, 3, GridMove::Down), None);
+        // One column is the list.
+        assert_eq!(grid_move(2, 8, 1, GridMove::Down), Some(3));
+        assert_eq!(grid_move(2, 8, 0, GridMove::Right), None);
+    }
+
+    #[test]
+    fn scrolling_keeps_the_selected_row_on_screen() {
+        assert_eq!(grid_first_row(0, 1, 3), 0);
+        assert_eq!(grid_first_row(0, 4, 3), 2);
+        assert_eq!(grid_first_row(5, 2, 3), 2);
+        assert_eq!(grid_first_row(2, 3, 0), 3);
+        assert_eq!(grid_columns(40), 2);
+        assert_eq!(grid_columns(4), 1);
+    }
+
+    #[test]
+    fn only_new_cover_lists_go_to_the_worker() {
+        let mut grid = CoverGrid::default();
+        assert_eq!(
+            grid.show(&["a", "b"]),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(grid.show(&["b", "a"]), None);
+        assert!(grid.insert("a".to_string(), Some(DynamicImage::new_rgba8(2, 3))));
+        assert_eq!(
+            grid.show(&["b", "c"]),
+            Some(vec!["b".to_string(), "c".to_string()])
+        );
+        assert!(grid.image("a").is_some_and(|image| image.is_some()));
+        assert!(!grid.insert("a".to_string(), None));
+        assert!(matches!(grid.image("a"), Some(None)));
+        assert!(grid.is_waiting());
+    }
+}
@@ -0,0 +1,295 @@
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyEvent, KeyEventKind};
+
+/// Without input for this long, the event loop waits `IDLE_POLL_INTERVAL` between ticks.
+pub(crate) const IDLE_AFTER: Duration = Duration::from_secs(30);
+pub(crate) const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the input thread checks whether the event loop has stopped.
+const INPUT_STOP_CHECK: Duration = Duration::from_millis(500);
+
+/// Where terminal events come from.
+pub(crate) trait EventSource {
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool>;
+    fn read(&mut self) -> io::Result<Event>;
+}
+
+/// Crossterm's input queue.
+pub(crate) struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        event::poll(timeout)
+    }
+
+    fn read(&mut self) -> io::Result<Event> {
+        event::read()
+    }
+}
+
+/// What the event loop waits on: terminal input, or word from a background thread.
+#[derive(Debug)]
+pub(crate) enum LoopMessage {
+    Input(Event),
+    /// Background work sent results; the loop collects them before waiting again.
+    Wake,
+    /// Reading the terminal failed and the input thread stopped.
+    InputFailed(String),
+}
+
+/// Terminal input and background wake-ups on one channel, so the event loop blocks on both
+/// with a single timeout. A wake ends a `poll` early with `false`.
+pub(crate) struct ChannelEvents {
+    messages: Receiver<LoopMessage>,
+    /// The input event `poll` took off the channel, for `read`.
+    ready: Option<Event>,
+}
+
+impl ChannelEvents {
+    pub(crate) fn new(messages: Receiver<LoopMessage>) -> Self {
+        Self {
+            messages,
+            ready: None,
+        }
+    }
+}
+
+impl EventSource for ChannelEvents {
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        if self.ready.is_some() {
+            return Ok(true);
+        }
+        let message = if timeout.is_zero() {
+            self.messages.try_recv().ok()
+        } else {
+            match self.messages.recv_timeout(timeout) {
+                Ok(message) => Some(message),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::other("event channel closed"));
+                }
+            }
+        };
+        match message {
+            Some(LoopMessage::Input(event)) => {
+                self.ready = Some(event);
+                Ok(true)
+            }
+            Some(LoopMessage::InputFailed(err)) => Err(io::Error::other(err)),
+            Some(LoopMessage::Wake) | None => Ok(false),
+        }
+    }
+
+    fn read(&mut self) -> io::Result<Event> {
+        self.ready
+            .take()
+            .ok_or_else(|| io::Error::other("no event polled"))
+    }
+}
+
+/// Reads the terminal on its own thread and forwards each event to the event loop. Dropping
+/// it stops the thread within `INPUT_STOP_CHECK`; an event that arrives meanwhile is left
+/// in crossterm's queue rather than read.
+pub(crate) struct InputThread {
+    stop: Arc<AtomicBool>,
+}
+
+impl InputThread {
+    pub(crate) fn spawn(messages: Sender<LoopMessage>) -> io::Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        thread::Builder::new()
+            .name("bookshelf-input".to_string())
+            .spawn(move || {
+                let mut terminal = TerminalEvents;
+                while !stopped.load(Ordering::Relaxed) {
+                    let message = match terminal.poll(INPUT_STOP_CHECK) {
+                        Ok(false) => continue,
+                        Ok(true) if stopped.load(Ordering::Relaxed) => break,
+                        Ok(true) => match terminal.read() {
+                            Ok(event) => LoopMessage::Input(event),
+                            Err(err) => LoopMessage::InputFailed(err.to_string()),
+                        },
+                        Err(err) => LoopMessage::InputFailed(err.to_string()),
+                    };
+                    let failed = matches!(message, LoopMessage::InputFailed(_));
+                    if messages.send(message).is_err() || failed {
+                        break;
+                    }
+                }
+            })?;
+        Ok(Self { stop })
+    }
+}
+
+impl Drop for InputThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// How long the event loop waits for input when nothing is running in the background:
+/// `tick_rate` normally, longer once nothing has come in for `IDLE_AFTER`.
+pub(crate) fn idle_wait(tick_rate: Duration, since_input: Duration) -> Duration {
+    if since_input >= IDLE_AFTER {
+        tick_rate.max(IDLE_POLL_INTERVAL)
+    } else {
+        tick_rate
+    }
+}
+
+/// Hands out events one frame at a time. Page flips already queued behind a page flip are
+/// taken together, so a burst of Right presses renders one page instead of each in turn. The
+/// first other event is held for the next frame, which keeps the order keys were typed in.
+#[derive(Debug, Default)]
+pub(crate) struct EventQueue {
+    held: Option<Event>,
+}
+
+impl EventQueue {
+    /// The held event, else the next one from `source` within `timeout`.
+    pub(crate) fn next(
+        &mut self,
+        source: &mut impl EventSource,
+        timeout: Duration,
+    ) -> io::Result<Option<Event>> {
+        if let Some(event) = self.held.take() {
+            return Ok(Some(event));
+        }
+        if !source.poll(timeout)? {
+            return Ok(None);
+        }
+        source.read().map(Some)
+    }
+
+    /// Page flips waiting in `source`, up to the first other event, which is held. Key
+    /// releases in between are dropped.
+    pub(crate) fn queued_flips(
+        &mut self,
+        source: &mut impl EventSource,
+        is_flip: impl Fn(&KeyEvent) -> bool,
+    ) -> io::Result<Vec<KeyEvent>> {
+        let mut flips = Vec::new();
+        while self.held.is_none() && source.poll(Duration::ZERO)? {
+            match source.read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Release => {}
+                Event::Key(key) if is_flip(&key) => flips.push(key),
+                event => self.held = Some(event),
+            }
+        }
+        Ok(flips)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+
+    struct Queued(VecDeque<Event>);
+
+    impl EventSource for Queued {
+        fn poll(&mut self, _timeout: Duration) -> io::Result<bool> {
+            Ok(!self.0.is_empty())
+        }
+
+        fn read(&mut self) -> io::Result<Event> {
+            self.0
+                .pop_front()
+                .ok_or_else(|| io::Error::other("no event queued"))
+        }
+    }
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn is_flip(key: &KeyEvent) -> bool {
+        matches!(key.code, KeyCode::Left | KeyCode::Right)
+    }
+
+    /// Each frame's events, as handled by the event loop.
+    fn frames(events: Vec<Event>) -> io::Result<Vec<Vec<Event>>> {
+        let mut source = Queued(events.into());
+        let mut queue = EventQueue::default();
+        let mut frames = Vec::new();
+        while let Some(event) = queue.next(&mut source, Duration::ZERO)? {
+            let mut frame = vec![event.clone()];
+            if let Event::Key(first) = event
+                && is_flip(&first)
+            {
+                let flips = queue.queued_flips(&mut source, is_flip)?;
+                frame.extend(flips.into_iter().map(Event::Key));
+            }
+            frames.push(frame);
+        }
+        Ok(frames)
+    }
+
+    #[test]
+    fn wakes_end_the_wait_without_an_event() -> io::Result<()> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut source = ChannelEvents::new(rx);
+        let g = key(KeyCode::Char('g'));
+        tx.send(LoopMessage::Wake).unwrap();
+        tx.send(LoopMessage::Input(g.clone())).unwrap();
+
+        let mut queue = EventQueue::default();
+        assert_eq!(queue.next(&mut source, Duration::from_secs(5))?, None);
+        assert_eq!(queue.next(&mut source, Duration::from_secs(5))?, Some(g));
+        assert_eq!(queue.next(&mut source, Duration::from_millis(1))?, None);
+
+        tx.send(LoopMessage::InputFailed("gone".to_string()))
+            .unwrap();
+        assert!(queue.next(&mut source, Duration::ZERO).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn idle_loops_wait_longer() {
+        let tick = Duration::from_millis(100);
+        assert_eq!(idle_wait(tick, Duration::from_secs(1)), tick);
+        assert_eq!(idle_wait(tick, IDLE_AFTER), IDLE_POLL_INTERVAL);
+        let slow = Duration::from_secs(5);
+        assert_eq!(idle_wait(slow, IDLE_AFTER), slow);
+    }
+
+    #[test]
+    fn queued_flips_share_a_frame_without_passing_other_keys() -> io::Result<()> {
+        let right = key(KeyCode::Right);
+        let left = key(KeyCode::Left);
+        let g = key(KeyCode::Char('g'));
+        let mut release = KeyEvent::new(KeyCode::Right, KeyModifiers::NONE);
+        release.kind = KeyEventKind::Release;
+
+        let frames = frames(vec![
+            right.clone(),
+            Event::Key(release),
+            right.clone(),
+            left.clone(),
+            g.clone(),
+            right.clone(),
+            right.clone(),
+            Event::FocusLost,
+        ])?;
+        assert_eq!(
+            frames,
+            vec![
+                vec![right.clone(), right.clone(), left],
+                vec![g],
+                vec![right.clone(), right],
+                vec![Event::FocusLost],
+            ]
+        );
+        Ok(())
+    }
+}
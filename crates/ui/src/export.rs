@@ -0,0 +1,181 @@
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+/// Resolution the export panel starts with.
+pub(crate) const DEFAULT_EXPORT_DPI: u32 = 150;
+/// Resolutions the export panel accepts.
+pub(crate) const EXPORT_DPI_RANGE: RangeInclusive<u32> = 36..=600;
+/// Failed pages named in the summary; any beyond these are only counted.
+const SUMMARY_FAILURES: usize = 3;
+
+/// Resolves export-panel input to a 1-based, inclusive page range.
+///
+/// Accepts `current`, a single page such as `12`, or a range such as `12-15`. Errors are the
+/// messages shown in the panel, worded like the goto panel's.
+pub(crate) fn resolve_export_range(
+    input: &str,
+    current: u32,
+    total: Option<u32>,
+) -> Result<(u32, u32), String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Enter a page range".to_string());
+    }
+    let (first, last) = if input.eq_ignore_ascii_case("current") {
+        (current, current)
+    } else if let Some((first, last)) = input.split_once('-') {
+        (parse_page(first)?, parse_page(last)?)
+    } else {
+        let page = parse_page(input)?;
+        (page, page)
+    };
+    if first > last {
+        return Err("First page is after the last".to_string());
+    }
+    let max = total.unwrap_or(u32::MAX);
+    if first < 1 || last > max {
+        return Err(match total {
+            Some(total) => format!("Page out of range (1..={total})"),
+            None => "Invalid page number".to_string(),
+        });
+    }
+    Ok((first, last))
+}
+
+fn parse_page(input: &str) -> Result<u32, String> {
+    input
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| "Invalid page range (12, 12-15 or current)".to_string())
+}
+
+pub(crate) fn resolve_export_dpi(input: &str) -> Result<u32, String> {
+    input
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|dpi| EXPORT_DPI_RANGE.contains(dpi))
+        .ok_or_else(|| {
+            format!(
+                "DPI must be {}..={}",
+                EXPORT_DPI_RANGE.start(),
+                EXPORT_DPI_RANGE.end()
+            )
+        })
+}
+
+/// File name of an exported page: the book's file stem and the 1-based page, e.g.
+/// `paper-p0012.png`.
+pub(crate) fn export_file_name(stem: &str, page: u32) -> String {
+    format!("{stem}-p{page:04}.png")
+}
+
+/// An export the render worker is working through, page by page.
+#[derive(Debug, Clone)]
+pub(crate) struct ExportRun {
+    pub(crate) book_path: String,
+    pub(crate) dir: PathBuf,
+    pub(crate) total: u32,
+    done: u32,
+    /// 1-based page and error, in page order.
+    failed: Vec<(u32, String)>,
+}
+
+impl ExportRun {
+    pub(crate) fn new(book_path: String, dir: PathBuf, total: u32) -> Self {
+        Self {
+            book_path,
+            dir,
+            total,
+            done: 0,
+            failed: Vec::new(),
+        }
+    }
+
+    /// Counts one finished page (1-based). Returns whether that was the last one.
+    pub(crate) fn record(&mut self, page: u32, result: Result<(), String>) -> bool {
+        self.done += 1;
+        if let Err(err) = result {
+            self.failed.push((page, err));
+        }
+        self.done >= self.total
+    }
+
+    pub(crate) fn progress(&self) -> String {
+        format!("exporting pages: {}/{}", self.done, self.total)
+    }
+
+    /// One line for the notice area: how many pages were written, and which failed and why.
+    pub(crate) fn summary(&self) -> String {
+        let written = self.total.saturating_sub(self.failed.len() as u32);
+        let dir = self.dir.display();
+        if self.failed.is_empty() {
+            let pages = if written == 1 { "page" } else { "pages" };
+            return format!("exported {written} {pages} to {dir}");
+        }
+        let mut failures: Vec<String> = self
+            .failed
+            .iter()
+            .take(SUMMARY_FAILURES)
+            .map(|(page, err)| format!("p{page} ({err})"))
+            .collect();
+        if self.failed.len() > SUMMARY_FAILURES {
+            failures.push(format!("{} more", self.failed.len() - SUMMARY_FAILURES));
+        }
+        format!(
+            "exported {written} of {} pages to {dir}; failed: {}",
+            self.total,
+            failures.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_resolve_and_validate_like_goto() {
+        assert_eq!(resolve_export_range("current", 7, Some(20)), Ok((7, 7)));
+        assert_eq!(resolve_export_range(" 12-15 ", 1, Some(20)), Ok((12, 15)));
+        assert_eq!(resolve_export_range("3", 1, None), Ok((3, 3)));
+        assert_eq!(
+            resolve_export_range("12-25", 1, Some(20)),
+            Err("Page out of range (1..=20)".to_string())
+        );
+        assert_eq!(
+            resolve_export_range("0", 1, Some(20)),
+            Err("Page out of range (1..=20)".to_string())
+        );
+        assert_eq!(
+            resolve_export_range("15-12", 1, Some(20)),
+            Err("First page is after the last".to_string())
+        );
+        assert!(resolve_export_range("twelve", 1, Some(20)).is_err());
+        assert!(resolve_export_range("", 1, Some(20)).is_err());
+
+        assert_eq!(resolve_export_dpi("300"), Ok(300));
+        assert!(resolve_export_dpi("2000").is_err());
+        assert_eq!(export_file_name("paper", 12), "paper-p0012.png");
+    }
+
+    #[test]
+    fn summary_lists_failed_pages() {
+        let mut run = ExportRun::new("/b.pdf".to_string(), PathBuf::from("/out"), 6);
+        assert!(!run.record(1, Ok(())));
+        assert_eq!(run.progress(), "exporting pages: 1/6");
+        for page in 2..=5 {
+            run.record(page, Err(format!("bad page {page}")));
+        }
+        assert!(run.record(6, Ok(())));
+        assert_eq!(
+            run.summary(),
+            "exported 2 of 6 pages to /out; failed: p2 (bad page 2), p3 (bad page 3), \
+             p4 (bad page 4), 1 more"
+        );
+
+        let mut run = ExportRun::new("/b.pdf".to_string(), PathBuf::from("/out"), 1);
+        run.record(4, Ok(()));
+        assert_eq!(run.summary(), "exported 1 page to /out");
+    }
+}
@@ -0,0 +1,148 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Used when no viewer is configured: the platform's "open with the default app" command.
+pub(crate) fn default_template() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "open %f"
+    } else if cfg!(windows) {
+        "explorer %f"
+    } else {
+        "xdg-open %f"
+    }
+}
+
+/// Builds the viewer command from `template`, substituting `%f` with the file and `%p` with the
+/// 1-based page. Words split on whitespace outside double quotes; a template without `%f` gets
+/// the file appended. An empty template uses [`default_template`].
+pub(crate) fn viewer_command(template: &str, path: &Path, page: u32) -> anyhow::Result<Command> {
+    let template = if template.trim().is_empty() {
+        default_template()
+    } else {
+        template
+    };
+    let file = path.to_string_lossy();
+    let page = page.to_string();
+    let mut words = split_words(template);
+    let has_file = words.iter().any(|word| word.contains("%f"));
+    let mut args = words
+        .drain(..)
+        .map(|word| substitute_placeholders(&word, &file, &page));
+    let program = args
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("external viewer command is empty"))?;
+    let mut command = Command::new(program);
+    command.args(args);
+    if !has_file {
+        command.arg(path);
+    }
+    Ok(command)
+}
+
+/// Starts the viewer detached from the terminal: no shared stdio, its own process group on
+/// Unix so terminal signals aimed at the UI skip it, and reaped on a background thread.
+pub(crate) fn spawn_viewer(template: &str, path: &Path, page: u32) -> anyhow::Result<()> {
+    let mut command = viewer_command(template, path, page)?;
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt as _;
+        command.process_group(0);
+    }
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .spawn()
+        .map_err(|err| anyhow::anyhow!("{program}: {err}"))?;
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+/// Replaces `%f` and `%p` in `word` in a single pass, so a file name containing `%p` is kept
+/// as it is.
+pub(crate) fn substitute_placeholders(word: &str, file: &str, page: &str) -> String {
+    let mut out = String::with_capacity(word.len());
+    let mut rest = word;
+    while let Some(at) = rest.find('%') {
+        out.push_str(&rest[..at]);
+        let tail = &rest[at..];
+        if let Some(after) = tail.strip_prefix("%f") {
+            out.push_str(file);
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("%p") {
+            out.push_str(page);
+            rest = after;
+        } else {
+            out.push('%');
+            rest = &tail[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub(crate) fn split_words(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for ch in input.chars() {
+        match ch {
+            '"' => in_quotes = !in_quotes,
+            ch if ch.is_whitespace() && !in_quotes => words.push(std::mem::take(&mut current)),
+            ch => current.push(ch),
+        }
+    }
+    words.push(current);
+    words.retain(|word| !word.is_empty());
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(command: &Command) -> Vec<String> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|part| part.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn substitutes_file_and_page() -> anyhow::Result<()> {
+        let path = Path::new("/books/My Book.pdf");
+        let command = viewer_command("zathura --page=%p \"%f\"", path, 12)?;
+        assert_eq!(
+            parts(&command),
+            vec!["zathura", "--page=12", "/books/My Book.pdf"]
+        );
+
+        let command = viewer_command("okular -p %p", path, 3)?;
+        assert_eq!(
+            parts(&command),
+            vec!["okular", "-p", "3", "/books/My Book.pdf"]
+        );
+
+        let command = viewer_command("  ", path, 1)?;
+        assert_eq!(
+            parts(&command).last().map(String::as_str),
+            Some("/books/My Book.pdf")
+        );
+        assert!(viewer_command("\"\"", path, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn spawn_reports_missing_programs() {
+        let err = spawn_viewer("bookshelf-no-such-viewer %f", Path::new("/tmp/a.pdf"), 1)
+            .expect_err("missing program");
+        assert!(
+            err.to_string().contains("bookshelf-no-such-viewer"),
+            "{err}"
+        );
+    }
+}
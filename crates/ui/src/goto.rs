@@ -0,0 +1,306 @@
+use bookshelf_core::TocItem;
+
+/// Resolves goto-panel input to a 1-based page.
+///
+/// Input that is exactly one of the book's printed page `labels` goes to that page, so "57"
+/// is the page printed 57 and "xii" a page of the front matter; labels past `total` are
+/// ignored. Otherwise it is a bare page
+/// number, a `+N`/`-N` offset from `current` (1-based), a percentage of `total` such as
+/// `50%`, or a chapter from `toc`: `c12` for the 12th top-level entry and `c intro` for the
+/// first entry whose title contains "intro". Errors are the messages shown in the panel.
+pub(crate) fn resolve_goto_target(
+    input: &str,
+    current: u32,
+    total: Option<u32>,
+    toc: &[TocItem],
+    labels: &[String],
+) -> Result<u32, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Enter a page number".to_string());
+    }
+    let labeled = total.map_or(labels.len(), |total| labels.len().min(total as usize));
+    if let Some(idx) = labels[..labeled].iter().position(|label| label == input) {
+        return Ok(idx as u32 + 1);
+    }
+
+    let page = if let Some(chapter) = input.strip_prefix('c').or_else(|| input.strip_prefix('C')) {
+        i64::from(chapter_page(chapter.trim(), toc)?)
+    } else if let Some(percent) = input.strip_suffix('%') {
+        let percent = percent
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|p| (0.0..=100.0).contains(p))
+            .ok_or_else(|| "Invalid percentage (0..=100)".to_string())?;
+        let total = total.ok_or_else(|| "Page count unknown; enter a page number".to_string())?;
+        // 0% is the first page and 100% the last.
+        let page = (f64::from(total) * percent / 100.0).round() as u32;
+        i64::from(page.clamp(1, total.max(1)))
+    } else if let Some(offset) = input.strip_prefix('+') {
+        let offset = parse_count(offset)?;
+        i64::from(current) + offset
+    } else if let Some(offset) = input.strip_prefix('-') {
+        let offset = parse_count(offset)?;
+        i64::from(current) - offset
+    } else {
+        parse_count(input)?
+    };
+
+    let max = total.map_or(i64::from(u32::MAX), i64::from);
+    if page < 1 || page > max {
+        return Err(match total {
+            Some(total) => format!("Page out of range (1..={total})"),
+            None => "Invalid page number".to_string(),
+        });
+    }
+    Ok(page as u32)
+}
+
+/// The printed label of `page` (1-based) when the book labels it other than by its number.
+pub(crate) fn page_label(labels: &[String], page: u32) -> Option<&str> {
+    let label = labels.get(page.checked_sub(1)? as usize)?;
+    (*label != page.to_string()).then_some(label.as_str())
+}
+
+/// `page` (1-based) for lists: its printed label with the number after it, "xii (12)", or
+/// just the number.
+pub(crate) fn labeled_page(labels: &[String], page: u32) -> String {
+    match page_label(labels, page) {
+        Some(label) => format!("{label} ({page})"),
+        None => page.to_string(),
+    }
+}
+
+/// Share of the book read at `page` (1-based), for the panel readout.
+pub(crate) fn page_percent(page: u32, total: u32) -> u32 {
+    if total == 0 {
+        return 0;
+    }
+    ((u64::from(page.min(total)) * 100) / u64::from(total)) as u32
+}
+
+/// Where a next/previous-annotation jump lands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AnnotationJump {
+    /// 1-based `page`, the `position`-th (1-based) of `total` annotated pages.
+    Page {
+        page: u32,
+        position: usize,
+        total: usize,
+    },
+    /// Already at or past the last (or before the first) annotated page; jumps don't wrap.
+    NoMore,
+    NoAnnotations,
+}
+
+/// The nearest page in `annotated` (sorted, 1-based, no repeats) after `current`, or before it
+/// when `forward` is false.
+pub(crate) fn annotation_jump(annotated: &[u32], current: u32, forward: bool) -> AnnotationJump {
+    if annotated.is_empty() {
+        return AnnotationJump::NoAnnotations;
+    }
+    let target = if forward {
+        annotated.iter().position(|page| *page > current)
+    } else {
+        annotated.iter().rposition(|page| *page < current)
+    };
+    match target {
+        Some(idx) => AnnotationJump::Page {
+            page: annotated[idx],
+            position: idx + 1,
+            total: annotated.len(),
+        },
+        None => AnnotationJump::NoMore,
+    }
+}
+
+/// The page of the chapter `query` names: a 1-based number among the top-level entries, or
+/// text the title contains, ignoring case.
+fn chapter_page(query: &str, toc: &[TocItem]) -> Result<u32, String> {
+    if toc.is_empty() {
+        return Err("book has no outline".to_string());
+    }
+    if query.is_empty() {
+        return Err("Enter a chapter number or title".to_string());
+    }
+    let item = if let Ok(number) = query.parse::<usize>() {
+        let top_level: Vec<&TocItem> = toc.iter().filter(|item| item.depth == 0).collect();
+        number
+            .checked_sub(1)
+            .and_then(|idx| top_level.get(idx).copied())
+            .ok_or_else(|| format!("no chapter {number} (1..={})", top_level.len()))?
+    } else {
+        let needle = query.to_lowercase();
+        toc.iter()
+            .find(|item| item.title.to_lowercase().contains(&needle))
+            .ok_or_else(|| format!("no chapter matching '{query}'"))?
+    };
+    item.page
+        .ok_or_else(|| format!("chapter '{}' has no page", item.title.trim()))
+}
+
+fn parse_count(value: &str) -> Result<i64, String> {
+    value
+        .trim()
+        .parse::<u32>()
+        .map(i64::from)
+        .map_err(|_| "Invalid page number".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(title: &str, page: Option<u32>, depth: usize) -> TocItem {
+        TocItem {
+            title: title.to_string(),
+            page,
+            depth,
+        }
+    }
+
+    #[test]
+    fn annotation_jumps_stop_at_the_ends() {
+        let pages = [3, 7, 20];
+        assert_eq!(
+            annotation_jump(&pages, 7, true),
+            AnnotationJump::Page {
+                page: 20,
+                position: 3,
+                total: 3
+            }
+        );
+        assert_eq!(
+            annotation_jump(&pages, 5, false),
+            AnnotationJump::Page {
+                page: 3,
+                position: 1,
+                total: 3
+            }
+        );
+        assert_eq!(annotation_jump(&pages, 20, true), AnnotationJump::NoMore);
+        assert_eq!(annotation_jump(&pages, 3, false), AnnotationJump::NoMore);
+        assert_eq!(annotation_jump(&[], 1, true), AnnotationJump::NoAnnotations);
+    }
+
+    #[test]
+    fn resolves_absolute_relative_and_percent_targets() {
+        assert_eq!(resolve_goto_target("12", 5, Some(900), &[], &[]), Ok(12));
+        assert_eq!(resolve_goto_target("+20", 5, Some(900), &[], &[]), Ok(25));
+        assert_eq!(resolve_goto_target("-4", 5, Some(900), &[], &[]), Ok(1));
+        assert_eq!(resolve_goto_target("50%", 5, Some(900), &[], &[]), Ok(450));
+        assert_eq!(resolve_goto_target("0%", 5, Some(900), &[], &[]), Ok(1));
+        assert_eq!(
+            resolve_goto_target("100 %", 5, Some(900), &[], &[]),
+            Ok(900)
+        );
+        assert_eq!(resolve_goto_target("+3", 5, None, &[], &[]), Ok(8));
+    }
+
+    #[test]
+    fn rejects_out_of_range_and_malformed_input() {
+        let out_of_range = Err("Page out of range (1..=900)".to_string());
+        assert_eq!(
+            resolve_goto_target("901", 5, Some(900), &[], &[]),
+            out_of_range
+        );
+        assert_eq!(
+            resolve_goto_target("-5", 5, Some(900), &[], &[]),
+            out_of_range
+        );
+        assert_eq!(
+            resolve_goto_target("+896", 5, Some(900), &[], &[]),
+            out_of_range
+        );
+        assert_eq!(
+            resolve_goto_target("0", 5, None, &[], &[]),
+            Err("Invalid page number".to_string())
+        );
+        assert_eq!(
+            resolve_goto_target("+", 5, Some(900), &[], &[]),
+            Err("Invalid page number".to_string())
+        );
+        assert_eq!(
+            resolve_goto_target("150%", 5, Some(900), &[], &[]),
+            Err("Invalid percentage (0..=100)".to_string())
+        );
+        assert!(resolve_goto_target("50%", 5, None, &[], &[]).is_err());
+        assert_eq!(
+            resolve_goto_target(" ", 5, Some(900), &[], &[]),
+            Err("Enter a page number".to_string())
+        );
+    }
+
+    #[test]
+    fn resolves_chapters_by_number_and_title() {
+        let toc = [
+            item("Introduction", Some(3), 0),
+            item("Setting up", Some(5), 1),
+            item("The Core", Some(40), 0),
+            item("Appendix", None, 0),
+        ];
+        assert_eq!(resolve_goto_target("c2", 1, Some(900), &toc, &[]), Ok(40));
+        assert_eq!(resolve_goto_target("C 1", 1, Some(900), &toc, &[]), Ok(3));
+        assert_eq!(
+            resolve_goto_target("c intro", 9, Some(900), &toc, &[]),
+            Ok(3)
+        );
+        assert_eq!(resolve_goto_target("c SET", 9, None, &toc, &[]), Ok(5));
+        assert_eq!(
+            resolve_goto_target("c glossary", 1, Some(900), &toc, &[]),
+            Err("no chapter matching 'glossary'".to_string())
+        );
+        assert_eq!(
+            resolve_goto_target("c3", 1, Some(900), &toc, &[]),
+            Err("chapter 'Appendix' has no page".to_string())
+        );
+        assert_eq!(
+            resolve_goto_target("c4", 1, Some(900), &toc, &[]),
+            Err("no chapter 4 (1..=3)".to_string())
+        );
+        assert_eq!(
+            resolve_goto_target("c1", 1, Some(900), &[], &[]),
+            Err("book has no outline".to_string())
+        );
+        // A chapter past the page count is still out of range.
+        assert_eq!(
+            resolve_goto_target("c2", 1, Some(10), &toc, &[]),
+            Err("Page out of range (1..=10)".to_string())
+        );
+    }
+
+    #[test]
+    fn printed_labels_win_over_page_numbers() {
+        let labels: Vec<String> = ["i", "ii", "1", "2", "3"].map(String::from).to_vec();
+        assert_eq!(resolve_goto_target("ii", 1, Some(5), &[], &labels), Ok(2));
+        assert_eq!(resolve_goto_target(" 1 ", 5, Some(5), &[], &labels), Ok(3));
+        // No page is printed 5, so it is the fifth page.
+        assert_eq!(resolve_goto_target("5", 1, Some(5), &[], &labels), Ok(5));
+        assert_eq!(resolve_goto_target("+1", 1, Some(5), &[], &labels), Ok(2));
+        assert_eq!(
+            resolve_goto_target("iv", 1, Some(5), &[], &labels),
+            Err("Invalid page number".to_string())
+        );
+
+        assert_eq!(labeled_page(&labels, 2), "ii (2)");
+        assert_eq!(labeled_page(&labels, 3), "1 (3)");
+        assert_eq!(labeled_page(&labels, 9), "9");
+        assert_eq!(labeled_page(&[], 2), "2");
+        let plain: Vec<String> = ["1", "x"].map(String::from).to_vec();
+        assert_eq!(page_label(&plain, 1), None);
+        assert_eq!(page_label(&plain, 0), None);
+
+        // More labels than pages: the extra labels name no page.
+        assert_eq!(resolve_goto_target("3", 1, Some(3), &[], &labels), Ok(3));
+        assert_eq!(resolve_goto_target("ii", 1, Some(3), &[], &labels), Ok(2));
+    }
+
+    #[test]
+    fn page_percent_rounds_down() {
+        assert_eq!(page_percent(450, 900), 50);
+        assert_eq!(page_percent(1, 900), 0);
+        assert_eq!(page_percent(900, 900), 100);
+        assert_eq!(page_percent(3, 0), 0);
+    }
+}
@@ -0,0 +1,1480 @@
+//! Drives a `Ui` without a terminal, for tests of whole panel flows: keys go through
+//! `Ui::handle_event` as they would from the event loop, and frames are drawn into a
+//! `TestBackend` so the screen can be checked as well as `AppContext`.
+//!
+//! Books here have no files behind them, so nothing asks pdfium for a page; a flow that does
+//! gets the engine's usual errors, and `BOOKSHELF_DISABLE_PDFIUM` applies as everywhere else.
+
+use bookshelf_application::AppContext;
+use bookshelf_core::{Book, ReadingStatus, Settings};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+
+use crate::{Ui, UiExit};
+
+const SCREEN_WIDTH: u16 = 120;
+const SCREEN_HEIGHT: u16 = 40;
+
+pub(crate) struct Harness {
+    pub(crate) ui: Ui,
+    terminal: Terminal<TestBackend>,
+}
+
+impl Harness {
+    pub(crate) fn new(ctx: AppContext) -> Self {
+        let terminal = Terminal::new(TestBackend::new(SCREEN_WIDTH, SCREEN_HEIGHT))
+            .expect("test backend terminal");
+        Self {
+            ui: Ui::new(ctx, None),
+            terminal,
+        }
+    }
+
+    /// A library of unread books titled `titles`, with the watcher off.
+    pub(crate) fn library(titles: &[&str]) -> Self {
+        Self::new(library_context(titles))
+    }
+
+    /// Later frames are drawn `width` columns by `height` rows.
+    pub(crate) fn resize(&mut self, width: u16, height: u16) {
+        self.terminal =
+            Terminal::new(TestBackend::new(width, height)).expect("test backend terminal");
+    }
+
+    pub(crate) fn press(&mut self, code: KeyCode) -> Option<UiExit> {
+        self.press_with(code, KeyModifiers::NONE)
+    }
+
+    pub(crate) fn press_with(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Option<UiExit> {
+        self.ui
+            .handle_event(Event::Key(KeyEvent::new(code, modifiers)))
+            .expect("key handled")
+    }
+
+    pub(crate) fn mouse(&mut self, kind: MouseEventKind, column: u16, row: u16) -> Option<UiExit> {
+        self.ui
+            .handle_event(Event::Mouse(MouseEvent {
+                kind,
+                column,
+                row,
+                modifiers: KeyModifiers::NONE,
+            }))
+            .expect("mouse event handled")
+    }
+
+    pub(crate) fn type_text(&mut self, text: &str) {
+        for ch in text.chars() {
+            self.press(KeyCode::Char(ch));
+        }
+    }
+
+    /// Draws a frame and returns it as text, one line per row.
+    pub(crate) fn screen(&mut self) -> String {
+        let Self { ui, terminal } = self;
+        terminal
+            .draw(|frame| ui.draw(frame.area(), frame))
+            .expect("frame drawn");
+        let buffer = terminal.backend().buffer();
+        let width = usize::from(buffer.area.width);
+        buffer
+            .content()
+            .chunks(width)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub(crate) fn library_context(titles: &[&str]) -> AppContext {
+    let books = titles
+        .iter()
+        .map(|title| Book {
+            path: format!("/library/{title}.pdf"),
+            title: title.to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        })
+        .collect();
+    let settings = Settings {
+        watch_library: false,
+        ..Settings::default()
+    };
+    AppContext::new(settings).with_library(String::new(), books)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    use bookshelf_application::{BookPathOp, CollectionFilter, LabelCatalogOp, merge_scan};
+    use bookshelf_core::{
+        BookKind, BookLabels, BookProgress, Bookmark, BookmarkView, LibraryRoot, Note, PageCount,
+        ReaderMode, ReaderTextMode, SettingsImport, TagKind, Theme, TocItem,
+    };
+    use crossterm::event::MouseButton;
+
+    use crate::{
+        PROGRESS_FLUSH_INTERVAL, ReaderPanel, SETTINGS_MENU_BACKUP, SETTINGS_MENU_DUPLICATES,
+        SETTINGS_MENU_EXPORT_SETTINGS, SETTINGS_MENU_IMPORT_SETTINGS, SETTINGS_MENU_RESTORE,
+        book_author, reader_text,
+    };
+
+    use super::*;
+
+    #[test]
+    fn esc_in_the_filters_panel_restores_the_snapshot() {
+        let mut harness = Harness::library(&["Dune", "Emma", "Ulysses"]);
+        harness.press(KeyCode::Down);
+        let screen = harness.screen();
+        assert!(screen.contains(">   · Emma (missing)"), "{screen}");
+
+        harness.press(KeyCode::Char('/'));
+        harness.type_text("dune");
+        harness.press_with(KeyCode::Char('s'), KeyModifiers::CONTROL);
+        assert_eq!(harness.ui.ctx.library_query, "dune");
+        assert!(harness.ui.ctx.status_filter.is_some());
+        harness.press(KeyCode::Esc);
+
+        assert!(!harness.ui.search_panel.open);
+        assert_eq!(harness.ui.ctx.library_query, "");
+        assert_eq!(harness.ui.ctx.status_filter, None);
+        assert_eq!(harness.ui.ctx.books[harness.ui.ctx.selected].title, "Emma");
+        let screen = harness.screen();
+        assert!(!screen.contains("matches"), "{screen}");
+        assert!(screen.contains("Ulysses"), "{screen}");
+    }
+
+    #[test]
+    fn enter_in_the_filters_panel_keeps_the_filters() {
+        let mut harness = Harness::library(&["Dune", "Emma", "Ulysses"]);
+        harness.press(KeyCode::Char('/'));
+        harness.type_text("dune");
+        harness.press(KeyCode::Enter);
+
+        assert_eq!(harness.ui.ctx.library_query, "dune");
+        let screen = harness.screen();
+        assert!(screen.contains("Library — 1/3 matches"), "{screen}");
+        assert!(!screen.contains("Ulysses"), "{screen}");
+    }
+
+    #[test]
+    fn esc_asks_before_quitting_with_unsaved_changes() {
+        let mut harness = Harness::library(&["Dune", "Emma"]);
+        assert_eq!(harness.press(KeyCode::Esc), Some(UiExit::Quit));
+
+        harness.press(KeyCode::Char('f'));
+        assert_eq!(harness.press(KeyCode::Esc), None);
+        let screen = harness.screen();
+        assert!(
+            screen.contains("unsaved changes will be written — Enter to quit, Esc to stay"),
+            "{screen}"
+        );
+        assert_eq!(harness.press(KeyCode::Esc), None);
+        assert!(!harness.screen().contains("unsaved changes"));
+        assert_eq!(harness.press(KeyCode::Esc), None);
+        // Other keys dismiss the prompt and are not acted on.
+        assert_eq!(harness.press(KeyCode::Down), None);
+        assert!(!harness.screen().contains("unsaved changes"));
+        assert_eq!(harness.ui.ctx.selected, 0);
+        assert_eq!(harness.press(KeyCode::Esc), None);
+        assert_eq!(harness.press(KeyCode::Enter), Some(UiExit::Quit));
+
+        // Saving now writes everything through the autosave hook, so Esc quits again.
+        harness.ui.autosave_sink = Some(Box::new(|ctx: &mut AppContext| {
+            let batch = ctx.take_dirty_batch(usize::MAX);
+            ctx.mark_batch_saved(&batch);
+            Ok(batch.len())
+        }));
+        harness.press_with(KeyCode::Char('s'), KeyModifiers::CONTROL);
+        assert!(harness.screen().contains("saved 1 change"));
+        assert!(!harness.ui.ctx.has_unsaved_changes());
+        assert_eq!(harness.press(KeyCode::Esc), Some(UiExit::Quit));
+
+        // Reading leaves progress to save, but nothing the user would be asked about.
+        harness.ui.ctx.record_book_opened("/library/Dune.pdf", 7);
+        harness
+            .ui
+            .ctx
+            .dirty_progress_paths
+            .insert("/library/Dune.pdf".to_string());
+        assert_eq!(harness.press(KeyCode::Esc), Some(UiExit::Quit));
+
+        harness.press(KeyCode::Char('f'));
+        harness.ui.ctx.settings.confirm_quit = false;
+        assert_eq!(harness.press(KeyCode::Esc), Some(UiExit::Quit));
+    }
+
+    #[test]
+    fn book_kinds_badge_library_rows_and_filter_them() {
+        let mut harness = Harness::library(&["Dune", "Emma", "Ulysses"]);
+        let count = |kind| PageCount {
+            pages: 100,
+            size_bytes: 1,
+            modified_secs: 1,
+            kind: Some(kind),
+        };
+        harness
+            .ui
+            .ctx
+            .set_page_count("/library/Dune.pdf", count(BookKind::ScannedPdf));
+        harness
+            .ui
+            .ctx
+            .set_page_count("/library/Emma.pdf", count(BookKind::TextPdf));
+        let screen = harness.screen();
+        assert!(screen.contains("· Emma pdf"), "{screen}");
+        assert!(screen.contains("Kind: scanned PDF (no text)"), "{screen}");
+
+        harness.press(KeyCode::Char('/'));
+        harness.press_with(KeyCode::Char('k'), KeyModifiers::CONTROL);
+        assert_eq!(harness.ui.ctx.kind_filter, Some(BookKind::TextPdf));
+        harness.press(KeyCode::Enter);
+        let screen = harness.screen();
+        assert!(screen.contains("Library — 1/3 matches"), "{screen}");
+        assert!(screen.contains("kind: pdf"), "{screen}");
+        assert!(!screen.contains("Ulysses"), "{screen}");
+
+        // Books whose kind is not known yet are left out of every kind.
+        harness.press(KeyCode::Char('/'));
+        harness.press_with(KeyCode::Char('k'), KeyModifiers::CONTROL);
+        assert_eq!(harness.ui.ctx.kind_filter, Some(BookKind::ScannedPdf));
+        harness.press(KeyCode::Enter);
+        assert_eq!(harness.ui.visible_indices(), vec![0]);
+    }
+
+    #[test]
+    fn a_kind_filter_asks_for_the_kinds_of_books_it_hides() {
+        let dir = std::env::temp_dir().join(format!("bookshelf-kinds-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("test dir");
+        let titles: Vec<String> = (0..60).map(|n| format!("Book {n:02}")).collect();
+        let titles: Vec<&str> = titles.iter().map(String::as_str).collect();
+        let mut ctx = library_context(&titles);
+        for book in &mut ctx.books {
+            let file = dir.join(format!("{}.pdf", book.title));
+            std::fs::write(&file, b"%PDF").expect("test file");
+            book.path = file.to_string_lossy().to_string();
+        }
+        let paths: HashSet<String> = ctx.books.iter().map(|b| b.path.clone()).collect();
+        let mut harness = Harness::new(ctx);
+        // Only the rows on screen are asked about while no kind filter is set.
+        harness.screen();
+        assert!(harness.ui.kinds_requested.len() < paths.len());
+
+        harness.ui.ctx.kind_filter = Some(BookKind::ScannedPdf);
+        let screen = harness.screen();
+        assert!(screen.contains("Library — 0/60 matches"), "{screen}");
+        assert_eq!(harness.ui.kinds_requested, paths);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn cover_grid_moves_in_two_dimensions_over_the_filtered_books() {
+        let mut harness = Harness::library(&["Dune", "Emma", "Kim", "Nana", "Ulysses"]);
+        harness.press(KeyCode::Char('v'));
+        assert!(harness.ui.ctx.settings.library_grid);
+        // The test terminal has no image protocol, so books are title cards, three a row.
+        let screen = harness.screen();
+        assert!(screen.contains("│    Dune     │"), "{screen}");
+        assert!(screen.contains("Ulysses"), "{screen}");
+        assert_eq!(harness.ui.cover_grid.columns, 3);
+
+        let selected =
+            |harness: &Harness| harness.ui.ctx.books[harness.ui.ctx.selected].title.clone();
+        harness.press(KeyCode::Right);
+        assert_eq!(selected(&harness), "Emma");
+        harness.press(KeyCode::Down);
+        assert_eq!(selected(&harness), "Ulysses");
+        harness.press(KeyCode::Right);
+        assert_eq!(selected(&harness), "Ulysses");
+        harness.press(KeyCode::Left);
+        harness.press(KeyCode::Up);
+        assert_eq!(selected(&harness), "Dune");
+
+        // Filters apply to the grid as to the list.
+        harness.press(KeyCode::Char('/'));
+        harness.type_text("m");
+        harness.press(KeyCode::Enter);
+        let screen = harness.screen();
+        assert!(!screen.contains("Dune"), "{screen}");
+        assert_eq!(selected(&harness), "Emma");
+        harness.press(KeyCode::Right);
+        assert_eq!(selected(&harness), "Kim");
+
+        harness.press(KeyCode::Char('v'));
+        let screen = harness.screen();
+        assert!(screen.contains(">   · Kim (missing)"), "{screen}");
+    }
+
+    #[test]
+    fn selecting_a_tag_namespace_filters_by_every_tag_under_it() {
+        let mut ctx = library_context(&["Dune", "Emma", "Kim"]);
+        for (title, tag) in [("Dune", "lang/rust"), ("Emma", "lang/go"), ("Kim", "draft")] {
+            ctx.labels_by_path.insert(
+                format!("/library/{title}.pdf"),
+                BookLabels {
+                    tags: vec![tag.to_string()],
+                    ..BookLabels::default()
+                },
+            );
+        }
+        let mut harness = Harness::new(ctx);
+
+        harness.press(KeyCode::Char('/'));
+        harness.press(KeyCode::Tab);
+        harness.press(KeyCode::Tab);
+        harness.press(KeyCode::Down);
+        harness.press(KeyCode::Char(' '));
+        assert_eq!(harness.ui.ctx.tag_filters, vec!["lang/".to_string()]);
+        let screen = harness.screen();
+        assert!(screen.contains("[x] ▾ lang/ (2)"), "{screen}");
+        assert!(screen.contains("[-]   rust (1)"), "{screen}");
+        assert!(screen.contains("[ ] draft (1)"), "{screen}");
+        assert!(screen.contains("Library — 2/3 matches"), "{screen}");
+
+        harness.press(KeyCode::Down);
+        harness.press(KeyCode::Char('z'));
+        assert_eq!(harness.ui.search_panel.tag_cursor, 1);
+        let screen = harness.screen();
+        assert!(screen.contains("[x] ▸ lang/ (2)"), "{screen}");
+        assert!(!screen.contains("rust (1)"), "{screen}");
+    }
+
+    #[test]
+    fn tags_typed_into_the_assign_filter_are_created() {
+        let mut ctx = library_context(&["Dune"]);
+        ctx.known_tags = vec!["classic".to_string(), "sf".to_string()];
+        let mut harness = Harness::new(ctx);
+
+        harness.press(KeyCode::Char('l'));
+        harness.press(KeyCode::Tab);
+        harness.press(KeyCode::Char('/'));
+        harness.type_text("space opera");
+        harness.press(KeyCode::Enter);
+        assert_eq!(
+            harness.ui.ctx.known_tags,
+            vec!["classic", "sf", "space opera"]
+        );
+        assert_eq!(
+            harness.ui.ctx.dirty_label_catalog_ops,
+            vec![LabelCatalogOp::Create {
+                kind: TagKind::Tag,
+                name: "space opera".to_string(),
+            }]
+        );
+        assert_eq!(
+            harness.ui.assign_labels_panel.staged.tags,
+            vec!["space opera"]
+        );
+
+        // A name the catalog already has, in any case, creates nothing.
+        harness.press(KeyCode::Char('/'));
+        harness.type_text("SF");
+        harness.press_with(KeyCode::Char('n'), KeyModifiers::CONTROL);
+        assert_eq!(
+            harness.ui.assign_labels_panel.error.as_deref(),
+            Some("Tag \"SF\" already exists")
+        );
+        assert_eq!(harness.ui.ctx.known_tags.len(), 3);
+        assert_eq!(harness.ui.ctx.dirty_label_catalog_ops.len(), 1);
+    }
+
+    #[test]
+    fn assigning_labels_to_marked_books_merges_their_tags() {
+        let mut ctx = library_context(&["Dune", "Emma", "Kim"]);
+        ctx.known_tags = vec!["classic".to_string(), "sf".to_string()];
+        for (title, tags) in [("Dune", vec!["sf", "classic"]), ("Emma", vec!["classic"])] {
+            ctx.labels_by_path.insert(
+                format!("/library/{title}.pdf"),
+                BookLabels {
+                    tags: tags.into_iter().map(str::to_string).collect(),
+                    ..BookLabels::default()
+                },
+            );
+        }
+        let mut harness = Harness::new(ctx);
+
+        harness.press(KeyCode::Char(' '));
+        harness.press(KeyCode::Down);
+        harness.press(KeyCode::Char(' '));
+        harness.press(KeyCode::Char('l'));
+        // Only the tag both books share starts out staged.
+        assert_eq!(harness.ui.assign_labels_panel.staged.tags, vec!["classic"]);
+        harness.press(KeyCode::Tab);
+        harness.press(KeyCode::Down);
+        harness.press(KeyCode::Char(' '));
+        harness.press(KeyCode::Enter);
+
+        let ctx = &harness.ui.ctx;
+        for title in ["Dune", "Emma"] {
+            let path = format!("/library/{title}.pdf");
+            let mut tags = ctx.labels_by_path[&path].tags.clone();
+            tags.sort();
+            assert_eq!(tags, vec!["classic", "sf"], "{title}");
+        }
+        assert!(!ctx.labels_by_path.contains_key("/library/Kim.pdf"));
+        assert_eq!(
+            ctx.dirty_label_paths,
+            HashSet::from([
+                "/library/Dune.pdf".to_string(),
+                "/library/Emma.pdf".to_string()
+            ])
+        );
+        assert!(harness.ui.marked_paths.is_empty());
+
+        // Esc in the library drops the marks before anything else.
+        harness.press(KeyCode::Char(' '));
+        assert_eq!(harness.ui.marked_paths.len(), 1);
+        assert_eq!(harness.press(KeyCode::Esc), None);
+        assert!(harness.ui.marked_paths.is_empty());
+    }
+
+    #[test]
+    fn selected_book_labels_quick_filter_the_library() {
+        let mut ctx = library_context(&["Dune", "Emma", "Kim", "Nana"]);
+        for (title, collection, tags) in [
+            ("Dune", Some("Desert"), vec!["sf", "classic"]),
+            ("Emma", Some("Desert"), vec!["classic"]),
+            ("Kim", None, vec!["sf"]),
+            ("Nana", None, vec![]),
+        ] {
+            ctx.labels_by_path.insert(
+                format!("/library/{title}.pdf"),
+                BookLabels {
+                    collection: collection.map(str::to_string),
+                    tags: tags.into_iter().map(str::to_string).collect(),
+                    ..BookLabels::default()
+                },
+            );
+        }
+        let mut harness = Harness::new(ctx);
+
+        harness.press(KeyCode::Char('C'));
+        let screen = harness.screen();
+        assert!(screen.contains("collection:Desert"), "{screen}");
+        assert!(screen.contains("Library — 2/4 matches"), "{screen}");
+        harness.press(KeyCode::Char('C'));
+        assert_eq!(harness.ui.ctx.collection_filter, CollectionFilter::Any);
+
+        // Dune has two tags, so a picker asks which.
+        harness.press(KeyCode::Char('T'));
+        let screen = harness.screen();
+        assert!(screen.contains("Filter by a tag of Dune"), "{screen}");
+        assert!(screen.contains("[ ] classic"), "{screen}");
+        harness.press(KeyCode::Down);
+        harness.press(KeyCode::Enter);
+        assert_eq!(harness.ui.ctx.tag_filters, vec!["sf".to_string()]);
+        let screen = harness.screen();
+        assert!(screen.contains("tags(or): sf"), "{screen}");
+        assert!(screen.contains("Library — 2/4 matches"), "{screen}");
+        harness.press(KeyCode::Char('T'));
+        assert!(harness.ui.ctx.tag_filters.is_empty());
+        assert!(!harness.ui.book_tag_picker.open);
+
+        // A book with nothing to filter by says so.
+        for _ in 0..3 {
+            harness.press(KeyCode::Down);
+        }
+        harness.press(KeyCode::Char('C'));
+        assert!(harness.screen().contains("Nana is in no collection"));
+        harness.press(KeyCode::Char('T'));
+        assert!(harness.screen().contains("Nana has no tags"));
+        assert!(harness.ui.ctx.tag_filters.is_empty());
+    }
+
+    #[test]
+    fn small_terminals_get_a_guard_or_a_single_pane() {
+        let mut harness = Harness::library(&["Dune", "Emma", "Ulysses"]);
+        for (width, height) in [(59, 20), (60, 19), (20, 5)] {
+            harness.resize(width, height);
+            let screen = harness.screen();
+            assert!(screen.contains("terminal too small"), "{screen}");
+        }
+
+        harness.resize(60, 20);
+        let screen = harness.screen();
+        assert!(screen.contains("Library"), "{screen}");
+        assert!(!screen.contains("Details"), "{screen}");
+        assert!(screen.contains("i details"), "{screen}");
+        harness.press(KeyCode::Char('i'));
+        let screen = harness.screen();
+        assert!(screen.contains("Details"), "{screen}");
+        assert!(screen.contains("i list"), "{screen}");
+        harness.press(KeyCode::Char('i'));
+
+        // Popups keep their content readable instead of shrinking with the screen.
+        harness.press(KeyCode::Char('d'));
+        let screen = harness.screen();
+        assert!(
+            screen.contains("D also delete the file from disk"),
+            "{screen}"
+        );
+        harness.press(KeyCode::Esc);
+        harness.press(KeyCode::Char('/'));
+        let screen = harness.screen();
+        assert!(screen.contains("Collections"), "{screen}");
+        assert!(screen.contains("Any (3)"), "{screen}");
+        harness.press(KeyCode::Esc);
+
+        harness.resize(90, 24);
+        let screen = harness.screen();
+        assert!(
+            screen.contains("Library") && screen.contains("Details"),
+            "{screen}"
+        );
+        assert!(!screen.contains("i details"), "{screen}");
+    }
+
+    #[test]
+    fn renaming_a_tag_updates_books_and_filters() {
+        let mut ctx = library_context(&["Dune", "Emma"]);
+        ctx.known_tags = vec!["scifi".to_string()];
+        ctx.labels_by_path.insert(
+            "/library/Dune.pdf".to_string(),
+            BookLabels {
+                tags: vec!["scifi".to_string()],
+                ..BookLabels::default()
+            },
+        );
+        ctx.tag_filters = vec!["scifi".to_string()];
+        let mut harness = Harness::new(ctx);
+
+        harness.press(KeyCode::Char('c'));
+        harness.press(KeyCode::Tab);
+        harness.press(KeyCode::Char('r'));
+        assert!(harness.ui.label_catalog_input_panel.open);
+        assert!(harness.screen().contains("Rename tag"));
+        for _ in "scifi".chars() {
+            harness.press(KeyCode::Backspace);
+        }
+        harness.type_text("sf");
+        harness.press(KeyCode::Enter);
+
+        let ctx = &harness.ui.ctx;
+        assert!(!harness.ui.label_catalog_input_panel.open);
+        assert_eq!(ctx.known_tags, vec!["sf".to_string()]);
+        assert_eq!(ctx.labels_by_path["/library/Dune.pdf"].tags, vec!["sf"]);
+        assert_eq!(ctx.tag_filters, vec!["sf".to_string()]);
+        assert!(
+            ctx.dirty_label_catalog_ops
+                .contains(&LabelCatalogOp::Rename {
+                    kind: TagKind::Tag,
+                    from: "scifi".to_string(),
+                    to: "sf".to_string(),
+                })
+        );
+    }
+
+    #[test]
+    fn bookmark_changes_mark_the_book_dirty() {
+        let mut harness = Harness::library(&["Dune"]);
+        let path = "/library/Dune.pdf".to_string();
+        // Opening a book needs pdfium; the panel only needs to know which book is open.
+        harness.ui.reader.open = true;
+        harness.ui.reader.book_path = Some(path.clone());
+        harness.ui.reader.page = 4;
+
+        harness.press(KeyCode::Char('b'));
+        assert!(harness.ui.bookmarks_panel.open);
+        harness.press(KeyCode::Char('a'));
+        assert_eq!(harness.ui.ctx.bookmarks_by_path[&path][0].page, 5);
+        assert!(harness.ui.ctx.dirty_bookmark_paths.contains(&path));
+
+        // As after a save.
+        harness.ui.ctx.dirty_bookmark_paths.clear();
+        harness.press(KeyCode::Char('a'));
+        assert!(
+            harness.ui.ctx.dirty_bookmark_paths.is_empty(),
+            "the same page twice changes nothing"
+        );
+
+        harness.press(KeyCode::Char('d'));
+        assert!(harness.ui.ctx.bookmarks_by_path[&path].is_empty());
+        assert!(harness.ui.ctx.dirty_bookmark_paths.contains(&path));
+    }
+
+    #[test]
+    fn goto_slider_stays_in_bounds_with_more_labels_than_pages() {
+        let mut harness = Harness::library(&["Dune"]);
+        harness.ui.reader.open = true;
+        harness.ui.reader.book_path = Some("/library/Dune.pdf".to_string());
+        harness.ui.reader.total_pages = Some(3);
+        harness.ui.reader.page_labels = ["i", "ii", "1", "2", "3", "4"].map(String::from).to_vec();
+        // A page count that went stale under the open page.
+        harness.ui.reader.page = 5;
+
+        harness.press(KeyCode::Char('g'));
+        assert!(harness.ui.goto_panel.open);
+        harness.press_with(KeyCode::Char('u'), KeyModifiers::CONTROL);
+        // The page printed 4 is past the last page, so it names no page.
+        harness.type_text("4");
+        let screen = harness.screen();
+        assert!(screen.contains("Go to page (1..=3)"), "{screen}");
+        assert!(screen.contains("──|  │"), "{screen}");
+
+        harness.press_with(KeyCode::Char('u'), KeyModifiers::CONTROL);
+        harness.type_text("ii");
+        let screen = harness.screen();
+        assert!(screen.contains("───●───"), "{screen}");
+        assert!(screen.contains("→ pii (2)"), "{screen}");
+    }
+
+    #[test]
+    fn bookmark_labels_can_be_added_and_edited() {
+        let mut harness = Harness::library(&["Dune"]);
+        let path = "/library/Dune.pdf".to_string();
+        harness.ui.reader.open = true;
+        harness.ui.reader.book_path = Some(path.clone());
+        harness.ui.reader.page = 4;
+        let labels = |harness: &Harness| -> Vec<(u32, String)> {
+            harness.ui.ctx.bookmarks_by_path[&path]
+                .iter()
+                .map(|b| (b.page, b.label.clone()))
+                .collect()
+        };
+
+        harness.press(KeyCode::Char('b'));
+        harness.press(KeyCode::Char('A'));
+        assert!(harness.ui.bookmarks_panel.input_open);
+        harness.type_text("Arrakis");
+        harness.press(KeyCode::Enter);
+        assert!(!harness.ui.bookmarks_panel.input_open);
+        assert_eq!(labels(&harness), [(5, "Arrakis".to_string())]);
+        assert!(harness.ui.ctx.dirty_bookmark_paths.contains(&path));
+
+        // As after a save.
+        harness.ui.ctx.dirty_bookmark_paths.clear();
+        harness.press(KeyCode::Char('e'));
+        assert_eq!(harness.ui.bookmarks_panel.input.as_str(), "Arrakis");
+        harness.press_with(KeyCode::Char('u'), KeyModifiers::CONTROL);
+        harness.type_text("Arrakeen");
+        harness.press(KeyCode::Enter);
+        assert_eq!(labels(&harness), [(5, "Arrakeen".to_string())]);
+        assert!(harness.ui.ctx.dirty_bookmark_paths.contains(&path));
+    }
+
+    #[test]
+    fn page_flips_record_progress_and_flush_it_once_per_interval() {
+        let saved = Rc::new(RefCell::new(Vec::new()));
+        let mut harness = Harness::library(&["Dune"]);
+        let path = "/library/Dune.pdf".to_string();
+        let sink_saved = Rc::clone(&saved);
+        harness.ui.progress_sink = Some(Box::new(move |path: &str, progress: &BookProgress| {
+            sink_saved
+                .borrow_mut()
+                .push((path.to_string(), progress.last_page));
+            Ok(())
+        }));
+        harness.ui.reader.open = true;
+        harness.ui.reader.book_path = Some(path.clone());
+        harness.ui.reader.total_pages = Some(100);
+        harness.ui.last_progress_flush = Instant::now() - PROGRESS_FLUSH_INTERVAL;
+        // What the event loop does after each batch of keys.
+        let flip = |harness: &mut Harness| {
+            harness.press(KeyCode::Right);
+            harness.ui.record_reader_progress();
+            harness.ui.flush_progress_if_due();
+        };
+
+        flip(&mut harness);
+        assert_eq!(harness.ui.ctx.progress_by_path[&path].last_page, 2);
+        assert_eq!(*saved.borrow(), vec![(path.clone(), 2)]);
+        assert!(harness.ui.ctx.dirty_progress_paths.is_empty());
+
+        for page in [3, 4, 5] {
+            flip(&mut harness);
+            assert_eq!(harness.ui.ctx.progress_by_path[&path].last_page, page);
+            assert!(harness.ui.ctx.dirty_progress_paths.contains(&path));
+        }
+        assert_eq!(saved.borrow().len(), 1, "flips within the interval wait");
+
+        harness.ui.last_progress_flush = Instant::now() - PROGRESS_FLUSH_INTERVAL;
+        harness.ui.flush_progress_if_due();
+        assert_eq!(*saved.borrow(), vec![(path.clone(), 2), (path.clone(), 5)]);
+        assert!(harness.ui.ctx.dirty_progress_paths.is_empty());
+    }
+
+    #[test]
+    fn a_book_keeps_its_reader_modes_without_changing_the_global_ones() {
+        let mut harness = Harness::library(&["Dune"]);
+        let open_dune = |ui: &mut Ui| {
+            let Ui {
+                reader,
+                ctx,
+                engine,
+                image_picker,
+                ..
+            } = ui;
+            let book = ctx.books[0].clone();
+            reader.open_book(&book, ctx, engine, image_picker);
+        };
+        open_dune(&mut harness.ui);
+        harness.ui.reader.cycle_text_mode();
+        assert_eq!(harness.ui.reader.text_mode, ReaderTextMode::Raw);
+        assert_eq!(
+            harness.ui.ctx.settings.reader_text_mode,
+            ReaderTextMode::Reflow
+        );
+
+        harness.ui.close_reader();
+        assert_eq!(
+            harness.ui.ctx.settings.reader_text_mode,
+            ReaderTextMode::Reflow
+        );
+        assert_eq!(
+            harness.ui.ctx.reader_state_by_path["/library/Dune.pdf"].reader_text_mode,
+            ReaderTextMode::Raw
+        );
+
+        open_dune(&mut harness.ui);
+        assert_eq!(harness.ui.reader.text_mode, ReaderTextMode::Raw);
+    }
+
+    #[test]
+    fn removing_a_book_selects_the_next_one() {
+        let mut harness = Harness::library(&["Dune", "Emma", "Ulysses"]);
+        harness.press(KeyCode::Down);
+        harness.press(KeyCode::Char('d'));
+        let screen = harness.screen();
+        assert!(screen.contains("Delete book"), "{screen}");
+        harness.press(KeyCode::Char('r'));
+
+        let titles = |harness: &Harness| -> Vec<String> {
+            harness
+                .ui
+                .ctx
+                .books
+                .iter()
+                .map(|b| b.title.clone())
+                .collect()
+        };
+        assert!(!harness.ui.delete_panel.open);
+        assert_eq!(titles(&harness), ["Dune", "Ulysses"]);
+        assert_eq!(
+            harness.ui.ctx.books[harness.ui.ctx.selected].title,
+            "Ulysses"
+        );
+        assert_eq!(
+            harness.ui.ctx.dirty_book_path_ops,
+            vec![BookPathOp::Remove {
+                path: "/library/Emma.pdf".to_string()
+            }]
+        );
+        assert_eq!(
+            harness.ui.library_notice.as_deref(),
+            Some("removed Emma from library")
+        );
+
+        // The last book hands the selection back to the one before it.
+        harness.press(KeyCode::Char('d'));
+        harness.press(KeyCode::Char('r'));
+        assert_eq!(titles(&harness), ["Dune"]);
+        assert_eq!(harness.ui.ctx.books[harness.ui.ctx.selected].title, "Dune");
+
+        harness.press(KeyCode::Char('d'));
+        harness.press(KeyCode::Esc);
+        assert!(!harness.ui.delete_panel.open);
+        assert_eq!(titles(&harness), ["Dune"]);
+    }
+
+    #[test]
+    fn deleting_a_book_removes_its_file() {
+        let dir = std::env::temp_dir().join(format!("bookshelf-delete-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("Folder.pdf")).expect("test dir");
+        let file = dir.join("Dune.pdf");
+        std::fs::write(&file, b"%PDF").expect("test file");
+        let mut ctx = library_context(&["Dune", "Emma", "Folder"]);
+        ctx.books[0].path = file.to_string_lossy().to_string();
+        ctx.books[2].path = dir.join("Folder.pdf").to_string_lossy().to_string();
+        let mut harness = Harness::new(ctx);
+
+        harness.press(KeyCode::Char('d'));
+        harness.press(KeyCode::Char('D'));
+        assert!(!file.exists());
+        assert_eq!(harness.ui.ctx.books.len(), 2);
+        assert_eq!(harness.ui.ctx.books[harness.ui.ctx.selected].title, "Emma");
+        assert_eq!(harness.ui.library_notice.as_deref(), Some("deleted Dune"));
+
+        // A file already gone still takes its entry with it.
+        harness.press(KeyCode::Char('d'));
+        harness.press(KeyCode::Char('D'));
+        assert_eq!(harness.ui.library_notice.as_deref(), Some("deleted Emma"));
+        assert_eq!(
+            harness.ui.ctx.dirty_book_path_ops,
+            vec![
+                BookPathOp::Remove {
+                    path: file.to_string_lossy().to_string()
+                },
+                BookPathOp::Remove {
+                    path: "/library/Emma.pdf".to_string()
+                },
+            ]
+        );
+
+        // A failed delete keeps the book and says why.
+        harness.press(KeyCode::Char('d'));
+        harness.press(KeyCode::Char('D'));
+        assert!(harness.ui.delete_panel.open);
+        assert!(
+            harness
+                .ui
+                .delete_panel
+                .error
+                .as_deref()
+                .is_some_and(|err| err.starts_with("Could not delete")),
+            "{:?}",
+            harness.ui.delete_panel.error
+        );
+        assert_eq!(harness.ui.ctx.books.len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn delete_panel_keys_choose_between_entry_and_file() {
+        let dir = std::env::temp_dir().join(format!("bookshelf-remove-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("test dir");
+        let mut ctx = library_context(&["Dune", "Emma"]);
+        for book in &mut ctx.books {
+            let file = dir.join(format!("{}.pdf", book.title));
+            std::fs::write(&file, b"%PDF").expect("test file");
+            book.path = file.to_string_lossy().to_string();
+        }
+        let dune = dir.join("Dune.pdf");
+        let emma = dir.join("Emma.pdf");
+        let mut harness = Harness::new(ctx);
+
+        // Esc and `n` close the panel without touching the entry or the file.
+        for key in [KeyCode::Esc, KeyCode::Char('n')] {
+            harness.press(KeyCode::Char('d'));
+            assert!(harness.ui.delete_panel.open);
+            harness.press(key);
+            assert!(!harness.ui.delete_panel.open);
+            assert_eq!(harness.ui.ctx.books.len(), 2);
+            assert!(dune.exists());
+        }
+        assert!(harness.ui.ctx.dirty_book_path_ops.is_empty());
+
+        // `r` drops the entry and leaves the file on disk.
+        harness.press(KeyCode::Char('d'));
+        harness.press(KeyCode::Char('r'));
+        assert!(dune.exists());
+        assert_eq!(harness.ui.ctx.books.len(), 1);
+        assert_eq!(harness.ui.ctx.books[0].title, "Emma");
+
+        // `D` drops both.
+        harness.press(KeyCode::Char('d'));
+        harness.press(KeyCode::Char('D'));
+        assert!(!emma.exists());
+        assert!(harness.ui.ctx.books.is_empty());
+        assert_eq!(
+            harness.ui.ctx.dirty_book_path_ops,
+            vec![
+                BookPathOp::Remove {
+                    path: dune.to_string_lossy().to_string()
+                },
+                BookPathOp::Remove {
+                    path: emma.to_string_lossy().to_string()
+                },
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn toc_entries_become_bookmarks_in_one_change() {
+        let mut harness = Harness::library(&["Dune"]);
+        let path = "/library/Dune.pdf".to_string();
+        harness.ui.ctx.bookmarks_by_path.insert(
+            path.clone(),
+            vec![Bookmark {
+                page: 30,
+                label: "Book Two".to_string(),
+                view: None,
+            }],
+        );
+        harness.ui.reader.open = true;
+        harness.ui.reader.book_path = Some(path.clone());
+        harness.ui.reader.book_title = Some("Dune".to_string());
+        harness.ui.reader.total_pages = Some(100);
+        let entry = |title: &str, page: u32, depth: usize| TocItem {
+            title: title.to_string(),
+            page: Some(page),
+            depth,
+        };
+        harness.ui.reader.toc = vec![
+            entry("Book One", 2, 0),
+            entry("Chapter 1", 3, 1),
+            entry("Book Two", 30, 0),
+            entry("Chapter 9", 31, 1),
+            entry("Appendix", 140, 0),
+        ];
+
+        harness.press(KeyCode::Char('t'));
+        harness.press(KeyCode::Char('B'));
+        assert!(
+            harness
+                .screen()
+                .contains("Bookmark entries down to level 1?")
+        );
+        harness.press(KeyCode::Char('2'));
+        harness.press(KeyCode::Enter);
+        assert!(!harness.ui.toc_panel.open);
+        assert_eq!(
+            harness.ui.reader.notice.as_deref(),
+            Some("added 3 bookmarks")
+        );
+        let bookmarks: Vec<(u32, &str)> = harness.ui.ctx.bookmarks_by_path[&path]
+            .iter()
+            .map(|b| (b.page, b.label.as_str()))
+            .collect();
+        assert_eq!(
+            bookmarks,
+            vec![
+                (2, "Book One"),
+                (3, "Chapter 1"),
+                (30, "Book Two"),
+                (31, "Chapter 9")
+            ]
+        );
+        assert!(harness.ui.ctx.dirty_bookmark_paths.contains(&path));
+
+        // With a filter typed, `B` is part of it.
+        harness.press(KeyCode::Char('t'));
+        harness.type_text("aB");
+        assert_eq!(harness.ui.toc_panel.query.as_str(), "aB");
+        assert_eq!(harness.ui.toc_panel.bookmark_depth, None);
+    }
+
+    #[test]
+    fn bookmarks_return_to_where_the_page_was_scrolled() {
+        let mut harness = Harness::library(&["Dune"]);
+        let path = "/library/Dune.pdf".to_string();
+        harness.ui.ctx.settings.reader_mode = ReaderMode::Text;
+        harness.ui.ctx.bookmarks_by_path.insert(
+            path.clone(),
+            vec![Bookmark {
+                page: 2,
+                label: "before views".to_string(),
+                view: None,
+            }],
+        );
+        harness.ui.reader.open = true;
+        harness.ui.reader.book_path = Some(path.clone());
+        harness.ui.reader.page = 4;
+        harness.ui.reader.scroll = 12;
+
+        harness.press(KeyCode::Char('b'));
+        harness.press(KeyCode::Char('a'));
+        assert_eq!(
+            harness.ui.ctx.bookmarks_by_path[&path][1].view,
+            Some(BookmarkView::Text { scroll: 12 })
+        );
+        let screen = harness.screen();
+        assert!(screen.contains("Page 5  (line 13)"), "{screen}");
+        assert!(screen.contains("Page 2 — before views "), "{screen}");
+
+        harness.ui.reader.page = 0;
+        harness.ui.reader.scroll = 0;
+        harness.press(KeyCode::Enter);
+        assert_eq!((harness.ui.reader.page, harness.ui.reader.scroll), (4, 12));
+
+        harness.ui.reader.scroll = 7;
+        harness.press(KeyCode::Char('b'));
+        harness.press(KeyCode::Up);
+        harness.press(KeyCode::Enter);
+        assert_eq!((harness.ui.reader.page, harness.ui.reader.scroll), (1, 0));
+    }
+
+    #[test]
+    fn notes_search_opens_the_matching_page_of_any_book() {
+        let dir = std::env::temp_dir().join(format!("bookshelf-notes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let file = dir.join("Loose Paper.pdf");
+        std::fs::write(&file, b"").expect("temp book");
+        let loose = bookshelf_core::encode_path(&file);
+
+        let mut ctx = library_context(&["Dune", "Emma"]);
+        // Counted already, so the empty file is not parsed as a PDF.
+        ctx.page_counts_by_path.insert(
+            loose.clone(),
+            PageCount {
+                pages: 20,
+                size_bytes: 0,
+                modified_secs: 1,
+                kind: None,
+            },
+        );
+        ctx.notes_by_path.insert(
+            "/library/Dune.pdf".to_string(),
+            vec![Note {
+                page: 12,
+                body: "Compare with the Raft paper".to_string(),
+            }],
+        );
+        ctx.notes_by_path.insert(
+            loose.clone(),
+            vec![Note {
+                page: 3,
+                body: "raft, again".to_string(),
+            }],
+        );
+        ctx.bookmarks_by_path.insert(
+            "/library/Emma.pdf".to_string(),
+            vec![Bookmark {
+                page: 7,
+                label: "No match here".to_string(),
+                view: None,
+            }],
+        );
+        let mut harness = Harness::new(ctx);
+
+        harness.press_with(KeyCode::Char('N'), KeyModifiers::SHIFT);
+        harness.type_text("RAFT");
+        let screen = harness.screen();
+        assert!(screen.contains("2 matches"), "{screen}");
+        assert!(
+            screen.contains("Dune — p12 — Compare with the Raft paper"),
+            "{screen}"
+        );
+        assert!(
+            screen.contains("Loose Paper — p3 — raft, again"),
+            "{screen}"
+        );
+
+        // Dune's file is not on disk.
+        harness.press(KeyCode::Enter);
+        assert!(harness.screen().contains("File is missing"));
+        assert!(!harness.ui.reader.open);
+
+        harness.press(KeyCode::Down);
+        harness.press(KeyCode::Enter);
+        let ui = &harness.ui;
+        assert!(!ui.notes_search_panel.open);
+        assert!(ui.reader.open);
+        assert_eq!(ui.reader.book_path.as_deref(), Some(loose.as_str()));
+        assert_eq!(ui.reader.page, 2);
+        assert_eq!(ui.reader.notice.as_deref(), Some("note on page 3"));
+        assert_eq!(ui.ctx.recent_paths.first(), Some(&loose));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn settings_panel_exports_and_imports_settings_files() {
+        let exported = Rc::new(RefCell::new(Vec::new()));
+        let mut harness = Harness::library(&["Dune"]);
+        let sink_exported = Rc::clone(&exported);
+        harness.ui.settings_export_sink =
+            Some(Box::new(move |file: &Path, settings: &Settings| {
+                sink_exported
+                    .borrow_mut()
+                    .push((file.to_path_buf(), settings.theme));
+                Ok(())
+            }));
+        harness.ui.settings_import_sink = Some(Box::new(|file: &Path| {
+            anyhow::ensure!(file == Path::new("home.json"), "no such file");
+            let mut settings = Settings {
+                theme: Theme::Light,
+                ..Settings::default()
+            };
+            settings
+                .key_bindings
+                .insert("search_notes".to_string(), vec!["n".to_string()]);
+            Ok(SettingsImport {
+                settings,
+                rejected: vec!["sort_mode".to_string()],
+            })
+        }));
+
+        harness.press(KeyCode::Char('s'));
+        harness.ui.settings_panel.selected = SETTINGS_MENU_EXPORT_SETTINGS;
+        harness.press(KeyCode::Enter);
+        let screen = harness.screen();
+        assert!(
+            screen.contains("Export settings to file: bookshelf-settings.json"),
+            "{screen}"
+        );
+        harness.press_with(KeyCode::Char('u'), KeyModifiers::CONTROL);
+        harness.type_text("work.json");
+        harness.press(KeyCode::Enter);
+        assert_eq!(
+            exported.borrow().as_slice(),
+            &[(PathBuf::from("work.json"), Theme::Dark)]
+        );
+        assert!(harness.screen().contains("exported settings to work.json"));
+
+        harness.press(KeyCode::Char('s'));
+        harness.ui.settings_panel.selected = SETTINGS_MENU_IMPORT_SETTINGS;
+        harness.press(KeyCode::Enter);
+        harness.press_with(KeyCode::Char('u'), KeyModifiers::CONTROL);
+        harness.type_text("home.json");
+        harness.press(KeyCode::Enter);
+        assert_eq!(harness.ui.ctx.settings.theme, Theme::Light);
+        assert_eq!(
+            harness.ui.library_notice.as_deref(),
+            Some("imported settings from home.json; defaults kept for sort_mode")
+        );
+        // The imported key bindings are in effect.
+        harness.press(KeyCode::Char('n'));
+        assert!(harness.ui.notes_search_panel.open);
+    }
+
+    #[test]
+    fn database_backups_restore_after_a_confirmation() {
+        let restored = Rc::new(RefCell::new(Vec::new()));
+        let mut harness = Harness::library(&["Dune"]);
+        harness.ui.backup_sink = Some(Box::new(|_ctx: &mut AppContext| {
+            Ok(PathBuf::from("/db/bookshelf-backup-20261016-101500.db"))
+        }));
+        harness.ui.backup_list_sink = Some(Box::new(|| {
+            Ok(vec![
+                PathBuf::from("/db/bookshelf-backup-20261016-101500.db"),
+                PathBuf::from("/db/bookshelf-backup-20261015-090000.db"),
+            ])
+        }));
+        let sink_restored = Rc::clone(&restored);
+        harness.ui.restore_sink = Some(Box::new(move |backup: &Path| {
+            sink_restored.borrow_mut().push(backup.to_path_buf());
+            Ok(())
+        }));
+
+        harness.press(KeyCode::Char('s'));
+        harness.ui.settings_panel.selected = SETTINGS_MENU_BACKUP;
+        harness.press(KeyCode::Enter);
+        assert_eq!(
+            harness.ui.library_notice.as_deref(),
+            Some("backed up database to bookshelf-backup-20261016-101500.db")
+        );
+
+        harness.press(KeyCode::Char('s'));
+        harness.ui.settings_panel.selected = SETTINGS_MENU_RESTORE;
+        harness.press(KeyCode::Enter);
+        assert!(harness.screen().contains("Restore from backup (2)"));
+        harness.press(KeyCode::Down);
+        assert_eq!(harness.press(KeyCode::Enter), None);
+        let screen = harness.screen();
+        assert!(
+            screen.contains("Replace the library with bookshelf-backup-20261015-090000.db?"),
+            "{screen}"
+        );
+        assert_eq!(harness.press(KeyCode::Char('n')), None);
+        assert!(restored.borrow().is_empty());
+
+        harness.press(KeyCode::Enter);
+        assert_eq!(harness.press(KeyCode::Char('y')), Some(UiExit::Reload));
+        assert_eq!(
+            restored.borrow().as_slice(),
+            &[PathBuf::from("/db/bookshelf-backup-20261015-090000.db")]
+        );
+        assert!(!harness.ui.backups_panel.open);
+    }
+
+    #[test]
+    fn pruning_rescan_removes_books_it_did_not_find() {
+        let root = std::env::temp_dir().join(format!("bookshelf-prune-{}", std::process::id()));
+        std::fs::create_dir_all(&root).expect("scan root");
+        let mut harness = Harness::library(&["Dune", "Emma"]);
+        let stored = harness.ui.ctx.books.clone();
+        harness.ui.library_sink = Some(Box::new(move |found: &[Book], complete: bool| {
+            Ok(merge_scan(&stored, found, complete))
+        }));
+
+        harness
+            .ui
+            .scan_panel
+            .open(&[LibraryRoot::new(root.to_string_lossy().to_string())]);
+        harness.press(KeyCode::Char('p'));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while harness.ui.library_scan.is_some() && Instant::now() < deadline {
+            harness.ui.poll_library_scan();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let ctx = &harness.ui.ctx;
+        assert!(ctx.books.is_empty());
+        assert!(ctx.dirty_book_path_ops.contains(&BookPathOp::Remove {
+            path: "/library/Emma.pdf".to_string()
+        }));
+        assert_eq!(
+            harness.ui.library_notice.as_deref(),
+            Some("scan done: 0 added, 0 updated, 2 missing removed, 0 unchanged")
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn text_zoom_steps_line_spacing_and_keeps_the_place() {
+        let mut harness = Harness::library(&["Dune"]);
+        let ui = &mut harness.ui;
+        ui.reader.scroll = 10;
+        ui.set_line_spacing(Some(true));
+        assert_eq!(ui.ctx.settings.reader_line_spacing, 15);
+        assert_eq!(ui.reader.scroll, 15);
+        assert_eq!(ui.reader.notice.as_deref(), Some("line spacing: 1.5"));
+        ui.set_line_spacing(Some(true));
+        ui.set_line_spacing(Some(true));
+        assert_eq!(ui.ctx.settings.reader_line_spacing, 20);
+        assert_eq!(ui.reader.scroll, 20);
+        ui.set_line_spacing(None);
+        assert_eq!(ui.ctx.settings.reader_line_spacing, 10);
+        assert_eq!(ui.reader.scroll, 10);
+
+        ui.reader.text_mode = ReaderTextMode::Raw;
+        ui.set_line_spacing(Some(true));
+        assert_eq!(ui.ctx.settings.reader_line_spacing, 10);
+
+        // Page turns keep two lines of text on screen, which are four rows double spaced.
+        let mut reader = ReaderPanel {
+            current_lines: Some(reader_text::plain_lines(&"line\n".repeat(25))),
+            text_height: 10,
+            line_spacing: 20,
+            ..ReaderPanel::default()
+        };
+        assert!(reader.scroll_text_page(true));
+        assert_eq!(reader.scroll, 6);
+    }
+
+    #[test]
+    fn saved_text_positions_hold_at_another_line_spacing() {
+        let mut harness = Harness::library(&["Dune"]);
+        let ui = &mut harness.ui;
+        let path = ui.ctx.books[0].path.clone();
+        let open = |ui: &mut Ui| {
+            let book = ui.ctx.books[0].clone();
+            ui.reader
+                .open_book(&book, &mut ui.ctx, &ui.engine, &mut ui.image_picker);
+        };
+
+        ui.ctx.settings.reader_line_spacing = 20;
+        open(ui);
+        ui.reader.scroll = 20;
+        assert_eq!(
+            ui.reader.bookmark_view(ReaderMode::Text),
+            Some(BookmarkView::Text { scroll: 10 })
+        );
+        ui.close_reader();
+        assert_eq!(ui.ctx.progress_by_path[&path].scroll, Some(10));
+
+        ui.ctx.settings.reader_line_spacing = 10;
+        open(ui);
+        assert_eq!(ui.reader.scroll, 10);
+        ui.close_reader();
+
+        ui.ctx.settings.reader_line_spacing = 15;
+        open(ui);
+        assert_eq!(ui.reader.scroll, 15);
+        let bookmark = Bookmark {
+            page: 1,
+            label: String::new(),
+            view: Some(BookmarkView::Text { scroll: 4 }),
+        };
+        ui.reader.jump_to_bookmark(&bookmark, ReaderMode::Text);
+        assert_eq!(ui.reader.scroll, 6);
+    }
+
+    #[test]
+    fn quitting_after_another_instance_saved_asks_whose_settings_win() {
+        let mut harness = Harness::library(&["Dune"]);
+        harness.ui.conflict_check = Some(Box::new(|ctx: &AppContext| Ok(ctx.db_generation == 0)));
+        assert_eq!(harness.press(KeyCode::Esc), None);
+        assert!(harness.ui.conflict_prompt);
+        // Any other key stays in the app.
+        assert_eq!(harness.press(KeyCode::Down), None);
+        assert!(!harness.ui.conflict_prompt);
+
+        assert_eq!(harness.press(KeyCode::Esc), None);
+        assert_eq!(harness.press(KeyCode::Char('n')), Some(UiExit::Quit));
+        assert!(harness.ui.ctx.keep_stored_settings);
+
+        // Without a conflict, quit is immediate.
+        harness.ui.ctx.db_generation = 1;
+        assert_eq!(harness.press(KeyCode::Esc), Some(UiExit::Quit));
+    }
+
+    #[test]
+    fn resumed_book_closes_to_the_library() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("bookshelf-resume-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let file = dir.join("last.pdf");
+        std::fs::write(&file, b"")?;
+        let path = bookshelf_core::encode_path(&file);
+        let mut books = library_context(&["other", "last"]).books;
+        books[0].last_opened = Some(1);
+        books[1].path = path.clone();
+        books[1].last_opened = Some(2);
+        let settings = Settings {
+            watch_library: false,
+            resume_last_book: true,
+            ..Settings::default()
+        };
+        let mut ctx = AppContext::new(settings).with_library(String::new(), books);
+        let mut harness = Harness::new(ctx.clone());
+        let reopened = harness.ui.reader.book_path.clone();
+        let closed = harness.press(KeyCode::Esc);
+        ctx.settings.resume_last_book = false;
+        let disabled = Harness::new(ctx);
+        std::fs::remove_dir_all(&dir)?;
+
+        assert_eq!(reopened, Some(path));
+        assert_eq!(harness.ui.ctx.selected, 1);
+        assert_eq!(closed, None);
+        assert!(!harness.ui.reader.open);
+        assert!(!disabled.ui.reader.open);
+        Ok(())
+    }
+
+    #[test]
+    fn series_assigned_in_the_panel_filter_in_number_order() {
+        let mut harness = Harness::library(&["Children", "Dune", "Messiah"]);
+        for (book, series) in [(2, "Dune #2"), (0, "dune #3"), (1, "Dune #1")] {
+            harness.ui.ctx.selected = book;
+            harness.press(KeyCode::Char('l'));
+            // Collections -> Tags -> Author -> Series.
+            for _ in 0..3 {
+                harness.press(KeyCode::Tab);
+            }
+            harness.press(KeyCode::Char('/'));
+            harness.type_text(series);
+            harness.press(KeyCode::Enter);
+            harness.press(KeyCode::Enter);
+        }
+        assert_eq!(harness.ui.ctx.known_series, vec!["Dune".to_string()]);
+        assert_eq!(
+            harness.ui.ctx.labels_by_path["/library/Children.pdf"].series,
+            Some(("Dune".to_string(), Some(3)))
+        );
+
+        harness.ui.ctx.selected = 1;
+        harness.press(KeyCode::Char('l'));
+        for _ in 0..2 {
+            harness.press(KeyCode::Tab);
+        }
+        harness.press(KeyCode::Char('/'));
+        harness.type_text("Frank Herbert");
+        harness.press(KeyCode::Enter);
+        harness.press(KeyCode::Enter);
+        let book = &harness.ui.ctx.books[1];
+        assert_eq!(
+            book_author(book, &harness.ui.ctx.labels_by_path[&book.path]),
+            Some("Frank Herbert")
+        );
+
+        harness.press(KeyCode::Char('/'));
+        for _ in 0..4 {
+            harness.press(KeyCode::Tab);
+        }
+        harness.press(KeyCode::Char(' '));
+        assert_eq!(harness.ui.ctx.series_filter.as_deref(), Some("Dune"));
+        let titles: Vec<&str> = harness
+            .ui
+            .visible_indices()
+            .into_iter()
+            .map(|idx| harness.ui.ctx.books[idx].title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["Dune", "Messiah", "Children"]);
+    }
+
+    #[test]
+    fn ignored_keys_draw_no_frames() {
+        let mut harness = Harness::library(&["Dune", "Emma"]);
+        let now = Instant::now();
+        harness.ui.redraw.drew();
+
+        // Unmapped keys and Up on the first book change nothing on screen.
+        for code in [KeyCode::Up, KeyCode::F(12), KeyCode::Char('§'), KeyCode::Up] {
+            harness.press(code);
+            assert!(!harness.ui.redraw.due(now), "{code:?} asked for a frame");
+        }
+
+        harness.press(KeyCode::Down);
+        assert!(harness.ui.redraw.due(now));
+        harness.ui.redraw.drew();
+        harness.press(KeyCode::Down);
+        assert!(!harness.ui.redraw.due(now));
+    }
+
+    #[test]
+    fn mouse_selects_library_rows_and_scrolls_the_selection() {
+        let mut harness = Harness::library(&["Dune", "Emma", "Kim"]);
+        // Drawing lays out the rows the mouse can hit.
+        let screen = harness.screen();
+        let (row, column) = screen
+            .lines()
+            .enumerate()
+            .find_map(|(row, line)| {
+                let at = line.find("Kim")?;
+                Some((row as u16, line[..at].chars().count() as u16))
+            })
+            .expect("Kim is listed");
+        let left = MouseEventKind::Down(MouseButton::Left);
+        let now = Instant::now();
+        harness.ui.redraw.drew();
+
+        harness.mouse(MouseEventKind::Moved, column, row);
+        harness.mouse(left, 0, row);
+        assert!(
+            !harness.ui.redraw.due(now),
+            "clicks outside the rows draw nothing"
+        );
+
+        harness.mouse(left, column, row);
+        assert_eq!(harness.ui.ctx.books[harness.ui.ctx.selected].title, "Kim");
+        assert!(harness.ui.redraw.due(now));
+
+        harness.mouse(MouseEventKind::ScrollUp, column, row);
+        assert_eq!(harness.ui.ctx.books[harness.ui.ctx.selected].title, "Emma");
+
+        // A double click opens the book; this one has no file behind it.
+        harness.mouse(left, column, row - 1);
+        harness.mouse(left, column, row - 1);
+        assert!(!harness.ui.reader.open);
+        assert!(
+            harness
+                .ui
+                .library_notice
+                .as_deref()
+                .is_some_and(|notice| notice.starts_with("file is missing"))
+        );
+    }
+
+    #[test]
+    fn duplicates_are_found_in_the_background_then_shown() {
+        let dir = std::env::temp_dir().join(format!("bookshelf-dupes-ui-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("test dir");
+        let mut ctx = library_context(&["Dune", "Dune copy", "Emma"]);
+        for (book, body) in ctx.books.iter_mut().zip(["dune", "dune", "emma"]) {
+            let file = dir.join(format!("{}.pdf", book.title));
+            std::fs::write(&file, body).expect("test file");
+            book.path = file.to_string_lossy().to_string();
+        }
+        let mut harness = Harness::new(ctx);
+
+        harness.press(KeyCode::Char('s'));
+        harness.ui.settings_panel.selected = SETTINGS_MENU_DUPLICATES;
+        harness.press(KeyCode::Enter);
+        let screen = harness.screen();
+        assert!(
+            screen.contains("finding duplicates… 0/3 books (Esc cancel)"),
+            "{screen}"
+        );
+        assert!(!harness.ui.duplicates_panel.open);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while harness.ui.duplicate_scan.is_some() && Instant::now() < deadline {
+            harness.ui.poll_duplicate_scan();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(harness.ui.duplicates_panel.open);
+        assert_eq!(harness.ui.duplicates_panel.groups.len(), 1);
+        assert_eq!(harness.ui.duplicates_panel.groups[0].len(), 2);
+        // The pass's fingerprints are kept for the next one.
+        assert_eq!(harness.ui.ctx.dirty_fingerprint_paths.len(), 3);
+    }
+}
@@ -1,3 +1,4 @@
+use bookshelf_core::{ImageViewport, KittyImageQuality};
 use ratatui_image::picker::{Capability, Picker, ProtocolType};
 
 fn term_is_xterm_kitty() -> bool {
@@ -6,6 +7,19 @@ fn term_is_xterm_kitty() -> bool {
         .is_some_and(|term| term.trim().starts_with("xterm-kitty"))
 }
 
+/// Terminals known to speak sixel but not kitty graphics. They only answer the capability
+/// query, so these hints decide whether to ask.
+fn sixel_hinted_env() -> bool {
+    let term = std::env::var("TERM").unwrap_or_default();
+    let term = term.trim();
+    term.starts_with("foot")
+        || term.starts_with("mlterm")
+        || term.contains("sixel")
+        || std::env::var("TERM_PROGRAM")
+            .ok()
+            .is_some_and(|program| program.trim().eq_ignore_ascii_case("wezterm"))
+}
+
 pub(crate) fn in_iterm_env() -> bool {
     std::env::var("ITERM_SESSION_ID")
         .ok()
@@ -49,11 +63,15 @@ pub(crate) fn should_query_stdio() -> bool {
         return true;
     }
 
-    if in_iterm_env() {
+    if in_iterm_env() || sixel_hinted_env() {
         return true;
     }
 
     // In tmux, we need to query to detect the outer terminal protocol.
+    in_tmux()
+}
+
+pub(crate) fn in_tmux() -> bool {
     std::env::var_os("TMUX").is_some()
 }
 
@@ -62,6 +80,9 @@ pub(crate) fn stdio_query_timeout() -> std::time::Duration {
     if in_kitty_env() || term_is_xterm_kitty() || in_iterm_env() {
         return std::time::Duration::from_millis(1500);
     }
+    if sixel_hinted_env() {
+        return std::time::Duration::from_millis(1000);
+    }
 
     // If we're in tmux, query quickly; if passthrough isn't enabled/supported, don't stall startup.
     if std::env::var_os("TMUX").is_some() {
@@ -86,15 +107,50 @@ pub(crate) fn kitty_supported(picker: &Picker) -> bool {
         .any(|cap| matches!(cap, Capability::Kitty))
 }
 
-pub(crate) fn prefer_kitty_if_supported(picker: &mut Picker) -> bool {
+/// Whether the terminal answered the capability query with sixel support.
+pub(crate) fn sixel_supported(picker: &Picker) -> bool {
+    picker.protocol_type() == ProtocolType::Sixel
+        || picker
+            .capabilities()
+            .iter()
+            .any(|cap| matches!(cap, Capability::Sixel))
+}
+
+/// Picks kitty graphics when available, else sixel. Returns `false` when neither is.
+pub(crate) fn prefer_graphics_protocol(picker: &mut Picker) -> bool {
     if in_iterm_env() {
         return false;
     }
-    if !kitty_supported(picker) {
-        return false;
+    if kitty_supported(picker) {
+        picker.set_protocol_type(ProtocolType::Kitty);
+        return true;
+    }
+    if sixel_supported(picker) {
+        picker.set_protocol_type(ProtocolType::Sixel);
+        return true;
+    }
+    false
+}
+
+/// Transmit caps in remote mode, below those of every quality.
+const REMOTE_MAX_TRANSMIT_PIXELS: u64 = 300_000;
+const REMOTE_MAX_SIXEL_PIXELS: u64 = 120_000;
+
+/// Largest image, in pixels, sent to the terminal per frame. Sixel payloads are palette-encoded
+/// text and grow much faster than kitty's, so they get a tighter budget. `remote` clamps both
+/// hard, whatever the quality.
+pub(crate) fn max_transmit_pixels(
+    picker: &Picker,
+    quality: KittyImageQuality,
+    viewport: Option<ImageViewport>,
+    remote: bool,
+) -> u64 {
+    match (picker.protocol_type(), remote) {
+        (ProtocolType::Sixel, false) => quality.max_sixel_pixels(viewport),
+        (ProtocolType::Sixel, true) => REMOTE_MAX_SIXEL_PIXELS,
+        (_, false) => quality.max_transmit_pixels(viewport),
+        (_, true) => REMOTE_MAX_TRANSMIT_PIXELS,
     }
-    picker.set_protocol_type(ProtocolType::Kitty);
-    true
 }
 
 pub(crate) fn image_supported(picker: &Picker) -> bool {
@@ -178,11 +234,60 @@ mod tests {
     fn prefer_kitty_sets_picker_protocol() {
         with_env_var("KITTY_WINDOW_ID", Option::<&str>::None, || {
             let mut picker = Picker::halfblocks();
-            picker.set_protocol_type(ProtocolType::Kitty);
-            assert!(!prefer_kitty_if_supported(&mut picker));
+            assert!(!prefer_graphics_protocol(&mut picker));
+            assert_eq!(picker.protocol_type(), ProtocolType::Halfblocks);
         });
     }
 
+    #[test]
+    fn sixel_pickers_keep_sixel_with_a_tighter_budget() {
+        with_env_vars(
+            &[
+                ("KITTY_WINDOW_ID", None),
+                ("ITERM_SESSION_ID", None),
+                ("TERM_PROGRAM", None),
+                ("LC_TERMINAL", None),
+            ],
+            || {
+                let mut picker = Picker::halfblocks();
+                picker.set_protocol_type(ProtocolType::Sixel);
+                assert!(prefer_graphics_protocol(&mut picker));
+                assert_eq!(protocol_label(&picker), "sixel");
+                assert!(image_supported(&picker));
+                let quality = KittyImageQuality::Balanced;
+                assert!(
+                    max_transmit_pixels(&picker, quality, None, false)
+                        < quality.max_transmit_pixels(None)
+                );
+                assert!(
+                    max_transmit_pixels(&picker, KittyImageQuality::Fast, None, true)
+                        < KittyImageQuality::Fast.max_sixel_pixels(None)
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn should_query_stdio_true_in_sixel_terminals() {
+        for (term, program) in [
+            ("foot", None),
+            ("mlterm", None),
+            ("xterm-256color", Some("WezTerm")),
+        ] {
+            with_env_vars(
+                &[
+                    ("KITTY_WINDOW_ID", None),
+                    ("TERM", Some(term)),
+                    ("TMUX", None),
+                    ("ITERM_SESSION_ID", None),
+                    ("TERM_PROGRAM", program),
+                    ("LC_TERMINAL", None),
+                ],
+                || assert!(should_query_stdio(), "{term} {program:?}"),
+            );
+        }
+    }
+
     #[test]
     fn kitty_supported_true_when_kitty_window_id_set() {
         with_env_var("KITTY_WINDOW_ID", Some("1"), || {
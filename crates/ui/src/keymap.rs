@@ -0,0 +1,732 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Screen whose key handler consults the bindings. Panels keep their own fixed keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum KeyScope {
+    Main,
+    Reader,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum KeyAction {
+    Quit,
+    PrevItem,
+    NextItem,
+    PrevColumn,
+    NextColumn,
+    OpenReader,
+    ToggleMark,
+    MarkAllVisible,
+    ToggleFavorite,
+    ToggleArchived,
+    CycleStatus,
+    MoveBookUp,
+    MoveBookDown,
+    OpenFilters,
+    OpenLabels,
+    OpenCatalog,
+    OpenSettings,
+    CycleSort,
+    ToggleLibraryView,
+    RelinkBook,
+    RemoveBook,
+    DeleteBook,
+    RefreshMetadata,
+    FilterByCollection,
+    FilterByTag,
+    ToggleDetails,
+    OpenHistory,
+    SearchNotes,
+    SaveNow,
+    CloseReader,
+    PrevPage,
+    NextPage,
+    ScrollUp,
+    ScrollDown,
+    PageUp,
+    PageDown,
+    PanLeft,
+    PanRight,
+    GotoPage,
+    SearchInBook,
+    OpenToc,
+    OpenLinks,
+    LinkBack,
+    OpenPdfAnnotations,
+    OpenBookmarks,
+    OpenNotes,
+    ToggleNotesSidebar,
+    PrevAnnotation,
+    NextAnnotation,
+    ToggleImageMode,
+    CycleTextMode,
+    ToggleTrimHeaders,
+    ToggleShowTrimmed,
+    ToggleColumns,
+    NarrowText,
+    WidenText,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    CycleImageFit,
+    RotateCounterClockwise,
+    RotateClockwise,
+    ToggleInvertColors,
+    ToggleSpread,
+    ToggleSpreadCover,
+    ToggleThumbnails,
+    Redraw,
+    SpawnKitty,
+    OpenExternal,
+    DumpPage,
+    CopyPageText,
+    ExportPages,
+}
+
+impl KeyAction {
+    pub(crate) const ALL: &'static [KeyAction] = &[
+        KeyAction::Quit,
+        KeyAction::PrevItem,
+        KeyAction::NextItem,
+        KeyAction::PrevColumn,
+        KeyAction::NextColumn,
+        KeyAction::OpenReader,
+        KeyAction::ToggleMark,
+        KeyAction::MarkAllVisible,
+        KeyAction::ToggleFavorite,
+        KeyAction::ToggleArchived,
+        KeyAction::CycleStatus,
+        KeyAction::MoveBookUp,
+        KeyAction::MoveBookDown,
+        KeyAction::OpenFilters,
+        KeyAction::OpenLabels,
+        KeyAction::OpenCatalog,
+        KeyAction::OpenSettings,
+        KeyAction::CycleSort,
+        KeyAction::ToggleLibraryView,
+        KeyAction::RelinkBook,
+        KeyAction::RemoveBook,
+        KeyAction::DeleteBook,
+        KeyAction::RefreshMetadata,
+        KeyAction::FilterByCollection,
+        KeyAction::FilterByTag,
+        KeyAction::ToggleDetails,
+        KeyAction::OpenHistory,
+        KeyAction::SearchNotes,
+        KeyAction::SaveNow,
+        KeyAction::CloseReader,
+        KeyAction::PrevPage,
+        KeyAction::NextPage,
+        KeyAction::ScrollUp,
+        KeyAction::ScrollDown,
+        KeyAction::PageUp,
+        KeyAction::PageDown,
+        KeyAction::PanLeft,
+        KeyAction::PanRight,
+        KeyAction::GotoPage,
+        KeyAction::SearchInBook,
+        KeyAction::OpenToc,
+        KeyAction::OpenLinks,
+        KeyAction::LinkBack,
+        KeyAction::OpenPdfAnnotations,
+        KeyAction::OpenBookmarks,
+        KeyAction::OpenNotes,
+        KeyAction::ToggleNotesSidebar,
+        KeyAction::PrevAnnotation,
+        KeyAction::NextAnnotation,
+        KeyAction::ToggleImageMode,
+        KeyAction::CycleTextMode,
+        KeyAction::ToggleTrimHeaders,
+        KeyAction::ToggleShowTrimmed,
+        KeyAction::ToggleColumns,
+        KeyAction::NarrowText,
+        KeyAction::WidenText,
+        KeyAction::ZoomIn,
+        KeyAction::ZoomOut,
+        KeyAction::ResetZoom,
+        KeyAction::CycleImageFit,
+        KeyAction::RotateCounterClockwise,
+        KeyAction::RotateClockwise,
+        KeyAction::ToggleInvertColors,
+        KeyAction::ToggleSpread,
+        KeyAction::ToggleSpreadCover,
+        KeyAction::ToggleThumbnails,
+        KeyAction::Redraw,
+        KeyAction::SpawnKitty,
+        KeyAction::OpenExternal,
+        KeyAction::DumpPage,
+        KeyAction::CopyPageText,
+        KeyAction::ExportPages,
+    ];
+
+    /// Name used in the persisted `key_bindings` settings map.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            KeyAction::Quit => "quit",
+            KeyAction::PrevItem => "prev_item",
+            KeyAction::NextItem => "next_item",
+            KeyAction::PrevColumn => "prev_column",
+            KeyAction::NextColumn => "next_column",
+            KeyAction::OpenReader => "open_reader",
+            KeyAction::ToggleMark => "toggle_mark",
+            KeyAction::MarkAllVisible => "mark_all_visible",
+            KeyAction::ToggleFavorite => "toggle_favorite",
+            KeyAction::ToggleArchived => "toggle_archived",
+            KeyAction::CycleStatus => "cycle_status",
+            KeyAction::MoveBookUp => "move_book_up",
+            KeyAction::MoveBookDown => "move_book_down",
+            KeyAction::OpenFilters => "open_filters",
+            KeyAction::OpenLabels => "open_labels",
+            KeyAction::OpenCatalog => "open_catalog",
+            KeyAction::OpenSettings => "open_settings",
+            KeyAction::CycleSort => "cycle_sort",
+            KeyAction::ToggleLibraryView => "toggle_library_view",
+            KeyAction::RelinkBook => "relink_book",
+            KeyAction::RemoveBook => "remove_book",
+            KeyAction::DeleteBook => "delete_book",
+            KeyAction::RefreshMetadata => "refresh_metadata",
+            KeyAction::FilterByCollection => "filter_by_collection",
+            KeyAction::FilterByTag => "filter_by_tag",
+            KeyAction::ToggleDetails => "toggle_details",
+            KeyAction::OpenHistory => "open_history",
+            KeyAction::SearchNotes => "search_notes",
+            KeyAction::SaveNow => "save_now",
+            KeyAction::CloseReader => "close_reader",
+            KeyAction::PrevPage => "prev_page",
+            KeyAction::NextPage => "next_page",
+            KeyAction::ScrollUp => "scroll_up",
+            KeyAction::ScrollDown => "scroll_down",
+            KeyAction::PageUp => "page_up",
+            KeyAction::PageDown => "page_down",
+            KeyAction::PanLeft => "pan_left",
+            KeyAction::PanRight => "pan_right",
+            KeyAction::GotoPage => "goto_page",
+            KeyAction::SearchInBook => "search_in_book",
+            KeyAction::OpenToc => "open_toc",
+            KeyAction::OpenLinks => "open_links",
+            KeyAction::LinkBack => "link_back",
+            KeyAction::OpenPdfAnnotations => "open_pdf_annotations",
+            KeyAction::OpenBookmarks => "open_bookmarks",
+            KeyAction::OpenNotes => "open_notes",
+            KeyAction::ToggleNotesSidebar => "toggle_notes_sidebar",
+            KeyAction::PrevAnnotation => "prev_annotation",
+            KeyAction::NextAnnotation => "next_annotation",
+            KeyAction::ToggleImageMode => "toggle_image_mode",
+            KeyAction::CycleTextMode => "cycle_text_mode",
+            KeyAction::ToggleTrimHeaders => "toggle_trim_headers",
+            KeyAction::ToggleShowTrimmed => "toggle_show_trimmed",
+            KeyAction::ToggleColumns => "toggle_columns",
+            KeyAction::NarrowText => "narrow_text",
+            KeyAction::WidenText => "widen_text",
+            KeyAction::ZoomIn => "zoom_in",
+            KeyAction::ZoomOut => "zoom_out",
+            KeyAction::ResetZoom => "reset_zoom",
+            KeyAction::CycleImageFit => "cycle_image_fit",
+            KeyAction::RotateCounterClockwise => "rotate_ccw",
+            KeyAction::RotateClockwise => "rotate_cw",
+            KeyAction::ToggleInvertColors => "toggle_invert_colors",
+            KeyAction::ToggleSpread => "toggle_spread",
+            KeyAction::ToggleSpreadCover => "toggle_spread_cover",
+            KeyAction::ToggleThumbnails => "toggle_thumbnails",
+            KeyAction::Redraw => "redraw",
+            KeyAction::SpawnKitty => "spawn_kitty",
+            KeyAction::OpenExternal => "open_external",
+            KeyAction::DumpPage => "dump_page",
+            KeyAction::CopyPageText => "copy_page_text",
+            KeyAction::ExportPages => "export_pages",
+        }
+    }
+
+    fn scopes(self) -> &'static [KeyScope] {
+        match self {
+            KeyAction::OpenHistory | KeyAction::SaveNow => &[KeyScope::Main, KeyScope::Reader],
+            KeyAction::Quit
+            | KeyAction::PrevItem
+            | KeyAction::NextItem
+            | KeyAction::PrevColumn
+            | KeyAction::NextColumn
+            | KeyAction::OpenReader
+            | KeyAction::ToggleMark
+            | KeyAction::MarkAllVisible
+            | KeyAction::ToggleFavorite
+            | KeyAction::ToggleArchived
+            | KeyAction::CycleStatus
+            | KeyAction::MoveBookUp
+            | KeyAction::MoveBookDown
+            | KeyAction::OpenFilters
+            | KeyAction::OpenLabels
+            | KeyAction::OpenCatalog
+            | KeyAction::OpenSettings
+            | KeyAction::CycleSort
+            | KeyAction::ToggleLibraryView
+            | KeyAction::RelinkBook
+            | KeyAction::RemoveBook
+            | KeyAction::DeleteBook
+            | KeyAction::RefreshMetadata
+            | KeyAction::FilterByCollection
+            | KeyAction::FilterByTag
+            | KeyAction::ToggleDetails
+            | KeyAction::SearchNotes => &[KeyScope::Main],
+            _ => &[KeyScope::Reader],
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            KeyAction::Quit => &["Esc"],
+            KeyAction::PrevItem => &["Up"],
+            KeyAction::NextItem => &["Down"],
+            // Only the cover grid has columns.
+            KeyAction::PrevColumn => &["Left"],
+            KeyAction::NextColumn => &["Right"],
+            KeyAction::OpenReader => &["Enter"],
+            KeyAction::ToggleMark => &["Space"],
+            KeyAction::MarkAllVisible => &["*"],
+            KeyAction::ToggleFavorite => &["f"],
+            KeyAction::ToggleArchived => &["A"],
+            KeyAction::CycleStatus => &["u"],
+            KeyAction::MoveBookUp => &["Shift+Up", "K"],
+            KeyAction::MoveBookDown => &["Shift+Down", "J"],
+            KeyAction::OpenFilters => &["/"],
+            KeyAction::OpenLabels => &["l"],
+            KeyAction::OpenCatalog => &["c"],
+            KeyAction::OpenSettings => &["s"],
+            KeyAction::CycleSort => &["o"],
+            KeyAction::ToggleLibraryView => &["v"],
+            KeyAction::RelinkBook => &["R"],
+            KeyAction::RemoveBook => &["X"],
+            KeyAction::DeleteBook => &["d", "Delete"],
+            KeyAction::RefreshMetadata => &["M"],
+            KeyAction::FilterByCollection => &["C"],
+            KeyAction::FilterByTag => &["T"],
+            // Only narrow screens hide the details behind the list.
+            KeyAction::ToggleDetails => &["i"],
+            KeyAction::OpenHistory => &["`", "Ctrl+o"],
+            KeyAction::SearchNotes => &["N"],
+            KeyAction::SaveNow => &["Ctrl+s"],
+            KeyAction::CloseReader => &["Esc"],
+            KeyAction::PrevPage => &["Left"],
+            KeyAction::NextPage => &["Right"],
+            KeyAction::ScrollUp => &["Up"],
+            KeyAction::ScrollDown => &["Down"],
+            KeyAction::PageUp => &["PgUp"],
+            KeyAction::PageDown => &["PgDn"],
+            KeyAction::PanLeft => &["Shift+Left"],
+            KeyAction::PanRight => &["Shift+Right"],
+            KeyAction::GotoPage => &["g"],
+            KeyAction::SearchInBook => &["/"],
+            KeyAction::OpenToc => &["t"],
+            KeyAction::OpenLinks => &["f"],
+            // Ctrl+o already opens the history in the reader.
+            KeyAction::LinkBack => &["Backspace"],
+            KeyAction::OpenPdfAnnotations => &["P"],
+            KeyAction::OpenBookmarks => &["b"],
+            KeyAction::OpenNotes => &["n"],
+            KeyAction::ToggleNotesSidebar => &["N"],
+            KeyAction::PrevAnnotation => &["{"],
+            KeyAction::NextAnnotation => &["}"],
+            KeyAction::ToggleImageMode => &["m"],
+            KeyAction::CycleTextMode => &["r"],
+            KeyAction::ToggleTrimHeaders => &["h"],
+            KeyAction::ToggleShowTrimmed => &["H"],
+            KeyAction::ToggleColumns => &["C"],
+            KeyAction::NarrowText => &["<"],
+            KeyAction::WidenText => &[">"],
+            KeyAction::ZoomIn => &["+", "="],
+            KeyAction::ZoomOut => &["-"],
+            KeyAction::ResetZoom => &["0"],
+            KeyAction::CycleImageFit => &["w"],
+            KeyAction::RotateCounterClockwise => &["["],
+            KeyAction::RotateClockwise => &["]"],
+            KeyAction::ToggleInvertColors => &["i"],
+            KeyAction::ToggleSpread => &["v"],
+            KeyAction::ToggleSpreadCover => &["V"],
+            KeyAction::ToggleThumbnails => &["T"],
+            KeyAction::Redraw => &["Ctrl+l"],
+            KeyAction::SpawnKitty => &["k"],
+            KeyAction::OpenExternal => &["o"],
+            KeyAction::DumpPage => &["d"],
+            KeyAction::CopyPageText => &["y"],
+            KeyAction::ExportPages => &["E"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already part of a character's case (`V`, `*`), so it is not compared.
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self {
+            code,
+            modifiers: modifiers
+                & (KeyModifiers::SHIFT | KeyModifiers::CONTROL | KeyModifiers::ALT),
+        }
+    }
+
+    fn from_event(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+
+    /// Parses specs like `j`, `Ctrl+o`, `Shift+Left` or `PgDn`.
+    pub(crate) fn parse(spec: &str) -> anyhow::Result<Self> {
+        let spec = spec.trim();
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = spec;
+        while let Some((prefix, tail)) = rest.split_once('+') {
+            if tail.is_empty() {
+                // A trailing `+` is the key itself, e.g. `Ctrl++`.
+                break;
+            }
+            modifiers |= match prefix.trim().to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => anyhow::bail!("unknown modifier `{other}` in `{spec}`"),
+            };
+            rest = tail;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(ch), None) => KeyCode::Char(ch),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "esc" | "escape" => KeyCode::Esc,
+                "enter" | "return" => KeyCode::Enter,
+                "space" => KeyCode::Char(' '),
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pgup" | "pageup" => KeyCode::PageUp,
+                "pgdn" | "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => anyhow::bail!("unknown key `{spec}`"),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(ch) => write!(f, "{ch}"),
+            KeyCode::Esc => f.write_str("Esc"),
+            KeyCode::Enter => f.write_str("Enter"),
+            KeyCode::Tab => f.write_str("Tab"),
+            KeyCode::Backspace => f.write_str("Backspace"),
+            KeyCode::Delete => f.write_str("Delete"),
+            KeyCode::Up => f.write_str("↑"),
+            KeyCode::Down => f.write_str("↓"),
+            KeyCode::Left => f.write_str("←"),
+            KeyCode::Right => f.write_str("→"),
+            KeyCode::PageUp => f.write_str("PgUp"),
+            KeyCode::PageDown => f.write_str("PgDn"),
+            KeyCode::Home => f.write_str("Home"),
+            KeyCode::End => f.write_str("End"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+/// Keys for the main screen and the reader, built from defaults plus the user's overrides.
+#[derive(Debug, Clone)]
+pub(crate) struct KeyBindings {
+    keys: HashMap<KeyAction, Vec<KeyBinding>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::from_overrides(&BTreeMap::new()).0
+    }
+}
+
+impl KeyBindings {
+    /// Applies `overrides` (action name → key specs) on top of the defaults.
+    ///
+    /// Returns the bindings plus a description of every problem found: unknown actions, bad
+    /// key specs and keys claimed by two actions on the same screen. On a conflict the
+    /// overridden action keeps the key, so an explicit binding never loses to a default.
+    pub(crate) fn from_overrides(overrides: &BTreeMap<String, Vec<String>>) -> (Self, Vec<String>) {
+        let mut problems = Vec::new();
+        for name in overrides.keys() {
+            if !KeyAction::ALL.iter().any(|a| a.as_str() == name) {
+                problems.push(format!("unknown action `{name}`"));
+            }
+        }
+
+        let mut keys: HashMap<KeyAction, Vec<KeyBinding>> = HashMap::new();
+        for &action in KeyAction::ALL {
+            let bindings = match overrides.get(action.as_str()) {
+                Some(specs) => specs
+                    .iter()
+                    .filter_map(|spec| {
+                        KeyBinding::parse(spec)
+                            .map_err(|err| problems.push(format!("{}: {err}", action.as_str())))
+                            .ok()
+                    })
+                    .collect(),
+                None => action
+                    .default_keys()
+                    .iter()
+                    .filter_map(|spec| KeyBinding::parse(spec).ok())
+                    .collect(),
+            };
+            keys.insert(action, bindings);
+        }
+
+        let overridden = |action: &KeyAction| overrides.contains_key(action.as_str());
+        let claim_order: Vec<KeyAction> = KeyAction::ALL
+            .iter()
+            .copied()
+            .filter(overridden)
+            .chain(KeyAction::ALL.iter().copied().filter(|a| !overridden(a)))
+            .collect();
+        for scope in [KeyScope::Main, KeyScope::Reader] {
+            let mut owners: HashMap<KeyBinding, KeyAction> = HashMap::new();
+            for &action in &claim_order {
+                if !action.scopes().contains(&scope) {
+                    continue;
+                }
+                let Some(bindings) = keys.get_mut(&action) else {
+                    continue;
+                };
+                bindings.retain(|binding| match owners.get(binding) {
+                    Some(owner) => {
+                        problems.push(format!(
+                            "`{binding}` is bound to both {} and {}; keeping {}",
+                            owner.as_str(),
+                            action.as_str(),
+                            owner.as_str()
+                        ));
+                        false
+                    }
+                    None => {
+                        owners.insert(*binding, action);
+                        true
+                    }
+                });
+            }
+        }
+
+        (Self { keys }, problems)
+    }
+
+    pub(crate) fn action(&self, scope: KeyScope, key: &KeyEvent) -> Option<KeyAction> {
+        let pressed = KeyBinding::from_event(key);
+        KeyAction::ALL.iter().copied().find(|action| {
+            action.scopes().contains(&scope)
+                && self
+                    .keys
+                    .get(action)
+                    .is_some_and(|bindings| bindings.contains(&pressed))
+        })
+    }
+
+    /// The first key bound to `action`, as a press of that key.
+    pub(crate) fn key_event(&self, action: KeyAction) -> Option<KeyEvent> {
+        self.keys
+            .get(&action)
+            .and_then(|bindings| bindings.first())
+            .map(|binding| KeyEvent::new(binding.code, binding.modifiers))
+    }
+
+    /// Short label for footers, e.g. `g` or `Ctrl+o`; `-` when the action is unbound.
+    pub(crate) fn label(&self, action: KeyAction) -> String {
+        self.keys
+            .get(&action)
+            .and_then(|bindings| bindings.first())
+            .map_or_else(|| "-".to_string(), ToString::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn defaults_have_no_conflicts_and_match_todays_keys() {
+        let (keys, problems) = KeyBindings::from_overrides(&BTreeMap::new());
+        assert!(problems.is_empty(), "{problems:?}");
+        assert_eq!(
+            keys.action(KeyScope::Main, &press(KeyCode::Down, KeyModifiers::NONE)),
+            Some(KeyAction::NextItem)
+        );
+        assert_eq!(
+            keys.action(KeyScope::Main, &press(KeyCode::Down, KeyModifiers::SHIFT)),
+            Some(KeyAction::MoveBookDown)
+        );
+        assert_eq!(
+            keys.action(
+                KeyScope::Main,
+                &press(KeyCode::Char('o'), KeyModifiers::CONTROL)
+            ),
+            Some(KeyAction::OpenHistory)
+        );
+        assert_eq!(
+            keys.action(
+                KeyScope::Main,
+                &press(KeyCode::Char('N'), KeyModifiers::SHIFT)
+            ),
+            Some(KeyAction::SearchNotes)
+        );
+        assert_eq!(
+            keys.action(
+                KeyScope::Main,
+                &press(KeyCode::Char('o'), KeyModifiers::NONE)
+            ),
+            Some(KeyAction::CycleSort)
+        );
+        assert_eq!(
+            keys.action(
+                KeyScope::Main,
+                &press(KeyCode::Char('d'), KeyModifiers::NONE)
+            ),
+            Some(KeyAction::DeleteBook)
+        );
+        assert_eq!(
+            keys.action(
+                KeyScope::Reader,
+                &press(KeyCode::Char('d'), KeyModifiers::NONE)
+            ),
+            Some(KeyAction::DumpPage)
+        );
+        assert_eq!(
+            keys.action(
+                KeyScope::Reader,
+                &press(KeyCode::Char('o'), KeyModifiers::NONE)
+            ),
+            Some(KeyAction::OpenExternal)
+        );
+        assert_eq!(
+            keys.action(KeyScope::Reader, &press(KeyCode::Left, KeyModifiers::SHIFT)),
+            Some(KeyAction::PanLeft)
+        );
+        assert_eq!(
+            keys.action(
+                KeyScope::Reader,
+                &press(KeyCode::Char('V'), KeyModifiers::SHIFT)
+            ),
+            Some(KeyAction::ToggleSpreadCover)
+        );
+        assert_eq!(
+            keys.action(
+                KeyScope::Reader,
+                &press(KeyCode::Char('N'), KeyModifiers::SHIFT)
+            ),
+            Some(KeyAction::ToggleNotesSidebar)
+        );
+        assert_eq!(
+            keys.action(
+                KeyScope::Reader,
+                &press(KeyCode::Char('f'), KeyModifiers::NONE)
+            ),
+            Some(KeyAction::OpenLinks)
+        );
+        assert_eq!(
+            keys.action(
+                KeyScope::Reader,
+                &press(KeyCode::Backspace, KeyModifiers::NONE)
+            ),
+            Some(KeyAction::LinkBack)
+        );
+        assert_eq!(
+            keys.action(
+                KeyScope::Reader,
+                &press(KeyCode::Char('P'), KeyModifiers::SHIFT)
+            ),
+            Some(KeyAction::OpenPdfAnnotations)
+        );
+        assert_eq!(
+            keys.action(
+                KeyScope::Reader,
+                &press(KeyCode::Char('l'), KeyModifiers::CONTROL)
+            ),
+            Some(KeyAction::Redraw)
+        );
+    }
+
+    #[test]
+    fn overrides_replace_defaults_and_report_conflicts() {
+        let overrides = BTreeMap::from([
+            (
+                "next_item".to_string(),
+                vec!["j".to_string(), "Down".to_string()],
+            ),
+            ("prev_page".to_string(), vec!["h".to_string()]),
+            ("bogus".to_string(), vec!["x".to_string()]),
+            ("zoom_in".to_string(), vec!["Hyper+z".to_string()]),
+        ]);
+        let (keys, problems) = KeyBindings::from_overrides(&overrides);
+
+        assert_eq!(
+            keys.action(
+                KeyScope::Main,
+                &press(KeyCode::Char('j'), KeyModifiers::NONE)
+            ),
+            Some(KeyAction::NextItem)
+        );
+        // The explicit binding wins over the default `h` (trim headers).
+        assert_eq!(
+            keys.action(
+                KeyScope::Reader,
+                &press(KeyCode::Char('h'), KeyModifiers::NONE)
+            ),
+            Some(KeyAction::PrevPage)
+        );
+        assert_eq!(
+            keys.action(KeyScope::Reader, &press(KeyCode::Left, KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(keys.label(KeyAction::ToggleTrimHeaders), "-");
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        assert!(problems.iter().any(|p| p.contains("bogus")));
+        assert!(problems.iter().any(|p| p.contains("Hyper")));
+        assert!(problems.iter().any(|p| p.contains("toggle_trim_headers")));
+    }
+
+    #[test]
+    fn parses_and_formats_specs() -> anyhow::Result<()> {
+        assert_eq!(KeyBinding::parse("Ctrl+o")?.to_string(), "Ctrl+o");
+        assert_eq!(KeyBinding::parse("ctrl++")?.to_string(), "Ctrl++");
+        assert_eq!(KeyBinding::parse("pgdn")?.to_string(), "PgDn");
+        assert_eq!(KeyBinding::parse("del")?.to_string(), "Delete");
+        assert_eq!(KeyBinding::parse("Shift+V")?, KeyBinding::parse("V")?);
+        assert!(KeyBinding::parse("F13").is_err());
+        Ok(())
+    }
+}
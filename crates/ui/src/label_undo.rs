@@ -0,0 +1,232 @@
+use std::collections::{HashMap, VecDeque};
+
+use bookshelf_core::{BookLabels, TagKind};
+
+/// Catalog deletes and renames remembered for undo; older ones are forgotten.
+pub(crate) const LABEL_UNDO_LIMIT: usize = 20;
+
+/// What a catalog delete or rename changed, enough to put it back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LabelUndo {
+    pub(crate) kind: TagKind,
+    /// The label's name before the operation.
+    pub(crate) name: String,
+    /// The new name for a rename; `None` for a delete.
+    pub(crate) renamed_to: Option<String>,
+    /// Labels of every book that carried the label, as they were before the operation.
+    pub(crate) prior_labels: Vec<(String, BookLabels)>,
+}
+
+impl LabelUndo {
+    pub(crate) fn capture(
+        kind: TagKind,
+        name: &str,
+        renamed_to: Option<&str>,
+        labels_by_path: &HashMap<String, BookLabels>,
+    ) -> Self {
+        let mut prior_labels: Vec<(String, BookLabels)> = labels_by_path
+            .iter()
+            .filter(|(_, labels)| labels.carries(kind, name))
+            .map(|(path, labels)| (path.clone(), labels.clone()))
+            .collect();
+        prior_labels.sort_by(|a, b| a.0.cmp(&b.0));
+        Self {
+            kind,
+            name: name.to_string(),
+            renamed_to: renamed_to.map(str::to_string),
+            prior_labels,
+        }
+    }
+
+    /// Puts the label back, spelled as before, on the books that carried it. Label changes made
+    /// since are kept: a renamed label the user has removed stays removed, and a book moved to
+    /// another collection (author, series) stays there. Returns the paths whose labels changed.
+    pub(crate) fn restore_labels(
+        &self,
+        labels_by_path: &mut HashMap<String, BookLabels>,
+    ) -> Vec<String> {
+        let mut changed = Vec::new();
+        for (path, prior) in &self.prior_labels {
+            let mut labels = labels_by_path.get(path).cloned().unwrap_or_default();
+            let before = labels.clone();
+            match self.kind {
+                TagKind::Tag => {
+                    let Some(spelling) = prior
+                        .tags
+                        .iter()
+                        .find(|tag| tag.eq_ignore_ascii_case(&self.name))
+                    else {
+                        continue;
+                    };
+                    let restore = match &self.renamed_to {
+                        Some(to) => {
+                            let count = labels.tags.len();
+                            labels.tags.retain(|tag| !tag.eq_ignore_ascii_case(to));
+                            labels.tags.len() != count
+                        }
+                        None => true,
+                    };
+                    if restore {
+                        labels.tags.push(spelling.clone());
+                    }
+                }
+                TagKind::Collection => restore_single(
+                    &mut labels.collection,
+                    &prior.collection,
+                    String::as_str,
+                    self.renamed_to.as_deref(),
+                ),
+                TagKind::Author => restore_single(
+                    &mut labels.author,
+                    &prior.author,
+                    String::as_str,
+                    self.renamed_to.as_deref(),
+                ),
+                TagKind::Series => restore_single(
+                    &mut labels.series,
+                    &prior.series,
+                    |(name, _)| name.as_str(),
+                    self.renamed_to.as_deref(),
+                ),
+            }
+            labels.normalize();
+            if labels == before {
+                continue;
+            }
+            if labels.is_empty() {
+                labels_by_path.remove(path);
+            } else {
+                labels_by_path.insert(path.clone(), labels);
+            }
+            changed.push(path.clone());
+        }
+        changed
+    }
+}
+
+/// Puts back a one-per-book label (collection, author, series): after a rename, only where the
+/// book still carries the new name; after a delete, only where the book has none of that kind.
+fn restore_single<T: Clone>(
+    current: &mut Option<T>,
+    prior: &Option<T>,
+    name: fn(&T) -> &str,
+    renamed_to: Option<&str>,
+) {
+    let restore = match renamed_to {
+        Some(to) => current
+            .as_ref()
+            .is_some_and(|value| name(value).eq_ignore_ascii_case(to)),
+        None => current.is_none(),
+    };
+    if restore {
+        *current = prior.clone();
+    }
+}
+
+/// Undo entries for the session, newest last.
+#[derive(Debug, Default)]
+pub(crate) struct LabelUndoStack {
+    entries: VecDeque<LabelUndo>,
+}
+
+impl LabelUndoStack {
+    pub(crate) fn push(&mut self, entry: LabelUndo) {
+        if self.entries.len() == LABEL_UNDO_LIMIT {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<LabelUndo> {
+        self.entries.pop_back()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(tags: &[&str], collection: Option<&str>) -> BookLabels {
+        BookLabels {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            collection: collection.map(str::to_string),
+            ..BookLabels::default()
+        }
+    }
+
+    #[test]
+    fn undoing_a_delete_restores_the_label_and_keeps_later_edits() {
+        let mut by_path = HashMap::from([
+            ("/a.pdf".to_string(), labels(&["Rust", "os"], Some("Work"))),
+            ("/b.pdf".to_string(), labels(&["rust"], None)),
+            ("/c.pdf".to_string(), labels(&["go"], None)),
+        ]);
+        let undo = LabelUndo::capture(TagKind::Tag, "rust", None, &by_path);
+        assert_eq!(undo.prior_labels.len(), 2);
+
+        // The delete, then an unrelated edit on one of the books.
+        by_path.insert("/a.pdf".to_string(), labels(&["os", "new"], Some("Work")));
+        by_path.remove("/b.pdf");
+
+        assert_eq!(undo.restore_labels(&mut by_path), vec!["/a.pdf", "/b.pdf"]);
+        assert_eq!(
+            by_path["/a.pdf"],
+            labels(&["new", "os", "Rust"], Some("Work"))
+        );
+        assert_eq!(by_path["/b.pdf"], labels(&["rust"], None));
+        assert_eq!(by_path["/c.pdf"], labels(&["go"], None));
+    }
+
+    #[test]
+    fn undoing_a_rename_only_touches_books_that_still_carry_the_new_name() {
+        let mut by_path = HashMap::from([
+            ("/a.pdf".to_string(), labels(&[], Some("Work"))),
+            ("/b.pdf".to_string(), labels(&[], Some("work"))),
+        ]);
+        let undo = LabelUndo::capture(TagKind::Collection, "Work", Some("Job"), &by_path);
+        by_path.insert("/a.pdf".to_string(), labels(&[], Some("Job")));
+        by_path.insert("/b.pdf".to_string(), labels(&[], Some("Home")));
+
+        assert_eq!(undo.restore_labels(&mut by_path), vec!["/a.pdf"]);
+        assert_eq!(by_path["/a.pdf"], labels(&[], Some("Work")));
+        assert_eq!(by_path["/b.pdf"], labels(&[], Some("Home")));
+    }
+
+    #[test]
+    fn undoing_a_series_delete_brings_back_the_number() {
+        let numbered = |name: &str, number: u32| BookLabels {
+            series: Some((name.to_string(), Some(number))),
+            ..BookLabels::default()
+        };
+        let mut by_path = HashMap::from([("/a.pdf".to_string(), numbered("Dune", 2))]);
+        let undo = LabelUndo::capture(TagKind::Series, "dune", None, &by_path);
+        by_path.remove("/a.pdf");
+
+        assert_eq!(undo.restore_labels(&mut by_path), vec!["/a.pdf"]);
+        assert_eq!(by_path["/a.pdf"], numbered("Dune", 2));
+    }
+
+    #[test]
+    fn stack_forgets_the_oldest_entries() {
+        let mut stack = LabelUndoStack::default();
+        for idx in 0..=LABEL_UNDO_LIMIT {
+            stack.push(LabelUndo::capture(
+                TagKind::Tag,
+                &idx.to_string(),
+                None,
+                &HashMap::new(),
+            ));
+        }
+        let mut names = Vec::new();
+        while let Some(entry) = stack.pop() {
+            names.push(entry.name);
+        }
+        assert_eq!(names.len(), LABEL_UNDO_LIMIT);
+        assert_eq!(names.first().map(String::as_str), Some("20"));
+        assert_eq!(names.last().map(String::as_str), Some("1"));
+    }
+}
@@ -1,44 +1,123 @@
 //! ratatui-based UI.
 
-use std::collections::VecDeque;
+use std::cell::RefCell;
 use std::hash::Hasher;
 use std::io::{self, Stdout};
 use std::path::Path;
 use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::time::Duration;
 use std::time::Instant;
 
 use anyhow::Context as _;
-use bookshelf_application::{AppContext, CollectionFilter, LabelCatalogOp, TagMatchMode};
+use bookshelf_application::{
+    AppContext, BookPathOp, BootReader, CollectionFilter, DuplicateDisposal, DuplicateEvent,
+    DuplicateReport, DuplicateScan, LabelCatalogOp, LibraryQuery, LibraryScan, LibraryWatcher,
+    MovedBook, ScanEvent, ScanMerge, ScanMetadata, ScanReport, TagMatchMode, TagTreeRow, Wake,
+    book_author, complete_tag_namespace, is_book_file, matches_collection_filter,
+    matches_name_filter, matches_tag_filter, tag_matches, tag_namespaces, tag_tree,
+};
 use bookshelf_core::{
-    Book, BookLabels, Bookmark, KittyImageQuality, Note, ReaderMode, ReaderTextMode, Settings,
-    TagKind, Theme, TocItem,
+    AUTOSAVE_SECS_CHOICES, AutoCrop, Book, BookKind, BookLabels, BookProgress, Bookmark,
+    BookmarkView, CleanupReport, DownscaleFilter, FURNITURE_MIN_FRACTION_CHOICES,
+    FURNITURE_SAMPLE_PAGE_CHOICES, ImageCacheLimit, ImageFit, ImageViewport, KittyImageQuality,
+    Note, PageRotation, READER_LINE_SPACING_CHOICES, ReaderMode, ReaderTextMode, ReaderViewState,
+    ReadingStatus, Settings, SettingsImport, SortMode, TICK_RATE_MS_CHOICES, TagKind, Theme,
+    TocItem, format_series, parse_series,
+};
+use bookshelf_engine::{
+    Engine, FurnitureConfig, PageFurniture, SearchHit, TextLayout, pdfium_library_name,
+    pdfium_search_paths,
 };
-use bookshelf_engine::{Engine, PageFurniture};
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::event::{
+    DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, Event, KeyCode,
+    KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use crossterm::terminal;
 use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
-use crossterm::{event, terminal};
 use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
 use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span, Text};
 use ratatui::widgets::{
-    Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph, Wrap,
+    Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Padding, Paragraph, Wrap,
 };
-use ratatui_image::picker::{Picker, cap_parser::QueryStdioOptions};
+use ratatui_image::picker::{Picker, ProtocolType, cap_parser::QueryStdioOptions};
 use ratatui_image::protocol::Protocol as ImageProtocol;
 use ratatui_image::protocol::kitty::Kitty;
 use ratatui_image::{Image as ImageWidget, Resize};
 
+mod backups;
+mod clipboard;
+mod cover_grid;
+mod event_queue;
+mod export;
+mod external_viewer;
+mod goto;
+#[cfg(test)]
+mod harness;
 mod image_protocol;
-mod kitty_spawn;
-use unicode_width::UnicodeWidthStr;
+mod keymap;
+mod label_undo;
+mod library_row;
+mod minimap;
+mod mouse;
+mod notes_search;
+mod notes_sidebar;
+mod page_cache;
+mod page_links;
+mod page_render;
+mod pdf_annotations;
+mod pdfium_setup;
+mod reader_text;
+mod reading_clock;
+mod redraw;
+mod scan_roots;
+mod terminal_spawn;
+mod text_input;
+mod toc;
+
+use backups::{BackupsPanel, backup_label};
+use cover_grid::{
+    CoverGrid, GRID_CAPTION_ROWS, GRID_CARD_CELL_HEIGHT, GRID_CELL_WIDTH, GRID_COVER_CELL_HEIGHT,
+    GridMove, grid_columns, grid_first_row, grid_move,
+};
+use event_queue::{ChannelEvents, EventQueue, InputThread, LoopMessage, idle_wait};
+use export::{
+    DEFAULT_EXPORT_DPI, ExportRun, export_file_name, resolve_export_dpi, resolve_export_range,
+};
+use goto::{
+    AnnotationJump, annotation_jump, labeled_page, page_label, page_percent, resolve_goto_target,
+};
+use keymap::{KeyAction, KeyBindings, KeyScope};
+use label_undo::{LabelUndo, LabelUndoStack};
+use library_row::row_decorations;
+use minimap::{MINIMAP_MIN_PAGE_WIDTH, MinimapMark, minimap_rows};
+use mouse::{HitRegions, HitTarget};
+use notes_search::{NOTES_SEARCH_MAX_HITS, NOTES_SEARCH_PAGE_ROWS, NoteHitKind, NotesSearchPanel};
+use notes_sidebar::NotesSidebar;
+use page_cache::{CachedPageImage, PageImageCache, filter_type, fit_to_frame};
+use page_links::LinksPanel;
+use page_render::{
+    BookKindJob, CoverDone, CoverJob, ExportJob, ExportPageDone, PageCountDone, PageCountJob,
+    PageImageDone, PageImageJob, PageImageKey, PageRenderWorker, RenderDone, ThumbnailDone,
+    ThumbnailJob, spread_pages, thumbnail_window,
+};
+use pdf_annotations::PdfAnnotationsPanel;
+use pdfium_setup::PdfiumSetupPanel;
+use reading_clock::ReadingClock;
+use redraw::Redraw;
+use scan_roots::{RootEdit, ScanPathPanel, root_status};
+use text_input::TextInput;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UiExit {
     Quit,
-    Rescan,
+    /// The library database was restored from a backup; load the session again from it,
+    /// without saving this one over it.
+    Reload,
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +126,78 @@ pub struct UiOutcome {
     pub exit: UiExit,
 }
 
+/// Persists `(path, progress)` while the UI is running so progress survives crashes.
+pub type ProgressSink = Box<dyn FnMut(&str, &BookProgress) -> anyhow::Result<()>>;
+
+/// Merges books found by a background library scan into the stored library; the flag says
+/// whether the scan completed, so stored books it did not find count as missing.
+pub type LibrarySink = Box<dyn FnMut(&[Book], bool) -> anyhow::Result<ScanMerge>>;
+
+/// Runs a database cleanup, keeping the given stored paths.
+pub type CleanupSink = Box<dyn FnMut(&[String]) -> anyhow::Result<CleanupReport>>;
+
+/// Saves the session's unsaved changes when the UI stops on an error or a panic.
+pub type SessionSink = Box<dyn FnMut(&mut AppContext) -> anyhow::Result<()>>;
+
+/// Writes part of the session's unsaved changes while the UI runs and returns how many it
+/// wrote; what it leaves stays dirty for the next call.
+pub type AutosaveSink = Box<dyn FnMut(&mut AppContext) -> anyhow::Result<usize>>;
+
+/// Calls an autosave sink until nothing is unsaved or a call writes nothing, and returns how
+/// many changes were written in all.
+pub fn save_all_changes(
+    sink: &mut dyn FnMut(&mut AppContext) -> anyhow::Result<usize>,
+    ctx: &mut AppContext,
+) -> anyhow::Result<usize> {
+    let mut written = 0;
+    while ctx.has_unsaved_changes() {
+        match sink(ctx)? {
+            0 => break,
+            count => written += count,
+        }
+    }
+    Ok(written)
+}
+
+/// Writes the given settings to a settings file.
+pub type SettingsExportSink = Box<dyn FnMut(&Path, &Settings) -> anyhow::Result<()>>;
+
+/// Replaces the stored settings with those in a settings file and returns them.
+pub type SettingsImportSink = Box<dyn FnMut(&Path) -> anyhow::Result<SettingsImport>>;
+
+/// Writes the session's unsaved changes and backs the library database up next to it.
+/// Returns the new backup.
+pub type BackupSink = Box<dyn FnMut(&mut AppContext) -> anyhow::Result<std::path::PathBuf>>;
+
+/// Lists the library database's backups, newest first.
+pub type BackupListSink = Box<dyn FnMut() -> anyhow::Result<Vec<std::path::PathBuf>>>;
+
+/// Replaces the library database with a backup. The UI then exits with `UiExit::Reload`.
+pub type RestoreSink = Box<dyn FnMut(&Path) -> anyhow::Result<()>>;
+
+/// Reports whether another instance saved to the database since this session loaded it.
+pub type ConflictCheck = Box<dyn FnMut(&AppContext) -> anyhow::Result<bool>>;
+
+const PROGRESS_FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+/// How long "saved" stays in the footer after an autosave.
+const SAVED_FLASH: Duration = Duration::from_secs(2);
+/// Smallest terminal the screens are laid out for; below it a notice is drawn instead.
+const MIN_SCREEN_WIDTH: u16 = 60;
+const MIN_SCREEN_HEIGHT: u16 = 20;
+/// Below this many columns the library list and the book details take turns on screen.
+const NARROW_LIBRARY_WIDTH: u16 = 90;
+/// Popups are not drawn smaller than this, the screen allowing, so their text still fits.
+const POPUP_MIN_WIDTH: u16 = 48;
+const POPUP_MIN_HEIGHT: u16 = 10;
+
+/// A `Wake` that posts to the event loop's channel.
+fn loop_wake(messages: &Sender<LoopMessage>) -> Wake {
+    let messages = messages.clone();
+    Arc::new(move || {
+        let _ = messages.send(LoopMessage::Wake);
+    })
+}
+
 pub struct Ui {
     ctx: AppContext,
     settings_panel: SettingsPanel,
@@ -55,24 +206,126 @@ pub struct Ui {
     label_manager_panel: LabelManagerPanel,
     assign_labels_panel: AssignLabelsPanel,
     label_catalog_input_panel: LabelCatalogInputPanel,
+    /// Catalog deletes and renames from this session, undone with `u` in the Catalog tab.
+    label_undo: LabelUndoStack,
     goto_panel: GotoPanel,
+    export_panel: ExportPanel,
+    pdfium_setup: PdfiumSetupPanel,
+    /// Page export running on the render worker.
+    export: Option<ExportRun>,
     bookmarks_panel: BookmarksPanel,
     notes_panel: NotesPanel,
+    notes_sidebar: NotesSidebar,
     toc_panel: TocPanel,
+    links_panel: LinksPanel,
+    pdf_annotations_panel: PdfAnnotationsPanel,
+    search_in_book_panel: SearchInBookPanel,
     reader: ReaderPanel,
     boot_reader_session: bool,
     ignore_next_esc_quit: bool,
     engine: Engine,
+    page_render: PageRenderWorker,
     image_picker: Picker,
-    spawned_kitties: Vec<std::process::Child>,
+    /// Graphics terminals started by the reader fallback, killed when the app quits.
+    spawned_terminals: Vec<std::process::Child>,
     meta_cache: BookMetaCache,
+    size_by_path: std::collections::HashMap<String, Option<u64>>,
+    marked_paths: std::collections::HashSet<String>,
+    progress_sink: Option<ProgressSink>,
+    last_progress_flush: Instant,
+    library_sink: Option<LibrarySink>,
+    cleanup_sink: Option<CleanupSink>,
+    settings_export_sink: Option<SettingsExportSink>,
+    settings_import_sink: Option<SettingsImportSink>,
+    backup_sink: Option<BackupSink>,
+    backup_list_sink: Option<BackupListSink>,
+    restore_sink: Option<RestoreSink>,
+    /// Library database this session uses, handed on to readers started in another terminal.
+    db_path: Option<std::path::PathBuf>,
+    session_sink: Option<SessionSink>,
+    autosave_sink: Option<AutosaveSink>,
+    last_autosave: Instant,
+    /// Until when the footer shows "saved" for the last autosave.
+    saved_flash_until: Option<Instant>,
+    conflict_check: Option<ConflictCheck>,
+    library_scan: Option<LibraryScanState>,
+    /// Duplicate pass started from the settings menu; the panel opens when it finishes.
+    duplicate_scan: Option<DuplicateScanState>,
+    /// Rescans when files change under the library roots (`Settings.watch_library`).
+    library_watcher: Option<LibraryWatcher>,
+    /// Outcome of the last library action, shown in the library title until the next key.
+    library_notice: Option<String>,
+    /// Book closed on its last page; `y` on the main screen marks it finished.
+    finish_prompt: Option<String>,
+    /// Quit found that another instance saved meanwhile; `y` quits keeping this session's
+    /// settings, `n` quits keeping theirs.
+    conflict_prompt: bool,
+    /// Quit found unsaved edits and asks first (`Settings.confirm_quit`); Enter quits, Esc
+    /// stays.
+    quit_prompt: bool,
+    /// Books whose file was not found on the last check.
+    missing_paths: std::collections::HashSet<String>,
+    /// Books whose file failed the scan's PDF check or could not be parsed this session, with
+    /// the error.
+    broken_paths: std::collections::HashMap<String, String>,
+    /// Books sent to the render worker to tell their kind this session.
+    kinds_requested: std::collections::HashSet<String>,
+    relink_panel: RelinkPanel,
+    delete_panel: DeletePanel,
+    book_tag_picker: BookTagPicker,
+    /// On narrow screens, whether the details take the place of the library list.
+    details_pane: bool,
+    history_panel: HistoryPanel,
+    notes_search_panel: NotesSearchPanel,
+    duplicates_panel: DuplicatesPanel,
+    backups_panel: BackupsPanel,
+    key_bindings: KeyBindings,
+    thumbnail_strip: ThumbnailStrip,
+    cover_grid: CoverGrid,
+    reading_clock: ReadingClock,
+    redraw: Redraw,
+    /// Set by the redraw key; the next frame clears the terminal before drawing.
+    clear_terminal: bool,
+    /// Rows the last frame drew that a click can select. Filled in while drawing.
+    hit_regions: RefCell<HitRegions>,
+    /// Whether the terminal sends mouse events; follows `Settings.mouse_capture`.
+    mouse_captured: bool,
+    /// Terminal input and background wake-ups for the event loop, which takes the receiver
+    /// when it starts.
+    loop_messages: Sender<LoopMessage>,
+    loop_inbox: Option<Receiver<LoopMessage>>,
+    /// When the last terminal event arrived; a long quiet spell slows the loop's ticks.
+    last_input: Instant,
+}
+
+struct DuplicateScanState {
+    scan: DuplicateScan,
+    /// Books fingerprinted so far, of `total`.
+    done: usize,
+    total: usize,
+}
+
+struct LibraryScanState {
+    scan: LibraryScan,
+    files_seen: usize,
+    found: Vec<Book>,
+    /// Books given a fingerprint by this scan: new to the library, or added since
+    /// the last scan, so possibly a missing book at a new path.
+    fingerprinted: Vec<String>,
+    known_paths: std::collections::HashSet<String>,
+    /// Folders the walk could not read, as `(path, error)`.
+    skipped: Vec<(String, String)>,
+    /// Remove stored books a complete scan does not find, as asked from the scan panel.
+    prune_missing: bool,
 }
 
 impl Ui {
-    pub fn new(mut ctx: AppContext) -> Self {
+    /// Builds the UI over `ctx`. With `boot_reader` the book opens in the reader straight away
+    /// and the session ends when the reader closes. Otherwise, with `Settings.resume_last_book`,
+    /// the most recently read book reopens and closing it shows the library.
+    pub fn new(mut ctx: AppContext, boot_reader: Option<BootReader>) -> Self {
         ctx.settings.normalize();
         let settings_panel = SettingsPanel::default();
-        let scan_panel = ScanPathPanel::new(join_roots(&ctx.settings));
         let search_panel = SearchPanel::default();
         let label_manager_panel = LabelManagerPanel::default();
         let assign_labels_panel = AssignLabelsPanel::default();
@@ -81,35 +334,167 @@ impl Ui {
         let bookmarks_panel = BookmarksPanel::default();
         let notes_panel = NotesPanel::default();
         let toc_panel = TocPanel::default();
+        let links_panel = LinksPanel::default();
+        let search_in_book_panel = SearchInBookPanel::default();
         let reader = ReaderPanel::default();
         let meta_cache = BookMetaCache::default();
         let image_picker = Picker::halfblocks();
+        let (key_bindings, key_binding_problems) =
+            KeyBindings::from_overrides(&ctx.settings.key_bindings);
+        let library_notice = (!key_binding_problems.is_empty())
+            .then(|| format!("key bindings: {}", key_binding_problems.join("; ")));
+        let (loop_messages, loop_inbox) = mpsc::channel();
+        let mut page_render = PageRenderWorker::default();
+        page_render.set_wake(loop_wake(&loop_messages));
         let mut ui = Self {
             ctx,
             settings_panel,
-            scan_panel,
+            scan_panel: ScanPathPanel::default(),
             search_panel,
             label_manager_panel,
             assign_labels_panel,
             label_catalog_input_panel,
+            label_undo: LabelUndoStack::default(),
             goto_panel,
+            export_panel: ExportPanel::default(),
+            pdfium_setup: PdfiumSetupPanel::default(),
+            export: None,
             bookmarks_panel,
             notes_panel,
+            notes_sidebar: NotesSidebar::default(),
             toc_panel,
+            links_panel,
+            pdf_annotations_panel: PdfAnnotationsPanel::default(),
+            search_in_book_panel,
             reader,
             boot_reader_session: false,
             ignore_next_esc_quit: false,
             engine: Engine::new(),
+            page_render,
             image_picker,
-            spawned_kitties: Vec::new(),
+            spawned_terminals: Vec::new(),
             meta_cache,
+            size_by_path: std::collections::HashMap::new(),
+            marked_paths: std::collections::HashSet::new(),
+            progress_sink: None,
+            last_progress_flush: Instant::now(),
+            library_sink: None,
+            cleanup_sink: None,
+            settings_export_sink: None,
+            settings_import_sink: None,
+            backup_sink: None,
+            backup_list_sink: None,
+            restore_sink: None,
+            db_path: None,
+            session_sink: None,
+            autosave_sink: None,
+            last_autosave: Instant::now(),
+            saved_flash_until: None,
+            conflict_check: None,
+            library_scan: None,
+            duplicate_scan: None,
+            library_watcher: None,
+            library_notice,
+            finish_prompt: None,
+            conflict_prompt: false,
+            quit_prompt: false,
+            missing_paths: std::collections::HashSet::new(),
+            broken_paths: std::collections::HashMap::new(),
+            kinds_requested: std::collections::HashSet::new(),
+            relink_panel: RelinkPanel::default(),
+            delete_panel: DeletePanel::default(),
+            book_tag_picker: BookTagPicker::default(),
+            details_pane: false,
+            history_panel: HistoryPanel::default(),
+            notes_search_panel: NotesSearchPanel::default(),
+            duplicates_panel: DuplicatesPanel::default(),
+            backups_panel: BackupsPanel::default(),
+            key_bindings,
+            thumbnail_strip: ThumbnailStrip::default(),
+            cover_grid: CoverGrid::default(),
+            reading_clock: ReadingClock::default(),
+            redraw: Redraw::default(),
+            clear_terminal: false,
+            hit_regions: RefCell::default(),
+            mouse_captured: false,
+            loop_messages,
+            loop_inbox: Some(loop_inbox),
+            last_input: Instant::now(),
         };
-        ui.bootstrap_reader_from_env();
+        ui.refresh_size_cache();
+        // Filters restored from the last session may hide the selected book.
+        ui.normalize_selection_to_visible();
+        if let Some(boot_reader) = boot_reader {
+            ui.open_boot_reader(boot_reader);
+        } else if ui.ctx.settings.resume_last_book {
+            ui.resume_last_book();
+        }
+        if !ui.boot_reader_session {
+            ui.restart_library_watcher();
+        }
         ui
     }
 
+    pub fn with_progress_sink(mut self, sink: ProgressSink) -> Self {
+        self.progress_sink = Some(sink);
+        self
+    }
+
+    pub fn with_library_sink(mut self, sink: LibrarySink) -> Self {
+        self.library_sink = Some(sink);
+        self
+    }
+
+    pub fn with_cleanup_sink(mut self, sink: CleanupSink) -> Self {
+        self.cleanup_sink = Some(sink);
+        self
+    }
+
+    pub fn with_settings_file_sinks(
+        mut self,
+        export: SettingsExportSink,
+        import: SettingsImportSink,
+    ) -> Self {
+        self.settings_export_sink = Some(export);
+        self.settings_import_sink = Some(import);
+        self
+    }
+
+    pub fn with_backup_sinks(
+        mut self,
+        backup: BackupSink,
+        list: BackupListSink,
+        restore: RestoreSink,
+    ) -> Self {
+        self.backup_sink = Some(backup);
+        self.backup_list_sink = Some(list);
+        self.restore_sink = Some(restore);
+        self
+    }
+
+    pub fn with_db_path(mut self, db_path: impl Into<std::path::PathBuf>) -> Self {
+        self.db_path = Some(db_path.into());
+        self
+    }
+
+    pub fn with_session_sink(mut self, sink: SessionSink) -> Self {
+        self.session_sink = Some(sink);
+        self
+    }
+
+    pub fn with_autosave_sink(mut self, sink: AutosaveSink) -> Self {
+        self.autosave_sink = Some(sink);
+        self
+    }
+
+    pub fn with_conflict_check(mut self, check: ConflictCheck) -> Self {
+        self.conflict_check = Some(check);
+        self
+    }
+
     pub fn run(&mut self) -> anyhow::Result<UiOutcome> {
-        let mut terminal = setup_terminal()?;
+        let mut terminal = setup_terminal(self.ctx.settings.mouse_capture)?;
+        self.mouse_captured = self.ctx.settings.mouse_capture;
         image_protocol::ensure_tmux_allow_passthrough();
         self.image_picker = if image_protocol::should_query_stdio() {
             let options = QueryStdioOptions {
@@ -122,496 +507,1046 @@ impl Ui {
         };
         self.image_picker
             .set_background_color(image::Rgba([255u8, 255u8, 255u8, 255u8]));
-        image_protocol::prefer_kitty_if_supported(&mut self.image_picker);
+        image_protocol::prefer_graphics_protocol(&mut self.image_picker);
         terminal.clear().ok();
-        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            self.event_loop(&mut terminal)
-        }));
+        let result = self.run_guarded(|ui| ui.event_loop(&mut terminal));
+        self.label_undo.clear();
         let restore_result = restore_terminal(&mut terminal);
 
         match (result, restore_result) {
-            (Ok(Ok(outcome)), Ok(())) => {
+            (Ok(outcome), Ok(())) => {
                 if outcome.exit == UiExit::Quit {
-                    self.kill_spawned_kitties();
+                    self.kill_spawned_terminals();
                 }
                 Ok(outcome)
             }
-            (Ok(Ok(outcome)), Err(err)) => {
+            (Ok(outcome), Err(err)) => {
                 if outcome.exit == UiExit::Quit {
-                    self.kill_spawned_kitties();
+                    self.kill_spawned_terminals();
                 }
                 Err(err)
             }
-            (Ok(Err(err)), Ok(())) => Err(err),
-            (Ok(_), Err(err)) => Err(err),
-            (Err(panic), Ok(())) => Err(anyhow::anyhow!(panic_to_string(panic))),
-            (Err(panic), Err(err)) => Err(anyhow::anyhow!(
-                "{}\n(additionally failed to restore terminal: {err})",
-                panic_to_string(panic)
+            (Err(err), Ok(())) => Err(err),
+            (Err(err), Err(restore_err)) => Err(anyhow::anyhow!(
+                "{err:#}\n(additionally failed to restore terminal: {restore_err})"
+            )),
+        }
+    }
+
+    /// Runs `body` (normally the event loop). When it errors or panics, the reading position
+    /// is recorded and the session sink saves the unsaved changes before the error is returned;
+    /// the normal save path after `run` is never reached in that case.
+    pub fn run_guarded(
+        &mut self,
+        body: impl FnOnce(&mut Self) -> anyhow::Result<UiOutcome>,
+    ) -> anyhow::Result<UiOutcome> {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| body(self)))
+            .unwrap_or_else(|panic| Err(anyhow::anyhow!(panic_to_string(panic))));
+        let Err(err) = result else {
+            return result;
+        };
+
+        self.record_reader_progress();
+        self.record_reader_view_state();
+        self.flush_progress();
+        match self.session_sink.as_mut().map(|sink| sink(&mut self.ctx)) {
+            Some(Err(save_err)) => Err(anyhow::anyhow!(
+                "{err:#}\n(additionally failed to save the session: {save_err:#})"
             )),
+            _ => Err(err),
         }
     }
 
-    fn kill_spawned_kitties(&mut self) {
-        for mut child in self.spawned_kitties.drain(..) {
+    fn kill_spawned_terminals(&mut self) {
+        for mut child in self.spawned_terminals.drain(..) {
             let _ = child.kill();
             let _ = child.wait();
         }
     }
 
-    fn accent_color(&self) -> Color {
-        match self.ctx.settings.theme {
-            Theme::Light => Color::Blue,
-            Theme::Dark => Color::Yellow,
+    fn record_reader_progress(&mut self) {
+        if !self.reader.open {
+            return;
         }
+        let Some(path) = self.reader.book_path.clone() else {
+            return;
+        };
+        let progress = self.reader.progress();
+        if self.ctx.progress_by_path.get(&path) == Some(&progress) {
+            return;
+        }
+        self.ctx.progress_by_path.insert(path.clone(), progress);
+        self.ctx.dirty_progress_paths.insert(path);
     }
 
-    fn bootstrap_reader_from_env(&mut self) {
-        let boot = std::env::var("BOOKSHELF_BOOT_READER")
-            .ok()
-            .is_some_and(|v| !v.trim().is_empty() && v.trim() != "0");
-        if !boot {
+    fn record_reader_view_state(&mut self) {
+        if !self.reader.open {
             return;
         }
-        self.boot_reader_session = true;
-
-        let Some(path) = std::env::var("BOOKSHELF_BOOT_READER_PATH").ok() else {
+        let Some(path) = self.reader.book_path.clone() else {
             return;
         };
+        let state = self.reader.view_state();
+        if self.ctx.reader_state_by_path.get(&path) == Some(&state) {
+            return;
+        }
+        self.ctx.reader_state_by_path.insert(path.clone(), state);
+        self.ctx.dirty_reader_state_paths.insert(path);
+    }
 
-        let page_index = std::env::var("BOOKSHELF_BOOT_READER_PAGE_INDEX")
-            .ok()
-            .and_then(|s| s.trim().parse::<u32>().ok())
-            .unwrap_or(0);
+    /// Credits reading time for the span since the last tick. Library overlays opened over the
+    /// reader (settings, search, history) pause the clock; reader panels do not.
+    fn tick_reading_clock(&mut self) {
+        let book = self
+            .reader
+            .open
+            .then(|| self.reader.book_path.clone())
+            .flatten();
+        let counting = self.reader.open
+            && !(self.settings_panel.open
+                || self.label_catalog_input_panel.open
+                || self.search_panel.open
+                || self.history_panel.open
+                || self.duplicates_panel.open
+                || self.backups_panel.open);
+        if let Some((path, secs)) =
+            self.reading_clock
+                .tick(book.as_deref(), counting, Instant::now())
+        {
+            self.ctx.add_reading_time(&path, secs);
+        }
+    }
 
-        let mode = std::env::var("BOOKSHELF_BOOT_READER_MODE").ok();
-        if let Some(mode) = mode {
-            if mode.trim().eq_ignore_ascii_case("image") {
-                self.ctx.settings.reader_mode = ReaderMode::Image;
-            } else if mode.trim().eq_ignore_ascii_case("text") {
-                self.ctx.settings.reader_mode = ReaderMode::Text;
+    /// When unsaved changes remain `Settings.autosave_secs` after the last autosave, hands
+    /// them to the autosave sink. Changes it leaves for later go on the next pass of the loop.
+    /// Returns `true` when the footer changed.
+    fn autosave_if_due(&mut self) -> bool {
+        let now = Instant::now();
+        let mut redraw = false;
+        if self.saved_flash_until.is_some_and(|until| until <= now) {
+            self.saved_flash_until = None;
+            redraw = true;
+        }
+        if self.autosave_due_in(now) != Some(Duration::ZERO) {
+            return redraw;
+        }
+        let Some(sink) = self.autosave_sink.as_mut() else {
+            return redraw;
+        };
+        match sink(&mut self.ctx) {
+            Ok(_) => {
+                if !self.ctx.has_unsaved_changes() {
+                    self.last_autosave = now;
+                }
+                self.saved_flash_until = Some(now + SAVED_FLASH);
+            }
+            Err(err) => {
+                // Tried again after another interval.
+                self.last_autosave = now;
+                let notice = Some(format!("autosave failed: {err:#}"));
+                if self.reader.open {
+                    self.reader.notice = notice;
+                } else {
+                    self.library_notice = notice;
+                }
             }
         }
+        true
+    }
 
-        let book = self
-            .ctx
-            .books
-            .iter()
-            .find(|b| b.path == path)
-            .cloned()
-            .unwrap_or_else(|| {
-                let decoded = bookshelf_core::decode_path(&path);
-                let title = decoded
-                    .file_stem()
-                    .map(|s| s.to_string_lossy().to_string())
-                    .unwrap_or_else(|| "untitled".to_string());
-                bookshelf_core::Book {
-                    path: path.clone(),
-                    title,
-                    last_opened: None,
-                    favorite: false,
-                }
-            });
+    /// Writes every unsaved change through the autosave sink, without waiting for the
+    /// interval. Returns the notice to show.
+    fn save_now(&mut self) -> String {
+        let Some(sink) = self.autosave_sink.as_mut() else {
+            return "saving is not available here".to_string();
+        };
+        if !self.ctx.has_unsaved_changes() {
+            return "nothing to save".to_string();
+        }
+        let written = match save_all_changes(sink, &mut self.ctx) {
+            Ok(written) => written,
+            Err(err) => return format!("save failed: {err:#}"),
+        };
+        let now = Instant::now();
+        self.last_autosave = now;
+        self.saved_flash_until = Some(now + SAVED_FLASH);
+        if written == 1 {
+            "saved 1 change".to_string()
+        } else {
+            format!("saved {written} changes")
+        }
+    }
 
-        self.reader.open_book(&book, &self.ctx, &self.engine);
-        self.reader.page = page_index;
-        if let Some(total) = self.reader.total_pages
-            && total > 0
-        {
-            self.reader.page = self.reader.page.min(total - 1);
+    /// Quits, unless another instance saved meanwhile: then asks whose settings to keep.
+    fn quit(&mut self) -> Option<UiExit> {
+        let conflict = self
+            .conflict_check
+            .as_mut()
+            .is_some_and(|check| check(&self.ctx).unwrap_or(false));
+        if conflict {
+            self.library_notice = Some(
+                "another window saved this library; y: quit keeping these settings, \
+                 n: quit keeping theirs (tags, bookmarks and notes are merged)"
+                    .to_string(),
+            );
+            self.conflict_prompt = true;
+            return None;
         }
-        self.reader.invalidate_render();
+        Some(UiExit::Quit)
+    }
 
-        // Best effort: clear env so we don't re-bootstrap on subsequent UI restarts.
-        unsafe {
-            std::env::remove_var("BOOKSHELF_BOOT_READER");
-            std::env::remove_var("BOOKSHELF_BOOT_READER_PATH");
-            std::env::remove_var("BOOKSHELF_BOOT_READER_PAGE_INDEX");
-            std::env::remove_var("BOOKSHELF_BOOT_READER_MODE");
+    /// Time until the next autosave, or `None` when autosave is off or nothing is unsaved.
+    fn autosave_due_in(&self, now: Instant) -> Option<Duration> {
+        let interval = Duration::from_secs(u64::from(self.ctx.settings.autosave_secs));
+        if interval.is_zero() || self.autosave_sink.is_none() || !self.ctx.has_unsaved_changes() {
+            return None;
         }
+        Some(interval.saturating_sub(now.duration_since(self.last_autosave)))
     }
 
-    fn event_loop(
-        &mut self,
-        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-    ) -> anyhow::Result<UiOutcome> {
-        let tick_rate = Duration::from_millis(250);
-        let mut needs_redraw = true;
+    /// "saved" for the footer while an autosave is fresh.
+    fn saved_flash_span(&self) -> Option<Span<'static>> {
+        self.saved_flash_until.map(|_| {
+            Span::styled(
+                "saved  ",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            )
+        })
+    }
 
-        loop {
-            if needs_redraw {
-                terminal.draw(|frame| self.draw(frame.area(), frame))?;
-                needs_redraw = false;
-            }
+    fn flush_progress_if_due(&mut self) {
+        if self.ctx.dirty_progress_paths.is_empty()
+            || self.last_progress_flush.elapsed() < PROGRESS_FLUSH_INTERVAL
+        {
+            return;
+        }
+        self.flush_progress();
+    }
 
-            if !event::poll(tick_rate)? {
+    fn flush_progress(&mut self) {
+        let Some(sink) = self.progress_sink.as_mut() else {
+            return;
+        };
+        self.last_progress_flush = Instant::now();
+        let dirty = std::mem::take(&mut self.ctx.dirty_progress_paths);
+        for path in dirty {
+            let Some(progress) = self.ctx.progress_by_path.get(&path).copied() else {
+                continue;
+            };
+            let relink_pending = self
+                .ctx
+                .dirty_book_path_ops
+                .iter()
+                .any(|op| matches!(op, BookPathOp::Relink { to, .. } if *to == path));
+            if relink_pending {
+                // The stored row still has the old path until the relink is saved on exit.
+                self.ctx.dirty_progress_paths.insert(path);
                 continue;
             }
+            if let Err(err) = sink(&path, &progress) {
+                self.reader.notice = Some(format!("save progress failed: {err}"));
+                self.ctx.dirty_progress_paths.insert(path);
+            }
+        }
+    }
 
-            match event::read()? {
-                Event::Resize(_, _) => {
-                    needs_redraw = true;
-                }
-                Event::Key(key) => {
-                    if key.kind == KeyEventKind::Release {
-                        continue;
-                    }
+    fn start_library_scan(&mut self) {
+        if let Some(running) = self.library_scan.take() {
+            running.scan.cancel();
+        }
+        let cwd = std::path::PathBuf::from(&self.ctx.cwd);
+        let known_paths: std::collections::HashSet<String> =
+            self.ctx.books.iter().map(|b| b.path.clone()).collect();
+        let metadata = ScanMetadata {
+            known_paths: known_paths.clone(),
+            read: |book| Engine::new().document_metadata(book).ok(),
+            fingerprinted: self
+                .ctx
+                .fingerprints_by_path
+                .iter()
+                .filter(|(_, fingerprint)| fingerprint.tail_hash.is_some())
+                .map(|(path, _)| path.clone())
+                .collect(),
+        };
+        self.library_scan = Some(LibraryScanState {
+            scan: LibraryScan::start(
+                &self.ctx.settings,
+                &cwd,
+                Some(metadata),
+                Some(loop_wake(&self.loop_messages)),
+            ),
+            files_seen: 0,
+            found: Vec::new(),
+            fingerprinted: Vec::new(),
+            known_paths,
+            skipped: Vec::new(),
+            prune_missing: false,
+        });
+        self.library_notice = None;
+    }
 
-                    needs_redraw = true;
+    /// Watches the current roots and scope, or stops watching when the setting is off.
+    fn restart_library_watcher(&mut self) {
+        self.library_watcher = None;
+        if !self.ctx.settings.watch_library {
+            return;
+        }
+        let cwd = std::path::PathBuf::from(&self.ctx.cwd);
+        match LibraryWatcher::start(
+            &self.ctx.settings,
+            &cwd,
+            Some(loop_wake(&self.loop_messages)),
+        ) {
+            Ok(watcher) => self.library_watcher = Some(watcher),
+            Err(err) => self.library_notice = Some(format!("watch library failed: {err:#}")),
+        }
+    }
 
-                    if self.settings_panel.open {
-                        if let Some(exit) = self.handle_settings_panel_key(key)? {
-                            return Ok(UiOutcome {
-                                ctx: self.ctx.clone(),
-                                exit,
-                            });
-                        }
-                    } else if self.label_catalog_input_panel.open {
-                        if let Some(exit) = self.handle_label_catalog_input_panel_key(key)? {
-                            return Ok(UiOutcome {
-                                ctx: self.ctx.clone(),
-                                exit,
-                            });
-                        }
-                    } else if self.search_panel.open {
-                        if let Some(exit) = self.handle_search_panel_key(key)? {
-                            return Ok(UiOutcome {
-                                ctx: self.ctx.clone(),
-                                exit,
-                            });
-                        }
-                    } else if self.reader.open && self.bookmarks_panel.open {
-                        if let Some(exit) = self.handle_bookmarks_panel_key(key)? {
-                            return Ok(UiOutcome {
-                                ctx: self.ctx.clone(),
-                                exit,
-                            });
-                        }
-                    } else if self.reader.open && self.goto_panel.open {
-                        if let Some(exit) = self.handle_goto_panel_key(key)? {
-                            return Ok(UiOutcome {
-                                ctx: self.ctx.clone(),
-                                exit,
-                            });
-                        }
-                    } else if self.reader.open && self.toc_panel.open {
-                        if let Some(exit) = self.handle_toc_panel_key(key)? {
-                            return Ok(UiOutcome {
-                                ctx: self.ctx.clone(),
-                                exit,
-                            });
-                        }
-                    } else if self.reader.open && self.notes_panel.open {
-                        if let Some(exit) = self.handle_notes_panel_key(key)? {
-                            return Ok(UiOutcome {
-                                ctx: self.ctx.clone(),
-                                exit,
-                            });
-                        }
-                    } else if self.reader.open {
-                        if let Some(exit) = self.handle_reader_key(key)? {
-                            return Ok(UiOutcome {
-                                ctx: self.ctx.clone(),
-                                exit,
-                            });
-                        }
-                    } else if self.scan_panel.open {
-                        if let Some(exit) = self.handle_scan_panel_key(key)? {
-                            return Ok(UiOutcome {
-                                ctx: self.ctx.clone(),
-                                exit,
-                            });
+    /// Rescans once a burst of file changes under the roots has settled. Returns `true` when a
+    /// scan was started.
+    fn poll_library_watcher(&mut self) -> bool {
+        let Some(watcher) = self.library_watcher.as_mut() else {
+            return false;
+        };
+        if !watcher.poll(Instant::now()) {
+            return false;
+        }
+        self.start_library_scan();
+        true
+    }
+
+    /// Applies pending scan events. Returns `true` when the library changed.
+    fn poll_library_scan(&mut self) -> bool {
+        let Some(state) = self.library_scan.as_mut() else {
+            return false;
+        };
+        let mut changed = false;
+        let mut finished = None;
+        while let Some(event) = state.scan.try_recv() {
+            changed = true;
+            match event {
+                ScanEvent::Batch {
+                    books,
+                    fingerprints,
+                    broken,
+                    skipped,
+                    files_seen,
+                } => {
+                    state.files_seen = files_seen;
+                    state.skipped.extend(skipped);
+                    for (path, fingerprint) in fingerprints {
+                        self.ctx.set_fingerprint(&path, fingerprint);
+                        state.fingerprinted.push(path);
+                    }
+                    for book in &books {
+                        self.broken_paths.remove(&book.path);
+                    }
+                    self.broken_paths.extend(broken);
+                    for book in books {
+                        if state.known_paths.insert(book.path.clone()) {
+                            self.ctx.record_book_added(&book.path, unix_now_secs());
+                            self.ctx.books.push(book.clone());
                         }
-                    } else if let Some(exit) = self.handle_main_key(key)? {
-                        return Ok(UiOutcome {
-                            ctx: self.ctx.clone(),
-                            exit,
-                        });
+                        state.found.push(book);
                     }
                 }
-                _ => {}
+                ScanEvent::Finished { files_seen, error } => {
+                    state.files_seen = files_seen;
+                    finished = Some(error);
+                    break;
+                }
             }
         }
-    }
 
-    fn handle_main_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        match key.code {
-            KeyCode::Esc => {
-                if self.boot_reader_session && self.ignore_next_esc_quit {
-                    self.ignore_next_esc_quit = false;
-                    return Ok(None);
+        if let Some(error) = finished
+            && let Some(state) = self.library_scan.take()
+        {
+            self.finish_library_scan(state, error);
+        }
+        if changed {
+            self.refresh_size_cache();
+            self.normalize_selection_to_visible();
+        }
+        changed
+    }
+
+    fn finish_library_scan(&mut self, mut state: LibraryScanState, error: Option<String>) {
+        // Workers finish books in any order; the library takes them in path order.
+        state.found.sort_by(|a, b| a.path.cmp(&b.path));
+        state.fingerprinted.sort();
+        let cancelled = state.scan.is_cancelled();
+        // Books in a folder the walk could not read are not missing.
+        let complete = error.is_none() && !cancelled && state.skipped.is_empty();
+        for book in &state.found {
+            self.missing_paths.remove(&book.path);
+        }
+        if complete {
+            // Books outside the scan are kept (they may be relinked) but checked on disk.
+            let found: std::collections::HashSet<&str> =
+                state.found.iter().map(|b| b.path.as_str()).collect();
+            for book in &self.ctx.books {
+                if !found.contains(book.path.as_str()) && !book_file_exists(&book.path) {
+                    self.missing_paths.insert(book.path.clone());
                 }
-                Ok(Some(UiExit::Quit))
-            }
-            KeyCode::Char('/') => {
-                self.open_filters_panel();
-                Ok(None)
             }
-            KeyCode::Char('l') => {
-                self.open_labels_panel();
-                Ok(None)
-            }
-            KeyCode::Char('c') => {
-                self.open_catalog_panel();
-                Ok(None)
-            }
-            KeyCode::Char('s') => {
-                self.settings_panel.open = true;
-                self.settings_panel.selected = 0;
-                Ok(None)
-            }
-            KeyCode::Enter => {
-                if let Some(idx) = self.selected_visible_index() {
-                    let opened_at = unix_now_secs();
-                    if let Some(book) = self.ctx.books.get_mut(idx) {
-                        book.last_opened = Some(opened_at);
-                        self.ctx
-                            .opened_at_by_path
-                            .insert(book.path.clone(), opened_at);
-                        let book = book.clone();
-                        self.reader.open_book(&book, &self.ctx, &self.engine);
+        }
+        let moved = if complete {
+            self.ctx.record_complete_scan(unix_now_secs());
+            self.relink_moved_books(&state.fingerprinted)
+        } else {
+            Vec::new()
+        };
+
+        let merged = self
+            .library_sink
+            .as_mut()
+            .map(|sink| sink(&state.found, complete));
+        let report = match merged {
+            Some(Ok(merge)) => {
+                let report = self
+                    .ctx
+                    .apply_scan_merge(&merge, complete && state.prune_missing);
+                if complete && state.prune_missing {
+                    for path in &merge.missing {
+                        self.missing_paths.remove(path);
+                        self.marked_paths.remove(path);
+                        self.size_by_path.remove(path);
                     }
+                    self.normalize_selection_to_visible();
                 }
-                Ok(None)
+                Some(report)
             }
-            KeyCode::Down => {
-                self.select_next_visible();
-                Ok(None)
+            Some(Err(err)) => {
+                self.library_notice = Some(format!("save library failed: {err}"));
+                return;
             }
-            KeyCode::Up => {
-                self.select_prev_visible();
-                Ok(None)
+            None => None,
+        };
+
+        let mut notice = match (&error, complete, report) {
+            (Some(err), _, _) => format!("scan failed: {err}"),
+            (None, true, Some(report)) => {
+                format!(
+                    "scan done: {}",
+                    scan_report_notice(&report, state.prune_missing)
+                )
             }
-            _ => Ok(None),
+            (None, true, None) => format!("scan done: {} books", state.found.len()),
+            (None, false, _) if cancelled => {
+                format!("scan cancelled: {} books found", state.found.len())
+            }
+            (None, false, _) => format!("scan done: {} books", state.found.len()),
+        };
+        if !state.skipped.is_empty() {
+            notice = format!("{notice}; {}", skipped_folders_notice(&state.skipped));
         }
+        if !moved.is_empty() {
+            notice = format!("{notice}; {}", moved_books_notice(&moved));
+        }
+        self.library_notice = Some(notice);
     }
 
-    fn open_filters_panel(&mut self) {
-        self.open_search_panel_mode(SearchPanelMode::Filters);
-        self.search_panel.focus = SearchFocus::Query;
+    /// Relinks missing books to files this scan `fingerprinted` with the same content.
+    fn relink_moved_books(&mut self, fingerprinted: &[String]) -> Vec<MovedBook> {
+        let mut missing: Vec<String> = self.missing_paths.iter().cloned().collect();
+        missing.sort();
+        let moved = self.ctx.relink_moved_books(&missing, fingerprinted);
+        for book in &moved {
+            self.missing_paths.remove(&book.from);
+            if self.marked_paths.remove(&book.from) {
+                self.marked_paths.insert(book.to.clone());
+            }
+            self.size_by_path.remove(&book.from);
+            self.size_by_path.remove(&book.to);
+        }
+        if !moved.is_empty() {
+            self.meta_cache = BookMetaCache::default();
+        }
+        moved
     }
 
-    fn open_labels_panel(&mut self) {
-        self.open_search_panel_mode(SearchPanelMode::Labels);
+    fn accent_color(&self) -> Color {
+        match self.ctx.settings.theme {
+            Theme::Light => Color::Blue,
+            Theme::Dark => Color::Yellow,
+        }
     }
 
-    fn open_catalog_panel(&mut self) {
-        self.open_search_panel_mode(SearchPanelMode::Catalog);
+    /// Opens the head of the reading history at its saved page. Books that left the library
+    /// or whose file is missing are skipped silently; the library shows as usual.
+    fn resume_last_book(&mut self) {
+        let Some(idx) = self.ctx.recent_paths.first().and_then(|path| {
+            self.ctx.books.iter().position(|book| {
+                &book.path == path
+                    && !self.broken_paths.contains_key(path)
+                    && book_file_exists(&book.path)
+            })
+        }) else {
+            return;
+        };
+        self.ctx.selected = idx;
+        let book = self.ctx.books[idx].clone();
+        self.reader
+            .open_book(&book, &mut self.ctx, &self.engine, &mut self.image_picker);
     }
 
-    fn open_search_panel_mode(&mut self, mode: SearchPanelMode) {
-        self.search_panel.open = true;
-        self.search_panel.mode = mode;
-        self.search_panel.tab = SearchTab::Search;
-        self.search_panel.focus = SearchFocus::Query;
-        self.search_panel.collection_cursor = 0;
-        self.search_panel.tag_cursor = 0;
-        self.reset_search_overlay_state();
-        self.search_panel.snapshot = Some(SearchSnapshot {
-            library_query: self.ctx.library_query.clone(),
-            favorites_only: self.ctx.favorites_only,
-            collection_filter: self.ctx.collection_filter.clone(),
-            tag_filters: self.ctx.tag_filters.clone(),
-            tag_match_mode: self.ctx.tag_match_mode,
-            selected_path: self
-                .ctx
-                .books
-                .get(self.ctx.selected)
-                .map(|b| b.path.clone()),
-        });
-        self.label_catalog_input_panel.open = false;
-        self.settings_panel.open = false;
-        self.scan_panel.open = false;
-        self.sync_search_panel_tab();
-        self.normalize_selection_to_visible();
-    }
+    fn open_boot_reader(&mut self, boot_reader: BootReader) {
+        self.boot_reader_session = true;
+        let BootReader {
+            path,
+            page_index,
+            mode,
+        } = boot_reader;
 
-    fn reset_search_overlay_state(&mut self) {
-        self.label_manager_panel.confirm_delete = None;
-        self.label_manager_panel.error = None;
-        self.label_manager_panel.filter_editing = false;
-        self.assign_labels_panel.query_editing = false;
-        self.assign_labels_panel.error = None;
-        self.assign_labels_panel.book_path = None;
+        let book = book_for_path(&self.ctx.books, &path);
+        self.reader
+            .open_book(&book, &mut self.ctx, &self.engine, &mut self.image_picker);
+        if let Some(mode) = mode {
+            self.reader.mode = mode;
+        }
+        self.reader.page = page_index;
+        if let Some(total) = self.reader.total_pages
+            && total > 0
+        {
+            self.reader.page = self.reader.page.min(total - 1);
+        }
+        self.reader.invalidate_render();
     }
 
-    fn close_search_panel(&mut self) {
-        self.search_panel.open = false;
-        self.search_panel.snapshot = None;
-        self.reset_search_overlay_state();
-    }
+    fn event_loop(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    ) -> anyhow::Result<UiOutcome> {
+        let mut events = EventQueue::default();
+        let Some(inbox) = self.loop_inbox.take() else {
+            anyhow::bail!("the event loop already ran");
+        };
+        let mut messages = ChannelEvents::new(inbox);
+        // Stops reading the terminal once the loop returns.
+        let _input = InputThread::spawn(self.loop_messages.clone())?;
 
-    fn cancel_search_panel(&mut self) {
-        if let Some(snapshot) = self.search_panel.snapshot.take() {
-            self.ctx.library_query = snapshot.library_query;
-            self.ctx.favorites_only = snapshot.favorites_only;
-            self.ctx.collection_filter = snapshot.collection_filter;
-            self.ctx.tag_filters = snapshot.tag_filters;
-            self.ctx.tag_match_mode = snapshot.tag_match_mode;
+        loop {
+            self.tick_reading_clock();
+            if self.mouse_captured != self.ctx.settings.mouse_capture {
+                self.mouse_captured = self.ctx.settings.mouse_capture;
+                if self.mouse_captured {
+                    crossterm::execute!(terminal.backend_mut(), EnableMouseCapture)?;
+                } else {
+                    crossterm::execute!(terminal.backend_mut(), DisableMouseCapture)?;
+                }
+            }
+            if self.redraw.due(Instant::now()) {
+                if std::mem::take(&mut self.clear_terminal) {
+                    terminal.clear()?;
+                }
+                terminal.draw(|frame| self.draw(frame.area(), frame))?;
+                self.redraw.drew();
+            }
 
-            if let Some(path) = snapshot.selected_path {
-                if let Some(idx) = self.ctx.books.iter().position(|b| b.path == path) {
-                    self.ctx.selected = idx;
+            if self.poll_library_watcher() {
+                self.redraw.mark();
+            }
+            if self.poll_library_scan() {
+                self.redraw.mark();
+            }
+            if self.poll_duplicate_scan() {
+                self.redraw.mark();
+            }
+            if self.autosave_if_due() {
+                self.redraw.mark();
+            }
+            while let Some(done) = self.page_render.try_recv() {
+                let redraw = match done {
+                    RenderDone::Page(done) => self.accept_page_image(done),
+                    RenderDone::Thumbnail(done) => self.accept_thumbnail(done),
+                    RenderDone::Cover(done) => self.accept_cover(done),
+                    RenderDone::GridCover(done) => self.accept_grid_cover(done),
+                    RenderDone::PageCount(done) => self.accept_page_count(done),
+                    RenderDone::ExportPage(done) => self.accept_export_page(done),
+                };
+                if redraw {
+                    self.redraw.mark();
                 }
             }
-            self.normalize_selection_to_visible();
-        }
-        self.search_panel.open = false;
-        self.reset_search_overlay_state();
-    }
+            if self.redraw.due(Instant::now()) {
+                continue;
+            }
 
-    fn handle_search_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        if key.code == KeyCode::Esc {
-            if self.search_panel.tab == SearchTab::Assign && self.assign_labels_panel.query_editing
+            let searching = self.search_in_book_panel.running;
+            if searching {
+                self.step_search_in_book();
+                self.redraw.mark();
+            }
+
+            // Background work wakes the loop when it has results, so waiting on it needs no
+            // faster tick; only an idle loop with nothing running slows down.
+            let now = Instant::now();
+            let tick_rate = Duration::from_millis(u64::from(self.ctx.settings.tick_rate_ms));
+            let mut poll_timeout = if searching {
+                Duration::ZERO
+            } else if self.reader.pending_page_image.is_some()
+                || !self.thumbnail_strip.pending.is_empty()
+                || self.cover_grid.is_waiting()
+                || self.library_scan.is_some()
+                || self.duplicate_scan.is_some()
+                || self.export.is_some()
             {
-                self.assign_labels_panel.query_editing = false;
-                return Ok(None);
+                tick_rate
+            } else {
+                idle_wait(tick_rate, now.duration_since(self.last_input))
+            };
+            if let Some(settles_at) = self
+                .library_watcher
+                .as_ref()
+                .and_then(LibraryWatcher::settles_at)
+            {
+                poll_timeout = poll_timeout.min(settles_at.saturating_duration_since(now));
+            }
+            if let Some(due_in) = self.autosave_due_in(now) {
+                poll_timeout = poll_timeout.min(due_in);
             }
+            if let Some(until) = self.saved_flash_until {
+                poll_timeout = poll_timeout.min(until.saturating_duration_since(now));
+            }
+            let poll_timeout = self.redraw.poll_timeout(now, poll_timeout);
+            let Some(event) = events.next(&mut messages, poll_timeout)? else {
+                self.flush_progress_if_due();
+                continue;
+            };
+            self.last_input = Instant::now();
 
-            if self.search_panel.tab == SearchTab::Manage
-                && (self.label_manager_panel.confirm_delete.is_some()
-                    || self.label_manager_panel.filter_editing)
+            let mut batch = vec![event];
+            if let Event::Key(key) = &batch[0]
+                && key.kind != KeyEventKind::Release
+                && self.is_page_flip(key)
             {
-                return self.handle_label_manager_panel_key(key);
+                let flips = events.queued_flips(&mut messages, |key| self.is_page_flip(key))?;
+                batch.extend(flips.into_iter().map(Event::Key));
+            }
+            for event in batch {
+                if let Some(exit) = self.handle_event(event)? {
+                    return Ok(UiOutcome {
+                        ctx: self.ctx.clone(),
+                        exit,
+                    });
+                }
             }
 
-            self.cancel_search_panel();
-            return Ok(None);
+            self.record_reader_progress();
+            self.record_reader_view_state();
+            self.flush_progress_if_due();
         }
+    }
 
-        if key.code == KeyCode::Enter {
-            if self.search_panel.tab == SearchTab::Assign && self.assign_labels_panel.query_editing
-            {
-                self.assign_labels_panel.query_editing = false;
-                return Ok(None);
-            }
+    /// Reader page turns; a run of them is handled before the next frame is drawn.
+    fn is_page_flip(&self, key: &KeyEvent) -> bool {
+        self.reader.open
+            && matches!(
+                self.key_bindings.action(KeyScope::Reader, key),
+                Some(KeyAction::PrevPage | KeyAction::NextPage)
+            )
+    }
 
-            if self.search_panel.tab == SearchTab::Manage && self.label_manager_panel.filter_editing
-            {
-                self.label_manager_panel.filter_editing = false;
-                return Ok(None);
+    /// Handles one terminal event. The event loop reads events and draws frames around this;
+    /// tests call it directly.
+    fn handle_event(&mut self, event: Event) -> anyhow::Result<Option<UiExit>> {
+        match event {
+            Event::Resize(_, _) => {
+                self.redraw.resized(Instant::now());
+            }
+            // tmux drops images placed through passthrough when its pane is hidden.
+            Event::FocusGained if image_protocol::in_tmux() && self.retransmit_reader_image() => {
+                self.redraw.mark();
+            }
+            Event::Key(key) if key.kind != KeyEventKind::Release => {
+                self.tick_reading_clock();
+                self.reading_clock.input(Instant::now());
+                return self.handle_key_event(key);
+            }
+            Event::Mouse(mouse) => return self.handle_mouse_event(mouse),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    /// Routes a key to whatever has focus. The next frame is drawn unless the handler reports
+    /// that the key changed nothing.
+    fn handle_key_event(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        self.redraw.begin_key();
+        let exit = if self.settings_panel.open {
+            self.handle_settings_panel_key(key)?
+        } else if self.label_catalog_input_panel.open {
+            self.handle_label_catalog_input_panel_key(key)?
+        } else if self.search_panel.open {
+            self.handle_search_panel_key(key)?
+        } else if self.history_panel.open {
+            self.handle_history_panel_key(key)?
+        } else if self.notes_search_panel.open {
+            self.handle_notes_search_panel_key(key)?
+        } else if self.duplicates_panel.open {
+            self.handle_duplicates_panel_key(key)?
+        } else if self.backups_panel.open {
+            self.handle_backups_panel_key(key)?
+        } else if self.reader.open && self.pdfium_setup.open {
+            self.handle_pdfium_setup_key(key)?
+        } else if self.reader.open && self.bookmarks_panel.open {
+            self.handle_bookmarks_panel_key(key)?
+        } else if self.reader.open && self.goto_panel.open {
+            self.handle_goto_panel_key(key)?
+        } else if self.reader.open && self.export_panel.open {
+            self.handle_export_panel_key(key)?
+        } else if self.reader.open && self.search_in_book_panel.open {
+            self.handle_search_in_book_panel_key(key)?
+        } else if self.reader.open && self.toc_panel.open {
+            self.handle_toc_panel_key(key)?
+        } else if self.reader.open && self.links_panel.open {
+            self.handle_links_panel_key(key)?
+        } else if self.reader.open && self.pdf_annotations_panel.open {
+            self.handle_pdf_annotations_panel_key(key)?
+        } else if self.reader.open && self.notes_panel.open {
+            self.handle_notes_panel_key(key)?
+        } else if self.reader.open && self.notes_sidebar_takes(&key) {
+            self.handle_notes_sidebar_key(key)?
+        } else if self.reader.open && self.thumbnail_strip_visible() {
+            self.handle_thumbnail_strip_key(key)?
+        } else if self.reader.open {
+            self.handle_reader_key(key)?
+        } else if self.relink_panel.open {
+            self.handle_relink_panel_key(key)?
+        } else if self.delete_panel.open {
+            self.handle_delete_panel_key(key)?
+        } else if self.book_tag_picker.open {
+            self.handle_book_tag_picker_key(key)?
+        } else if self.scan_panel.open {
+            self.handle_scan_panel_key(key)?
+        } else {
+            self.handle_main_key(key)?
+        };
+        self.redraw.end_key();
+        Ok(exit)
+    }
+
+    /// The wheel presses the scroll keys; a left click selects the row the last frame drew
+    /// under the pointer. Other mouse events, motion included, change nothing.
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) -> anyhow::Result<Option<UiExit>> {
+        match mouse.kind {
+            MouseEventKind::ScrollDown => self.handle_mouse_scroll(true),
+            MouseEventKind::ScrollUp => self.handle_mouse_scroll(false),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(target) = self.hit_regions.get_mut().hit(mouse.column, mouse.row) else {
+                    return Ok(None);
+                };
+                self.redraw.begin_key();
+                let exit = self.handle_click(target)?;
+                self.redraw.end_key();
+                Ok(exit)
             }
+            _ => Ok(None),
+        }
+    }
 
-            match self.search_panel.tab {
-                SearchTab::Search => {
-                    self.close_search_panel();
-                }
-                SearchTab::Assign => {
-                    self.commit_assign_labels_panel();
-                    self.close_search_panel();
-                }
-                SearchTab::Manage => {
-                    if self.label_manager_panel.confirm_delete.is_some() {
-                        return self.handle_label_manager_panel_key(key);
-                    }
-                    self.close_search_panel();
+    /// Scrolls the reader page (or pans the image) and moves the library selection through
+    /// the bound keys. Popups get the arrow keys when those are what is bound.
+    fn handle_mouse_scroll(&mut self, down: bool) -> anyhow::Result<Option<UiExit>> {
+        let (scope, action) = match (self.reader.open, down) {
+            (true, true) => (KeyScope::Reader, KeyAction::ScrollDown),
+            (true, false) => (KeyScope::Reader, KeyAction::ScrollUp),
+            (false, true) => (KeyScope::Main, KeyAction::NextItem),
+            (false, false) => (KeyScope::Main, KeyAction::PrevItem),
+        };
+        let arrow = KeyEvent::from(if down { KeyCode::Down } else { KeyCode::Up });
+        let key = if self.key_bindings.action(scope, &arrow) == Some(action) {
+            arrow
+        } else {
+            self.key_bindings.key_event(action).unwrap_or(arrow)
+        };
+        self.handle_key_event(key)
+    }
+
+    /// A click selects the row; in a popup it also jumps, like Enter. A double click on a
+    /// library row opens the book.
+    fn handle_click(&mut self, target: HitTarget) -> anyhow::Result<Option<UiExit>> {
+        let double = self.hit_regions.get_mut().click(target, Instant::now());
+        let enter = KeyEvent::from(KeyCode::Enter);
+        match target {
+            HitTarget::Library(idx) => {
+                self.library_notice = None;
+                self.ctx.selected = idx;
+                if double {
+                    self.open_selected_book();
                 }
+                Ok(None)
+            }
+            HitTarget::Toc(pos) => {
+                self.toc_panel.selected = pos;
+                self.handle_toc_panel_key(enter)
+            }
+            HitTarget::Bookmark(pos) => {
+                self.bookmarks_panel.selected = pos;
+                self.handle_bookmarks_panel_key(enter)
+            }
+            HitTarget::Note(pos) => {
+                self.notes_panel.selected = pos;
+                self.handle_notes_panel_key(enter)
             }
-            return Ok(None);
-        }
-
-        match self.search_panel.tab {
-            SearchTab::Search => self.handle_search_filters_tab_key(key),
-            SearchTab::Assign => self.handle_assign_labels_panel_key(key),
-            SearchTab::Manage => self.handle_label_manager_panel_key(key),
         }
     }
 
-    fn handle_search_filters_tab_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        if key.modifiers.contains(KeyModifiers::CONTROL)
-            && let KeyCode::Char('u') = key.code
+    fn handle_main_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        let notice_cleared = self.library_notice.take().is_some();
+        if let Some(path) = self.finish_prompt.take()
+            && key.code == KeyCode::Char('y')
+            && key.modifiers.is_empty()
         {
-            self.ctx.library_query.clear();
-            self.ctx.favorites_only = false;
-            self.ctx.collection_filter = CollectionFilter::Any;
-            self.ctx.tag_filters.clear();
-            self.ctx.tag_match_mode = TagMatchMode::Or;
+            if self.ctx.set_book_status(&path, ReadingStatus::Finished)
+                && let Some(book) = self.ctx.books.iter().find(|b| b.path == path)
+            {
+                self.library_notice = Some(format!("marked {} finished", book.title));
+            }
             self.normalize_selection_to_visible();
             return Ok(None);
         }
-
-        match key.code {
-            KeyCode::Tab => {
-                self.search_panel.focus = self.search_panel.focus.next();
-                Ok(None)
+        // Any key but Enter dismisses the quit prompt without acting on it.
+        if std::mem::take(&mut self.quit_prompt) {
+            if key.code == KeyCode::Enter && key.modifiers.is_empty() {
+                return Ok(self.quit());
             }
-            KeyCode::BackTab => {
-                self.search_panel.focus = self.search_panel.focus.prev();
-                Ok(None)
+            return Ok(None);
+        }
+        if std::mem::take(&mut self.conflict_prompt) && key.modifiers.is_empty() {
+            match key.code {
+                KeyCode::Char('y') => return Ok(Some(UiExit::Quit)),
+                KeyCode::Char('n') => {
+                    self.ctx.keep_stored_settings = true;
+                    return Ok(Some(UiExit::Quit));
+                }
+                _ => {}
             }
-            KeyCode::Left => {
-                if self.ctx.tag_match_mode == TagMatchMode::And {
-                    self.ctx.tag_match_mode = TagMatchMode::Or;
+        }
+        let Some(action) = self.key_bindings.action(KeyScope::Main, &key) else {
+            if !notice_cleared {
+                self.redraw.ignore_key();
+            }
+            return Ok(None);
+        };
+        match action {
+            KeyAction::Quit => {
+                if self.boot_reader_session && self.ignore_next_esc_quit {
+                    self.ignore_next_esc_quit = false;
+                    return Ok(None);
+                }
+                if let Some(state) = &self.library_scan {
+                    state.scan.cancel();
+                    return Ok(None);
+                }
+                if let Some(state) = &self.duplicate_scan {
+                    state.scan.cancel();
+                    return Ok(None);
+                }
+                if !self.marked_paths.is_empty() {
+                    self.marked_paths.clear();
+                    return Ok(None);
+                }
+                if self.ctx.settings.confirm_quit && self.ctx.has_unsaved_edits() {
+                    self.quit_prompt = true;
+                    return Ok(None);
                 }
+                Ok(self.quit())
+            }
+            KeyAction::SaveNow => {
+                self.library_notice = Some(self.save_now());
                 Ok(None)
             }
-            KeyCode::Right => {
-                if self.ctx.tag_match_mode == TagMatchMode::Or {
-                    self.ctx.tag_match_mode = TagMatchMode::And;
+            KeyAction::ToggleMark => {
+                if let Some(path) = self.selected_book_path()
+                    && !self.marked_paths.remove(&path)
+                {
+                    self.marked_paths.insert(path);
                 }
                 Ok(None)
             }
-            KeyCode::Up => {
-                match self.search_panel.focus {
-                    SearchFocus::Query => {}
-                    SearchFocus::Collections => {
-                        self.search_panel.collection_cursor =
-                            self.search_panel.collection_cursor.saturating_sub(1);
-                    }
-                    SearchFocus::Tags => {
-                        self.search_panel.tag_cursor =
-                            self.search_panel.tag_cursor.saturating_sub(1);
+            KeyAction::MarkAllVisible => {
+                let visible_paths: Vec<String> = self
+                    .visible_indices()
+                    .into_iter()
+                    .filter_map(|idx| self.ctx.books.get(idx).map(|b| b.path.clone()))
+                    .collect();
+                if visible_paths
+                    .iter()
+                    .all(|path| self.marked_paths.contains(path))
+                {
+                    for path in &visible_paths {
+                        self.marked_paths.remove(path);
                     }
+                } else {
+                    self.marked_paths.extend(visible_paths);
                 }
                 Ok(None)
             }
-            KeyCode::Down => {
-                match self.search_panel.focus {
-                    SearchFocus::Query => {}
-                    SearchFocus::Collections => {
-                        self.search_panel.collection_cursor =
-                            self.search_panel.collection_cursor.saturating_add(1);
-                    }
-                    SearchFocus::Tags => {
-                        self.search_panel.tag_cursor =
-                            self.search_panel.tag_cursor.saturating_add(1);
-                    }
+            KeyAction::ToggleFavorite => {
+                if let Some(idx) = self.selected_visible_index()
+                    && let Some(book) = self.ctx.books.get_mut(idx)
+                {
+                    book.favorite = !book.favorite;
+                    self.ctx.dirty_favorite_paths.insert(book.path.clone());
+                    self.normalize_selection_to_visible();
                 }
                 Ok(None)
             }
-            KeyCode::Char('f') => {
-                self.ctx.favorites_only = !self.ctx.favorites_only;
-                self.normalize_selection_to_visible();
+            KeyAction::ToggleArchived => {
+                if let Some(idx) = self.selected_visible_index()
+                    && let Some(book) = self.ctx.books.get(idx)
+                {
+                    let path = book.path.clone();
+                    let title = book.title.clone();
+                    let archived = !book.archived;
+                    self.ctx.set_book_archived(&path, archived);
+                    self.library_notice = Some(if archived {
+                        format!("archived: {title}")
+                    } else {
+                        format!("restored: {title}")
+                    });
+                    self.normalize_selection_to_visible();
+                }
                 Ok(None)
             }
-            KeyCode::Char(' ') => {
-                match self.search_panel.focus {
-                    SearchFocus::Query => {
-                        self.ctx.library_query.push(' ');
-                        self.normalize_selection_to_visible();
-                    }
-                    SearchFocus::Collections => {
-                        self.apply_collection_cursor();
-                        self.normalize_selection_to_visible();
-                    }
-                    SearchFocus::Tags => {
-                        self.toggle_tag_cursor();
-                        self.normalize_selection_to_visible();
-                    }
+            KeyAction::CycleStatus => {
+                if let Some(idx) = self.selected_visible_index()
+                    && let Some(book) = self.ctx.books.get(idx)
+                {
+                    let path = book.path.clone();
+                    let status = book.status.next();
+                    self.ctx.set_book_status(&path, status);
+                    self.library_notice = Some(format!("status: {status}"));
+                    self.normalize_selection_to_visible();
                 }
                 Ok(None)
             }
-            KeyCode::Backspace => {
-                if self.search_panel.focus == SearchFocus::Query {
-                    self.ctx.library_query.pop();
-                    self.normalize_selection_to_visible();
+            KeyAction::MoveBookUp | KeyAction::MoveBookDown => {
+                let Some(collection) = self.collection_view_name().map(str::to_string) else {
+                    self.library_notice = Some(format!(
+                        "pick a collection and turn on its reading list (Ctrl+r in {}) to reorder",
+                        self.key_bindings.label(KeyAction::OpenFilters)
+                    ));
+                    return Ok(None);
+                };
+                if let Some(path) = self.selected_book_path()
+                    && !self
+                        .ctx
+                        .move_in_collection(&path, action == KeyAction::MoveBookUp)
+                {
+                    self.library_notice = Some(format!("already at the end of {collection}"));
                 }
                 Ok(None)
             }
-            KeyCode::Char(ch) => {
-                if self.search_panel.focus == SearchFocus::Query && !ch.is_control() {
-                    self.ctx.library_query.push(ch);
-                    self.normalize_selection_to_visible();
+            KeyAction::OpenFilters => {
+                self.open_filters_panel();
+                Ok(None)
+            }
+            KeyAction::OpenLabels => {
+                self.open_labels_panel();
+                Ok(None)
+            }
+            KeyAction::OpenCatalog => {
+                self.open_catalog_panel();
+                Ok(None)
+            }
+            KeyAction::OpenSettings => {
+                self.settings_panel.open = true;
+                self.settings_panel.selected = 0;
+                self.settings_panel.command_input = None;
+                Ok(None)
+            }
+            KeyAction::OpenHistory => {
+                self.open_history_panel();
+                Ok(None)
+            }
+            KeyAction::CycleSort => {
+                self.ctx.settings.cycle_sort_mode();
+                self.refresh_size_cache();
+                Ok(None)
+            }
+            KeyAction::ToggleLibraryView => {
+                self.ctx.settings.toggle_library_grid();
+                Ok(None)
+            }
+            KeyAction::RelinkBook => {
+                if let Some(path) = self.selected_book_path()
+                    && self.missing_paths.contains(&path)
+                {
+                    self.relink_panel = RelinkPanel {
+                        open: true,
+                        path: Some(path),
+                        input: TextInput::default(),
+                        error: None,
+                    };
+                }
+                Ok(None)
+            }
+            KeyAction::RemoveBook => {
+                if let Some(path) = self.selected_book_path()
+                    && (self.missing_paths.contains(&path) || self.broken_paths.contains_key(&path))
+                {
+                    let title = self.remove_library_entry(&path);
+                    self.library_notice = Some(format!("removed {title}"));
+                }
+                Ok(None)
+            }
+            KeyAction::DeleteBook => {
+                if let Some(path) = self.selected_book_path() {
+                    self.delete_panel = DeletePanel {
+                        open: true,
+                        path: Some(path),
+                        error: None,
+                    };
+                }
+                Ok(None)
+            }
+            KeyAction::RefreshMetadata => {
+                self.refresh_metadata();
+                Ok(None)
+            }
+            KeyAction::FilterByCollection => {
+                self.filter_by_selected_collection();
+                Ok(None)
+            }
+            KeyAction::FilterByTag => {
+                self.filter_by_selected_tag();
+                Ok(None)
+            }
+            KeyAction::ToggleDetails => {
+                self.details_pane = !self.details_pane;
+                Ok(None)
+            }
+            KeyAction::SearchNotes => {
+                self.notes_search_panel = NotesSearchPanel {
+                    open: true,
+                    ..NotesSearchPanel::default()
+                };
+                Ok(None)
+            }
+            KeyAction::OpenReader => {
+                self.open_selected_book();
+                Ok(None)
+            }
+            KeyAction::NextItem
+            | KeyAction::PrevItem
+            | KeyAction::PrevColumn
+            | KeyAction::NextColumn => {
+                let moved = match (self.ctx.settings.library_grid, action) {
+                    (true, KeyAction::NextItem) => self.select_in_grid(GridMove::Down),
+                    (true, KeyAction::PrevItem) => self.select_in_grid(GridMove::Up),
+                    (true, KeyAction::PrevColumn) => self.select_in_grid(GridMove::Left),
+                    (true, _) => self.select_in_grid(GridMove::Right),
+                    (false, KeyAction::NextItem) => self.select_next_visible(),
+                    (false, KeyAction::PrevItem) => self.select_prev_visible(),
+                    // The list has one column.
+                    (false, _) => false,
+                };
+                if !moved && !notice_cleared {
+                    self.redraw.ignore_key();
                 }
                 Ok(None)
             }
@@ -619,2109 +1554,6417 @@ impl Ui {
         }
     }
 
-    fn normalize_tag_filters(&mut self) {
-        self.ctx.tag_filters.retain(|t| !t.trim().is_empty());
-        self.ctx.tag_filters.sort_by(|a, b| {
-            a.to_ascii_lowercase()
-                .cmp(&b.to_ascii_lowercase())
-                .then_with(|| a.cmp(b))
-        });
-        self.ctx
-            .tag_filters
-            .dedup_by(|a, b| a.eq_ignore_ascii_case(b));
-    }
-
-    fn all_tag_names(&self) -> Vec<String> {
-        let mut tags: Vec<String> = self
-            .ctx
-            .labels_by_path
-            .values()
-            .flat_map(|labels| labels.tags.iter().cloned())
-            .collect();
-        tags.retain(|t| !t.trim().is_empty());
-        tags.sort_by(|a, b| a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase()));
-        tags.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
-        tags
-    }
-
-    fn all_collection_names(&self) -> Vec<String> {
-        let mut cols: Vec<String> = self
+    /// Lists only the selected book's collection, or lists every collection again when that
+    /// is the one already filtered by.
+    fn filter_by_selected_collection(&mut self) {
+        let Some(idx) = self.selected_visible_index() else {
+            return;
+        };
+        let Some(book) = self.ctx.books.get(idx) else {
+            return;
+        };
+        let title = book.title.clone();
+        let collection = self
             .ctx
             .labels_by_path
-            .values()
-            .filter_map(|labels| labels.collection.clone())
-            .collect();
-        cols.retain(|c| !c.trim().is_empty());
-        cols.sort_by(|a, b| a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase()));
-        cols.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
-        cols
-    }
-
-    fn matches_query(&self, book: &Book) -> bool {
-        let query = self.ctx.library_query.trim();
-        if query.is_empty() {
-            return true;
-        }
-        let query = query.to_ascii_lowercase();
-
-        let title = book.title.to_ascii_lowercase();
-        if title.contains(&query) {
-            return true;
+            .get(&book.path)
+            .and_then(|labels| labels.collection.clone());
+        let Some(collection) = collection else {
+            self.library_notice = Some(format!(
+                "{title} is in no collection; {} adds it to one",
+                self.key_bindings.label(KeyAction::OpenLabels)
+            ));
+            return;
+        };
+        let already = matches!(
+            &self.ctx.collection_filter,
+            CollectionFilter::Selected(current) if current.eq_ignore_ascii_case(&collection)
+        );
+        if already {
+            self.ctx.collection_filter = CollectionFilter::Any;
+            self.ctx.collection_view = false;
+            self.library_notice = Some("collection filter cleared".to_string());
+        } else {
+            self.library_notice = Some(format!("showing collection: {collection}"));
+            self.ctx.collection_filter = CollectionFilter::Selected(collection);
         }
-
-        let path = bookshelf_core::display_path(&book.path).to_ascii_lowercase();
-        path.contains(&query)
-    }
-
-    fn matches_favorites_only(&self, book: &Book) -> bool {
-        !self.ctx.favorites_only || book.favorite
+        self.normalize_selection_to_visible();
     }
 
-    fn matches_collection(&self, book: &Book) -> bool {
-        let labels = self
+    /// Offers the selected book's tags to filter by. A book with one tag is filtered by it
+    /// straight away; when the tag filter holds only this book's tags, it is cleared instead.
+    fn filter_by_selected_tag(&mut self) {
+        let Some(idx) = self.selected_visible_index() else {
+            return;
+        };
+        let Some(book) = self.ctx.books.get(idx) else {
+            return;
+        };
+        let title = book.title.clone();
+        let mut tags = self
             .ctx
             .labels_by_path
             .get(&book.path)
-            .cloned()
+            .map(|labels| labels.tags.clone())
             .unwrap_or_default();
-        matches_collection_filter(&self.ctx.collection_filter, labels.collection.as_deref())
+        tags.retain(|tag| !tag.trim().is_empty());
+        tags.sort_by_key(|tag| tag.to_ascii_lowercase());
+        if tags.is_empty() {
+            self.library_notice = Some(format!(
+                "{title} has no tags; {} adds some",
+                self.key_bindings.label(KeyAction::OpenLabels)
+            ));
+            return;
+        }
+        let filtered_by_this_book = !self.ctx.tag_filters.is_empty()
+            && self
+                .ctx
+                .tag_filters
+                .iter()
+                .all(|filter| tags.iter().any(|tag| tag.eq_ignore_ascii_case(filter)));
+        if filtered_by_this_book {
+            self.ctx.tag_filters.clear();
+            self.library_notice = Some("tag filter cleared".to_string());
+            self.normalize_selection_to_visible();
+        } else if let [tag] = tags.as_slice() {
+            let tag = tag.clone();
+            self.toggle_tag_filter(&tag);
+        } else {
+            self.book_tag_picker = BookTagPicker {
+                open: true,
+                title,
+                tags,
+                selected: 0,
+            };
+        }
     }
 
-    fn matches_tags(&self, book: &Book) -> bool {
-        let labels = self
+    /// Adds `tag` to the tag filter, or takes it out when it is there already.
+    fn toggle_tag_filter(&mut self, tag: &str) {
+        if let Some(pos) = self
             .ctx
-            .labels_by_path
-            .get(&book.path)
-            .cloned()
-            .unwrap_or_default();
-        matches_tag_filter(&self.ctx.tag_filters, self.ctx.tag_match_mode, &labels.tags)
+            .tag_filters
+            .iter()
+            .position(|filter| filter.eq_ignore_ascii_case(tag))
+        {
+            self.ctx.tag_filters.remove(pos);
+            self.library_notice = Some(format!("no longer filtering by tag: {tag}"));
+        } else {
+            self.ctx.tag_filters.push(tag.to_string());
+            self.library_notice = Some(format!("filtering by tag: {tag}"));
+        }
+        self.normalize_tag_filters();
+        self.normalize_selection_to_visible();
     }
 
-    fn counts_by_collection_for_search(
-        &self,
-    ) -> (usize, usize, std::collections::HashMap<String, usize>) {
-        let mut total = 0usize;
-        let mut none_count = 0usize;
-        let mut by_name: std::collections::HashMap<String, usize> =
-            std::collections::HashMap::new();
-
-        for book in &self.ctx.books {
-            if !self.matches_query(book) || !self.matches_favorites_only(book) {
-                continue;
+    fn handle_book_tag_picker_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        match key.code {
+            KeyCode::Esc => self.book_tag_picker = BookTagPicker::default(),
+            KeyCode::Up => {
+                self.book_tag_picker.selected = self.book_tag_picker.selected.saturating_sub(1);
             }
-            if !self.matches_tags(book) {
-                continue;
+            KeyCode::Down => {
+                let last = self.book_tag_picker.tags.len().saturating_sub(1);
+                self.book_tag_picker.selected = (self.book_tag_picker.selected + 1).min(last);
             }
-            total += 1;
-
-            let labels = self
-                .ctx
-                .labels_by_path
-                .get(&book.path)
-                .cloned()
-                .unwrap_or_default();
-            if let Some(col) = labels.collection.as_deref() {
-                *by_name.entry(col.to_string()).or_insert(0) += 1;
-            } else {
-                none_count += 1;
+            KeyCode::Enter => {
+                let picker = std::mem::take(&mut self.book_tag_picker);
+                if let Some(tag) = picker.tags.get(picker.selected) {
+                    self.toggle_tag_filter(tag);
+                }
             }
+            _ => self.redraw.ignore_key(),
         }
+        Ok(None)
+    }
 
-        (total, none_count, by_name)
+    /// Opens the selected book in the reader, or says why it cannot be read.
+    fn open_selected_book(&mut self) {
+        if let Some(path) = self.selected_book_path()
+            && !book_file_exists(&path)
+        {
+            self.missing_paths.insert(path);
+            self.library_notice = Some(format!(
+                "file is missing: {} relink, {} remove",
+                self.key_bindings.label(KeyAction::RelinkBook),
+                self.key_bindings.label(KeyAction::RemoveBook)
+            ));
+            return;
+        }
+        if let Some(book) = self
+            .selected_visible_index()
+            .and_then(|idx| self.ctx.books.get(idx))
+            .cloned()
+        {
+            if let Some(err) = self.broken_book_error(&book) {
+                self.library_notice = Some(format!(
+                    "file is corrupt ({err}): {} remove",
+                    self.key_bindings.label(KeyAction::RemoveBook)
+                ));
+                return;
+            }
+            self.reader
+                .open_book(&book, &mut self.ctx, &self.engine, &mut self.image_picker);
+        }
     }
 
-    fn counts_by_tag_for_search(&self) -> (usize, std::collections::HashMap<String, usize>) {
-        let mut total = 0usize;
-        let mut by_name: std::collections::HashMap<String, usize> =
-            std::collections::HashMap::new();
+    /// Re-reads title and author from the marked books (or the selected one), replacing what
+    /// the library has. Scans never do this for books already in the library.
+    fn refresh_metadata(&mut self) {
+        let paths: Vec<String> = if self.marked_paths.is_empty() {
+            self.selected_book_path().into_iter().collect()
+        } else {
+            self.ctx
+                .books
+                .iter()
+                .filter(|b| self.marked_paths.contains(&b.path))
+                .map(|b| b.path.clone())
+                .collect()
+        };
+        if paths.is_empty() {
+            return;
+        }
 
-        for book in &self.ctx.books {
-            if !self.matches_query(book) || !self.matches_favorites_only(book) {
+        let (mut updated, mut failed) = (0, 0);
+        for path in &paths {
+            let Some(book) = self.ctx.books.iter().find(|b| &b.path == path).cloned() else {
                 continue;
-            }
-            if !self.matches_collection(book) {
-                continue;
-            }
-            total += 1;
-
-            let labels = self
-                .ctx
-                .labels_by_path
-                .get(&book.path)
-                .cloned()
-                .unwrap_or_default();
-            for tag in labels.tags {
-                if tag.trim().is_empty() {
-                    continue;
+            };
+            match self.engine.document_metadata(&book) {
+                Ok(metadata) => {
+                    if self.ctx.refresh_book_metadata(path, &metadata) {
+                        updated += 1;
+                    }
                 }
-                *by_name.entry(tag).or_insert(0) += 1;
+                Err(_) => failed += 1,
             }
         }
-
-        (total, by_name)
-    }
-
-    fn collection_entries_for_search(&self) -> Vec<CollectionEntry> {
-        let (total, none_count, counts) = self.counts_by_collection_for_search();
-        let mut out = Vec::new();
-        out.push(CollectionEntry {
-            label: "Any".to_string(),
-            filter: CollectionFilter::Any,
-            count: total,
-        });
-        out.push(CollectionEntry {
-            label: "None".to_string(),
-            filter: CollectionFilter::None,
-            count: none_count,
-        });
-
-        for name in self.all_collection_names() {
-            let count = counts
-                .iter()
-                .find(|(k, _)| k.eq_ignore_ascii_case(&name))
-                .map(|(_, v)| *v)
-                .unwrap_or(0);
-            out.push(CollectionEntry {
-                label: name.clone(),
-                filter: CollectionFilter::Selected(name),
-                count,
-            });
+        self.normalize_selection_to_visible();
+        let mut notice = format!("metadata: {updated} of {} updated", paths.len());
+        if failed > 0 {
+            notice.push_str(&format!(", {failed} unreadable"));
         }
-        out
+        self.library_notice = Some(notice);
     }
 
-    fn tag_entries_for_search(&self) -> Vec<TagEntry> {
-        let (_total, counts) = self.counts_by_tag_for_search();
-        let mut out = Vec::new();
-        for name in self.all_tag_names() {
-            let count = counts
-                .iter()
-                .find(|(k, _)| k.eq_ignore_ascii_case(&name))
-                .map(|(_, v)| *v)
-                .unwrap_or(0);
-            out.push(TagEntry { name, count });
-        }
-        out
+    fn open_filters_panel(&mut self) {
+        self.open_search_panel_mode(SearchPanelMode::Filters);
+        self.search_panel.focus = SearchFocus::Query;
     }
 
-    fn apply_collection_cursor(&mut self) {
-        let entries = self.collection_entries_for_search();
-        if entries.is_empty() {
-            return;
-        }
-        self.search_panel.collection_cursor = self
-            .search_panel
-            .collection_cursor
-            .min(entries.len().saturating_sub(1));
-        let entry = &entries[self.search_panel.collection_cursor];
-        self.ctx.collection_filter = entry.filter.clone();
-        self.normalize_selection_to_visible();
+    fn open_labels_panel(&mut self) {
+        self.open_search_panel_mode(SearchPanelMode::Labels);
     }
 
-    fn toggle_tag_cursor(&mut self) {
-        let entries = self.tag_entries_for_search();
-        if entries.is_empty() {
-            return;
-        }
-        self.search_panel.tag_cursor = self
-            .search_panel
-            .tag_cursor
-            .min(entries.len().saturating_sub(1));
-        let tag = &entries[self.search_panel.tag_cursor].name;
+    fn open_catalog_panel(&mut self) {
+        self.open_search_panel_mode(SearchPanelMode::Catalog);
+    }
 
-        if let Some(pos) = self
-            .ctx
-            .tag_filters
-            .iter()
-            .position(|t| t.eq_ignore_ascii_case(tag))
-        {
-            self.ctx.tag_filters.remove(pos);
-        } else {
-            self.ctx.tag_filters.push(tag.clone());
-        }
-        self.normalize_tag_filters();
+    fn open_search_panel_mode(&mut self, mode: SearchPanelMode) {
+        self.search_panel.open = true;
+        self.search_panel.mode = mode;
+        self.search_panel.tab = SearchTab::Search;
+        self.search_panel.focus = SearchFocus::Query;
+        self.search_panel.collection_cursor = 0;
+        self.search_panel.tag_cursor = 0;
+        self.search_panel.author_cursor = 0;
+        self.search_panel.series_cursor = 0;
+        self.search_panel.query.set(self.ctx.library_query.clone());
+        self.reset_search_overlay_state();
+        self.search_panel.snapshot = Some(SearchSnapshot {
+            library_query: self.ctx.library_query.clone(),
+            favorites_only: self.ctx.favorites_only,
+            include_archived: self.ctx.include_archived,
+            status_filter: self.ctx.status_filter,
+            added_within_days: self.ctx.added_within_days,
+            kind_filter: self.ctx.kind_filter,
+            collection_filter: self.ctx.collection_filter.clone(),
+            collection_view: self.ctx.collection_view,
+            tag_filters: self.ctx.tag_filters.clone(),
+            tag_match_mode: self.ctx.tag_match_mode,
+            author_filter: self.ctx.author_filter.clone(),
+            series_filter: self.ctx.series_filter.clone(),
+            selected_path: self
+                .ctx
+                .books
+                .get(self.ctx.selected)
+                .map(|b| b.path.clone()),
+        });
+        self.label_catalog_input_panel.open = false;
+        self.settings_panel.open = false;
+        self.scan_panel.open = false;
+        self.sync_search_panel_tab();
         self.normalize_selection_to_visible();
     }
 
-    fn handle_reader_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        match key.code {
-            KeyCode::Esc => {
-                if let Some(path) = self.reader.book_path.clone() {
-                    self.ctx
-                        .progress_by_path
-                        .insert(path, self.reader.page.saturating_add(1));
-                }
-                if self.boot_reader_session {
-                    return Ok(Some(UiExit::Quit));
-                }
-                self.reader = ReaderPanel::default();
-                self.goto_panel = GotoPanel::default();
-                self.bookmarks_panel = BookmarksPanel::default();
-                self.notes_panel = NotesPanel::default();
-                self.toc_panel = TocPanel::default();
-                Ok(None)
-            }
-            KeyCode::Char('g') => {
-                self.goto_panel.open = true;
-                self.goto_panel.error = None;
-                self.goto_panel.input = self.reader.page.saturating_add(1).to_string();
-                self.bookmarks_panel.open = false;
-                self.notes_panel.open = false;
-                self.toc_panel.open = false;
-                Ok(None)
-            }
-            KeyCode::Char('d') => {
-                if let Some(book) = self.reader.current_book() {
-                    let dir = Path::new(&self.ctx.cwd).join("tmp");
-                    std::fs::create_dir_all(&dir)?;
+    fn reset_search_overlay_state(&mut self) {
+        self.label_manager_panel.confirm_delete = None;
+        self.label_manager_panel.error = None;
+        self.label_manager_panel.filter_editing = false;
+        self.assign_labels_panel.query_editing = false;
+        self.assign_labels_panel.error = None;
+        self.assign_labels_panel.book_path = None;
+        self.assign_labels_panel.bulk_paths.clear();
+    }
 
-                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-                    std::hash::Hash::hash(&book.path, &mut hasher);
-                    let id = hasher.finish();
+    fn close_search_panel(&mut self) {
+        self.search_panel.open = false;
+        self.search_panel.snapshot = None;
+        self.reset_search_overlay_state();
+    }
 
-                    let path = dir.join(format!(
-                        "bookshelf-reader-debug-{id:016x}-p{}.txt",
-                        self.reader.page + 1
-                    ));
-                    let term = std::env::var("TERM").unwrap_or_default();
-                    let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
-                    let tmux = std::env::var("TMUX").unwrap_or_default();
-                    let kitty_window_id = std::env::var("KITTY_WINDOW_ID").unwrap_or_default();
-                    let protocol_label = image_protocol::protocol_label(&self.image_picker);
-                    let image_supported = image_protocol::image_supported(&self.image_picker);
-                    let kitty_supported = image_protocol::kitty_supported(&self.image_picker);
-                    let capabilities = self.image_picker.capabilities();
-                    let (font_w, font_h) = self.image_picker.font_size();
-                    let timing_block = self.reader.last_image_timings.map(|t| {
-                        let rasterize_ms = t
-                            .rasterize_ms
-                            .map(|v| v.to_string())
-                            .unwrap_or_else(|| "-".to_string());
-                        format!(
-                            "\nreader-image:\n  total_ms={}\n  rasterize_ms={}\n  viewport_ms={}\n  downscale_ms={}\n  protocol_ms={}\n  viewport_px={}x{}\n  transmit_px={}x{}\n  render_width_px={}\n",
-                            t.total_ms,
-                            rasterize_ms,
-                            t.viewport_ms,
-                            t.downscale_ms,
-                            t.protocol_ms,
-                            t.viewport_px.0,
-                            t.viewport_px.1,
-                            t.transmit_px.0,
-                            t.transmit_px.1,
-                            t.render_width_px,
-                        )
-                    });
-                    let debug = format!(
-                        "env:\n  TERM={term}\n  TERM_PROGRAM={term_program}\n  TMUX={tmux}\n  KITTY_WINDOW_ID={kitty_window_id}\n\nratatui-image:\n  protocol={protocol_label}\n  image_supported={image_supported}\n  kitty_supported={kitty_supported}\n  capabilities={capabilities:?}\n  font_size_px={font_w}x{font_h}{}\n\n-----\n\n{}",
-                        timing_block.unwrap_or_default(),
-                        self.engine.debug_page_text(&book, self.reader.page)?
-                    );
-                    std::fs::write(&path, debug)?;
-                    self.reader.notice = Some(format!("wrote {}", path.display()));
-                }
-                Ok(None)
-            }
-            KeyCode::Char('b') => {
-                self.bookmarks_panel.open = true;
-                self.bookmarks_panel.selected = 0;
-                self.goto_panel.open = false;
-                self.notes_panel.open = false;
-                self.toc_panel.open = false;
-                Ok(None)
-            }
-            KeyCode::Char('n') => {
-                self.notes_panel.open = true;
-                self.notes_panel.selected = 0;
-                self.notes_panel.error = None;
-                self.goto_panel.open = false;
-                self.bookmarks_panel.open = false;
-                self.toc_panel.open = false;
-                Ok(None)
-            }
-            KeyCode::Char('t') => {
-                self.open_toc_panel();
-                Ok(None)
-            }
-            KeyCode::Char('m') => {
-                match self.ctx.settings.reader_mode {
-                    ReaderMode::Text => {
-                        if image_protocol::image_supported(&self.image_picker) {
-                            image_protocol::prefer_kitty_if_supported(&mut self.image_picker);
-                            self.ctx.settings.reader_mode = ReaderMode::Image;
-                            self.reader.invalidate_render();
-                            let label = image_protocol::protocol_label(&self.image_picker);
-                            let mut notice = format!("mode: image ({label})");
-                            if image_protocol::in_iterm_env() && label == "iterm2" {
-                                notice.push_str("; enable iTerm2 inline images (imgcat) if blank");
-                            }
-                            self.reader.notice = Some(notice);
-                        } else {
-                            self.ctx.settings.reader_mode = ReaderMode::Text;
-                            let in_tmux = std::env::var_os("TMUX").is_some();
-                            self.reader.notice = Some(if in_tmux {
-                                "image mode needs a graphics protocol + tmux allow-passthrough; press k to open kitty reader"
-                                    .to_string()
-                            } else {
-                                "image mode requires kitty/iterm2 graphics; press k to open kitty reader"
-                                    .to_string()
-                            });
-                        }
-                    }
-                    ReaderMode::Image => {
-                        self.ctx.settings.reader_mode = ReaderMode::Text;
-                        self.reader.invalidate_render();
-                        self.reader.notice = Some("mode: text".to_string());
-                    }
-                }
-                Ok(None)
+    fn cancel_search_panel(&mut self) {
+        if let Some(snapshot) = self.search_panel.snapshot.take() {
+            self.search_panel.query.set(snapshot.library_query.clone());
+            self.ctx.library_query = snapshot.library_query;
+            self.ctx.favorites_only = snapshot.favorites_only;
+            self.ctx.include_archived = snapshot.include_archived;
+            self.ctx.status_filter = snapshot.status_filter;
+            self.ctx.added_within_days = snapshot.added_within_days;
+            self.ctx.kind_filter = snapshot.kind_filter;
+            self.ctx.collection_filter = snapshot.collection_filter;
+            self.ctx.collection_view = snapshot.collection_view;
+            self.ctx.tag_filters = snapshot.tag_filters;
+            self.ctx.tag_match_mode = snapshot.tag_match_mode;
+            self.ctx.author_filter = snapshot.author_filter;
+            self.ctx.series_filter = snapshot.series_filter;
+
+            if let Some(path) = snapshot.selected_path
+                && let Some(idx) = self.ctx.books.iter().position(|b| b.path == path)
+            {
+                self.ctx.selected = idx;
             }
-            KeyCode::Char('r') => {
-                if self.ctx.settings.reader_mode == ReaderMode::Text {
-                    self.ctx.settings.cycle_reader_text_mode();
-                    self.reader.invalidate_render();
-                    self.reader.notice =
-                        Some(format!("text: {}", self.ctx.settings.reader_text_mode));
-                }
-                Ok(None)
+            self.normalize_selection_to_visible();
+        }
+        self.search_panel.open = false;
+        self.reset_search_overlay_state();
+    }
+
+    fn handle_search_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        if key.code == KeyCode::Esc {
+            if self.search_panel.tab == SearchTab::Assign && self.assign_labels_panel.query_editing
+            {
+                self.assign_labels_panel.query_editing = false;
+                return Ok(None);
             }
-            KeyCode::Char('h') => {
-                if self.ctx.settings.reader_mode == ReaderMode::Text {
-                    self.ctx.settings.toggle_reader_trim_headers_footers();
-                    self.reader.invalidate_render();
-                    let status = if self.ctx.settings.reader_trim_headers_footers {
-                        "on"
-                    } else {
-                        "off"
-                    };
-                    self.reader.notice = Some(format!("trim headers/footers: {status}"));
-                }
-                Ok(None)
+
+            if self.search_panel.tab == SearchTab::Manage
+                && (self.label_manager_panel.confirm_delete.is_some()
+                    || self.label_manager_panel.filter_editing)
+            {
+                return self.handle_label_manager_panel_key(key);
             }
-            KeyCode::Left => {
-                if self.ctx.settings.reader_mode == ReaderMode::Image
-                    && key.modifiers.contains(KeyModifiers::SHIFT)
-                {
-                    self.reader.pan_image_by_cells(&self.image_picker, -5, 0);
+
+            self.cancel_search_panel();
+            return Ok(None);
+        }
+
+        if key.code == KeyCode::Enter {
+            if self.search_panel.tab == SearchTab::Assign && self.assign_labels_panel.query_editing
+            {
+                if self.assign_tag_query_creates() {
+                    self.create_tag_from_assign_query();
+                } else if self.assign_query_sets_value() {
+                    self.set_value_from_assign_query();
                 } else {
-                    self.reader.prev_page();
+                    self.assign_labels_panel.query_editing = false;
                 }
-                Ok(None)
+                return Ok(None);
             }
-            KeyCode::Right => {
-                if self.ctx.settings.reader_mode == ReaderMode::Image
-                    && key.modifiers.contains(KeyModifiers::SHIFT)
-                {
-                    self.reader.pan_image_by_cells(&self.image_picker, 5, 0);
-                } else {
-                    self.reader.next_page();
-                }
-                Ok(None)
+
+            if self.search_panel.tab == SearchTab::Manage && self.label_manager_panel.filter_editing
+            {
+                self.label_manager_panel.filter_editing = false;
+                return Ok(None);
             }
-            KeyCode::Up => {
-                if self.ctx.settings.reader_mode == ReaderMode::Image {
-                    self.reader.pan_image_by_cells(&self.image_picker, 0, -3);
-                } else {
-                    self.reader.scroll_up();
+
+            match self.search_panel.tab {
+                SearchTab::Search => {
+                    self.close_search_panel();
                 }
-                Ok(None)
-            }
-            KeyCode::Down => {
-                if self.ctx.settings.reader_mode == ReaderMode::Image {
-                    self.reader.pan_image_by_cells(&self.image_picker, 0, 3);
-                } else {
-                    self.reader.scroll_down();
+                SearchTab::Assign => {
+                    self.commit_assign_labels_panel();
+                    self.close_search_panel();
                 }
-                Ok(None)
-            }
-            KeyCode::PageUp => {
-                if self.ctx.settings.reader_mode == ReaderMode::Image {
-                    let step = self
-                        .reader
-                        .render_key
-                        .map(|k| k.height.saturating_sub(2))
-                        .unwrap_or(10);
-                    self.reader
-                        .pan_image_by_cells(&self.image_picker, 0, -i32::from(step));
-                } else {
-                    for _ in 0..10 {
-                        self.reader.scroll_up();
+                SearchTab::Manage => {
+                    if self.label_manager_panel.confirm_delete.is_some() {
+                        return self.handle_label_manager_panel_key(key);
                     }
+                    self.close_search_panel();
                 }
-                Ok(None)
             }
-            KeyCode::PageDown => {
-                if self.ctx.settings.reader_mode == ReaderMode::Image {
-                    let step = self
-                        .reader
-                        .render_key
-                        .map(|k| k.height.saturating_sub(2))
-                        .unwrap_or(10);
-                    self.reader
-                        .pan_image_by_cells(&self.image_picker, 0, i32::from(step));
-                } else {
-                    for _ in 0..10 {
-                        self.reader.scroll_down();
-                    }
-                }
+            return Ok(None);
+        }
+
+        match self.search_panel.tab {
+            SearchTab::Search => self.handle_search_filters_tab_key(key),
+            SearchTab::Assign => self.handle_assign_labels_panel_key(key),
+            SearchTab::Manage => self.handle_label_manager_panel_key(key),
+        }
+    }
+
+    fn handle_search_filters_tab_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && let KeyCode::Char('u') = key.code
+        {
+            self.ctx.library_query.clear();
+            self.search_panel.query.clear();
+            self.ctx.favorites_only = false;
+            self.ctx.include_archived = false;
+            self.ctx.status_filter = None;
+            self.ctx.added_within_days = None;
+            self.ctx.kind_filter = None;
+            self.ctx.collection_filter = CollectionFilter::Any;
+            self.ctx.collection_view = false;
+            self.ctx.tag_filters.clear();
+            self.ctx.tag_match_mode = TagMatchMode::Or;
+            self.ctx.author_filter = None;
+            self.ctx.series_filter = None;
+            self.normalize_selection_to_visible();
+            return Ok(None);
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && let KeyCode::Char('r') = key.code
+        {
+            self.toggle_collection_view();
+            return Ok(None);
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && let KeyCode::Char('s') = key.code
+        {
+            self.ctx.status_filter = next_status_filter(self.ctx.status_filter);
+            self.normalize_selection_to_visible();
+            return Ok(None);
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && let KeyCode::Char('n') = key.code
+        {
+            self.ctx.added_within_days = next_added_within_filter(self.ctx.added_within_days);
+            self.normalize_selection_to_visible();
+            return Ok(None);
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && let KeyCode::Char('k') = key.code
+        {
+            self.ctx.kind_filter = next_kind_filter(self.ctx.kind_filter);
+            self.normalize_selection_to_visible();
+            return Ok(None);
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && let KeyCode::Char('a') = key.code
+        {
+            self.ctx.include_archived = !self.ctx.include_archived;
+            self.normalize_selection_to_visible();
+            return Ok(None);
+        }
+
+        match key.code {
+            KeyCode::Tab => {
+                self.search_panel.focus = self.search_panel.focus.next();
                 Ok(None)
             }
-            KeyCode::Char('k') => {
-                if self.ctx.settings.reader_mode == ReaderMode::Text
-                    && !image_protocol::image_supported(&self.image_picker)
-                {
-                    let spawned = if let Some(path) = self.reader.book_path.as_deref() {
-                        kitty_spawn::spawn_kitty_reader_with_current_exe(path, self.reader.page)
-                    } else {
-                        kitty_spawn::spawn_kitty_with_current_exe()
-                    };
-                    match spawned {
-                        Ok(child) => {
-                            self.spawned_kitties.push(child);
-                            self.reader.notice = Some("spawned kitty reader".to_string());
-                        }
-                        Err(err) => self.reader.notice = Some(format!("kitty spawn failed: {err}")),
-                    }
-                }
+            KeyCode::BackTab => {
+                self.search_panel.focus = self.search_panel.focus.prev();
                 Ok(None)
             }
-            KeyCode::Char('+') | KeyCode::Char('=') => {
-                if self.ctx.settings.reader_mode == ReaderMode::Image {
-                    self.reader.zoom_image_in();
-                }
+            KeyCode::Char('f') => {
+                self.ctx.favorites_only = !self.ctx.favorites_only;
+                self.normalize_selection_to_visible();
                 Ok(None)
             }
-            KeyCode::Char('-') => {
-                if self.ctx.settings.reader_mode == ReaderMode::Image {
-                    self.reader.zoom_image_out();
-                }
+            _ if self.search_panel.focus == SearchFocus::Query
+                && self.search_panel.query.handle_key(key) =>
+            {
+                self.ctx.library_query = self.search_panel.query.as_str().to_string();
+                self.normalize_selection_to_visible();
                 Ok(None)
             }
-            KeyCode::Char('0') => {
-                if self.ctx.settings.reader_mode == ReaderMode::Image {
-                    self.reader.reset_image_view();
+            KeyCode::Left => {
+                if self.ctx.tag_match_mode == TagMatchMode::And {
+                    self.ctx.tag_match_mode = TagMatchMode::Or;
                 }
                 Ok(None)
             }
-            _ => Ok(None),
-        }
-    }
-
-    fn handle_goto_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        match key.code {
-            KeyCode::Esc => {
-                self.goto_panel.open = false;
-                self.goto_panel.input.clear();
-                self.goto_panel.error = None;
+            KeyCode::Right => {
+                if self.ctx.tag_match_mode == TagMatchMode::Or {
+                    self.ctx.tag_match_mode = TagMatchMode::And;
+                }
                 Ok(None)
             }
-            KeyCode::Enter => {
-                let input = self.goto_panel.input.trim();
-                if input.is_empty() {
-                    self.goto_panel.error = Some("Enter a page number".to_string());
-                    return Ok(None);
-                }
-
-                let page = match input.parse::<u32>() {
-                    Ok(p) if p >= 1 => p,
-                    _ => {
-                        self.goto_panel.error = Some("Invalid page number".to_string());
-                        return Ok(None);
+            KeyCode::Up => {
+                match self.search_panel.focus {
+                    SearchFocus::Query => {}
+                    SearchFocus::Collections => {
+                        self.search_panel.collection_cursor =
+                            self.search_panel.collection_cursor.saturating_sub(1);
+                    }
+                    SearchFocus::Tags => {
+                        self.search_panel.tag_cursor =
+                            self.search_panel.tag_cursor.saturating_sub(1);
+                    }
+                    SearchFocus::Authors => {
+                        self.search_panel.author_cursor =
+                            self.search_panel.author_cursor.saturating_sub(1);
+                    }
+                    SearchFocus::Series => {
+                        self.search_panel.series_cursor =
+                            self.search_panel.series_cursor.saturating_sub(1);
                     }
-                };
-
-                if let Some(total) = self.reader.total_pages
-                    && page > total
-                {
-                    self.goto_panel.error = Some(format!("Page out of range (1..={total})"));
-                    return Ok(None);
                 }
-
-                self.reader.page = page.saturating_sub(1);
-                self.reader.invalidate_render();
-                self.reader.notice = Some(format!("jumped to page {page}"));
-                self.goto_panel.open = false;
-                self.goto_panel.error = None;
                 Ok(None)
             }
-            KeyCode::Backspace => {
-                self.goto_panel.input.pop();
+            KeyCode::Down => {
+                match self.search_panel.focus {
+                    SearchFocus::Query => {}
+                    SearchFocus::Collections => {
+                        self.search_panel.collection_cursor =
+                            self.search_panel.collection_cursor.saturating_add(1);
+                    }
+                    SearchFocus::Tags => {
+                        self.search_panel.tag_cursor =
+                            self.search_panel.tag_cursor.saturating_add(1);
+                    }
+                    SearchFocus::Authors => {
+                        self.search_panel.author_cursor =
+                            self.search_panel.author_cursor.saturating_add(1);
+                    }
+                    SearchFocus::Series => {
+                        self.search_panel.series_cursor =
+                            self.search_panel.series_cursor.saturating_add(1);
+                    }
+                }
                 Ok(None)
             }
-            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.goto_panel.input.clear();
+            KeyCode::Char(' ') => {
+                match self.search_panel.focus {
+                    SearchFocus::Query => {}
+                    SearchFocus::Collections => {
+                        self.apply_collection_cursor();
+                        self.normalize_selection_to_visible();
+                    }
+                    SearchFocus::Tags => {
+                        self.toggle_tag_cursor();
+                        self.normalize_selection_to_visible();
+                    }
+                    SearchFocus::Authors => self.toggle_name_cursor(TagKind::Author),
+                    SearchFocus::Series => self.toggle_name_cursor(TagKind::Series),
+                }
                 Ok(None)
             }
-            KeyCode::Char(ch) if ch.is_ascii_digit() => {
-                self.goto_panel.input.push(ch);
+            KeyCode::Char('z') if self.search_panel.focus == SearchFocus::Tags => {
+                self.toggle_tag_fold();
                 Ok(None)
             }
             _ => Ok(None),
         }
     }
 
-    fn open_toc_panel(&mut self) {
-        self.toc_panel.open = true;
-        self.toc_panel.error = None;
-        self.toc_panel.query.clear();
-        self.goto_panel.open = false;
-        self.bookmarks_panel.open = false;
-        self.notes_panel.open = false;
-
-        let Some(book) = self.reader.current_book() else {
-            self.toc_panel.items.clear();
-            self.toc_panel.path = None;
-            self.toc_panel.error = Some("no book".to_string());
+    /// Turns the reading list on for the selected collection, taking the collection under the
+    /// cursor when none is selected yet, or turns it off.
+    fn toggle_collection_view(&mut self) {
+        if self.ctx.collection_view {
+            self.ctx.collection_view = false;
             return;
-        };
-
-        if self.toc_panel.path.as_deref() != Some(&book.path) {
-            match self.engine.toc(&book) {
-                Ok(items) => {
-                    self.toc_panel.items = items;
-                    self.toc_panel.path = Some(book.path.clone());
-                    self.toc_panel.error = None;
-                }
-                Err(err) => {
-                    self.toc_panel.items.clear();
-                    self.toc_panel.path = Some(book.path.clone());
-                    self.toc_panel.error = Some(err.to_string());
-                }
-            }
         }
-
-        let current_page = self.reader.page.saturating_add(1);
-        let mut best = 0usize;
-        for (idx, item) in self.toc_panel.items.iter().enumerate() {
-            if let Some(page) = item.page
-                && page <= current_page
+        if !matches!(self.ctx.collection_filter, CollectionFilter::Selected(_)) {
+            let entries = self.collection_entries_for_search();
+            let cursor = self
+                .search_panel
+                .collection_cursor
+                .min(entries.len().saturating_sub(1));
+            if let Some(entry) = entries
+                .into_iter()
+                .nth(cursor)
+                .filter(|entry| matches!(entry.filter, CollectionFilter::Selected(_)))
             {
-                best = idx;
+                self.ctx.collection_filter = entry.filter;
             }
         }
-        let visible = self.toc_visible_indices();
-        self.toc_panel.selected = visible.iter().position(|idx| *idx == best).unwrap_or(0);
+        self.ctx.collection_view =
+            matches!(self.ctx.collection_filter, CollectionFilter::Selected(_));
+        self.normalize_selection_to_visible();
     }
 
-    fn toc_visible_indices(&self) -> Vec<usize> {
-        let query = self.toc_panel.query.trim().to_lowercase();
-        if query.is_empty() {
-            return (0..self.toc_panel.items.len()).collect();
+    /// The collection listed as a reading list, when the collection view is on.
+    fn collection_view_name(&self) -> Option<&str> {
+        match &self.ctx.collection_filter {
+            CollectionFilter::Selected(name) if self.ctx.collection_view => Some(name),
+            _ => None,
         }
+    }
 
-        self.toc_panel
-            .items
-            .iter()
-            .enumerate()
-            .filter_map(|(idx, item)| {
-                if item.title.to_lowercase().contains(&query) {
-                    Some(idx)
-                } else {
-                    None
-                }
-            })
-            .collect()
+    fn normalize_tag_filters(&mut self) {
+        self.ctx.tag_filters.retain(|t| !t.trim().is_empty());
+        self.ctx.tag_filters.sort_by(|a, b| {
+            a.to_ascii_lowercase()
+                .cmp(&b.to_ascii_lowercase())
+                .then_with(|| a.cmp(b))
+        });
+        self.ctx
+            .tag_filters
+            .dedup_by(|a, b| a.eq_ignore_ascii_case(b));
     }
 
-    fn handle_toc_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        let visible = self.toc_visible_indices();
-        match key.code {
-            KeyCode::Esc => {
-                self.toc_panel.open = false;
-                Ok(None)
-            }
-            KeyCode::Up => {
-                self.toc_panel.selected = self.toc_panel.selected.saturating_sub(1);
-                Ok(None)
-            }
-            KeyCode::Down => {
-                let len = visible.len();
-                if len > 0 {
-                    self.toc_panel.selected = (self.toc_panel.selected + 1).min(len - 1);
-                }
-                Ok(None)
-            }
-            KeyCode::Enter => {
-                let Some(item_idx) = visible.get(self.toc_panel.selected).copied() else {
-                    return Ok(None);
-                };
-                let Some(item) = self.toc_panel.items.get(item_idx) else {
-                    return Ok(None);
-                };
-                let Some(page) = item.page else {
-                    self.reader.notice = Some("TOC entry has no page".to_string());
-                    return Ok(None);
-                };
-                self.reader.page = page.saturating_sub(1);
-                self.reader.invalidate_render();
-                self.toc_panel.open = false;
-                Ok(None)
-            }
-            KeyCode::Backspace => {
-                self.toc_panel.query.pop();
-                self.toc_panel.selected = 0;
-                Ok(None)
-            }
-            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.toc_panel.query.clear();
-                self.toc_panel.selected = 0;
-                Ok(None)
-            }
-            KeyCode::Char(ch) => {
-                if !ch.is_control() {
-                    self.toc_panel.query.push(ch);
-                    self.toc_panel.selected = 0;
-                }
-                Ok(None)
-            }
-            _ => Ok(None),
-        }
+    fn all_tag_names(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .ctx
+            .labels_by_path
+            .values()
+            .flat_map(|labels| labels.tags.iter().cloned())
+            .collect();
+        tags.retain(|t| !t.trim().is_empty());
+        tags.sort_by_key(|a| a.to_ascii_lowercase());
+        tags.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        tags
     }
 
-    fn handle_bookmarks_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        match key.code {
-            KeyCode::Esc => {
-                self.bookmarks_panel.open = false;
-                Ok(None)
-            }
-            KeyCode::Up => {
-                self.bookmarks_panel.selected = self.bookmarks_panel.selected.saturating_sub(1);
-                Ok(None)
-            }
-            KeyCode::Down => {
-                let len = self.current_bookmarks().len();
-                if len > 0 {
-                    self.bookmarks_panel.selected =
-                        (self.bookmarks_panel.selected + 1).min(len - 1);
-                }
-                Ok(None)
-            }
-            KeyCode::Enter => {
-                let Some(bookmark) = self
-                    .current_bookmarks()
-                    .get(self.bookmarks_panel.selected)
-                    .cloned()
-                else {
-                    return Ok(None);
-                };
-                self.reader.page = bookmark.page.saturating_sub(1);
-                self.reader.invalidate_render();
-                self.bookmarks_panel.open = false;
-                Ok(None)
-            }
-            KeyCode::Char('a') => {
-                let Some(path) = self.reader.book_path.clone() else {
-                    return Ok(None);
-                };
-                let page = self.reader.page.saturating_add(1);
-                let bookmarks = self.ctx.bookmarks_by_path.entry(path.clone()).or_default();
-                if !bookmarks
-                    .iter()
-                    .any(|b| b.page == page && b.label.is_empty())
-                {
-                    bookmarks.push(Bookmark {
-                        page,
-                        label: String::new(),
-                    });
-                    bookmarks.sort_by_key(|b| (b.page, b.label.clone()));
-                    self.ctx.dirty_bookmark_paths.insert(path);
-                    self.reader.notice = Some(format!("bookmarked page {page}"));
-                    self.bookmarks_panel.selected = bookmarks
-                        .iter()
-                        .position(|b| b.page == page && b.label.is_empty())
-                        .unwrap_or(0);
-                }
-                Ok(None)
-            }
-            KeyCode::Char('d') => {
-                let Some(path) = self.reader.book_path.clone() else {
-                    return Ok(None);
-                };
-                let bookmarks = self.ctx.bookmarks_by_path.entry(path.clone()).or_default();
-                if self.bookmarks_panel.selected < bookmarks.len() {
-                    bookmarks.remove(self.bookmarks_panel.selected);
-                    bookmarks.sort_by_key(|b| (b.page, b.label.clone()));
-                    self.ctx.dirty_bookmark_paths.insert(path);
-                    if bookmarks.is_empty() {
-                        self.bookmarks_panel.selected = 0;
-                    } else {
-                        self.bookmarks_panel.selected =
-                            self.bookmarks_panel.selected.min(bookmarks.len() - 1);
-                    }
-                }
-                Ok(None)
-            }
-            _ => Ok(None),
-        }
+    fn all_collection_names(&self) -> Vec<String> {
+        let mut cols: Vec<String> = self
+            .ctx
+            .labels_by_path
+            .values()
+            .filter_map(|labels| labels.collection.clone())
+            .collect();
+        cols.retain(|c| !c.trim().is_empty());
+        cols.sort_by_key(|a| a.to_ascii_lowercase());
+        cols.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        cols
     }
 
-    fn handle_notes_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        if self.notes_panel.input_open {
-            return self.handle_notes_input_key(key);
+    fn matches_query(&self, query: &LibraryQuery, book: &Book) -> bool {
+        if query.is_empty() {
+            return true;
         }
+        let labels = self
+            .ctx
+            .labels_by_path
+            .get(&book.path)
+            .cloned()
+            .unwrap_or_default();
+        query.matches(book, &labels)
+    }
 
-        match key.code {
-            KeyCode::Esc => {
-                self.notes_panel.open = false;
-                self.notes_panel.error = None;
-                Ok(None)
-            }
-            KeyCode::Up => {
-                self.notes_panel.selected = self.notes_panel.selected.saturating_sub(1);
-                Ok(None)
-            }
-            KeyCode::Down => {
-                let len = self.current_notes().len();
-                if len > 0 {
-                    self.notes_panel.selected = (self.notes_panel.selected + 1).min(len - 1);
-                }
-                Ok(None)
-            }
-            KeyCode::Enter => {
-                let Some(note) = self.current_notes().get(self.notes_panel.selected).cloned()
-                else {
-                    return Ok(None);
-                };
-                self.reader.page = note.page.saturating_sub(1);
-                self.reader.invalidate_render();
-                self.notes_panel.open = false;
-                Ok(None)
-            }
-            KeyCode::Char('a') => {
-                self.notes_panel.input_open = true;
-                self.notes_panel.input_page = self.reader.page.saturating_add(1);
-                self.notes_panel.input.clear();
-                self.notes_panel.error = None;
-                Ok(None)
-            }
-            KeyCode::Char('d') => {
-                let Some(path) = self.reader.book_path.clone() else {
-                    return Ok(None);
-                };
-                let notes = self.ctx.notes_by_path.entry(path.clone()).or_default();
-                if self.notes_panel.selected < notes.len() {
-                    notes.remove(self.notes_panel.selected);
-                    notes.sort_by_key(|n| (n.page, n.body.clone()));
-                    self.ctx.dirty_note_paths.insert(path);
-                    if notes.is_empty() {
-                        self.notes_panel.selected = 0;
-                    } else {
-                        self.notes_panel.selected = self.notes_panel.selected.min(notes.len() - 1);
-                    }
-                }
-                Ok(None)
-            }
-            _ => Ok(None),
-        }
+    fn matches_favorite_and_status(&self, book: &Book) -> bool {
+        self.ctx.is_listed(book)
+            && (!self.ctx.favorites_only || book.favorite)
+            && self.ctx.status_filter.is_none_or(|s| s == book.status)
+            && self.ctx.added_within_filter(&book.path, unix_now_secs())
+            && self.ctx.kind_filter_matches(&book.path)
     }
 
-    fn handle_notes_input_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        match key.code {
-            KeyCode::Esc => {
-                self.notes_panel.input_open = false;
-                self.notes_panel.input.clear();
-                self.notes_panel.error = None;
-                Ok(None)
-            }
-            KeyCode::Enter => {
-                let body = self.notes_panel.input.trim().to_string();
-                if body.is_empty() {
-                    self.notes_panel.error = Some("Note cannot be empty".to_string());
-                    return Ok(None);
-                }
-                let Some(path) = self.reader.book_path.clone() else {
-                    return Ok(None);
-                };
-                let page = self.notes_panel.input_page.max(1);
-                let notes = self.ctx.notes_by_path.entry(path.clone()).or_default();
-                notes.push(Note { page, body });
-                notes.sort_by_key(|n| (n.page, n.body.clone()));
-                self.ctx.dirty_note_paths.insert(path);
-                self.notes_panel.input_open = false;
-                self.notes_panel.input.clear();
-                self.notes_panel.error = None;
-                Ok(None)
+    fn matches_collection(&self, book: &Book) -> bool {
+        let labels = self
+            .ctx
+            .labels_by_path
+            .get(&book.path)
+            .cloned()
+            .unwrap_or_default();
+        matches_collection_filter(&self.ctx.collection_filter, labels.collection.as_deref())
+    }
+
+    fn matches_tags(&self, book: &Book) -> bool {
+        let labels = self
+            .ctx
+            .labels_by_path
+            .get(&book.path)
+            .cloned()
+            .unwrap_or_default();
+        matches_tag_filter(&self.ctx.tag_filters, self.ctx.tag_match_mode, &labels.tags)
+    }
+
+    fn counts_by_collection_for_search(
+        &self,
+    ) -> (usize, usize, std::collections::HashMap<String, usize>) {
+        let mut total = 0usize;
+        let mut none_count = 0usize;
+        let mut by_name: std::collections::HashMap<String, usize> =
+            std::collections::HashMap::new();
+
+        let query = LibraryQuery::parse(&self.ctx.library_query);
+        for book in &self.ctx.books {
+            if !self.matches_query(&query, book) || !self.matches_favorite_and_status(book) {
+                continue;
             }
-            KeyCode::Backspace => {
-                self.notes_panel.input.pop();
-                Ok(None)
+            if !self.matches_tags(book) {
+                continue;
             }
-            KeyCode::Char(ch) => {
-                if !ch.is_control() {
-                    self.notes_panel.input.push(ch);
-                }
-                Ok(None)
+            total += 1;
+
+            let labels = self
+                .ctx
+                .labels_by_path
+                .get(&book.path)
+                .cloned()
+                .unwrap_or_default();
+            if let Some(col) = labels.collection.as_deref() {
+                *by_name.entry(col.to_string()).or_insert(0) += 1;
+            } else {
+                none_count += 1;
             }
-            _ => Ok(None),
         }
+
+        (total, none_count, by_name)
     }
 
-    fn handle_settings_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        match key.code {
-            KeyCode::Esc => {
-                self.settings_panel.open = false;
-                Ok(None)
-            }
-            KeyCode::Up => {
-                self.settings_panel.selected = self.settings_panel.selected.saturating_sub(1);
-                Ok(None)
+    fn counts_by_tag_for_search(&self) -> (usize, std::collections::HashMap<String, usize>) {
+        let mut total = 0usize;
+        let mut by_name: std::collections::HashMap<String, usize> =
+            std::collections::HashMap::new();
+
+        let query = LibraryQuery::parse(&self.ctx.library_query);
+        for book in &self.ctx.books {
+            if !self.matches_query(&query, book) || !self.matches_favorite_and_status(book) {
+                continue;
             }
-            KeyCode::Down => {
-                self.settings_panel.selected = (self.settings_panel.selected + 1)
-                    .min(SETTINGS_MENU_ITEM_COUNT.saturating_sub(1));
-                Ok(None)
+            if !self.matches_collection(book) {
+                continue;
             }
-            KeyCode::Left => {
-                if self.settings_panel.selected == SETTINGS_MENU_KITTY_IMAGE_QUALITY {
-                    self.ctx.settings.cycle_kitty_image_quality_prev();
+            total += 1;
+
+            let labels = self
+                .ctx
+                .labels_by_path
+                .get(&book.path)
+                .cloned()
+                .unwrap_or_default();
+            // A namespace counts each book under it once, however many of its tags it has.
+            let mut namespaces = std::collections::HashSet::new();
+            for tag in labels.tags {
+                if tag.trim().is_empty() {
+                    continue;
                 }
-                if self.settings_panel.selected == SETTINGS_MENU_THEME {
-                    self.ctx.settings.cycle_theme();
+                for namespace in tag_namespaces(tag.trim()) {
+                    namespaces.insert(namespace.to_ascii_lowercase());
                 }
-                Ok(None)
+                *by_name.entry(tag).or_insert(0) += 1;
             }
-            KeyCode::Right => {
-                if self.settings_panel.selected == SETTINGS_MENU_KITTY_IMAGE_QUALITY {
-                    self.ctx.settings.cycle_kitty_image_quality_next();
-                }
-                if self.settings_panel.selected == SETTINGS_MENU_THEME {
-                    self.ctx.settings.cycle_theme();
-                }
-                Ok(None)
-            }
-            KeyCode::Enter => {
-                match self.settings_panel.selected {
-                    SETTINGS_MENU_SCAN_PATHS => {
-                        self.scan_panel.open = true;
-                        self.scan_panel.selected = 0;
-                        self.scan_panel.input = join_roots(&self.ctx.settings);
-                        self.scan_panel.error = None;
-                        self.settings_panel.open = false;
-                    }
-                    SETTINGS_MENU_KITTY_IMAGE_QUALITY => {
-                        self.ctx.settings.cycle_kitty_image_quality_next();
-                    }
-                    SETTINGS_MENU_THEME => {
-                        self.ctx.settings.cycle_theme();
-                    }
-                    _ => {}
-                }
-                Ok(None)
+            for namespace in namespaces {
+                *by_name.entry(namespace).or_insert(0) += 1;
             }
-            _ => Ok(None),
         }
-    }
 
-    fn handle_scan_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        if key.modifiers.contains(KeyModifiers::CONTROL)
-            && let KeyCode::Char('u') = key.code
-        {
-            self.scan_panel.input.clear();
-            return Ok(None);
-        }
+        (total, by_name)
+    }
 
-        match key.code {
-            KeyCode::Esc => {
-                self.scan_panel.open = false;
-                self.scan_panel.error = None;
-                Ok(None)
-            }
-            KeyCode::Up => {
-                self.scan_panel.selected = self.scan_panel.selected.saturating_sub(1);
-                Ok(None)
-            }
-            KeyCode::Down => {
-                self.scan_panel.selected = (self.scan_panel.selected + 1).min(1);
-                Ok(None)
-            }
-            KeyCode::Left | KeyCode::Right => {
-                if self.scan_panel.selected == 1 {
-                    self.ctx.settings.cycle_scan_scope();
-                }
-                Ok(None)
-            }
-            KeyCode::Enter => {
-                let roots = parse_roots_input(&self.scan_panel.input);
-                if roots.is_empty() {
-                    self.scan_panel.error = Some("Enter at least one path".to_string());
-                    return Ok(None);
-                }
+    fn collection_entries_for_search(&self) -> Vec<CollectionEntry> {
+        let (total, none_count, counts) = self.counts_by_collection_for_search();
+        let mut out = Vec::new();
+        out.push(CollectionEntry {
+            label: "Any".to_string(),
+            filter: CollectionFilter::Any,
+            count: total,
+        });
+        out.push(CollectionEntry {
+            label: "None".to_string(),
+            filter: CollectionFilter::None,
+            count: none_count,
+        });
 
-                self.ctx.settings.library_roots = roots;
-                self.ctx.settings.normalize();
-                self.scan_panel.open = false;
-                self.scan_panel.error = None;
-                Ok(Some(UiExit::Rescan))
-            }
-            KeyCode::Backspace => {
-                if self.scan_panel.selected == 0 {
-                    self.scan_panel.input.pop();
-                }
-                Ok(None)
-            }
-            KeyCode::Char(ch) => {
-                if self.scan_panel.selected == 0 && !ch.is_control() {
-                    self.scan_panel.input.push(ch);
-                }
-                Ok(None)
-            }
-            _ => Ok(None),
+        for name in self.all_collection_names() {
+            let count = counts
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(&name))
+                .map(|(_, v)| *v)
+                .unwrap_or(0);
+            out.push(CollectionEntry {
+                label: name.clone(),
+                filter: CollectionFilter::Selected(name),
+                count,
+            });
         }
+        out
     }
 
-    fn selected_book_path(&self) -> Option<String> {
-        self.selected_visible_index()
-            .and_then(|idx| self.ctx.books.get(idx))
-            .map(|b| b.path.clone())
+    /// Rows of the Filters tag list: tags grouped under their namespaces, minus the rows
+    /// under folded ones.
+    fn tag_entries_for_search(&self) -> Vec<TagTreeEntry> {
+        let (_total, counts) = self.counts_by_tag_for_search();
+        let mut out = Vec::new();
+        for row in tag_tree(&self.all_tag_names(), &self.search_panel.folded_tags) {
+            let count = counts
+                .iter()
+                .filter(|(k, _)| k.eq_ignore_ascii_case(&row.filter))
+                .map(|(_, v)| *v)
+                .sum();
+            out.push(TagTreeEntry { row, count });
+        }
+        out
     }
 
-    fn sync_search_panel_tab(&mut self) {
-        let tab = match self.search_panel.mode {
-            SearchPanelMode::Filters => SearchTab::Search,
-            SearchPanelMode::Labels => SearchTab::Assign,
-            SearchPanelMode::Catalog => SearchTab::Manage,
+    /// Folds or unfolds the namespace under the cursor. On a tag, folds the namespace it is in
+    /// and moves the cursor there.
+    fn toggle_tag_fold(&mut self) {
+        let entries = self.tag_entries_for_search();
+        let Some(entry) = entries.get(
+            self.search_panel
+                .tag_cursor
+                .min(entries.len().saturating_sub(1)),
+        ) else {
+            return;
         };
-
-        if self.search_panel.tab != tab {
-            self.label_manager_panel.confirm_delete = None;
-            self.label_manager_panel.error = None;
-            self.label_manager_panel.filter_editing = false;
-            self.assign_labels_panel.query_editing = false;
-            self.assign_labels_panel.error = None;
-
-            self.search_panel.tab = tab;
+        let namespace = if entry.row.is_namespace() {
+            entry.row.filter.clone()
+        } else if let Some(namespace) = tag_namespaces(&entry.row.filter).last() {
+            namespace.to_string()
+        } else {
+            return;
+        };
+        let key = namespace.to_ascii_lowercase();
+        if !self.search_panel.folded_tags.remove(&key) {
+            self.search_panel.folded_tags.insert(key);
         }
-
-        if tab == SearchTab::Assign {
-            let selected_path = self.selected_book_path();
-            if selected_path != self.assign_labels_panel.book_path {
-                self.prepare_assign_labels_panel();
-            }
+        if let Some(pos) = self
+            .tag_entries_for_search()
+            .iter()
+            .position(|entry| entry.row.filter.eq_ignore_ascii_case(&namespace))
+        {
+            self.search_panel.tag_cursor = pos;
         }
     }
 
-    fn prepare_assign_labels_panel(&mut self) {
-        self.assign_labels_panel.focus = AssignFocus::Collections;
-        self.assign_labels_panel.collection_cursor = 0;
-        self.assign_labels_panel.tag_cursor = 0;
-        self.assign_labels_panel.collection_query.clear();
-        self.assign_labels_panel.tag_query.clear();
-        self.assign_labels_panel.query_editing = false;
-        self.assign_labels_panel.error = None;
-
-        if let Some(path) = self.selected_book_path()
-            && let Some(book) = self.ctx.books.iter().find(|b| b.path == path)
-        {
-            let mut labels = self
+    /// Authors or series of the books the other filters leave, with how many books each has.
+    fn name_entries_for_search(&self, kind: TagKind) -> Vec<TagEntry> {
+        let query = LibraryQuery::parse(&self.ctx.library_query);
+        let mut out: Vec<TagEntry> = Vec::new();
+        for book in &self.ctx.books {
+            if !self.matches_query(&query, book)
+                || !self.matches_favorite_and_status(book)
+                || !self.matches_collection(book)
+                || !self.matches_tags(book)
+            {
+                continue;
+            }
+            let labels = self
                 .ctx
                 .labels_by_path
-                .get(&path)
+                .get(&book.path)
                 .cloned()
                 .unwrap_or_default();
-            labels.normalize();
-            self.assign_labels_panel.book_path = Some(path);
-            self.assign_labels_panel.staged_favorite = book.favorite;
-            self.assign_labels_panel.staged = labels;
-        } else {
-            self.assign_labels_panel.book_path = None;
-            self.assign_labels_panel.staged_favorite = false;
-            self.assign_labels_panel.staged = BookLabels::default();
-            self.assign_labels_panel.error = Some("No selection".to_string());
+            let name = match kind {
+                TagKind::Author => book_author(book, &labels),
+                _ => labels.series_name(),
+            };
+            let Some(name) = name.map(str::trim).filter(|name| !name.is_empty()) else {
+                continue;
+            };
+            match out.iter_mut().find(|e| e.name.eq_ignore_ascii_case(name)) {
+                Some(entry) => entry.count += 1,
+                None => out.push(TagEntry {
+                    name: name.to_string(),
+                    count: 1,
+                }),
+            }
         }
+        out.sort_by_key(|e| e.name.to_ascii_lowercase());
+        out
     }
 
-    fn open_label_catalog_input_panel(
-        &mut self,
-        mode: LabelCatalogInputMode,
-        kind: TagKind,
-        from: Option<String>,
-        prefill: String,
-    ) {
-        self.label_catalog_input_panel.open = true;
-        self.label_catalog_input_panel.mode = mode;
-        self.label_catalog_input_panel.kind = kind;
-        self.label_catalog_input_panel.from = from;
-        self.label_catalog_input_panel.input = prefill;
-        self.label_catalog_input_panel.error = None;
-        self.settings_panel.open = false;
-        self.scan_panel.open = false;
+    /// Filters by the author or series under the cursor, or clears that filter when it is
+    /// the one already set.
+    fn toggle_name_cursor(&mut self, kind: TagKind) {
+        let entries = self.name_entries_for_search(kind);
+        let (cursor, filter) = match kind {
+            TagKind::Author => (
+                &mut self.search_panel.author_cursor,
+                &mut self.ctx.author_filter,
+            ),
+            _ => (
+                &mut self.search_panel.series_cursor,
+                &mut self.ctx.series_filter,
+            ),
+        };
+        if entries.is_empty() {
+            return;
+        }
+        *cursor = (*cursor).min(entries.len() - 1);
+        let name = &entries[*cursor].name;
+        if filter
+            .as_deref()
+            .is_some_and(|current| current.eq_ignore_ascii_case(name))
+        {
+            *filter = None;
+        } else {
+            *filter = Some(name.clone());
+        }
         self.normalize_selection_to_visible();
     }
 
-    fn label_manager_entries(&self, tab: LabelManagerTab) -> Vec<(String, usize)> {
-        let query = self.label_manager_panel.filter.trim().to_ascii_lowercase();
-
-        let mut collection_counts: std::collections::HashMap<String, usize> =
-            std::collections::HashMap::new();
-        let mut tag_counts: std::collections::HashMap<String, usize> =
-            std::collections::HashMap::new();
-        for labels in self.ctx.labels_by_path.values() {
-            if let Some(col) = labels.collection.as_deref() {
-                let col = col.trim();
-                if !col.is_empty() {
-                    *collection_counts
-                        .entry(col.to_ascii_lowercase())
-                        .or_insert(0) += 1;
-                }
-            }
-            for tag in &labels.tags {
-                let tag = tag.trim();
-                if tag.is_empty() {
-                    continue;
-                }
-                *tag_counts.entry(tag.to_ascii_lowercase()).or_insert(0) += 1;
-            }
+    fn apply_collection_cursor(&mut self) {
+        let entries = self.collection_entries_for_search();
+        if entries.is_empty() {
+            return;
         }
+        self.search_panel.collection_cursor = self
+            .search_panel
+            .collection_cursor
+            .min(entries.len().saturating_sub(1));
+        let entry = &entries[self.search_panel.collection_cursor];
+        self.ctx.collection_filter = entry.filter.clone();
+        self.normalize_selection_to_visible();
+    }
 
-        let names: &[String] = match tab {
-            LabelManagerTab::Collections => &self.ctx.known_collections,
-            LabelManagerTab::Tags => &self.ctx.known_tags,
-        };
+    fn toggle_tag_cursor(&mut self) {
+        let entries = self.tag_entries_for_search();
+        if entries.is_empty() {
+            return;
+        }
+        self.search_panel.tag_cursor = self
+            .search_panel
+            .tag_cursor
+            .min(entries.len().saturating_sub(1));
+        let tag = &entries[self.search_panel.tag_cursor].row.filter;
 
-        let mut out = Vec::new();
-        for name in names {
-            let trimmed = name.trim();
-            if trimmed.is_empty() {
-                continue;
-            }
-            if !query.is_empty() && !trimmed.to_ascii_lowercase().contains(&query) {
-                continue;
-            }
+        if let Some(pos) = self
+            .ctx
+            .tag_filters
+            .iter()
+            .position(|t| t.eq_ignore_ascii_case(tag))
+        {
+            self.ctx.tag_filters.remove(pos);
+        } else {
+            self.ctx.tag_filters.push(tag.clone());
+        }
+        self.normalize_tag_filters();
+        self.normalize_selection_to_visible();
+    }
 
-            let count = match tab {
-                LabelManagerTab::Collections => collection_counts
-                    .get(&trimmed.to_ascii_lowercase())
-                    .copied()
-                    .unwrap_or(0),
-                LabelManagerTab::Tags => tag_counts
-                    .get(&trimmed.to_ascii_lowercase())
-                    .copied()
-                    .unwrap_or(0),
-            };
-            out.push((trimmed.to_string(), count));
+    /// Closes the reader and resets its panels; progress and view state are recorded first.
+    fn close_reader(&mut self) {
+        self.record_reader_progress();
+        self.record_reader_view_state();
+        self.reader.close_book();
+        self.goto_panel = GotoPanel::default();
+        self.export_panel = ExportPanel::default();
+        self.pdfium_setup.open = false;
+        self.bookmarks_panel = BookmarksPanel::default();
+        self.notes_panel = NotesPanel::default();
+        self.notes_sidebar.focused = false;
+        self.notes_sidebar.selected = 0;
+        self.toc_panel = TocPanel::default();
+        self.links_panel = LinksPanel::default();
+        self.pdf_annotations_panel = PdfAnnotationsPanel::default();
+        self.search_in_book_panel = SearchInBookPanel::default();
+        self.thumbnail_strip.close();
+    }
+
+    /// Path and title of the open book when the reader shows its last page and the book is
+    /// not marked finished yet.
+    fn unfinished_book_at_last_page(&self) -> Option<(String, String)> {
+        let path = self.reader.book_path.as_deref()?;
+        if !self.reader.on_last_page(self.reader.mode) {
+            return None;
         }
-        out
+        let book = self.ctx.books.iter().find(|b| b.path == path)?;
+        (book.status != ReadingStatus::Finished).then(|| (book.path.clone(), book.title.clone()))
     }
 
-    fn label_manager_selected_target(&self) -> Option<LabelDeleteTarget> {
-        let tab = self.label_manager_panel.tab;
-        let entries = self.label_manager_entries(tab);
-        let cursor = match tab {
-            LabelManagerTab::Collections => self.label_manager_panel.collections_cursor,
-            LabelManagerTab::Tags => self.label_manager_panel.tags_cursor,
-        };
-        let (name, _count) = entries.get(cursor).cloned()?;
-        let kind = match tab {
-            LabelManagerTab::Collections => TagKind::Collection,
-            LabelManagerTab::Tags => TagKind::Tag,
+    fn open_history_panel(&mut self) {
+        self.history_panel = HistoryPanel {
+            open: true,
+            // The current book heads the list, so start on the previous one.
+            selected: usize::from(self.reader.open && self.ctx.recent_paths.len() > 1),
+            error: None,
         };
-        Some(LabelDeleteTarget { kind, name })
     }
 
-    fn handle_label_manager_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        if let Some(target) = self.label_manager_panel.confirm_delete.clone() {
-            match key.code {
-                KeyCode::Char('y') | KeyCode::Enter => {
-                    self.apply_delete_label(&target);
-                    self.label_manager_panel.confirm_delete = None;
-                    self.label_manager_panel.error = None;
-                }
-                KeyCode::Char('n') | KeyCode::Esc => {
-                    self.label_manager_panel.confirm_delete = None;
-                }
-                _ => {}
-            }
-            return Ok(None);
-        }
-
-        if key.modifiers.contains(KeyModifiers::CONTROL)
-            && let KeyCode::Char('u') = key.code
-        {
-            self.label_manager_panel.filter.clear();
-            self.label_manager_panel.filter_editing = false;
-            self.label_manager_panel.error = None;
-            return Ok(None);
-        }
-
-        if self.label_manager_panel.filter_editing {
-            match key.code {
-                KeyCode::Esc => {
-                    self.label_manager_panel.filter_editing = false;
-                }
-                KeyCode::Backspace => {
-                    self.label_manager_panel.filter.pop();
-                }
-                KeyCode::Tab => {
-                    self.label_manager_panel.filter_editing = false;
-                    self.label_manager_panel.tab = match self.label_manager_panel.tab {
-                        LabelManagerTab::Collections => LabelManagerTab::Tags,
-                        LabelManagerTab::Tags => LabelManagerTab::Collections,
-                    };
-                }
-                KeyCode::Char(ch) => {
-                    if !ch.is_control() {
-                        self.label_manager_panel.filter.push(ch);
-                    }
-                }
-                _ => {}
-            }
-
-            let entries = self.label_manager_entries(self.label_manager_panel.tab);
-            let cursor = match self.label_manager_panel.tab {
-                LabelManagerTab::Collections => &mut self.label_manager_panel.collections_cursor,
-                LabelManagerTab::Tags => &mut self.label_manager_panel.tags_cursor,
-            };
-            if entries.is_empty() {
-                *cursor = 0;
-            } else {
-                *cursor = (*cursor).min(entries.len().saturating_sub(1));
-            }
-            return Ok(None);
-        }
-
+    fn handle_history_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        let len = self.ctx.recent_paths.len();
         match key.code {
-            KeyCode::Tab => {
-                self.label_manager_panel.tab = match self.label_manager_panel.tab {
-                    LabelManagerTab::Collections => LabelManagerTab::Tags,
-                    LabelManagerTab::Tags => LabelManagerTab::Collections,
-                };
+            KeyCode::Esc | KeyCode::Char('`') => {
+                self.history_panel = HistoryPanel::default();
                 Ok(None)
             }
-            KeyCode::Char('/') => {
-                self.label_manager_panel.filter_editing = true;
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.history_panel = HistoryPanel::default();
                 Ok(None)
             }
             KeyCode::Up => {
-                let cursor = match self.label_manager_panel.tab {
-                    LabelManagerTab::Collections => {
-                        &mut self.label_manager_panel.collections_cursor
-                    }
-                    LabelManagerTab::Tags => &mut self.label_manager_panel.tags_cursor,
-                };
-                *cursor = cursor.saturating_sub(1);
+                self.history_panel.selected = self.history_panel.selected.saturating_sub(1);
                 Ok(None)
             }
             KeyCode::Down => {
-                let entries = self.label_manager_entries(self.label_manager_panel.tab);
-                let cursor = match self.label_manager_panel.tab {
-                    LabelManagerTab::Collections => {
-                        &mut self.label_manager_panel.collections_cursor
-                    }
-                    LabelManagerTab::Tags => &mut self.label_manager_panel.tags_cursor,
-                };
-                if !entries.is_empty() {
-                    *cursor = (*cursor).saturating_add(1).min(entries.len() - 1);
+                if len > 0 {
+                    self.history_panel.selected = (self.history_panel.selected + 1).min(len - 1);
                 }
                 Ok(None)
             }
-            KeyCode::Char('n') => {
-                let kind = match self.label_manager_panel.tab {
-                    LabelManagerTab::Collections => TagKind::Collection,
-                    LabelManagerTab::Tags => TagKind::Tag,
-                };
-                let prefill = self.label_manager_panel.filter.clone();
-                self.open_label_catalog_input_panel(
-                    LabelCatalogInputMode::Create,
-                    kind,
-                    None,
-                    prefill,
-                );
-                Ok(None)
-            }
-            KeyCode::Char('r') => {
-                let Some(target) = self.label_manager_selected_target() else {
-                    self.label_manager_panel.error = Some("Nothing selected".to_string());
+            KeyCode::Enter => {
+                let Some(path) = self
+                    .ctx
+                    .recent_paths
+                    .get(self.history_panel.selected)
+                    .cloned()
+                else {
                     return Ok(None);
                 };
-                self.open_label_catalog_input_panel(
-                    LabelCatalogInputMode::Rename,
-                    target.kind,
-                    Some(target.name.clone()),
-                    target.name,
-                );
-                Ok(None)
-            }
-            KeyCode::Char('d') => {
-                let Some(target) = self.label_manager_selected_target() else {
-                    self.label_manager_panel.error = Some("Nothing selected".to_string());
+                if self.reader.open && self.reader.book_path.as_deref() == Some(path.as_str()) {
+                    self.history_panel = HistoryPanel::default();
+                    return Ok(None);
+                }
+                if !book_file_exists(&path) {
+                    self.missing_paths.insert(path);
+                    self.history_panel.error = Some("File is missing".to_string());
+                    return Ok(None);
+                }
+                let Some(book) = self.ctx.books.iter().find(|b| b.path == path).cloned() else {
+                    self.history_panel.error = Some("Book is no longer in the library".to_string());
                     return Ok(None);
                 };
-                self.label_manager_panel.confirm_delete = Some(target);
+
+                self.history_panel = HistoryPanel::default();
+                if self.reader.open {
+                    self.close_reader();
+                }
+                if let Some(idx) = self.ctx.books.iter().position(|b| b.path == book.path) {
+                    self.ctx.selected = idx;
+                }
+                self.reader
+                    .open_book(&book, &mut self.ctx, &self.engine, &mut self.image_picker);
                 Ok(None)
             }
             _ => Ok(None),
         }
     }
 
-    fn assign_visible_collections(&self) -> Vec<String> {
-        let query = self
-            .assign_labels_panel
-            .collection_query
-            .trim()
-            .to_ascii_lowercase();
-        self.ctx
-            .known_collections
-            .iter()
-            .filter(|c| {
-                let c = c.trim();
-                !c.is_empty() && (query.is_empty() || c.to_ascii_lowercase().contains(&query))
-            })
-            .cloned()
-            .collect()
-    }
-
-    fn assign_visible_tags(&self) -> Vec<String> {
-        let query = self
-            .assign_labels_panel
-            .tag_query
-            .trim()
-            .to_ascii_lowercase();
-        self.ctx
-            .known_tags
-            .iter()
-            .filter(|t| {
-                let t = t.trim();
-                !t.is_empty() && (query.is_empty() || t.to_ascii_lowercase().contains(&query))
-            })
-            .cloned()
-            .collect()
-    }
-
-    fn commit_assign_labels_panel(&mut self) {
-        let Some(path) = self.assign_labels_panel.book_path.clone() else {
-            return;
-        };
-
-        if let Some(book) = self.ctx.books.iter_mut().find(|b| b.path == path) {
-            if book.favorite != self.assign_labels_panel.staged_favorite {
-                book.favorite = self.assign_labels_panel.staged_favorite;
-                self.ctx.dirty_favorite_paths.insert(path.clone());
+    fn handle_notes_search_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        let panel = &mut self.notes_search_panel;
+        let len = panel.hits.len();
+        match key.code {
+            KeyCode::Esc => {
+                self.notes_search_panel = NotesSearchPanel::default();
             }
-        }
-
-        let mut labels = self.assign_labels_panel.staged.clone();
-        labels.normalize();
-        self.ctx.ensure_known_labels(&labels);
-        if labels.tags.is_empty() && labels.collection.is_none() {
-            self.ctx.labels_by_path.remove(&path);
-        } else {
-            self.ctx.labels_by_path.insert(path.clone(), labels);
-        }
-        self.ctx.dirty_label_paths.insert(path);
-
-        self.assign_labels_panel.query_editing = false;
-        self.assign_labels_panel.error = None;
-        self.normalize_selection_to_visible();
-    }
-
-    fn handle_assign_labels_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
-        if self.assign_labels_panel.book_path.is_none() {
-            return Ok(None);
-        }
-
-        if key.modifiers.contains(KeyModifiers::CONTROL)
-            && let KeyCode::Char('u') = key.code
-        {
-            match self.assign_labels_panel.focus {
-                AssignFocus::Collections => self.assign_labels_panel.collection_query.clear(),
-                AssignFocus::Tags => self.assign_labels_panel.tag_query.clear(),
+            KeyCode::Up => panel.selected = panel.selected.saturating_sub(1),
+            KeyCode::Down => panel.selected = (panel.selected + 1).min(len.saturating_sub(1)),
+            KeyCode::PageUp => {
+                panel.selected = panel.selected.saturating_sub(NOTES_SEARCH_PAGE_ROWS)
             }
-            return Ok(None);
-        }
-
-        if self.assign_labels_panel.query_editing {
-            match key.code {
-                KeyCode::Esc => {
-                    self.assign_labels_panel.query_editing = false;
+            KeyCode::PageDown => {
+                panel.selected =
+                    (panel.selected + NOTES_SEARCH_PAGE_ROWS).min(len.saturating_sub(1));
+            }
+            KeyCode::Enter => {
+                let Some(hit) = panel.hits.get(panel.selected).cloned() else {
+                    return Ok(None);
+                };
+                if !book_file_exists(&hit.path) {
+                    self.missing_paths.insert(hit.path);
+                    self.notes_search_panel.error = Some("File is missing".to_string());
+                    return Ok(None);
                 }
-                KeyCode::Enter => {
-                    self.commit_assign_labels_panel();
+                let book = book_for_path(&self.ctx.books, &hit.path);
+                if let Some(err) = self.broken_book_error(&book) {
+                    self.notes_search_panel.error = Some(format!("File is corrupt: {err}"));
+                    return Ok(None);
                 }
-                KeyCode::Backspace => match self.assign_labels_panel.focus {
-                    AssignFocus::Collections => {
-                        self.assign_labels_panel.collection_query.pop();
-                    }
-                    AssignFocus::Tags => {
-                        self.assign_labels_panel.tag_query.pop();
-                    }
-                },
-                KeyCode::Tab => {
-                    self.assign_labels_panel.query_editing = false;
-                    self.assign_labels_panel.focus = match self.assign_labels_panel.focus {
-                        AssignFocus::Collections => AssignFocus::Tags,
-                        AssignFocus::Tags => AssignFocus::Collections,
-                    };
+                self.notes_search_panel = NotesSearchPanel::default();
+                if let Some(idx) = self.ctx.books.iter().position(|b| b.path == hit.path) {
+                    self.ctx.selected = idx;
                 }
-                KeyCode::Char(ch) => {
-                    if !ch.is_control() {
-                        match self.assign_labels_panel.focus {
-                            AssignFocus::Collections => {
-                                self.assign_labels_panel.collection_query.push(ch);
-                            }
-                            AssignFocus::Tags => {
-                                self.assign_labels_panel.tag_query.push(ch);
-                            }
-                        }
-                    }
+                self.reader
+                    .open_book(&book, &mut self.ctx, &self.engine, &mut self.image_picker);
+                self.reader.page = hit.page.saturating_sub(1);
+                if let Some(total) = self.reader.total_pages
+                    && total > 0
+                {
+                    self.reader.page = self.reader.page.min(total - 1);
                 }
-                _ => {}
+                self.reader.invalidate_render();
+                let what = match hit.kind {
+                    NoteHitKind::Note => "note",
+                    NoteHitKind::Bookmark => "bookmark",
+                };
+                self.reader.notice = Some(format!("{what} on page {}", hit.page));
             }
-
-            let collections = self.assign_visible_collections();
-            let tags = self.assign_visible_tags();
-            self.assign_labels_panel.collection_cursor = self
-                .assign_labels_panel
-                .collection_cursor
-                .min(collections.len()); // +1 for "(none)"
-            if tags.is_empty() {
-                self.assign_labels_panel.tag_cursor = 0;
-            } else {
-                self.assign_labels_panel.tag_cursor =
-                    self.assign_labels_panel.tag_cursor.min(tags.len() - 1);
+            _ => {
+                if panel.input.handle_key(key) {
+                    panel.search(
+                        &self.ctx.notes_by_path,
+                        &self.ctx.bookmarks_by_path,
+                        |path| book_for_path(&self.ctx.books, path).title,
+                    );
+                } else {
+                    self.redraw.ignore_key();
+                }
             }
-            return Ok(None);
         }
+        Ok(None)
+    }
 
-        match key.code {
-            KeyCode::Tab => {
-                self.assign_labels_panel.focus = match self.assign_labels_panel.focus {
-                    AssignFocus::Collections => AssignFocus::Tags,
-                    AssignFocus::Tags => AssignFocus::Collections,
-                };
+    fn handle_reader_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        let Some(action) = self.key_bindings.action(KeyScope::Reader, &key) else {
+            self.redraw.ignore_key();
+            return Ok(None);
+        };
+        match action {
+            KeyAction::CloseReader => {
+                let finished_book = self.unfinished_book_at_last_page();
+                self.close_reader();
+                if self.boot_reader_session {
+                    return Ok(Some(UiExit::Quit));
+                }
+                if let Some((path, title)) = finished_book {
+                    self.library_notice =
+                        Some(format!("reached the end of {title}; y marks it finished"));
+                    self.finish_prompt = Some(path);
+                }
                 Ok(None)
             }
-            KeyCode::Char('/') => {
-                self.assign_labels_panel.query_editing = true;
+            KeyAction::SearchInBook => {
+                self.open_search_in_book_panel();
                 Ok(None)
             }
-            KeyCode::Char('f') => {
-                self.assign_labels_panel.staged_favorite =
-                    !self.assign_labels_panel.staged_favorite;
+            KeyAction::OpenHistory => {
+                self.open_history_panel();
                 Ok(None)
             }
-            KeyCode::Up => {
-                match self.assign_labels_panel.focus {
-                    AssignFocus::Collections => {
-                        self.assign_labels_panel.collection_cursor =
-                            self.assign_labels_panel.collection_cursor.saturating_sub(1);
-                    }
-                    AssignFocus::Tags => {
-                        self.assign_labels_panel.tag_cursor =
-                            self.assign_labels_panel.tag_cursor.saturating_sub(1);
-                    }
-                }
+            KeyAction::PrevAnnotation | KeyAction::NextAnnotation => {
+                self.jump_to_annotation(action == KeyAction::NextAnnotation);
                 Ok(None)
             }
-            KeyCode::Down => {
-                match self.assign_labels_panel.focus {
-                    AssignFocus::Collections => {
-                        let collections = self.assign_visible_collections();
-                        let max = collections.len() + 1;
-                        if max > 0 {
-                            self.assign_labels_panel.collection_cursor = self
-                                .assign_labels_panel
-                                .collection_cursor
-                                .saturating_add(1)
-                                .min(max - 1);
-                        }
-                    }
-                    AssignFocus::Tags => {
-                        let tags = self.assign_visible_tags();
-                        if !tags.is_empty() {
-                            self.assign_labels_panel.tag_cursor = self
-                                .assign_labels_panel
-                                .tag_cursor
-                                .saturating_add(1)
-                                .min(tags.len() - 1);
-                        }
-                    }
+            KeyAction::GotoPage => {
+                self.goto_panel.open = true;
+                self.goto_panel.error = None;
+                self.goto_panel
+                    .input
+                    .set(self.reader.page.saturating_add(1).to_string());
+                self.bookmarks_panel.open = false;
+                self.notes_panel.open = false;
+                self.toc_panel.open = false;
+                Ok(None)
+            }
+            KeyAction::DumpPage => {
+                if let Some(book) = self.reader.current_book() {
+                    let dir = Path::new(&self.ctx.cwd).join("tmp");
+                    std::fs::create_dir_all(&dir)?;
+
+                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                    std::hash::Hash::hash(&book.path, &mut hasher);
+                    let id = hasher.finish();
+
+                    let path = dir.join(format!(
+                        "bookshelf-reader-debug-{id:016x}-p{}.txt",
+                        self.reader.page + 1
+                    ));
+                    let term = std::env::var("TERM").unwrap_or_default();
+                    let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+                    let tmux = std::env::var("TMUX").unwrap_or_default();
+                    let kitty_window_id = std::env::var("KITTY_WINDOW_ID").unwrap_or_default();
+                    let protocol_label = image_protocol::protocol_label(&self.image_picker);
+                    let image_supported = image_protocol::image_supported(&self.image_picker);
+                    let kitty_supported = image_protocol::kitty_supported(&self.image_picker);
+                    let capabilities = self.image_picker.capabilities();
+                    let (font_w, font_h) = self.image_picker.font_size();
+                    let timing_block = self.reader.last_image_timings.map(|t| {
+                        let rasterize_ms = t
+                            .rasterize_ms
+                            .map(|v| v.to_string())
+                            .unwrap_or_else(|| "-".to_string());
+                        format!(
+                            "\nreader-image:\n  total_ms={}\n  rasterize_ms={}\n  viewport_ms={}\n  downscale_ms={}\n  protocol_ms={}\n  viewport_px={}x{}\n  transmit_px={}x{}\n  render_width_px={}\n  quality={}\n  filter={}\n  max_render_px={}\n  max_transmit_px={}\n",
+                            t.total_ms,
+                            rasterize_ms,
+                            t.viewport_ms,
+                            t.downscale_ms,
+                            t.protocol_ms,
+                            t.viewport_px.0,
+                            t.viewport_px.1,
+                            t.transmit_px.0,
+                            t.transmit_px.1,
+                            t.render_width_px,
+                            self.ctx.settings.kitty_image_quality,
+                            t.filter,
+                            t.max_render_px,
+                            t.max_transmit_px,
+                        )
+                    });
+                    let cache_block = format!(
+                        "\npage-image-cache:\n  entries={}\n  cache_bytes={}\n  limit_bytes={}\n  page_bytes={}\n",
+                        self.reader.page_image_cache.len(),
+                        self.reader.page_image_cache.bytes(),
+                        self.ctx.settings.image_cache_limit.max_bytes(),
+                        self.reader
+                            .page_image
+                            .as_ref()
+                            .map_or(0, CachedPageImage::bytes),
+                    );
+                    let debug = format!(
+                        "env:\n  TERM={term}\n  TERM_PROGRAM={term_program}\n  TMUX={tmux}\n  KITTY_WINDOW_ID={kitty_window_id}\n\nratatui-image:\n  protocol={protocol_label}\n  image_supported={image_supported}\n  kitty_supported={kitty_supported}\n  capabilities={capabilities:?}\n  font_size_px={font_w}x{font_h}{}{cache_block}\n-----\n\n{}",
+                        timing_block.unwrap_or_default(),
+                        self.engine.debug_page_text(&book, self.reader.page)?
+                    );
+                    std::fs::write(&path, debug)?;
+                    self.reader.notice = Some(format!("wrote {}", path.display()));
                 }
                 Ok(None)
             }
-            KeyCode::Char(' ') => {
-                match self.assign_labels_panel.focus {
-                    AssignFocus::Collections => {
-                        let collections = self.assign_visible_collections();
-                        if self.assign_labels_panel.collection_cursor == 0 {
-                            self.assign_labels_panel.staged.collection = None;
-                        } else if let Some(name) = collections
-                            .get(self.assign_labels_panel.collection_cursor - 1)
-                            .cloned()
-                        {
-                            self.assign_labels_panel.staged.collection = Some(name);
-                        }
+            KeyAction::CopyPageText => {
+                self.copy_page_text();
+                Ok(None)
+            }
+            KeyAction::ExportPages => {
+                self.export_panel = ExportPanel {
+                    open: true,
+                    range: TextInput::new("current"),
+                    dir: TextInput::new(self.ctx.cwd.clone()),
+                    dpi: TextInput::new(DEFAULT_EXPORT_DPI.to_string()),
+                    focus: ExportField::Range,
+                    error: None,
+                };
+                Ok(None)
+            }
+            KeyAction::OpenBookmarks => {
+                self.bookmarks_panel.open = true;
+                self.bookmarks_panel.selected = 0;
+                self.goto_panel.open = false;
+                self.notes_panel.open = false;
+                self.toc_panel.open = false;
+                Ok(None)
+            }
+            KeyAction::OpenNotes => {
+                self.notes_panel.open = true;
+                self.notes_panel.selected = 0;
+                self.notes_panel.error = None;
+                self.goto_panel.open = false;
+                self.bookmarks_panel.open = false;
+                self.toc_panel.open = false;
+                Ok(None)
+            }
+            KeyAction::ToggleNotesSidebar => {
+                self.notes_sidebar.toggle();
+                self.reader.notice = Some(if !self.notes_sidebar.open {
+                    "notes pane: off".to_string()
+                } else if self.notes_sidebar.collapsed() {
+                    format!(
+                        "notes pane: on, shown from {} columns",
+                        notes_sidebar::NOTES_SIDEBAR_MIN_WIDTH
+                    )
+                } else {
+                    "notes pane: on (Tab switches focus)".to_string()
+                });
+                Ok(None)
+            }
+            KeyAction::OpenToc => {
+                self.open_toc_panel();
+                Ok(None)
+            }
+            KeyAction::OpenLinks => {
+                self.open_links_panel();
+                Ok(None)
+            }
+            KeyAction::OpenPdfAnnotations => {
+                self.open_pdf_annotations_panel(false);
+                Ok(None)
+            }
+            KeyAction::LinkBack => {
+                match self.links_panel.back() {
+                    Some(page) => {
+                        self.reader.page = page;
+                        self.reader.invalidate_render();
+                        self.reader.notice = Some(format!("back to page {}", page + 1));
                     }
-                    AssignFocus::Tags => {
-                        let tags = self.assign_visible_tags();
-                        let Some(name) = tags.get(self.assign_labels_panel.tag_cursor).cloned()
-                        else {
-                            return Ok(None);
+                    None => self.reader.notice = Some("no link to go back from".to_string()),
+                }
+                Ok(None)
+            }
+            KeyAction::ToggleImageMode => {
+                match self.reader.mode {
+                    ReaderMode::Text => {
+                        let pdfium = match self.reader.current_book() {
+                            Some(book) if self.engine.renders_with_pdfium(&book) => {
+                                self.engine.check_pdfium()
+                            }
+                            _ => Ok(()),
                         };
-                        if let Some(pos) = self
-                            .assign_labels_panel
-                            .staged
-                            .tags
-                            .iter()
-                            .position(|t| t.eq_ignore_ascii_case(&name))
+                        if let Err(err) = pdfium
+                            && image_protocol::image_supported(&self.image_picker)
                         {
-                            self.assign_labels_panel.staged.tags.remove(pos);
+                            self.show_pdfium_setup(&err);
+                        } else if image_protocol::image_supported(&self.image_picker) {
+                            image_protocol::prefer_graphics_protocol(&mut self.image_picker);
+                            self.reader.mode = ReaderMode::Image;
+                            self.reader.enter_image_mode();
+                            let label = image_protocol::protocol_label(&self.image_picker);
+                            let mut notice = format!("mode: image ({label})");
+                            if image_protocol::in_iterm_env() && label == "iterm2" {
+                                notice.push_str("; enable iTerm2 inline images (imgcat) if blank");
+                            }
+                            self.reader.notice = Some(notice);
                         } else {
-                            self.assign_labels_panel.staged.tags.push(name);
+                            self.reader.mode = ReaderMode::Text;
+                            let in_tmux = std::env::var_os("TMUX").is_some();
+                            self.reader.notice = Some(if in_tmux {
+                                "image mode needs a graphics protocol + tmux allow-passthrough; press k to open a graphics terminal"
+                                    .to_string()
+                            } else {
+                                "image mode requires kitty, sixel or iterm2 graphics; press k to open a graphics terminal"
+                                    .to_string()
+                            });
                         }
-                        self.assign_labels_panel.staged.normalize();
+                    }
+                    ReaderMode::Image => {
+                        self.reader.mode = ReaderMode::Text;
+                        self.reader.leave_image_mode();
+                        self.reader.notice = Some("mode: text".to_string());
                     }
                 }
                 Ok(None)
             }
-            _ => Ok(None),
-        }
-    }
-
-    fn remove_name_case_insensitive(values: &mut Vec<String>, name: &str) {
-        values.retain(|v| !v.eq_ignore_ascii_case(name));
-    }
-
-    fn apply_delete_label(&mut self, target: &LabelDeleteTarget) {
-        self.ctx
-            .dirty_label_catalog_ops
-            .push(LabelCatalogOp::Delete {
-                kind: target.kind,
-                name: target.name.clone(),
-            });
-        match target.kind {
-            TagKind::Tag => {
-                Self::remove_name_case_insensitive(&mut self.ctx.known_tags, &target.name);
-                self.ctx
-                    .tag_filters
-                    .retain(|t| !t.eq_ignore_ascii_case(&target.name));
-                self.normalize_tag_filters();
+            KeyAction::CycleTextMode => {
+                if self.reader.mode == ReaderMode::Text {
+                    self.reader.cycle_text_mode();
+                    self.reader.invalidate_render();
+                    self.reader.notice = Some(format!("text: {}", self.reader.text_mode));
+                }
+                Ok(None)
             }
-            TagKind::Collection => {
-                Self::remove_name_case_insensitive(&mut self.ctx.known_collections, &target.name);
-                if let CollectionFilter::Selected(selected) = &self.ctx.collection_filter
-                    && selected.eq_ignore_ascii_case(&target.name)
-                {
-                    self.ctx.collection_filter = CollectionFilter::Any;
+            KeyAction::ToggleTrimHeaders => {
+                if self.reader.mode == ReaderMode::Text {
+                    self.ctx.settings.toggle_reader_trim_headers_footers();
+                    self.reader.invalidate_render();
+                    let status = if self.ctx.settings.reader_trim_headers_footers {
+                        "on"
+                    } else {
+                        "off"
+                    };
+                    self.reader.notice = Some(format!("trim headers/footers: {status}"));
                 }
+                Ok(None)
             }
-        }
-        self.ctx.normalize_label_catalog();
-
-        let mut to_remove = Vec::new();
-        for (path, labels) in self.ctx.labels_by_path.iter_mut() {
-            match target.kind {
-                TagKind::Tag => {
-                    labels
-                        .tags
-                        .retain(|t| !t.eq_ignore_ascii_case(&target.name));
-                }
-                TagKind::Collection => {
-                    if labels
-                        .collection
-                        .as_deref()
-                        .is_some_and(|c| c.eq_ignore_ascii_case(&target.name))
-                    {
-                        labels.collection = None;
+            KeyAction::ToggleShowTrimmed => {
+                if self.reader.mode == ReaderMode::Text {
+                    if self.ctx.settings.reader_trim_headers_footers {
+                        self.reader.show_trimmed_furniture = !self.reader.show_trimmed_furniture;
+                        self.reader.invalidate_render();
+                        let status = if self.reader.show_trimmed_furniture {
+                            "shown struck through"
+                        } else {
+                            "hidden"
+                        };
+                        self.reader.notice = Some(format!("trimmed lines: {status}"));
+                    } else {
+                        self.reader.notice = Some("trim headers/footers is off".to_string());
                     }
                 }
+                Ok(None)
             }
-            labels.normalize();
-            if labels.tags.is_empty() && labels.collection.is_none() {
-                to_remove.push(path.clone());
+            KeyAction::ToggleColumns => {
+                if self.reader.mode == ReaderMode::Text {
+                    self.ctx.settings.toggle_reader_detect_columns();
+                    self.reader.invalidate_render();
+                    let status = if self.ctx.settings.reader_detect_columns {
+                        "auto"
+                    } else {
+                        "single"
+                    };
+                    self.reader.notice = Some(format!("columns: {status}"));
+                }
+                Ok(None)
             }
-        }
-        for path in to_remove {
-            self.ctx.labels_by_path.remove(&path);
-        }
-
-        match target.kind {
-            TagKind::Tag => {
-                self.assign_labels_panel
-                    .staged
-                    .tags
-                    .retain(|t| !t.eq_ignore_ascii_case(&target.name));
-                self.assign_labels_panel.staged.normalize();
+            KeyAction::NarrowText | KeyAction::WidenText => {
+                if self.reader.mode == ReaderMode::Text {
+                    if self.reader.text_mode == ReaderTextMode::Raw {
+                        self.reader.notice =
+                            Some("text width applies to wrap and reflow".to_string());
+                        return Ok(None);
+                    }
+                    let full = self.reader.render_key.map_or(80, |key| key.width);
+                    self.ctx
+                        .settings
+                        .step_reader_text_width(action == KeyAction::WidenText, full);
+                    self.reader.invalidate_render();
+                    self.reader.notice = Some(match self.ctx.settings.reader_text_width {
+                        0 => "text width: full".to_string(),
+                        width => format!("text width: {width}"),
+                    });
+                }
+                Ok(None)
             }
-            TagKind::Collection => {
-                if self
-                    .assign_labels_panel
-                    .staged
-                    .collection
-                    .as_deref()
-                    .is_some_and(|c| c.eq_ignore_ascii_case(&target.name))
-                {
-                    self.assign_labels_panel.staged.collection = None;
+            KeyAction::PanLeft if self.reader.mode == ReaderMode::Image => {
+                self.reader.pan_image_by_cells(&self.image_picker, -5, 0);
+                Ok(None)
+            }
+            KeyAction::PanRight if self.reader.mode == ReaderMode::Image => {
+                self.reader.pan_image_by_cells(&self.image_picker, 5, 0);
+                Ok(None)
+            }
+            // Text pages with lines wider than the screen, such as tables, pan sideways.
+            KeyAction::PanLeft | KeyAction::PanRight
+                if self.reader.mode == ReaderMode::Text && self.reader.text_overflow() > 0 =>
+            {
+                if !self.reader.pan_text(action == KeyAction::PanRight) {
+                    self.redraw.ignore_key();
                 }
+                Ok(None)
             }
+            // Otherwise the pan keys turn pages like before.
+            KeyAction::PrevPage | KeyAction::PanLeft => {
+                let turned = if self.reader.mode == ReaderMode::Image && self.reader.spread {
+                    self.reader.prev_spread()
+                } else {
+                    self.reader.prev_page()
+                };
+                if !turned {
+                    self.redraw.ignore_key();
+                }
+                Ok(None)
+            }
+            KeyAction::NextPage | KeyAction::PanRight => {
+                let turned = if self.reader.mode == ReaderMode::Image && self.reader.spread {
+                    self.reader.next_spread()
+                } else {
+                    self.reader.next_page()
+                };
+                if !turned {
+                    self.redraw.ignore_key();
+                }
+                Ok(None)
+            }
+            KeyAction::ScrollUp => {
+                if self.reader.mode == ReaderMode::Image {
+                    self.reader.pan_image_by_cells(&self.image_picker, 0, -3);
+                } else if !self.reader.scroll_up() {
+                    self.redraw.ignore_key();
+                }
+                Ok(None)
+            }
+            KeyAction::ScrollDown => {
+                if self.reader.mode == ReaderMode::Image {
+                    self.reader.pan_image_by_cells(&self.image_picker, 0, 3);
+                } else if !self.reader.scroll_down() {
+                    self.redraw.ignore_key();
+                }
+                Ok(None)
+            }
+            KeyAction::PageUp => {
+                if self.reader.mode == ReaderMode::Image {
+                    let step = self
+                        .reader
+                        .render_key
+                        .map(|k| k.height.saturating_sub(2))
+                        .unwrap_or(10);
+                    self.reader
+                        .pan_image_by_cells(&self.image_picker, 0, -i32::from(step));
+                } else if !self.reader.scroll_text_page(false) {
+                    self.redraw.ignore_key();
+                }
+                Ok(None)
+            }
+            KeyAction::PageDown => {
+                if self.reader.mode == ReaderMode::Image {
+                    let step = self
+                        .reader
+                        .render_key
+                        .map(|k| k.height.saturating_sub(2))
+                        .unwrap_or(10);
+                    self.reader
+                        .pan_image_by_cells(&self.image_picker, 0, i32::from(step));
+                } else if !self.reader.scroll_text_page(true) {
+                    self.redraw.ignore_key();
+                }
+                Ok(None)
+            }
+            KeyAction::OpenExternal => {
+                if let Some(path) = self.reader.book_path.as_deref() {
+                    let page = self.reader.page.saturating_add(1);
+                    self.reader.notice = Some(
+                        match external_viewer::spawn_viewer(
+                            &self.ctx.settings.external_viewer,
+                            &bookshelf_core::decode_path(path),
+                            page,
+                        ) {
+                            Ok(()) => format!("opened page {page} in external viewer"),
+                            Err(err) => format!("external viewer failed: {err:#}"),
+                        },
+                    );
+                }
+                Ok(None)
+            }
+            KeyAction::SpawnKitty => {
+                if self.reader.mode == ReaderMode::Text
+                    && !image_protocol::image_supported(&self.image_picker)
+                {
+                    let reader = self.reader.book_path.as_deref().map(|book_path| {
+                        terminal_spawn::ReaderBootstrap {
+                            book_path,
+                            page_index: self.reader.page,
+                        }
+                    });
+                    match terminal_spawn::spawn_graphics_terminal(
+                        &self.ctx.settings.graphics_terminal,
+                        reader,
+                        self.db_path.as_deref(),
+                    ) {
+                        Ok(spawned) => {
+                            self.spawned_terminals.push(spawned.child);
+                            self.reader.notice =
+                                Some(format!("opened the reader in {}", spawned.name));
+                        }
+                        Err(err) => {
+                            self.reader.notice = Some(format!("graphics terminal failed: {err}"))
+                        }
+                    }
+                }
+                Ok(None)
+            }
+            KeyAction::ZoomIn => {
+                if self.reader.mode == ReaderMode::Image {
+                    self.reader.zoom_image_in();
+                } else {
+                    self.set_line_spacing(Some(true));
+                }
+                Ok(None)
+            }
+            KeyAction::ZoomOut => {
+                if self.reader.mode == ReaderMode::Image {
+                    self.reader.zoom_image_out();
+                } else {
+                    self.set_line_spacing(Some(false));
+                }
+                Ok(None)
+            }
+            KeyAction::ResetZoom => {
+                if self.reader.mode == ReaderMode::Image {
+                    self.reader.reset_image_view();
+                } else {
+                    self.set_line_spacing(None);
+                }
+                Ok(None)
+            }
+            KeyAction::ToggleInvertColors => {
+                if self.reader.mode == ReaderMode::Image {
+                    self.ctx.settings.toggle_reader_invert_colors();
+                    // The cached bitmap is keyed on the flag; drop the shown frame so it
+                    // re-renders.
+                    self.reader.current_image = None;
+                    self.reader.render_key = None;
+                    let status = if self.ctx.settings.reader_invert_colors {
+                        "on"
+                    } else {
+                        "off"
+                    };
+                    self.reader.notice = Some(format!("invert colors: {status}"));
+                } else {
+                    self.reader.notice = Some("invert colors needs image mode (m)".to_string());
+                }
+                Ok(None)
+            }
+            KeyAction::CycleImageFit => {
+                if self.reader.mode == ReaderMode::Image {
+                    self.reader.cycle_image_fit();
+                }
+                Ok(None)
+            }
+            KeyAction::RotateCounterClockwise | KeyAction::RotateClockwise => {
+                if self.reader.mode == ReaderMode::Image {
+                    let rotation = if action == KeyAction::RotateClockwise {
+                        self.reader.rotation.clockwise()
+                    } else {
+                        self.reader.rotation.counter_clockwise()
+                    };
+                    self.reader.set_rotation(rotation);
+                } else {
+                    self.reader.notice = Some("rotation needs image mode (m)".to_string());
+                }
+                Ok(None)
+            }
+            KeyAction::ToggleSpread => {
+                if self.reader.mode == ReaderMode::Image {
+                    self.reader.spread = !self.reader.spread;
+                    self.reader.invalidate_render();
+                    let status = if self.reader.spread { "on" } else { "off" };
+                    self.reader.notice = Some(format!("spread: {status}"));
+                } else {
+                    self.reader.notice = Some("spread view needs image mode (m)".to_string());
+                }
+                Ok(None)
+            }
+            KeyAction::ToggleSpreadCover => {
+                if self.reader.mode == ReaderMode::Image && self.reader.spread {
+                    self.reader.spread_cover_alone = !self.reader.spread_cover_alone;
+                    self.reader.invalidate_render();
+                    self.reader.notice = Some(if self.reader.spread_cover_alone {
+                        "spread: cover alone".to_string()
+                    } else {
+                        "spread: pairs from page 1".to_string()
+                    });
+                }
+                Ok(None)
+            }
+            KeyAction::Redraw => {
+                self.retransmit_reader_image();
+                self.clear_terminal = true;
+                Ok(None)
+            }
+            KeyAction::SaveNow => {
+                self.reader.notice = Some(self.save_now());
+                Ok(None)
+            }
+            KeyAction::ToggleThumbnails => {
+                if self.reader.mode == ReaderMode::Image {
+                    if self.thumbnail_strip.open {
+                        self.thumbnail_strip.close();
+                    } else {
+                        self.thumbnail_strip.open = true;
+                        self.thumbnail_strip.follow(self.reader.page);
+                    }
+                } else {
+                    self.reader.notice = Some("thumbnails need image mode (m)".to_string());
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Sends the shown page image to the terminal again on the next frame, for terminals that
+    /// dropped it. Returns false when the reader shows no image.
+    fn retransmit_reader_image(&mut self) -> bool {
+        if !self.reader.open || self.reader.mode != ReaderMode::Image {
+            return false;
+        }
+        self.reader.current_image = None;
+        self.reader.render_key = None;
+        true
+    }
+
+    /// The text-mode zoom: widens (`Some(true)`) or tightens the line spacing, or goes back
+    /// to single spacing. The view stays on the same lines of text.
+    fn set_line_spacing(&mut self, wider: Option<bool>) {
+        if self.reader.text_mode == ReaderTextMode::Raw {
+            self.reader.notice = Some("line spacing applies to wrap and reflow".to_string());
+            return;
+        }
+        let before = self.ctx.settings.reader_line_spacing;
+        match wider {
+            Some(wider) => self.ctx.settings.step_reader_line_spacing(wider),
+            None => self.ctx.settings.reader_line_spacing = READER_LINE_SPACING_CHOICES[0],
+        }
+        let after = self.ctx.settings.reader_line_spacing;
+        let scroll = u32::from(self.reader.scroll) * u32::from(after) / u32::from(before.max(1));
+        self.reader.invalidate_render();
+        self.reader.line_spacing = after;
+        self.reader.scroll = u16::try_from(scroll).unwrap_or(u16::MAX);
+        self.reader.notice = Some(format!("line spacing: {}.{}", after / 10, after % 10));
+    }
+
+    fn copy_page_text(&mut self) {
+        let text = match self.reader.page_text(&self.ctx, &self.engine) {
+            Ok(text) => text,
+            Err(err) => {
+                self.reader.notice = Some(format!("copy failed: {err}"));
+                return;
+            }
+        };
+        if is_non_text_page(&text) {
+            self.reader.notice = Some("nothing to copy: page has no text layer".to_string());
+            return;
+        }
+        self.reader.notice = Some(match clipboard::copy_to_clipboard(&text) {
+            Ok(()) => format!("copied {} chars", text.chars().count()),
+            Err(err) => format!("copy failed: {err}"),
+        });
+    }
+
+    fn thumbnail_strip_visible(&self) -> bool {
+        self.thumbnail_strip.open && self.reader.mode == ReaderMode::Image
+    }
+
+    /// Left/Right move the thumbnail cursor and Enter jumps to it; other keys go to the reader.
+    fn handle_thumbnail_strip_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        let strip = &mut self.thumbnail_strip;
+        match key.code {
+            KeyCode::Esc => {
+                strip.close();
+                Ok(None)
+            }
+            KeyCode::Left => {
+                strip.cursor = strip.cursor.saturating_sub(1);
+                Ok(None)
+            }
+            KeyCode::Right => {
+                let last = self.reader.total_pages.map(|t| t.saturating_sub(1));
+                strip.cursor = strip.cursor.saturating_add(1).min(last.unwrap_or(u32::MAX));
+                Ok(None)
+            }
+            KeyCode::Enter => {
+                if strip.cursor != self.reader.page {
+                    self.reader.page = strip.cursor;
+                    self.reader.invalidate_render();
+                    strip.follow(strip.cursor);
+                }
+                Ok(None)
+            }
+            _ => self.handle_reader_key(key),
+        }
+    }
+
+    /// Takes a finished thumbnail render. Returns `true` when the visible strip needs a redraw.
+    fn accept_thumbnail(&mut self, done: ThumbnailDone) -> bool {
+        let visible = self.reader.open
+            && self.thumbnail_strip_visible()
+            && self.reader.book_path.as_deref() == Some(done.book_path.as_str());
+        let current_book = self.reader.book_path.clone();
+        self.thumbnail_strip.insert(
+            done.book_path,
+            done.page,
+            done.image.ok(),
+            current_book.as_deref(),
+        );
+        visible
+    }
+
+    fn accept_page_count(&mut self, done: PageCountDone) -> bool {
+        let count = match done.count {
+            Ok(count) => count,
+            Err(err) => {
+                let shown = self.meta_cache.path.as_deref() == Some(done.book_path.as_str());
+                let changed = self.broken_paths.get(&done.book_path) != Some(&err);
+                self.broken_paths.insert(done.book_path, err);
+                return shown && changed && !self.reader.open;
+            }
+        };
+        self.broken_paths.remove(&done.book_path);
+        let kind_changed = self.ctx.book_kind(&done.book_path) != count.kind;
+        self.ctx.set_page_count(&done.book_path, count);
+        if kind_changed && self.ctx.kind_filter.is_some() {
+            self.normalize_selection_to_visible();
+        }
+        if self.meta_cache.path.as_deref() != Some(done.book_path.as_str()) {
+            // The library row shows the kind.
+            return kind_changed && !self.reader.open;
+        }
+        self.meta_cache.page_count = Some(count.pages);
+        !self.reader.open
+    }
+
+    /// Why `book` cannot be opened, if its file is known to be broken or cannot be counted
+    /// now. Books with a remembered page count were parsed before and are not checked.
+    fn broken_book_error(&mut self, book: &bookshelf_core::Book) -> Option<String> {
+        if let Some(err) = self.broken_paths.get(&book.path) {
+            return Some(err.clone());
+        }
+        if self.ctx.page_counts_by_path.contains_key(&book.path) {
+            return None;
+        }
+        let err = self.engine.page_count(book).err()?.to_string();
+        self.broken_paths.insert(book.path.clone(), err.clone());
+        Some(err)
+    }
+
+    /// Takes a library grid cover. Returns `true` when it is on screen.
+    fn accept_grid_cover(&mut self, done: CoverDone) -> bool {
+        let shown = self.cover_grid.insert(done.book_path, done.image.ok());
+        shown && self.ctx.settings.library_grid && !self.reader.open
+    }
+
+    fn accept_cover(&mut self, done: CoverDone) -> bool {
+        if self.meta_cache.path.as_deref() != Some(done.book_path.as_str()) {
+            return false;
+        }
+        self.meta_cache.cover = Some(done.image.ok().map(Arc::new));
+        self.meta_cache.cover_protocol = None;
+        !self.reader.open
+    }
+
+    /// Takes a worker render; the first failure of the session caused by a missing Pdfium
+    /// opens the setup panel.
+    fn accept_page_image(&mut self, done: PageImageDone) -> bool {
+        let failed = done.image.is_err();
+        let redraw = self.reader.accept_page_image(done, &self.engine);
+        if redraw
+            && failed
+            && !self.pdfium_setup.shown
+            && let Err(err) = self.engine.check_pdfium()
+        {
+            self.show_pdfium_setup(&err);
+        }
+        redraw
+    }
+
+    fn show_pdfium_setup(&mut self, err: &anyhow::Error) {
+        self.pdfium_setup
+            .show(err, pdfium_library_name(), pdfium_search_paths());
+        self.goto_panel.open = false;
+        self.export_panel.open = false;
+    }
+
+    fn handle_pdfium_setup_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.pdfium_setup.open = false,
+            KeyCode::Char('r') | KeyCode::Enter => match self.engine.recheck_pdfium() {
+                Ok(()) => {
+                    self.pdfium_setup.open = false;
+                    self.page_render.recheck_pdfium();
+                    self.reader.mode = ReaderMode::Image;
+                    self.reader.invalidate_render();
+                    self.reader.notice = Some("pdfium loaded; mode: image".to_string());
+                }
+                Err(err) => self.pdfium_setup.still_missing(&err, pdfium_search_paths()),
+            },
+            _ => self.redraw.ignore_key(),
+        }
+        Ok(None)
+    }
+
+    fn handle_export_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        match key.code {
+            KeyCode::Esc => {
+                self.export_panel = ExportPanel::default();
+                Ok(None)
+            }
+            KeyCode::Tab | KeyCode::Down => {
+                self.export_panel.focus = self.export_panel.focus.next();
+                Ok(None)
+            }
+            KeyCode::BackTab | KeyCode::Up => {
+                self.export_panel.focus = self.export_panel.focus.prev();
+                Ok(None)
+            }
+            KeyCode::Enter => {
+                match self.start_export() {
+                    Ok(()) => self.export_panel = ExportPanel::default(),
+                    Err(err) => self.export_panel.error = Some(err),
+                }
+                Ok(None)
+            }
+            _ => {
+                let panel = &mut self.export_panel;
+                match panel.focus {
+                    ExportField::Range => panel.range.handle_key(key),
+                    ExportField::Dir => panel.dir.handle_key(key),
+                    ExportField::Dpi => panel
+                        .dpi
+                        .handle_key_accepting(key, |ch| ch.is_ascii_digit()),
+                };
+                Ok(None)
+            }
+        }
+    }
+
+    /// Validates the export panel and hands the pages to the render worker. Errors are the
+    /// messages shown in the panel.
+    fn start_export(&mut self) -> Result<(), String> {
+        if self.export.is_some() {
+            return Err("An export is already running".to_string());
+        }
+        let Some(book) = self.reader.current_book() else {
+            return Err("No book open".to_string());
+        };
+        if self.engine.is_epub(&book) {
+            return Err("EPUB chapters have no page images to export".to_string());
+        }
+        let (first, last) = resolve_export_range(
+            self.export_panel.range.as_str(),
+            self.reader.page.saturating_add(1),
+            self.reader.total_pages,
+        )?;
+        let dpi = resolve_export_dpi(self.export_panel.dpi.as_str())?;
+        let dir = self.export_panel.dir.as_str().trim();
+        if dir.is_empty() {
+            return Err("Enter a folder".to_string());
+        }
+        let mut dir = std::path::PathBuf::from(dir);
+        if dir.is_relative() {
+            dir = Path::new(&self.ctx.cwd).join(dir);
+        }
+        std::fs::create_dir_all(&dir)
+            .map_err(|err| format!("Cannot create {}: {err}", dir.display()))?;
+
+        let run = ExportRun::new(book.path.clone(), dir.clone(), last - first + 1);
+        self.reader.notice = Some(run.progress());
+        self.export = Some(run);
+        self.page_render.request_export(ExportJob {
+            book,
+            first: first - 1,
+            last: last - 1,
+            dir,
+            dpi,
+        });
+        Ok(())
+    }
+
+    /// Counts an exported page and reports progress, then the summary once the last page is
+    /// in, in the reader's notice line (or the library's, if the reader was closed).
+    fn accept_export_page(&mut self, done: ExportPageDone) -> bool {
+        let Some(run) = self
+            .export
+            .as_mut()
+            .filter(|run| run.book_path == done.book_path)
+        else {
+            return false;
+        };
+        let finished = run.record(
+            done.page + 1,
+            done.result.map(|_| ()).map_err(|err| format!("{err:#}")),
+        );
+        let notice = if finished {
+            run.summary()
+        } else {
+            run.progress()
+        };
+        if finished {
+            self.export = None;
+        }
+        if self.reader.open {
+            self.reader.notice = Some(notice);
+        } else {
+            self.library_notice = Some(notice);
+        }
+        true
+    }
+
+    fn handle_goto_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        match key.code {
+            KeyCode::Esc => {
+                self.goto_panel.open = false;
+                self.goto_panel.input.clear();
+                self.goto_panel.error = None;
+                Ok(None)
+            }
+            KeyCode::Enter => {
+                let page = match resolve_goto_target(
+                    self.goto_panel.input.as_str(),
+                    self.reader.page.saturating_add(1),
+                    self.reader.total_pages,
+                    &self.reader.toc,
+                    &self.reader.page_labels,
+                ) {
+                    Ok(page) => page,
+                    Err(err) => {
+                        self.goto_panel.error = Some(err);
+                        return Ok(None);
+                    }
+                };
+
+                self.reader.page = page.saturating_sub(1);
+                self.reader.invalidate_render();
+                self.reader.notice = Some(format!(
+                    "jumped to page {}",
+                    labeled_page(&self.reader.page_labels, page)
+                ));
+                self.goto_panel.open = false;
+                self.goto_panel.error = None;
+                Ok(None)
+            }
+            _ => {
+                // After a leading `c` the rest is a chapter number or title; a book with
+                // printed labels takes any text, since labels can be "xii" or "A-3".
+                let input = self.goto_panel.input.as_str();
+                let chapter = input.starts_with(['c', 'C']);
+                let empty = input.is_empty();
+                let labeled = !self.reader.page_labels.is_empty();
+                self.goto_panel.input.handle_key_accepting(key, |ch| {
+                    ch.is_ascii_digit()
+                        || matches!(ch, '+' | '-' | '%' | '.')
+                        || chapter
+                        || labeled
+                        || (empty && matches!(ch, 'c' | 'C'))
+                });
+                Ok(None)
+            }
+        }
+    }
+
+    fn open_toc_panel(&mut self) {
+        self.toc_panel.open = true;
+        self.toc_panel.error = None;
+        self.toc_panel.query.clear();
+        self.toc_panel.bookmark_depth = None;
+        self.goto_panel.open = false;
+        self.bookmarks_panel.open = false;
+        self.notes_panel.open = false;
+
+        let Some(book) = self.reader.current_book() else {
+            self.toc_panel.items.clear();
+            self.toc_panel.path = None;
+            self.toc_panel.error = Some("no book".to_string());
+            return;
+        };
+
+        if self.toc_panel.path.as_deref() != Some(&book.path) {
+            self.toc_panel.items = self.reader.toc.clone();
+            self.toc_panel.path = Some(book.path.clone());
+            self.toc_panel.error = self.reader.toc_error.clone();
+        }
+
+        let best =
+            toc::current_toc_index(&self.toc_panel.items, self.reader.page.saturating_add(1))
+                .unwrap_or(0);
+        let visible = self.toc_visible_indices();
+        self.toc_panel.selected = visible.iter().position(|idx| *idx == best).unwrap_or(0);
+    }
+
+    fn toc_visible_indices(&self) -> Vec<usize> {
+        let query = self.toc_panel.query.as_str().trim().to_lowercase();
+        if query.is_empty() {
+            return (0..self.toc_panel.items.len()).collect();
+        }
+
+        self.toc_panel
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, item)| {
+                if item.title.to_lowercase().contains(&query) {
+                    Some(idx)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn handle_toc_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        if let Some(depth) = self.toc_panel.bookmark_depth {
+            match key.code {
+                KeyCode::Enter => {
+                    self.toc_panel.bookmark_depth = None;
+                    self.bookmark_toc_entries(depth);
+                }
+                KeyCode::Esc => self.toc_panel.bookmark_depth = None,
+                KeyCode::Char(ch @ '1'..='9') => {
+                    self.toc_panel.bookmark_depth = Some(usize::from(ch as u8 - b'1'));
+                }
+                _ => {}
+            }
+            return Ok(None);
+        }
+        let visible = self.toc_visible_indices();
+        match key.code {
+            // Typed into the filter once there is one; the filter ignores case.
+            KeyCode::Char('B')
+                if self.toc_panel.query.as_str().is_empty() && !self.toc_panel.items.is_empty() =>
+            {
+                self.toc_panel.bookmark_depth = Some(0);
+                Ok(None)
+            }
+            KeyCode::Esc => {
+                self.toc_panel.open = false;
+                Ok(None)
+            }
+            KeyCode::Up => {
+                self.toc_panel.selected = self.toc_panel.selected.saturating_sub(1);
+                Ok(None)
+            }
+            KeyCode::Down => {
+                let len = visible.len();
+                if len > 0 {
+                    self.toc_panel.selected = (self.toc_panel.selected + 1).min(len - 1);
+                }
+                Ok(None)
+            }
+            KeyCode::Enter => {
+                let Some(item_idx) = visible.get(self.toc_panel.selected).copied() else {
+                    return Ok(None);
+                };
+                let Some(item) = self.toc_panel.items.get(item_idx) else {
+                    return Ok(None);
+                };
+                let Some(page) = item.page else {
+                    self.reader.notice = Some("TOC entry has no page".to_string());
+                    return Ok(None);
+                };
+                self.reader.page = page.saturating_sub(1);
+                self.reader.invalidate_render();
+                self.toc_panel.open = false;
+                Ok(None)
+            }
+            _ => {
+                let before = self.toc_panel.query.as_str().to_string();
+                if self.toc_panel.query.handle_key(key) && self.toc_panel.query.as_str() != before {
+                    self.toc_panel.selected = 0;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Bookmarks the start of every outline entry down to `depth`, labeled with its title, as
+    /// one change to the book's bookmarks.
+    fn bookmark_toc_entries(&mut self, depth: usize) {
+        let Some(path) = self.reader.book_path.clone() else {
+            return;
+        };
+        let bookmarks = self.ctx.bookmarks_by_path.entry(path.clone()).or_default();
+        let added = toc::chapter_bookmarks(
+            &self.toc_panel.items,
+            depth,
+            self.reader.total_pages,
+            bookmarks,
+        );
+        self.toc_panel.open = false;
+        if added.is_empty() {
+            self.reader.notice = Some("no bookmarks to add".to_string());
+            return;
+        }
+        let count = added.len();
+        bookmarks.extend(added);
+        bookmarks.sort_by_key(|b| (b.page, b.label.clone()));
+        self.ctx.dirty_bookmark_paths.insert(path);
+        self.reader.notice = Some(format!(
+            "added {count} bookmark{}",
+            if count == 1 { "" } else { "s" }
+        ));
+    }
+
+    fn open_links_panel(&mut self) {
+        self.goto_panel.open = false;
+        self.bookmarks_panel.open = false;
+        self.notes_panel.open = false;
+        self.toc_panel.open = false;
+
+        let page = self.reader.page;
+        let links = match self.reader.current_book() {
+            Some(book) => self.engine.page_links(&book, page),
+            None => Err(anyhow::anyhow!("no book")),
+        };
+        self.links_panel.show(page.saturating_add(1), links);
+    }
+
+    fn handle_links_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        match key.code {
+            KeyCode::Esc => self.links_panel.open = false,
+            KeyCode::Up => {
+                self.links_panel.selected = self.links_panel.selected.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                let len = self.links_panel.links.len();
+                if len > 0 {
+                    self.links_panel.selected = (self.links_panel.selected + 1).min(len - 1);
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(page) = self.links_panel.follow(self.reader.page) {
+                    self.reader.page = page;
+                    self.reader.invalidate_render();
+                    self.reader.notice = Some(format!(
+                        "page {}; {} goes back",
+                        page + 1,
+                        self.key_bindings.label(KeyAction::LinkBack)
+                    ));
+                }
+            }
+            _ => self.redraw.ignore_key(),
+        }
+        Ok(None)
+    }
+
+    /// Lists the PDF's own annotations on the current page, or in the whole book.
+    fn open_pdf_annotations_panel(&mut self, whole_book: bool) {
+        self.goto_panel.open = false;
+        self.bookmarks_panel.open = false;
+        self.notes_panel.open = false;
+        self.toc_panel.open = false;
+
+        let page = self.reader.page;
+        let annotations = match self.reader.current_book() {
+            Some(book) if whole_book => self.engine.annotations(&book),
+            Some(book) => self.engine.page_annotations(&book, page),
+            None => Err(anyhow::anyhow!("no book")),
+        };
+        let shown_page = (!whole_book).then_some(page.saturating_add(1));
+        self.pdf_annotations_panel.show(shown_page, annotations);
+    }
+
+    fn handle_pdf_annotations_panel_key(
+        &mut self,
+        key: KeyEvent,
+    ) -> anyhow::Result<Option<UiExit>> {
+        match key.code {
+            KeyCode::Esc => self.pdf_annotations_panel.open = false,
+            KeyCode::Up => {
+                self.pdf_annotations_panel.selected =
+                    self.pdf_annotations_panel.selected.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                let len = self.pdf_annotations_panel.annotations.len();
+                if len > 0 {
+                    self.pdf_annotations_panel.selected =
+                        (self.pdf_annotations_panel.selected + 1).min(len - 1);
+                }
+            }
+            KeyCode::Tab => {
+                let whole_book = !self.pdf_annotations_panel.whole_book();
+                self.open_pdf_annotations_panel(whole_book);
+            }
+            KeyCode::Enter => {
+                if let Some(page) = self.pdf_annotations_panel.jump()
+                    && page != self.reader.page
+                {
+                    self.reader.page = page;
+                    self.reader.invalidate_render();
+                    self.reader.notice = Some(format!("page {}", page + 1));
+                }
+            }
+            _ => self.redraw.ignore_key(),
+        }
+        Ok(None)
+    }
+
+    fn open_search_in_book_panel(&mut self) {
+        self.goto_panel.open = false;
+        self.bookmarks_panel.open = false;
+        self.notes_panel.open = false;
+        self.toc_panel.open = false;
+
+        let path = self.reader.book_path.clone();
+        if self.search_in_book_panel.path != path {
+            self.search_in_book_panel = SearchInBookPanel {
+                path,
+                ..SearchInBookPanel::default()
+            };
+        }
+        self.search_in_book_panel.open = true;
+        self.search_in_book_panel.editing = true;
+        self.search_in_book_panel.error = None;
+    }
+
+    fn start_search_in_book(&mut self) {
+        let panel = &mut self.search_in_book_panel;
+        let query = panel.input.as_str().trim().to_string();
+        if query.is_empty() {
+            panel.error = Some("Enter a search query".to_string());
+            return;
+        }
+        panel.query = query;
+        panel.results.clear();
+        panel.selected = 0;
+        panel.next_page = 0;
+        panel.total_pages = self.reader.total_pages;
+        panel.running = true;
+        panel.editing = false;
+        panel.error = None;
+    }
+
+    fn step_search_in_book(&mut self) {
+        let Some(book) = self.reader.current_book() else {
+            self.search_in_book_panel.running = false;
+            return;
+        };
+        let panel = &mut self.search_in_book_panel;
+        let total = panel.total_pages.unwrap_or(0);
+        for _ in 0..SEARCH_IN_BOOK_PAGES_PER_TICK {
+            if panel.next_page >= total || panel.results.len() >= SEARCH_IN_BOOK_MAX_RESULTS {
+                panel.running = false;
+                return;
+            }
+            if let Ok(Some(hit)) = self
+                .engine
+                .search_page(&book, panel.next_page, &panel.query)
+            {
+                panel.results.push(hit);
+            }
+            panel.next_page += 1;
+        }
+    }
+
+    fn handle_search_in_book_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        match key.code {
+            KeyCode::Esc => {
+                if self.search_in_book_panel.running {
+                    self.search_in_book_panel.running = false;
+                    self.reader.notice = Some("search cancelled".to_string());
+                } else {
+                    self.search_in_book_panel.open = false;
+                }
+                Ok(None)
+            }
+            KeyCode::Up => {
+                self.search_in_book_panel.selected =
+                    self.search_in_book_panel.selected.saturating_sub(1);
+                self.search_in_book_panel.editing = false;
+                Ok(None)
+            }
+            KeyCode::Down => {
+                let len = self.search_in_book_panel.results.len();
+                if len > 0 {
+                    self.search_in_book_panel.selected =
+                        (self.search_in_book_panel.selected + 1).min(len - 1);
+                }
+                self.search_in_book_panel.editing = false;
+                Ok(None)
+            }
+            KeyCode::Enter => {
+                let panel = &self.search_in_book_panel;
+                if panel.editing || panel.input.as_str().trim() != panel.query {
+                    self.start_search_in_book();
+                    return Ok(None);
+                }
+                let Some(hit) = panel.results.get(panel.selected).cloned() else {
+                    return Ok(None);
+                };
+                self.search_in_book_panel.running = false;
+                self.search_in_book_panel.open = false;
+                self.reader.page = hit.page.saturating_sub(1);
+                self.reader.invalidate_render();
+                self.reader.search_highlight =
+                    Some((self.reader.page, self.search_in_book_panel.query.clone()));
+                self.reader.scroll_to_highlight = true;
+                self.reader.notice = Some(format!(
+                    "match for \"{}\" on page {}",
+                    self.search_in_book_panel.query, hit.page
+                ));
+                Ok(None)
+            }
+            _ => {
+                if self.search_in_book_panel.input.handle_key(key) {
+                    self.search_in_book_panel.editing = true;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    fn handle_bookmarks_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        if self.bookmarks_panel.input_open {
+            return self.handle_bookmarks_input_key(key);
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                self.bookmarks_panel.open = false;
+                Ok(None)
+            }
+            KeyCode::Up => {
+                self.bookmarks_panel.selected = self.bookmarks_panel.selected.saturating_sub(1);
+                Ok(None)
+            }
+            KeyCode::Down => {
+                let len = self.current_bookmarks().len();
+                if len > 0 {
+                    self.bookmarks_panel.selected =
+                        (self.bookmarks_panel.selected + 1).min(len - 1);
+                }
+                Ok(None)
+            }
+            KeyCode::Enter => {
+                let Some(bookmark) = self
+                    .current_bookmarks()
+                    .get(self.bookmarks_panel.selected)
+                    .cloned()
+                else {
+                    return Ok(None);
+                };
+                self.reader.jump_to_bookmark(&bookmark, self.reader.mode);
+                self.bookmarks_panel.open = false;
+                Ok(None)
+            }
+            KeyCode::Char('a') => {
+                let Some(path) = self.reader.book_path.clone() else {
+                    return Ok(None);
+                };
+                let page = self.reader.page.saturating_add(1);
+                let view = self.reader.bookmark_view(self.reader.mode);
+                let bookmarks = self.ctx.bookmarks_by_path.entry(path.clone()).or_default();
+                if !bookmarks
+                    .iter()
+                    .any(|b| b.page == page && b.label.is_empty())
+                {
+                    bookmarks.push(Bookmark {
+                        page,
+                        label: String::new(),
+                        view,
+                    });
+                    bookmarks.sort_by_key(|b| (b.page, b.label.clone()));
+                    self.ctx.dirty_bookmark_paths.insert(path);
+                    self.reader.notice = Some(format!("bookmarked page {page}"));
+                    self.bookmarks_panel.selected = bookmarks
+                        .iter()
+                        .position(|b| b.page == page && b.label.is_empty())
+                        .unwrap_or(0);
+                }
+                Ok(None)
+            }
+            KeyCode::Char('A') => {
+                self.bookmarks_panel.input_open = true;
+                self.bookmarks_panel.input_page = self.reader.page.saturating_add(1);
+                self.bookmarks_panel.input.clear();
+                self.bookmarks_panel.editing = None;
+                self.bookmarks_panel.error = None;
+                Ok(None)
+            }
+            KeyCode::Char('e') => {
+                let Some(bookmark) = self
+                    .current_bookmarks()
+                    .get(self.bookmarks_panel.selected)
+                    .cloned()
+                else {
+                    return Ok(None);
+                };
+                self.bookmarks_panel.input_open = true;
+                self.bookmarks_panel.input_page = bookmark.page;
+                self.bookmarks_panel.input.set(bookmark.label.clone());
+                self.bookmarks_panel.editing = Some(bookmark);
+                self.bookmarks_panel.error = None;
+                Ok(None)
+            }
+            KeyCode::Char('d') => {
+                let Some(path) = self.reader.book_path.clone() else {
+                    return Ok(None);
+                };
+                let bookmarks = self.ctx.bookmarks_by_path.entry(path.clone()).or_default();
+                if self.bookmarks_panel.selected < bookmarks.len() {
+                    bookmarks.remove(self.bookmarks_panel.selected);
+                    bookmarks.sort_by_key(|b| (b.page, b.label.clone()));
+                    self.ctx.dirty_bookmark_paths.insert(path);
+                    if bookmarks.is_empty() {
+                        self.bookmarks_panel.selected = 0;
+                    } else {
+                        self.bookmarks_panel.selected =
+                            self.bookmarks_panel.selected.min(bookmarks.len() - 1);
+                    }
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn handle_bookmarks_input_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        match key.code {
+            KeyCode::Esc => {
+                self.bookmarks_panel.input_open = false;
+                self.bookmarks_panel.input.clear();
+                self.bookmarks_panel.editing = None;
+                self.bookmarks_panel.error = None;
+                Ok(None)
+            }
+            KeyCode::Enter => {
+                let Some(path) = self.reader.book_path.clone() else {
+                    return Ok(None);
+                };
+                let label = self.bookmarks_panel.input.as_str().trim().to_string();
+                let page = self.bookmarks_panel.input_page.max(1);
+                let original = self.bookmarks_panel.editing.clone();
+                // Editing a label keeps the view the bookmark was made with.
+                let view = match &original {
+                    Some(original) => original.view,
+                    None => self.reader.bookmark_view(self.reader.mode),
+                };
+                let bookmarks = self.ctx.bookmarks_by_path.entry(path.clone()).or_default();
+                let duplicate = bookmarks
+                    .iter()
+                    .any(|b| b.page == page && b.label == label && Some(b) != original.as_ref());
+                if duplicate {
+                    self.bookmarks_panel.error =
+                        Some(format!("Page {page} already has this bookmark"));
+                    return Ok(None);
+                }
+                if let Some(original) = &original
+                    && let Some(pos) = bookmarks.iter().position(|b| b == original)
+                {
+                    bookmarks.remove(pos);
+                }
+                bookmarks.push(Bookmark {
+                    page,
+                    label: label.clone(),
+                    view,
+                });
+                bookmarks.sort_by_key(|b| (b.page, b.label.clone()));
+                self.bookmarks_panel.selected = bookmarks
+                    .iter()
+                    .position(|b| b.page == page && b.label == label)
+                    .unwrap_or(0);
+                self.ctx.dirty_bookmark_paths.insert(path);
+                if original.is_none() {
+                    self.reader.notice = Some(format!("bookmarked page {page}"));
+                }
+                self.bookmarks_panel.input_open = false;
+                self.bookmarks_panel.input.clear();
+                self.bookmarks_panel.editing = None;
+                self.bookmarks_panel.error = None;
+                Ok(None)
+            }
+            _ => {
+                self.bookmarks_panel.input.handle_key(key);
+                Ok(None)
+            }
+        }
+    }
+
+    fn handle_notes_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        if self.notes_panel.input_open {
+            return self.handle_notes_input_key(key);
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                self.notes_panel.open = false;
+                self.notes_panel.error = None;
+                Ok(None)
+            }
+            KeyCode::Up => {
+                self.notes_panel.selected = self.notes_panel.selected.saturating_sub(1);
+                Ok(None)
+            }
+            KeyCode::Down => {
+                let len = self.current_notes().len();
+                if len > 0 {
+                    self.notes_panel.selected = (self.notes_panel.selected + 1).min(len - 1);
+                }
+                Ok(None)
+            }
+            KeyCode::Enter => {
+                let Some(note) = self.current_notes().get(self.notes_panel.selected).cloned()
+                else {
+                    return Ok(None);
+                };
+                self.reader.page = note.page.saturating_sub(1);
+                self.reader.invalidate_render();
+                self.notes_panel.open = false;
+                Ok(None)
+            }
+            KeyCode::Char('a') => {
+                self.notes_panel.input_open = true;
+                self.notes_panel.editing = None;
+                self.notes_panel.input_page = self.reader.page.saturating_add(1);
+                self.notes_panel.input.clear();
+                self.notes_panel.error = None;
+                Ok(None)
+            }
+            KeyCode::Char('e') => {
+                let Some(note) = self.current_notes().get(self.notes_panel.selected).cloned()
+                else {
+                    return Ok(None);
+                };
+                self.notes_panel.input_open = true;
+                self.notes_panel.editing = Some(self.notes_panel.selected);
+                self.notes_panel.input_page = note.page;
+                self.notes_panel.input.set(note.body);
+                self.notes_panel.error = None;
+                Ok(None)
+            }
+            KeyCode::Char('d') => {
+                let Some(path) = self.reader.book_path.clone() else {
+                    return Ok(None);
+                };
+                let notes = self.ctx.notes_by_path.entry(path.clone()).or_default();
+                if self.notes_panel.selected < notes.len() {
+                    notes.remove(self.notes_panel.selected);
+                    notes.sort_by_key(|n| (n.page, n.body.clone()));
+                    self.ctx.dirty_note_paths.insert(path);
+                    if notes.is_empty() {
+                        self.notes_panel.selected = 0;
+                    } else {
+                        self.notes_panel.selected = self.notes_panel.selected.min(notes.len() - 1);
+                    }
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Tab while the notes sidebar shows, and the list keys while it has focus. Other keys go
+    /// to the page.
+    fn notes_sidebar_takes(&self, key: &KeyEvent) -> bool {
+        self.notes_sidebar.visible()
+            && (key.code == KeyCode::Tab
+                || self.notes_sidebar.focused
+                    && matches!(
+                        key.code,
+                        KeyCode::Esc
+                            | KeyCode::Up
+                            | KeyCode::Down
+                            | KeyCode::Enter
+                            | KeyCode::Char('a')
+                    ))
+    }
+
+    fn handle_notes_sidebar_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        let notes = self.current_notes();
+        let listed = notes_sidebar::nearby_notes(&notes, self.reader.page.saturating_add(1));
+        let sidebar = &mut self.notes_sidebar;
+        match key.code {
+            KeyCode::Tab => sidebar.focused = !sidebar.focused,
+            KeyCode::Esc => sidebar.focused = false,
+            KeyCode::Up => sidebar.selected = sidebar.selected.saturating_sub(1),
+            KeyCode::Down => {
+                sidebar.selected = sidebar
+                    .selected
+                    .saturating_add(1)
+                    .min(listed.len().saturating_sub(1));
+            }
+            KeyCode::Enter => {
+                let Some(note) = listed
+                    .get(sidebar.selected.min(listed.len().saturating_sub(1)))
+                    .map(|&idx| notes[idx].clone())
+                else {
+                    self.redraw.ignore_key();
+                    return Ok(None);
+                };
+                let page = note.page.saturating_sub(1);
+                if page != self.reader.page {
+                    self.reader.page = page;
+                    self.reader.invalidate_render();
+                }
+                self.select_sidebar_note(note.page, &note.body);
+            }
+            KeyCode::Char('a') => {
+                self.notes_panel = NotesPanel {
+                    open: true,
+                    input_open: true,
+                    input_page: self.reader.page.saturating_add(1),
+                    from_sidebar: true,
+                    ..NotesPanel::default()
+                };
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    /// Keeps the sidebar cursor on the note with `page` and `body` after the list around it
+    /// changed.
+    fn select_sidebar_note(&mut self, page: u32, body: &str) {
+        let notes = self.current_notes();
+        let listed = notes_sidebar::nearby_notes(&notes, self.reader.page.saturating_add(1));
+        if let Some(pos) = listed
+            .iter()
+            .position(|&idx| notes[idx].page == page && notes[idx].body == body)
+        {
+            self.notes_sidebar.selected = pos;
+        }
+    }
+
+    fn handle_notes_input_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        let newline = match key.code {
+            KeyCode::Enter => key.modifiers.contains(KeyModifiers::ALT),
+            KeyCode::Char('j') => key.modifiers.contains(KeyModifiers::CONTROL),
+            _ => false,
+        };
+        if newline {
+            self.notes_panel.input.insert('\n');
+            return Ok(None);
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                self.notes_panel.input_open = false;
+                self.notes_panel.editing = None;
+                self.notes_panel.input.clear();
+                self.notes_panel.error = None;
+                if std::mem::take(&mut self.notes_panel.from_sidebar) {
+                    self.notes_panel.open = false;
+                }
+                Ok(None)
+            }
+            KeyCode::Enter => {
+                let body = self.notes_panel.input.as_str().trim().to_string();
+                if body.is_empty() {
+                    self.notes_panel.error = Some("Note cannot be empty".to_string());
+                    return Ok(None);
+                }
+                let Some(path) = self.reader.book_path.clone() else {
+                    return Ok(None);
+                };
+                let page = self.notes_panel.input_page.max(1);
+                let notes = self.ctx.notes_by_path.entry(path.clone()).or_default();
+                // An edit replaces the note in place and keeps its page.
+                match self.notes_panel.editing.take() {
+                    Some(idx) if idx < notes.len() => notes[idx].body = body.clone(),
+                    _ => notes.push(Note {
+                        page,
+                        body: body.clone(),
+                    }),
+                }
+                notes.sort_by_key(|n| (n.page, n.body.clone()));
+                self.notes_panel.selected = notes
+                    .iter()
+                    .position(|n| n.page == page && n.body == body)
+                    .unwrap_or(0);
+                self.ctx.dirty_note_paths.insert(path);
+                self.notes_panel.input_open = false;
+                self.notes_panel.input.clear();
+                self.notes_panel.error = None;
+                if std::mem::take(&mut self.notes_panel.from_sidebar) {
+                    self.notes_panel.open = false;
+                    self.select_sidebar_note(page, &body);
+                }
+                Ok(None)
+            }
+            _ => {
+                self.notes_panel.input.handle_key(key);
+                Ok(None)
+            }
+        }
+    }
+
+    fn handle_settings_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        if let Some((action, input)) = self.settings_panel.file_input.as_mut() {
+            match key.code {
+                KeyCode::Enter => {
+                    let action = *action;
+                    let file = input.as_str().trim().to_string();
+                    self.settings_panel.file_input = None;
+                    if !file.is_empty() {
+                        self.run_settings_file(action, Path::new(&file));
+                    }
+                }
+                KeyCode::Esc => self.settings_panel.file_input = None,
+                _ => {
+                    input.handle_key(key);
+                }
+            }
+            return Ok(None);
+        }
+        if let Some((command, input)) = self.settings_panel.command_input.as_mut() {
+            match key.code {
+                KeyCode::Enter => {
+                    *command.value_mut(&mut self.ctx.settings) = input.as_str().trim().to_string();
+                    self.settings_panel.command_input = None;
+                }
+                KeyCode::Esc => self.settings_panel.command_input = None,
+                _ => {
+                    input.handle_key(key);
+                }
+            }
+            return Ok(None);
+        }
+        if self.settings_panel.confirm_cleanup.is_some() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                    self.settings_panel.confirm_cleanup = None;
+                    self.run_cleanup();
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.settings_panel.confirm_cleanup = None;
+                }
+                _ => {}
+            }
+            return Ok(None);
+        }
+        match key.code {
+            KeyCode::Esc => {
+                self.settings_panel.open = false;
+                Ok(None)
+            }
+            KeyCode::Up => {
+                self.settings_panel.selected = self.settings_panel.selected.saturating_sub(1);
+                Ok(None)
+            }
+            KeyCode::Down => {
+                self.settings_panel.selected = (self.settings_panel.selected + 1)
+                    .min(SETTINGS_MENU_ITEM_COUNT.saturating_sub(1));
+                Ok(None)
+            }
+            KeyCode::Left => {
+                if self.settings_panel.selected == SETTINGS_MENU_KITTY_IMAGE_QUALITY {
+                    self.ctx.settings.cycle_kitty_image_quality_prev();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_DOWNSCALE_FILTER {
+                    self.ctx.settings.cycle_downscale_filter_prev();
+                    self.reader.forget_page_images();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_AUTO_CROP {
+                    self.ctx.settings.cycle_auto_crop_prev();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_IMAGE_CACHE_LIMIT {
+                    self.ctx.settings.cycle_image_cache_limit_prev();
+                    self.reader.trim_page_image_cache(&self.ctx.settings);
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_REMOTE_MODE {
+                    self.ctx.settings.toggle_remote_mode();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_FURNITURE_SAMPLE_PAGES {
+                    self.ctx
+                        .settings
+                        .cycle_furniture_sample_pages(key.code == KeyCode::Right);
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_FURNITURE_MIN_FRACTION {
+                    self.ctx
+                        .settings
+                        .cycle_furniture_min_fraction(key.code == KeyCode::Right);
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_THEME {
+                    self.ctx.settings.cycle_theme();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_WATCH_LIBRARY {
+                    self.ctx.settings.toggle_watch_library();
+                    self.restart_library_watcher();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_FINGERPRINT_BOOKS {
+                    self.ctx.settings.toggle_fingerprint_books();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_LIBRARY_DECORATIONS {
+                    self.ctx.settings.toggle_library_decorations();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_RESUME_LAST_BOOK {
+                    self.ctx.settings.toggle_resume_last_book();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_MOUSE_CAPTURE {
+                    self.ctx.settings.toggle_mouse_capture();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_READER_MINIMAP {
+                    self.ctx.settings.toggle_reader_minimap();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_TICK_RATE {
+                    self.ctx
+                        .settings
+                        .cycle_tick_rate(key.code == KeyCode::Right);
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_AUTOSAVE {
+                    self.ctx.settings.cycle_autosave(key.code == KeyCode::Right);
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_CONFIRM_QUIT {
+                    self.ctx.settings.toggle_confirm_quit();
+                }
+                Ok(None)
+            }
+            KeyCode::Right => {
+                if self.settings_panel.selected == SETTINGS_MENU_KITTY_IMAGE_QUALITY {
+                    self.ctx.settings.cycle_kitty_image_quality_next();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_DOWNSCALE_FILTER {
+                    self.ctx.settings.cycle_downscale_filter_next();
+                    self.reader.forget_page_images();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_AUTO_CROP {
+                    self.ctx.settings.cycle_auto_crop_next();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_IMAGE_CACHE_LIMIT {
+                    self.ctx.settings.cycle_image_cache_limit_next();
+                    self.reader.trim_page_image_cache(&self.ctx.settings);
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_REMOTE_MODE {
+                    self.ctx.settings.toggle_remote_mode();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_FURNITURE_SAMPLE_PAGES {
+                    self.ctx
+                        .settings
+                        .cycle_furniture_sample_pages(key.code == KeyCode::Right);
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_FURNITURE_MIN_FRACTION {
+                    self.ctx
+                        .settings
+                        .cycle_furniture_min_fraction(key.code == KeyCode::Right);
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_THEME {
+                    self.ctx.settings.cycle_theme();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_WATCH_LIBRARY {
+                    self.ctx.settings.toggle_watch_library();
+                    self.restart_library_watcher();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_FINGERPRINT_BOOKS {
+                    self.ctx.settings.toggle_fingerprint_books();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_LIBRARY_DECORATIONS {
+                    self.ctx.settings.toggle_library_decorations();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_RESUME_LAST_BOOK {
+                    self.ctx.settings.toggle_resume_last_book();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_MOUSE_CAPTURE {
+                    self.ctx.settings.toggle_mouse_capture();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_READER_MINIMAP {
+                    self.ctx.settings.toggle_reader_minimap();
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_TICK_RATE {
+                    self.ctx
+                        .settings
+                        .cycle_tick_rate(key.code == KeyCode::Right);
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_AUTOSAVE {
+                    self.ctx.settings.cycle_autosave(key.code == KeyCode::Right);
+                }
+                if self.settings_panel.selected == SETTINGS_MENU_CONFIRM_QUIT {
+                    self.ctx.settings.toggle_confirm_quit();
+                }
+                Ok(None)
+            }
+            KeyCode::Enter => {
+                match self.settings_panel.selected {
+                    SETTINGS_MENU_SCAN_PATHS => {
+                        self.scan_panel.open(&self.ctx.settings.library_roots);
+                        self.settings_panel.open = false;
+                    }
+                    SETTINGS_MENU_KITTY_IMAGE_QUALITY => {
+                        self.ctx.settings.cycle_kitty_image_quality_next();
+                    }
+                    SETTINGS_MENU_DOWNSCALE_FILTER => {
+                        self.ctx.settings.cycle_downscale_filter_next();
+                        self.reader.forget_page_images();
+                    }
+                    SETTINGS_MENU_AUTO_CROP => {
+                        self.ctx.settings.cycle_auto_crop_next();
+                    }
+                    SETTINGS_MENU_IMAGE_CACHE_LIMIT => {
+                        self.ctx.settings.cycle_image_cache_limit_next();
+                        self.reader.trim_page_image_cache(&self.ctx.settings);
+                    }
+                    SETTINGS_MENU_REMOTE_MODE => {
+                        self.ctx.settings.toggle_remote_mode();
+                    }
+                    SETTINGS_MENU_FURNITURE_SAMPLE_PAGES => {
+                        self.ctx.settings.cycle_furniture_sample_pages(true);
+                    }
+                    SETTINGS_MENU_FURNITURE_MIN_FRACTION => {
+                        self.ctx.settings.cycle_furniture_min_fraction(true);
+                    }
+                    SETTINGS_MENU_EXTERNAL_VIEWER => {
+                        self.settings_panel
+                            .edit_command(CommandSetting::ExternalViewer, &mut self.ctx.settings);
+                    }
+                    SETTINGS_MENU_GRAPHICS_TERMINAL => {
+                        self.settings_panel
+                            .edit_command(CommandSetting::GraphicsTerminal, &mut self.ctx.settings);
+                    }
+                    SETTINGS_MENU_THEME => {
+                        self.ctx.settings.cycle_theme();
+                    }
+                    SETTINGS_MENU_WATCH_LIBRARY => {
+                        self.ctx.settings.toggle_watch_library();
+                        self.restart_library_watcher();
+                    }
+                    SETTINGS_MENU_FINGERPRINT_BOOKS => {
+                        self.ctx.settings.toggle_fingerprint_books();
+                    }
+                    SETTINGS_MENU_LIBRARY_DECORATIONS => {
+                        self.ctx.settings.toggle_library_decorations();
+                    }
+                    SETTINGS_MENU_RESUME_LAST_BOOK => {
+                        self.ctx.settings.toggle_resume_last_book();
+                    }
+                    SETTINGS_MENU_MOUSE_CAPTURE => {
+                        self.ctx.settings.toggle_mouse_capture();
+                    }
+                    SETTINGS_MENU_READER_MINIMAP => {
+                        self.ctx.settings.toggle_reader_minimap();
+                    }
+                    SETTINGS_MENU_TICK_RATE => {
+                        self.ctx.settings.cycle_tick_rate(true);
+                    }
+                    SETTINGS_MENU_AUTOSAVE => {
+                        self.ctx.settings.cycle_autosave(true);
+                    }
+                    SETTINGS_MENU_CONFIRM_QUIT => {
+                        self.ctx.settings.toggle_confirm_quit();
+                    }
+                    SETTINGS_MENU_EXPORT_SETTINGS => {
+                        self.settings_panel.file_input = Some((
+                            SettingsFileAction::Export,
+                            TextInput::new(SETTINGS_FILE_NAME),
+                        ));
+                    }
+                    SETTINGS_MENU_IMPORT_SETTINGS => {
+                        self.settings_panel.file_input = Some((
+                            SettingsFileAction::Import,
+                            TextInput::new(SETTINGS_FILE_NAME),
+                        ));
+                    }
+                    SETTINGS_MENU_BACKUP => self.run_backup(),
+                    SETTINGS_MENU_RESTORE => {
+                        self.settings_panel.open = false;
+                        let backups = match self.backup_list_sink.as_mut() {
+                            Some(sink) => sink(),
+                            None => Err(anyhow::anyhow!("restore unavailable")),
+                        };
+                        self.backups_panel.show(backups);
+                    }
+                    SETTINGS_MENU_DUPLICATES => {
+                        self.settings_panel.open = false;
+                        self.start_duplicate_scan();
+                    }
+                    SETTINGS_MENU_CLEANUP => {
+                        let missing = self
+                            .ctx
+                            .books
+                            .iter()
+                            .filter(|book| !book_file_exists(&book.path))
+                            .count();
+                        self.settings_panel.confirm_cleanup = Some(missing);
+                    }
+                    _ => {}
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Saves the session and backs the database up next to it, saying where in the library
+    /// title.
+    fn run_backup(&mut self) {
+        self.library_notice = Some(match self.backup_sink.as_mut() {
+            None => "backup unavailable".to_string(),
+            Some(sink) => match sink(&mut self.ctx) {
+                Ok(backup) => format!("backed up database to {}", backup_label(&backup)),
+                Err(err) => format!("backup failed: {err:#}"),
+            },
+        });
+        self.settings_panel.open = false;
+    }
+
+    fn handle_backups_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        if self.backups_panel.confirm {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                    self.backups_panel.confirm = false;
+                    return Ok(self.restore_selected_backup());
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.backups_panel.confirm = false;
+                }
+                _ => {}
+            }
+            return Ok(None);
+        }
+        match key.code {
+            KeyCode::Esc => self.backups_panel = BackupsPanel::default(),
+            KeyCode::Up => {
+                self.backups_panel.selected = self.backups_panel.selected.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.backups_panel.selected = (self.backups_panel.selected + 1)
+                    .min(self.backups_panel.backups.len().saturating_sub(1));
+            }
+            KeyCode::Enter => self.backups_panel.ask_to_restore(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    /// Swaps the selected backup in. The session loaded from the old database is stale then,
+    /// so the UI exits to be loaded again.
+    fn restore_selected_backup(&mut self) -> Option<UiExit> {
+        let backup = self.backups_panel.selected_backup()?.to_path_buf();
+        let Some(sink) = self.restore_sink.as_mut() else {
+            self.backups_panel.error = Some("restore unavailable".to_string());
+            return None;
+        };
+        match sink(&backup) {
+            Ok(()) => {
+                self.backups_panel = BackupsPanel::default();
+                Some(UiExit::Reload)
+            }
+            Err(err) => {
+                self.backups_panel.error = Some(format!("Restore failed: {err:#}"));
+                None
+            }
+        }
+    }
+
+    /// Starts a duplicate pass in the background; `poll_duplicate_scan` opens the panel with
+    /// what it finds.
+    fn start_duplicate_scan(&mut self) {
+        if let Some(running) = self.duplicate_scan.take() {
+            running.scan.cancel();
+        }
+        self.duplicate_scan = Some(DuplicateScanState {
+            scan: self
+                .ctx
+                .start_duplicate_scan(Some(loop_wake(&self.loop_messages))),
+            done: 0,
+            total: self.ctx.books.len(),
+        });
+        self.library_notice = None;
+    }
+
+    /// Applies pending duplicate pass events. Returns `true` when there is something new to
+    /// show.
+    fn poll_duplicate_scan(&mut self) -> bool {
+        let Some(state) = self.duplicate_scan.as_mut() else {
+            return false;
+        };
+        let mut changed = false;
+        let mut finished = None;
+        while let Some(event) = state.scan.try_recv() {
+            changed = true;
+            match event {
+                DuplicateEvent::Progress { done, total } => {
+                    state.done = done;
+                    state.total = total;
+                }
+                DuplicateEvent::Finished {
+                    report,
+                    fingerprints,
+                } => {
+                    finished = Some((report, fingerprints));
+                    break;
+                }
+            }
+        }
+
+        if let Some((report, fingerprints)) = finished {
+            self.duplicate_scan = None;
+            for (path, fingerprint) in fingerprints {
+                self.ctx.set_fingerprint(&path, fingerprint);
+            }
+            match report {
+                Some(report) => self.open_duplicates_panel(report),
+                None => self.library_notice = Some("duplicate search cancelled".to_string()),
+            }
+        }
+        changed
+    }
+
+    /// Lists what a duplicate pass found; with nothing found, says so in the library title.
+    fn open_duplicates_panel(&mut self, report: DuplicateReport) {
+        if report.groups.is_empty() {
+            self.library_notice = Some(match report.unreadable {
+                0 => "no duplicates found".to_string(),
+                n => format!("no duplicates found ({n} files unreadable)"),
+            });
+            return;
+        }
+        self.duplicates_panel = DuplicatesPanel {
+            open: true,
+            groups: report.groups,
+            selected: 0,
+            error: None,
+        };
+    }
+
+    fn handle_duplicates_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        let len = self
+            .duplicates_panel
+            .groups
+            .iter()
+            .map(Vec::len)
+            .sum::<usize>();
+        match key.code {
+            KeyCode::Esc => self.duplicates_panel = DuplicatesPanel::default(),
+            KeyCode::Up => {
+                self.duplicates_panel.selected = self.duplicates_panel.selected.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.duplicates_panel.selected =
+                    (self.duplicates_panel.selected + 1).min(len.saturating_sub(1));
+            }
+            KeyCode::Enter | KeyCode::Char('a') => {
+                self.merge_selected_duplicates(DuplicateDisposal::Archive);
+            }
+            KeyCode::Char('r') => self.merge_selected_duplicates(DuplicateDisposal::Remove),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    /// Keeps the copy under the cursor, folds the rest of its group into it and archives or
+    /// removes them.
+    fn merge_selected_duplicates(&mut self, disposal: DuplicateDisposal) {
+        let Some((group_idx, keep_idx)) = self.duplicates_panel.cursor() else {
+            return;
+        };
+        let group = self.duplicates_panel.groups[group_idx].clone();
+        let keep = group[keep_idx].clone();
+        let others: Vec<String> = group.into_iter().filter(|path| *path != keep).collect();
+        if self.reader.open
+            && self
+                .reader
+                .book_path
+                .as_ref()
+                .is_some_and(|open| others.contains(open))
+        {
+            self.duplicates_panel.error = Some("Close the open book or keep that copy".to_string());
+            return;
+        }
+
+        let merged = self.ctx.merge_duplicates(&keep, &others, disposal);
+        for path in &others {
+            self.marked_paths.remove(path);
+            if disposal == DuplicateDisposal::Remove {
+                self.missing_paths.remove(path);
+                self.size_by_path.remove(path);
+            }
+        }
+        self.meta_cache = BookMetaCache::default();
+        self.normalize_selection_to_visible();
+
+        let title = self
+            .ctx
+            .books
+            .iter()
+            .find(|b| b.path == keep)
+            .map_or_else(|| bookshelf_core::display_path(&keep), |b| b.title.clone());
+        let verb = match disposal {
+            DuplicateDisposal::Archive => "archived",
+            DuplicateDisposal::Remove => "removed",
+        };
+        self.library_notice = Some(format!("kept {title}; {verb} {merged} copies"));
+
+        let panel = &mut self.duplicates_panel;
+        panel.groups.remove(group_idx);
+        panel.error = None;
+        if panel.groups.is_empty() {
+            *panel = DuplicatesPanel::default();
+            return;
+        }
+        // Land on the first copy of the group that took this one's place.
+        let len = panel.groups.iter().map(Vec::len).sum::<usize>();
+        panel.selected = panel.groups[..group_idx.min(panel.groups.len())]
+            .iter()
+            .map(Vec::len)
+            .sum::<usize>()
+            .min(len - 1);
+    }
+
+    /// Exports the session's settings to `file`, or imports `file`'s in their place, and says
+    /// how it went in the library title.
+    fn run_settings_file(&mut self, action: SettingsFileAction, file: &Path) {
+        let shown = file.display();
+        self.library_notice = Some(match action {
+            SettingsFileAction::Export => match self.settings_export_sink.as_mut() {
+                None => "settings export unavailable".to_string(),
+                Some(sink) => match sink(file, &self.ctx.settings) {
+                    Ok(()) => format!("exported settings to {shown}"),
+                    Err(err) => format!("settings export failed: {err:#}"),
+                },
+            },
+            SettingsFileAction::Import => match self.settings_import_sink.as_mut() {
+                None => "settings import unavailable".to_string(),
+                Some(sink) => match sink(file) {
+                    Ok(import) => {
+                        let mut notice = format!("imported settings from {shown}");
+                        if !import.rejected.is_empty() {
+                            notice.push_str(&format!(
+                                "; defaults kept for {}",
+                                import.rejected.join(", ")
+                            ));
+                        }
+                        let problems = self.apply_settings(import.settings);
+                        if !problems.is_empty() {
+                            notice.push_str(&format!("; key bindings: {}", problems.join("; ")));
+                        }
+                        notice
+                    }
+                    Err(err) => format!("settings import failed: {err:#}"),
+                },
+            },
+        });
+        self.settings_panel.open = false;
+    }
+
+    /// Switches the session to `settings`, rebuilding what depends on them. Returns problems
+    /// with their key bindings.
+    fn apply_settings(&mut self, settings: Settings) -> Vec<String> {
+        self.ctx.settings = settings;
+        let (key_bindings, problems) = KeyBindings::from_overrides(&self.ctx.settings.key_bindings);
+        self.key_bindings = key_bindings;
+        self.restart_library_watcher();
+        self.reader.trim_page_image_cache(&self.ctx.settings);
+        self.reader.invalidate_render();
+        self.refresh_size_cache();
+        problems
+    }
+
+    /// Runs the database cleanup and reports the outcome in the library title.
+    fn run_cleanup(&mut self) {
+        let Some(sink) = self.cleanup_sink.as_mut() else {
+            self.library_notice = Some("cleanup unavailable".to_string());
+            return;
+        };
+        match sink(&self.ctx.cleanup_keep_paths()) {
+            Ok(report) => {
+                self.ctx.apply_cleanup(&report);
+                for path in &report.removed_books {
+                    self.missing_paths.remove(path);
+                    self.marked_paths.remove(path);
+                    self.size_by_path.remove(path);
+                }
+                self.normalize_selection_to_visible();
+                self.library_notice = Some(format!(
+                    "cleanup: removed {} missing books, {} orphaned rows, {} unused labels; freed {}",
+                    report.removed_books.len(),
+                    report.orphaned_rows,
+                    report.removed_tags.len(),
+                    format_bytes(report.freed_bytes())
+                ));
+            }
+            Err(err) => {
+                self.library_notice = Some(format!("cleanup failed: {err:#}"));
+            }
+        }
+        self.settings_panel.open = false;
+    }
+
+    fn handle_scan_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        let panel = &mut self.scan_panel;
+        if panel.edit.is_some() {
+            match key.code {
+                KeyCode::Esc => {
+                    panel.edit = None;
+                    panel.error = None;
+                }
+                KeyCode::Enter => panel.error = panel.finish_edit().err(),
+                _ => {
+                    panel.input.handle_key(key);
+                }
+            }
+            return Ok(None);
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                panel.open = false;
+                panel.error = None;
+            }
+            KeyCode::Up => panel.selected = panel.selected.saturating_sub(1),
+            KeyCode::Down => panel.selected = (panel.selected + 1).min(panel.scope_row()),
+            KeyCode::Left | KeyCode::Right if panel.selected == panel.scope_row() => {
+                self.ctx.settings.cycle_scan_scope();
+            }
+            KeyCode::Char('a') => panel.start_add(),
+            KeyCode::Char('e') => panel.start_change(),
+            KeyCode::Char('d') => panel.remove_selected(),
+            KeyCode::Char(' ') => panel.toggle_selected(),
+            KeyCode::Enter | KeyCode::Char('p') => {
+                let roots = match panel.applied_roots() {
+                    Ok(roots) => roots,
+                    Err(err) => {
+                        panel.error = Some(err);
+                        return Ok(None);
+                    }
+                };
+                panel.open = false;
+                panel.error = None;
+                self.ctx.settings.library_roots = roots;
+                self.ctx.settings.normalize();
+                self.settings_panel.open = false;
+                self.start_library_scan();
+                if key.code == KeyCode::Char('p')
+                    && let Some(state) = self.library_scan.as_mut()
+                {
+                    state.prune_missing = true;
+                }
+                self.restart_library_watcher();
+            }
+            _ => self.redraw.ignore_key(),
+        }
+        Ok(None)
+    }
+
+    fn handle_relink_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        match key.code {
+            KeyCode::Esc => {
+                self.relink_panel = RelinkPanel::default();
+                Ok(None)
+            }
+            KeyCode::Enter => {
+                let Some(from) = self.relink_panel.path.clone() else {
+                    self.relink_panel = RelinkPanel::default();
+                    return Ok(None);
+                };
+                let input = self.relink_panel.input.as_str().trim();
+                if input.is_empty() {
+                    self.relink_panel.error = Some("Enter the new file path".to_string());
+                    return Ok(None);
+                }
+                let mut new_path = std::path::PathBuf::from(input);
+                if new_path.is_relative() {
+                    new_path = std::path::Path::new(&self.ctx.cwd).join(new_path);
+                }
+                if !new_path.is_file() {
+                    self.relink_panel.error = Some(format!("Not a file: {}", new_path.display()));
+                    return Ok(None);
+                }
+                if !is_book_file(&new_path) {
+                    self.relink_panel.error = Some("Not a PDF, EPUB or CBZ file".to_string());
+                    return Ok(None);
+                }
+                let new_path = std::fs::canonicalize(&new_path).unwrap_or(new_path);
+                let to = bookshelf_core::encode_path(&new_path);
+                if to == from {
+                    self.relink_panel.error = Some("Same path as before".to_string());
+                    return Ok(None);
+                }
+                let title = new_path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "untitled".to_string());
+
+                self.ctx.relink_book(&from, &to, title.clone());
+                self.missing_paths.remove(&from);
+                self.missing_paths.remove(&to);
+                self.broken_paths.remove(&from);
+                self.broken_paths.remove(&to);
+                if self.marked_paths.remove(&from) {
+                    self.marked_paths.insert(to.clone());
+                }
+                self.size_by_path.remove(&from);
+                self.size_by_path.remove(&to);
+                self.refresh_size_cache();
+                self.meta_cache = BookMetaCache::default();
+                self.normalize_selection_to_visible();
+                self.relink_panel = RelinkPanel::default();
+                self.library_notice = Some(format!("relinked {title}"));
+                Ok(None)
+            }
+            _ => {
+                self.relink_panel.input.handle_key(key);
+                Ok(None)
+            }
+        }
+    }
+
+    fn handle_delete_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        let Some(path) = self.delete_panel.path.clone() else {
+            self.delete_panel = DeletePanel::default();
+            return Ok(None);
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('n') => {
+                self.delete_panel = DeletePanel::default();
+            }
+            KeyCode::Char('r') => {
+                let title = self.remove_library_entry(&path);
+                self.delete_panel = DeletePanel::default();
+                self.library_notice = Some(format!("removed {title} from library"));
+            }
+            KeyCode::Char('D') => {
+                let file = bookshelf_core::decode_path(&path);
+                match std::fs::remove_file(&file) {
+                    Ok(()) => {}
+                    // Already gone; still drop the entry.
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(err) => {
+                        self.delete_panel.error =
+                            Some(format!("Could not delete {}: {err}", file.display()));
+                        return Ok(None);
+                    }
+                }
+                let title = self.remove_library_entry(&path);
+                self.delete_panel = DeletePanel::default();
+                self.library_notice = Some(format!("deleted {title}"));
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    /// Drops a book from the library and the screen's caches, moving the selection to the next
+    /// visible book (or the previous one at the end of the list). Returns the book's title.
+    fn remove_library_entry(&mut self, path: &str) -> String {
+        let visible = self.visible_indices();
+        let next_path = visible
+            .iter()
+            .position(|&idx| self.ctx.books.get(idx).is_some_and(|b| b.path == path))
+            .and_then(|pos| {
+                visible
+                    .get(pos + 1)
+                    .or(pos.checked_sub(1).and_then(|p| visible.get(p)))
+            })
+            .and_then(|&idx| self.ctx.books.get(idx))
+            .map(|b| b.path.clone());
+        let title = self
+            .ctx
+            .books
+            .iter()
+            .find(|b| b.path == path)
+            .map(|b| b.title.clone())
+            .unwrap_or_default();
+
+        self.ctx.remove_book(path);
+        self.missing_paths.remove(path);
+        self.broken_paths.remove(path);
+        self.marked_paths.remove(path);
+        self.size_by_path.remove(path);
+        self.meta_cache = BookMetaCache::default();
+        if let Some(idx) =
+            next_path.and_then(|next| self.ctx.books.iter().position(|b| b.path == next))
+        {
+            self.ctx.selected = idx;
+        }
+        self.normalize_selection_to_visible();
+        title
+    }
+
+    fn selected_book_path(&self) -> Option<String> {
+        self.selected_visible_index()
+            .and_then(|idx| self.ctx.books.get(idx))
+            .map(|b| b.path.clone())
+    }
+
+    fn sync_search_panel_tab(&mut self) {
+        let tab = match self.search_panel.mode {
+            SearchPanelMode::Filters => SearchTab::Search,
+            SearchPanelMode::Labels => SearchTab::Assign,
+            SearchPanelMode::Catalog => SearchTab::Manage,
+        };
+
+        if self.search_panel.tab != tab {
+            self.label_manager_panel.confirm_delete = None;
+            self.label_manager_panel.error = None;
+            self.label_manager_panel.filter_editing = false;
+            self.assign_labels_panel.query_editing = false;
+            self.assign_labels_panel.error = None;
+
+            self.search_panel.tab = tab;
+        }
+
+        if tab == SearchTab::Assign {
+            let selected_path = self.selected_book_path();
+            if selected_path != self.assign_labels_panel.book_path {
+                self.prepare_assign_labels_panel();
+            }
+        }
+    }
+
+    fn prepare_assign_labels_panel(&mut self) {
+        self.assign_labels_panel.focus = AssignFocus::Collections;
+        self.assign_labels_panel.collection_cursor = 0;
+        self.assign_labels_panel.tag_cursor = 0;
+        self.assign_labels_panel.collection_query.clear();
+        self.assign_labels_panel.tag_query.clear();
+        self.assign_labels_panel.query_editing = false;
+        self.assign_labels_panel.error = None;
+        self.assign_labels_panel.bulk_paths.clear();
+
+        let marked: Vec<&Book> = self
+            .ctx
+            .books
+            .iter()
+            .filter(|b| self.marked_paths.contains(&b.path))
+            .collect();
+        if !marked.is_empty() {
+            // Stage only what all marked books share; commit applies the difference.
+            let mut common = BookLabels::default();
+            let first_labels = self
+                .ctx
+                .labels_by_path
+                .get(&marked[0].path)
+                .cloned()
+                .unwrap_or_default();
+            common.tags = first_labels
+                .tags
+                .iter()
+                .filter(|tag| {
+                    marked.iter().all(|b| {
+                        self.ctx
+                            .labels_by_path
+                            .get(&b.path)
+                            .is_some_and(|l| l.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+                    })
+                })
+                .cloned()
+                .collect();
+            if marked.iter().all(|b| {
+                self.ctx
+                    .labels_by_path
+                    .get(&b.path)
+                    .and_then(|l| l.collection.as_deref())
+                    == first_labels.collection.as_deref()
+            }) {
+                common.collection = first_labels.collection.clone();
+            }
+            let labels_of = |book: &Book| self.ctx.labels_by_path.get(&book.path);
+            if marked.iter().all(|b| {
+                labels_of(b).and_then(|l| l.author.as_deref()) == first_labels.author.as_deref()
+            }) {
+                common.author = first_labels.author.clone();
+            }
+            if marked.iter().all(|b| {
+                labels_of(b).and_then(BookLabels::series_name) == first_labels.series_name()
+            }) {
+                // Each book keeps its own number; only the series is shared.
+                common.series = first_labels
+                    .series_name()
+                    .map(|name| (name.to_string(), None));
+            }
+            common.normalize();
+            let all_favorite = marked.iter().all(|b| b.favorite);
+            let common_status =
+                Some(marked[0].status).filter(|status| marked.iter().all(|b| b.status == *status));
+
+            self.assign_labels_panel.book_path = Some(marked[0].path.clone());
+            self.assign_labels_panel.bulk_paths = marked.iter().map(|b| b.path.clone()).collect();
+            self.assign_labels_panel.staged_favorite = all_favorite;
+            self.assign_labels_panel.initial_favorite = all_favorite;
+            self.assign_labels_panel.staged_status = common_status;
+            self.assign_labels_panel.initial_status = common_status;
+            self.assign_labels_panel.initial = common.clone();
+            self.assign_labels_panel.staged = common;
+            return;
+        }
+
+        if let Some(path) = self.selected_book_path()
+            && let Some(book) = self.ctx.books.iter().find(|b| b.path == path)
+        {
+            let mut labels = self
+                .ctx
+                .labels_by_path
+                .get(&path)
+                .cloned()
+                .unwrap_or_default();
+            labels.normalize();
+            self.assign_labels_panel.book_path = Some(path);
+            self.assign_labels_panel.staged_favorite = book.favorite;
+            self.assign_labels_panel.staged_status = Some(book.status);
+            self.assign_labels_panel.staged = labels;
+        } else {
+            self.assign_labels_panel.book_path = None;
+            self.assign_labels_panel.staged_favorite = false;
+            self.assign_labels_panel.staged_status = None;
+            self.assign_labels_panel.staged = BookLabels::default();
+            self.assign_labels_panel.error = Some("No selection".to_string());
+        }
+    }
+
+    fn open_label_catalog_input_panel(
+        &mut self,
+        mode: LabelCatalogInputMode,
+        kind: TagKind,
+        from: Option<String>,
+        prefill: String,
+    ) {
+        self.label_catalog_input_panel.open = true;
+        self.label_catalog_input_panel.mode = mode;
+        self.label_catalog_input_panel.kind = kind;
+        self.label_catalog_input_panel.from = from;
+        self.label_catalog_input_panel.input.set(prefill);
+        self.label_catalog_input_panel.error = None;
+        self.settings_panel.open = false;
+        self.scan_panel.open = false;
+        self.normalize_selection_to_visible();
+    }
+
+    fn label_manager_entries(&self, tab: LabelManagerTab) -> Vec<(String, usize)> {
+        let query = self
+            .label_manager_panel
+            .filter
+            .as_str()
+            .trim()
+            .to_ascii_lowercase();
+
+        let kind = tab.kind();
+        let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        for labels in self.ctx.labels_by_path.values() {
+            let names: Vec<&str> = match kind {
+                TagKind::Tag => labels.tags.iter().map(String::as_str).collect(),
+                TagKind::Collection => labels.collection.as_deref().into_iter().collect(),
+                TagKind::Author => labels.author.as_deref().into_iter().collect(),
+                TagKind::Series => labels.series_name().into_iter().collect(),
+            };
+            for name in names {
+                let name = name.trim();
+                if name.is_empty() {
+                    continue;
+                }
+                *counts.entry(name.to_ascii_lowercase()).or_insert(0) += 1;
+            }
+        }
+
+        let mut out = Vec::new();
+        for name in self.ctx.known_labels(kind) {
+            let trimmed = name.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            if !query.is_empty() && !trimmed.to_ascii_lowercase().contains(&query) {
+                continue;
+            }
+
+            let count = counts
+                .get(&trimmed.to_ascii_lowercase())
+                .copied()
+                .unwrap_or(0);
+            out.push((trimmed.to_string(), count));
+        }
+        out
+    }
+
+    fn label_manager_selected_target(&self) -> Option<LabelDeleteTarget> {
+        let tab = self.label_manager_panel.tab;
+        let entries = self.label_manager_entries(tab);
+        let (name, _count) = entries.get(self.label_manager_panel.cursor(tab)).cloned()?;
+        Some(LabelDeleteTarget {
+            kind: tab.kind(),
+            name,
+        })
+    }
+
+    fn handle_label_manager_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        if let Some(target) = self.label_manager_panel.confirm_delete.clone() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => {
+                    self.apply_delete_label(&target);
+                    self.label_manager_panel.confirm_delete = None;
+                    self.label_manager_panel.error = None;
+                }
+                KeyCode::Char('n') | KeyCode::Esc => {
+                    self.label_manager_panel.confirm_delete = None;
+                }
+                _ => {}
+            }
+            return Ok(None);
+        }
+
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && let KeyCode::Char('u') = key.code
+        {
+            self.label_manager_panel.filter.clear();
+            self.label_manager_panel.filter_editing = false;
+            self.label_manager_panel.error = None;
+            return Ok(None);
+        }
+
+        if self.label_manager_panel.filter_editing {
+            match key.code {
+                KeyCode::Esc => {
+                    self.label_manager_panel.filter_editing = false;
+                }
+                KeyCode::Tab => {
+                    self.label_manager_panel.filter_editing = false;
+                    self.label_manager_panel.tab = self.label_manager_panel.tab.next();
+                }
+                _ => {
+                    self.label_manager_panel.filter.handle_key(key);
+                }
+            }
+
+            let entries = self.label_manager_entries(self.label_manager_panel.tab);
+            let cursor = self.label_manager_panel.cursor_mut();
+            if entries.is_empty() {
+                *cursor = 0;
+            } else {
+                *cursor = (*cursor).min(entries.len().saturating_sub(1));
+            }
+            return Ok(None);
+        }
+
+        match key.code {
+            KeyCode::Tab => {
+                self.label_manager_panel.tab = self.label_manager_panel.tab.next();
+                Ok(None)
+            }
+            KeyCode::Char('/') => {
+                self.label_manager_panel.filter_editing = true;
+                Ok(None)
+            }
+            KeyCode::Up => {
+                let cursor = self.label_manager_panel.cursor_mut();
+                *cursor = cursor.saturating_sub(1);
+                Ok(None)
+            }
+            KeyCode::Down => {
+                let entries = self.label_manager_entries(self.label_manager_panel.tab);
+                let cursor = self.label_manager_panel.cursor_mut();
+                if !entries.is_empty() {
+                    *cursor = (*cursor).saturating_add(1).min(entries.len() - 1);
+                }
+                Ok(None)
+            }
+            KeyCode::Char('n') => {
+                let kind = self.label_manager_panel.tab.kind();
+                let prefill = self.label_manager_panel.filter.as_str().to_string();
+                self.open_label_catalog_input_panel(
+                    LabelCatalogInputMode::Create,
+                    kind,
+                    None,
+                    prefill,
+                );
+                Ok(None)
+            }
+            KeyCode::Char('r') => {
+                let Some(target) = self.label_manager_selected_target() else {
+                    self.label_manager_panel.error = Some("Nothing selected".to_string());
+                    return Ok(None);
+                };
+                self.open_label_catalog_input_panel(
+                    LabelCatalogInputMode::Rename,
+                    target.kind,
+                    Some(target.name.clone()),
+                    target.name,
+                );
+                Ok(None)
+            }
+            KeyCode::Char('d') => {
+                let Some(target) = self.label_manager_selected_target() else {
+                    self.label_manager_panel.error = Some("Nothing selected".to_string());
+                    return Ok(None);
+                };
+                self.label_manager_panel.confirm_delete = Some(target);
+                Ok(None)
+            }
+            KeyCode::Char('u') => {
+                self.undo_label_catalog_op();
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn assign_visible_collections(&self) -> Vec<String> {
+        let query = self
+            .assign_labels_panel
+            .collection_query
+            .as_str()
+            .trim()
+            .to_ascii_lowercase();
+        self.ctx
+            .known_collections
+            .iter()
+            .filter(|c| {
+                let c = c.trim();
+                !c.is_empty() && (query.is_empty() || c.to_ascii_lowercase().contains(&query))
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn assign_visible_tags(&self) -> Vec<String> {
+        let query = self
+            .assign_labels_panel
+            .tag_query
+            .as_str()
+            .trim()
+            .to_ascii_lowercase();
+        self.ctx
+            .known_tags
+            .iter()
+            .filter(|t| {
+                let t = t.trim();
+                !t.is_empty() && (query.is_empty() || t.to_ascii_lowercase().contains(&query))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Authors to pick from: the catalog plus the authors read from the documents.
+    fn assign_visible_authors(&self) -> Vec<String> {
+        let query = self
+            .assign_labels_panel
+            .author_query
+            .as_str()
+            .trim()
+            .to_ascii_lowercase();
+        let mut authors: Vec<String> = self
+            .ctx
+            .known_authors
+            .iter()
+            .chain(self.ctx.books.iter().filter_map(|b| b.author.as_ref()))
+            .filter(|a| {
+                let a = a.trim();
+                !a.is_empty() && (query.is_empty() || a.to_ascii_lowercase().contains(&query))
+            })
+            .cloned()
+            .collect();
+        authors.sort_by_key(|a| a.to_ascii_lowercase());
+        authors.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        authors
+    }
+
+    fn assign_visible_series(&self) -> Vec<String> {
+        let (query, _number) = parse_series(self.assign_labels_panel.series_query.as_str());
+        let query = query.to_ascii_lowercase();
+        self.ctx
+            .known_series
+            .iter()
+            .filter(|s| {
+                let s = s.trim();
+                !s.is_empty() && (query.is_empty() || s.to_ascii_lowercase().contains(&query))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Whether Enter in the filter should set the typed author or series on the book.
+    fn assign_query_sets_value(&self) -> bool {
+        let query = match self.assign_labels_panel.focus {
+            AssignFocus::Author => &self.assign_labels_panel.author_query,
+            AssignFocus::Series => &self.assign_labels_panel.series_query,
+            AssignFocus::Collections | AssignFocus::Tags => return false,
+        };
+        !query.as_str().trim().is_empty()
+    }
+
+    /// Stages the author or series typed into the Assign filter, adding a new name to the
+    /// catalog. `Dune #2` also sets the book's number; `#2` alone renumbers the staged series.
+    fn set_value_from_assign_query(&mut self) {
+        let kind = match self.assign_labels_panel.focus {
+            AssignFocus::Author => TagKind::Author,
+            AssignFocus::Series => TagKind::Series,
+            AssignFocus::Collections | AssignFocus::Tags => return,
+        };
+        let typed = self
+            .assign_labels_panel
+            .focused_query()
+            .as_str()
+            .to_string();
+        let (name, number) = match kind {
+            TagKind::Series => parse_series(&typed),
+            _ => (typed.trim().to_string(), None),
+        };
+        if name.is_empty() {
+            match (&mut self.assign_labels_panel.staged.series, number) {
+                (Some((_, current)), Some(number)) => *current = Some(number),
+                _ => {
+                    self.assign_labels_panel.error = Some("Pick a series first".to_string());
+                    return;
+                }
+            }
+        } else {
+            let known = self
+                .ctx
+                .known_labels(kind)
+                .iter()
+                .find(|k| k.eq_ignore_ascii_case(&name))
+                .cloned();
+            let name = match known {
+                Some(known) => known,
+                None => {
+                    self.ctx.known_labels_mut(kind).push(name.clone());
+                    self.ctx.normalize_label_catalog();
+                    self.ctx
+                        .dirty_label_catalog_ops
+                        .push(LabelCatalogOp::Create {
+                            kind,
+                            name: name.clone(),
+                        });
+                    name
+                }
+            };
+            if kind == TagKind::Series {
+                self.assign_labels_panel.staged.series = Some((name, number));
+            } else {
+                self.assign_labels_panel.staged.author = Some(name);
+            }
+        }
+        self.assign_labels_panel.focused_query().clear();
+        self.assign_labels_panel.query_editing = false;
+        self.assign_labels_panel.error = None;
+    }
+
+    fn commit_assign_labels_panel(&mut self) {
+        if !self.assign_labels_panel.bulk_paths.is_empty() {
+            self.commit_bulk_assign_labels();
+            return;
+        }
+
+        let Some(path) = self.assign_labels_panel.book_path.clone() else {
+            return;
+        };
+
+        if let Some(book) = self.ctx.books.iter_mut().find(|b| b.path == path)
+            && book.favorite != self.assign_labels_panel.staged_favorite
+        {
+            book.favorite = self.assign_labels_panel.staged_favorite;
+            self.ctx.dirty_favorite_paths.insert(path.clone());
+        }
+        if let Some(status) = self.assign_labels_panel.staged_status {
+            self.ctx.set_book_status(&path, status);
+        }
+
+        let mut labels = self.assign_labels_panel.staged.clone();
+        labels.normalize();
+        self.ctx.ensure_known_labels(&labels);
+        self.ctx.set_book_labels(&path, labels);
+
+        self.assign_labels_panel.query_editing = false;
+        self.assign_labels_panel.error = None;
+        self.normalize_selection_to_visible();
+    }
+
+    fn commit_bulk_assign_labels(&mut self) {
+        let panel = &self.assign_labels_panel;
+        let mut staged = panel.staged.clone();
+        staged.normalize();
+        let removed_tags: Vec<String> = panel
+            .initial
+            .tags
+            .iter()
+            .filter(|tag| !staged.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+            .cloned()
+            .collect();
+        let collection_changed = staged.collection != panel.initial.collection;
+        let author_changed = staged.author != panel.initial.author;
+        let series_changed = staged.series != panel.initial.series;
+        let favorite_changed = panel.staged_favorite != panel.initial_favorite;
+        let staged_favorite = panel.staged_favorite;
+        let staged_status = panel
+            .staged_status
+            .filter(|_| panel.staged_status != panel.initial_status);
+        let paths = std::mem::take(&mut self.assign_labels_panel.bulk_paths);
+
+        self.ctx.ensure_known_labels(&staged);
+        for path in &paths {
+            if favorite_changed
+                && let Some(book) = self.ctx.books.iter_mut().find(|b| &b.path == path)
+                && book.favorite != staged_favorite
+            {
+                book.favorite = staged_favorite;
+                self.ctx.dirty_favorite_paths.insert(path.clone());
+            }
+            if let Some(status) = staged_status {
+                self.ctx.set_book_status(path, status);
+            }
+
+            let mut labels = self
+                .ctx
+                .labels_by_path
+                .get(path)
+                .cloned()
+                .unwrap_or_default();
+            labels
+                .tags
+                .retain(|t| !removed_tags.iter().any(|r| r.eq_ignore_ascii_case(t)));
+            for tag in &staged.tags {
+                if !labels.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                    labels.tags.push(tag.clone());
+                }
+            }
+            if collection_changed {
+                labels.collection = staged.collection.clone();
+            }
+            if author_changed {
+                labels.author = staged.author.clone();
+            }
+            if series_changed {
+                labels.series = staged.series.clone();
+            }
+            self.ctx.set_book_labels(path, labels);
+        }
+
+        self.marked_paths.clear();
+        self.assign_labels_panel.query_editing = false;
+        self.assign_labels_panel.error = None;
+        self.normalize_selection_to_visible();
+    }
+
+    fn handle_assign_labels_panel_key(&mut self, key: KeyEvent) -> anyhow::Result<Option<UiExit>> {
+        if self.assign_labels_panel.book_path.is_none() {
+            return Ok(None);
+        }
+
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && let KeyCode::Char('u') = key.code
+        {
+            self.assign_labels_panel.focused_query().clear();
+            return Ok(None);
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && let KeyCode::Char('n') = key.code
+        {
+            self.assign_labels_panel.focus = AssignFocus::Tags;
+            self.create_tag_from_assign_query();
+            return Ok(None);
+        }
+
+        if self.assign_labels_panel.query_editing {
+            match key.code {
+                KeyCode::Esc => {
+                    self.assign_labels_panel.query_editing = false;
+                }
+                KeyCode::Enter if self.assign_tag_query_creates() => {
+                    self.create_tag_from_assign_query();
+                }
+                KeyCode::Enter if self.assign_query_sets_value() => {
+                    self.set_value_from_assign_query();
+                }
+                KeyCode::Enter => {
+                    self.commit_assign_labels_panel();
+                }
+                KeyCode::Tab => {
+                    if let Some(completed) = self.assign_tag_namespace_completion() {
+                        self.assign_labels_panel.tag_query.set(completed);
+                    } else {
+                        self.assign_labels_panel.query_editing = false;
+                        self.assign_labels_panel.focus = self.assign_labels_panel.focus.next();
+                    }
+                }
+                _ => {
+                    self.assign_labels_panel.focused_query().handle_key(key);
+                }
+            }
+
+            let collections = self.assign_visible_collections();
+            let tags = self.assign_visible_tags();
+            self.assign_labels_panel.collection_cursor = self
+                .assign_labels_panel
+                .collection_cursor
+                .min(collections.len()); // +1 for "(none)"
+            if tags.is_empty() {
+                self.assign_labels_panel.tag_cursor = 0;
+            } else {
+                self.assign_labels_panel.tag_cursor =
+                    self.assign_labels_panel.tag_cursor.min(tags.len() - 1);
+            }
+            self.assign_labels_panel.author_cursor = self
+                .assign_labels_panel
+                .author_cursor
+                .min(self.assign_visible_authors().len());
+            self.assign_labels_panel.series_cursor = self
+                .assign_labels_panel
+                .series_cursor
+                .min(self.assign_visible_series().len());
+            return Ok(None);
+        }
+
+        match key.code {
+            KeyCode::Tab => {
+                self.assign_labels_panel.focus = self.assign_labels_panel.focus.next();
+                Ok(None)
+            }
+            KeyCode::Char('/') => {
+                self.assign_labels_panel.query_editing = true;
+                Ok(None)
+            }
+            KeyCode::Char('f') => {
+                self.assign_labels_panel.staged_favorite =
+                    !self.assign_labels_panel.staged_favorite;
+                Ok(None)
+            }
+            KeyCode::Char('u') => {
+                self.assign_labels_panel.staged_status = Some(
+                    self.assign_labels_panel
+                        .staged_status
+                        .map_or(ReadingStatus::Unread, ReadingStatus::next),
+                );
+                Ok(None)
+            }
+            KeyCode::Up => {
+                match self.assign_labels_panel.focus {
+                    AssignFocus::Collections => {
+                        self.assign_labels_panel.collection_cursor =
+                            self.assign_labels_panel.collection_cursor.saturating_sub(1);
+                    }
+                    AssignFocus::Tags => {
+                        self.assign_labels_panel.tag_cursor =
+                            self.assign_labels_panel.tag_cursor.saturating_sub(1);
+                    }
+                    AssignFocus::Author => {
+                        self.assign_labels_panel.author_cursor =
+                            self.assign_labels_panel.author_cursor.saturating_sub(1);
+                    }
+                    AssignFocus::Series => {
+                        self.assign_labels_panel.series_cursor =
+                            self.assign_labels_panel.series_cursor.saturating_sub(1);
+                    }
+                }
+                Ok(None)
+            }
+            KeyCode::Down => {
+                match self.assign_labels_panel.focus {
+                    AssignFocus::Collections => {
+                        let collections = self.assign_visible_collections();
+                        let max = collections.len() + 1;
+                        if max > 0 {
+                            self.assign_labels_panel.collection_cursor = self
+                                .assign_labels_panel
+                                .collection_cursor
+                                .saturating_add(1)
+                                .min(max - 1);
+                        }
+                    }
+                    AssignFocus::Tags => {
+                        let tags = self.assign_visible_tags();
+                        if !tags.is_empty() {
+                            self.assign_labels_panel.tag_cursor = self
+                                .assign_labels_panel
+                                .tag_cursor
+                                .saturating_add(1)
+                                .min(tags.len() - 1);
+                        }
+                    }
+                    // Below "(none)", one row per name.
+                    AssignFocus::Author => {
+                        let max = self.assign_visible_authors().len();
+                        self.assign_labels_panel.author_cursor =
+                            (self.assign_labels_panel.author_cursor + 1).min(max);
+                    }
+                    AssignFocus::Series => {
+                        let max = self.assign_visible_series().len();
+                        self.assign_labels_panel.series_cursor =
+                            (self.assign_labels_panel.series_cursor + 1).min(max);
+                    }
+                }
+                Ok(None)
+            }
+            KeyCode::Char(' ') => {
+                match self.assign_labels_panel.focus {
+                    AssignFocus::Collections => {
+                        let collections = self.assign_visible_collections();
+                        if self.assign_labels_panel.collection_cursor == 0 {
+                            self.assign_labels_panel.staged.collection = None;
+                        } else if let Some(name) = collections
+                            .get(self.assign_labels_panel.collection_cursor - 1)
+                            .cloned()
+                        {
+                            self.assign_labels_panel.staged.collection = Some(name);
+                        }
+                    }
+                    AssignFocus::Tags => {
+                        let tags = self.assign_visible_tags();
+                        let Some(name) = tags.get(self.assign_labels_panel.tag_cursor).cloned()
+                        else {
+                            return Ok(None);
+                        };
+                        if let Some(pos) = self
+                            .assign_labels_panel
+                            .staged
+                            .tags
+                            .iter()
+                            .position(|t| t.eq_ignore_ascii_case(&name))
+                        {
+                            self.assign_labels_panel.staged.tags.remove(pos);
+                        } else {
+                            self.assign_labels_panel.staged.tags.push(name);
+                        }
+                        self.assign_labels_panel.staged.normalize();
+                    }
+                    AssignFocus::Author => {
+                        let cursor = self.assign_labels_panel.author_cursor;
+                        self.assign_labels_panel.staged.author = match cursor {
+                            0 => None,
+                            _ => self.assign_visible_authors().get(cursor - 1).cloned(),
+                        };
+                    }
+                    AssignFocus::Series => {
+                        let cursor = self.assign_labels_panel.series_cursor;
+                        let number = self.assign_labels_panel.staged.series_number();
+                        self.assign_labels_panel.staged.series = match cursor {
+                            0 => None,
+                            _ => self
+                                .assign_visible_series()
+                                .get(cursor - 1)
+                                .map(|name| (name.clone(), number)),
+                        };
+                    }
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether another catalog label of `kind` already uses `name`, ignoring case. A rename
+    /// passes its old name as `except` so changing only the spelling is allowed.
+    fn label_name_taken(&self, kind: TagKind, name: &str, except: Option<&str>) -> bool {
+        self.ctx.known_labels(kind).iter().any(|k| {
+            k.eq_ignore_ascii_case(name) && !except.is_some_and(|e| k.eq_ignore_ascii_case(e))
+        })
+    }
+
+    /// Whether Enter in the tag filter should create the typed tag: nothing in the catalog
+    /// matches it.
+    fn assign_tag_query_creates(&self) -> bool {
+        self.assign_labels_panel.focus == AssignFocus::Tags
+            && !self
+                .assign_labels_panel
+                .tag_query
+                .as_str()
+                .trim()
+                .is_empty()
+            && self.assign_visible_tags().is_empty()
+    }
+
+    /// The tag namespace the Assign tag filter can be completed to, as Tab offers it.
+    fn assign_tag_namespace_completion(&self) -> Option<String> {
+        if !self.assign_labels_panel.query_editing
+            || self.assign_labels_panel.focus != AssignFocus::Tags
+        {
+            return None;
+        }
+        complete_tag_namespace(
+            self.assign_labels_panel.tag_query.as_str(),
+            &self.ctx.known_tags,
+        )
+    }
+
+    /// Adds the tag typed into the Assign filter to the catalog and stages it on the book.
+    fn create_tag_from_assign_query(&mut self) {
+        let name = self
+            .assign_labels_panel
+            .tag_query
+            .as_str()
+            .trim()
+            .to_string();
+        if name.is_empty() {
+            self.assign_labels_panel.error = Some("Type a tag name to create".to_string());
+            return;
+        }
+        if self.label_name_taken(TagKind::Tag, &name, None) {
+            self.assign_labels_panel.error = Some(format!("Tag \"{name}\" already exists"));
+            return;
+        }
+
+        self.ctx.known_tags.push(name.clone());
+        self.ctx.normalize_label_catalog();
+        self.ctx
+            .dirty_label_catalog_ops
+            .push(LabelCatalogOp::Create {
+                kind: TagKind::Tag,
+                name: name.clone(),
+            });
+        self.assign_labels_panel.staged.tags.push(name.clone());
+        self.assign_labels_panel.staged.normalize();
+
+        self.assign_labels_panel.tag_query.clear();
+        self.assign_labels_panel.query_editing = false;
+        self.assign_labels_panel.error = None;
+        self.assign_labels_panel.tag_cursor = self
+            .assign_visible_tags()
+            .iter()
+            .position(|t| t == &name)
+            .unwrap_or(0);
+    }
+
+    fn remove_name_case_insensitive(values: &mut Vec<String>, name: &str) {
+        values.retain(|v| !v.eq_ignore_ascii_case(name));
+    }
+
+    fn apply_delete_label(&mut self, target: &LabelDeleteTarget) {
+        self.label_undo.push(LabelUndo::capture(
+            target.kind,
+            &target.name,
+            None,
+            &self.ctx.labels_by_path,
+        ));
+        self.ctx
+            .dirty_label_catalog_ops
+            .push(LabelCatalogOp::Delete {
+                kind: target.kind,
+                name: target.name.clone(),
+            });
+        Self::remove_name_case_insensitive(self.ctx.known_labels_mut(target.kind), &target.name);
+        self.retarget_label_filter(target.kind, &target.name, None);
+        self.ctx.normalize_label_catalog();
+
+        let mut to_remove = Vec::new();
+        for (path, labels) in self.ctx.labels_by_path.iter_mut() {
+            labels.remove_label(target.kind, &target.name);
+            labels.normalize();
+            if labels.is_empty() {
+                to_remove.push(path.clone());
+            }
+        }
+        for path in to_remove {
+            self.ctx.labels_by_path.remove(&path);
+        }
+
+        self.assign_labels_panel
+            .staged
+            .remove_label(target.kind, &target.name);
+        self.assign_labels_panel.staged.normalize();
+    }
+
+    /// Points library filters on the label `from` at `to`, or drops them when the label is
+    /// deleted (`to` is `None`).
+    fn retarget_label_filter(&mut self, kind: TagKind, from: &str, to: Option<&str>) {
+        match kind {
+            TagKind::Tag => {
+                self.ctx.tag_filters = self
+                    .ctx
+                    .tag_filters
+                    .iter()
+                    .filter_map(|tag| {
+                        if tag.eq_ignore_ascii_case(from) {
+                            to.map(str::to_string)
+                        } else {
+                            Some(tag.clone())
+                        }
+                    })
+                    .collect();
+                self.normalize_tag_filters();
+            }
+            TagKind::Collection => {
+                if let CollectionFilter::Selected(selected) = &self.ctx.collection_filter
+                    && selected.eq_ignore_ascii_case(from)
+                {
+                    self.ctx.collection_filter = match to {
+                        Some(to) => CollectionFilter::Selected(to.to_string()),
+                        None => CollectionFilter::Any,
+                    };
+                }
+            }
+            TagKind::Author | TagKind::Series => {
+                let filter = if kind == TagKind::Author {
+                    &mut self.ctx.author_filter
+                } else {
+                    &mut self.ctx.series_filter
+                };
+                if filter
+                    .as_deref()
+                    .is_some_and(|f| f.eq_ignore_ascii_case(from))
+                {
+                    *filter = to.map(str::to_string);
+                }
+            }
+        }
+    }
+
+    fn apply_rename_label(&mut self, kind: TagKind, from: &str, to: &str) -> bool {
+        let undo = LabelUndo::capture(kind, from.trim(), Some(to.trim()), &self.ctx.labels_by_path);
+        if !self.rename_label_in_session(kind, from, to) {
+            return false;
+        }
+        self.label_undo.push(undo);
+        true
+    }
+
+    /// Reverts the latest catalog delete or rename, queueing the catalog change and the
+    /// restored book labels for saving.
+    fn undo_label_catalog_op(&mut self) {
+        let Some(undo) = self.label_undo.pop() else {
+            self.label_manager_panel.error = Some("Nothing to undo".to_string());
+            return;
+        };
+        let known = self.ctx.known_labels(undo.kind);
+        let exists = |name: &str| known.iter().any(|k| k.eq_ignore_ascii_case(name));
+        let renamed_back = match &undo.renamed_to {
+            Some(to) if exists(to) => {
+                if exists(&undo.name) && !undo.name.eq_ignore_ascii_case(to) {
+                    self.label_manager_panel.error =
+                        Some(format!("Can't undo: \"{}\" exists again", undo.name));
+                    return;
+                }
+                self.rename_label_in_session(undo.kind, to, &undo.name)
+            }
+            _ => false,
+        };
+        let op = if let (true, Some(to)) = (renamed_back, &undo.renamed_to) {
+            LabelCatalogOp::Rename {
+                kind: undo.kind,
+                from: to.clone(),
+                to: undo.name.clone(),
+            }
+        } else {
+            // A deleted label, or a renamed one that has since gone: bring the old name back.
+            self.ctx.known_labels_mut(undo.kind).push(undo.name.clone());
+            self.ctx.normalize_label_catalog();
+            LabelCatalogOp::Create {
+                kind: undo.kind,
+                name: undo.name.clone(),
+            }
+        };
+        self.ctx.dirty_label_catalog_ops.push(op);
+        for path in undo.restore_labels(&mut self.ctx.labels_by_path) {
+            self.ctx.dirty_label_paths.insert(path);
+        }
+        self.label_manager_panel.error = None;
+        let action = if undo.renamed_to.is_some() {
+            "rename"
+        } else {
+            "delete"
+        };
+        self.library_notice = Some(format!(
+            "undid {action} of {} \"{}\" ({} books)",
+            undo.kind,
+            undo.name,
+            undo.prior_labels.len()
+        ));
+    }
+
+    fn rename_label_in_session(&mut self, kind: TagKind, from: &str, to: &str) -> bool {
+        let from = from.trim();
+        let to = to.trim();
+        if from.is_empty() || to.is_empty() {
+            return false;
+        }
+
+        let list = self.ctx.known_labels_mut(kind);
+        let Some(pos) = list.iter().position(|v| v.eq_ignore_ascii_case(from)) else {
+            return false;
+        };
+        list[pos] = to.to_string();
+        self.ctx.normalize_label_catalog();
+        self.retarget_label_filter(kind, from, Some(to));
+
+        let mut to_remove = Vec::new();
+        for (path, labels) in self.ctx.labels_by_path.iter_mut() {
+            labels.rename_label(kind, from, to);
+            labels.normalize();
+            if labels.is_empty() {
+                to_remove.push(path.clone());
+            }
+        }
+        for path in to_remove {
+            self.ctx.labels_by_path.remove(&path);
+        }
+
+        self.assign_labels_panel.staged.rename_label(kind, from, to);
+        self.assign_labels_panel.staged.normalize();
+        true
+    }
+
+    fn handle_label_catalog_input_panel_key(
+        &mut self,
+        key: KeyEvent,
+    ) -> anyhow::Result<Option<UiExit>> {
+        match key.code {
+            KeyCode::Esc => {
+                self.label_catalog_input_panel.open = false;
+                self.label_catalog_input_panel.error = None;
+                self.label_catalog_input_panel.input.clear();
+                self.label_catalog_input_panel.from = None;
+                Ok(None)
+            }
+            KeyCode::Enter => {
+                let name = self
+                    .label_catalog_input_panel
+                    .input
+                    .as_str()
+                    .trim()
+                    .to_string();
+                if name.is_empty() {
+                    self.label_catalog_input_panel.error =
+                        Some(match self.label_catalog_input_panel.kind {
+                            TagKind::Author => "Enter an author name".to_string(),
+                            kind => format!("Enter a {kind} name"),
+                        });
+                    return Ok(None);
+                }
+
+                match self.label_catalog_input_panel.mode {
+                    LabelCatalogInputMode::Create => {
+                        if self.label_name_taken(self.label_catalog_input_panel.kind, &name, None) {
+                            self.label_catalog_input_panel.error =
+                                Some("Already exists".to_string());
+                            return Ok(None);
+                        }
+
+                        self.ctx
+                            .known_labels_mut(self.label_catalog_input_panel.kind)
+                            .push(name.clone());
+                        self.ctx.normalize_label_catalog();
+                        self.ctx
+                            .dirty_label_catalog_ops
+                            .push(LabelCatalogOp::Create {
+                                kind: self.label_catalog_input_panel.kind,
+                                name: name.clone(),
+                            });
+                    }
+                    LabelCatalogInputMode::Rename => {
+                        let Some(from) = self.label_catalog_input_panel.from.clone() else {
+                            self.label_catalog_input_panel.error =
+                                Some("Nothing selected".to_string());
+                            return Ok(None);
+                        };
+
+                        if self.label_name_taken(
+                            self.label_catalog_input_panel.kind,
+                            &name,
+                            Some(&from),
+                        ) {
+                            self.label_catalog_input_panel.error =
+                                Some("Already exists".to_string());
+                            return Ok(None);
+                        }
+
+                        if !self.apply_rename_label(
+                            self.label_catalog_input_panel.kind,
+                            &from,
+                            &name,
+                        ) {
+                            self.label_catalog_input_panel.error =
+                                Some("Nothing selected".to_string());
+                            return Ok(None);
+                        }
+
+                        self.ctx
+                            .dirty_label_catalog_ops
+                            .push(LabelCatalogOp::Rename {
+                                kind: self.label_catalog_input_panel.kind,
+                                from,
+                                to: name.clone(),
+                            });
+                    }
+                }
+
+                self.label_catalog_input_panel.open = false;
+                self.label_catalog_input_panel.error = None;
+                self.label_catalog_input_panel.input.clear();
+                self.label_catalog_input_panel.from = None;
+                Ok(None)
+            }
+            _ => {
+                self.label_catalog_input_panel.input.handle_key(key);
+                Ok(None)
+            }
+        }
+    }
+
+    fn visible_indices(&self) -> Vec<usize> {
+        let query = LibraryQuery::parse(&self.ctx.library_query);
+        let now = unix_now_secs();
+        let mut out = Vec::new();
+        for (idx, book) in self.ctx.books.iter().enumerate() {
+            if !self.ctx.is_listed(book) {
+                continue;
+            }
+            if self.ctx.favorites_only && !book.favorite {
+                continue;
+            }
+            if self
+                .ctx
+                .status_filter
+                .is_some_and(|status| status != book.status)
+            {
+                continue;
+            }
+            if !self.ctx.added_within_filter(&book.path, now) {
+                continue;
+            }
+            if !self.ctx.kind_filter_matches(&book.path) {
+                continue;
+            }
+
+            let labels = self
+                .ctx
+                .labels_by_path
+                .get(&book.path)
+                .cloned()
+                .unwrap_or_default();
+            if !matches_collection_filter(&self.ctx.collection_filter, labels.collection.as_deref())
+            {
+                continue;
+            }
+            if !matches_tag_filter(&self.ctx.tag_filters, self.ctx.tag_match_mode, &labels.tags) {
+                continue;
+            }
+            if !matches_name_filter(
+                self.ctx.author_filter.as_deref(),
+                book_author(book, &labels),
+            ) || !matches_name_filter(self.ctx.series_filter.as_deref(), labels.series_name())
+            {
+                continue;
+            }
+
+            if query.matches(book, &labels) {
+                out.push(idx);
+            }
+        }
+        self.sort_visible(&mut out);
+        out
+    }
+
+    fn sort_visible(&self, indices: &mut [usize]) {
+        let books = &self.ctx.books;
+        if self.collection_view_name().is_some() {
+            indices.sort_by(|&a, &b| self.ctx.cmp_collection_order(&books[a], &books[b]));
+            return;
+        }
+        if self.ctx.series_filter.is_some() {
+            indices.sort_by(|&a, &b| self.ctx.cmp_series_order(&books[a], &books[b]));
+            return;
+        }
+        let by_title = |a: usize, b: usize| {
+            books[a]
+                .title
+                .to_lowercase()
+                .cmp(&books[b].title.to_lowercase())
+        };
+
+        match self.ctx.settings.sort_mode {
+            SortMode::Title => indices.sort_by(|&a, &b| by_title(a, b)),
+            SortMode::LastOpened => indices.sort_by(|&a, &b| {
+                cmp_desc_missing_last(books[a].last_opened, books[b].last_opened)
+                    .then_with(|| by_title(a, b))
+            }),
+            SortMode::Added => indices.sort_by(|&a, &b| {
+                let added = |idx: usize| self.ctx.added_at_by_path.get(&books[idx].path).copied();
+                cmp_desc_missing_last(added(a), added(b)).then_with(|| by_title(a, b))
+            }),
+            SortMode::Size => indices.sort_by(|&a, &b| {
+                let size = |idx: usize| self.size_by_path.get(&books[idx].path).copied().flatten();
+                cmp_desc_missing_last(size(a), size(b)).then_with(|| by_title(a, b))
+            }),
+        }
+    }
+
+    fn refresh_size_cache(&mut self) {
+        if self.ctx.settings.sort_mode != SortMode::Size {
+            return;
+        }
+        for book in &self.ctx.books {
+            if self.size_by_path.contains_key(&book.path) {
+                continue;
+            }
+            let decoded = bookshelf_core::decode_path(&book.path);
+            let size = std::fs::metadata(&decoded).ok().map(|m| m.len());
+            self.size_by_path.insert(book.path.clone(), size);
+        }
+    }
+
+    fn normalize_selection_to_visible(&mut self) {
+        if self.ctx.books.is_empty() {
+            self.ctx.selected = 0;
+            return;
+        }
+
+        if self.ctx.selected >= self.ctx.books.len() {
+            self.ctx.selected = 0;
+        }
+
+        let visible = self.visible_indices();
+        if visible.is_empty() {
+            self.ctx.selected = 0;
+            return;
+        }
+
+        if !visible.contains(&self.ctx.selected) {
+            self.ctx.selected = visible[0];
+        }
+    }
+
+    fn selected_visible_index(&self) -> Option<usize> {
+        let visible = self.visible_indices();
+        if visible.is_empty() {
+            return None;
+        }
+
+        let selected = self
+            .ctx
+            .selected
+            .min(self.ctx.books.len().saturating_sub(1));
+        if visible.contains(&selected) {
+            Some(selected)
+        } else {
+            Some(visible[0])
+        }
+    }
+
+    /// Returns whether the selection moved.
+    fn select_next_visible(&mut self) -> bool {
+        let visible = self.visible_indices();
+        if visible.is_empty() {
+            return false;
+        }
+
+        let Some(pos) = visible.iter().position(|idx| *idx == self.ctx.selected) else {
+            self.ctx.selected = visible[0];
+            return true;
+        };
+        if pos + 1 < visible.len() {
+            self.ctx.selected = visible[pos + 1];
+            return true;
+        }
+        false
+    }
+
+    /// Returns whether the selection moved.
+    fn select_prev_visible(&mut self) -> bool {
+        let visible = self.visible_indices();
+        if visible.is_empty() {
+            return false;
+        }
+
+        let Some(pos) = visible.iter().position(|idx| *idx == self.ctx.selected) else {
+            self.ctx.selected = visible[0];
+            return true;
+        };
+        if pos > 0 {
+            self.ctx.selected = visible[pos - 1];
+            return true;
+        }
+        false
+    }
+
+    /// Moves the selection one cell in the cover grid. Returns whether it moved.
+    fn select_in_grid(&mut self, mv: GridMove) -> bool {
+        let visible = self.visible_indices();
+        if visible.is_empty() {
+            return false;
+        }
+
+        let Some(pos) = visible.iter().position(|idx| *idx == self.ctx.selected) else {
+            self.ctx.selected = visible[0];
+            return true;
+        };
+        match grid_move(pos, visible.len(), self.cover_grid.columns, mv) {
+            Some(next) => {
+                self.ctx.selected = visible[next];
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Key hints under the library; `narrow` adds the key that switches list and details.
+    fn main_footer_lines(&self, narrow: bool) -> Vec<Line<'static>> {
+        if self.quit_prompt {
+            return vec![Line::from(vec![
+                Span::raw("unsaved changes will be written — "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to quit, "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to stay"),
+            ])];
+        }
+
+        if self.label_catalog_input_panel.open {
+            return vec![Line::from(vec![
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" cancel  "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" apply  "),
+                Span::styled("Backspace", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" delete  "),
+                Span::styled("Ctrl+u", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" clear"),
+            ])];
+        }
+
+        if self.search_panel.open {
+            let enter_action = match self.search_panel.tab {
+                SearchTab::Search => "close",
+                SearchTab::Assign => {
+                    if !self.assign_labels_panel.query_editing {
+                        "apply + close"
+                    } else if self.assign_tag_query_creates() {
+                        "create tag"
+                    } else {
+                        "done"
+                    }
+                }
+                SearchTab::Manage => {
+                    if self.label_manager_panel.confirm_delete.is_some() {
+                        "delete"
+                    } else if self.label_manager_panel.filter_editing {
+                        "done"
+                    } else {
+                        "close"
+                    }
+                }
+            };
+
+            return vec![Line::from(vec![
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" cancel  "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(" {enter_action}")),
+            ])];
+        }
+
+        let mut first_line = Line::from(vec![
+            Span::styled(
+                self.key_bindings.label(KeyAction::Quit),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" quit  "),
+            Span::styled(
+                format!(
+                    "{}/{}",
+                    self.key_bindings.label(KeyAction::PrevItem),
+                    self.key_bindings.label(KeyAction::NextItem)
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" move  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::OpenReader),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" read  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::ToggleMark),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" mark  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::MarkAllVisible),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" mark all"),
+        ]);
+        if narrow {
+            let hint = [
+                Span::styled(
+                    self.key_bindings.label(KeyAction::ToggleDetails),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(if self.details_pane {
+                    " list  "
+                } else {
+                    " details  "
+                }),
+            ];
+            first_line.spans.splice(0..0, hint);
+        }
+        vec![
+            first_line,
+            Line::from(vec![
+                Span::styled(
+                    self.key_bindings.label(KeyAction::OpenFilters),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" filters  "),
+                Span::styled(
+                    format!(
+                        "{}/{}",
+                        self.key_bindings.label(KeyAction::FilterByCollection),
+                        self.key_bindings.label(KeyAction::FilterByTag)
+                    ),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" same collection/tag  "),
+                Span::styled(
+                    self.key_bindings.label(KeyAction::OpenLabels),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" labels  "),
+                Span::styled(
+                    self.key_bindings.label(KeyAction::OpenCatalog),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" catalog  "),
+                Span::styled(
+                    self.key_bindings.label(KeyAction::OpenSettings),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" settings  "),
+                Span::styled(
+                    self.key_bindings.label(KeyAction::CycleSort),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" sort  "),
+                Span::styled(
+                    self.key_bindings.label(KeyAction::ToggleLibraryView),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(if self.ctx.settings.library_grid {
+                    " list  "
+                } else {
+                    " covers  "
+                }),
+                Span::styled(
+                    self.key_bindings.label(KeyAction::OpenHistory),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" recent  "),
+                Span::styled(
+                    self.key_bindings.label(KeyAction::SearchNotes),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" notes  "),
+                Span::styled(
+                    self.key_bindings.label(KeyAction::SaveNow),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" save"),
+            ]),
+        ]
+    }
+
+    fn main_header_lines(&self) -> Vec<Line<'static>> {
+        let mut lines = vec![Line::from(vec![
+            Span::styled("Bookshelf", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" — library"),
+        ])];
+
+        if let Some(status_line) = self.active_filter_status_line() {
+            lines.push(status_line);
+        }
+
+        lines
+    }
+
+    fn active_filter_status_line(&self) -> Option<Line<'static>> {
+        if self.search_panel.open {
+            return None;
+        }
+
+        let mut parts: Vec<Span<'static>> = Vec::new();
+        let style = Style::default().fg(Color::Cyan);
+
+        let query = LibraryQuery::parse(&self.ctx.library_query);
+        if !query.is_empty() {
+            parts.push(Span::styled(format!("filter: {query}"), style));
+        }
+
+        if self.ctx.favorites_only {
+            parts.push(Span::styled("favorites only".to_string(), style));
+        }
+
+        if self.ctx.include_archived {
+            parts.push(Span::styled("including archived".to_string(), style));
+        }
+
+        if let Some(status) = self.ctx.status_filter {
+            parts.push(Span::styled(format!("status: {status}"), style));
+        }
+
+        if let Some(days) = self.ctx.added_within_days {
+            parts.push(Span::styled(format_added_within(days), style));
+        }
+
+        if let Some(kind) = self.ctx.kind_filter {
+            parts.push(Span::styled(format!("kind: {kind}"), style));
+        }
+
+        if let Some(label) = self.active_label_filter_summary() {
+            parts.push(Span::styled(label, style));
+        }
+
+        if parts.is_empty() {
+            None
+        } else {
+            let mut spans = Vec::with_capacity(parts.len() * 2 - 1);
+            for (idx, part) in parts.into_iter().enumerate() {
+                if idx > 0 {
+                    spans.push(Span::raw("  |  "));
+                }
+                spans.push(part);
+            }
+            Some(Line::from(spans))
+        }
+    }
+
+    fn active_label_filter_summary(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        match &self.ctx.collection_filter {
+            CollectionFilter::Any => {}
+            CollectionFilter::None => parts.push("collection:none".to_string()),
+            CollectionFilter::Selected(name) if self.ctx.collection_view => {
+                parts.push(format!("reading list:{name}"))
+            }
+            CollectionFilter::Selected(name) => parts.push(format!("collection:{name}")),
+        }
+
+        if !self.ctx.tag_filters.is_empty() {
+            let mode = match self.ctx.tag_match_mode {
+                TagMatchMode::And => "and",
+                TagMatchMode::Or => "or",
+            };
+            parts.push(format!("tags({mode}): {}", self.ctx.tag_filters.join(", ")));
+        }
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" "))
+        }
+    }
+
+    fn refresh_meta_cache(&mut self) {
+        let Some(selected_idx) = self.selected_visible_index() else {
+            self.meta_cache = BookMetaCache::default();
+            return;
+        };
+        let Some(book) = self.ctx.books.get(selected_idx) else {
+            self.meta_cache = BookMetaCache::default();
+            return;
+        };
+
+        if self.meta_cache.path.as_deref() == Some(&book.path) {
+            return;
+        }
+
+        let decoded = bookshelf_core::decode_path(&book.path);
+        let metadata = std::fs::metadata(&decoded).ok();
+        let size_bytes = metadata.as_ref().map(|m| m.len());
+        // A remembered count shows at once; counting (slow on network filesystems) happens on
+        // the render worker when there is none or the file changed since.
+        let page_count = if let Some(metadata) = &metadata {
+            self.missing_paths.remove(&book.path);
+            let modified_secs = page_render::file_modified_secs(metadata);
+            let cached = self.ctx.page_counts_by_path.get(&book.path).copied();
+            let fresh = cached.is_some_and(|count| {
+                count.kind.is_some() && count.is_fresh(metadata.len(), modified_secs)
+            });
+            if !fresh {
+                self.page_render.request_page_count(PageCountJob {
+                    book: book.clone(),
+                    size_bytes: metadata.len(),
+                    modified_secs,
+                });
+            }
+            cached.map(|count| count.pages)
+        } else {
+            self.missing_paths.insert(book.path.clone());
+            None
+        };
+
+        // Covers need a graphics protocol; without one the pane stays text-only.
+        let cover_requested = metadata.is_some()
+            && !self.broken_paths.contains_key(&book.path)
+            && image_protocol::image_supported(&self.image_picker);
+        if cover_requested {
+            self.page_render
+                .request_cover(CoverJob { book: book.clone() });
+        }
+        self.meta_cache = BookMetaCache {
+            path: Some(book.path.clone()),
+            size_bytes,
+            page_count,
+            cover_requested,
+            cover: None,
+            cover_protocol: None,
+        };
+    }
+
+    fn current_bookmarks(&self) -> Vec<Bookmark> {
+        let Some(path) = self.reader.book_path.as_ref() else {
+            return Vec::new();
+        };
+        self.ctx
+            .bookmarks_by_path
+            .get(path)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Moves to the nearest page after (or before) the current one that has a note or a
+    /// bookmark. Stops at the ends instead of wrapping.
+    fn jump_to_annotation(&mut self, forward: bool) {
+        let bookmarks = self.current_bookmarks();
+        let notes = self.current_notes();
+        let mut annotated: Vec<u32> = bookmarks
+            .iter()
+            .map(|b| b.page)
+            .chain(notes.iter().map(|n| n.page))
+            .collect();
+        annotated.sort_unstable();
+        annotated.dedup();
+
+        let current = self.reader.page.saturating_add(1);
+        self.reader.notice = Some(match annotation_jump(&annotated, current, forward) {
+            AnnotationJump::Page {
+                page,
+                position,
+                total,
+            } => {
+                self.reader.page = page.saturating_sub(1);
+                self.reader.invalidate_render();
+                let has_note = notes.iter().any(|n| n.page == page);
+                let has_bookmark = bookmarks.iter().any(|b| b.page == page);
+                let kind = match (has_note, has_bookmark) {
+                    (true, true) => "note + bookmark",
+                    (true, false) => "note",
+                    _ => "bookmark",
+                };
+                format!("{kind} {position}/{total} (page {page})")
+            }
+            AnnotationJump::NoMore => "no more annotations".to_string(),
+            AnnotationJump::NoAnnotations => "no notes or bookmarks in this book".to_string(),
+        });
+    }
+
+    fn current_notes(&self) -> Vec<Note> {
+        let Some(path) = self.reader.book_path.as_ref() else {
+            return Vec::new();
+        };
+        self.ctx
+            .notes_by_path
+            .get(path)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn draw(&mut self, area: Rect, frame: &mut ratatui::Frame) {
+        frame.render_widget(Clear, area);
+        self.hit_regions.get_mut().clear();
+        if area.width < MIN_SCREEN_WIDTH || area.height < MIN_SCREEN_HEIGHT {
+            draw_too_small(area, frame);
+            return;
+        }
+        if self.reader.open {
+            self.draw_reader(area, frame);
+            return;
+        }
+
+        self.normalize_selection_to_visible();
+        self.refresh_meta_cache();
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(3),
+            ])
+            .split(area);
+
+        let title = Paragraph::new(Text::from(self.main_header_lines()))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::BOTTOM));
+        frame.render_widget(title, layout[0]);
+
+        // Narrow screens show the list or the details; wide ones show both side by side.
+        let narrow = area.width < NARROW_LIBRARY_WIDTH;
+        let (library_area, details_area) = if narrow && self.details_pane {
+            (None, Some(layout[1]))
+        } else if narrow {
+            (Some(layout[1]), None)
+        } else {
+            let body_layout = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+                .split(layout[1]);
+            (Some(body_layout[0]), Some(body_layout[1]))
+        };
+
+        if let Some(library_area) = library_area {
+            self.draw_library(frame, library_area);
+        }
+        if let Some(details_area) = details_area {
+            let cover_area = self.details_cover_area(details_area);
+            frame.render_widget(self.draw_details(details_area, cover_area), details_area);
+            if let Some(cover_area) = cover_area {
+                self.draw_details_cover(frame, cover_area);
+            }
+        }
+
+        let mut footer_lines = self.main_footer_lines(narrow);
+        if let (Some(saved), Some(first)) = (self.saved_flash_span(), footer_lines.first_mut()) {
+            first.spans.insert(0, saved);
+        }
+        let footer = Paragraph::new(Text::from(footer_lines))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::TOP));
+        frame.render_widget(footer, layout[2]);
+
+        if self.settings_panel.open {
+            self.draw_settings_panel(area, frame);
+        }
+
+        if self.scan_panel.open {
+            self.draw_scan_panel(area, frame);
+        }
+
+        if self.relink_panel.open {
+            self.draw_relink_panel(area, frame);
+        }
+
+        if self.delete_panel.open {
+            self.draw_delete_panel(area, frame);
+        }
+
+        if self.book_tag_picker.open {
+            self.draw_book_tag_picker(area, frame);
+        }
+
+        if self.history_panel.open {
+            self.draw_history_panel(area, frame);
+        }
+
+        if self.notes_search_panel.open {
+            self.draw_notes_search_panel(area, frame);
+        }
+
+        if self.duplicates_panel.open {
+            self.draw_duplicates_panel(area, frame);
+        }
+
+        if self.backups_panel.open {
+            self.draw_backups_panel(area, frame);
+        }
+
+        if self.search_panel.open {
+            self.draw_search_panel(area, frame);
+        }
+
+        if self.label_catalog_input_panel.open {
+            self.draw_label_catalog_input_panel(area, frame);
+        }
+
+        // Library rows under a popup are not clickable.
+        if self.settings_panel.open
+            || self.scan_panel.open
+            || self.relink_panel.open
+            || self.delete_panel.open
+            || self.history_panel.open
+            || self.notes_search_panel.open
+            || self.duplicates_panel.open
+            || self.backups_panel.open
+            || self.search_panel.open
+            || self.label_catalog_input_panel.open
+            || self.book_tag_picker.open
+        {
+            self.hit_regions.get_mut().clear();
+        }
+    }
+
+    fn draw_search_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(90, 78, area);
+        frame.render_widget(Clear, popup_area);
+
+        let title = match self.search_panel.mode {
+            SearchPanelMode::Filters => "Filters".to_string(),
+            SearchPanelMode::Labels => "Labels".to_string(),
+            SearchPanelMode::Catalog => "Catalog".to_string(),
+        };
+
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        frame.render_widget(block.clone(), popup_area);
+
+        let inner = block.inner(popup_area);
+        match self.search_panel.mode {
+            SearchPanelMode::Filters => {
+                self.draw_search_filters_tab(inner, frame);
+            }
+            SearchPanelMode::Labels => self.draw_search_assign_tab(inner, frame),
+            SearchPanelMode::Catalog => self.draw_search_manage_tab(inner, frame),
+        }
+    }
+
+    fn draw_search_filters_tab(&self, area: Rect, frame: &mut ratatui::Frame) {
+        // On short screens the summary and the help give up rows so the lists keep some.
+        let (summary_rows, help_rows) = if area.height < 20 { (3, 2) } else { (8, 4) };
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(summary_rows),
+                Constraint::Min(0),
+                Constraint::Length(help_rows),
+            ])
+            .split(area);
+
+        let filters_lines = self.search_panel_summary_lines(self.search_panel.focus);
+        let header = Paragraph::new(Text::from(filters_lines))
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Left);
+        frame.render_widget(header, sections[0]);
+
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(30),
+                Constraint::Percentage(40),
+                Constraint::Percentage(30),
+            ])
+            .split(sections[1]);
+        let people = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(body[2]);
+
+        self.draw_search_collections_list(body[0], frame);
+        self.draw_search_tags_list(body[1], frame);
+        self.draw_search_names_list(TagKind::Author, people[0], frame);
+        self.draw_search_names_list(TagKind::Series, people[1], frame);
+
+        let help_lines = vec![
+            Line::from(vec![
+                Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" focus  "),
+                Span::styled("↑/↓", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" move  "),
+                Span::styled("Space", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" toggle  "),
+                Span::styled("z", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" fold tag group"),
+            ]),
+            Line::from(vec![
+                Span::styled("←/→", Style::default().add_modifier(Modifier::BOLD)),
+                // The query keeps ←/→ for its cursor.
+                Span::raw(if self.search_panel.focus == SearchFocus::Query {
+                    " cursor  "
+                } else {
+                    " tags AND/OR  "
+                }),
+                Span::styled("f", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" favorites-only  "),
+                Span::styled("Ctrl+s", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" status  "),
+                Span::styled("Ctrl+n", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" recently added  "),
+                Span::styled("Ctrl+k", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" kind  "),
+                Span::styled("Ctrl+r", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" reading list  "),
+                Span::styled("Ctrl+a", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" archived  "),
+                Span::styled("Ctrl+u", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" clear all"),
+            ]),
+            Line::from(vec![
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" cancel  "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" close"),
+            ]),
+        ];
+        let help = Paragraph::new(Text::from(help_lines))
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Left);
+        frame.render_widget(help, sections[2]);
+    }
+
+    fn search_panel_summary_lines(&self, focus: SearchFocus) -> Vec<Line<'static>> {
+        let base_label_style = Style::default().add_modifier(Modifier::BOLD);
+        let focus_style = Style::default()
+            .fg(self.accent_color())
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+
+        let query_label_style = if focus == SearchFocus::Query {
+            focus_style
+        } else {
+            base_label_style
+        };
+        let query_value_style = if focus == SearchFocus::Query {
+            focus_style
+        } else {
+            Style::default()
+        };
+
+        let collection_label_style = if focus == SearchFocus::Collections {
+            focus_style
+        } else {
+            base_label_style
+        };
+        let collection_value_style = if focus == SearchFocus::Collections {
+            focus_style
+        } else {
+            Style::default()
+        };
+
+        let tags_label_style = if focus == SearchFocus::Tags {
+            focus_style
+        } else {
+            base_label_style
+        };
+        let tags_value_style = if focus == SearchFocus::Tags {
+            focus_style
+        } else {
+            Style::default()
+        };
+
+        let name_label_style = |list: SearchFocus| {
+            if focus == list {
+                focus_style
+            } else {
+                base_label_style
+            }
+        };
+
+        let query = self.ctx.library_query.clone();
+        let parsed = LibraryQuery::parse(&query);
+        let parsed = if parsed.is_empty() {
+            "tag: col: fav:yes \"phrase\"".to_string()
+        } else {
+            parsed.to_string()
+        };
+        let fav = if self.ctx.favorites_only { "on" } else { "off" };
+        let archived = if self.ctx.include_archived {
+            "shown"
+        } else {
+            "hidden"
+        };
+        let status = self
+            .ctx
+            .status_filter
+            .map_or("any", |status| status.as_str());
+        let added = self
+            .ctx
+            .added_within_days
+            .map_or_else(|| "any".to_string(), format_added_within);
+        let kind = self.ctx.kind_filter.map_or("any", |kind| kind.as_str());
+        let collection = match &self.ctx.collection_filter {
+            CollectionFilter::Any => "any".to_string(),
+            CollectionFilter::None => "none".to_string(),
+            CollectionFilter::Selected(name) => name.clone(),
+        };
+        let mode = match self.ctx.tag_match_mode {
+            TagMatchMode::And => "AND",
+            TagMatchMode::Or => "OR",
+        };
+        let tags = if self.ctx.tag_filters.is_empty() {
+            "(none)".to_string()
+        } else {
+            self.ctx.tag_filters.join(", ")
+        };
+
+        let query_line = if focus == SearchFocus::Query {
+            let mut spans = vec![Span::styled("Query: ", query_label_style)];
+            spans.extend(self.search_panel.query.spans(query_value_style));
+            Line::from(spans)
+        } else {
+            Line::from(vec![
+                Span::styled("Query: ", query_label_style),
+                Span::styled(query, query_value_style),
+            ])
+        };
+
+        vec![
+            query_line,
+            Line::from(vec![
+                Span::styled("Parsed: ", base_label_style),
+                Span::styled(parsed, Style::default().fg(Color::DarkGray)),
+            ]),
+            Line::from(vec![
+                Span::styled("Favorites only: ", base_label_style),
+                Span::raw(fav),
+                Span::raw("  "),
+                Span::styled("Archived: ", base_label_style),
+                Span::raw(archived),
+                Span::raw("  "),
+                Span::styled("Status: ", base_label_style),
+                Span::raw(status),
+                Span::raw("  "),
+                Span::styled("Added: ", base_label_style),
+                Span::raw(added),
+                Span::raw("  "),
+                Span::styled("Kind: ", base_label_style),
+                Span::raw(kind),
+                Span::raw("  "),
+                Span::styled("Collection: ", collection_label_style),
+                Span::styled(collection, collection_value_style),
+                Span::raw("  "),
+                Span::styled("Reading list: ", collection_label_style),
+                Span::raw(if self.ctx.collection_view {
+                    "on"
+                } else {
+                    "off"
+                }),
+            ]),
+            Line::from(vec![
+                Span::styled("Tags: ", tags_label_style),
+                Span::styled(tags, tags_value_style),
+            ]),
+            Line::from(vec![
+                Span::styled("Tag match: ", tags_label_style),
+                Span::styled(mode, tags_value_style),
+            ]),
+            Line::from(vec![
+                Span::styled("Author: ", name_label_style(SearchFocus::Authors)),
+                Span::raw(self.ctx.author_filter.clone().unwrap_or("any".to_string())),
+                Span::raw("  "),
+                Span::styled("Series: ", name_label_style(SearchFocus::Series)),
+                Span::raw(self.ctx.series_filter.clone().unwrap_or("any".to_string())),
+            ]),
+        ]
+    }
+
+    fn draw_search_collections_list(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let entries = self.collection_entries_for_search();
+        let mut cursor = self.search_panel.collection_cursor;
+        if !entries.is_empty() {
+            cursor = cursor.min(entries.len().saturating_sub(1));
+        } else {
+            cursor = 0;
+        }
+
+        let focus = self.search_panel.focus == SearchFocus::Collections;
+        let title_style = if focus {
+            Style::default()
+                .fg(self.accent_color())
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        } else {
+            Style::default()
+        };
+
+        let items: Vec<ListItem> = if entries.is_empty() {
+            vec![ListItem::new(Line::raw("(none)"))]
+        } else {
+            entries
+                .iter()
+                .map(|e| {
+                    let selected = e.filter == self.ctx.collection_filter;
+                    let prefix = if selected { "●" } else { " " };
+                    ListItem::new(Line::raw(format!("{prefix} {} ({})", e.label, e.count)))
+                })
+                .collect()
+        };
+
+        let focused_list_style = Style::default()
+            .fg(Color::Black)
+            .bg(self.accent_color())
+            .add_modifier(Modifier::BOLD);
+        let unfocused_list_style = Style::default().fg(Color::White).bg(Color::Gray);
+
+        let highlight_style = if focus {
+            focused_list_style
+        } else {
+            unfocused_list_style
+        };
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(if focus {
+                        Style::default().fg(self.accent_color())
+                    } else {
+                        Style::default()
+                    })
+                    .title(Span::styled("Collections", title_style)),
+            )
+            .highlight_style(highlight_style)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+        let mut state = ListState::default();
+        if !entries.is_empty() {
+            state.select(Some(cursor));
+        }
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_search_tags_list(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let entries = self.tag_entries_for_search();
+        let mut cursor = self.search_panel.tag_cursor;
+        if !entries.is_empty() {
+            cursor = cursor.min(entries.len().saturating_sub(1));
+        } else {
+            cursor = 0;
+        }
+
+        let focus = self.search_panel.focus == SearchFocus::Tags;
+        let mode = match self.ctx.tag_match_mode {
+            TagMatchMode::And => "AND",
+            TagMatchMode::Or => "OR",
+        };
+        let title_style = if focus {
+            Style::default()
+                .fg(self.accent_color())
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        } else {
+            Style::default()
+        };
+        let title = format!("Tags ({mode})");
+
+        let items: Vec<ListItem> = if entries.is_empty() {
+            vec![ListItem::new(Line::raw("(none)"))]
+        } else {
+            entries
+                .iter()
+                .map(|e| {
+                    let selected = self
+                        .ctx
+                        .tag_filters
+                        .iter()
+                        .any(|t| t.eq_ignore_ascii_case(&e.row.filter));
+                    // Tags under a selected namespace pass the filter too.
+                    let prefix = if selected {
+                        "[x]"
+                    } else if self
+                        .ctx
+                        .tag_filters
+                        .iter()
+                        .any(|t| t.ends_with('/') && tag_matches(t, &e.row.filter))
+                    {
+                        "[-]"
+                    } else {
+                        "[ ]"
+                    };
+                    let fold = if !e.row.is_namespace() {
+                        ""
+                    } else if self
+                        .search_panel
+                        .folded_tags
+                        .contains(&e.row.filter.to_ascii_lowercase())
+                    {
+                        "▸ "
+                    } else {
+                        "▾ "
+                    };
+                    ListItem::new(Line::raw(format!(
+                        "{prefix} {}{fold}{} ({})",
+                        "  ".repeat(e.row.depth),
+                        e.row.label,
+                        e.count
+                    )))
+                })
+                .collect()
+        };
+
+        let focused_list_style = Style::default()
+            .fg(Color::Black)
+            .bg(self.accent_color())
+            .add_modifier(Modifier::BOLD);
+        let unfocused_list_style = Style::default().fg(Color::White).bg(Color::Gray);
+
+        let highlight_style = if focus {
+            focused_list_style
+        } else {
+            unfocused_list_style
+        };
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(if focus {
+                        Style::default().fg(self.accent_color())
+                    } else {
+                        Style::default()
+                    })
+                    .title(Span::styled(title, title_style)),
+            )
+            .highlight_style(highlight_style)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+        let mut state = ListState::default();
+        if !entries.is_empty() {
+            state.select(Some(cursor));
+        }
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_search_names_list(&self, kind: TagKind, area: Rect, frame: &mut ratatui::Frame) {
+        let entries = self.name_entries_for_search(kind);
+        let (title, cursor, focus, filter) = match kind {
+            TagKind::Author => (
+                "Authors",
+                self.search_panel.author_cursor,
+                SearchFocus::Authors,
+                self.ctx.author_filter.as_deref(),
+            ),
+            _ => (
+                "Series",
+                self.search_panel.series_cursor,
+                SearchFocus::Series,
+                self.ctx.series_filter.as_deref(),
+            ),
+        };
+        let focus = self.search_panel.focus == focus;
+        let title_style = if focus {
+            Style::default()
+                .fg(self.accent_color())
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        } else {
+            Style::default()
+        };
+
+        let items: Vec<ListItem> = if entries.is_empty() {
+            vec![ListItem::new(Line::raw("(none)"))]
+        } else {
+            entries
+                .iter()
+                .map(|e| {
+                    let selected = filter.is_some_and(|f| f.eq_ignore_ascii_case(&e.name));
+                    let prefix = if selected { "●" } else { " " };
+                    ListItem::new(Line::raw(format!("{prefix} {} ({})", e.name, e.count)))
+                })
+                .collect()
+        };
+
+        let highlight_style = if focus {
+            Style::default()
+                .fg(Color::Black)
+                .bg(self.accent_color())
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White).bg(Color::Gray)
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(if focus {
+                        Style::default().fg(self.accent_color())
+                    } else {
+                        Style::default()
+                    })
+                    .title(Span::styled(title, title_style)),
+            )
+            .highlight_style(highlight_style)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+        let mut state = ListState::default();
+        if !entries.is_empty() {
+            state.select(Some(cursor.min(entries.len() - 1)));
         }
-    }
-
-    fn apply_rename_label(&mut self, kind: TagKind, from: &str, to: &str) -> bool {
-        let from = from.trim();
-        let to = to.trim();
-        if from.is_empty() || to.is_empty() {
-            return false;
-        }
+        frame.render_stateful_widget(list, area, &mut state);
+    }
 
-        let list = match kind {
-            TagKind::Tag => &mut self.ctx.known_tags,
-            TagKind::Collection => &mut self.ctx.known_collections,
-        };
-        let Some(pos) = list.iter().position(|v| v.eq_ignore_ascii_case(from)) else {
-            return false;
+    fn draw_search_manage_tab(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(2),
+                Constraint::Min(0),
+                Constraint::Length(4),
+            ])
+            .split(area);
+
+        let filter_style = if self.label_manager_panel.filter_editing {
+            Style::default()
+                .fg(self.accent_color())
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
         };
-        list[pos] = to.to_string();
-        self.ctx.normalize_label_catalog();
 
-        match kind {
-            TagKind::Tag => {
-                for tag in &mut self.ctx.tag_filters {
-                    if tag.eq_ignore_ascii_case(from) {
-                        *tag = to.to_string();
-                    }
-                }
-                self.normalize_tag_filters();
-            }
-            TagKind::Collection => {
-                if let CollectionFilter::Selected(selected) = &mut self.ctx.collection_filter
-                    && selected.eq_ignore_ascii_case(from)
-                {
-                    *selected = to.to_string();
-                }
-            }
-        }
+        let filter_label = format!("Filter ({}): ", self.label_manager_panel.tab.title());
 
-        let mut to_remove = Vec::new();
-        for (path, labels) in self.ctx.labels_by_path.iter_mut() {
-            match kind {
-                TagKind::Tag => {
-                    for tag in &mut labels.tags {
-                        if tag.eq_ignore_ascii_case(from) {
-                            *tag = to.to_string();
-                        }
-                    }
-                }
-                TagKind::Collection => {
-                    if labels
-                        .collection
-                        .as_deref()
-                        .is_some_and(|c| c.eq_ignore_ascii_case(from))
-                    {
-                        labels.collection = Some(to.to_string());
-                    }
-                }
-            }
-            labels.normalize();
-            if labels.tags.is_empty() && labels.collection.is_none() {
-                to_remove.push(path.clone());
-            }
-        }
-        for path in to_remove {
-            self.ctx.labels_by_path.remove(&path);
+        let mut filter_spans = vec![Span::styled(
+            filter_label,
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+        if self.label_manager_panel.filter_editing {
+            filter_spans.extend(self.label_manager_panel.filter.spans(filter_style));
+        } else {
+            filter_spans.push(Span::styled(
+                self.label_manager_panel.filter.as_str().to_string(),
+                filter_style,
+            ));
         }
+        filter_spans.push(Span::raw("  "));
+        filter_spans.push(Span::raw("(/ to edit, Ctrl+u clear)"));
+        let header_lines = vec![Line::from(filter_spans)];
+        let header = Paragraph::new(Text::from(header_lines))
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Left);
+        frame.render_widget(header, sections[0]);
 
-        match kind {
-            TagKind::Tag => {
-                for tag in &mut self.assign_labels_panel.staged.tags {
-                    if tag.eq_ignore_ascii_case(from) {
-                        *tag = to.to_string();
-                    }
-                }
-                self.assign_labels_panel.staged.normalize();
-            }
-            TagKind::Collection => {
-                if self
-                    .assign_labels_panel
-                    .staged
-                    .collection
-                    .as_deref()
-                    .is_some_and(|c| c.eq_ignore_ascii_case(from))
-                {
-                    self.assign_labels_panel.staged.collection = Some(to.to_string());
-                }
-            }
-        }
-        true
-    }
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(25); 4])
+            .split(sections[1]);
 
-    fn handle_label_catalog_input_panel_key(
-        &mut self,
-        key: KeyEvent,
-    ) -> anyhow::Result<Option<UiExit>> {
-        if key.modifiers.contains(KeyModifiers::CONTROL)
-            && let KeyCode::Char('u') = key.code
-        {
-            self.label_catalog_input_panel.input.clear();
-            return Ok(None);
-        }
+        let focused_list_style = Style::default()
+            .fg(Color::Black)
+            .bg(self.accent_color())
+            .add_modifier(Modifier::BOLD);
+        let unfocused_list_style = Style::default().fg(Color::White).bg(Color::Gray);
 
-        match key.code {
-            KeyCode::Esc => {
-                self.label_catalog_input_panel.open = false;
-                self.label_catalog_input_panel.error = None;
-                self.label_catalog_input_panel.input.clear();
-                self.label_catalog_input_panel.from = None;
-                Ok(None)
+        for (tab, area) in LabelManagerTab::ALL.into_iter().zip(body.iter()) {
+            let entries = self.label_manager_entries(tab);
+            let cursor = self
+                .label_manager_panel
+                .cursor(tab)
+                .min(entries.len().saturating_sub(1));
+            let focus = self.label_manager_panel.tab == tab;
+            let title_style = if focus {
+                Style::default()
+                    .fg(self.accent_color())
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            let items: Vec<ListItem> = if entries.is_empty() {
+                vec![ListItem::new(Line::raw("(none)"))]
+            } else {
+                entries
+                    .iter()
+                    .map(|(name, count)| ListItem::new(Line::raw(format!("{name} ({count})"))))
+                    .collect()
+            };
+            let list = List::new(items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(if focus {
+                            Style::default().fg(self.accent_color())
+                        } else {
+                            Style::default()
+                        })
+                        .title(Span::styled(tab.title(), title_style)),
+                )
+                .highlight_style(if focus {
+                    focused_list_style
+                } else {
+                    unfocused_list_style
+                })
+                .highlight_symbol("> ")
+                .highlight_spacing(HighlightSpacing::Always);
+            let mut state = ListState::default();
+            if !entries.is_empty() {
+                state.select(Some(cursor));
             }
-            KeyCode::Enter => {
-                let name = self.label_catalog_input_panel.input.trim().to_string();
-                if name.is_empty() {
-                    self.label_catalog_input_panel.error =
-                        Some(match self.label_catalog_input_panel.kind {
-                            TagKind::Tag => "Enter a tag name".to_string(),
-                            TagKind::Collection => "Enter a collection name".to_string(),
-                        });
-                    return Ok(None);
-                }
-
-                match self.label_catalog_input_panel.mode {
-                    LabelCatalogInputMode::Create => {
-                        let exists = match self.label_catalog_input_panel.kind {
-                            TagKind::Tag => self
-                                .ctx
-                                .known_tags
-                                .iter()
-                                .any(|t| t.eq_ignore_ascii_case(&name)),
-                            TagKind::Collection => self
-                                .ctx
-                                .known_collections
-                                .iter()
-                                .any(|c| c.eq_ignore_ascii_case(&name)),
-                        };
-                        if exists {
-                            self.label_catalog_input_panel.error =
-                                Some("Already exists".to_string());
-                            return Ok(None);
-                        }
-
-                        match self.label_catalog_input_panel.kind {
-                            TagKind::Tag => self.ctx.known_tags.push(name.clone()),
-                            TagKind::Collection => self.ctx.known_collections.push(name.clone()),
-                        }
-                        self.ctx.normalize_label_catalog();
-                        self.ctx
-                            .dirty_label_catalog_ops
-                            .push(LabelCatalogOp::Create {
-                                kind: self.label_catalog_input_panel.kind,
-                                name: name.clone(),
-                            });
-                    }
-                    LabelCatalogInputMode::Rename => {
-                        let Some(from) = self.label_catalog_input_panel.from.clone() else {
-                            self.label_catalog_input_panel.error =
-                                Some("Nothing selected".to_string());
-                            return Ok(None);
-                        };
-
-                        let conflicts = match self.label_catalog_input_panel.kind {
-                            TagKind::Tag => self.ctx.known_tags.iter().any(|t| {
-                                t.eq_ignore_ascii_case(&name) && !t.eq_ignore_ascii_case(&from)
-                            }),
-                            TagKind::Collection => self.ctx.known_collections.iter().any(|c| {
-                                c.eq_ignore_ascii_case(&name) && !c.eq_ignore_ascii_case(&from)
-                            }),
-                        };
-                        if conflicts {
-                            self.label_catalog_input_panel.error =
-                                Some("Already exists".to_string());
-                            return Ok(None);
-                        }
+            frame.render_stateful_widget(list, *area, &mut state);
+        }
 
-                        if !self.apply_rename_label(
-                            self.label_catalog_input_panel.kind,
-                            &from,
-                            &name,
-                        ) {
-                            self.label_catalog_input_panel.error =
-                                Some("Nothing selected".to_string());
-                            return Ok(None);
-                        }
+        let enter_action = if self.label_manager_panel.confirm_delete.is_some() {
+            "delete"
+        } else if self.label_manager_panel.filter_editing {
+            "done"
+        } else {
+            "close"
+        };
 
-                        self.ctx
-                            .dirty_label_catalog_ops
-                            .push(LabelCatalogOp::Rename {
-                                kind: self.label_catalog_input_panel.kind,
-                                from,
-                                to: name.clone(),
-                            });
-                    }
-                }
+        let mut footer_lines = Vec::new();
+        footer_lines.push(Line::from(vec![
+            Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" switch panel  "),
+            Span::styled("/", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" filter"),
+        ]));
+        footer_lines.push(Line::from(vec![
+            Span::styled("n", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" new  "),
+            Span::styled("r", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" rename  "),
+            Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" delete  "),
+            Span::styled("u", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" undo  "),
+            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" cancel  "),
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!(" {enter_action}")),
+        ]));
 
-                self.label_catalog_input_panel.open = false;
-                self.label_catalog_input_panel.error = None;
-                self.label_catalog_input_panel.input.clear();
-                self.label_catalog_input_panel.from = None;
-                Ok(None)
-            }
-            KeyCode::Backspace => {
-                self.label_catalog_input_panel.input.pop();
-                Ok(None)
-            }
-            KeyCode::Char(ch) => {
-                if !ch.is_control() {
-                    self.label_catalog_input_panel.input.push(ch);
-                }
-                Ok(None)
-            }
-            _ => Ok(None),
+        if let Some(target) = &self.label_manager_panel.confirm_delete {
+            footer_lines.push(Line::raw(""));
+            footer_lines.push(Line::styled(
+                format!("Delete {} \"{}\"? (y/n)", target.kind, target.name),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+        } else if let Some(err) = &self.label_manager_panel.error {
+            footer_lines.push(Line::raw(""));
+            footer_lines.push(Line::styled(
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
         }
-    }
 
-    fn visible_indices(&self) -> Vec<usize> {
-        let query = self.ctx.library_query.trim().to_ascii_lowercase();
-        let mut out = Vec::new();
-        for (idx, book) in self.ctx.books.iter().enumerate() {
-            if self.ctx.favorites_only && !book.favorite {
-                continue;
-            }
+        let footer = Paragraph::new(Text::from(footer_lines))
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Left);
+        frame.render_widget(footer, sections[2]);
+    }
 
-            let labels = self
-                .ctx
-                .labels_by_path
-                .get(&book.path)
-                .cloned()
-                .unwrap_or_default();
-            if !matches_collection_filter(&self.ctx.collection_filter, labels.collection.as_deref())
-            {
-                continue;
-            }
-            if !matches_tag_filter(&self.ctx.tag_filters, self.ctx.tag_match_mode, &labels.tags) {
-                continue;
-            }
+    fn draw_search_assign_tab(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(6),
+                Constraint::Min(0),
+                Constraint::Length(4),
+            ])
+            .split(area);
 
-            if query.is_empty() {
-                out.push(idx);
-                continue;
-            }
+        let (filter_label, filter_value) = match self.assign_labels_panel.focus {
+            AssignFocus::Collections => (
+                "Filter (collections): ",
+                &self.assign_labels_panel.collection_query,
+            ),
+            AssignFocus::Tags => ("Filter (tags): ", &self.assign_labels_panel.tag_query),
+            AssignFocus::Author => ("Filter (authors): ", &self.assign_labels_panel.author_query),
+            AssignFocus::Series => (
+                "Filter (series, Name #n): ",
+                &self.assign_labels_panel.series_query,
+            ),
+        };
+        let filter_style = if self.assign_labels_panel.query_editing {
+            Style::default()
+                .fg(self.accent_color())
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
 
-            let title = book.title.to_ascii_lowercase();
-            if title.contains(&query) {
-                out.push(idx);
-                continue;
-            }
+        let bulk_count = self.assign_labels_panel.bulk_paths.len();
+        let book_title = if bulk_count > 0 {
+            format!("{bulk_count} marked books (tags are merged)")
+        } else {
+            self.assign_labels_panel
+                .book_path
+                .as_deref()
+                .and_then(|path| self.ctx.books.iter().find(|b| b.path == path))
+                .map(|b| b.title.clone())
+                .unwrap_or_else(|| "(no selection)".to_string())
+        };
 
-            let path = bookshelf_core::display_path(&book.path).to_ascii_lowercase();
-            if path.contains(&query) {
-                out.push(idx);
-            }
-        }
-        out
-    }
+        let fav = if self.assign_labels_panel.staged_favorite {
+            "yes"
+        } else {
+            "no"
+        };
 
-    fn normalize_selection_to_visible(&mut self) {
-        if self.ctx.books.is_empty() {
-            self.ctx.selected = 0;
-            return;
+        let mut header_lines = Vec::new();
+        header_lines.push(Line::from(vec![
+            Span::styled("Selected: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(book_title),
+        ]));
+        let status = self
+            .assign_labels_panel
+            .staged_status
+            .map_or("mixed", |status| status.as_str());
+        header_lines.push(Line::from(vec![
+            Span::styled("Favorite: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(fav),
+            Span::raw("  "),
+            Span::raw("(f toggles)"),
+            Span::raw("  "),
+            Span::styled("Status: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(status),
+            Span::raw("  "),
+            Span::raw("(u cycles)"),
+        ]));
+        let mut filter_spans = vec![Span::styled(
+            filter_label,
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+        if self.assign_labels_panel.query_editing {
+            filter_spans.extend(filter_value.spans(filter_style));
+        } else {
+            filter_spans.push(Span::styled(
+                filter_value.as_str().to_string(),
+                filter_style,
+            ));
         }
+        filter_spans.push(Span::raw("  "));
+        filter_spans.push(Span::raw("(/ to edit, Ctrl+u clear)"));
+        header_lines.push(Line::from(filter_spans));
 
-        if self.ctx.selected >= self.ctx.books.len() {
-            self.ctx.selected = 0;
+        if let Some(err) = &self.assign_labels_panel.error {
+            header_lines.push(Line::styled(
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+        } else {
+            header_lines.push(Line::raw(""));
         }
+        header_lines.push(Line::raw(""));
 
-        let visible = self.visible_indices();
-        if visible.is_empty() {
-            self.ctx.selected = 0;
-            return;
-        }
+        let header = Paragraph::new(Text::from(header_lines))
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Left);
+        frame.render_widget(header, sections[0]);
 
-        if !visible.contains(&self.ctx.selected) {
-            self.ctx.selected = visible[0];
-        }
-    }
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(30),
+                Constraint::Percentage(40),
+                Constraint::Percentage(30),
+            ])
+            .split(sections[1]);
+        let people = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(body[2]);
 
-    fn selected_visible_index(&self) -> Option<usize> {
-        let visible = self.visible_indices();
-        if visible.is_empty() {
-            return None;
-        }
+        let focused_list_style = Style::default()
+            .fg(Color::Black)
+            .bg(self.accent_color())
+            .add_modifier(Modifier::BOLD);
+        let unfocused_list_style = Style::default().fg(Color::White).bg(Color::Gray);
 
-        let selected = self
-            .ctx
-            .selected
-            .min(self.ctx.books.len().saturating_sub(1));
-        if visible.contains(&selected) {
-            Some(selected)
+        // Collections
+        let collections_focus = self.assign_labels_panel.focus == AssignFocus::Collections;
+        let collections = self.assign_visible_collections();
+        let mut collection_cursor = self.assign_labels_panel.collection_cursor;
+        let collection_max = collections.len() + 1;
+        if collection_max > 0 {
+            collection_cursor = collection_cursor.min(collection_max - 1);
         } else {
-            Some(visible[0])
+            collection_cursor = 0;
         }
-    }
 
-    fn select_next_visible(&mut self) {
-        let visible = self.visible_indices();
-        if visible.is_empty() {
-            return;
+        let mut collection_items = Vec::new();
+        let none_selected = self.assign_labels_panel.staged.collection.is_none();
+        let prefix = if none_selected { "●" } else { " " };
+        collection_items.push(ListItem::new(Line::raw(format!("{prefix} (none)"))));
+        for name in &collections {
+            let selected = self
+                .assign_labels_panel
+                .staged
+                .collection
+                .as_deref()
+                .is_some_and(|c| c.eq_ignore_ascii_case(name));
+            let prefix = if selected { "●" } else { " " };
+            collection_items.push(ListItem::new(Line::raw(format!("{prefix} {name}"))));
         }
 
-        let Some(pos) = visible.iter().position(|idx| *idx == self.ctx.selected) else {
-            self.ctx.selected = visible[0];
-            return;
+        let collections_title_style = if self.assign_labels_panel.focus == AssignFocus::Collections
+        {
+            Style::default()
+                .fg(self.accent_color())
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
         };
-        if pos + 1 < visible.len() {
-            self.ctx.selected = visible[pos + 1];
-        }
-    }
 
-    fn select_prev_visible(&mut self) {
-        let visible = self.visible_indices();
-        if visible.is_empty() {
-            return;
+        let collections_list = List::new(collection_items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(if collections_focus {
+                        Style::default().fg(self.accent_color())
+                    } else {
+                        Style::default()
+                    })
+                    .title(Span::styled("Collection", collections_title_style)),
+            )
+            .highlight_style(if collections_focus {
+                focused_list_style
+            } else {
+                unfocused_list_style
+            })
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+        let mut collection_state = ListState::default();
+        if collection_max > 0 {
+            collection_state.select(Some(collection_cursor));
         }
+        frame.render_stateful_widget(collections_list, body[0], &mut collection_state);
 
-        let Some(pos) = visible.iter().position(|idx| *idx == self.ctx.selected) else {
-            self.ctx.selected = visible[0];
-            return;
-        };
-        if pos > 0 {
-            self.ctx.selected = visible[pos - 1];
+        // Tags
+        let tags_focus = self.assign_labels_panel.focus == AssignFocus::Tags;
+        let tags = self.assign_visible_tags();
+        let mut tag_cursor = self.assign_labels_panel.tag_cursor;
+        if !tags.is_empty() {
+            tag_cursor = tag_cursor.min(tags.len() - 1);
+        } else {
+            tag_cursor = 0;
         }
-    }
 
-    fn main_footer_lines(&self) -> Vec<Line<'static>> {
-        if self.label_catalog_input_panel.open {
-            return vec![Line::from(vec![
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" cancel  "),
-                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" apply  "),
-                Span::styled("Backspace", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" delete  "),
-                Span::styled("Ctrl+u", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" clear"),
-            ])];
+        let mut tag_items = Vec::new();
+        if tags.is_empty() {
+            tag_items.push(ListItem::new(Line::raw("(none)")));
+        } else {
+            for tag in &tags {
+                let selected = self
+                    .assign_labels_panel
+                    .staged
+                    .tags
+                    .iter()
+                    .any(|t| t.eq_ignore_ascii_case(tag));
+                let prefix = if selected { "[x]" } else { "[ ]" };
+                tag_items.push(ListItem::new(Line::raw(format!("{prefix} {tag}"))));
+            }
         }
 
-        if self.search_panel.open {
-            let enter_action = match self.search_panel.tab {
-                SearchTab::Search => "close",
-                SearchTab::Assign => {
-                    if self.assign_labels_panel.query_editing {
-                        "done"
-                    } else {
-                        "apply + close"
-                    }
-                }
-                SearchTab::Manage => {
-                    if self.label_manager_panel.confirm_delete.is_some() {
-                        "delete"
-                    } else if self.label_manager_panel.filter_editing {
-                        "done"
-                    } else {
-                        "close"
-                    }
-                }
-            };
+        let tags_title_style = if self.assign_labels_panel.focus == AssignFocus::Tags {
+            Style::default()
+                .fg(self.accent_color())
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
 
-            return vec![Line::from(vec![
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" cancel  "),
-                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(format!(" {enter_action}")),
-            ])];
+        let tags_list = List::new(tag_items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(if tags_focus {
+                        Style::default().fg(self.accent_color())
+                    } else {
+                        Style::default()
+                    })
+                    .title(Span::styled("Tags", tags_title_style)),
+            )
+            .highlight_style(if tags_focus {
+                focused_list_style
+            } else {
+                unfocused_list_style
+            })
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+        let mut tag_state = ListState::default();
+        if !tags.is_empty() {
+            tag_state.select(Some(tag_cursor));
         }
+        frame.render_stateful_widget(tags_list, body[1], &mut tag_state);
 
-        vec![
+        let staged = &self.assign_labels_panel.staged;
+        self.draw_assign_choice_list(
+            frame,
+            people[0],
+            "Author",
+            &self.assign_visible_authors(),
+            staged.author.as_deref().map(|a| (a, a.to_string())),
+            self.assign_labels_panel.author_cursor,
+            self.assign_labels_panel.focus == AssignFocus::Author,
+        );
+        self.draw_assign_choice_list(
+            frame,
+            people[1],
+            "Series",
+            &self.assign_visible_series(),
+            staged
+                .series_name()
+                .map(|name| (name, format_series(name, staged.series_number()))),
+            self.assign_labels_panel.series_cursor,
+            self.assign_labels_panel.focus == AssignFocus::Series,
+        );
+
+        let enter_action = if !self.assign_labels_panel.query_editing {
+            "apply + close"
+        } else if self.assign_tag_query_creates() {
+            "create tag"
+        } else if self.assign_query_sets_value() {
+            match self.assign_labels_panel.focus {
+                AssignFocus::Author => "set author",
+                _ => "set series",
+            }
+        } else {
+            "done"
+        };
+
+        let tab_action = match self.assign_tag_namespace_completion() {
+            Some(completed) => format!(" complete {completed}  "),
+            None => " focus  ".to_string(),
+        };
+        let footer_lines = vec![
             Line::from(vec![
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" quit  "),
+                Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(tab_action),
                 Span::styled("↑/↓", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" move  "),
-                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" read"),
+                Span::raw(" move"),
             ]),
             Line::from(vec![
                 Span::styled("/", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" filters  "),
-                Span::styled("l", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" labels  "),
-                Span::styled("c", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" catalog  "),
-                Span::styled("s", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" settings"),
+                Span::raw(" filter  "),
+                Span::styled("f", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" favorite  "),
+                Span::styled("u", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" status  "),
+                Span::styled("Space", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" toggle"),
             ]),
-        ]
+            Line::from(vec![
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" cancel  "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(" {enter_action}  ")),
+                Span::styled("Ctrl+n", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" new tag from filter"),
+            ]),
+        ];
+        let footer = Paragraph::new(Text::from(footer_lines))
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Left);
+        frame.render_widget(footer, sections[2]);
     }
 
-    fn main_header_lines(&self) -> Vec<Line<'static>> {
-        let mut lines = vec![Line::from(vec![
-            Span::styled("Bookshelf", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" — library"),
-        ])];
-
-        if let Some(status_line) = self.active_filter_status_line() {
-            lines.push(status_line);
+    /// A pick-one list under "(none)", like the collection list. `staged` is the picked name
+    /// and how to show it.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_assign_choice_list(
+        &self,
+        frame: &mut ratatui::Frame,
+        area: Rect,
+        title: &str,
+        names: &[String],
+        staged: Option<(&str, String)>,
+        cursor: usize,
+        focus: bool,
+    ) {
+        let mut items = Vec::new();
+        let prefix = if staged.is_none() { "●" } else { " " };
+        items.push(ListItem::new(Line::raw(format!("{prefix} (none)"))));
+        for name in names {
+            let line = match &staged {
+                Some((picked, shown)) if picked.eq_ignore_ascii_case(name) => format!("● {shown}"),
+                _ => format!("  {name}"),
+            };
+            items.push(ListItem::new(Line::raw(line)));
         }
 
-        lines
+        let title_style = if focus {
+            Style::default()
+                .fg(self.accent_color())
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(if focus {
+                        Style::default().fg(self.accent_color())
+                    } else {
+                        Style::default()
+                    })
+                    .title(Span::styled(title.to_string(), title_style)),
+            )
+            .highlight_style(if focus {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(self.accent_color())
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White).bg(Color::Gray)
+            })
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+        let mut state = ListState::default();
+        state.select(Some(cursor.min(names.len())));
+        frame.render_stateful_widget(list, area, &mut state);
     }
 
-    fn active_filter_status_line(&self) -> Option<Line<'static>> {
-        if self.search_panel.open {
-            return None;
-        }
-
-        let mut parts: Vec<Span<'static>> = Vec::new();
-        let style = Style::default().fg(Color::Cyan);
+    fn draw_label_catalog_input_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(70, 35, area);
+        frame.render_widget(Clear, popup_area);
 
-        let query = self.ctx.library_query.trim();
-        if !query.is_empty() {
-            parts.push(Span::styled(format!("filter: {query}"), style));
-        }
+        let kind = self.label_catalog_input_panel.kind;
+        let title = match self.label_catalog_input_panel.mode {
+            LabelCatalogInputMode::Create => format!("New {kind}"),
+            LabelCatalogInputMode::Rename => format!("Rename {kind}"),
+        };
 
-        if self.ctx.favorites_only {
-            parts.push(Span::styled("favorites only".to_string(), style));
-        }
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        frame.render_widget(block.clone(), popup_area);
 
-        if let Some(label) = self.active_label_filter_summary() {
-            parts.push(Span::styled(label, style));
-        }
+        let inner = block.inner(popup_area);
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(5),
+            ])
+            .split(inner);
 
-        if parts.is_empty() {
-            None
-        } else {
-            let mut spans = Vec::with_capacity(parts.len() * 2 - 1);
-            for (idx, part) in parts.into_iter().enumerate() {
-                if idx > 0 {
-                    spans.push(Span::raw("  |  "));
-                }
-                spans.push(part);
-            }
-            Some(Line::from(spans))
-        }
-    }
+        let prompt = format!("{}: ", kind.title());
+        let header = Paragraph::new(self.label_catalog_input_panel.input.labeled_line(
+            Span::styled(prompt, Style::default().add_modifier(Modifier::BOLD)),
+        ))
+        .wrap(Wrap { trim: true })
+        .alignment(Alignment::Left);
+        frame.render_widget(header, sections[0]);
 
-    fn active_label_filter_summary(&self) -> Option<String> {
-        let mut parts = Vec::new();
-        match &self.ctx.collection_filter {
-            CollectionFilter::Any => {}
-            CollectionFilter::None => parts.push("collection:none".to_string()),
-            CollectionFilter::Selected(name) => parts.push(format!("collection:{name}")),
-        }
+        let mut help_lines = Vec::new();
+        help_lines.push(Line::from(vec![
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" apply  "),
+            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" cancel  "),
+            Span::styled("Backspace", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" delete  "),
+            Span::styled("Ctrl+U", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" clear"),
+        ]));
 
-        if !self.ctx.tag_filters.is_empty() {
-            let mode = match self.ctx.tag_match_mode {
-                TagMatchMode::And => "and",
-                TagMatchMode::Or => "or",
-            };
-            parts.push(format!("tags({mode}): {}", self.ctx.tag_filters.join(", ")));
+        if let Some(err) = &self.label_catalog_input_panel.error {
+            help_lines.push(Line::raw(""));
+            help_lines.push(Line::styled(
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
         }
 
-        if parts.is_empty() {
-            None
-        } else {
-            Some(parts.join(" "))
-        }
+        let help = Paragraph::new(Text::from(help_lines))
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Left);
+        frame.render_widget(help, sections[2]);
     }
 
-    fn refresh_meta_cache(&mut self) {
-        let Some(selected_idx) = self.selected_visible_index() else {
-            self.meta_cache = BookMetaCache::default();
-            return;
-        };
-        let Some(book) = self.ctx.books.get(selected_idx) else {
-            self.meta_cache = BookMetaCache::default();
-            return;
-        };
-
-        if self.meta_cache.path.as_deref() == Some(&book.path) {
-            return;
-        }
-
-        let decoded = bookshelf_core::decode_path(&book.path);
-        let size_bytes = std::fs::metadata(&decoded).ok().map(|m| m.len());
-        let page_count = self.engine.page_count(book).ok();
-
-        self.meta_cache = BookMetaCache {
-            path: Some(book.path.clone()),
-            size_bytes,
-            page_count,
-        };
-    }
+    fn draw_bookmarks_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(70, 55, area);
+        frame.render_widget(Clear, popup_area);
 
-    fn current_bookmarks(&self) -> Vec<Bookmark> {
-        let Some(path) = self.reader.book_path.as_ref() else {
-            return Vec::new();
+        let title = match (
+            self.bookmarks_panel.input_open,
+            &self.bookmarks_panel.editing,
+        ) {
+            (false, _) => "Bookmarks",
+            (true, None) => "Bookmarks — Add",
+            (true, Some(_)) => "Bookmarks — Edit label",
         };
-        self.ctx
-            .bookmarks_by_path
-            .get(path)
-            .cloned()
-            .unwrap_or_default()
-    }
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        frame.render_widget(block.clone(), popup_area);
 
-    fn current_notes(&self) -> Vec<Note> {
-        let Some(path) = self.reader.book_path.as_ref() else {
-            return Vec::new();
+        let inner = block.inner(popup_area);
+        let header_height = if self.bookmarks_panel.input_open {
+            3
+        } else {
+            0
         };
-        self.ctx
-            .notes_by_path
-            .get(path)
-            .cloned()
-            .unwrap_or_default()
-    }
-
-    fn draw(&mut self, area: Rect, frame: &mut ratatui::Frame) {
-        frame.render_widget(Clear, area);
-        if self.reader.open {
-            self.draw_reader(area, frame);
-            return;
-        }
-
-        self.normalize_selection_to_visible();
-        self.refresh_meta_cache();
-
-        let layout = Layout::default()
+        let sections = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(3),
+                Constraint::Length(header_height),
                 Constraint::Min(0),
-                Constraint::Length(3),
+                Constraint::Length(2),
             ])
-            .split(area);
-
-        let title = Paragraph::new(Text::from(self.main_header_lines()))
-            .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::BOTTOM));
-        frame.render_widget(title, layout[0]);
+            .split(inner);
 
-        let body_layout = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
-            .split(layout[1]);
+        if self.bookmarks_panel.input_open {
+            let mut header_lines = vec![
+                Line::from(vec![
+                    Span::styled("Page: ", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(self.bookmarks_panel.input_page.to_string()),
+                ]),
+                self.bookmarks_panel.input.labeled_line(Span::styled(
+                    "Label: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                )),
+            ];
+            if let Some(err) = &self.bookmarks_panel.error {
+                header_lines.push(Line::from(vec![Span::styled(
+                    err.clone(),
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                )]));
+            }
+            frame.render_widget(Paragraph::new(Text::from(header_lines)), sections[0]);
+        }
 
-        self.draw_library(frame, body_layout[0]);
-        frame.render_widget(self.draw_details(body_layout[1]), body_layout[1]);
+        let bookmarks = self.current_bookmarks();
+        let items = if bookmarks.is_empty() {
+            vec![ListItem::new(Line::raw("(none)"))]
+        } else {
+            bookmarks
+                .iter()
+                .map(|b| {
+                    let page = labeled_page(&self.reader.page_labels, b.page);
+                    let mut label = if b.label.trim().is_empty() {
+                        format!("Page {page}")
+                    } else {
+                        format!("Page {page} — {}", b.label.trim())
+                    };
+                    match b.view {
+                        Some(BookmarkView::Text { scroll }) => {
+                            label.push_str(&format!("  (line {})", scroll + 1));
+                        }
+                        Some(BookmarkView::Image { zoom_percent, .. }) => {
+                            label.push_str(&format!("  ({zoom_percent}% view)"));
+                        }
+                        None => {}
+                    }
+                    ListItem::new(Line::raw(label))
+                })
+                .collect()
+        };
 
-        let footer = Paragraph::new(Text::from(self.main_footer_lines()))
-            .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::TOP));
-        frame.render_widget(footer, layout[2]);
+        let highlight_style = Style::default()
+            .fg(Color::Black)
+            .bg(self.accent_color())
+            .add_modifier(Modifier::BOLD);
 
-        if self.settings_panel.open {
-            self.draw_settings_panel(area, frame);
-        }
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::NONE))
+            .highlight_style(highlight_style)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
 
-        if self.scan_panel.open {
-            self.draw_scan_panel(area, frame);
+        let mut state = ListState::default();
+        if !bookmarks.is_empty() {
+            state.select(Some(self.bookmarks_panel.selected.min(bookmarks.len() - 1)));
         }
-
-        if self.search_panel.open {
-            self.draw_search_panel(area, frame);
+        frame.render_stateful_widget(list, sections[1], &mut state);
+        if !self.bookmarks_panel.input_open {
+            self.hit_regions.borrow_mut().add_list_rows(
+                sections[1],
+                state.offset(),
+                &vec![1; bookmarks.len()],
+                HitTarget::Bookmark,
+            );
         }
 
-        if self.label_catalog_input_panel.open {
-            self.draw_label_catalog_input_panel(area, frame);
-        }
+        let footer_spans = if self.bookmarks_panel.input_open {
+            vec![
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" cancel  "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" save"),
+            ]
+        } else {
+            vec![
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" close  "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" jump  "),
+                Span::styled("a", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" add current  "),
+                Span::styled("A", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" add with label  "),
+                Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" edit label  "),
+                Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" delete"),
+            ]
+        };
+        let footer = Paragraph::new(Line::from(footer_spans)).alignment(Alignment::Center);
+        frame.render_widget(footer, sections[2]);
     }
 
-    fn draw_search_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
-        let popup_area = centered_rect(90, 78, area);
+    fn draw_notes_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(80, 60, area);
         frame.render_widget(Clear, popup_area);
 
-        let title = match self.search_panel.mode {
-            SearchPanelMode::Filters => "Filters".to_string(),
-            SearchPanelMode::Labels => "Labels".to_string(),
-            SearchPanelMode::Catalog => "Catalog".to_string(),
+        let title = match (self.notes_panel.input_open, self.notes_panel.editing) {
+            (true, Some(_)) => "Notes — Edit",
+            (true, None) => "Notes — Add",
+            (false, _) => "Notes",
         };
-
         let block = Block::default().borders(Borders::ALL).title(Span::styled(
             title,
             Style::default().add_modifier(Modifier::BOLD),
@@ -2729,723 +7972,1303 @@ impl Ui {
         frame.render_widget(block.clone(), popup_area);
 
         let inner = block.inner(popup_area);
-        match self.search_panel.mode {
-            SearchPanelMode::Filters => {
-                self.draw_search_filters_tab(inner, frame);
+
+        let mut header_lines = Vec::new();
+        if self.notes_panel.input_open {
+            header_lines.push(Line::from(vec![
+                Span::styled("Page: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(self.notes_panel.input_page.to_string()),
+            ]));
+            for (idx, line) in self
+                .notes_panel
+                .input
+                .lines(Style::default())
+                .into_iter()
+                .enumerate()
+            {
+                let label = if idx == 0 { "Text: " } else { "      " };
+                let mut spans = vec![Span::styled(
+                    label,
+                    Style::default().add_modifier(Modifier::BOLD),
+                )];
+                spans.extend(line.spans);
+                header_lines.push(Line::from(spans));
             }
-            SearchPanelMode::Labels => self.draw_search_assign_tab(inner, frame),
-            SearchPanelMode::Catalog => self.draw_search_manage_tab(inner, frame),
+        } else {
+            header_lines.push(Line::raw("Use 'a' to add a note for the current page."));
+            header_lines.push(Line::raw("Use 'e' to edit the selected note."));
         }
-    }
-
-    fn draw_search_filters_tab(&self, area: Rect, frame: &mut ratatui::Frame) {
+        if let Some(err) = &self.notes_panel.error {
+            header_lines.push(Line::from(vec![Span::styled(
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )]));
+        }
+        // Long notes grow the input area, but the list keeps at least half the panel.
+        let header_height = (header_lines.len() as u16 + 1)
+            .max(4)
+            .min((inner.height / 2).max(4));
         let sections = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(7),
+                Constraint::Length(header_height),
                 Constraint::Min(0),
-                Constraint::Length(4),
+                Constraint::Length(2),
             ])
-            .split(area);
-
-        let filters_lines = self.search_panel_summary_lines(self.search_panel.focus);
-        let header = Paragraph::new(Text::from(filters_lines))
-            .wrap(Wrap { trim: true })
-            .alignment(Alignment::Left);
+            .split(inner);
+        let header = Paragraph::new(Text::from(header_lines)).wrap(Wrap { trim: false });
         frame.render_widget(header, sections[0]);
 
-        let body = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
-            .split(sections[1]);
+        let notes = self.current_notes();
+        let items = if notes.is_empty() {
+            vec![ListItem::new(Line::raw("(none)"))]
+        } else {
+            notes
+                .iter()
+                .map(|n| {
+                    let mut lines = n.body.trim().lines();
+                    let first = lines.next().unwrap_or_default();
+                    let more = if lines.next().is_some() { " …" } else { "" };
+                    let label = if first.is_empty() {
+                        format!("Page {}", n.page)
+                    } else {
+                        format!("Page {} — {first}{more}", n.page)
+                    };
+                    ListItem::new(Line::raw(label))
+                })
+                .collect()
+        };
 
-        self.draw_search_collections_list(body[0], frame);
-        self.draw_search_tags_list(body[1], frame);
+        let highlight_style = Style::default()
+            .fg(Color::Black)
+            .bg(self.accent_color())
+            .add_modifier(Modifier::BOLD);
 
-        let help_lines = vec![
-            Line::from(vec![
-                Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" focus  "),
-                Span::styled("↑/↓", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" move  "),
-                Span::styled("Space", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" toggle"),
-            ]),
-            Line::from(vec![
-                Span::styled("←/→", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" tags AND/OR  "),
-                Span::styled("f", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" favorites-only  "),
-                Span::styled("Ctrl+u", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" clear all"),
-            ]),
-            Line::from(vec![
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::NONE))
+            .highlight_style(highlight_style)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        let mut state = ListState::default();
+        if !notes.is_empty() {
+            state.select(Some(self.notes_panel.selected.min(notes.len() - 1)));
+        }
+        frame.render_stateful_widget(list, sections[1], &mut state);
+        if !self.notes_panel.input_open {
+            self.hit_regions.borrow_mut().add_list_rows(
+                sections[1],
+                state.offset(),
+                &vec![1; notes.len()],
+                HitTarget::Note,
+            );
+        }
+
+        let footer_spans = if self.notes_panel.input_open {
+            vec![
                 Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(" cancel  "),
+                Span::styled("Alt+Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" newline  "),
                 Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" close"),
-            ]),
-        ];
-        let help = Paragraph::new(Text::from(help_lines))
-            .wrap(Wrap { trim: true })
-            .alignment(Alignment::Left);
-        frame.render_widget(help, sections[2]);
+                Span::raw(" save"),
+            ]
+        } else {
+            vec![
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" close  "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" jump  "),
+                Span::styled("a", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" add  "),
+                Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" edit  "),
+                Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" delete"),
+            ]
+        };
+        let footer = Paragraph::new(Line::from(footer_spans)).alignment(Alignment::Center);
+        frame.render_widget(footer, sections[2]);
     }
 
-    fn search_panel_summary_lines(&self, focus: SearchFocus) -> Vec<Line<'static>> {
-        let base_label_style = Style::default().add_modifier(Modifier::BOLD);
-        let focus_style = Style::default()
-            .fg(self.accent_color())
-            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    fn draw_toc_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(80, 70, area);
+        frame.render_widget(Clear, popup_area);
 
-        let query_label_style = if focus == SearchFocus::Query {
-            focus_style
-        } else {
-            base_label_style
-        };
-        let query_value_style = if focus == SearchFocus::Query {
-            focus_style
-        } else {
-            Style::default()
-        };
+        let visible = self.toc_visible_indices();
 
-        let collection_label_style = if focus == SearchFocus::Collections {
-            focus_style
-        } else {
-            base_label_style
-        };
-        let collection_value_style = if focus == SearchFocus::Collections {
-            focus_style
-        } else {
-            Style::default()
+        let title = match self.toc_panel.error.as_deref() {
+            Some(_) => "Table of Contents (error)".to_string(),
+            None => {
+                if self.toc_panel.query.as_str().trim().is_empty() {
+                    format!("Table of Contents — {}", self.toc_panel.items.len())
+                } else {
+                    format!(
+                        "Table of Contents — {}/{}",
+                        visible.len(),
+                        self.toc_panel.items.len()
+                    )
+                }
+            }
         };
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        frame.render_widget(block.clone(), popup_area);
 
-        let tags_label_style = if focus == SearchFocus::Tags {
-            focus_style
-        } else {
-            base_label_style
-        };
-        let tags_value_style = if focus == SearchFocus::Tags {
-            focus_style
-        } else {
-            Style::default()
-        };
+        let inner = block.inner(popup_area);
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(2),
+            ])
+            .split(inner);
 
-        let query = self.ctx.library_query.clone();
-        let fav = if self.ctx.favorites_only { "on" } else { "off" };
-        let collection = match &self.ctx.collection_filter {
-            CollectionFilter::Any => "any".to_string(),
-            CollectionFilter::None => "none".to_string(),
-            CollectionFilter::Selected(name) => name.clone(),
-        };
-        let mode = match self.ctx.tag_match_mode {
-            TagMatchMode::And => "AND",
-            TagMatchMode::Or => "OR",
-        };
-        let tags = if self.ctx.tag_filters.is_empty() {
-            "(none)".to_string()
+        let mut header_lines = Vec::new();
+        header_lines.push(Line::raw(
+            "↑/↓ select, Enter jump, B bookmark chapter starts, Esc close.",
+        ));
+        header_lines.push(self.toc_panel.query.labeled_line(Span::styled(
+            "Filter: ",
+            Style::default().add_modifier(Modifier::BOLD),
+        )));
+        if let Some(err) = &self.toc_panel.error {
+            header_lines.push(Line::from(vec![Span::styled(
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )]));
+        }
+        frame.render_widget(
+            Paragraph::new(Text::from(header_lines)).wrap(Wrap { trim: true }),
+            sections[0],
+        );
+
+        let items: Vec<ListItem> = if self.toc_panel.items.is_empty() {
+            vec![ListItem::new(Line::raw("(no outline found)"))]
+        } else if !self.toc_panel.query.as_str().trim().is_empty() && visible.is_empty() {
+            vec![ListItem::new(Line::raw("(no matches)"))]
         } else {
-            self.ctx.tag_filters.join(", ")
+            visible
+                .iter()
+                .filter_map(|idx| self.toc_panel.items.get(*idx))
+                .map(|item| {
+                    let indent = "  ".repeat(item.depth.min(12));
+                    let page = item
+                        .page
+                        .map(|p| format!("p{}", labeled_page(&self.reader.page_labels, p)))
+                        .unwrap_or_else(|| "-".to_string());
+                    ListItem::new(Line::raw(format!("{indent}{}  [{page}]", item.title)))
+                })
+                .collect()
         };
 
-        vec![
-            Line::from(vec![
-                Span::styled("Query: ", query_label_style),
-                Span::styled(query, query_value_style),
-            ]),
-            Line::from(vec![
-                Span::styled("Favorites only: ", base_label_style),
-                Span::raw(fav),
-                Span::raw("  "),
-                Span::styled("Collection: ", collection_label_style),
-                Span::styled(collection, collection_value_style),
-            ]),
-            Line::from(vec![
-                Span::styled("Tags: ", tags_label_style),
-                Span::styled(tags, tags_value_style),
+        let highlight_style = Style::default()
+            .fg(Color::Black)
+            .bg(self.accent_color())
+            .add_modifier(Modifier::BOLD);
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::NONE))
+            .highlight_style(highlight_style)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        let mut state = ListState::default();
+        if !visible.is_empty() {
+            state.select(Some(self.toc_panel.selected.min(visible.len() - 1)));
+        }
+        frame.render_stateful_widget(list, sections[1], &mut state);
+        self.hit_regions.borrow_mut().add_list_rows(
+            sections[1],
+            state.offset(),
+            &vec![1; visible.len()],
+            HitTarget::Toc,
+        );
+
+        let footer = match self.toc_panel.bookmark_depth {
+            Some(depth) => Line::from(vec![
+                Span::styled(
+                    format!("Bookmark entries down to level {}?  ", depth + 1),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled("1-9", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" level  "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" add  "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" cancel"),
             ]),
-            Line::from(vec![
-                Span::styled("Tag match: ", tags_label_style),
-                Span::styled(mode, tags_value_style),
+            None => Line::from(vec![
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" close  "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" jump  "),
+                Span::styled("B", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" bookmark chapters  "),
+                Span::styled("Ctrl+u", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" clear"),
             ]),
-        ]
+        };
+        let footer = Paragraph::new(footer).alignment(Alignment::Center);
+        frame.render_widget(footer, sections[2]);
     }
 
-    fn draw_search_collections_list(&self, area: Rect, frame: &mut ratatui::Frame) {
-        let entries = self.collection_entries_for_search();
-        let mut cursor = self.search_panel.collection_cursor;
-        if !entries.is_empty() {
-            cursor = cursor.min(entries.len().saturating_sub(1));
-        } else {
-            cursor = 0;
-        }
+    fn draw_links_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(70, 60, area);
+        frame.render_widget(Clear, popup_area);
 
-        let focus = self.search_panel.focus == SearchFocus::Collections;
-        let title_style = if focus {
-            Style::default()
-                .fg(self.accent_color())
-                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
-        } else {
-            Style::default()
+        let panel = &self.links_panel;
+        let title = match panel.error {
+            Some(_) => format!("Links on page {} (error)", panel.page),
+            None => format!("Links on page {} — {}", panel.page, panel.links.len()),
         };
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        frame.render_widget(block.clone(), popup_area);
 
-        let items: Vec<ListItem> = if entries.is_empty() {
-            vec![ListItem::new(Line::raw("(none)"))]
+        let inner = block.inner(popup_area);
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(2),
+                Constraint::Min(0),
+                Constraint::Length(2),
+            ])
+            .split(inner);
+
+        let header = match &panel.error {
+            Some(err) => Line::from(vec![Span::styled(
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )]),
+            None => Line::raw("↑/↓ select, Enter follow, Esc close."),
+        };
+        frame.render_widget(
+            Paragraph::new(header).wrap(Wrap { trim: true }),
+            sections[0],
+        );
+
+        let items: Vec<ListItem> = if panel.links.is_empty() {
+            vec![ListItem::new(Line::raw("(no links to other pages)"))]
         } else {
-            entries
+            panel
+                .links
                 .iter()
-                .map(|e| {
-                    let selected = e.filter == self.ctx.collection_filter;
-                    let prefix = if selected { "●" } else { " " };
-                    ListItem::new(Line::raw(format!("{prefix} {} ({})", e.label, e.count)))
+                .map(|link| {
+                    ListItem::new(Line::raw(format!(
+                        "{}  [p{}]",
+                        link.label, link.target_page
+                    )))
                 })
                 .collect()
         };
 
-        let focused_list_style = Style::default()
+        let highlight_style = Style::default()
             .fg(Color::Black)
             .bg(self.accent_color())
             .add_modifier(Modifier::BOLD);
-        let unfocused_list_style = Style::default().fg(Color::White).bg(Color::Gray);
-
-        let highlight_style = if focus {
-            focused_list_style
-        } else {
-            unfocused_list_style
-        };
 
         let list = List::new(items)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(if focus {
-                        Style::default().fg(self.accent_color())
-                    } else {
-                        Style::default()
-                    })
-                    .title(Span::styled("Collections", title_style)),
-            )
+            .block(Block::default().borders(Borders::NONE))
             .highlight_style(highlight_style)
             .highlight_symbol("> ")
             .highlight_spacing(HighlightSpacing::Always);
+
         let mut state = ListState::default();
-        if !entries.is_empty() {
-            state.select(Some(cursor));
+        if !panel.links.is_empty() {
+            state.select(Some(panel.selected.min(panel.links.len() - 1)));
         }
-        frame.render_stateful_widget(list, area, &mut state);
-    }
+        frame.render_stateful_widget(list, sections[1], &mut state);
 
-    fn draw_search_tags_list(&self, area: Rect, frame: &mut ratatui::Frame) {
-        let entries = self.tag_entries_for_search();
-        let mut cursor = self.search_panel.tag_cursor;
-        if !entries.is_empty() {
-            cursor = cursor.min(entries.len().saturating_sub(1));
-        } else {
-            cursor = 0;
+        let mut footer_spans = vec![
+            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" close  "),
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" follow"),
+        ];
+        if panel.back_len() > 0 {
+            footer_spans.push(Span::raw("  "));
+            footer_spans.push(Span::styled(
+                self.key_bindings.label(KeyAction::LinkBack),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(format!(" back ({})", panel.back_len())));
         }
+        let footer = Paragraph::new(Line::from(footer_spans)).alignment(Alignment::Center);
+        frame.render_widget(footer, sections[2]);
+    }
 
-        let focus = self.search_panel.focus == SearchFocus::Tags;
-        let mode = match self.ctx.tag_match_mode {
-            TagMatchMode::And => "AND",
-            TagMatchMode::Or => "OR",
+    fn draw_pdf_annotations_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(70, 60, area);
+        frame.render_widget(Clear, popup_area);
+
+        let panel = &self.pdf_annotations_panel;
+        let place = match panel.page {
+            Some(page) => format!("on page {page}"),
+            None => "in this book".to_string(),
         };
-        let title_style = if focus {
-            Style::default()
-                .fg(self.accent_color())
-                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
-        } else {
-            Style::default()
+        let title = match panel.error {
+            Some(_) => format!("Annotations {place} (error)"),
+            None => format!("Annotations {place} — {}", panel.annotations.len()),
         };
-        let title = format!("Tags ({mode})");
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        frame.render_widget(block.clone(), popup_area);
 
-        let items: Vec<ListItem> = if entries.is_empty() {
-            vec![ListItem::new(Line::raw("(none)"))]
+        let inner = block.inner(popup_area);
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(2),
+                Constraint::Min(0),
+                Constraint::Length(2),
+            ])
+            .split(inner);
+
+        let header = match &panel.error {
+            Some(err) => Line::from(vec![Span::styled(
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )]),
+            None => Line::raw("↑/↓ select, Enter go to page, Esc close."),
+        };
+        frame.render_widget(
+            Paragraph::new(header).wrap(Wrap { trim: true }),
+            sections[0],
+        );
+
+        let items: Vec<ListItem> = if panel.annotations.is_empty() {
+            vec![ListItem::new(Line::raw("(none found)"))]
         } else {
-            entries
+            panel
+                .annotations
                 .iter()
-                .map(|e| {
-                    let selected = self
-                        .ctx
-                        .tag_filters
-                        .iter()
-                        .any(|t| t.eq_ignore_ascii_case(&e.name));
-                    let prefix = if selected { "[x]" } else { "[ ]" };
-                    ListItem::new(Line::raw(format!("{prefix} {} ({})", e.name, e.count)))
-                })
+                .map(|annotation| ListItem::new(Line::raw(panel.row(annotation))))
                 .collect()
         };
 
-        let focused_list_style = Style::default()
+        let highlight_style = Style::default()
             .fg(Color::Black)
             .bg(self.accent_color())
             .add_modifier(Modifier::BOLD);
-        let unfocused_list_style = Style::default().fg(Color::White).bg(Color::Gray);
-
-        let highlight_style = if focus {
-            focused_list_style
-        } else {
-            unfocused_list_style
-        };
 
         let list = List::new(items)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(if focus {
-                        Style::default().fg(self.accent_color())
-                    } else {
-                        Style::default()
-                    })
-                    .title(Span::styled(title, title_style)),
-            )
+            .block(Block::default().borders(Borders::NONE))
             .highlight_style(highlight_style)
             .highlight_symbol("> ")
             .highlight_spacing(HighlightSpacing::Always);
+
         let mut state = ListState::default();
-        if !entries.is_empty() {
-            state.select(Some(cursor));
+        if !panel.annotations.is_empty() {
+            state.select(Some(panel.selected.min(panel.annotations.len() - 1)));
         }
-        frame.render_stateful_widget(list, area, &mut state);
+        frame.render_stateful_widget(list, sections[1], &mut state);
+
+        let footer = Paragraph::new(Line::from(vec![
+            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" close  "),
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" go to page  "),
+            Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(if panel.whole_book() {
+                " this page"
+            } else {
+                " whole book"
+            }),
+        ]))
+        .alignment(Alignment::Center);
+        frame.render_widget(footer, sections[2]);
     }
 
-    fn draw_search_manage_tab(&self, area: Rect, frame: &mut ratatui::Frame) {
-        let sections = Layout::default()
+    fn draw_reader(&mut self, area: Rect, frame: &mut ratatui::Frame) {
+        if self.reader.mode == ReaderMode::Image
+            && !image_protocol::image_supported(&self.image_picker)
+        {
+            self.reader.mode = ReaderMode::Text;
+            self.reader.current_image = None;
+            self.reader.render_key = None;
+            self.reader.notice =
+                Some("image protocol not detected; image mode disabled".to_string());
+        }
+
+        let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(2),
+                Constraint::Length(3),
                 Constraint::Min(0),
-                Constraint::Length(4),
+                Constraint::Length(2),
             ])
             .split(area);
 
-        let filter_style = if self.label_manager_panel.filter_editing {
-            Style::default()
-                .fg(self.accent_color())
-                .add_modifier(Modifier::BOLD)
-        } else {
-            Style::default()
-        };
-
-        let filter_label = match self.label_manager_panel.tab {
-            LabelManagerTab::Collections => "Filter (Collections): ",
-            LabelManagerTab::Tags => "Filter (Tags): ",
+        let mut title_text = match &self.reader.book_title {
+            Some(title) => format!("Reader — {title}"),
+            None => "Reader".to_string(),
         };
+        if let Some(secs) = self
+            .reader
+            .book_path
+            .as_ref()
+            .and_then(|path| self.ctx.reading_secs_by_path.get(path))
+            .filter(|secs| **secs >= 60)
+        {
+            title_text.push_str(&format!(" · {} read", format_reading_time(*secs)));
+        }
 
-        let header_lines = vec![Line::from(vec![
-            Span::styled(filter_label, Style::default().add_modifier(Modifier::BOLD)),
-            Span::styled(self.label_manager_panel.filter.clone(), filter_style),
-            Span::raw("  "),
-            Span::raw("(/ to edit, Ctrl+u clear)"),
-        ])];
-        let header = Paragraph::new(Text::from(header_lines))
-            .wrap(Wrap { trim: true })
-            .alignment(Alignment::Left);
-        frame.render_widget(header, sections[0]);
+        if self.reader.mode == ReaderMode::Image {
+            image_protocol::prefer_graphics_protocol(&mut self.image_picker);
+        }
 
-        let body = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-            .split(sections[1]);
+        let page_title = {
+            let page = self.reader.page.saturating_add(1);
+            let spread_right = (self.reader.mode == ReaderMode::Image && self.reader.spread)
+                .then(|| {
+                    spread_pages(
+                        self.reader.page,
+                        self.reader.total_pages,
+                        self.reader.spread_cover_alone,
+                    )
+                    .1
+                })
+                .flatten();
+            // The printed label leads, with the page's place in the file after it.
+            let labels = &self.reader.page_labels;
+            let printed = page_label(labels, page).map(|left| match spread_right {
+                Some(right) => format!(
+                    "{left}-{}",
+                    page_label(labels, right + 1)
+                        .map_or_else(|| (right + 1).to_string(), str::to_string)
+                ),
+                None => left.to_string(),
+            });
+            let page = match spread_right {
+                Some(right) => format!("{page}-{}", right.saturating_add(1)),
+                None => page.to_string(),
+            };
+            let page_part = match (printed, self.reader.total_pages) {
+                (Some(printed), Some(total)) => format!("p{printed} ({page}/{total})"),
+                (Some(printed), None) => format!("p{printed} ({page})"),
+                (None, Some(total)) => format!("p{page}/{total}"),
+                (None, None) => format!("p{page}"),
+            };
 
-        let collections_entries = self.label_manager_entries(LabelManagerTab::Collections);
-        let tags_entries = self.label_manager_entries(LabelManagerTab::Tags);
+            let mode_part = match self.reader.mode {
+                ReaderMode::Text => match self.reader.text_scroll_label() {
+                    Some(position) => {
+                        format!("{} · {position}", self.reader.text_mode)
+                    }
+                    None => self.reader.text_mode.to_string(),
+                },
+                ReaderMode::Image => {
+                    let (fw, fh) = self.image_picker.font_size();
+                    let rotation = match self.reader.rotation {
+                        PageRotation::Upright => String::new(),
+                        rotation => format!(" ↻{rotation}"),
+                    };
+                    format!(
+                        "{} fit-{} {}%{rotation} · {}x{}px",
+                        image_protocol::protocol_label(&self.image_picker),
+                        self.reader.image_fit,
+                        self.reader.image_zoom_percent,
+                        fw,
+                        fh
+                    )
+                }
+            };
 
-        let mut collections_cursor = self.label_manager_panel.collections_cursor;
-        if !collections_entries.is_empty() {
-            collections_cursor = collections_cursor.min(collections_entries.len() - 1);
-        } else {
-            collections_cursor = 0;
-        }
+            // Notes are page-level, so the badge sits on the frame rather than on any line.
+            let last_shown = spread_right.unwrap_or(self.reader.page).saturating_add(1);
+            let has_note = self
+                .reader
+                .book_path
+                .as_ref()
+                .and_then(|path| self.ctx.notes_by_path.get(path))
+                .is_some_and(|notes| {
+                    notes
+                        .iter()
+                        .any(|note| (self.reader.page + 1..=last_shown).contains(&note.page))
+                });
+            let mut spans = vec![Span::raw(format!("{page_part} · {mode_part}"))];
+            if has_note {
+                spans.push(Span::raw(" "));
+                spans.push(Span::styled(
+                    " note ",
+                    Style::default()
+                        .fg(Color::Black)
+                        .bg(self.accent_color())
+                        .add_modifier(Modifier::BOLD),
+                ));
+            }
+            Line::from(spans)
+        };
 
-        let mut tags_cursor = self.label_manager_panel.tags_cursor;
-        if !tags_entries.is_empty() {
-            tags_cursor = tags_cursor.min(tags_entries.len() - 1);
-        } else {
-            tags_cursor = 0;
+        if let Some(idx) =
+            toc::current_toc_index(&self.reader.toc, self.reader.page.saturating_add(1))
+        {
+            // One header row; the chapter gives way before the title does. 3 columns for ` · `.
+            let used = UnicodeWidthStr::width(title_text.as_str()) + 3;
+            let room = usize::from(layout[0].width).saturating_sub(used);
+            let chapter = toc::truncate_to_width(self.reader.toc[idx].title.trim(), room);
+            if UnicodeWidthStr::width(chapter.as_str()) >= 4 {
+                title_text.push_str(&format!(" · {chapter}"));
+            }
         }
 
-        let focused_list_style = Style::default()
-            .fg(Color::Black)
-            .bg(self.accent_color())
-            .add_modifier(Modifier::BOLD);
-        let unfocused_list_style = Style::default().fg(Color::White).bg(Color::Gray);
+        let header = Paragraph::new(Line::from(vec![Span::styled(
+            title_text,
+            Style::default().add_modifier(Modifier::BOLD),
+        )]))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::BOTTOM));
+        frame.render_widget(header, layout[0]);
 
-        // Collections
-        let collections_focus = self.label_manager_panel.tab == LabelManagerTab::Collections;
-        let collections_title_style = if collections_focus {
-            Style::default()
-                .fg(self.accent_color())
-                .add_modifier(Modifier::BOLD)
+        // The notes sidebar takes the right of the reader, then the strip is carved off the
+        // bottom of what is left; the page is centered in the rest.
+        let (page_area, sidebar_area) = self.notes_sidebar.split(layout[1]);
+        let (page_area, strip_area) = if self.thumbnail_strip_visible()
+            && page_area.height >= THUMBNAIL_STRIP_HEIGHT.saturating_mul(2)
+        {
+            let split = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Min(0),
+                    Constraint::Length(THUMBNAIL_STRIP_HEIGHT),
+                ])
+                .split(page_area);
+            (split[0], Some(split[1]))
         } else {
-            Style::default()
+            (page_area, None)
         };
-        let collections_items: Vec<ListItem> = if collections_entries.is_empty() {
-            vec![ListItem::new(Line::raw("(none)"))]
-        } else {
-            collections_entries
-                .iter()
-                .map(|(name, count)| ListItem::new(Line::raw(format!("{name} ({count})"))))
-                .collect()
+        if let Some(sidebar_area) = sidebar_area {
+            self.draw_notes_sidebar(sidebar_area, frame);
+        }
+        // The position column sits right of the page, so pages render one column narrower.
+        let page_area = match self.reader.total_pages {
+            Some(total)
+                if self.ctx.settings.reader_minimap
+                    && total > 0
+                    && page_area.width > MINIMAP_MIN_PAGE_WIDTH =>
+            {
+                let page_area = Rect {
+                    width: page_area.width - 1,
+                    ..page_area
+                };
+                let column = Rect {
+                    x: page_area.right(),
+                    width: 1,
+                    ..page_area
+                };
+                self.draw_reader_minimap(column, total, frame);
+                page_area
+            }
+            _ => page_area,
         };
-        let collections_list = List::new(collections_items)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(if collections_focus {
-                        Style::default().fg(self.accent_color())
-                    } else {
-                        Style::default()
-                    })
-                    .title(Span::styled("Collections", collections_title_style)),
-            )
-            .highlight_style(if collections_focus {
-                focused_list_style
-            } else {
-                unfocused_list_style
-            })
-            .highlight_symbol("> ")
-            .highlight_spacing(HighlightSpacing::Always);
-        let mut collections_state = ListState::default();
-        if !collections_entries.is_empty() {
-            collections_state.select(Some(collections_cursor));
-        }
-        frame.render_stateful_widget(collections_list, body[0], &mut collections_state);
 
-        // Tags
-        let tags_focus = self.label_manager_panel.tab == LabelManagerTab::Tags;
-        let tags_title_style = if tags_focus {
-            Style::default()
-                .fg(self.accent_color())
-                .add_modifier(Modifier::BOLD)
-        } else {
-            Style::default()
-        };
-        let tags_items: Vec<ListItem> = if tags_entries.is_empty() {
-            vec![ListItem::new(Line::raw("(none)"))]
-        } else {
-            tags_entries
-                .iter()
-                .map(|(name, count)| ListItem::new(Line::raw(format!("{name} ({count})"))))
-                .collect()
-        };
-        let tags_list = List::new(tags_items)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(if tags_focus {
-                        Style::default().fg(self.accent_color())
-                    } else {
-                        Style::default()
-                    })
-                    .title(Span::styled("Tags", tags_title_style)),
-            )
-            .highlight_style(if tags_focus {
-                focused_list_style
+        let inner_width = page_area.width.saturating_sub(2);
+        let inner_height = page_area.height.saturating_sub(2);
+        self.reader.ensure_rendered(
+            &self.ctx,
+            &self.engine,
+            &mut self.page_render,
+            &self.image_picker,
+            inner_width,
+            inner_height,
+        );
+
+        if self.reader.mode == ReaderMode::Image {
+            let block = Block::default().borders(Borders::ALL).title(page_title);
+            frame.render_widget(block.clone(), page_area);
+            let inner = block.inner(page_area);
+
+            if let Some(protocol) = self.reader.current_image.as_ref() {
+                let proto_area = protocol.area();
+                let draw_width = proto_area.width.min(inner.width);
+                let draw_height = proto_area.height.min(inner.height);
+                let draw_area = Rect::new(
+                    inner.x + inner.width.saturating_sub(draw_width) / 2,
+                    inner.y + inner.height.saturating_sub(draw_height) / 2,
+                    draw_width,
+                    draw_height,
+                );
+                frame.render_widget(ImageWidget::new(protocol), draw_area);
             } else {
-                unfocused_list_style
-            })
-            .highlight_symbol("> ")
-            .highlight_spacing(HighlightSpacing::Always);
-        let mut tags_state = ListState::default();
-        if !tags_entries.is_empty() {
-            tags_state.select(Some(tags_cursor));
+                frame.render_widget(
+                    Paragraph::new(self.reader.visible_text()).wrap(Wrap { trim: false }),
+                    inner,
+                );
+            }
+            if let Some(strip_area) = strip_area {
+                self.draw_thumbnail_strip(strip_area, frame);
+            }
+        } else {
+            let body = Paragraph::new(self.reader.visible_text())
+                .block(Block::default().borders(Borders::ALL).title(page_title))
+                .scroll((0, self.reader.h_scroll));
+            frame.render_widget(body, page_area);
         }
-        frame.render_stateful_widget(tags_list, body[1], &mut tags_state);
 
-        let enter_action = if self.label_manager_panel.confirm_delete.is_some() {
-            "delete"
-        } else if self.label_manager_panel.filter_editing {
-            "done"
+        let up_down_label = if self.reader.mode == ReaderMode::Image {
+            "pan-y"
         } else {
-            "close"
+            "scroll"
         };
 
-        let mut footer_lines = Vec::new();
-        footer_lines.push(Line::from(vec![
-            Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" switch panel  "),
-            Span::styled("/", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" filter"),
-        ]));
-        footer_lines.push(Line::from(vec![
-            Span::styled("n", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" new  "),
-            Span::styled("r", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" rename  "),
-            Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" delete  "),
-            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" cancel  "),
-            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(format!(" {enter_action}")),
-        ]));
+        let mut footer_spans = vec![
+            Span::styled(
+                self.key_bindings.label(KeyAction::CloseReader),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" back  "),
+            Span::styled(
+                format!(
+                    "{}/{}",
+                    self.key_bindings.label(KeyAction::PrevPage),
+                    self.key_bindings.label(KeyAction::NextPage)
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" page  "),
+            Span::styled(
+                format!(
+                    "{}/{}",
+                    self.key_bindings.label(KeyAction::ScrollUp),
+                    self.key_bindings.label(KeyAction::ScrollDown)
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(" {up_down_label}  ")),
+            Span::styled(
+                self.key_bindings.label(KeyAction::GotoPage),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" goto  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::OpenToc),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" toc  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::OpenLinks),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" links  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::OpenPdfAnnotations),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" annotations  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::SearchInBook),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" search  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::OpenBookmarks),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" bookmarks  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::OpenNotes),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" notes  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::ToggleNotesSidebar),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" notes-pane  "),
+            Span::styled(
+                format!(
+                    "{}/{}",
+                    self.key_bindings.label(KeyAction::PrevAnnotation),
+                    self.key_bindings.label(KeyAction::NextAnnotation)
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" prev/next annotated  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::DumpPage),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" dump  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::CopyPageText),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" copy  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::OpenExternal),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" external  "),
+            Span::styled(
+                self.key_bindings.label(KeyAction::OpenHistory),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" recent"),
+        ];
 
-        if let Some(target) = &self.label_manager_panel.confirm_delete {
-            footer_lines.push(Line::raw(""));
-            footer_lines.push(Line::styled(
-                format!("Delete {} \"{}\"? (y/n)", target.kind, target.name),
-                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        let image_ok = image_protocol::image_supported(&self.image_picker);
+        if self.reader.mode == ReaderMode::Image || image_ok {
+            footer_spans.push(Span::raw("  "));
+            footer_spans.push(Span::styled(
+                self.key_bindings.label(KeyAction::ToggleImageMode),
+                Style::default().add_modifier(Modifier::BOLD),
             ));
-        } else if let Some(err) = &self.label_manager_panel.error {
-            footer_lines.push(Line::raw(""));
-            footer_lines.push(Line::styled(
-                err.clone(),
-                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            footer_spans.push(Span::raw(" mode"));
+        }
+
+        if self.reader.mode == ReaderMode::Text && !image_ok {
+            footer_spans.push(Span::raw("  "));
+            footer_spans.push(Span::styled(
+                self.key_bindings.label(KeyAction::SpawnKitty),
+                Style::default().add_modifier(Modifier::BOLD),
             ));
+            footer_spans.push(Span::raw(" graphics-reader"));
         }
 
-        let footer = Paragraph::new(Text::from(footer_lines))
-            .wrap(Wrap { trim: true })
-            .alignment(Alignment::Left);
-        frame.render_widget(footer, sections[2]);
-    }
+        if self.reader.mode == ReaderMode::Text {
+            footer_spans.push(Span::raw("  "));
+            footer_spans.push(Span::styled(
+                self.key_bindings.label(KeyAction::CycleTextMode),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" raw/wrap/reflow"));
+
+            footer_spans.push(Span::raw("  "));
+            footer_spans.push(Span::styled(
+                self.key_bindings.label(KeyAction::ToggleTrimHeaders),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" trim-hf"));
+
+            footer_spans.push(Span::raw("  "));
+            footer_spans.push(Span::styled(
+                self.key_bindings.label(KeyAction::ToggleShowTrimmed),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" show-cut"));
 
-    fn draw_search_assign_tab(&self, area: Rect, frame: &mut ratatui::Frame) {
-        let sections = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(6),
-                Constraint::Min(0),
-                Constraint::Length(4),
-            ])
-            .split(area);
+            footer_spans.push(Span::raw("  "));
+            footer_spans.push(Span::styled(
+                self.key_bindings.label(KeyAction::ToggleColumns),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" columns"));
 
-        let (filter_label, filter_value) = match self.assign_labels_panel.focus {
-            AssignFocus::Collections => (
-                "Filter (collections): ",
-                &self.assign_labels_panel.collection_query,
-            ),
-            AssignFocus::Tags => ("Filter (tags): ", &self.assign_labels_panel.tag_query),
-        };
-        let filter_style = if self.assign_labels_panel.query_editing {
-            Style::default()
-                .fg(self.accent_color())
-                .add_modifier(Modifier::BOLD)
-        } else {
-            Style::default()
-        };
+            footer_spans.push(Span::raw("  "));
+            footer_spans.push(Span::styled(
+                format!(
+                    "{}/{}",
+                    self.key_bindings.label(KeyAction::NarrowText),
+                    self.key_bindings.label(KeyAction::WidenText)
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" width"));
 
-        let book_title = self
-            .assign_labels_panel
-            .book_path
-            .as_deref()
-            .and_then(|path| self.ctx.books.iter().find(|b| b.path == path))
-            .map(|b| b.title.clone())
-            .unwrap_or_else(|| "(no selection)".to_string());
+            footer_spans.push(Span::raw("  "));
+            footer_spans.push(Span::styled(
+                format!(
+                    "{}/{}",
+                    self.key_bindings.label(KeyAction::ZoomIn),
+                    self.key_bindings.label(KeyAction::ZoomOut)
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" spacing"));
 
-        let fav = if self.assign_labels_panel.staged_favorite {
-            "yes"
-        } else {
-            "no"
-        };
+            if self.reader.text_overflow() > 0 {
+                footer_spans.push(Span::raw("  "));
+                footer_spans.push(Span::styled(
+                    format!(
+                        "{}/{}",
+                        self.key_bindings.label(KeyAction::PanLeft),
+                        self.key_bindings.label(KeyAction::PanRight)
+                    ),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ));
+                footer_spans.push(Span::raw(" pan-x"));
+            }
+        }
 
-        let mut header_lines = Vec::new();
-        header_lines.push(Line::from(vec![
-            Span::styled("Selected: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(book_title),
-        ]));
-        header_lines.push(Line::from(vec![
-            Span::styled("Favorite: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(fav),
-            Span::raw("  "),
-            Span::raw("(f toggles)"),
-        ]));
-        header_lines.push(Line::from(vec![
-            Span::styled(filter_label, Style::default().add_modifier(Modifier::BOLD)),
-            Span::styled(filter_value.clone(), filter_style),
-            Span::raw("  "),
-            Span::raw("(/ to edit, Ctrl+u clear)"),
-        ]));
+        if self.reader.mode == ReaderMode::Image {
+            footer_spans.push(Span::raw("  "));
+            footer_spans.push(Span::styled(
+                format!(
+                    "{}/{}",
+                    self.key_bindings.label(KeyAction::ZoomIn),
+                    self.key_bindings.label(KeyAction::ZoomOut)
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" zoom  "));
+            footer_spans.push(Span::styled(
+                self.key_bindings.label(KeyAction::ResetZoom),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" reset  "));
+            footer_spans.push(Span::styled(
+                self.key_bindings.label(KeyAction::CycleImageFit),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" fit  "));
+            footer_spans.push(Span::styled(
+                format!(
+                    "{}/{}",
+                    self.key_bindings.label(KeyAction::RotateCounterClockwise),
+                    self.key_bindings.label(KeyAction::RotateClockwise)
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" rotate  "));
+            footer_spans.push(Span::styled(
+                self.key_bindings.label(KeyAction::ToggleInvertColors),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" invert  "));
+            footer_spans.push(Span::styled(
+                format!(
+                    "{}/{}",
+                    self.key_bindings.label(KeyAction::PanLeft),
+                    self.key_bindings.label(KeyAction::PanRight)
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" pan-x  "));
+            footer_spans.push(Span::styled(
+                format!(
+                    "{}/{}",
+                    self.key_bindings.label(KeyAction::PageUp),
+                    self.key_bindings.label(KeyAction::PageDown)
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" page-pan  "));
+            footer_spans.push(Span::styled(
+                self.key_bindings.label(KeyAction::ToggleSpread),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" spread  "));
+            footer_spans.push(Span::styled(
+                self.key_bindings.label(KeyAction::ToggleThumbnails),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            footer_spans.push(Span::raw(" thumbs"));
+            if self.reader.spread {
+                footer_spans.push(Span::raw("  "));
+                footer_spans.push(Span::styled(
+                    self.key_bindings.label(KeyAction::ToggleSpreadCover),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ));
+                footer_spans.push(Span::raw(" cover"));
+            }
+        }
 
-        if let Some(err) = &self.assign_labels_panel.error {
-            header_lines.push(Line::styled(
-                err.clone(),
-                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        if let Some(note) = &self.reader.notice {
+            footer_spans.push(Span::raw("  |  "));
+            footer_spans.push(Span::styled(
+                note.clone(),
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
             ));
-        } else {
-            header_lines.push(Line::raw(""));
         }
-        header_lines.push(Line::raw(""));
 
-        let header = Paragraph::new(Text::from(header_lines))
+        if let Some(saved) = self.saved_flash_span() {
+            footer_spans.insert(0, saved);
+        }
+        let footer = Paragraph::new(Line::from(footer_spans))
+            .alignment(Alignment::Center)
             .wrap(Wrap { trim: true })
-            .alignment(Alignment::Left);
-        frame.render_widget(header, sections[0]);
+            .block(Block::default().borders(Borders::TOP));
+        frame.render_widget(footer, layout[2]);
 
-        let body = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
-            .split(sections[1]);
+        if self.bookmarks_panel.open {
+            self.draw_bookmarks_panel(area, frame);
+        }
+        if self.goto_panel.open {
+            self.draw_goto_panel(area, frame);
+        }
+        if self.export_panel.open {
+            self.draw_export_panel(area, frame);
+        }
+        if self.pdfium_setup.open {
+            self.draw_pdfium_setup_panel(area, frame);
+        }
+        if self.toc_panel.open {
+            self.draw_toc_panel(area, frame);
+        }
+        if self.links_panel.open {
+            self.draw_links_panel(area, frame);
+        }
+        if self.pdf_annotations_panel.open {
+            self.draw_pdf_annotations_panel(area, frame);
+        }
+        if self.notes_panel.open {
+            self.draw_notes_panel(area, frame);
+        }
+        if self.search_in_book_panel.open {
+            self.draw_search_in_book_panel(area, frame);
+        }
+        if self.history_panel.open {
+            self.draw_history_panel(area, frame);
+        }
+    }
 
-        let focused_list_style = Style::default()
-            .fg(Color::Black)
-            .bg(self.accent_color())
-            .add_modifier(Modifier::BOLD);
-        let unfocused_list_style = Style::default().fg(Color::White).bg(Color::Gray);
+    /// Where the current page is in the book, with ticks at bookmarks and chapter starts. Built
+    /// from the bookmarks and TOC already in memory.
+    fn draw_reader_minimap(&self, area: Rect, total: u32, frame: &mut ratatui::Frame) {
+        let rows = minimap_rows(
+            area.height,
+            self.reader.page,
+            total,
+            self.current_bookmarks().iter().map(|b| b.page),
+            self.reader.toc.iter().filter_map(|item| item.page),
+        );
+        let lines: Vec<Line> = rows
+            .into_iter()
+            .map(|mark| {
+                let style = match mark {
+                    MinimapMark::Track => Style::default().add_modifier(Modifier::DIM),
+                    MinimapMark::Chapter => Style::default(),
+                    MinimapMark::Bookmark => Style::default().fg(Color::Yellow),
+                    MinimapMark::Current => Style::default().fg(self.accent_color()),
+                };
+                Line::styled(mark.glyph(), style)
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), area);
+    }
+
+    /// Notes on and around the current page, next to it.
+    fn draw_notes_sidebar(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let accent = self.accent_color();
+        let page = self.reader.page.saturating_add(1);
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Notes near p{page}"));
+        if self.notes_sidebar.focused {
+            block = block.border_style(Style::default().fg(accent));
+        }
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        if inner.width == 0 || inner.height < 2 {
+            return;
+        }
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
 
-        // Collections
-        let collections_focus = self.assign_labels_panel.focus == AssignFocus::Collections;
-        let collections = self.assign_visible_collections();
-        let mut collection_cursor = self.assign_labels_panel.collection_cursor;
-        let collection_max = collections.len() + 1;
-        if collection_max > 0 {
-            collection_cursor = collection_cursor.min(collection_max - 1);
+        let notes = self.current_notes();
+        let listed = notes_sidebar::nearby_notes(&notes, page);
+        // The highlight symbol takes two columns.
+        let width = usize::from(sections[0].width).saturating_sub(2);
+        let items: Vec<ListItem> = if listed.is_empty() {
+            vec![ListItem::new(Line::raw("(no notes nearby)"))]
         } else {
-            collection_cursor = 0;
+            listed
+                .iter()
+                .map(|&idx| {
+                    let note = &notes[idx];
+                    let mut header = Style::default().add_modifier(Modifier::BOLD);
+                    if note.page == page {
+                        header = header.fg(accent);
+                    }
+                    let mut lines =
+                        vec![Line::from(Span::styled(format!("p{}", note.page), header))];
+                    lines.extend(
+                        note.body
+                            .trim()
+                            .lines()
+                            .map(|line| Line::raw(toc::truncate_to_width(line, width))),
+                    );
+                    ListItem::new(lines)
+                })
+                .collect()
+        };
+        let mut list = List::new(items).highlight_spacing(HighlightSpacing::Always);
+        let mut state = ListState::default();
+        if self.notes_sidebar.focused && !listed.is_empty() {
+            list = list
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+                .highlight_symbol("> ");
+            state.select(Some(self.notes_sidebar.selected.min(listed.len() - 1)));
         }
+        frame.render_stateful_widget(list, sections[0], &mut state);
+        frame.render_widget(
+            Paragraph::new(Line::styled(
+                "Tab focus · a add · Enter go",
+                Style::default().add_modifier(Modifier::DIM),
+            )),
+            sections[1],
+        );
+    }
 
-        let mut collection_items = Vec::new();
-        let none_selected = self.assign_labels_panel.staged.collection.is_none();
-        let prefix = if none_selected { "●" } else { " " };
-        collection_items.push(ListItem::new(Line::raw(format!("{prefix} (none)"))));
-        for name in &collections {
-            let selected = self
-                .assign_labels_panel
-                .staged
-                .collection
-                .as_deref()
-                .is_some_and(|c| c.eq_ignore_ascii_case(name));
-            let prefix = if selected { "●" } else { " " };
-            collection_items.push(ListItem::new(Line::raw(format!("{prefix} {name}"))));
+    fn draw_thumbnail_strip(&mut self, area: Rect, frame: &mut ratatui::Frame) {
+        let Some(book) = self.reader.current_book() else {
+            return;
+        };
+        let total = self.reader.total_pages;
+        let accent = self.accent_color();
+        let strip = &mut self.thumbnail_strip;
+        if strip.synced_page != Some(self.reader.page) {
+            strip.follow(self.reader.page);
+        }
+        if strip.protocols_book.as_deref() != Some(book.path.as_str()) {
+            // The worker drops queued thumbnails of the previous book.
+            strip.pending.retain(|(path, _)| *path == book.path);
+            strip.protocols.clear();
+            strip.protocols_book = Some(book.path.clone());
+        }
+
+        let block = Block::default().borders(Borders::ALL).title(format!(
+            "thumbnails · p{} · ←/→ move  Enter jump  Esc close",
+            strip.cursor.saturating_add(1)
+        ));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        if inner.width == 0 || inner.height < 2 {
+            return;
         }
 
-        let collections_title_style = if self.assign_labels_panel.focus == AssignFocus::Collections
-        {
-            Style::default()
-                .fg(self.accent_color())
-                .add_modifier(Modifier::BOLD)
-        } else {
-            Style::default()
-        };
+        // Narrow terminals show fewer pages rather than unreadably small ones.
+        const MIN_SLOT_WIDTH: u16 = 6;
+        let fit = u32::from(inner.width / MIN_SLOT_WIDTH).max(1);
+        let radius = THUMBNAIL_RADIUS.min(fit.saturating_sub(1) / 2);
+        let (first, last) = thumbnail_window(strip.cursor, total, radius);
+        let slots = last - first + 1;
+        let slot_width = inner.width / u16::try_from(slots).unwrap_or(u16::MAX).max(1);
+        let image_size = (slot_width.saturating_sub(1).max(1), inner.height - 1);
+
+        strip
+            .protocols
+            .retain(|page, _| (first..=last).contains(page));
+        for (index, page) in (first..=last).enumerate() {
+            let slot = Rect::new(
+                inner.x + slot_width * index as u16,
+                inner.y,
+                slot_width,
+                inner.height,
+            );
+            let key = (book.path.clone(), page);
+            match strip.images.get(&key) {
+                Some(Some(image)) => {
+                    let stale = strip
+                        .protocols
+                        .get(&page)
+                        .is_none_or(|(size, _)| *size != image_size);
+                    if stale {
+                        let area = Rect::new(0, 0, image_size.0, image_size.1);
+                        match self.image_picker.new_protocol(
+                            (**image).clone(),
+                            area,
+                            Resize::Fit(None),
+                        ) {
+                            Ok(protocol) => {
+                                strip.protocols.insert(page, (image_size, protocol));
+                            }
+                            Err(_) => {
+                                strip.protocols.remove(&page);
+                            }
+                        }
+                    }
+                    if let Some((_, protocol)) = strip.protocols.get(&page) {
+                        let proto_area = protocol.area();
+                        let width = proto_area.width.min(image_size.0);
+                        let height = proto_area.height.min(image_size.1);
+                        let draw_area = Rect::new(
+                            slot.x + (image_size.0 - width) / 2,
+                            slot.y + (image_size.1 - height) / 2,
+                            width,
+                            height,
+                        );
+                        frame.render_widget(ImageWidget::new(protocol), draw_area);
+                    }
+                }
+                Some(None) => {
+                    let area = Rect::new(slot.x, slot.y, image_size.0, image_size.1);
+                    frame.render_widget(Paragraph::new("×").alignment(Alignment::Center), area);
+                }
+                None => {
+                    if strip.pending.insert(key) {
+                        self.page_render.request_thumbnail(ThumbnailJob {
+                            book: book.clone(),
+                            page,
+                        });
+                    }
+                    let area = Rect::new(slot.x, slot.y, image_size.0, image_size.1);
+                    frame.render_widget(Paragraph::new("…").alignment(Alignment::Center), area);
+                }
+            }
 
-        let collections_list = List::new(collection_items)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(if collections_focus {
-                        Style::default().fg(self.accent_color())
-                    } else {
-                        Style::default()
-                    })
-                    .title(Span::styled("Collection", collections_title_style)),
-            )
-            .highlight_style(if collections_focus {
-                focused_list_style
-            } else {
-                unfocused_list_style
-            })
-            .highlight_symbol("> ")
-            .highlight_spacing(HighlightSpacing::Always);
-        let mut collection_state = ListState::default();
-        if collection_max > 0 {
-            collection_state.select(Some(collection_cursor));
+            let mut style = Style::default();
+            if page == self.reader.page {
+                style = style.fg(accent).add_modifier(Modifier::BOLD);
+            }
+            if page == strip.cursor {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            let label_area = Rect::new(slot.x, slot.y + image_size.1, image_size.0, 1);
+            frame.render_widget(
+                Paragraph::new(Span::styled(page.saturating_add(1).to_string(), style))
+                    .alignment(Alignment::Center),
+                label_area,
+            );
         }
-        frame.render_stateful_widget(collections_list, body[0], &mut collection_state);
+    }
+
+    fn draw_search_in_book_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(80, 70, area);
+        frame.render_widget(Clear, popup_area);
 
-        // Tags
-        let tags_focus = self.assign_labels_panel.focus == AssignFocus::Tags;
-        let tags = self.assign_visible_tags();
-        let mut tag_cursor = self.assign_labels_panel.tag_cursor;
-        if !tags.is_empty() {
-            tag_cursor = tag_cursor.min(tags.len() - 1);
+        let panel = &self.search_in_book_panel;
+        let title = if panel.query.is_empty() {
+            "Search in Book".to_string()
         } else {
-            tag_cursor = 0;
-        }
+            format!("Search in Book — {} matches", panel.results.len())
+        };
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        frame.render_widget(block.clone(), popup_area);
 
-        let mut tag_items = Vec::new();
-        if tags.is_empty() {
-            tag_items.push(ListItem::new(Line::raw("(none)")));
+        let inner = block.inner(popup_area);
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(2),
+            ])
+            .split(inner);
+
+        let mut header_lines = Vec::new();
+        let label = Span::styled("Query: ", Style::default().add_modifier(Modifier::BOLD));
+        header_lines.push(if panel.editing {
+            panel.input.labeled_line(label)
         } else {
-            for tag in &tags {
-                let selected = self
-                    .assign_labels_panel
-                    .staged
-                    .tags
-                    .iter()
-                    .any(|t| t.eq_ignore_ascii_case(tag));
-                let prefix = if selected { "[x]" } else { "[ ]" };
-                tag_items.push(ListItem::new(Line::raw(format!("{prefix} {tag}"))));
-            }
+            Line::from(vec![label, Span::raw(panel.input.as_str().to_string())])
+        });
+        let status = match (panel.running, panel.total_pages) {
+            (true, Some(total)) => format!(
+                "searching… page {}/{total}",
+                panel.next_page.saturating_add(1).min(total)
+            ),
+            (true, None) => "searching…".to_string(),
+            (false, _) if panel.query.is_empty() => "Type a query, Enter to search.".to_string(),
+            (false, Some(total)) if panel.next_page < total => format!(
+                "stopped at page {}/{total}",
+                panel.next_page.saturating_add(1)
+            ),
+            (false, _) => "done".to_string(),
+        };
+        header_lines.push(Line::styled(status, Style::default().fg(Color::Cyan)));
+        if let Some(err) = &panel.error {
+            header_lines.push(Line::from(vec![Span::styled(
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )]));
         }
+        frame.render_widget(
+            Paragraph::new(Text::from(header_lines)).wrap(Wrap { trim: true }),
+            sections[0],
+        );
 
-        let tags_title_style = if self.assign_labels_panel.focus == AssignFocus::Tags {
-            Style::default()
-                .fg(self.accent_color())
-                .add_modifier(Modifier::BOLD)
+        let match_style = Style::default()
+            .fg(self.accent_color())
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        let items: Vec<ListItem> = if panel.results.is_empty() {
+            let label = if panel.running || panel.query.is_empty() {
+                ""
+            } else {
+                "(no matches)"
+            };
+            vec![ListItem::new(Line::raw(label))]
         } else {
-            Style::default()
+            panel
+                .results
+                .iter()
+                .map(|hit| {
+                    let snippet = &hit.snippet;
+                    ListItem::new(Line::from(vec![
+                        Span::styled(
+                            format!("p{:<5} ", hit.page),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(snippet[..hit.match_start].to_string()),
+                        Span::styled(
+                            snippet[hit.match_start..hit.match_end].to_string(),
+                            match_style,
+                        ),
+                        Span::raw(snippet[hit.match_end..].to_string()),
+                    ]))
+                })
+                .collect()
         };
 
-        let tags_list = List::new(tag_items)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(if tags_focus {
-                        Style::default().fg(self.accent_color())
-                    } else {
-                        Style::default()
-                    })
-                    .title(Span::styled("Tags", tags_title_style)),
-            )
-            .highlight_style(if tags_focus {
-                focused_list_style
-            } else {
-                unfocused_list_style
-            })
+        let highlight_style = Style::default()
+            .fg(Color::Black)
+            .bg(self.accent_color())
+            .add_modifier(Modifier::BOLD);
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::NONE))
+            .highlight_style(highlight_style)
             .highlight_symbol("> ")
             .highlight_spacing(HighlightSpacing::Always);
-        let mut tag_state = ListState::default();
-        if !tags.is_empty() {
-            tag_state.select(Some(tag_cursor));
+
+        let mut state = ListState::default();
+        if !panel.results.is_empty() && !panel.editing {
+            state.select(Some(panel.selected.min(panel.results.len() - 1)));
         }
-        frame.render_stateful_widget(tags_list, body[1], &mut tag_state);
+        frame.render_stateful_widget(list, sections[1], &mut state);
 
-        let enter_action = if self.assign_labels_panel.query_editing {
-            "done"
+        let esc_action = if panel.running {
+            " cancel  "
         } else {
-            "apply + close"
+            " close  "
         };
-
-        let footer_lines = vec![
-            Line::from(vec![
-                Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" focus  "),
-                Span::styled("↑/↓", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" move"),
-            ]),
-            Line::from(vec![
-                Span::styled("/", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" filter  "),
-                Span::styled("f", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" favorite  "),
-                Span::styled("Space", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" toggle"),
-            ]),
-            Line::from(vec![
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" cancel  "),
-                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(format!(" {enter_action}")),
-            ]),
-        ];
-        let footer = Paragraph::new(Text::from(footer_lines))
-            .wrap(Wrap { trim: true })
-            .alignment(Alignment::Left);
+        let enter_action = if panel.editing {
+            " search  "
+        } else {
+            " jump  "
+        };
+        let footer = Paragraph::new(Line::from(vec![
+            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(esc_action),
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(enter_action),
+            Span::styled("↑/↓", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" select  "),
+            Span::styled("Ctrl+u", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" clear"),
+        ]))
+        .alignment(Alignment::Center);
         frame.render_widget(footer, sections[2]);
     }
 
-    fn draw_label_catalog_input_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
-        let popup_area = centered_rect(70, 35, area);
+    fn draw_goto_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(48, 28, area);
         frame.render_widget(Clear, popup_area);
 
-        let title = match (
-            self.label_catalog_input_panel.mode,
-            self.label_catalog_input_panel.kind,
-        ) {
-            (LabelCatalogInputMode::Create, TagKind::Tag) => "New tag",
-            (LabelCatalogInputMode::Create, TagKind::Collection) => "New collection",
-            (LabelCatalogInputMode::Rename, TagKind::Tag) => "Rename tag",
-            (LabelCatalogInputMode::Rename, TagKind::Collection) => "Rename collection",
+        let current = self.reader.page.saturating_add(1);
+        let now = labeled_page(&self.reader.page_labels, current);
+        let title = match self.reader.total_pages {
+            Some(total) => format!(
+                "Go to page (1..={total}) or c<chapter> · now p{now} ({}%)",
+                page_percent(current, total)
+            ),
+            None => format!("Go to page or c<chapter> · now p{now}"),
         };
 
         let block = Block::default().borders(Borders::ALL).title(Span::styled(
@@ -3455,84 +9278,191 @@ impl Ui {
         frame.render_widget(block.clone(), popup_area);
 
         let inner = block.inner(popup_area);
-        let sections = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(3),
-                Constraint::Min(0),
-                Constraint::Length(5),
-            ])
-            .split(inner);
+        let mut lines = vec![self.goto_panel.input.labeled_line(Span::styled(
+            "Page: ",
+            Style::default().add_modifier(Modifier::BOLD),
+        ))];
+
+        if let Some(total) = self.reader.total_pages.filter(|total| *total > 0) {
+            // Slider readout: `|` marks the current page, `●` where Enter would land.
+            let target = resolve_goto_target(
+                self.goto_panel.input.as_str(),
+                current,
+                Some(total),
+                &self.reader.toc,
+                &self.reader.page_labels,
+            )
+            .ok();
+            let width = usize::from(inner.width.saturating_sub(2)).clamp(10, 60);
+            // A page past `total` (a stale count) is drawn at the end of the slider.
+            let cell = |page: u32| {
+                ((u64::from(page.clamp(1, total) - 1) * (width as u64 - 1))
+                    / u64::from(total.saturating_sub(1).max(1))) as usize
+            };
+            let mut slider: Vec<char> = vec!['─'; width];
+            slider[cell(current)] = '|';
+            if let Some(target) = target {
+                slider[cell(target)] = '●';
+            }
+            lines.push(Line::raw(slider.into_iter().collect::<String>()));
+            lines.push(Line::raw(match target {
+                Some(target) => format!(
+                    "→ p{} ({}%)",
+                    labeled_page(&self.reader.page_labels, target),
+                    page_percent(target, total)
+                ),
+                None => String::new(),
+            }));
+        } else {
+            lines.push(Line::raw(""));
+        }
+        lines.push(Line::raw(if self.reader.page_labels.is_empty() {
+            "12 page, 50% percent, +20/-5 relative, c3 or c intro chapter."
+        } else {
+            "57 or xii printed page, 50% percent, +20/-5 relative, c3 or c intro chapter."
+        }));
+        lines.push(Line::raw("Enter jumps, Esc cancels, Ctrl+u clears."));
+
+        if let Some(err) = &self.goto_panel.error {
+            lines.push(Line::raw(""));
+            lines.push(Line::from(Span::styled(
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )));
+        }
+
+        let paragraph = Paragraph::new(Text::from(lines))
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Left);
+        frame.render_widget(paragraph, inner);
+    }
+
+    fn draw_export_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(56, 32, area);
+        frame.render_widget(Clear, popup_area);
 
-        let prompt = match self.label_catalog_input_panel.kind {
-            TagKind::Tag => "Tag: ",
-            TagKind::Collection => "Collection: ",
+        let current = self.reader.page.saturating_add(1);
+        let title = match self.reader.total_pages {
+            Some(total) => format!("Export pages to PNG (1..={total}) · now p{current}"),
+            None => format!("Export pages to PNG · now p{current}"),
         };
-        let header = Paragraph::new(Line::from(vec![
-            Span::styled(prompt, Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(self.label_catalog_input_panel.input.clone()),
-        ]))
-        .wrap(Wrap { trim: true })
-        .alignment(Alignment::Left);
-        frame.render_widget(header, sections[0]);
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        frame.render_widget(block.clone(), popup_area);
 
-        let mut help_lines = Vec::new();
-        help_lines.push(Line::from(vec![
-            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" apply  "),
-            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" cancel  "),
-            Span::styled("Backspace", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" delete  "),
-            Span::styled("Ctrl+U", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" clear"),
-        ]));
+        let inner = block.inner(popup_area);
+        let panel = &self.export_panel;
+        let field = |label: &'static str, input: &TextInput, focus: ExportField| {
+            let label = Span::styled(label, Style::default().add_modifier(Modifier::BOLD));
+            if panel.focus == focus {
+                input.labeled_line(label)
+            } else {
+                Line::from(vec![label, Span::raw(input.as_str().to_string())])
+            }
+        };
+        let stem = self
+            .reader
+            .book_path
+            .as_deref()
+            .map(bookshelf_core::decode_path)
+            .and_then(|path| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "book".to_string());
+        let mut lines = vec![
+            field("Pages: ", &panel.range, ExportField::Range),
+            field("Folder: ", &panel.dir, ExportField::Dir),
+            field("DPI: ", &panel.dpi, ExportField::Dpi),
+            Line::raw(""),
+            Line::raw(format!(
+                "current, 12 or 12-15. Files are named {}.",
+                export_file_name(&stem, current)
+            )),
+            Line::raw("Tab switches field, Enter exports, Esc cancels."),
+        ];
 
-        if let Some(err) = &self.label_catalog_input_panel.error {
-            help_lines.push(Line::raw(""));
-            help_lines.push(Line::styled(
+        if let Some(err) = &panel.error {
+            lines.push(Line::raw(""));
+            lines.push(Line::from(Span::styled(
                 err.clone(),
                 Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-            ));
+            )));
         }
 
-        let help = Paragraph::new(Text::from(help_lines))
+        let paragraph = Paragraph::new(Text::from(lines))
             .wrap(Wrap { trim: true })
             .alignment(Alignment::Left);
-        frame.render_widget(help, sections[2]);
+        frame.render_widget(paragraph, inner);
     }
 
-    fn draw_bookmarks_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
-        let popup_area = centered_rect(70, 55, area);
+    fn draw_pdfium_setup_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(70, 50, area);
         frame.render_widget(Clear, popup_area);
 
         let block = Block::default().borders(Borders::ALL).title(Span::styled(
-            "Bookmarks",
+            "Image rendering unavailable",
             Style::default().add_modifier(Modifier::BOLD),
         ));
         frame.render_widget(block.clone(), popup_area);
 
         let inner = block.inner(popup_area);
-        let sections = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Min(0), Constraint::Length(2)])
-            .split(inner);
-        let bookmarks = self.current_bookmarks();
-        let items = if bookmarks.is_empty() {
-            vec![ListItem::new(Line::raw("(none)"))]
-        } else {
-            bookmarks
-                .iter()
-                .map(|b| {
-                    let label = if b.label.trim().is_empty() {
-                        format!("Page {}", b.page)
-                    } else {
-                        format!("Page {} — {}", b.page, b.label.trim())
-                    };
-                    ListItem::new(Line::raw(label))
-                })
-                .collect()
-        };
+        let mut lines: Vec<Line> = self
+            .pdfium_setup
+            .lines()
+            .into_iter()
+            .map(Line::raw)
+            .collect();
+        if let Some(status) = &self.pdfium_setup.status {
+            lines.push(Line::raw(""));
+            lines.push(Line::from(Span::styled(
+                status.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )));
+        }
+        lines.push(Line::raw(""));
+        lines.push(Line::from(vec![
+            Span::styled("r", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" re-check  "),
+            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" close"),
+        ]));
+
+        let paragraph = Paragraph::new(Text::from(lines))
+            .wrap(Wrap { trim: false })
+            .alignment(Alignment::Left);
+        frame.render_widget(paragraph, inner);
+    }
+
+    fn draw_book_tag_picker(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let picker = &self.book_tag_picker;
+        let popup_area = centered_rect(40, 40, area);
+        frame.render_widget(Clear, popup_area);
+
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            format!("Filter by a tag of {}", picker.title),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        frame.render_widget(block.clone(), popup_area);
+
+        let inner = block.inner(popup_area);
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(2)])
+            .split(inner);
+
+        let items: Vec<ListItem> = picker
+            .tags
+            .iter()
+            .map(|tag| {
+                let filtered = self
+                    .ctx
+                    .tag_filters
+                    .iter()
+                    .any(|filter| filter.eq_ignore_ascii_case(tag));
+                let mark = if filtered { "[x]" } else { "[ ]" };
+                ListItem::new(Line::raw(format!("{mark} {tag}")))
+            })
+            .collect();
 
         let highlight_style = Style::default()
             .fg(Color::Black)
@@ -3546,8 +9476,8 @@ impl Ui {
             .highlight_spacing(HighlightSpacing::Always);
 
         let mut state = ListState::default();
-        if !bookmarks.is_empty() {
-            state.select(Some(self.bookmarks_panel.selected.min(bookmarks.len() - 1)));
+        if !picker.tags.is_empty() {
+            state.select(Some(picker.selected.min(picker.tags.len() - 1)));
         }
         frame.render_stateful_widget(list, sections[0], &mut state);
 
@@ -3555,27 +9485,19 @@ impl Ui {
             Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(" close  "),
             Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" jump  "),
-            Span::styled("a", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" add current  "),
-            Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" delete"),
+            Span::raw(" filter by it (again: stop)"),
         ]))
-        .alignment(Alignment::Center);
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::TOP));
         frame.render_widget(footer, sections[1]);
     }
 
-    fn draw_notes_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
-        let popup_area = centered_rect(80, 60, area);
+    fn draw_history_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(60, 45, area);
         frame.render_widget(Clear, popup_area);
 
-        let title = if self.notes_panel.input_open {
-            "Notes — Add"
-        } else {
-            "Notes"
-        };
         let block = Block::default().borders(Borders::ALL).title(Span::styled(
-            title,
+            "Recently opened",
             Style::default().add_modifier(Modifier::BOLD),
         ));
         frame.render_widget(block.clone(), popup_area);
@@ -3583,50 +9505,39 @@ impl Ui {
         let inner = block.inner(popup_area);
         let sections = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(4),
-                Constraint::Min(0),
-                Constraint::Length(2),
-            ])
+            .constraints([Constraint::Min(0), Constraint::Length(2)])
             .split(inner);
 
-        let mut header_lines = Vec::new();
-        if self.notes_panel.input_open {
-            header_lines.push(Line::from(vec![
-                Span::styled("Page: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(self.notes_panel.input_page.to_string()),
-            ]));
-            header_lines.push(Line::from(vec![
-                Span::styled("Text: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(self.notes_panel.input.clone()),
-            ]));
-        } else {
-            header_lines.push(Line::raw("Notes are single-line for now."));
-            header_lines.push(Line::raw("Use 'a' to add a note for the current page."));
-        }
-        if let Some(err) = &self.notes_panel.error {
-            header_lines.push(Line::from(vec![Span::styled(
-                err.clone(),
-                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-            )]));
-        }
-        let header = Paragraph::new(Text::from(header_lines)).wrap(Wrap { trim: true });
-        frame.render_widget(header, sections[0]);
-
-        let notes = self.current_notes();
-        let items = if notes.is_empty() {
-            vec![ListItem::new(Line::raw("(none)"))]
+        let recent = &self.ctx.recent_paths;
+        let items = if recent.is_empty() {
+            vec![ListItem::new(Line::raw("(no books opened yet)"))]
         } else {
-            notes
+            recent
                 .iter()
-                .map(|n| {
-                    let body = n.body.trim();
-                    let label = if body.is_empty() {
-                        format!("Page {}", n.page)
+                .map(|path| {
+                    let book = self.ctx.books.iter().find(|b| b.path == *path);
+                    let title = book
+                        .map_or_else(|| bookshelf_core::display_path(path), |b| b.title.clone());
+                    let opened = format_last_opened(
+                        self.ctx
+                            .opened_at_by_path
+                            .get(path)
+                            .copied()
+                            .or(book.and_then(|b| b.last_opened)),
+                    );
+                    let current = if self.reader.book_path.as_deref() == Some(path.as_str()) {
+                        " (reading)"
                     } else {
-                        format!("Page {} — {}", n.page, body)
+                        ""
                     };
-                    ListItem::new(Line::raw(label))
+                    let mut spans = vec![
+                        Span::raw(format!("{title}{current}  ")),
+                        Span::styled(opened, Style::default().fg(Color::Gray)),
+                    ];
+                    if self.missing_paths.contains(path) {
+                        spans.push(Span::raw(" (missing)"));
+                    }
+                    ListItem::new(Line::from(spans))
                 })
                 .collect()
         };
@@ -3643,56 +9554,35 @@ impl Ui {
             .highlight_spacing(HighlightSpacing::Always);
 
         let mut state = ListState::default();
-        if !notes.is_empty() {
-            state.select(Some(self.notes_panel.selected.min(notes.len() - 1)));
+        if !recent.is_empty() {
+            state.select(Some(self.history_panel.selected.min(recent.len() - 1)));
         }
-        frame.render_stateful_widget(list, sections[1], &mut state);
+        frame.render_stateful_widget(list, sections[0], &mut state);
 
-        let footer_spans = if self.notes_panel.input_open {
-            vec![
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" cancel  "),
-                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" save"),
-            ]
-        } else {
-            vec![
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" close  "),
-                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" jump  "),
-                Span::styled("a", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" add  "),
-                Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" delete"),
-            ]
-        };
+        let mut footer_spans = vec![
+            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" close  "),
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" open"),
+        ];
+        if let Some(err) = &self.history_panel.error {
+            footer_spans.push(Span::raw("  |  "));
+            footer_spans.push(Span::styled(
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+        }
         let footer = Paragraph::new(Line::from(footer_spans)).alignment(Alignment::Center);
-        frame.render_widget(footer, sections[2]);
+        frame.render_widget(footer, sections[1]);
     }
 
-    fn draw_toc_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+    fn draw_notes_search_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
         let popup_area = centered_rect(80, 70, area);
         frame.render_widget(Clear, popup_area);
 
-        let visible = self.toc_visible_indices();
-
-        let title = match self.toc_panel.error.as_deref() {
-            Some(_) => "Table of Contents (error)".to_string(),
-            None => {
-                if self.toc_panel.query.trim().is_empty() {
-                    format!("Table of Contents — {}", self.toc_panel.items.len())
-                } else {
-                    format!(
-                        "Table of Contents — {}/{}",
-                        visible.len(),
-                        self.toc_panel.items.len()
-                    )
-                }
-            }
-        };
+        let panel = &self.notes_search_panel;
         let block = Block::default().borders(Borders::ALL).title(Span::styled(
-            title,
+            "Search notes and bookmarks",
             Style::default().add_modifier(Modifier::BOLD),
         ));
         frame.render_widget(block.clone(), popup_area);
@@ -3701,44 +9591,72 @@ impl Ui {
         let sections = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(3),
+                Constraint::Length(2),
                 Constraint::Min(0),
                 Constraint::Length(2),
             ])
             .split(inner);
 
-        let mut header_lines = Vec::new();
-        header_lines.push(Line::raw("↑/↓ select, Enter jump, Esc close."));
-        header_lines.push(Line::from(vec![
-            Span::styled("Filter: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(self.toc_panel.query.clone()),
-        ]));
-        if let Some(err) = &self.toc_panel.error {
-            header_lines.push(Line::from(vec![Span::styled(
-                err.clone(),
-                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-            )]));
-        }
+        let label = Span::styled("Query: ", Style::default().add_modifier(Modifier::BOLD));
+        let status = if panel.input.as_str().trim().is_empty() {
+            "Type to search every book's notes and bookmark labels.".to_string()
+        } else if panel.total > panel.hits.len() {
+            format!(
+                "showing {} of {} matches",
+                NOTES_SEARCH_MAX_HITS, panel.total
+            )
+        } else {
+            format!("{} matches", panel.total)
+        };
         frame.render_widget(
-            Paragraph::new(Text::from(header_lines)).wrap(Wrap { trim: true }),
+            Paragraph::new(Text::from(vec![
+                panel.input.labeled_line(label),
+                Line::styled(status, Style::default().fg(Color::Cyan)),
+            ])),
             sections[0],
         );
 
-        let items: Vec<ListItem> = if self.toc_panel.items.is_empty() {
-            vec![ListItem::new(Line::raw("(no outline found)"))]
-        } else if !self.toc_panel.query.trim().is_empty() && visible.is_empty() {
-            vec![ListItem::new(Line::raw("(no matches)"))]
+        let match_style = Style::default()
+            .fg(self.accent_color())
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        let items: Vec<ListItem> = if panel.hits.is_empty() {
+            let label = if panel.input.as_str().trim().is_empty() {
+                ""
+            } else {
+                "(no matches)"
+            };
+            vec![ListItem::new(Line::raw(label))]
         } else {
-            visible
+            panel
+                .hits
                 .iter()
-                .filter_map(|idx| self.toc_panel.items.get(*idx))
-                .map(|item| {
-                    let indent = "  ".repeat(item.depth.min(12));
-                    let page = item
-                        .page
-                        .map(|p| format!("p{p}"))
-                        .unwrap_or_else(|| "-".to_string());
-                    ListItem::new(Line::raw(format!("{indent}{}  [{page}]", item.title)))
+                .map(|hit| {
+                    let snippet = &hit.snippet;
+                    let mut spans = vec![
+                        Span::styled(
+                            hit.title.clone(),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(format!(" — p{} — ", hit.page)),
+                    ];
+                    if hit.kind == NoteHitKind::Bookmark {
+                        spans.push(Span::styled(
+                            "[bookmark] ",
+                            Style::default().fg(Color::Gray),
+                        ));
+                    }
+                    spans.extend([
+                        Span::raw(snippet[..hit.match_start].to_string()),
+                        Span::styled(
+                            snippet[hit.match_start..hit.match_end].to_string(),
+                            match_style,
+                        ),
+                        Span::raw(snippet[hit.match_end..].to_string()),
+                    ]);
+                    if self.missing_paths.contains(&hit.path) {
+                        spans.push(Span::raw(" (missing)"));
+                    }
+                    ListItem::new(Line::from(spans))
                 })
                 .collect()
         };
@@ -3755,287 +9673,303 @@ impl Ui {
             .highlight_spacing(HighlightSpacing::Always);
 
         let mut state = ListState::default();
-        if !visible.is_empty() {
-            state.select(Some(self.toc_panel.selected.min(visible.len() - 1)));
+        if !panel.hits.is_empty() {
+            state.select(Some(panel.selected.min(panel.hits.len() - 1)));
         }
         frame.render_stateful_widget(list, sections[1], &mut state);
 
-        let footer = Paragraph::new(Line::from(vec![
+        let mut footer_spans = vec![
             Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(" close  "),
             Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" jump  "),
+            Span::raw(" open  "),
+            Span::styled("↑/↓", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" select  "),
             Span::styled("Ctrl+u", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(" clear"),
-        ]))
-        .alignment(Alignment::Center);
+        ];
+        if let Some(err) = &panel.error {
+            footer_spans.push(Span::raw("  |  "));
+            footer_spans.push(Span::styled(
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+        }
+        let footer = Paragraph::new(Line::from(footer_spans)).alignment(Alignment::Center);
         frame.render_widget(footer, sections[2]);
     }
 
-    fn draw_reader(&mut self, area: Rect, frame: &mut ratatui::Frame) {
-        if self.ctx.settings.reader_mode == ReaderMode::Image
-            && !image_protocol::image_supported(&self.image_picker)
-        {
-            self.ctx.settings.reader_mode = ReaderMode::Text;
-            self.reader.current_image = None;
-            self.reader.render_key = None;
-            self.reader.notice =
-                Some("image protocol not detected; image mode disabled".to_string());
-        }
-
-        let layout = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(3),
-                Constraint::Min(0),
-                Constraint::Length(2),
-            ])
-            .split(area);
-
-        let title_text = match &self.reader.book_title {
-            Some(title) => format!("Reader — {title}"),
-            None => "Reader".to_string(),
-        };
-
-        if self.ctx.settings.reader_mode == ReaderMode::Image {
-            image_protocol::prefer_kitty_if_supported(&mut self.image_picker);
-        }
-
-        let page_title = {
-            let page = self.reader.page.saturating_add(1);
-            let page_part = if let Some(total) = self.reader.total_pages {
-                format!("p{page}/{total}")
-            } else {
-                format!("p{page}")
-            };
-
-            let mode_part = match self.ctx.settings.reader_mode {
-                ReaderMode::Text => self.ctx.settings.reader_text_mode.to_string(),
-                ReaderMode::Image => {
-                    let (fw, fh) = self.image_picker.font_size();
-                    format!(
-                        "{} {}% · {}x{}px",
-                        image_protocol::protocol_label(&self.image_picker),
-                        self.reader.image_zoom_percent,
-                        fw,
-                        fh
-                    )
-                }
-            };
-
-            format!("{page_part} · {mode_part}")
-        };
+    fn draw_duplicates_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(80, 60, area);
+        frame.render_widget(Clear, popup_area);
 
-        let header = Paragraph::new(Line::from(vec![Span::styled(
-            title_text,
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            format!(
+                "Duplicate books ({} groups)",
+                self.duplicates_panel.groups.len()
+            ),
             Style::default().add_modifier(Modifier::BOLD),
-        )]))
-        .alignment(Alignment::Center)
-        .wrap(Wrap { trim: true })
-        .block(Block::default().borders(Borders::BOTTOM));
-        frame.render_widget(header, layout[0]);
-
-        let inner_width = layout[1].width.saturating_sub(2);
-        let inner_height = layout[1].height.saturating_sub(2);
-        self.reader.ensure_rendered(
-            &self.ctx,
-            &self.engine,
-            &self.image_picker,
-            inner_width,
-            inner_height,
-        );
+        ));
+        frame.render_widget(block.clone(), popup_area);
 
-        if self.ctx.settings.reader_mode == ReaderMode::Image {
-            let block = Block::default().borders(Borders::ALL).title(page_title);
-            frame.render_widget(block.clone(), layout[1]);
-            let inner = block.inner(layout[1]);
+        let inner = block.inner(popup_area);
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(2)])
+            .split(inner);
 
-            if let Some(protocol) = self.reader.current_image.as_ref() {
-                let proto_area = protocol.area();
-                let draw_width = proto_area.width.min(inner.width);
-                let draw_height = proto_area.height.min(inner.height);
-                let draw_area = Rect::new(
-                    inner.x + inner.width.saturating_sub(draw_width) / 2,
-                    inner.y + inner.height.saturating_sub(draw_height) / 2,
-                    draw_width,
-                    draw_height,
-                );
-                frame.render_widget(ImageWidget::new(protocol), draw_area);
-            } else {
-                let content = self.reader.current_text.clone().unwrap_or_else(|| {
-                    self.reader
-                        .last_error
-                        .clone()
-                        .unwrap_or_else(|| "loading...".to_string())
-                });
-                let text = Text::from(
-                    content
-                        .lines()
-                        .skip(self.reader.scroll as usize)
-                        .map(|line| Line::raw(line.to_string()))
-                        .collect::<Vec<_>>(),
-                );
-                frame.render_widget(Paragraph::new(text).wrap(Wrap { trim: false }), inner);
+        // A header row per group, then one row per copy; only copies can be selected.
+        let mut items = Vec::new();
+        let mut selected_row = None;
+        let mut copy_idx = 0;
+        for group in &self.duplicates_panel.groups {
+            let size = group
+                .first()
+                .and_then(|path| self.ctx.fingerprints_by_path.get(path))
+                .map(|fingerprint| format_bytes(fingerprint.size_bytes))
+                .unwrap_or_default();
+            items.push(ListItem::new(Line::styled(
+                format!("{} copies · {size}", group.len()),
+                Style::default()
+                    .fg(Color::Gray)
+                    .add_modifier(Modifier::BOLD),
+            )));
+            for path in group {
+                if copy_idx == self.duplicates_panel.selected {
+                    selected_row = Some(items.len());
+                }
+                copy_idx += 1;
+                let mut details = Vec::new();
+                if let Some(progress) = self.ctx.progress_by_path.get(path) {
+                    details.push(format!("p{}", progress.last_page));
+                }
+                let notes = self.ctx.notes_by_path.get(path).map_or(0, Vec::len);
+                if notes > 0 {
+                    details.push(format!("{notes} notes"));
+                }
+                let bookmarks = self.ctx.bookmarks_by_path.get(path).map_or(0, Vec::len);
+                if bookmarks > 0 {
+                    details.push(format!("{bookmarks} bookmarks"));
+                }
+                if self
+                    .ctx
+                    .books
+                    .iter()
+                    .any(|book| book.path == *path && book.archived)
+                {
+                    details.push("archived".to_string());
+                }
+                let mut spans = vec![Span::raw(format!(
+                    "  {}",
+                    bookshelf_core::display_path(path)
+                ))];
+                if !details.is_empty() {
+                    spans.push(Span::styled(
+                        format!("  {}", details.join(", ")),
+                        Style::default().fg(Color::Gray),
+                    ));
+                }
+                items.push(ListItem::new(Line::from(spans)));
             }
-        } else {
-            let content = self.reader.current_text.clone().unwrap_or_else(|| {
-                self.reader
-                    .last_error
-                    .clone()
-                    .unwrap_or_else(|| "loading...".to_string())
-            });
-            let text = Text::from(
-                content
-                    .lines()
-                    .skip(self.reader.scroll as usize)
-                    .map(|line| Line::raw(line.to_string()))
-                    .collect::<Vec<_>>(),
-            );
-            let body = Paragraph::new(text)
-                .block(Block::default().borders(Borders::ALL).title(page_title));
-            frame.render_widget(body, layout[1]);
         }
 
-        let up_down_label = if self.ctx.settings.reader_mode == ReaderMode::Image {
-            "pan-y"
-        } else {
-            "scroll"
-        };
+        let highlight_style = Style::default()
+            .fg(Color::Black)
+            .bg(self.accent_color())
+            .add_modifier(Modifier::BOLD);
+        let list = List::new(items)
+            .highlight_style(highlight_style)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+        let mut state = ListState::default();
+        state.select(selected_row);
+        frame.render_stateful_widget(list, sections[0], &mut state);
 
         let mut footer_spans = vec![
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" keep this, archive the rest  "),
+            Span::styled("r", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" keep this, remove the rest  "),
             Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" back  "),
-            Span::styled("←/→", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" page  "),
-            Span::styled("↑/↓", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(format!(" {up_down_label}  ")),
-            Span::styled("g", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" goto  "),
-            Span::styled("t", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" toc  "),
-            Span::styled("b", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" bookmarks  "),
-            Span::styled("n", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" notes  "),
-            Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" dump"),
+            Span::raw(" close"),
         ];
-
-        let image_ok = image_protocol::image_supported(&self.image_picker);
-        if self.ctx.settings.reader_mode == ReaderMode::Image || image_ok {
-            footer_spans.push(Span::raw("  "));
+        if let Some(err) = &self.duplicates_panel.error {
+            footer_spans.push(Span::raw("  |  "));
             footer_spans.push(Span::styled(
-                "m",
-                Style::default().add_modifier(Modifier::BOLD),
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
             ));
-            footer_spans.push(Span::raw(" mode"));
         }
+        let footer = Paragraph::new(Line::from(footer_spans))
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Center);
+        frame.render_widget(footer, sections[1]);
+    }
 
-        if self.ctx.settings.reader_mode == ReaderMode::Text && !image_ok {
-            footer_spans.push(Span::raw("  "));
-            footer_spans.push(Span::styled(
-                "k",
-                Style::default().add_modifier(Modifier::BOLD),
-            ));
-            footer_spans.push(Span::raw(" kitty-reader"));
-        }
+    fn draw_backups_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(70, 50, area);
+        frame.render_widget(Clear, popup_area);
 
-        if self.ctx.settings.reader_mode == ReaderMode::Text {
-            footer_spans.push(Span::raw("  "));
-            footer_spans.push(Span::styled(
-                "r",
-                Style::default().add_modifier(Modifier::BOLD),
-            ));
-            footer_spans.push(Span::raw(" raw/wrap/reflow"));
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            format!("Restore from backup ({})", self.backups_panel.backups.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        frame.render_widget(block.clone(), popup_area);
 
-            footer_spans.push(Span::raw("  "));
-            footer_spans.push(Span::styled(
-                "h",
-                Style::default().add_modifier(Modifier::BOLD),
-            ));
-            footer_spans.push(Span::raw(" trim-hf"));
-        }
+        let inner = block.inner(popup_area);
+        let sections = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(2)])
+            .split(inner);
 
-        if self.ctx.settings.reader_mode == ReaderMode::Image {
-            footer_spans.push(Span::raw("  "));
-            footer_spans.push(Span::styled(
-                "+/-",
-                Style::default().add_modifier(Modifier::BOLD),
-            ));
-            footer_spans.push(Span::raw(" zoom  "));
-            footer_spans.push(Span::styled(
-                "0",
-                Style::default().add_modifier(Modifier::BOLD),
-            ));
-            footer_spans.push(Span::raw(" reset  "));
-            footer_spans.push(Span::styled(
-                "Shift+←/→",
-                Style::default().add_modifier(Modifier::BOLD),
-            ));
-            footer_spans.push(Span::raw(" pan-x  "));
-            footer_spans.push(Span::styled(
-                "PgUp/PgDn",
+        let items: Vec<ListItem> = self
+            .backups_panel
+            .backups
+            .iter()
+            .map(|backup| ListItem::new(Line::raw(backup_label(backup))))
+            .collect();
+        let highlight_style = Style::default()
+            .fg(Color::Black)
+            .bg(self.accent_color())
+            .add_modifier(Modifier::BOLD);
+        let list = List::new(items)
+            .highlight_style(highlight_style)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+        let mut state = ListState::default();
+        state.select(
+            (!self.backups_panel.backups.is_empty()).then_some(self.backups_panel.selected),
+        );
+        frame.render_stateful_widget(list, sections[0], &mut state);
+
+        let footer_lines = match self.backups_panel.selected_backup() {
+            Some(backup) if self.backups_panel.confirm => vec![
+                Line::from(Span::styled(
+                    format!(
+                        "Replace the library with {}? Changes since then are lost.",
+                        backup_label(backup)
+                    ),
+                    Style::default().fg(Color::Yellow),
+                )),
+                Line::from(vec![
+                    Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" restore  "),
+                    Span::styled("n/Esc", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" cancel"),
+                ]),
+            ],
+            _ => {
+                let mut spans = vec![
+                    Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" restore  "),
+                    Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" close"),
+                ];
+                if let Some(err) = &self.backups_panel.error {
+                    spans.push(Span::raw("  |  "));
+                    spans.push(Span::styled(
+                        err.clone(),
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ));
+                }
+                vec![Line::from(spans)]
+            }
+        };
+        let footer = Paragraph::new(footer_lines)
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Center);
+        frame.render_widget(footer, sections[1]);
+    }
+
+    fn draw_relink_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(70, 30, area);
+        frame.render_widget(Clear, popup_area);
+
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            "Relink missing book",
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        frame.render_widget(block.clone(), popup_area);
+
+        let inner = block.inner(popup_area);
+        let old_path = self
+            .relink_panel
+            .path
+            .as_deref()
+            .map(bookshelf_core::display_path)
+            .unwrap_or_default();
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled("Was: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(old_path),
+            ]),
+            self.relink_panel.input.labeled_line(Span::styled(
+                "New path: ",
                 Style::default().add_modifier(Modifier::BOLD),
-            ));
-            footer_spans.push(Span::raw(" page-pan"));
-        }
+            )),
+            Line::raw(""),
+            Line::raw("Progress, bookmarks, notes and labels move to the new file."),
+            Line::raw("Enter relinks, Esc cancels, Ctrl+u clears."),
+        ];
 
-        if let Some(note) = &self.reader.notice {
-            footer_spans.push(Span::raw("  |  "));
-            footer_spans.push(Span::styled(
-                note.clone(),
-                Style::default()
-                    .fg(Color::Green)
-                    .add_modifier(Modifier::BOLD),
-            ));
+        if let Some(err) = &self.relink_panel.error {
+            lines.push(Line::raw(""));
+            lines.push(Line::from(Span::styled(
+                err.clone(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )));
         }
 
-        let footer = Paragraph::new(Line::from(footer_spans))
-            .alignment(Alignment::Center)
+        let paragraph = Paragraph::new(Text::from(lines))
             .wrap(Wrap { trim: true })
-            .block(Block::default().borders(Borders::TOP));
-        frame.render_widget(footer, layout[2]);
-
-        if self.bookmarks_panel.open {
-            self.draw_bookmarks_panel(area, frame);
-        }
-        if self.goto_panel.open {
-            self.draw_goto_panel(area, frame);
-        }
-        if self.toc_panel.open {
-            self.draw_toc_panel(area, frame);
-        }
-        if self.notes_panel.open {
-            self.draw_notes_panel(area, frame);
-        }
+            .alignment(Alignment::Left);
+        frame.render_widget(paragraph, inner);
     }
 
-    fn draw_goto_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
-        let popup_area = centered_rect(48, 28, area);
+    fn draw_delete_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
+        let popup_area = centered_rect(60, 30, area);
         frame.render_widget(Clear, popup_area);
 
-        let title = match self.reader.total_pages {
-            Some(total) => format!("Go to page (1..={total})"),
-            None => "Go to page".to_string(),
-        };
-
         let block = Block::default().borders(Borders::ALL).title(Span::styled(
-            title,
+            "Delete book",
             Style::default().add_modifier(Modifier::BOLD),
         ));
         frame.render_widget(block.clone(), popup_area);
 
         let inner = block.inner(popup_area);
+        let path = self.delete_panel.path.as_deref().unwrap_or_default();
+        let title = self
+            .ctx
+            .books
+            .iter()
+            .find(|b| b.path == path)
+            .map(|b| b.title.clone())
+            .unwrap_or_default();
+        let key = |label: &'static str| {
+            Span::styled(label, Style::default().add_modifier(Modifier::BOLD))
+        };
         let mut lines = vec![
+            Line::from(Span::styled(
+                title,
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Line::raw(bookshelf_core::display_path(path)),
+            Line::raw(""),
             Line::from(vec![
-                Span::styled("Page: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(self.goto_panel.input.clone()),
+                key("r"),
+                Span::raw(" remove from library (progress, bookmarks, notes and labels go too)"),
             ]),
-            Line::raw(""),
-            Line::raw("Enter jumps, Esc cancels, Ctrl+u clears."),
+            Line::from(vec![
+                key("D"),
+                Span::styled(
+                    " also delete the file from disk",
+                    Style::default().fg(Color::Red),
+                ),
+            ]),
+            Line::from(vec![key("Esc"), Span::raw(" cancel")]),
         ];
 
-        if let Some(err) = &self.goto_panel.error {
+        if let Some(err) = &self.delete_panel.error {
             lines.push(Line::raw(""));
             lines.push(Line::from(Span::styled(
                 err.clone(),
@@ -4049,8 +9983,23 @@ impl Ui {
         frame.render_widget(paragraph, inner);
     }
 
+    /// A settings row that exports or imports the settings, showing the file being typed.
+    fn settings_file_item(&self, action: SettingsFileAction, label: &str) -> ListItem<'static> {
+        match &self.settings_panel.file_input {
+            Some((typing, input)) if *typing == action => {
+                let mut spans = vec![Span::styled(
+                    format!("{label}: "),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )];
+                spans.extend(input.spans(Style::default().fg(self.accent_color())));
+                ListItem::new(Line::from(spans))
+            }
+            _ => ListItem::new(Line::raw(label.to_string())),
+        }
+    }
+
     fn draw_settings_panel(&self, area: Rect, frame: &mut ratatui::Frame) {
-        let popup_area = centered_rect(45, 30, area);
+        let popup_area = centered_rect(45, 60, area);
         frame.render_widget(Clear, popup_area);
 
         let block = Block::default().borders(Borders::ALL).title(Span::styled(
@@ -4063,7 +10012,7 @@ impl Ui {
         let inner = block.inner(popup_area);
         let sections = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .constraints([Constraint::Min(0), Constraint::Length(4)])
             .split(inner);
 
         let highlight_style = Style::default()
@@ -4073,7 +10022,110 @@ impl Ui {
 
         let kitty_quality_row_selected =
             self.settings_panel.selected == SETTINGS_MENU_KITTY_IMAGE_QUALITY;
+        let filter_row_selected = self.settings_panel.selected == SETTINGS_MENU_DOWNSCALE_FILTER;
+        let mut filter_spans = vec![Span::styled(
+            "Downscale filter: ",
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+        for (idx, filter) in [
+            DownscaleFilter::Auto,
+            DownscaleFilter::Nearest,
+            DownscaleFilter::Triangle,
+            DownscaleFilter::CatmullRom,
+            DownscaleFilter::Lanczos3,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if idx > 0 {
+                filter_spans.push(Span::raw(" "));
+            }
+            filter_spans.push(option_chip(
+                filter.as_str(),
+                self.ctx.settings.downscale_filter == filter,
+                filter_row_selected,
+            ));
+        }
+        let auto_crop_row_selected = self.settings_panel.selected == SETTINGS_MENU_AUTO_CROP;
+        let cache_row_selected = self.settings_panel.selected == SETTINGS_MENU_IMAGE_CACHE_LIMIT;
+        let remote_row_selected = self.settings_panel.selected == SETTINGS_MENU_REMOTE_MODE;
+        let sample_row_selected =
+            self.settings_panel.selected == SETTINGS_MENU_FURNITURE_SAMPLE_PAGES;
+        let fraction_row_selected =
+            self.settings_panel.selected == SETTINGS_MENU_FURNITURE_MIN_FRACTION;
+        let mut sample_spans = vec![Span::styled(
+            "Header/footer sample pages: ",
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+        for (idx, pages) in FURNITURE_SAMPLE_PAGE_CHOICES.iter().enumerate() {
+            if idx > 0 {
+                sample_spans.push(Span::raw(" "));
+            }
+            sample_spans.push(option_chip(
+                &pages.to_string(),
+                self.ctx.settings.furniture_sample_pages == *pages,
+                sample_row_selected,
+            ));
+        }
+        let mut fraction_spans = vec![Span::styled(
+            "Header/footer repeats on: ",
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+        for (idx, fraction) in FURNITURE_MIN_FRACTION_CHOICES.iter().enumerate() {
+            if idx > 0 {
+                fraction_spans.push(Span::raw(" "));
+            }
+            fraction_spans.push(option_chip(
+                &format!("{:.0}%", fraction * 100.0),
+                self.ctx.settings.furniture_min_fraction == *fraction,
+                fraction_row_selected,
+            ));
+        }
         let theme_row_selected = self.settings_panel.selected == SETTINGS_MENU_THEME;
+        let watch_row_selected = self.settings_panel.selected == SETTINGS_MENU_WATCH_LIBRARY;
+        let fingerprint_row_selected =
+            self.settings_panel.selected == SETTINGS_MENU_FINGERPRINT_BOOKS;
+        let decorations_row_selected =
+            self.settings_panel.selected == SETTINGS_MENU_LIBRARY_DECORATIONS;
+        let resume_row_selected = self.settings_panel.selected == SETTINGS_MENU_RESUME_LAST_BOOK;
+        let mouse_row_selected = self.settings_panel.selected == SETTINGS_MENU_MOUSE_CAPTURE;
+        let minimap_row_selected = self.settings_panel.selected == SETTINGS_MENU_READER_MINIMAP;
+        let tick_row_selected = self.settings_panel.selected == SETTINGS_MENU_TICK_RATE;
+        let mut tick_spans = vec![Span::styled(
+            "Tick rate: ",
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+        for (idx, tick_rate_ms) in TICK_RATE_MS_CHOICES.iter().enumerate() {
+            if idx > 0 {
+                tick_spans.push(Span::raw(" "));
+            }
+            tick_spans.push(option_chip(
+                &format!("{tick_rate_ms}ms"),
+                self.ctx.settings.tick_rate_ms == *tick_rate_ms,
+                tick_row_selected,
+            ));
+        }
+        let autosave_row_selected = self.settings_panel.selected == SETTINGS_MENU_AUTOSAVE;
+        let confirm_quit_row_selected = self.settings_panel.selected == SETTINGS_MENU_CONFIRM_QUIT;
+        let mut autosave_spans = vec![Span::styled(
+            "Autosave: ",
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+        for (idx, autosave_secs) in AUTOSAVE_SECS_CHOICES.iter().enumerate() {
+            if idx > 0 {
+                autosave_spans.push(Span::raw(" "));
+            }
+            let label = match autosave_secs {
+                0 => "off".to_string(),
+                secs if secs % 60 == 0 => format!("{}m", secs / 60),
+                secs => format!("{secs}s"),
+            };
+            autosave_spans.push(option_chip(
+                &label,
+                self.ctx.settings.autosave_secs == *autosave_secs,
+                autosave_row_selected,
+            ));
+        }
         let items = vec![
             ListItem::new(Line::raw("Scan Paths")),
             ListItem::new(Line::from(vec![
@@ -4082,23 +10134,129 @@ impl Ui {
                     Style::default().add_modifier(Modifier::BOLD),
                 ),
                 option_chip(
-                    "fast",
-                    self.ctx.settings.kitty_image_quality == KittyImageQuality::Fast,
-                    kitty_quality_row_selected,
+                    "fast",
+                    self.ctx.settings.kitty_image_quality == KittyImageQuality::Fast,
+                    kitty_quality_row_selected,
+                ),
+                Span::raw(" "),
+                option_chip(
+                    "balanced",
+                    self.ctx.settings.kitty_image_quality == KittyImageQuality::Balanced,
+                    kitty_quality_row_selected,
+                ),
+                Span::raw(" "),
+                option_chip(
+                    "sharp",
+                    self.ctx.settings.kitty_image_quality == KittyImageQuality::Sharp,
+                    kitty_quality_row_selected,
+                ),
+                Span::raw(" "),
+                option_chip(
+                    "auto",
+                    self.ctx.settings.kitty_image_quality == KittyImageQuality::Auto,
+                    kitty_quality_row_selected,
+                ),
+            ])),
+            ListItem::new(Line::from(filter_spans)),
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    "Auto-crop margins: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                option_chip(
+                    "off",
+                    self.ctx.settings.auto_crop == AutoCrop::Off,
+                    auto_crop_row_selected,
+                ),
+                Span::raw(" "),
+                option_chip(
+                    "light",
+                    self.ctx.settings.auto_crop == AutoCrop::Light,
+                    auto_crop_row_selected,
+                ),
+                Span::raw(" "),
+                option_chip(
+                    "aggressive",
+                    self.ctx.settings.auto_crop == AutoCrop::Aggressive,
+                    auto_crop_row_selected,
+                ),
+            ])),
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    "Page image cache: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                option_chip(
+                    "64 MB",
+                    self.ctx.settings.image_cache_limit == ImageCacheLimit::Small,
+                    cache_row_selected,
                 ),
                 Span::raw(" "),
                 option_chip(
-                    "balanced",
-                    self.ctx.settings.kitty_image_quality == KittyImageQuality::Balanced,
-                    kitty_quality_row_selected,
+                    "256 MB",
+                    self.ctx.settings.image_cache_limit == ImageCacheLimit::Medium,
+                    cache_row_selected,
                 ),
                 Span::raw(" "),
                 option_chip(
-                    "sharp",
-                    self.ctx.settings.kitty_image_quality == KittyImageQuality::Sharp,
-                    kitty_quality_row_selected,
+                    "1 GB",
+                    self.ctx.settings.image_cache_limit == ImageCacheLimit::Large,
+                    cache_row_selected,
+                ),
+            ])),
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    "Remote mode (small images): ",
+                    Style::default().add_modifier(Modifier::BOLD),
                 ),
+                option_chip("on", self.ctx.settings.remote_mode, remote_row_selected),
+                Span::raw(" "),
+                option_chip("off", !self.ctx.settings.remote_mode, remote_row_selected),
             ])),
+            ListItem::new(Line::from(sample_spans)),
+            ListItem::new(Line::from(fraction_spans)),
+            ListItem::new({
+                let label = Span::styled(
+                    "External viewer: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                );
+                match &self.settings_panel.command_input {
+                    Some((CommandSetting::ExternalViewer, input)) => {
+                        let mut spans = vec![label];
+                        spans.extend(input.spans(Style::default().fg(self.accent_color())));
+                        Line::from(spans)
+                    }
+                    _ if self.ctx.settings.external_viewer.is_empty() => Line::from(vec![
+                        label,
+                        Span::raw(format!("default ({})", external_viewer::default_template())),
+                    ]),
+                    _ => Line::from(vec![
+                        label,
+                        Span::raw(self.ctx.settings.external_viewer.clone()),
+                    ]),
+                }
+            }),
+            ListItem::new({
+                let label = Span::styled(
+                    "Graphics terminal: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                );
+                match &self.settings_panel.command_input {
+                    Some((CommandSetting::GraphicsTerminal, input)) => {
+                        let mut spans = vec![label];
+                        spans.extend(input.spans(Style::default().fg(self.accent_color())));
+                        Line::from(spans)
+                    }
+                    _ if self.ctx.settings.graphics_terminal.is_empty() => Line::from(vec![
+                        label,
+                        Span::raw("auto (kitty, wezterm, ghostty, foot)"),
+                    ]),
+                    _ => Line::from(vec![
+                        label,
+                        Span::raw(self.ctx.settings.graphics_terminal.clone()),
+                    ]),
+                }
+            }),
             ListItem::new(Line::from(vec![
                 Span::styled("Theme: ", Style::default().add_modifier(Modifier::BOLD)),
                 option_chip(
@@ -4113,6 +10271,110 @@ impl Ui {
                     theme_row_selected,
                 ),
             ])),
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    "Watch library roots: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                option_chip("on", self.ctx.settings.watch_library, watch_row_selected),
+                Span::raw(" "),
+                option_chip("off", !self.ctx.settings.watch_library, watch_row_selected),
+            ])),
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    "Fingerprint books on scan: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                option_chip(
+                    "on",
+                    self.ctx.settings.fingerprint_books,
+                    fingerprint_row_selected,
+                ),
+                Span::raw(" "),
+                option_chip(
+                    "off",
+                    !self.ctx.settings.fingerprint_books,
+                    fingerprint_row_selected,
+                ),
+            ])),
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    "Progress and counts in list: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                option_chip(
+                    "on",
+                    self.ctx.settings.library_decorations,
+                    decorations_row_selected,
+                ),
+                Span::raw(" "),
+                option_chip(
+                    "off",
+                    !self.ctx.settings.library_decorations,
+                    decorations_row_selected,
+                ),
+            ])),
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    "Resume last book on start: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                option_chip(
+                    "on",
+                    self.ctx.settings.resume_last_book,
+                    resume_row_selected,
+                ),
+                Span::raw(" "),
+                option_chip(
+                    "off",
+                    !self.ctx.settings.resume_last_book,
+                    resume_row_selected,
+                ),
+            ])),
+            ListItem::new(Line::from(vec![
+                Span::styled("Mouse: ", Style::default().add_modifier(Modifier::BOLD)),
+                option_chip("on", self.ctx.settings.mouse_capture, mouse_row_selected),
+                Span::raw(" "),
+                option_chip("off", !self.ctx.settings.mouse_capture, mouse_row_selected),
+            ])),
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    "Reader position column: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                option_chip("on", self.ctx.settings.reader_minimap, minimap_row_selected),
+                Span::raw(" "),
+                option_chip(
+                    "off",
+                    !self.ctx.settings.reader_minimap,
+                    minimap_row_selected,
+                ),
+            ])),
+            ListItem::new(Line::from(tick_spans)),
+            ListItem::new(Line::from(autosave_spans)),
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    "Ask before quitting with unsaved changes: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                option_chip(
+                    "on",
+                    self.ctx.settings.confirm_quit,
+                    confirm_quit_row_selected,
+                ),
+                Span::raw(" "),
+                option_chip(
+                    "off",
+                    !self.ctx.settings.confirm_quit,
+                    confirm_quit_row_selected,
+                ),
+            ])),
+            self.settings_file_item(SettingsFileAction::Export, "Export settings to file"),
+            self.settings_file_item(SettingsFileAction::Import, "Import settings from file"),
+            ListItem::new(Line::raw("Backup database")),
+            ListItem::new(Line::raw("Restore from backup")),
+            ListItem::new(Line::raw("Find duplicate books")),
+            ListItem::new(Line::raw("Clean up database")),
         ];
 
         let list = List::new(items)
@@ -4129,29 +10391,87 @@ impl Ui {
         ));
         frame.render_stateful_widget(list, sections[0], &mut state);
 
-        let help_lines = vec![Line::from(vec![
-            Span::styled("↑/↓", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" select  "),
-            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" open/toggle  "),
-            Span::styled("←/→", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" adjust  "),
-            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" close"),
-        ])];
+        let help_lines = if let Some((action, _)) = &self.settings_panel.file_input {
+            vec![
+                Line::raw(match action {
+                    SettingsFileAction::Export => {
+                        "JSON file to write: roots, reader and library preferences, theme, keys"
+                    }
+                    SettingsFileAction::Import => {
+                        "JSON file to read; replaces the current settings"
+                    }
+                }),
+                Line::from(vec![
+                    Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(match action {
+                        SettingsFileAction::Export => " export  ",
+                        SettingsFileAction::Import => " import  ",
+                    }),
+                    Span::styled("Ctrl+u", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" clear  "),
+                    Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" cancel"),
+                ]),
+            ]
+        } else if let Some((command, _)) = &self.settings_panel.command_input {
+            vec![
+                Line::raw(command.hint()),
+                Line::from(vec![
+                    Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" save  "),
+                    Span::styled("Ctrl+u", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" clear  "),
+                    Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" cancel"),
+                ]),
+            ]
+        } else if let Some(missing) = self.settings_panel.confirm_cleanup {
+            vec![
+                Line::from(Span::styled(
+                    format!(
+                        "Remove {missing} missing books, orphaned rows and unused labels, then compact?"
+                    ),
+                    Style::default().fg(Color::Yellow),
+                )),
+                Line::from(vec![
+                    Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" clean up  "),
+                    Span::styled("n/Esc", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" cancel"),
+                ]),
+            ]
+        } else {
+            vec![Line::from(vec![
+                Span::styled("↑/↓", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" select  "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" open/toggle  "),
+                Span::styled("←/→", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" adjust  "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" close"),
+            ])]
+        };
         let help = Paragraph::new(Text::from(help_lines))
             .wrap(Wrap { trim: true })
             .alignment(Alignment::Left);
         frame.render_widget(help, sections[1]);
     }
 
-    fn draw_library(&self, frame: &mut ratatui::Frame, area: Rect) {
+    fn draw_library(&mut self, frame: &mut ratatui::Frame, area: Rect) {
+        if self.ctx.kind_filter.is_some() {
+            self.request_unknown_book_kinds();
+        }
         let visible = self.visible_indices();
         let has_filters = !self.ctx.library_query.trim().is_empty()
             || self.ctx.favorites_only
+            || self.ctx.include_archived
+            || self.ctx.status_filter.is_some()
+            || self.ctx.added_within_days.is_some()
+            || self.ctx.kind_filter.is_some()
             || !matches!(self.ctx.collection_filter, CollectionFilter::Any)
             || !self.ctx.tag_filters.is_empty();
-        let title = if has_filters {
+        let mut title = if has_filters {
             format!(
                 "Library — {}/{} matches",
                 visible.len(),
@@ -4160,18 +10480,35 @@ impl Ui {
         } else {
             "Library".to_string()
         };
+        if !self.marked_paths.is_empty() {
+            title.push_str(&format!(" — {} marked", self.marked_paths.len()));
+        }
+        if let Some(state) = &self.library_scan {
+            title.push_str(&format!(
+                " — scanning… {} files (Esc cancel)",
+                state.files_seen
+            ));
+        } else if let Some(state) = &self.duplicate_scan {
+            title.push_str(&format!(
+                " — finding duplicates… {}/{} books (Esc cancel)",
+                state.done, state.total
+            ));
+        } else if let Some(notice) = &self.library_notice {
+            title.push_str(&format!(" — {notice}"));
+        }
         let block = Block::default().borders(Borders::ALL).title(title);
 
         if self.ctx.books.is_empty() {
             let mut lines = Vec::new();
-            lines.push(Line::raw("No PDFs found."));
+            lines.push(Line::raw("No books found."));
             lines.push(Line::raw(""));
             lines.push(Line::raw("Roots:"));
             if self.ctx.settings.library_roots.is_empty() {
                 lines.push(Line::raw("(empty)"));
             } else {
                 for root in &self.ctx.settings.library_roots {
-                    lines.push(Line::raw(format!("- {root}")));
+                    let off = if root.enabled { "" } else { " (off)" };
+                    lines.push(Line::raw(format!("- {}{off}", root.path)));
                 }
             }
 
@@ -4198,19 +10535,78 @@ impl Ui {
             return;
         }
 
-        let max_title_width = area.width.saturating_sub(6) as usize;
+        if self.ctx.settings.library_grid {
+            self.draw_cover_grid(frame, area, block, &visible);
+            return;
+        }
+
+        let max_title_width = area.width.saturating_sub(7) as usize;
         let items: Vec<ListItem> = visible
             .iter()
             .filter_map(|idx| self.ctx.books.get(*idx))
             .map(|book| {
-                let label = if book.favorite {
-                    format!("★ {}", book.title)
+                let mark = if self.marked_paths.contains(&book.path) {
+                    "✓"
+                } else {
+                    " "
+                };
+                let fav = if book.favorite { "★" } else { " " };
+                let status = book.status.glyph();
+                let missing = self.missing_paths.contains(&book.path);
+                let label = if missing {
+                    format!("{} (missing)", book.title)
+                } else if self.broken_paths.contains_key(&book.path) {
+                    format!("{} (corrupt)", book.title)
+                } else if book.archived {
+                    format!("{} (archived)", book.title)
                 } else {
-                    format!("  {}", book.title)
+                    book.title.clone()
                 };
-                let wrapped = wrap_text(&label, max_title_width.max(8));
-                let lines = wrapped.into_iter().map(Line::raw).collect::<Vec<_>>();
-                ListItem::new(Text::from(lines))
+                let wrapped =
+                    library_row_lines(&format!("{mark}{fav}{status} "), &label, max_title_width);
+                let mut lines = wrapped.into_iter().map(Line::raw).collect::<Vec<_>>();
+                if self.ctx.is_new(&book.path) {
+                    let badge = Span::styled(
+                        "new",
+                        Style::default()
+                            .fg(self.accent_color())
+                            .add_modifier(Modifier::BOLD),
+                    );
+                    match lines.last_mut() {
+                        Some(last) if last.width() + 4 <= max_title_width => {
+                            last.spans.push(Span::raw(" "));
+                            last.spans.push(badge);
+                        }
+                        _ => lines.push(Line::from(vec![Span::raw("   "), badge])),
+                    }
+                }
+                if let Some(kind) = self.ctx.book_kind(&book.path) {
+                    let badge =
+                        Span::styled(kind.as_str(), Style::default().add_modifier(Modifier::DIM));
+                    match lines.last_mut() {
+                        Some(last) if last.width() + 1 + badge.width() <= max_title_width => {
+                            last.spans.push(Span::raw(" "));
+                            last.spans.push(badge);
+                        }
+                        _ => lines.push(Line::from(vec![Span::raw("   "), badge])),
+                    }
+                }
+                if let Some(suffix) = self.row_decorations(&book.path) {
+                    let suffix_style = Style::default().add_modifier(Modifier::DIM);
+                    match lines.last_mut() {
+                        Some(last) if last.width() + 1 + suffix.width() <= max_title_width => {
+                            last.spans.push(Span::raw(" "));
+                            last.spans.push(Span::styled(suffix, suffix_style));
+                        }
+                        _ => lines.push(Line::styled(format!("   {suffix}"), suffix_style)),
+                    }
+                }
+                let item = ListItem::new(Text::from(lines));
+                if missing || book.archived {
+                    item.style(Style::default().add_modifier(Modifier::DIM))
+                } else {
+                    item
+                }
             })
             .collect();
 
@@ -4219,6 +10615,8 @@ impl Ui {
             .bg(self.accent_color())
             .add_modifier(Modifier::BOLD);
 
+        let heights: Vec<usize> = items.iter().map(ListItem::height).collect();
+        let list_area = block.inner(area);
         let list = List::new(items)
             .block(block)
             .highlight_style(highlight_style)
@@ -4229,9 +10627,330 @@ impl Ui {
         let visible_pos = visible.iter().position(|idx| *idx == self.ctx.selected);
         state.select(visible_pos);
         frame.render_stateful_widget(list, area, &mut state);
+        let mut rows_height = 0;
+        let rows_shown = heights[state.offset()..]
+            .iter()
+            .take_while(|height| {
+                let fits = rows_height < usize::from(list_area.height);
+                rows_height += **height;
+                fits
+            })
+            .count();
+        self.request_book_kinds(&visible[state.offset()..state.offset() + rows_shown]);
+        self.hit_regions
+            .borrow_mut()
+            .add_list_rows(list_area, state.offset(), &heights, |pos| {
+                HitTarget::Library(visible[pos])
+            });
     }
 
-    fn draw_details(&self, _area: Rect) -> Paragraph<'static> {
+    /// The library as rows of covers with their titles underneath. Without an image protocol
+    /// each book is a boxed title card instead.
+    fn draw_cover_grid(
+        &mut self,
+        frame: &mut ratatui::Frame,
+        area: Rect,
+        block: Block<'_>,
+        visible: &[usize],
+    ) {
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        if inner.is_empty() {
+            return;
+        }
+
+        let with_covers = image_protocol::image_supported(&self.image_picker);
+        let cell_height = if with_covers {
+            GRID_COVER_CELL_HEIGHT
+        } else {
+            GRID_CARD_CELL_HEIGHT
+        };
+        let columns = grid_columns(inner.width);
+        let rows_shown = usize::from(inner.height / cell_height).max(1);
+        let selected_pos = visible
+            .iter()
+            .position(|idx| *idx == self.ctx.selected)
+            .unwrap_or(0);
+        let first_row = grid_first_row(
+            self.cover_grid.first_row,
+            selected_pos / columns,
+            rows_shown,
+        );
+        self.cover_grid.columns = columns;
+        self.cover_grid.first_row = first_row;
+        let shown: Vec<usize> = visible
+            .iter()
+            .copied()
+            .skip(first_row * columns)
+            .take(rows_shown * columns)
+            .collect();
+
+        if with_covers {
+            let books: Vec<&Book> = shown
+                .iter()
+                .filter_map(|idx| self.ctx.books.get(*idx))
+                .filter(|book| {
+                    !self.missing_paths.contains(&book.path)
+                        && !self.broken_paths.contains_key(&book.path)
+                })
+                .collect();
+            let paths: Vec<&str> = books.iter().map(|book| book.path.as_str()).collect();
+            if let Some(wanted) = self.cover_grid.show(&paths) {
+                let jobs = books
+                    .iter()
+                    .filter(|book| wanted.contains(&book.path))
+                    .map(|book| CoverJob {
+                        book: (*book).clone(),
+                    })
+                    .collect();
+                self.page_render.request_grid_covers(jobs);
+            }
+        }
+        self.request_book_kinds(&shown);
+
+        let accent = self.accent_color();
+        let highlight_style = Style::default()
+            .fg(Color::Black)
+            .bg(accent)
+            .add_modifier(Modifier::BOLD);
+        for (slot, idx) in shown.iter().enumerate() {
+            let Some(book) = self.ctx.books.get(*idx) else {
+                continue;
+            };
+            let cell = Rect::new(
+                inner.x + (slot % columns) as u16 * GRID_CELL_WIDTH,
+                inner.y + (slot / columns) as u16 * cell_height,
+                GRID_CELL_WIDTH - 1,
+                cell_height,
+            )
+            .intersection(inner);
+            if cell.is_empty() {
+                continue;
+            }
+            self.hit_regions
+                .get_mut()
+                .add(cell, HitTarget::Library(*idx));
+
+            let selected = *idx == self.ctx.selected;
+            let mut caption = String::new();
+            if self.marked_paths.contains(&book.path) {
+                caption.push_str("✓ ");
+            }
+            if book.favorite {
+                caption.push_str("★ ");
+            }
+            caption.push_str(&book.title);
+            let mut style = Style::default();
+            if self.missing_paths.contains(&book.path) || book.archived {
+                style = style.add_modifier(Modifier::DIM);
+            }
+
+            if !with_covers {
+                let border_style = if selected {
+                    Style::default().fg(accent).add_modifier(Modifier::BOLD)
+                } else {
+                    style
+                };
+                let card = Paragraph::new(caption)
+                    .style(if selected { highlight_style } else { style })
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true })
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(border_style),
+                    );
+                frame.render_widget(card, cell);
+                continue;
+            }
+
+            let cover_rows = cell.height.saturating_sub(GRID_CAPTION_ROWS);
+            let cover_area = Rect {
+                height: cover_rows,
+                ..cell
+            };
+            match self.cover_grid.image(&book.path) {
+                Some(Some(_)) => {
+                    let size = (cover_area.width, cover_area.height);
+                    let picker = &mut self.image_picker;
+                    let protocol = self.cover_grid.protocol(&book.path, size, |image| {
+                        picker
+                            .new_protocol(
+                                image.clone(),
+                                Rect::new(0, 0, size.0, size.1),
+                                Resize::Fit(None),
+                            )
+                            .ok()
+                    });
+                    if let Some(protocol) = protocol {
+                        // Covers sit on their captions, centered across the cell.
+                        let proto_area = protocol.area();
+                        let width = proto_area.width.min(cover_area.width);
+                        let height = proto_area.height.min(cover_area.height);
+                        let draw_area = Rect::new(
+                            cover_area.x + (cover_area.width - width) / 2,
+                            cover_area.bottom() - height,
+                            width,
+                            height,
+                        );
+                        frame.render_widget(ImageWidget::new(protocol), draw_area);
+                    }
+                }
+                Some(None) => {
+                    frame.render_widget(
+                        Paragraph::new("×")
+                            .alignment(Alignment::Center)
+                            .block(Block::default().borders(Borders::ALL).border_style(style)),
+                        cover_area,
+                    );
+                }
+                None => {
+                    let row = Rect {
+                        y: cover_area.y + cover_area.height / 2,
+                        height: cover_area.height.min(1),
+                        ..cover_area
+                    };
+                    frame.render_widget(Paragraph::new("…").alignment(Alignment::Center), row);
+                }
+            }
+            let caption_area = Rect {
+                y: cell.y + cover_rows,
+                height: cell.height - cover_rows,
+                ..cell
+            };
+            frame.render_widget(
+                Paragraph::new(caption)
+                    .style(if selected { highlight_style } else { style })
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true }),
+                caption_area,
+            );
+        }
+    }
+
+    /// Has the render worker tell the kinds of these library rows, once a session for each
+    /// book. It passes over files unchanged since their kind was found.
+    fn request_book_kinds(&mut self, shown: &[usize]) {
+        let jobs: Vec<BookKindJob> = shown
+            .iter()
+            .filter_map(|idx| self.ctx.books.get(*idx))
+            .filter(|book| {
+                !self.missing_paths.contains(&book.path)
+                    && self.kinds_requested.insert(book.path.clone())
+            })
+            .map(|book| BookKindJob {
+                book: book.clone(),
+                known: self.ctx.page_counts_by_path.get(&book.path).copied(),
+            })
+            .collect();
+        if !jobs.is_empty() {
+            self.page_render.request_book_kinds(jobs);
+        }
+    }
+
+    /// Asks for the kind of every book not told yet. A kind filter hides those books, so they
+    /// never come on screen to be asked about as rows.
+    fn request_unknown_book_kinds(&mut self) {
+        let unknown: Vec<usize> = self
+            .ctx
+            .books
+            .iter()
+            .enumerate()
+            .filter(|(_, book)| {
+                self.ctx.book_kind(&book.path).is_none()
+                    && !self.kinds_requested.contains(&book.path)
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        self.request_book_kinds(&unknown);
+    }
+
+    /// Progress and annotation counts shown after a library row, unless turned off in Settings.
+    fn row_decorations(&self, path: &str) -> Option<String> {
+        if !self.ctx.settings.library_decorations {
+            return None;
+        }
+        row_decorations(
+            self.ctx.progress_by_path.get(path).map(|p| p.last_page),
+            self.ctx
+                .page_counts_by_path
+                .get(path)
+                .map(|count| count.pages),
+            self.ctx.notes_by_path.get(path).map_or(0, Vec::len),
+            self.ctx.bookmarks_by_path.get(path).map_or(0, Vec::len),
+        )
+    }
+
+    /// Cells for the selected book's cover in the top-right of the details pane; `None` when
+    /// there is no cover to show.
+    fn details_cover_area(&self, details: Rect) -> Option<Rect> {
+        if !self.meta_cache.cover_requested || matches!(self.meta_cache.cover, Some(None)) {
+            return None;
+        }
+        let inner = Block::default().borders(Borders::ALL).inner(details);
+        let cols = (inner.width / 3).min(24);
+        if cols < 8 || inner.height < 6 {
+            return None;
+        }
+        // Until the cover arrives, reserve room for a portrait (A4-shaped) page.
+        let (image_w, image_h) = match &self.meta_cache.cover {
+            Some(Some(image)) => (image.width().max(1), image.height()),
+            _ => (1000, 1414),
+        };
+        let (font_w, font_h) = self.image_picker.font_size();
+        let rows = (u64::from(cols) * u64::from(font_w) * u64::from(image_h))
+            .div_ceil(u64::from(image_w) * u64::from(font_h.max(1)));
+        let rows = u16::try_from(rows)
+            .unwrap_or(u16::MAX)
+            .clamp(3, inner.height / 2);
+        Some(Rect::new(inner.x + inner.width - cols, inner.y, cols, rows))
+    }
+
+    fn draw_details_cover(&mut self, frame: &mut ratatui::Frame, area: Rect) {
+        let Some(Some(image)) = &self.meta_cache.cover else {
+            frame.render_widget(
+                Paragraph::new("loading cover…")
+                    .style(Style::default().fg(Color::DarkGray))
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true }),
+                area,
+            );
+            return;
+        };
+        let size = (area.width, area.height);
+        let stale = self
+            .meta_cache
+            .cover_protocol
+            .as_ref()
+            .is_none_or(|(built_for, _)| *built_for != size);
+        if stale {
+            self.meta_cache.cover_protocol = self
+                .image_picker
+                .new_protocol(
+                    (**image).clone(),
+                    Rect::new(0, 0, size.0, size.1),
+                    Resize::Fit(None),
+                )
+                .ok()
+                .map(|protocol| (size, protocol));
+        }
+        if let Some((_, protocol)) = &self.meta_cache.cover_protocol {
+            let proto_area = protocol.area();
+            let width = proto_area.width.min(area.width);
+            let draw_area = Rect::new(
+                area.x + area.width - width,
+                area.y,
+                width,
+                proto_area.height.min(area.height),
+            );
+            frame.render_widget(ImageWidget::new(protocol), draw_area);
+        }
+    }
+
+    fn draw_details(&self, area: Rect, cover_area: Option<Rect>) -> Paragraph<'static> {
+        // Text wraps beside the cover rather than under it.
+        let cover_cols = cover_area.map_or(0, |area| area.width.saturating_add(1));
+        let text_width = area.width.saturating_sub(2).saturating_sub(cover_cols);
         let mut lines = Vec::new();
         lines.push(Line::from(vec![
             Span::styled("Reader: ", Style::default().add_modifier(Modifier::BOLD)),
@@ -4242,11 +10961,22 @@ impl Ui {
             Span::raw("  "),
             Span::styled("Roots: ", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(self.ctx.settings.library_roots.len().to_string()),
+            Span::raw("  "),
+            Span::styled("Sort: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("{} (o)", self.ctx.settings.sort_mode.label())),
         ]));
         let fav_status = if self.ctx.favorites_only { "on" } else { "off" };
+        let status_filter = self
+            .ctx
+            .status_filter
+            .map_or("any", |status| status.as_str());
+        let kind_filter = self.ctx.kind_filter.map_or("any", |kind| kind.as_str());
         let collection = match &self.ctx.collection_filter {
             CollectionFilter::Any => "any".to_string(),
             CollectionFilter::None => "none".to_string(),
+            CollectionFilter::Selected(name) if self.ctx.collection_view => {
+                format!("{name} (reading list)")
+            }
             CollectionFilter::Selected(name) => name.clone(),
         };
         let tag_mode = match self.ctx.tag_match_mode {
@@ -4266,6 +10996,8 @@ impl Ui {
         lines.push(Line::from(vec![
             Span::styled("Filters: ", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(format!("fav:{fav_status} (←/→)  ")),
+            Span::raw(format!("status:{status_filter}  ")),
+            Span::raw(format!("kind:{kind_filter}  ")),
             Span::raw(format!("collection:{collection}  ")),
             Span::raw(format!("tags({tag_mode}): {tags}  ")),
             Span::raw(format!("query:{query}")),
@@ -4273,19 +11005,77 @@ impl Ui {
         lines.push(Line::raw(""));
 
         if let Some(book) = self.ctx.books.get(self.ctx.selected) {
+            let labels = self
+                .ctx
+                .labels_by_path
+                .get(&book.path)
+                .cloned()
+                .unwrap_or_default();
             lines.push(Line::from(vec![
                 Span::styled("Selected: ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(book.title.clone()),
             ]));
-            lines.push(Line::raw(bookshelf_core::display_path(&book.path)));
+            if let Some(author) = book_author(book, &labels) {
+                lines.push(Line::from(vec![
+                    Span::styled("Author: ", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(author.to_string()),
+                ]));
+            }
+            if let Some(series) = labels.series_name() {
+                lines.push(Line::from(vec![
+                    Span::styled("Series: ", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(format_series(series, labels.series_number())),
+                ]));
+            }
+            lines.extend(
+                wrap_path(
+                    &bookshelf_core::display_path(&book.path),
+                    usize::from(text_width),
+                )
+                .into_iter()
+                .map(Line::raw),
+            );
+            if self.missing_paths.contains(&book.path) {
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        "File missing: ",
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        self.key_bindings.label(KeyAction::RelinkBook),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(" relink  "),
+                    Span::styled(
+                        self.key_bindings.label(KeyAction::RemoveBook),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(" remove from library"),
+                ]));
+            }
+            let broken = self.broken_paths.get(&book.path);
+            if let Some(err) = broken {
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        "File corrupt: ",
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(format!("{err}  ")),
+                    Span::styled(
+                        self.key_bindings.label(KeyAction::RemoveBook),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(" remove from library"),
+                ]));
+            }
             lines.push(Line::raw(""));
 
             let size = format_bytes_opt(self.meta_cache.size_bytes);
-            let pages = self
-                .meta_cache
-                .page_count
-                .map(|n| n.to_string())
-                .unwrap_or_else(|| "-".to_string());
+            let pages = match (self.meta_cache.page_count, broken) {
+                (Some(n), _) => n.to_string(),
+                (None, Some(_)) => "(corrupt)".to_string(),
+                (None, None) => "-".to_string(),
+            };
             lines.push(Line::from(vec![
                 Span::styled("Size: ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(size),
@@ -4293,6 +11083,14 @@ impl Ui {
                 Span::styled("Pages: ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(pages),
             ]));
+            lines.push(Line::from(vec![
+                Span::styled("Kind: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(
+                    self.ctx
+                        .book_kind(&book.path)
+                        .map_or("-", |kind| kind.describe()),
+                ),
+            ]));
             lines.push(Line::from(vec![
                 Span::styled(
                     "Last opened: ",
@@ -4300,13 +11098,26 @@ impl Ui {
                 ),
                 Span::raw(format_last_opened(book.last_opened)),
             ]));
-            lines.push(Line::raw(""));
-            let labels = self
+            let added = self.ctx.added_at_by_path.get(&book.path).copied();
+            let mut added_spans = vec![
+                Span::styled("Added: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(added.map_or_else(|| "-".to_string(), |at| format_last_opened(Some(at)))),
+            ];
+            if self.ctx.is_new(&book.path) {
+                added_spans.push(Span::raw(" (new since the last scan)"));
+            }
+            lines.push(Line::from(added_spans));
+            let time_read = self
                 .ctx
-                .labels_by_path
+                .reading_secs_by_path
                 .get(&book.path)
-                .cloned()
-                .unwrap_or_default();
+                .map(|secs| format_reading_time(*secs))
+                .unwrap_or_else(|| "-".to_string());
+            lines.push(Line::from(vec![
+                Span::styled("Time read: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(time_read),
+            ]));
+            lines.push(Line::raw(""));
             let tags = if labels.tags.is_empty() {
                 "(none)".to_string()
             } else {
@@ -4317,6 +11128,27 @@ impl Ui {
                 Span::styled("Favorite: ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(if book.favorite { "yes" } else { "no" }),
             ]));
+            if book.archived {
+                lines.push(Line::from(vec![
+                    Span::styled("Archived: ", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw("yes ("),
+                    Span::styled(
+                        self.key_bindings.label(KeyAction::ToggleArchived),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(" to restore)"),
+                ]));
+            }
+            lines.push(Line::from(vec![
+                Span::styled("Status: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(book.status.to_string()),
+                Span::raw(" ("),
+                Span::styled(
+                    self.key_bindings.label(KeyAction::CycleStatus),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(")"),
+            ]));
             lines.push(Line::from(vec![
                 Span::styled(
                     "Collection: ",
@@ -4329,12 +11161,31 @@ impl Ui {
                 Span::raw(tags),
             ]));
             lines.push(Line::raw(""));
+            lines.push(Line::from(vec![
+                Span::styled(
+                    self.key_bindings.label(KeyAction::RefreshMetadata),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" refresh title/author from file metadata"),
+            ]));
+            lines.push(Line::from(vec![
+                Span::styled(
+                    self.key_bindings.label(KeyAction::DeleteBook),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(" delete from library or disk"),
+            ]));
         } else {
             lines.push(Line::raw("No selection."));
         }
 
         Paragraph::new(Text::from(lines))
-            .block(Block::default().borders(Borders::ALL).title("Details"))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Details")
+                    .padding(Padding::right(cover_cols)),
+            )
             .wrap(Wrap { trim: true })
     }
 
@@ -4361,31 +11212,54 @@ impl Ui {
             .bg(self.accent_color())
             .add_modifier(Modifier::BOLD);
 
-        let scope_row_selected = self.scan_panel.selected == 1;
+        let panel = &self.scan_panel;
+        let scope_row_selected = panel.selected == panel.scope_row();
+        let cwd = Path::new(&self.ctx.cwd);
 
-        let items = vec![
-            ListItem::new(Line::from(vec![
-                Span::styled("Paths: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(self.scan_panel.input.clone()),
-            ])),
-            ListItem::new(Line::from(vec![
-                Span::styled(
-                    "Scan scope: ",
-                    Style::default().add_modifier(Modifier::BOLD),
-                ),
-                option_chip(
-                    "direct",
-                    self.ctx.settings.scan_scope == bookshelf_core::ScanScope::Direct,
-                    scope_row_selected,
-                ),
-                Span::raw(" "),
-                option_chip(
-                    "recursive",
-                    self.ctx.settings.scan_scope == bookshelf_core::ScanScope::Recursive,
-                    scope_row_selected,
-                ),
-            ])),
-        ];
+        let mut items: Vec<ListItem> = panel
+            .roots
+            .iter()
+            .enumerate()
+            .map(|(idx, root)| {
+                if panel.edit == Some(RootEdit::Change(idx)) {
+                    return ListItem::new(panel.input.labeled_line(Span::raw("    ")));
+                }
+                let check = if root.enabled { "[x]" } else { "[ ]" };
+                let status = root_status(&root.path, cwd, &self.ctx.books);
+                let status_style = if status == "not found" {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default().fg(Color::Gray)
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{check} {}  ", root.path)),
+                    Span::styled(status, status_style),
+                ]))
+            })
+            .collect();
+        if panel.edit == Some(RootEdit::Add) {
+            items.push(ListItem::new(panel.input.labeled_line(Span::styled(
+                "New path: ",
+                Style::default().add_modifier(Modifier::BOLD),
+            ))));
+        }
+        items.push(ListItem::new(Line::from(vec![
+            Span::styled(
+                "Scan scope: ",
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            option_chip(
+                "direct",
+                self.ctx.settings.scan_scope == bookshelf_core::ScanScope::Direct,
+                scope_row_selected,
+            ),
+            Span::raw(" "),
+            option_chip(
+                "recursive",
+                self.ctx.settings.scan_scope == bookshelf_core::ScanScope::Recursive,
+                scope_row_selected,
+            ),
+        ])));
 
         let list = List::new(items)
             .highlight_style(highlight_style)
@@ -4393,26 +11267,49 @@ impl Ui {
             .highlight_symbol("> ");
 
         let mut state = ListState::default();
-        state.select(Some(self.scan_panel.selected.min(1)));
+        let selected = match panel.edit {
+            Some(RootEdit::Add) => panel.scope_row(),
+            Some(RootEdit::Change(idx)) => idx,
+            None => panel.selected,
+        };
+        state.select(Some(selected));
         frame.render_stateful_widget(list, sections[0], &mut state);
 
+        let bold = Style::default().add_modifier(Modifier::BOLD);
         let mut help_lines = Vec::new();
-        help_lines.push(Line::from(vec![
-            Span::styled("↑/↓", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" select  "),
-            Span::styled("←/→", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" change scope  "),
-            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" apply + rescan"),
-        ]));
-        help_lines.push(Line::from(vec![
-            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" cancel  "),
-            Span::styled("Backspace", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" delete  "),
-            Span::styled("Ctrl+U", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(" clear"),
-        ]));
+        if panel.edit.is_some() {
+            help_lines.push(Line::from(vec![
+                Span::styled("Enter", bold),
+                Span::raw(" save path  "),
+                Span::styled("Esc", bold),
+                Span::raw(" cancel  "),
+                Span::styled("Ctrl+U", bold),
+                Span::raw(" clear"),
+            ]));
+        } else {
+            help_lines.push(Line::from(vec![
+                Span::styled("↑/↓", bold),
+                Span::raw(" select  "),
+                Span::styled("a", bold),
+                Span::raw(" add  "),
+                Span::styled("e", bold),
+                Span::raw(" edit  "),
+                Span::styled("d", bold),
+                Span::raw(" remove  "),
+                Span::styled("Space", bold),
+                Span::raw(" on/off  "),
+                Span::styled("←/→", bold),
+                Span::raw(" scope"),
+            ]));
+            help_lines.push(Line::from(vec![
+                Span::styled("Enter", bold),
+                Span::raw(" apply + rescan  "),
+                Span::styled("p", bold),
+                Span::raw(" rescan + remove missing  "),
+                Span::styled("Esc", bold),
+                Span::raw(" cancel"),
+            ]));
+        }
 
         if let Some(err) = &self.scan_panel.error {
             help_lines.push(Line::raw(""));
@@ -4429,35 +11326,89 @@ impl Ui {
     }
 }
 
-#[derive(Debug, Clone)]
-struct ScanPathPanel {
+#[derive(Debug, Clone, Default)]
+struct SettingsPanel {
     open: bool,
     selected: usize,
-    input: String,
-    error: Option<String>,
+    /// Set while the cleanup confirmation is shown: books whose file is missing.
+    confirm_cleanup: Option<usize>,
+    /// Set while a command setting is being edited.
+    command_input: Option<(CommandSetting, TextInput)>,
+    /// Set while the file to export settings to, or import them from, is being typed.
+    file_input: Option<(SettingsFileAction, TextInput)>,
 }
 
-impl ScanPathPanel {
-    fn new(input: String) -> Self {
-        Self {
-            open: false,
-            selected: 0,
-            input,
-            error: None,
+impl SettingsPanel {
+    fn edit_command(&mut self, command: CommandSetting, settings: &mut Settings) {
+        let value = command.value_mut(settings).clone();
+        self.command_input = Some((command, TextInput::new(value)));
+    }
+}
+
+/// A settings row holding a command template, edited as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandSetting {
+    ExternalViewer,
+    GraphicsTerminal,
+}
+
+impl CommandSetting {
+    fn value_mut(self, settings: &mut Settings) -> &mut String {
+        match self {
+            CommandSetting::ExternalViewer => &mut settings.external_viewer,
+            CommandSetting::GraphicsTerminal => &mut settings.graphics_terminal,
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            CommandSetting::ExternalViewer => {
+                "%f file, %p page (1-based); empty for the system default"
+            }
+            CommandSetting::GraphicsTerminal => {
+                "%e bookshelf + reader args, %f file, %p page; empty tries kitty, wezterm, ghostty, foot"
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Default)]
-struct SettingsPanel {
-    open: bool,
-    selected: usize,
+/// Whether the settings panel's file row writes the settings out or reads them in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingsFileAction {
+    Export,
+    Import,
 }
 
+/// File the settings panel offers to export to and import from, in the current folder.
+const SETTINGS_FILE_NAME: &str = "bookshelf-settings.json";
+
 const SETTINGS_MENU_SCAN_PATHS: usize = 0;
 const SETTINGS_MENU_KITTY_IMAGE_QUALITY: usize = 1;
-const SETTINGS_MENU_THEME: usize = 2;
-const SETTINGS_MENU_ITEM_COUNT: usize = 3;
+const SETTINGS_MENU_DOWNSCALE_FILTER: usize = 2;
+const SETTINGS_MENU_AUTO_CROP: usize = 3;
+const SETTINGS_MENU_IMAGE_CACHE_LIMIT: usize = 4;
+const SETTINGS_MENU_REMOTE_MODE: usize = 5;
+const SETTINGS_MENU_FURNITURE_SAMPLE_PAGES: usize = 6;
+const SETTINGS_MENU_FURNITURE_MIN_FRACTION: usize = 7;
+const SETTINGS_MENU_EXTERNAL_VIEWER: usize = 8;
+const SETTINGS_MENU_GRAPHICS_TERMINAL: usize = 9;
+const SETTINGS_MENU_THEME: usize = 10;
+const SETTINGS_MENU_WATCH_LIBRARY: usize = 11;
+const SETTINGS_MENU_FINGERPRINT_BOOKS: usize = 12;
+const SETTINGS_MENU_LIBRARY_DECORATIONS: usize = 13;
+const SETTINGS_MENU_RESUME_LAST_BOOK: usize = 14;
+const SETTINGS_MENU_MOUSE_CAPTURE: usize = 15;
+const SETTINGS_MENU_READER_MINIMAP: usize = 16;
+const SETTINGS_MENU_TICK_RATE: usize = 17;
+const SETTINGS_MENU_AUTOSAVE: usize = 18;
+const SETTINGS_MENU_CONFIRM_QUIT: usize = 19;
+const SETTINGS_MENU_EXPORT_SETTINGS: usize = 20;
+const SETTINGS_MENU_IMPORT_SETTINGS: usize = 21;
+const SETTINGS_MENU_BACKUP: usize = 22;
+const SETTINGS_MENU_RESTORE: usize = 23;
+const SETTINGS_MENU_DUPLICATES: usize = 24;
+const SETTINGS_MENU_CLEANUP: usize = 25;
+const SETTINGS_MENU_ITEM_COUNT: usize = 26;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum SearchPanelMode {
@@ -4481,7 +11432,13 @@ struct SearchPanel {
     focus: SearchFocus,
     collection_cursor: usize,
     tag_cursor: usize,
+    author_cursor: usize,
+    series_cursor: usize,
+    /// Editor for `ctx.library_query`, which is kept in sync with it.
+    query: TextInput,
     snapshot: Option<SearchSnapshot>,
+    /// Tag namespaces (lowercase, with the trailing slash) folded in the tag list.
+    folded_tags: std::collections::HashSet<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -4489,6 +11446,8 @@ enum SearchFocus {
     Query,
     Collections,
     Tags,
+    Authors,
+    Series,
 }
 
 impl SearchFocus {
@@ -4496,15 +11455,19 @@ impl SearchFocus {
         match self {
             SearchFocus::Query => SearchFocus::Collections,
             SearchFocus::Collections => SearchFocus::Tags,
-            SearchFocus::Tags => SearchFocus::Query,
+            SearchFocus::Tags => SearchFocus::Authors,
+            SearchFocus::Authors => SearchFocus::Series,
+            SearchFocus::Series => SearchFocus::Query,
         }
     }
 
     fn prev(self) -> Self {
         match self {
-            SearchFocus::Query => SearchFocus::Tags,
+            SearchFocus::Query => SearchFocus::Series,
             SearchFocus::Collections => SearchFocus::Query,
             SearchFocus::Tags => SearchFocus::Collections,
+            SearchFocus::Authors => SearchFocus::Tags,
+            SearchFocus::Series => SearchFocus::Authors,
         }
     }
 }
@@ -4513,9 +11476,16 @@ impl SearchFocus {
 struct SearchSnapshot {
     library_query: String,
     favorites_only: bool,
+    include_archived: bool,
+    status_filter: Option<ReadingStatus>,
+    added_within_days: Option<u32>,
+    kind_filter: Option<BookKind>,
     collection_filter: CollectionFilter,
+    collection_view: bool,
     tag_filters: Vec<String>,
     tag_match_mode: TagMatchMode,
+    author_filter: Option<String>,
+    series_filter: Option<String>,
     selected_path: Option<String>,
 }
 
@@ -4528,7 +11498,11 @@ impl Default for SearchPanel {
             focus: SearchFocus::Query,
             collection_cursor: 0,
             tag_cursor: 0,
+            author_cursor: 0,
+            series_cursor: 0,
+            query: TextInput::default(),
             snapshot: None,
+            folded_tags: std::collections::HashSet::new(),
         }
     }
 }
@@ -4537,6 +11511,44 @@ impl Default for SearchPanel {
 enum LabelManagerTab {
     Collections,
     Tags,
+    Authors,
+    Series,
+}
+
+impl LabelManagerTab {
+    const ALL: [LabelManagerTab; 4] = [
+        LabelManagerTab::Collections,
+        LabelManagerTab::Tags,
+        LabelManagerTab::Authors,
+        LabelManagerTab::Series,
+    ];
+
+    fn kind(self) -> TagKind {
+        match self {
+            LabelManagerTab::Collections => TagKind::Collection,
+            LabelManagerTab::Tags => TagKind::Tag,
+            LabelManagerTab::Authors => TagKind::Author,
+            LabelManagerTab::Series => TagKind::Series,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            LabelManagerTab::Collections => "Collections",
+            LabelManagerTab::Tags => "Tags",
+            LabelManagerTab::Authors => "Authors",
+            LabelManagerTab::Series => "Series",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            LabelManagerTab::Collections => LabelManagerTab::Tags,
+            LabelManagerTab::Tags => LabelManagerTab::Authors,
+            LabelManagerTab::Authors => LabelManagerTab::Series,
+            LabelManagerTab::Series => LabelManagerTab::Collections,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -4544,20 +11556,45 @@ struct LabelManagerPanel {
     tab: LabelManagerTab,
     collections_cursor: usize,
     tags_cursor: usize,
+    authors_cursor: usize,
+    series_cursor: usize,
     filter_editing: bool,
-    filter: String,
+    filter: TextInput,
     confirm_delete: Option<LabelDeleteTarget>,
     error: Option<String>,
 }
 
+impl LabelManagerPanel {
+    fn cursor(&self, tab: LabelManagerTab) -> usize {
+        match tab {
+            LabelManagerTab::Collections => self.collections_cursor,
+            LabelManagerTab::Tags => self.tags_cursor,
+            LabelManagerTab::Authors => self.authors_cursor,
+            LabelManagerTab::Series => self.series_cursor,
+        }
+    }
+
+    /// Cursor of the list that has focus.
+    fn cursor_mut(&mut self) -> &mut usize {
+        match self.tab {
+            LabelManagerTab::Collections => &mut self.collections_cursor,
+            LabelManagerTab::Tags => &mut self.tags_cursor,
+            LabelManagerTab::Authors => &mut self.authors_cursor,
+            LabelManagerTab::Series => &mut self.series_cursor,
+        }
+    }
+}
+
 impl Default for LabelManagerPanel {
     fn default() -> Self {
         Self {
             tab: LabelManagerTab::Collections,
             collections_cursor: 0,
             tags_cursor: 0,
+            authors_cursor: 0,
+            series_cursor: 0,
             filter_editing: false,
-            filter: String::new(),
+            filter: TextInput::default(),
             confirm_delete: None,
             error: None,
         }
@@ -4574,6 +11611,19 @@ struct LabelDeleteTarget {
 enum AssignFocus {
     Collections,
     Tags,
+    Author,
+    Series,
+}
+
+impl AssignFocus {
+    fn next(self) -> Self {
+        match self {
+            AssignFocus::Collections => AssignFocus::Tags,
+            AssignFocus::Tags => AssignFocus::Author,
+            AssignFocus::Author => AssignFocus::Series,
+            AssignFocus::Series => AssignFocus::Collections,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -4581,27 +11631,60 @@ struct AssignLabelsPanel {
     focus: AssignFocus,
     collection_cursor: usize,
     tag_cursor: usize,
-    collection_query: String,
-    tag_query: String,
+    author_cursor: usize,
+    series_cursor: usize,
+    collection_query: TextInput,
+    tag_query: TextInput,
+    author_query: TextInput,
+    /// Series name to look for, optionally followed by the book's number (`Dune #2`).
+    series_query: TextInput,
     query_editing: bool,
     book_path: Option<String>,
+    /// Marked library paths when assigning in bulk; empty for single-book mode.
+    bulk_paths: Vec<String>,
     staged_favorite: bool,
+    /// `None` while marked books differ and the status is left alone.
+    staged_status: Option<ReadingStatus>,
     staged: BookLabels,
+    initial_favorite: bool,
+    initial_status: Option<ReadingStatus>,
+    initial: BookLabels,
     error: Option<String>,
 }
 
+impl AssignLabelsPanel {
+    /// Filter of the list that has focus.
+    fn focused_query(&mut self) -> &mut TextInput {
+        match self.focus {
+            AssignFocus::Collections => &mut self.collection_query,
+            AssignFocus::Tags => &mut self.tag_query,
+            AssignFocus::Author => &mut self.author_query,
+            AssignFocus::Series => &mut self.series_query,
+        }
+    }
+}
+
 impl Default for AssignLabelsPanel {
     fn default() -> Self {
         Self {
             focus: AssignFocus::Collections,
             collection_cursor: 0,
             tag_cursor: 0,
-            collection_query: String::new(),
-            tag_query: String::new(),
+            author_cursor: 0,
+            series_cursor: 0,
+            collection_query: TextInput::default(),
+            tag_query: TextInput::default(),
+            author_query: TextInput::default(),
+            series_query: TextInput::default(),
             query_editing: false,
             book_path: None,
+            bulk_paths: Vec::new(),
             staged_favorite: false,
+            staged_status: None,
             staged: BookLabels::default(),
+            initial_favorite: false,
+            initial_status: None,
+            initial: BookLabels::default(),
             error: None,
         }
     }
@@ -4619,7 +11702,7 @@ struct LabelCatalogInputPanel {
     mode: LabelCatalogInputMode,
     kind: TagKind,
     from: Option<String>,
-    input: String,
+    input: TextInput,
     error: Option<String>,
 }
 
@@ -4630,7 +11713,7 @@ impl Default for LabelCatalogInputPanel {
             mode: LabelCatalogInputMode::Create,
             kind: TagKind::Tag,
             from: None,
-            input: String::new(),
+            input: TextInput::default(),
             error: None,
         }
     }
@@ -4649,27 +11732,221 @@ struct TagEntry {
     count: usize,
 }
 
+/// A row of the Filters tag list; a namespace counts the books with any tag under it.
+#[derive(Debug, Clone)]
+struct TagTreeEntry {
+    row: TagTreeRow,
+    count: usize,
+}
+
 #[derive(Debug, Clone, Default)]
 struct GotoPanel {
     open: bool,
-    input: String,
+    input: TextInput,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ExportPanel {
+    open: bool,
+    range: TextInput,
+    dir: TextInput,
+    dpi: TextInput,
+    focus: ExportField,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum ExportField {
+    #[default]
+    Range,
+    Dir,
+    Dpi,
+}
+
+impl ExportField {
+    fn next(self) -> Self {
+        match self {
+            ExportField::Range => ExportField::Dir,
+            ExportField::Dir => ExportField::Dpi,
+            ExportField::Dpi => ExportField::Range,
+        }
+    }
+
+    fn prev(self) -> Self {
+        match self {
+            ExportField::Range => ExportField::Dpi,
+            ExportField::Dir => ExportField::Range,
+            ExportField::Dpi => ExportField::Dir,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct HistoryPanel {
+    open: bool,
+    selected: usize,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct DuplicatesPanel {
+    open: bool,
+    /// Paths holding the same file, one group per document.
+    groups: Vec<Vec<String>>,
+    /// Copy under the cursor, counting through all groups in order.
+    selected: usize,
+    error: Option<String>,
+}
+
+impl DuplicatesPanel {
+    /// Group and position within it of the copy under the cursor.
+    fn cursor(&self) -> Option<(usize, usize)> {
+        let mut remaining = self.selected;
+        for (group_idx, group) in self.groups.iter().enumerate() {
+            if remaining < group.len() {
+                return Some((group_idx, remaining));
+            }
+            remaining -= group.len();
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct DeletePanel {
+    open: bool,
+    /// Library path of the book to delete.
+    path: Option<String>,
+    error: Option<String>,
+}
+
+/// The selected book's tags, offered to filter the library by.
+#[derive(Debug, Clone, Default)]
+struct BookTagPicker {
+    open: bool,
+    /// Title of the book the tags are from.
+    title: String,
+    tags: Vec<String>,
+    selected: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RelinkPanel {
+    open: bool,
+    /// Library path of the missing book being relinked.
+    path: Option<String>,
+    input: TextInput,
     error: Option<String>,
 }
 
+/// Pages shown on each side of the cursor in the thumbnail strip.
+const THUMBNAIL_RADIUS: u32 = 5;
+/// Rows taken by the thumbnail strip, borders included.
+const THUMBNAIL_STRIP_HEIGHT: u16 = 9;
+const THUMBNAIL_CACHE_MAX: usize = 96;
+
+/// Thumbnail strip along the bottom of the image-mode reader.
+#[derive(Default)]
+struct ThumbnailStrip {
+    open: bool,
+    cursor: u32,
+    /// Reader page the cursor was last moved to; turning pages elsewhere moves the cursor along.
+    synced_page: Option<u32>,
+    /// Low-resolution renders by (book path, page); `None` marks a page that failed to render.
+    images: std::collections::HashMap<(String, u32), Option<Arc<image::DynamicImage>>>,
+    pending: std::collections::HashSet<(String, u32)>,
+    /// Protocols for the visible thumbnails of the current book, with the cell size they fit.
+    protocols: std::collections::HashMap<u32, ((u16, u16), ImageProtocol)>,
+    protocols_book: Option<String>,
+}
+
+impl ThumbnailStrip {
+    fn close(&mut self) {
+        self.open = false;
+        self.protocols.clear();
+        // Queued renders still land in the cache, but nothing waits on them any more.
+        self.pending.clear();
+    }
+
+    fn follow(&mut self, page: u32) {
+        self.cursor = page;
+        self.synced_page = Some(page);
+    }
+
+    fn insert(
+        &mut self,
+        book_path: String,
+        page: u32,
+        image: Option<image::DynamicImage>,
+        current_book: Option<&str>,
+    ) {
+        let key = (book_path, page);
+        self.pending.remove(&key);
+        self.images.insert(key, image.map(Arc::new));
+        while self.images.len() > THUMBNAIL_CACHE_MAX {
+            // Other books go first, then the pages farthest from the cursor.
+            let cursor = self.cursor;
+            let Some(evict) = self
+                .images
+                .keys()
+                .max_by_key(|(path, page)| {
+                    (Some(path.as_str()) != current_book, page.abs_diff(cursor))
+                })
+                .cloned()
+            else {
+                break;
+            };
+            self.images.remove(&evict);
+            if Some(evict.0.as_str()) == current_book {
+                self.protocols.remove(&evict.1);
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 struct BookmarksPanel {
     open: bool,
     selected: usize,
+    input_open: bool,
+    input_page: u32,
+    input: TextInput,
+    /// Bookmark being relabeled; `None` when the input creates a new one.
+    editing: Option<Bookmark>,
+    error: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
 struct TocPanel {
     open: bool,
     selected: usize,
-    query: String,
+    query: TextInput,
     path: Option<String>,
     items: Vec<TocItem>,
     error: Option<String>,
+    /// Set while asking how deep `B` bookmarks chapter starts: entries at this depth
+    /// (0-based) or above.
+    bookmark_depth: Option<usize>,
+}
+
+const SEARCH_IN_BOOK_PAGES_PER_TICK: u32 = 4;
+const SEARCH_IN_BOOK_MAX_RESULTS: usize = 500;
+
+#[derive(Debug, Clone, Default)]
+struct SearchInBookPanel {
+    open: bool,
+    editing: bool,
+    input: TextInput,
+    /// Query of the running/finished search (may lag `input` while editing).
+    query: String,
+    path: Option<String>,
+    results: Vec<SearchHit>,
+    selected: usize,
+    next_page: u32,
+    total_pages: Option<u32>,
+    running: bool,
+    error: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -4677,9 +11954,13 @@ struct NotesPanel {
     open: bool,
     selected: usize,
     input_open: bool,
+    /// Index of the note the input replaces; `None` when adding.
+    editing: Option<usize>,
     input_page: u32,
-    input: String,
+    input: TextInput,
     error: Option<String>,
+    /// The input was opened from the notes sidebar; closing it closes the panel too.
+    from_sidebar: bool,
 }
 
 impl Default for NotesPanel {
@@ -4688,18 +11969,43 @@ impl Default for NotesPanel {
             open: false,
             selected: 0,
             input_open: false,
+            editing: None,
             input_page: 1,
-            input: String::new(),
+            input: TextInput::default(),
             error: None,
+            from_sidebar: false,
         }
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Clone, Default)]
 struct BookMetaCache {
     path: Option<String>,
     size_bytes: Option<u64>,
     page_count: Option<u32>,
+    /// A cover render was requested for `path`; false when there is no graphics protocol.
+    cover_requested: bool,
+    /// First page at `COVER_WIDTH_PX`: `None` while it renders, `Some(None)` when it failed.
+    cover: Option<Option<Arc<image::DynamicImage>>>,
+    /// Protocol for `cover`, with the cell size it was built for.
+    cover_protocol: Option<((u16, u16), ImageProtocol)>,
+}
+
+// `ImageProtocol` has no `Debug`, so the cover and its protocol are summarised.
+impl std::fmt::Debug for BookMetaCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BookMetaCache")
+            .field("path", &self.path)
+            .field("size_bytes", &self.size_bytes)
+            .field("page_count", &self.page_count)
+            .field("cover_requested", &self.cover_requested)
+            .field("cover", &self.cover.as_ref().map(Option::is_some))
+            .field(
+                "cover_protocol",
+                &self.cover_protocol.as_ref().map(|(size, _)| size),
+            )
+            .finish()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -4711,16 +12017,7 @@ struct ReaderRenderKey {
     height: u16,
 }
 
-#[derive(Clone)]
-struct CachedPageImage {
-    page: u32,
-    zoom_percent: u16,
-    render_width_px: u32,
-    font_size: (u16, u16),
-    image: Arc<image::DynamicImage>,
-}
-
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy)]
 struct ReaderImageTimings {
     total_ms: u128,
     rasterize_ms: Option<u128>,
@@ -4730,6 +12027,11 @@ struct ReaderImageTimings {
     viewport_px: (u32, u32),
     transmit_px: (u32, u32),
     render_width_px: u32,
+    /// Budgets the image quality chose for this viewport.
+    max_render_px: u64,
+    max_transmit_px: u64,
+    /// Filter the page was shrunk with, `Auto` already resolved.
+    filter: DownscaleFilter,
 }
 
 #[derive(Clone)]
@@ -4740,19 +12042,65 @@ struct ReaderPanel {
     page: u32,
     total_pages: Option<u32>,
     scroll: u16,
+    /// Rows the text-mode page was last drawn in; scrolling stops with the last line on them.
+    text_height: u16,
+    /// Columns a text page is scrolled right, for lines wider than the screen such as tables.
+    h_scroll: u16,
+    /// Columns the text-mode page was last drawn in.
+    text_width: u16,
+    /// Tenths of a line the text-mode page was last spaced at; see `reader_line_spacing`.
+    line_spacing: u16,
     image_zoom_percent: u16,
+    image_fit: ImageFit,
+    rotation: PageRotation,
     image_pan_x_px: u32,
     image_pan_y_px: u32,
     page_image: Option<CachedPageImage>,
-    page_image_cache: VecDeque<CachedPageImage>,
+    page_image_cache: PageImageCache,
     page_furniture: Option<PageFurniture>,
-    current_text: Option<String>,
+    /// Show the header and footer lines trimming cuts, struck through, instead of dropping
+    /// them. A debug aid for checking what the trimming takes.
+    show_trimmed_furniture: bool,
+    /// Outline read once when the book opens; shared by the header and the TOC panel.
+    toc: Vec<TocItem>,
+    toc_error: Option<String>,
+    /// Printed page labels, one per page, read when the book opens; empty when the book has
+    /// none or they are just the page numbers.
+    page_labels: Vec<String>,
+    /// Text-mode page (or placeholder) as screen lines, already wrapped and styled.
+    current_lines: Option<Vec<Line<'static>>>,
+    /// In-book search query to mark, with the 0-based page the search jumped to.
+    search_highlight: Option<(u32, String)>,
+    /// Scroll to the first highlighted match on the next render.
+    scroll_to_highlight: bool,
     current_image: Option<ImageProtocol>,
     last_error: Option<String>,
     notice: Option<String>,
     render_key: Option<ReaderRenderKey>,
     last_image_timings: Option<ReaderImageTimings>,
+    /// Page image requested from the render worker and not yet delivered.
+    pending_page_image: Option<PageImageKey>,
+    last_rasterize_ms: Option<u128>,
     next_kitty_image_id: u32,
+    /// Mode the open book is read in: its saved view state, else the global setting.
+    mode: ReaderMode,
+    text_mode: ReaderTextMode,
+    /// Two-page spread view (image mode only).
+    spread: bool,
+    /// In spread view, show page 1 alone and pair the rest from page 2.
+    spread_cover_alone: bool,
+    /// The open book has no text (a comic archive) and opens in image mode.
+    image_only: bool,
+    /// Image-mode view left for text mode with `m`, restored when `m` comes back to its page.
+    left_image_view: Option<LeftImageView>,
+}
+
+/// Where the reader was in image mode when it switched to text mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LeftImageView {
+    page: u32,
+    zoom_percent: u16,
+    pan_px: (u32, u32),
 }
 
 impl Default for ReaderPanel {
@@ -4764,39 +12112,205 @@ impl Default for ReaderPanel {
             page: 0,
             total_pages: None,
             scroll: 0,
+            text_height: 1,
+            h_scroll: 0,
+            text_width: 1,
+            line_spacing: 10,
             image_zoom_percent: 100,
+            image_fit: ImageFit::Page,
+            rotation: PageRotation::Upright,
             image_pan_x_px: 0,
             image_pan_y_px: 0,
             page_image: None,
-            page_image_cache: VecDeque::new(),
+            page_image_cache: PageImageCache::default(),
             page_furniture: None,
-            current_text: None,
+            show_trimmed_furniture: false,
+            toc: Vec::new(),
+            toc_error: None,
+            page_labels: Vec::new(),
+            current_lines: None,
+            search_highlight: None,
+            scroll_to_highlight: false,
             current_image: None,
             last_error: None,
             notice: None,
             render_key: None,
             last_image_timings: None,
+            pending_page_image: None,
+            last_rasterize_ms: None,
             next_kitty_image_id: 1,
+            mode: ReaderMode::Text,
+            text_mode: ReaderTextMode::Reflow,
+            spread: false,
+            spread_cover_alone: true,
+            image_only: false,
+            left_image_view: None,
         }
     }
 }
 
 impl ReaderPanel {
-    fn open_book(&mut self, book: &bookshelf_core::Book, ctx: &AppContext, engine: &Engine) {
+    fn open_book(
+        &mut self,
+        book: &bookshelf_core::Book,
+        ctx: &mut AppContext,
+        engine: &Engine,
+        picker: &mut Picker,
+    ) {
         self.open = true;
         self.book_path = Some(book.path.clone());
         self.book_title = Some(book.title.clone());
+        self.search_highlight = None;
+        ctx.record_book_opened(&book.path, unix_now_secs());
         self.page_image_cache.clear();
         self.page_furniture = None;
-        let saved = ctx.progress_by_path.get(&book.path).copied().unwrap_or(1);
-        self.page = saved.saturating_sub(1);
+        (self.toc, self.toc_error) = match engine.toc(book) {
+            Ok(items) => (items, None),
+            Err(err) => (Vec::new(), Some(err.to_string())),
+        };
+        self.page_labels = engine.page_labels(book).unwrap_or_default();
+        let saved = ctx
+            .progress_by_path
+            .get(&book.path)
+            .copied()
+            .unwrap_or(BookProgress::at_page(1));
+        self.page = saved.last_page.saturating_sub(1);
         self.total_pages = engine.page_count(book).ok();
         if let Some(total) = self.total_pages
             && total > 0
         {
             self.page = self.page.min(total.saturating_sub(1));
         }
+
+        // Per-book view state overrides the global reader settings while the book is open.
+        let state = ctx.reader_state_by_path.get(&book.path).copied();
+        self.mode = state.map_or(ctx.settings.reader_mode, |s| s.reader_mode);
+        self.text_mode = state.map_or(ctx.settings.reader_text_mode, |s| s.reader_text_mode);
+        self.image_zoom_percent = state.map_or(100, |s| s.image_zoom_percent.clamp(50, 400));
+        self.image_fit = state.map_or(ImageFit::Page, |s| s.image_fit);
+        self.rotation = state.map_or(PageRotation::Upright, |s| s.rotation);
+        self.image_only = engine.is_image_only(book);
+        self.left_image_view = None;
+        if self.image_only && image_protocol::image_supported(picker) {
+            image_protocol::prefer_graphics_protocol(picker);
+            self.mode = ReaderMode::Image;
+        }
+        self.invalidate_render();
+        if self.image_only && !image_protocol::image_supported(picker) {
+            self.notice = Some(
+                "image protocol not detected; comic pages need image mode (k: graphics reader)"
+                    .to_string(),
+            );
+        }
+        // The position within the page only applies if the saved page itself was restored.
+        self.line_spacing = text_line_spacing(self.text_mode, &ctx.settings);
+        if self.page == saved.last_page.saturating_sub(1) {
+            self.scroll = self.spaced_rows(saved.scroll.unwrap_or(0));
+            (self.image_pan_x_px, self.image_pan_y_px) = saved.pan_px.unwrap_or((0, 0));
+        }
+    }
+
+    fn close_book(&mut self) {
+        *self = ReaderPanel::default();
+    }
+
+    fn cycle_text_mode(&mut self) {
+        self.text_mode = match self.text_mode {
+            ReaderTextMode::Raw => ReaderTextMode::Wrap,
+            ReaderTextMode::Wrap => ReaderTextMode::Reflow,
+            ReaderTextMode::Reflow => ReaderTextMode::Raw,
+        };
+    }
+
+    fn on_last_page(&self, mode: ReaderMode) -> bool {
+        let Some(total) = self.total_pages.filter(|total| *total > 0) else {
+            return false;
+        };
+        let last_shown = if mode == ReaderMode::Image && self.spread {
+            let (left, right) = spread_pages(self.page, self.total_pages, self.spread_cover_alone);
+            right.unwrap_or(left)
+        } else {
+            self.page
+        };
+        last_shown + 1 >= total
+    }
+
+    fn view_state(&self) -> ReaderViewState {
+        ReaderViewState {
+            reader_mode: self.mode,
+            reader_text_mode: self.text_mode,
+            image_zoom_percent: self.image_zoom_percent,
+            image_fit: self.image_fit,
+            rotation: self.rotation,
+        }
+    }
+
+    /// Where the reader is within the page in `mode`, for a bookmark to come back to; `None`
+    /// at the top of the page with nothing zoomed or panned.
+    fn bookmark_view(&self, mode: ReaderMode) -> Option<BookmarkView> {
+        let view = match mode {
+            ReaderMode::Text => BookmarkView::Text {
+                scroll: self.unspaced_lines(self.scroll),
+            },
+            ReaderMode::Image => BookmarkView::Image {
+                zoom_percent: self.image_zoom_percent,
+                pan_px: (self.image_pan_x_px, self.image_pan_y_px),
+            },
+        };
+        let at_top = matches!(
+            view,
+            BookmarkView::Text { scroll: 0 }
+                | BookmarkView::Image {
+                    zoom_percent: 100,
+                    pan_px: (0, 0)
+                }
+        );
+        (!at_top).then_some(view)
+    }
+
+    /// Opens the bookmarked page where the bookmark was made in it. Bookmarks without a view,
+    /// or made in the other reader mode, open at the top of the page.
+    fn jump_to_bookmark(&mut self, bookmark: &Bookmark, mode: ReaderMode) {
+        self.page = bookmark.page.saturating_sub(1);
         self.invalidate_render();
+        match (bookmark.view, mode) {
+            (Some(BookmarkView::Text { scroll }), ReaderMode::Text) => {
+                self.scroll = self.spaced_rows(scroll);
+            }
+            (
+                Some(BookmarkView::Image {
+                    zoom_percent,
+                    pan_px,
+                }),
+                ReaderMode::Image,
+            ) => {
+                self.set_image_zoom_percent(zoom_percent);
+                (self.image_pan_x_px, self.image_pan_y_px) = pan_px;
+            }
+            _ => {}
+        }
+    }
+
+    fn progress(&self) -> BookProgress {
+        BookProgress {
+            last_page: self.page.saturating_add(1),
+            scroll: Some(self.unspaced_lines(self.scroll)),
+            pan_px: Some((self.image_pan_x_px, self.image_pan_y_px)),
+        }
+    }
+
+    /// Screen rows down to text line `line` at the current line spacing. Saved positions are
+    /// kept in lines of text, so they hold at any spacing.
+    fn spaced_rows(&self, line: u16) -> u16 {
+        let rows = u32::from(line) * u32::from(self.line_spacing.max(10)) / 10;
+        u16::try_from(rows).unwrap_or(u16::MAX)
+    }
+
+    /// The text line shown at screen row `rows`; a blank spacing row counts as the line after it.
+    fn unspaced_lines(&self, rows: u16) -> u16 {
+        let spacing = u32::from(self.line_spacing.max(10));
+        let line = (u32::from(rows) * 10).div_ceil(spacing);
+        u16::try_from(line).unwrap_or(u16::MAX)
     }
 
     fn current_book(&self) -> Option<bookshelf_core::Book> {
@@ -4805,15 +12319,19 @@ impl ReaderPanel {
             title: self.book_title.clone()?,
             last_opened: None,
             favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
         })
     }
 
     fn invalidate_render(&mut self) {
         self.scroll = 0;
+        self.h_scroll = 0;
         self.image_pan_x_px = 0;
         self.image_pan_y_px = 0;
         self.page_image = None;
-        self.current_text = None;
+        self.current_lines = None;
         self.current_image = None;
         self.last_error = None;
         self.notice = None;
@@ -4821,37 +12339,128 @@ impl ReaderPanel {
         self.last_image_timings = None;
     }
 
-    fn cache_page_image(&mut self, image: CachedPageImage) {
-        const MAX: usize = 3;
-        if let Some(pos) = self.page_image_cache.iter().position(|c| {
-            c.page == image.page
-                && c.zoom_percent == image.zoom_percent
-                && c.render_width_px == image.render_width_px
-                && c.font_size == image.font_size
-        }) {
-            let _ = self.page_image_cache.remove(pos);
+    /// Drops what the current reader mode drew before switching to the other one. Unlike
+    /// `invalidate_render`, the page bitmap, zoom and pan stay, so switching back is instant.
+    fn invalidate_mode_render(&mut self) {
+        self.scroll = 0;
+        self.h_scroll = 0;
+        self.current_lines = None;
+        self.current_image = None;
+        self.last_error = None;
+        self.notice = None;
+        self.render_key = None;
+        self.last_image_timings = None;
+    }
+
+    /// Switches from image to text mode, remembering the image view to come back to.
+    fn leave_image_mode(&mut self) {
+        self.left_image_view = Some(LeftImageView {
+            page: self.page,
+            zoom_percent: self.image_zoom_percent,
+            pan_px: (self.image_pan_x_px, self.image_pan_y_px),
+        });
+        self.invalidate_mode_render();
+    }
+
+    /// Switches from text to image mode, back at the zoom and pan image mode was left with when
+    /// the page is still the same.
+    fn enter_image_mode(&mut self) {
+        if let Some(view) = self.left_image_view.take().filter(|v| v.page == self.page) {
+            if view.zoom_percent != self.image_zoom_percent {
+                self.image_zoom_percent = view.zoom_percent;
+                self.page_image = None;
+            }
+            (self.image_pan_x_px, self.image_pan_y_px) = view.pan_px;
+        }
+        self.invalidate_mode_render();
+    }
+
+    /// What the text-mode placeholder for a page without text offers to do instead.
+    fn non_text_hint(&self, image_ok: bool) -> &'static str {
+        if !image_ok {
+            "image/chart (k: graphics reader)"
+        } else if self
+            .left_image_view
+            .is_some_and(|view| view.page == self.page)
+        {
+            "image/chart (m: back to image)"
+        } else {
+            "image/chart (m: image mode)"
+        }
+    }
+
+    /// Takes a finished worker render. Returns `true` when the reader needs a redraw; results for
+    /// another book or a page/zoom the reader already left are dropped.
+    fn accept_page_image(&mut self, done: PageImageDone, engine: &Engine) -> bool {
+        if !self.open
+            || self.book_path.as_deref() != Some(done.book_path.as_str())
+            || self.pending_page_image != Some(done.key)
+        {
+            return false;
         }
-        self.page_image_cache.push_front(image);
-        while self.page_image_cache.len() > MAX {
-            self.page_image_cache.pop_back();
+        self.pending_page_image = None;
+        match done.image {
+            Ok(image) => {
+                // Cached once shown, after a page fit to the frame is scaled down to it.
+                self.page_image = Some(CachedPageImage {
+                    key: done.key,
+                    render_width_px: done.render_width_px,
+                    image: Arc::new(image),
+                });
+                self.last_rasterize_ms = Some(done.rasterize_ms);
+                self.current_lines = None;
+                self.render_key = None;
+            }
+            Err(err) => {
+                let fallback = self
+                    .current_book()
+                    .and_then(|book| engine.render_page_text(&book, done.key.page).ok())
+                    .unwrap_or_else(|| "no text found".to_string());
+                self.page_image = None;
+                self.current_lines = Some(reader_text::plain_lines(&format!(
+                    "(image render failed; showing text)\n(error: {err})\n\n{fallback}"
+                )));
+                // Keep `render_key` so the placeholder frame shows the fallback instead of
+                // retrying.
+                self.current_image = None;
+                self.last_error = None;
+            }
         }
+        true
+    }
+
+    /// Drops rendered page images, which were shrunk with the old downscale filter, so the
+    /// page is drawn again with the new one.
+    fn forget_page_images(&mut self) {
+        self.page_image_cache.clear();
+        self.page_image = None;
+        self.current_image = None;
+        self.render_key = None;
+    }
+
+    fn trim_page_image_cache(&mut self, settings: &bookshelf_core::Settings) {
+        self.page_image_cache
+            .trim(settings.image_cache_limit.max_bytes());
     }
 
     fn ensure_rendered(
         &mut self,
         ctx: &AppContext,
         engine: &Engine,
+        page_render: &mut PageRenderWorker,
         picker: &Picker,
         width: u16,
         height: u16,
     ) {
         let width = width.max(1);
         let height = height.max(1);
-        let mode = ctx.settings.reader_mode;
-        let text_mode = ctx.settings.reader_text_mode;
+        self.text_height = height;
+        self.text_width = width;
+        let mode = self.mode;
+        let text_mode = self.text_mode;
 
         let Some(book) = self.current_book() else {
-            self.current_text = None;
+            self.current_lines = None;
             self.last_error = Some("no book".to_string());
             self.render_key = Some(ReaderRenderKey {
                 page: self.page,
@@ -4863,6 +12472,11 @@ impl ReaderPanel {
             return;
         };
 
+        if mode == ReaderMode::Image && self.spread {
+            // Jumps (goto, bookmarks, search) may land on the right-hand page of a spread.
+            self.page = spread_pages(self.page, self.total_pages, self.spread_cover_alone).0;
+        }
+
         let key = ReaderRenderKey {
             page: self.page,
             mode,
@@ -4871,7 +12485,10 @@ impl ReaderPanel {
             height,
         };
 
-        if (self.current_text.is_some() || self.current_image.is_some())
+        // A failed render is not retried on every frame either; only a new key renders again.
+        if (self.current_lines.is_some()
+            || self.current_image.is_some()
+            || self.last_error.is_some())
             && self.render_key == Some(key)
         {
             return;
@@ -4888,111 +12505,121 @@ impl ReaderPanel {
                 let viewport_h_px = u32::from(height)
                     .saturating_mul(u32::from(font_h_px))
                     .max(1);
+                let image_viewport = Some(ImageViewport {
+                    cell_px: (font_w_px, font_h_px),
+                    cells: (width, height),
+                });
+                let quality = ctx.settings.kitty_image_quality;
+                let max_render_px = quality.max_render_pixels(image_viewport);
+                let max_transmit_px = image_protocol::max_transmit_pixels(
+                    picker,
+                    quality,
+                    image_viewport,
+                    ctx.settings.remote_mode,
+                );
 
-                let fit_page_to_frame = self.image_zoom_percent == 100
+                let fit_page_to_frame = self.image_fit == ImageFit::Page
+                    && self.image_zoom_percent == 100
                     && self.image_pan_x_px == 0
                     && self.image_pan_y_px == 0;
 
-                let (page_w_pt, page_h_pt) = engine
-                    .page_size_points(&book, self.page)
-                    .unwrap_or((1.0, 1.0));
-                let page_ratio = (page_w_pt as f64 / page_h_pt.max(1.0) as f64).clamp(0.05, 20.0);
-
-                let base_render_width_px = if fit_page_to_frame {
-                    let fit_w = (viewport_h_px as f64 * page_ratio).round().max(1.0) as u32;
-                    viewport_w_px.min(fit_w)
+                let spread_page = if self.spread {
+                    spread_pages(self.page, self.total_pages, self.spread_cover_alone).1
                 } else {
-                    viewport_w_px
+                    None
                 };
 
-                let render_width_px = (u64::from(base_render_width_px)
-                    .saturating_mul(u64::from(self.image_zoom_percent.max(1))))
-                    / 100;
-                let render_width_px = render_width_px.clamp(1, i32::MAX as u64) as u32;
-
-                let max_render_pixels = ctx.settings.kitty_image_quality.max_render_pixels().max(1);
-                const MAX_RENDER_WIDTH_PX: u32 = 8192;
-                let max_width_by_pixels = ((max_render_pixels as f64) * page_ratio)
-                    .sqrt()
-                    .floor()
-                    .max(1.0) as u32;
-                let render_width_px = render_width_px
-                    .min(MAX_RENDER_WIDTH_PX)
-                    .min(max_width_by_pixels)
-                    .max(1);
-
-                let need_new_page_image = match self.page_image.as_ref() {
-                    None => true,
-                    Some(cached) => {
-                        cached.page != self.page
-                            || cached.zoom_percent != self.image_zoom_percent
-                            || cached.render_width_px != render_width_px
-                            || cached.font_size != (font_w_px, font_h_px)
-                    }
+                let image_key = PageImageKey {
+                    page: self.page,
+                    zoom_percent: self.image_zoom_percent,
+                    viewport_px: (viewport_w_px, viewport_h_px),
+                    fit: self.image_fit,
+                    fit_page_to_frame,
+                    max_render_pixels: max_render_px,
+                    font_size: (font_w_px, font_h_px),
+                    spread_page,
+                    invert: ctx.settings.reader_invert_colors,
+                    auto_crop: if self.image_pan_x_px == 0 && self.image_pan_y_px == 0 {
+                        ctx.settings.auto_crop
+                    } else {
+                        AutoCrop::Off
+                    },
+                    rotation: self.rotation,
                 };
 
-                let mut rasterize_ms: Option<u128> = None;
-                if need_new_page_image {
-                    if let Some(pos) = self.page_image_cache.iter().position(|c| {
-                        c.page == self.page
-                            && c.zoom_percent == self.image_zoom_percent
-                            && c.render_width_px == render_width_px
-                            && c.font_size == (font_w_px, font_h_px)
-                    }) && let Some(cached) = self.page_image_cache.remove(pos)
-                    {
+                if self.page_image.as_ref().map(|c| c.key) != Some(image_key) {
+                    // A hit stays cached, so flipping between rotations or pages stays instant.
+                    if let Some(cached) = self.page_image_cache.get(image_key) {
                         self.page_image = Some(cached);
                     } else {
-                        let rasterize_start = Instant::now();
-                        match render_page_image(engine, &book, self.page, render_width_px) {
-                            Ok(image) => {
-                                let cached = CachedPageImage {
-                                    page: self.page,
-                                    zoom_percent: self.image_zoom_percent,
-                                    render_width_px,
-                                    font_size: (font_w_px, font_h_px),
-                                    image: Arc::new(image),
-                                };
-                                self.cache_page_image(cached.clone());
-                                self.page_image = Some(cached);
-                                rasterize_ms = Some(rasterize_start.elapsed().as_millis());
-                            }
-                            Err(err) => {
-                                self.page_image = None;
-                                let fallback = engine
-                                    .render_page_text(&book, self.page)
-                                    .unwrap_or_else(|_| "no text found".to_string());
-                                self.current_text = Some(format!(
-                                    "(image render failed; showing text)\n(error: {err})\n\n{fallback}"
-                                ));
-                                self.current_image = None;
-                                self.last_error = None;
-                                self.render_key = Some(key);
-                                return;
-                            }
+                        if self.pending_page_image != Some(image_key) {
+                            self.pending_page_image = Some(image_key);
+                            page_render.request(PageImageJob {
+                                book: book.clone(),
+                                key: image_key,
+                            });
                         }
+                        self.page_image = None;
+                        self.current_lines = Some(reader_text::plain_lines(&format!(
+                            "rendering page {}…",
+                            self.page.saturating_add(1)
+                        )));
+                        self.current_image = None;
+                        self.last_error = None;
+                        self.render_key = Some(key);
+                        return;
                     }
                 }
+                let rasterize_ms = self.last_rasterize_ms.take();
+                let render_width_px = self
+                    .page_image
+                    .as_ref()
+                    .map_or(0, |cached| cached.render_width_px);
 
                 let size = Rect::new(0, 0, width, height);
+                let filter = ctx
+                    .settings
+                    .downscale_filter
+                    .for_quality(ctx.settings.kitty_image_quality);
                 let protocol_start = Instant::now();
                 let mut downscale_ms = 0;
                 let (protocol_result, viewport_ms, transmit_px) = if fit_page_to_frame {
-                    let cached = match self.page_image.as_ref() {
+                    let cached = match self.page_image.as_mut() {
                         Some(cached) => cached,
                         None => {
-                            self.current_text = Some("no image cached".to_string());
+                            self.current_lines = Some(reader_text::plain_lines("no image cached"));
                             self.current_image = None;
                             self.last_error = None;
                             self.render_key = Some(key);
                             return;
                         }
                     };
+                    // The whole page is on screen and never panned, so the full render is
+                    // only kept at frame size.
+                    let downscale_start = Instant::now();
+                    if let Some(fitted) =
+                        fit_to_frame(&cached.image, viewport_w_px, viewport_h_px, filter)
+                    {
+                        cached.image = Arc::new(fitted);
+                        downscale_ms = downscale_start.elapsed().as_millis();
+                    }
+                    let mut fit_image = (*cached.image).clone();
+                    // Frame-size images already fit kitty's budget unless remote mode shrinks it.
+                    if picker.protocol_type() == ProtocolType::Sixel || ctx.settings.remote_mode {
+                        let downscale_start = Instant::now();
+                        if let Some(resized) =
+                            downscale_to_pixels(&fit_image, max_transmit_px, filter)
+                        {
+                            fit_image = resized;
+                            downscale_ms += downscale_start.elapsed().as_millis();
+                        }
+                    }
+                    let (w, h) = (fit_image.width(), fit_image.height());
                     let proto = picker.new_protocol(
-                        (*cached.image).clone(),
+                        fit_image,
                         size,
-                        Resize::Fit(Some(image::imageops::FilterType::Triangle)),
+                        Resize::Fit(Some(filter_type(filter))),
                     );
-                    let (w, h) = (cached.image.width(), cached.image.height());
                     (proto, 0, (w, h))
                 } else {
                     let viewport_start = Instant::now();
@@ -5000,7 +12627,8 @@ impl ReaderPanel {
                         let cached = match self.page_image.as_ref() {
                             Some(cached) => cached,
                             None => {
-                                self.current_text = Some("no image cached".to_string());
+                                self.current_lines =
+                                    Some(reader_text::plain_lines("no image cached"));
                                 self.current_image = None;
                                 self.last_error = None;
                                 self.render_key = Some(key);
@@ -5013,6 +12641,7 @@ impl ReaderPanel {
                             viewport_h_px,
                             self.image_pan_x_px,
                             self.image_pan_y_px,
+                            image_key.invert,
                         )
                     };
                     let viewport_ms = viewport_start.elapsed().as_millis();
@@ -5021,32 +12650,18 @@ impl ReaderPanel {
 
                     let kitty_ok = image_protocol::kitty_supported(picker);
                     let image_ok = image_protocol::image_supported(picker);
-                    let max_transmit_px = ctx.settings.kitty_image_quality.max_transmit_pixels();
-                    let (transmit_image, transmit_px) = {
-                        let px = u64::from(view_image.width())
-                            .saturating_mul(u64::from(view_image.height()));
-                        if image_ok && px > max_transmit_px {
-                            let scale = (max_transmit_px as f64 / px.max(1) as f64)
-                                .sqrt()
-                                .clamp(0.01, 1.0);
-                            let new_w =
-                                ((view_image.width() as f64) * scale).round().max(1.0) as u32;
-                            let new_h =
-                                ((view_image.height() as f64) * scale).round().max(1.0) as u32;
-                            let downscale_start = Instant::now();
-                            let resized = view_image.resize_exact(
-                                new_w,
-                                new_h,
-                                image::imageops::FilterType::Triangle,
-                            );
+                    let downscale_start = Instant::now();
+                    let transmit_image = match image_ok
+                        .then(|| downscale_to_pixels(&view_image, max_transmit_px, filter))
+                        .flatten()
+                    {
+                        Some(resized) => {
                             downscale_ms = downscale_start.elapsed().as_millis();
-                            (resized, (new_w, new_h))
-                        } else {
-                            let w = view_image.width();
-                            let h = view_image.height();
-                            (view_image, (w, h))
+                            resized
                         }
+                        None => view_image,
                     };
+                    let transmit_px = (transmit_image.width(), transmit_image.height());
 
                     let proto = if kitty_ok {
                         let cols = u16::try_from(
@@ -5080,10 +12695,15 @@ impl ReaderPanel {
                     (proto, viewport_ms, transmit_px)
                 };
 
+                if let Some(cached) = self.page_image.clone() {
+                    self.page_image_cache
+                        .insert(cached, ctx.settings.image_cache_limit.max_bytes());
+                }
+
                 match protocol_result {
                     Ok(protocol) => {
                         let protocol_ms = protocol_start.elapsed().as_millis();
-                        self.current_text = None;
+                        self.current_lines = None;
                         self.current_image = Some(protocol);
                         self.last_error = None;
                         self.last_image_timings = Some(ReaderImageTimings {
@@ -5095,6 +12715,9 @@ impl ReaderPanel {
                             viewport_px: (viewport_w_px, viewport_h_px),
                             transmit_px,
                             render_width_px,
+                            max_render_px,
+                            max_transmit_px,
+                            filter,
                         });
                     }
                     Err(err) => {
@@ -5102,9 +12725,9 @@ impl ReaderPanel {
                             .render_page_text(&book, self.page)
                             .unwrap_or_else(|_| "no text found".to_string());
                         let protocol_ms = protocol_start.elapsed().as_millis();
-                        self.current_text = Some(format!(
+                        self.current_lines = Some(reader_text::plain_lines(&format!(
                             "(image protocol failed; showing text)\n(error: {err})\n\n{fallback}"
-                        ));
+                        )));
                         self.current_image = None;
                         self.last_error = None;
                         self.last_image_timings = Some(ReaderImageTimings {
@@ -5116,6 +12739,9 @@ impl ReaderPanel {
                             viewport_px: (viewport_w_px, viewport_h_px),
                             transmit_px,
                             render_width_px,
+                            max_render_px,
+                            max_transmit_px,
+                            filter,
                         });
                     }
                 }
@@ -5126,47 +12752,95 @@ impl ReaderPanel {
                     && ctx.settings.reader_trim_headers_footers
                 {
                     if self.page_furniture.is_none() {
-                        self.page_furniture = engine.detect_page_furniture(&book).ok();
+                        self.page_furniture = engine
+                            .detect_page_furniture_with(&book, furniture_config(&ctx.settings))
+                            .ok();
                     }
                     self.page_furniture.as_ref()
                 } else {
                     None
                 };
 
+                let layout = reader_text_layout(&ctx.settings);
                 match engine.render_page_for_reader(
-                    &book, self.page, mode, text_mode, furniture, width, height,
+                    &book, self.page, mode, text_mode, layout, furniture, width, height,
                 ) {
                     Ok(text) => {
-                        let text = if is_non_text_page(&text) {
-                            let image_ok = image_protocol::image_supported(picker);
-                            let hint = if image_ok {
-                                "image/chart (m: image mode)"
-                            } else {
-                                "image/chart (k: kitty-reader)"
-                            };
-                            non_text_placeholder(width, height, hint)
+                        let lines = if is_non_text_page(&text) {
+                            let hint = self.non_text_hint(image_protocol::image_supported(picker));
+                            reader_text::plain_lines(&non_text_placeholder(width, height, hint))
                         } else {
-                            match text_mode {
+                            let column = text_column_width(ctx.settings.reader_text_width, width);
+                            self.line_spacing = text_line_spacing(text_mode, &ctx.settings);
+                            let text = match text_mode {
                                 ReaderTextMode::Raw => text,
-                                ReaderTextMode::Wrap => {
-                                    wrap_preserving_lines(&text, width as usize)
-                                }
-                                ReaderTextMode::Reflow => wrap_reflow_text(&text, width as usize),
+                                ReaderTextMode::Wrap => center_text_block(
+                                    &wrap_preserving_lines(&text, column),
+                                    column,
+                                    width as usize,
+                                ),
+                                ReaderTextMode::Reflow => center_text_block(
+                                    &wrap_reflow_text(&text, column),
+                                    column,
+                                    width as usize,
+                                ),
+                            };
+                            let text = reader_text::space_lines(&text, self.line_spacing);
+                            let query = self
+                                .search_highlight
+                                .as_ref()
+                                .filter(|(page, _)| *page == self.page)
+                                .map(|(_, query)| query.as_str());
+                            let (page_lines, first_match) = reader_text::styled_page_lines(
+                                &text,
+                                query,
+                                Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+                            );
+                            let cut = furniture
+                                .and_then(|furniture| {
+                                    engine
+                                        .page_furniture_cut(&book, self.page, layout, furniture)
+                                        .ok()
+                                })
+                                .unwrap_or_default();
+                            let mut lines = Vec::with_capacity(page_lines.len() + 1);
+                            if self.show_trimmed_furniture {
+                                lines.extend(
+                                    cut.header
+                                        .iter()
+                                        .map(|line| reader_text::trimmed_furniture_line(line)),
+                                );
+                            } else if !cut.is_empty() {
+                                lines.push(reader_text::furniture_marker_line());
+                            }
+                            let offset = lines.len();
+                            lines.extend(page_lines);
+                            if self.show_trimmed_furniture {
+                                lines.extend(
+                                    cut.footer
+                                        .iter()
+                                        .map(|line| reader_text::trimmed_furniture_line(line)),
+                                );
                             }
+                            if std::mem::take(&mut self.scroll_to_highlight)
+                                && let Some(idx) = first_match
+                            {
+                                // Keep a line of context above the match when there is one.
+                                self.scroll = (idx + offset).saturating_sub(1) as u16;
+                            }
+                            lines
                         };
-                        let lines = text.lines().count() as u16;
-                        if lines == 0 {
-                            self.scroll = 0;
-                        } else {
-                            self.scroll = self.scroll.min(lines.saturating_sub(1));
-                        }
-                        self.current_text = Some(text);
+                        self.scroll = self
+                            .scroll
+                            .min(reader_text::max_text_scroll(lines.len(), height));
+                        self.current_lines = Some(lines);
+                        self.h_scroll = self.h_scroll.min(self.text_overflow());
                         self.current_image = None;
                         self.last_error = None;
                         self.last_image_timings = None;
                     }
                     Err(err) => {
-                        self.current_text = None;
+                        self.current_lines = None;
                         self.current_image = None;
                         self.last_error = Some(err.to_string());
                         self.last_image_timings = None;
@@ -5178,34 +12852,164 @@ impl ReaderPanel {
         self.render_key = Some(key);
     }
 
-    fn next_page(&mut self) {
+    /// Extracted text of the current page, before wrapping for the screen. Read from the book
+    /// rather than `current_lines`, which holds wrapped lines or the non-text placeholder.
+    fn page_text(&mut self, ctx: &AppContext, engine: &Engine) -> anyhow::Result<String> {
+        let Some(book) = self.current_book() else {
+            anyhow::bail!("no book");
+        };
+        let text_mode = self.text_mode;
+        let furniture =
+            if text_mode != ReaderTextMode::Raw && ctx.settings.reader_trim_headers_footers {
+                if self.page_furniture.is_none() {
+                    self.page_furniture = engine
+                        .detect_page_furniture_with(&book, furniture_config(&ctx.settings))
+                        .ok();
+                }
+                self.page_furniture.as_ref()
+            } else {
+                None
+            };
+        engine.render_page_text_for_reader(
+            &book,
+            self.page,
+            text_mode,
+            reader_text_layout(&ctx.settings),
+            furniture,
+        )
+    }
+
+    /// Returns whether the page changed; the last page stays rendered as it is.
+    fn next_page(&mut self) -> bool {
         let Some(total) = self.total_pages else {
             self.page = self.page.saturating_add(1);
             self.invalidate_render();
-            return;
+            return true;
         };
-        if total == 0 {
-            return;
+        if self.page.saturating_add(1) >= total {
+            return false;
+        }
+        self.page += 1;
+        self.invalidate_render();
+        true
+    }
+
+    /// Returns whether the page changed; the first page stays rendered as it is.
+    fn prev_page(&mut self) -> bool {
+        if self.page == 0 {
+            return false;
+        }
+        self.page -= 1;
+        self.invalidate_render();
+        true
+    }
+
+    fn next_spread(&mut self) -> bool {
+        let (left, right) = spread_pages(self.page, self.total_pages, self.spread_cover_alone);
+        let next = right.unwrap_or(left).saturating_add(1);
+        if self.total_pages.is_some_and(|total| next >= total) {
+            return false;
         }
-        self.page = (self.page + 1).min(total - 1);
+        self.page = next;
         self.invalidate_render();
+        true
     }
 
-    fn prev_page(&mut self) {
-        self.page = self.page.saturating_sub(1);
+    fn prev_spread(&mut self) -> bool {
+        let (left, _) = spread_pages(self.page, self.total_pages, self.spread_cover_alone);
+        if left == 0 {
+            return false;
+        }
+        self.page = spread_pages(left - 1, self.total_pages, self.spread_cover_alone).0;
         self.invalidate_render();
+        true
     }
 
-    fn scroll_up(&mut self) {
+    /// Returns whether the view moved.
+    fn scroll_up(&mut self) -> bool {
+        let before = self.scroll;
         self.scroll = self.scroll.saturating_sub(1);
+        self.scroll != before
     }
 
-    fn scroll_down(&mut self) {
-        let Some(text) = &self.current_text else {
-            return;
+    /// Text-mode lines from the scroll position down, or the error/loading message.
+    fn visible_text(&self) -> Text<'static> {
+        match &self.current_lines {
+            Some(lines) => Text::from(
+                lines
+                    .iter()
+                    .skip(self.scroll as usize)
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            ),
+            None => Text::from(reader_text::plain_lines(
+                self.last_error.as_deref().unwrap_or("loading..."),
+            )),
+        }
+    }
+
+    /// Returns whether the view moved.
+    fn scroll_down(&mut self) -> bool {
+        let Some(lines) = &self.current_lines else {
+            return false;
+        };
+        let before = self.scroll;
+        self.scroll = self
+            .scroll
+            .saturating_add(1)
+            .min(reader_text::max_text_scroll(lines.len(), self.text_height));
+        self.scroll != before
+    }
+
+    /// Moves a text page by one screen less the overlap. Returns whether the view moved.
+    fn scroll_text_page(&mut self, down: bool) -> bool {
+        let Some(lines) = &self.current_lines else {
+            return false;
+        };
+        let before = self.scroll;
+        let step = reader_text::text_page_step(self.text_height, self.line_spacing);
+        self.scroll = if down {
+            self.scroll
+                .saturating_add(step)
+                .min(reader_text::max_text_scroll(lines.len(), self.text_height))
+        } else {
+            self.scroll.saturating_sub(step)
+        };
+        self.scroll != before
+    }
+
+    /// Columns the widest line of the text page runs past the screen; only lines kept as they
+    /// are, such as table rows, can.
+    fn text_overflow(&self) -> u16 {
+        let widest = self
+            .current_lines
+            .iter()
+            .flatten()
+            .map(Line::width)
+            .max()
+            .unwrap_or(0);
+        u16::try_from(widest)
+            .unwrap_or(u16::MAX)
+            .saturating_sub(self.text_width)
+    }
+
+    /// Scrolls a text page sideways. Returns whether the view moved.
+    fn pan_text(&mut self, right: bool) -> bool {
+        let before = self.h_scroll;
+        self.h_scroll = if right {
+            self.h_scroll
+                .saturating_add(reader_text::TEXT_PAN_COLUMNS)
+                .min(self.text_overflow())
+        } else {
+            self.h_scroll.saturating_sub(reader_text::TEXT_PAN_COLUMNS)
         };
-        let lines = text.lines().count() as u16;
-        self.scroll = (self.scroll + 1).min(lines.saturating_sub(1));
+        self.h_scroll != before
+    }
+
+    /// The page frame's scroll position, while a text page is taller than the screen.
+    fn text_scroll_label(&self) -> Option<String> {
+        let lines = self.current_lines.as_ref()?;
+        reader_text::text_scroll_label(self.scroll, lines.len(), self.text_height)
     }
 
     fn pan_image_by_cells(&mut self, picker: &Picker, dx_cols: i32, dy_rows: i32) {
@@ -5246,6 +13050,28 @@ impl ReaderPanel {
         self.notice = Some("zoom: 100%".to_string());
     }
 
+    /// Switches to the next fit mode, starting again from the top-left of the page.
+    fn cycle_image_fit(&mut self) {
+        self.image_fit = self.image_fit.next();
+        self.image_pan_x_px = 0;
+        self.image_pan_y_px = 0;
+        self.page_image = None;
+        self.current_image = None;
+        self.render_key = None;
+        self.notice = Some(format!("fit: {}", self.image_fit));
+    }
+
+    /// Turns every page of the book, starting again from the top-left. Earlier turns stay in
+    /// the page cache, so turning back shows them without rendering again.
+    fn set_rotation(&mut self, rotation: PageRotation) {
+        self.rotation = rotation;
+        self.image_pan_x_px = 0;
+        self.image_pan_y_px = 0;
+        self.current_image = None;
+        self.render_key = None;
+        self.notice = Some(format!("rotation: {rotation}"));
+    }
+
     fn set_image_zoom_percent(&mut self, zoom_percent: u16) {
         const MIN: u16 = 50;
         const MAX: u16 = 400;
@@ -5271,20 +13097,21 @@ fn add_signed_u32(value: u32, delta: i32) -> u32 {
     }
 }
 
-fn render_page_image(
-    engine: &Engine,
-    book: &bookshelf_core::Book,
-    page_index: u32,
-    target_width_px: u32,
-) -> anyhow::Result<image::DynamicImage> {
-    let target_width_px = i32::try_from(target_width_px.clamp(1, i32::MAX as u32))
-        .unwrap_or(i32::MAX)
-        .max(1);
-    let bitmap = engine.render_page_bitmap_rgba(book, page_index, target_width_px, i32::MAX)?;
-    let image =
-        image::RgbaImage::from_raw(bitmap.width as u32, bitmap.height as u32, bitmap.pixels)
-            .ok_or_else(|| anyhow::anyhow!("invalid RGBA pixel buffer from pdfium"))?;
-    Ok(image::DynamicImage::ImageRgba8(image))
+/// Shrinks `image` to about `max_px` pixels with `filter`, keeping its aspect ratio. `None`
+/// when it already fits.
+fn downscale_to_pixels(
+    image: &image::DynamicImage,
+    max_px: u64,
+    filter: DownscaleFilter,
+) -> Option<image::DynamicImage> {
+    let px = u64::from(image.width()).saturating_mul(u64::from(image.height()));
+    if px <= max_px {
+        return None;
+    }
+    let scale = (max_px as f64 / px.max(1) as f64).sqrt().clamp(0.01, 1.0);
+    let new_w = ((image.width() as f64) * scale).round().max(1.0) as u32;
+    let new_h = ((image.height() as f64) * scale).round().max(1.0) as u32;
+    Some(image.resize_exact(new_w, new_h, filter_type(filter)))
 }
 
 fn build_viewport_image(
@@ -5293,6 +13120,7 @@ fn build_viewport_image(
     viewport_h_px: u32,
     pan_x_px: u32,
     pan_y_px: u32,
+    dark_background: bool,
 ) -> (image::DynamicImage, u32, u32) {
     let viewport_w_px = viewport_w_px.max(1);
     let viewport_h_px = viewport_h_px.max(1);
@@ -5305,10 +13133,12 @@ fn build_viewport_image(
     let pan_x_px = pan_x_px.min(max_pan_x);
     let pan_y_px = pan_y_px.min(max_pan_y);
 
+    // Margins around a page smaller than the viewport match the (possibly inverted) paper.
+    let paper = if dark_background { 0u8 } else { 255u8 };
     let mut viewport: image::DynamicImage = image::ImageBuffer::from_pixel(
         viewport_w_px,
         viewport_h_px,
-        image::Rgba([255u8, 255u8, 255u8, 255u8]),
+        image::Rgba([paper, paper, paper, 255u8]),
     )
     .into();
 
@@ -5333,18 +13163,27 @@ fn build_viewport_image(
     (viewport, pan_x_px, pan_y_px)
 }
 
-fn setup_terminal() -> anyhow::Result<Terminal<CrosstermBackend<Stdout>>> {
+fn setup_terminal(mouse_capture: bool) -> anyhow::Result<Terminal<CrosstermBackend<Stdout>>> {
     terminal::enable_raw_mode().context("enable raw mode")?;
     let mut stdout = io::stdout();
-    crossterm::execute!(stdout, EnterAlternateScreen).context("enter alt screen")?;
+    crossterm::execute!(stdout, EnterAlternateScreen, EnableFocusChange)
+        .context("enter alt screen")?;
+    if mouse_capture {
+        crossterm::execute!(stdout, EnableMouseCapture).context("enable mouse capture")?;
+    }
     let backend = CrosstermBackend::new(stdout);
     Terminal::new(backend).context("create terminal")
 }
 
 fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> anyhow::Result<()> {
     terminal::disable_raw_mode().context("disable raw mode")?;
-    crossterm::execute!(terminal.backend_mut(), LeaveAlternateScreen)
-        .context("leave alt screen")?;
+    crossterm::execute!(
+        terminal.backend_mut(),
+        DisableMouseCapture,
+        DisableFocusChange,
+        LeaveAlternateScreen
+    )
+    .context("leave alt screen")?;
     Ok(())
 }
 
@@ -5358,7 +13197,45 @@ fn panic_to_string(panic: Box<dyn std::any::Any + Send>) -> String {
     }
 }
 
+/// Says the terminal is too small, in place of any screen.
+fn draw_too_small(area: Rect, frame: &mut ratatui::Frame) {
+    let lines = vec![
+        Line::from(Span::styled(
+            format!("terminal too small (need {MIN_SCREEN_WIDTH}x{MIN_SCREEN_HEIGHT})"),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::raw(format!("now {}x{}", area.width, area.height)),
+    ];
+    let top = area.height.saturating_sub(lines.len() as u16) / 2;
+    let text_area = Rect {
+        y: area.y + top,
+        height: area.height - top,
+        ..area
+    };
+    frame.render_widget(
+        Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true }),
+        text_area,
+    );
+}
+
+/// A popup `percent_x` by `percent_y` of `r`, centered, and at least `POPUP_MIN_WIDTH` by
+/// `POPUP_MIN_HEIGHT` where `r` has the room.
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup = centered_rect_by_percent(percent_x, percent_y, r);
+    let width = popup.width.max(POPUP_MIN_WIDTH.min(r.width));
+    let height = popup.height.max(POPUP_MIN_HEIGHT.min(r.height));
+    Rect {
+        x: r.x + (r.width - width) / 2,
+        y: r.y + (r.height - height) / 2,
+        width,
+        height,
+    }
+    .intersection(r)
+}
+
+fn centered_rect_by_percent(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -5378,45 +13255,45 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-fn join_roots(settings: &Settings) -> String {
-    settings.library_roots.join(";")
+/// Orders larger values first; `None` (unknown) sorts after every known value.
+fn cmp_desc_missing_last<T: Ord>(a: Option<T>, b: Option<T>) -> std::cmp::Ordering {
+    b.cmp(&a)
 }
 
-fn parse_roots_input(input: &str) -> Vec<String> {
-    input
-        .split([';', ','])
-        .map(|part| part.trim().to_string())
-        .filter(|part| !part.is_empty())
-        .collect()
+/// Filters panel status cycle: any, then each status in turn.
+/// Filters panel recently-added cycle, in days.
+fn next_added_within_filter(days: Option<u32>) -> Option<u32> {
+    match days {
+        None => Some(1),
+        Some(1) => Some(7),
+        Some(7) => Some(30),
+        Some(_) => None,
+    }
 }
 
-fn matches_collection_filter(filter: &CollectionFilter, book_collection: Option<&str>) -> bool {
-    match filter {
-        CollectionFilter::Any => true,
-        CollectionFilter::None => book_collection.is_none(),
-        CollectionFilter::Selected(wanted) => book_collection
-            .as_ref()
-            .is_some_and(|c| c.eq_ignore_ascii_case(wanted)),
+fn format_added_within(days: u32) -> String {
+    if days == 1 {
+        "added in last day".to_string()
+    } else {
+        format!("added in last {days} days")
     }
 }
 
-fn matches_tag_filter(selected: &[String], mode: TagMatchMode, book_tags: &[String]) -> bool {
-    if selected.is_empty() {
-        return true;
-    }
-    if book_tags.is_empty() {
-        return false;
-    }
+fn next_kind_filter(filter: Option<BookKind>) -> Option<BookKind> {
+    let next = filter.map_or(0, |kind| {
+        BookKind::ALL
+            .iter()
+            .position(|known| *known == kind)
+            .map_or(0, |idx| idx + 1)
+    });
+    BookKind::ALL.get(next).copied()
+}
 
-    match mode {
-        TagMatchMode::And => selected.iter().all(|t| {
-            let t = t.trim();
-            !t.is_empty() && book_tags.iter().any(|bt| bt.eq_ignore_ascii_case(t))
-        }),
-        TagMatchMode::Or => selected.iter().any(|t| {
-            let t = t.trim();
-            !t.is_empty() && book_tags.iter().any(|bt| bt.eq_ignore_ascii_case(t))
-        }),
+fn next_status_filter(filter: Option<ReadingStatus>) -> Option<ReadingStatus> {
+    match filter {
+        None => Some(ReadingStatus::Unread),
+        Some(ReadingStatus::Finished) => None,
+        Some(status) => Some(status.next()),
     }
 }
 
@@ -5483,6 +13360,71 @@ fn wrap_text(text: &str, max_width: usize) -> Vec<String> {
     }
 }
 
+/// Like `wrap_text`, but a word wider than a whole line is cut with `…` instead of split
+/// across lines, so a title written without spaces (as CJK titles are) stays one line.
+fn wrap_label(text: &str, max_width: usize) -> Vec<String> {
+    if max_width == 0 {
+        return vec![text.to_string()];
+    }
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| toc::truncate_to_width(word, max_width))
+        .collect();
+    wrap_text(&words.join(" "), max_width)
+}
+
+/// A library row's lines within `max_width` columns: `prefix` (the mark, favorite and status
+/// glyphs) on the first line, and the label wrapped beside it with later lines indented to
+/// match.
+fn library_row_lines(prefix: &str, label: &str, max_width: usize) -> Vec<String> {
+    let prefix_width = UnicodeWidthStr::width(prefix);
+    let indent = " ".repeat(prefix_width);
+    wrap_label(label, max_width.saturating_sub(prefix_width).max(1))
+        .into_iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let lead = if idx == 0 { prefix } else { indent.as_str() };
+            format!("{lead}{line}")
+        })
+        .collect()
+}
+
+/// `path` on lines of at most `max_width` columns, broken after a `/` where one fits and
+/// anywhere in a component that does not.
+fn wrap_path(path: &str, max_width: usize) -> Vec<String> {
+    if max_width == 0 {
+        return vec![path.to_string()];
+    }
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut current_width = 0usize;
+    for part in path.split_inclusive(['/', '\\']) {
+        let part_width = UnicodeWidthStr::width(part);
+        if current_width + part_width > max_width && !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+            current_width = 0;
+        }
+        if part_width <= max_width {
+            current.push_str(part);
+            current_width += part_width;
+            continue;
+        }
+        for ch in part.chars() {
+            let w = UnicodeWidthChar::width(ch).unwrap_or(0);
+            if current_width + w > max_width && !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+                current_width = 0;
+            }
+            current.push(ch);
+            current_width += w;
+        }
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
 fn wrap_preserving_lines(text: &str, max_width: usize) -> String {
     if max_width == 0 {
         return text.to_string();
@@ -5537,6 +13479,13 @@ fn wrap_reflow_text(text: &str, max_width: usize) -> String {
             continue;
         }
 
+        // Reflow squeezes the spaces out of every other line; those left are table rows.
+        if looks_preformatted(line) {
+            flush_paragraph(&mut out_lines, &mut paragraph);
+            out_lines.push(line.to_string());
+            continue;
+        }
+
         if !paragraph.is_empty() {
             paragraph.push(' ');
         }
@@ -5551,10 +13500,67 @@ fn wrap_reflow_text(text: &str, max_width: usize) -> String {
     out_lines.join("\n")
 }
 
+/// Tenths of a line text-mode pages are spaced at: the `reader_line_spacing` setting, except
+/// raw text, which keeps the page's own layout.
+fn text_line_spacing(text_mode: ReaderTextMode, settings: &Settings) -> u16 {
+    match text_mode {
+        ReaderTextMode::Raw => 10,
+        _ => settings.reader_line_spacing,
+    }
+}
+
+/// Columns wrapped text may use: the `reader_text_width` setting, or all of `width` when it
+/// is 0 or wider than the reader.
+fn text_column_width(setting: u16, width: u16) -> usize {
+    match setting {
+        0 => usize::from(width),
+        setting => usize::from(setting.min(width)),
+    }
+}
+
+/// Centers wrapped text in `width` columns by indenting every line the same amount. The
+/// block is `column` wide, or as wide as its widest line (preformatted lines are not wrapped),
+/// so tables keep their alignment and nothing is pushed off the right edge.
+fn center_text_block(text: &str, column: usize, width: usize) -> String {
+    let widest = text.lines().map(UnicodeWidthStr::width).max().unwrap_or(0);
+    let indent = width.saturating_sub(column.max(widest)) / 2;
+    if indent == 0 {
+        return text.to_string();
+    }
+    let pad = " ".repeat(indent);
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{pad}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 fn looks_preformatted(line: &str) -> bool {
     line.contains('\t') || line.contains("  ")
 }
 
+/// Header/footer detection tuned by the furniture settings.
+fn furniture_config(settings: &Settings) -> FurnitureConfig {
+    FurnitureConfig {
+        sample_pages: settings.furniture_sample_pages,
+        min_fraction: settings.furniture_min_fraction,
+    }
+}
+
+/// Text-mode reading order for PDF pages.
+fn reader_text_layout(settings: &Settings) -> TextLayout {
+    if settings.reader_detect_columns {
+        TextLayout::Columns
+    } else {
+        TextLayout::Stream
+    }
+}
+
 fn is_non_text_page(text: &str) -> bool {
     let trimmed = text.trim();
     trimmed.is_empty() || trimmed.eq_ignore_ascii_case("no text found")
@@ -5620,6 +13626,84 @@ fn option_chip(label: &str, selected: bool, row_selected: bool) -> Span<'static>
     Span::styled(label.to_string(), base)
 }
 
+/// Library notice counting what a finished scan did; `pruned` when missing books were removed.
+fn scan_report_notice(report: &ScanReport, pruned: bool) -> String {
+    let missing = if pruned && report.missing > 0 {
+        format!("{} missing removed", report.missing)
+    } else {
+        format!("{} missing", report.missing)
+    };
+    format!(
+        "{} added, {} updated, {missing}, {} unchanged",
+        report.added, report.updated, report.unchanged
+    )
+}
+
+/// Library notice for folders a scan could not read, naming the first.
+fn skipped_folders_notice(skipped: &[(String, String)]) -> String {
+    let noun = if skipped.len() == 1 {
+        "folder"
+    } else {
+        "folders"
+    };
+    let first = skipped
+        .first()
+        .map(|(path, error)| format!(" ({path}: {error})"))
+        .unwrap_or_default();
+    format!(
+        "{} unreadable {noun} skipped, none marked missing{first}",
+        skipped.len()
+    )
+}
+
+/// Library notice naming each book a scan relinked, with the data that came along.
+fn moved_books_notice(moved: &[MovedBook]) -> String {
+    let books: Vec<String> = moved
+        .iter()
+        .map(|book| {
+            if book.carried.is_empty() {
+                book.title.clone()
+            } else {
+                format!("{} ({})", book.title, book.carried.join(", "))
+            }
+        })
+        .collect();
+    let noun = if moved.len() == 1 { "book" } else { "books" };
+    format!(
+        "relinked {} moved {noun}: {}",
+        moved.len(),
+        books.join("; ")
+    )
+}
+
+/// The library's book at `path`, or one titled by its file name for a file outside it.
+fn book_for_path(books: &[Book], path: &str) -> Book {
+    books
+        .iter()
+        .find(|b| b.path == path)
+        .cloned()
+        .unwrap_or_else(|| {
+            let decoded = bookshelf_core::decode_path(path);
+            let title = decoded
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "untitled".to_string());
+            Book {
+                path: path.to_string(),
+                title,
+                last_opened: None,
+                favorite: false,
+                author: None,
+                status: ReadingStatus::Unread,
+                archived: false,
+            }
+        })
+}
+
+fn book_file_exists(path: &str) -> bool {
+    std::fs::metadata(bookshelf_core::decode_path(path)).is_ok()
+}
+
 fn unix_now_secs() -> i64 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -5649,6 +13733,17 @@ fn format_last_opened(last_opened: Option<i64>) -> String {
     format!("{}d ago", delta / (60 * 60 * 24))
 }
 
+fn format_reading_time(secs: u64) -> String {
+    if secs < 60 {
+        return "<1m".to_string();
+    }
+    let minutes = secs / 60;
+    if minutes < 60 {
+        return format!("{minutes}m");
+    }
+    format!("{}h {}m", minutes / 60, minutes % 60)
+}
+
 fn format_bytes_opt(bytes: Option<u64>) -> String {
     bytes.map(format_bytes).unwrap_or_else(|| "-".to_string())
 }
@@ -5668,3 +13763,159 @@ fn format_bytes(bytes: u64) -> String {
         format!("{bytes} B")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_blocks_center_without_breaking_preformatted_lines() {
+        assert_eq!(text_column_width(0, 120), 120);
+        assert_eq!(text_column_width(80, 120), 80);
+        assert_eq!(text_column_width(80, 50), 50);
+
+        let text = wrap_reflow_text("one two three four five six", 10);
+        let centered = center_text_block(&text, 10, 30);
+        assert_eq!(
+            centered,
+            "          one two\n          three four\n          five six"
+        );
+
+        // A preformatted line wider than the column widens the block; all lines shift alike.
+        let text = wrap_preserving_lines("a  b  c  d  e  f\n\nx", 8);
+        assert_eq!(center_text_block(&text, 8, 20), "  a  b  c  d  e  f\n\n  x");
+        assert_eq!(center_text_block("abc", 10, 10), "abc");
+    }
+
+    #[test]
+    fn text_pages_scroll_a_screen_at_a_time_down_to_the_last_line() {
+        let mut reader = ReaderPanel {
+            current_lines: Some(reader_text::plain_lines(&"line\n".repeat(25))),
+            text_height: 10,
+            ..ReaderPanel::default()
+        };
+        assert!(reader.scroll_text_page(true));
+        assert_eq!(reader.scroll, 8);
+        assert!(reader.scroll_text_page(true));
+        assert_eq!(reader.scroll, 15);
+        assert_eq!(reader.text_scroll_label().as_deref(), Some("bottom"));
+        assert!(!reader.scroll_text_page(true));
+        assert!(!reader.scroll_down());
+
+        assert!(reader.scroll_text_page(false));
+        assert_eq!(reader.scroll, 7);
+        assert_eq!(reader.text_scroll_label().as_deref(), Some("46%"));
+    }
+
+    #[test]
+    fn table_rows_survive_wrapping_and_pan_sideways() {
+        let table = "Fruit               Qty       Price\nApples              12        3.50";
+        let page =
+            format!("The prices below held for the whole season.\n\n{table}\n\nPrices went up.");
+        let wrapped = wrap_preserving_lines(&page, 20);
+        assert!(wrapped.contains(table), "{wrapped}");
+        let reflowed = wrap_reflow_text(&page, 20);
+        assert!(reflowed.contains(table), "{reflowed}");
+        assert!(reflowed.starts_with("The prices below\nheld for the whole\nseason.\n\n"));
+
+        let mut reader = ReaderPanel {
+            current_lines: Some(reader_text::plain_lines(&reflowed)),
+            text_width: 20,
+            ..ReaderPanel::default()
+        };
+        assert_eq!(reader.text_overflow(), 15);
+        assert!(!reader.pan_text(false));
+        assert!(reader.pan_text(true));
+        assert!(reader.pan_text(true));
+        assert_eq!(reader.h_scroll, 15);
+        assert!(!reader.pan_text(true));
+        reader.invalidate_render();
+        assert_eq!(reader.h_scroll, 0);
+    }
+
+    #[test]
+    fn toggling_to_text_and_back_keeps_the_page_image_and_view() {
+        let key = PageImageKey {
+            page: 4,
+            zoom_percent: 150,
+            viewport_px: (800, 600),
+            fit: ImageFit::Page,
+            fit_page_to_frame: false,
+            max_render_pixels: 1_000_000,
+            font_size: (8, 16),
+            spread_page: None,
+            invert: false,
+            auto_crop: AutoCrop::Off,
+            rotation: PageRotation::Upright,
+        };
+        let mut reader = ReaderPanel {
+            page: 4,
+            image_zoom_percent: 150,
+            image_pan_x_px: 120,
+            image_pan_y_px: 300,
+            page_image: Some(CachedPageImage {
+                key,
+                render_width_px: 10,
+                image: Arc::new(image::DynamicImage::new_rgba8(10, 10)),
+            }),
+            ..ReaderPanel::default()
+        };
+        assert_eq!(reader.non_text_hint(true), "image/chart (m: image mode)");
+        reader.leave_image_mode();
+        assert!(reader.page_image.is_some());
+        assert_eq!(reader.non_text_hint(true), "image/chart (m: back to image)");
+        assert_eq!(
+            reader.non_text_hint(false),
+            "image/chart (k: graphics reader)"
+        );
+        reader.enter_image_mode();
+        assert_eq!(
+            reader.page_image.as_ref().map(|cached| cached.key),
+            Some(key)
+        );
+        assert_eq!((reader.image_pan_x_px, reader.image_pan_y_px), (120, 300));
+        assert_eq!(reader.image_zoom_percent, 150);
+
+        // Turning the page in text mode starts the next image view over.
+        reader.leave_image_mode();
+        reader.page = 5;
+        reader.invalidate_render();
+        assert_eq!(reader.non_text_hint(true), "image/chart (m: image mode)");
+        reader.enter_image_mode();
+        assert_eq!((reader.image_pan_x_px, reader.image_pan_y_px), (0, 0));
+        assert!(reader.left_image_view.is_none());
+    }
+
+    #[test]
+    fn cjk_titles_wrap_by_display_width() {
+        assert_eq!(
+            library_row_lines("✓★• ", "吾輩は猫である", 12),
+            vec!["✓★• 吾輩は…"]
+        );
+        assert_eq!(
+            library_row_lines("✓★• ", "Rust 编程之道 第二版", 14),
+            vec!["✓★• Rust", "    编程之道", "    第二版"]
+        );
+        assert_eq!(
+            library_row_lines(" ★• ", "Rust 编程之道 第二版", 24),
+            vec![" ★• Rust 编程之道 第二版"]
+        );
+        for width in 5..30 {
+            for title in [
+                "吾輩は猫である",
+                "三体 (missing)",
+                "ノルウェイの森 上 Norwegian Wood",
+            ] {
+                for line in library_row_lines("✓★• ", title, width) {
+                    assert!(UnicodeWidthStr::width(line.as_str()) <= width, "{line:?}");
+                }
+            }
+        }
+
+        assert_eq!(
+            wrap_path("/home/読書/本棚/very-long-file-name.pdf", 12),
+            vec!["/home/読書/", "本棚/", "very-long-fi", "le-name.pdf"]
+        );
+        assert_eq!(wrap_path("/books/a.pdf", 40), vec!["/books/a.pdf"]);
+    }
+}
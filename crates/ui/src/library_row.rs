@@ -0,0 +1,53 @@
+/// Cells in the progress bar after a library row.
+const PROGRESS_BAR_CELLS: u32 = 5;
+
+/// Compact suffix for a library row: a progress bar with the page fraction, then note and
+/// bookmark counts, e.g. `▰▰▱▱▱ 12/40 3n 2b`. Progress is left out until the book has been
+/// opened and its page count is cached; `None` when there is nothing to show.
+pub(crate) fn row_decorations(
+    last_page: Option<u32>,
+    pages: Option<u32>,
+    notes: usize,
+    bookmarks: usize,
+) -> Option<String> {
+    let mut parts = Vec::new();
+    if let (Some(page), Some(total)) = (last_page, pages.filter(|total| *total > 0)) {
+        let page = page.clamp(1, total);
+        let filled = (page * PROGRESS_BAR_CELLS).div_ceil(total);
+        let bar: String = (0..PROGRESS_BAR_CELLS)
+            .map(|cell| if cell < filled { '▰' } else { '▱' })
+            .collect();
+        parts.push(format!("{bar} {page}/{total}"));
+    }
+    if notes > 0 {
+        parts.push(format!("{notes}n"));
+    }
+    if bookmarks > 0 {
+        parts.push(format!("{bookmarks}b"));
+    }
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_progress_only_with_a_known_page_count() {
+        assert_eq!(
+            row_decorations(Some(12), Some(40), 3, 2).as_deref(),
+            Some("▰▰▱▱▱ 12/40 3n 2b")
+        );
+        assert_eq!(
+            row_decorations(Some(40), Some(40), 0, 0).as_deref(),
+            Some("▰▰▰▰▰ 40/40")
+        );
+        assert_eq!(
+            row_decorations(Some(1), Some(300), 0, 1).as_deref(),
+            Some("▰▱▱▱▱ 1/300 1b")
+        );
+        assert_eq!(row_decorations(Some(12), None, 1, 0).as_deref(), Some("1n"));
+        assert_eq!(row_decorations(None, Some(40), 0, 0), None);
+        assert_eq!(row_decorations(Some(3), Some(0), 0, 0), None);
+    }
+}
@@ -0,0 +1,90 @@
+/// Narrowest reader page that gives up a column to the minimap.
+pub(crate) const MINIMAP_MIN_PAGE_WIDTH: u16 = 20;
+
+/// What one row of the reader's position column shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MinimapMark {
+    Track,
+    /// A table of contents entry starts in this stretch of the book.
+    Chapter,
+    Bookmark,
+    /// The page being read.
+    Current,
+}
+
+impl MinimapMark {
+    pub(crate) fn glyph(self) -> &'static str {
+        match self {
+            MinimapMark::Track => "│",
+            MinimapMark::Chapter => "├",
+            MinimapMark::Bookmark => "◆",
+            MinimapMark::Current => "█",
+        }
+    }
+}
+
+/// The position column for a book of `total` pages drawn `height` rows tall. `page` is
+/// 0-based; `bookmarks` and `chapters` are 1-based pages, as stored. Where several land on
+/// one row, the current page wins over a bookmark and a bookmark over a chapter start.
+pub(crate) fn minimap_rows(
+    height: u16,
+    page: u32,
+    total: u32,
+    bookmarks: impl IntoIterator<Item = u32>,
+    chapters: impl IntoIterator<Item = u32>,
+) -> Vec<MinimapMark> {
+    let mut rows = vec![MinimapMark::Track; usize::from(height)];
+    if rows.is_empty() || total == 0 {
+        return rows;
+    }
+    // Below `height`, since the page is below `total`.
+    let row_of = |page: u32| {
+        (u64::from(page.min(total - 1)) * u64::from(height) / u64::from(total)) as usize
+    };
+    for chapter in chapters {
+        rows[row_of(chapter.saturating_sub(1))] = MinimapMark::Chapter;
+    }
+    for bookmark in bookmarks {
+        rows[row_of(bookmark.saturating_sub(1))] = MinimapMark::Bookmark;
+    }
+    rows[row_of(page)] = MinimapMark::Current;
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MinimapMark::{Bookmark, Chapter, Current, Track};
+
+    #[test]
+    fn marks_land_at_their_share_of_the_book() {
+        // 100 pages over 5 rows: 20 pages a row.
+        let rows = minimap_rows(5, 49, 100, [95], [1, 21, 60]);
+        assert_eq!(rows, vec![Chapter, Chapter, Current, Track, Bookmark]);
+
+        // The last page sits on the last row, and marks past the end stay on the column.
+        assert_eq!(
+            minimap_rows(3, 99, 100, [500], []),
+            vec![Track, Track, Current]
+        );
+    }
+
+    #[test]
+    fn the_current_page_wins_a_shared_row() {
+        let rows = minimap_rows(2, 0, 10, [1, 2], [1, 6]);
+        assert_eq!(rows, vec![Current, Chapter]);
+
+        let rows = minimap_rows(2, 9, 10, [7], [6]);
+        assert_eq!(rows, vec![Track, Current]);
+        assert_eq!(minimap_rows(2, 0, 10, [7], [6]), vec![Current, Bookmark]);
+    }
+
+    #[test]
+    fn short_books_spread_over_the_column() {
+        // Fewer pages than rows: each page gets a row of its own, with gaps between.
+        let rows = minimap_rows(6, 2, 3, [], []);
+        assert_eq!(rows, vec![Track, Track, Track, Track, Current, Track]);
+        assert!(minimap_rows(0, 0, 3, [], []).is_empty());
+        assert_eq!(minimap_rows(2, 0, 0, [], []), vec![Track, Track]);
+    }
+}
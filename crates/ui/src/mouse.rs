@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+use ratatui::layout::{Position, Rect};
+
+/// Two clicks on the same row this close together open it.
+pub(crate) const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// The list row a click landed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HitTarget {
+    /// A library row, by index into `ctx.books`.
+    Library(usize),
+    /// A table of contents row, by position in the filtered list.
+    Toc(usize),
+    Bookmark(usize),
+    Note(usize),
+}
+
+/// Where the last frame drew the things a click can select. Rebuilt on every frame, so a click
+/// always hits what is on screen.
+#[derive(Debug, Default)]
+pub(crate) struct HitRegions {
+    regions: Vec<(Rect, HitTarget)>,
+    last_click: Option<(HitTarget, Instant)>,
+}
+
+impl HitRegions {
+    /// Forgets the last frame's regions; the pending click stays for double-click detection.
+    pub(crate) fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    /// Records the rows a list drew into `area`: row heights in `heights`, the first shown
+    /// being `offset`. `target` maps a row position to what it selects.
+    pub(crate) fn add_list_rows(
+        &mut self,
+        area: Rect,
+        offset: usize,
+        heights: &[usize],
+        target: impl Fn(usize) -> HitTarget,
+    ) {
+        let bottom = area.bottom();
+        let mut y = area.y;
+        for (pos, height) in heights.iter().enumerate().skip(offset) {
+            if y >= bottom {
+                break;
+            }
+            let height = (*height).min(usize::from(bottom - y)) as u16;
+            let row = Rect { y, height, ..area };
+            self.regions.push((row, target(pos)));
+            y += height;
+        }
+    }
+
+    /// Records one region, such as a cell of the library grid.
+    pub(crate) fn add(&mut self, area: Rect, target: HitTarget) {
+        self.regions.push((area, target));
+    }
+
+    /// The topmost region under the cell; later regions were drawn over earlier ones.
+    pub(crate) fn hit(&self, column: u16, row: u16) -> Option<HitTarget> {
+        self.regions
+            .iter()
+            .rev()
+            .find(|(area, _)| area.contains(Position::new(column, row)))
+            .map(|(_, target)| *target)
+    }
+
+    /// Records a click on `target`; true when it completes a double click.
+    pub(crate) fn click(&mut self, target: HitTarget, now: Instant) -> bool {
+        let double = self
+            .last_click
+            .is_some_and(|(last, at)| last == target && now.duration_since(at) <= DOUBLE_CLICK);
+        self.last_click = (!double).then_some((target, now));
+        double
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_rows_follow_offset_and_height() {
+        let mut regions = HitRegions::default();
+        let area = Rect::new(2, 1, 20, 4);
+        regions.add_list_rows(area, 1, &[1, 2, 1, 3], HitTarget::Library);
+
+        assert_eq!(regions.hit(5, 1), Some(HitTarget::Library(1)));
+        assert_eq!(regions.hit(5, 2), Some(HitTarget::Library(1)));
+        assert_eq!(regions.hit(5, 3), Some(HitTarget::Library(2)));
+        // The last row is cut off by the bottom of the list.
+        assert_eq!(regions.hit(5, 4), Some(HitTarget::Library(3)));
+        assert_eq!(regions.hit(5, 5), None);
+        assert_eq!(regions.hit(1, 1), None);
+    }
+
+    #[test]
+    fn later_regions_are_on_top() {
+        let mut regions = HitRegions::default();
+        regions.add_list_rows(Rect::new(0, 0, 10, 5), 0, &[1; 5], HitTarget::Library);
+        regions.add_list_rows(Rect::new(0, 2, 10, 1), 0, &[1], HitTarget::Toc);
+
+        assert_eq!(regions.hit(0, 2), Some(HitTarget::Toc(0)));
+        assert_eq!(regions.hit(0, 3), Some(HitTarget::Library(3)));
+        regions.clear();
+        assert_eq!(regions.hit(0, 2), None);
+    }
+
+    #[test]
+    fn double_click_needs_the_same_row_in_time() {
+        let mut regions = HitRegions::default();
+        let start = Instant::now();
+        let row = HitTarget::Library(3);
+
+        assert!(!regions.click(row, start));
+        assert!(regions.click(row, start + Duration::from_millis(200)));
+        // A third click starts over rather than opening again.
+        assert!(!regions.click(row, start + Duration::from_millis(300)));
+        assert!(!regions.click(HitTarget::Library(4), start + Duration::from_millis(350)));
+        assert!(!regions.click(row, start + Duration::from_millis(400)));
+        assert!(!regions.click(row, start + Duration::from_millis(900)));
+    }
+}
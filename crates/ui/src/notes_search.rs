@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use bookshelf_core::{Bookmark, Note};
+use bookshelf_engine::match_snippet;
+
+use crate::text_input::TextInput;
+
+/// Matches listed at most; the header says how many more there were.
+pub(crate) const NOTES_SEARCH_MAX_HITS: usize = 200;
+/// Rows PageUp and PageDown move the selection.
+pub(crate) const NOTES_SEARCH_PAGE_ROWS: usize = 10;
+
+/// What a match was found in. Notes come before bookmarks on the same page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum NoteHitKind {
+    Note,
+    Bookmark,
+}
+
+/// A note or bookmark label matching the query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NoteHit {
+    pub(crate) path: String,
+    pub(crate) title: String,
+    /// 1-based, as notes and bookmarks keep it.
+    pub(crate) page: u32,
+    pub(crate) kind: NoteHitKind,
+    pub(crate) snippet: String,
+    /// Byte range of the match in `snippet`.
+    pub(crate) match_start: usize,
+    pub(crate) match_end: usize,
+}
+
+/// The library's search over every book's notes and bookmark labels.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NotesSearchPanel {
+    pub(crate) open: bool,
+    pub(crate) input: TextInput,
+    pub(crate) selected: usize,
+    pub(crate) hits: Vec<NoteHit>,
+    /// Matches before the list was cut to `NOTES_SEARCH_MAX_HITS`.
+    pub(crate) total: usize,
+    pub(crate) error: Option<String>,
+}
+
+impl NotesSearchPanel {
+    /// Runs the typed query again, keeping the selection in the new list.
+    pub(crate) fn search(
+        &mut self,
+        notes: &HashMap<String, Vec<Note>>,
+        bookmarks: &HashMap<String, Vec<Bookmark>>,
+        title: impl Fn(&str) -> String,
+    ) {
+        (self.hits, self.total) = search_notes(self.input.as_str(), notes, bookmarks, title);
+        self.selected = self.selected.min(self.hits.len().saturating_sub(1));
+        self.error = None;
+    }
+}
+
+/// Notes and bookmark labels containing `query` (ignoring case), by book title and then page;
+/// at most `NOTES_SEARCH_MAX_HITS` of them, with the number of matches there were.
+pub(crate) fn search_notes(
+    query: &str,
+    notes: &HashMap<String, Vec<Note>>,
+    bookmarks: &HashMap<String, Vec<Bookmark>>,
+    title: impl Fn(&str) -> String,
+) -> (Vec<NoteHit>, usize) {
+    if query.trim().is_empty() {
+        return (Vec::new(), 0);
+    }
+    let mut titles: HashMap<String, String> = HashMap::new();
+    let mut hits = Vec::new();
+    let mut add = |path: &str, page: u32, kind: NoteHitKind, text: &str| {
+        let Some((snippet, match_start, match_end)) = match_snippet(text, query) else {
+            return;
+        };
+        let title = titles
+            .entry(path.to_string())
+            .or_insert_with(|| title(path))
+            .clone();
+        hits.push(NoteHit {
+            path: path.to_string(),
+            title,
+            page,
+            kind,
+            snippet,
+            match_start,
+            match_end,
+        });
+    };
+    for (path, notes) in notes {
+        for note in notes {
+            add(path, note.page, NoteHitKind::Note, &note.body);
+        }
+    }
+    for (path, bookmarks) in bookmarks {
+        for bookmark in bookmarks {
+            add(path, bookmark.page, NoteHitKind::Bookmark, &bookmark.label);
+        }
+    }
+    hits.sort_by(|a, b| {
+        a.title
+            .to_lowercase()
+            .cmp(&b.title.to_lowercase())
+            .then_with(|| a.path.cmp(&b.path))
+            .then_with(|| a.page.cmp(&b.page))
+            .then_with(|| a.kind.cmp(&b.kind))
+    });
+    let total = hits.len();
+    hits.truncate(NOTES_SEARCH_MAX_HITS);
+    (hits, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(page: u32, body: &str) -> Note {
+        Note {
+            page,
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn notes_and_bookmark_labels_match_across_books() {
+        let notes = HashMap::from([
+            (
+                "/b.pdf".to_string(),
+                vec![
+                    note(12, "Compare with the\n  Raft paper"),
+                    note(3, "unrelated"),
+                ],
+            ),
+            ("/a.pdf".to_string(), vec![note(40, "raft again")]),
+        ]);
+        let bookmarks = HashMap::from([(
+            "/b.pdf".to_string(),
+            vec![Bookmark {
+                page: 12,
+                label: "RAFT log".to_string(),
+                view: None,
+            }],
+        )]);
+        let title = |path: &str| path.trim_start_matches('/').to_uppercase();
+        let (hits, total) = search_notes(" raft ", &notes, &bookmarks, title);
+        assert_eq!(total, 3);
+        let rows: Vec<(&str, u32, NoteHitKind)> = hits
+            .iter()
+            .map(|hit| (hit.title.as_str(), hit.page, hit.kind))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("A.PDF", 40, NoteHitKind::Note),
+                ("B.PDF", 12, NoteHitKind::Note),
+                ("B.PDF", 12, NoteHitKind::Bookmark),
+            ]
+        );
+        let hit = &hits[1];
+        assert_eq!(hit.snippet, "Compare with the Raft paper");
+        assert_eq!(&hit.snippet[hit.match_start..hit.match_end], "Raft");
+
+        assert_eq!(search_notes("  ", &notes, &bookmarks, title).1, 0);
+
+        let many = HashMap::from([(
+            "/c.pdf".to_string(),
+            (1..=NOTES_SEARCH_MAX_HITS as u32 + 5)
+                .map(|page| note(page, "raft"))
+                .collect(),
+        )]);
+        let (hits, total) = search_notes("raft", &many, &HashMap::new(), title);
+        assert_eq!(hits.len(), NOTES_SEARCH_MAX_HITS);
+        assert_eq!(total, NOTES_SEARCH_MAX_HITS + 5);
+    }
+}
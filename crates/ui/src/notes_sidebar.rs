@@ -0,0 +1,109 @@
+use bookshelf_core::Note;
+use ratatui::layout::Rect;
+
+/// Pages either side of the current one whose notes the sidebar also lists.
+pub(crate) const NOTES_SIDEBAR_NEARBY_PAGES: u32 = 5;
+/// Narrowest reader that keeps the sidebar; below it the page gets the whole width.
+pub(crate) const NOTES_SIDEBAR_MIN_WIDTH: u16 = 100;
+/// Share of the reader width left to the page while the sidebar shows.
+const NOTES_SIDEBAR_PAGE_PERCENT: u32 = 70;
+
+/// The reader's notes pane, next to the page.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NotesSidebar {
+    pub(crate) open: bool,
+    /// Up/Down and Enter go to the notes list instead of the page.
+    pub(crate) focused: bool,
+    /// Index into the listed notes.
+    pub(crate) selected: usize,
+    /// Width of the reader area on the last frame.
+    width: u16,
+}
+
+impl NotesSidebar {
+    pub(crate) fn toggle(&mut self) {
+        self.open = !self.open;
+        self.focused = false;
+        self.selected = 0;
+    }
+
+    /// Whether the last frame was wide enough to draw it.
+    pub(crate) fn visible(&self) -> bool {
+        self.open && self.width >= NOTES_SIDEBAR_MIN_WIDTH
+    }
+
+    /// Open but collapsed, because the reader was too narrow on the last frame. Before the
+    /// first frame it is not known yet.
+    pub(crate) fn collapsed(&self) -> bool {
+        self.open && self.width > 0 && self.width < NOTES_SIDEBAR_MIN_WIDTH
+    }
+
+    /// Splits the reader's page area into the page and the sidebar. Under the minimum width the
+    /// page keeps the whole area and focus goes back to it.
+    pub(crate) fn split(&mut self, area: Rect) -> (Rect, Option<Rect>) {
+        self.width = area.width;
+        if !self.visible() {
+            self.focused = false;
+            return (area, None);
+        }
+        let page_width =
+            (u32::from(area.width) * NOTES_SIDEBAR_PAGE_PERCENT / 100).min(u32::from(u16::MAX));
+        let page_width = page_width as u16;
+        let page = Rect {
+            width: page_width,
+            ..area
+        };
+        let sidebar = Rect {
+            x: area.x + page_width,
+            width: area.width - page_width,
+            ..area
+        };
+        (page, Some(sidebar))
+    }
+}
+
+/// Indices of the `notes` on `page` (1-based) or within `NOTES_SIDEBAR_NEARBY_PAGES` of it,
+/// in page order.
+pub(crate) fn nearby_notes(notes: &[Note], page: u32) -> Vec<usize> {
+    let mut nearby: Vec<usize> = (0..notes.len())
+        .filter(|&idx| notes[idx].page.abs_diff(page) <= NOTES_SIDEBAR_NEARBY_PAGES)
+        .collect();
+    nearby.sort_by_key(|&idx| notes[idx].page);
+    nearby
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidebar_takes_the_right_of_wide_readers_only() {
+        let mut sidebar = NotesSidebar::default();
+        let wide = Rect::new(0, 1, 120, 40);
+        assert_eq!(sidebar.split(wide), (wide, None));
+
+        sidebar.toggle();
+        sidebar.focused = true;
+        let (page, notes) = sidebar.split(wide);
+        assert_eq!(page, Rect::new(0, 1, 84, 40));
+        assert_eq!(notes, Some(Rect::new(84, 1, 36, 40)));
+        assert!(sidebar.visible() && sidebar.focused);
+
+        let narrow = Rect::new(0, 1, 80, 40);
+        assert_eq!(sidebar.split(narrow), (narrow, None));
+        assert!(sidebar.collapsed());
+        assert!(!sidebar.focused);
+    }
+
+    #[test]
+    fn nearby_notes_are_listed_in_page_order() {
+        let note = |page: u32| Note {
+            page,
+            body: format!("on {page}"),
+        };
+        let notes = vec![note(12), note(3), note(10), note(16), note(4), note(10)];
+        assert_eq!(nearby_notes(&notes, 10), vec![2, 5, 0]);
+        assert_eq!(nearby_notes(&notes, 8), vec![1, 4, 2, 5, 0]);
+        assert!(nearby_notes(&notes, 30).is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use bookshelf_core::DownscaleFilter;
+use image::DynamicImage;
+use image::imageops::FilterType;
+
+use crate::page_render::PageImageKey;
+
+/// A rasterized page. Pages fit whole into the frame are kept at frame size once shown;
+/// only pages that can be panned keep the full render, since panning reads pixels outside
+/// the viewport.
+#[derive(Clone)]
+pub(crate) struct CachedPageImage {
+    pub(crate) key: PageImageKey,
+    pub(crate) render_width_px: u32,
+    pub(crate) image: Arc<DynamicImage>,
+}
+
+impl CachedPageImage {
+    /// Bytes of decoded pixels.
+    pub(crate) fn bytes(&self) -> usize {
+        self.image.as_bytes().len()
+    }
+}
+
+/// Recently shown pages, newest first, held under a byte ceiling.
+#[derive(Clone, Default)]
+pub(crate) struct PageImageCache {
+    entries: VecDeque<CachedPageImage>,
+}
+
+impl PageImageCache {
+    /// The entry for `key`, moved to the front.
+    pub(crate) fn get(&mut self, key: PageImageKey) -> Option<CachedPageImage> {
+        let pos = self.entries.iter().position(|c| c.key == key)?;
+        let entry = self.entries.remove(pos)?;
+        self.entries.push_front(entry.clone());
+        Some(entry)
+    }
+
+    /// Adds or replaces the entry for `image.key`, then evicts the oldest entries until the
+    /// cache fits `max_bytes`. The newest entry stays even when it alone is larger.
+    pub(crate) fn insert(&mut self, image: CachedPageImage, max_bytes: usize) {
+        self.entries.retain(|c| c.key != image.key);
+        self.entries.push_front(image);
+        self.trim(max_bytes);
+    }
+
+    pub(crate) fn trim(&mut self, max_bytes: usize) {
+        while self.entries.len() > 1 && self.bytes() > max_bytes {
+            self.entries.pop_back();
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn bytes(&self) -> usize {
+        self.entries.iter().map(CachedPageImage::bytes).sum()
+    }
+}
+
+/// `image` scaled down with `filter`, aspect kept, to fit `max_w` x `max_h`; `None` when it
+/// already fits.
+pub(crate) fn fit_to_frame(
+    image: &DynamicImage,
+    max_w: u32,
+    max_h: u32,
+    filter: DownscaleFilter,
+) -> Option<DynamicImage> {
+    if image.width() <= max_w && image.height() <= max_h {
+        return None;
+    }
+    Some(image.resize(max_w.max(1), max_h.max(1), filter_type(filter)))
+}
+
+/// The `image` resampling filter for `filter`; `Auto` should be resolved with
+/// `DownscaleFilter::for_quality` first and falls back to triangle.
+pub(crate) fn filter_type(filter: DownscaleFilter) -> FilterType {
+    match filter {
+        DownscaleFilter::Nearest => FilterType::Nearest,
+        DownscaleFilter::Auto | DownscaleFilter::Triangle => FilterType::Triangle,
+        DownscaleFilter::CatmullRom => FilterType::CatmullRom,
+        DownscaleFilter::Lanczos3 => FilterType::Lanczos3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bookshelf_core::{AutoCrop, ImageFit, PageRotation};
+
+    use super::*;
+
+    fn key(page: u32) -> PageImageKey {
+        PageImageKey {
+            page,
+            zoom_percent: 100,
+            viewport_px: (100, 100),
+            fit: ImageFit::Page,
+            fit_page_to_frame: true,
+            max_render_pixels: 1_000_000,
+            font_size: (10, 20),
+            spread_page: None,
+            invert: false,
+            auto_crop: AutoCrop::Off,
+            rotation: PageRotation::Upright,
+        }
+    }
+
+    fn page(page: u32, width: u32, height: u32) -> CachedPageImage {
+        CachedPageImage {
+            key: key(page),
+            render_width_px: width,
+            image: Arc::new(DynamicImage::new_rgba8(width, height)),
+        }
+    }
+
+    #[test]
+    fn cache_evicts_oldest_pages_over_the_ceiling() {
+        let mut cache = PageImageCache::default();
+        // 40_000 bytes each.
+        cache.insert(page(0, 100, 100), 100_000);
+        cache.insert(page(1, 100, 100), 100_000);
+        assert_eq!((cache.len(), cache.bytes()), (2, 80_000));
+
+        assert!(cache.get(key(0)).is_some());
+        cache.insert(page(2, 100, 100), 100_000);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(key(1)).is_none());
+        assert!(cache.get(key(0)).is_some());
+
+        // Replacing an entry with its frame-size copy frees the difference.
+        cache.insert(page(0, 50, 50), 100_000);
+        assert_eq!((cache.len(), cache.bytes()), (2, 50_000));
+
+        // A page larger than the ceiling still stays while it is the newest.
+        cache.insert(page(3, 200, 200), 100_000);
+        assert_eq!((cache.len(), cache.bytes()), (1, 160_000));
+        cache.trim(0);
+        assert_eq!(cache.len(), 1);
+    }
+
+    /// A page of one-pixel black strokes on white, standing in for small text.
+    fn stroke_page(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageLuma8(image::GrayImage::from_fn(width, height, |x, y| {
+            let ink = (x % 7 == 0 && y % 12 < 9) || y % 12 == 0;
+            image::Luma([if ink { 0 } else { 255 }])
+        }))
+    }
+
+    /// Spread of the gray levels: strokes smeared into the paper bring it down.
+    fn contrast(image: &DynamicImage) -> f64 {
+        let luma = image.to_luma8();
+        let count = luma.len() as f64;
+        let mean = luma.iter().map(|&v| f64::from(v)).sum::<f64>() / count;
+        (luma
+            .iter()
+            .map(|&v| (f64::from(v) - mean).powi(2))
+            .sum::<f64>()
+            / count)
+            .sqrt()
+    }
+
+    #[test]
+    fn downscale_filters_trade_speed_for_sharpness() {
+        // An A4 page at 100 dpi halved, as a fit page is shrunk into the frame. The budget is
+        // loose enough for unoptimized test builds; a resize path gone badly wrong blows it.
+        let page = stroke_page(826, 1169);
+        let budget = std::time::Duration::from_secs(5);
+        let mut runs = Vec::new();
+        for filter in [
+            DownscaleFilter::Nearest,
+            DownscaleFilter::Triangle,
+            DownscaleFilter::CatmullRom,
+            DownscaleFilter::Lanczos3,
+        ] {
+            let start = std::time::Instant::now();
+            let fitted = fit_to_frame(&page, 413, 585, filter).expect("page is larger");
+            let elapsed = start.elapsed();
+            assert!(elapsed < budget, "{filter} took {elapsed:?}");
+            assert_eq!((fitted.width(), fitted.height()), (413, 585));
+            runs.push((elapsed, contrast(&fitted)));
+        }
+        let [nearest, triangle, catmull_rom, lanczos3] = runs.as_slice() else {
+            unreachable!();
+        };
+        // Nearest skips resampling altogether; lanczos3 weighs the most source pixels.
+        assert!(nearest.0 < lanczos3.0);
+        // Triangle smears the strokes most; the sharper filters keep more of their contrast.
+        assert!(triangle.1 < catmull_rom.1);
+        assert!(catmull_rom.1 <= lanczos3.1);
+    }
+
+    #[test]
+    fn frame_fit_keeps_aspect_and_skips_small_pages() {
+        let page = DynamicImage::new_rgba8(1000, 2000);
+        let fitted = fit_to_frame(&page, 400, 400, DownscaleFilter::Triangle)
+            .map(|image| (image.width(), image.height()));
+        assert_eq!(fitted, Some((200, 400)));
+        assert!(fit_to_frame(&page, 1000, 2000, DownscaleFilter::Triangle).is_none());
+    }
+}
@@ -0,0 +1,95 @@
+use bookshelf_engine::PageLink;
+
+/// Pages kept to return to; the oldest are dropped first.
+const MAX_LINK_JUMPS: usize = 100;
+
+/// The reader's list of links on the current page, and the pages left by following them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LinksPanel {
+    pub(crate) open: bool,
+    pub(crate) selected: usize,
+    pub(crate) links: Vec<PageLink>,
+    /// 1-based page the links were read from.
+    pub(crate) page: u32,
+    pub(crate) error: Option<String>,
+    /// 0-based pages a link was followed from, newest last.
+    back: Vec<u32>,
+}
+
+impl LinksPanel {
+    pub(crate) fn show(&mut self, page: u32, links: anyhow::Result<Vec<PageLink>>) {
+        self.open = true;
+        self.selected = 0;
+        self.page = page;
+        (self.links, self.error) = match links {
+            Ok(links) => (links, None),
+            Err(err) => (Vec::new(), Some(err.to_string())),
+        };
+    }
+
+    /// Follows the selected link from the 0-based `from` page: closes the panel and returns
+    /// the 0-based target, remembering `from` to go back to.
+    pub(crate) fn follow(&mut self, from: u32) -> Option<u32> {
+        let target = self.links.get(self.selected)?.target_page.saturating_sub(1);
+        self.open = false;
+        if target != from {
+            if self.back.len() == MAX_LINK_JUMPS {
+                self.back.remove(0);
+            }
+            self.back.push(from);
+        }
+        Some(target)
+    }
+
+    /// The 0-based page the last followed link left.
+    pub(crate) fn back(&mut self) -> Option<u32> {
+        self.back.pop()
+    }
+
+    pub(crate) fn back_len(&self) -> usize {
+        self.back.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bookshelf_engine::LinkRect;
+
+    use super::*;
+
+    fn link(target_page: u32) -> PageLink {
+        PageLink {
+            rect: LinkRect {
+                left: 0.0,
+                bottom: 0.0,
+                right: 10.0,
+                top: 10.0,
+            },
+            target_page,
+            label: format!("to {target_page}"),
+        }
+    }
+
+    #[test]
+    fn followed_links_return_in_reverse_order() {
+        let mut panel = LinksPanel::default();
+        panel.show(3, Ok(vec![link(9), link(3)]));
+        assert_eq!(panel.follow(2), Some(8));
+        assert!(!panel.open);
+
+        panel.show(9, Ok(vec![link(20)]));
+        assert_eq!(panel.follow(8), Some(19));
+        // A link to the page already shown leaves nothing to return to.
+        panel.show(20, Ok(vec![link(20)]));
+        assert_eq!(panel.follow(19), Some(19));
+        assert_eq!(panel.back_len(), 2);
+
+        assert_eq!(panel.back(), Some(8));
+        assert_eq!(panel.back(), Some(2));
+        assert_eq!(panel.back(), None);
+
+        panel.show(1, Err(anyhow::anyhow!("broken")));
+        assert_eq!(panel.error.as_deref(), Some("broken"));
+        assert_eq!(panel.follow(0), None);
+    }
+}
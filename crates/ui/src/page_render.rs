@@ -0,0 +1,924 @@
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Instant;
+
+use bookshelf_application::Wake;
+use bookshelf_core::{AutoCrop, Book, ImageFit, PageCount, PageRotation};
+use bookshelf_engine::Engine;
+
+use crate::export::export_file_name;
+
+/// Everything that decides the rasterized bitmap for a page; results are matched on this.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PageImageKey {
+    pub(crate) page: u32,
+    pub(crate) zoom_percent: u16,
+    pub(crate) viewport_px: (u32, u32),
+    pub(crate) fit: ImageFit,
+    /// Page fit at 100% zoom and no pan: the bitmap is scaled into the frame instead of cropped.
+    pub(crate) fit_page_to_frame: bool,
+    pub(crate) max_render_pixels: u64,
+    pub(crate) font_size: (u16, u16),
+    /// Right-hand page composited next to `page` in spread view.
+    pub(crate) spread_page: Option<u32>,
+    /// Page colors inverted for dark reading.
+    pub(crate) invert: bool,
+    /// Margin trimming; `Off` while the view is panned so pan offsets keep their meaning.
+    pub(crate) auto_crop: AutoCrop,
+    /// Turn applied to the finished bitmap; sizing already accounts for it.
+    pub(crate) rotation: PageRotation,
+}
+
+pub(crate) struct PageImageJob {
+    pub(crate) book: Book,
+    pub(crate) key: PageImageKey,
+}
+
+pub(crate) struct PageImageDone {
+    pub(crate) book_path: String,
+    pub(crate) key: PageImageKey,
+    pub(crate) render_width_px: u32,
+    pub(crate) rasterize_ms: u128,
+    pub(crate) image: anyhow::Result<image::DynamicImage>,
+}
+
+/// Target width of thumbnail renders; the strip scales them down to its cells.
+pub(crate) const THUMBNAIL_WIDTH_PX: u32 = 120;
+
+/// Width of the low-resolution render that auto-crop scans for the content box.
+const CROP_PROBE_WIDTH_PX: u32 = 240;
+
+pub(crate) struct ThumbnailJob {
+    pub(crate) book: Book,
+    pub(crate) page: u32,
+}
+
+pub(crate) struct ThumbnailDone {
+    pub(crate) book_path: String,
+    pub(crate) page: u32,
+    pub(crate) image: anyhow::Result<image::DynamicImage>,
+}
+
+/// Target width of the cover shown in the library details pane.
+pub(crate) const COVER_WIDTH_PX: u32 = 200;
+
+pub(crate) struct CoverJob {
+    pub(crate) book: Book,
+}
+
+pub(crate) struct CoverDone {
+    pub(crate) book_path: String,
+    pub(crate) image: anyhow::Result<image::DynamicImage>,
+}
+
+/// Counts a book's pages for the details pane and tells its kind; the file stamp is read by
+/// the requester.
+pub(crate) struct PageCountJob {
+    pub(crate) book: Book,
+    pub(crate) size_bytes: u64,
+    pub(crate) modified_secs: i64,
+}
+
+/// Tells the kind of a book shown in the library, unless `known` was taken from the file as it
+/// is now. The worker reads the file stamp, so the UI thread never waits on the filesystem.
+pub(crate) struct BookKindJob {
+    pub(crate) book: Book,
+    pub(crate) known: Option<PageCount>,
+}
+
+pub(crate) struct PageCountDone {
+    pub(crate) book_path: String,
+    /// The parse error when the document could not be opened.
+    pub(crate) count: Result<PageCount, String>,
+}
+
+/// Writes `first..=last` (0-based) as PNG files in `dir`, rendered at `dpi`.
+pub(crate) struct ExportJob {
+    pub(crate) book: Book,
+    pub(crate) first: u32,
+    pub(crate) last: u32,
+    pub(crate) dir: PathBuf,
+    pub(crate) dpi: u32,
+}
+
+/// One page of an export; the worker sends one of these per page.
+pub(crate) struct ExportPageDone {
+    pub(crate) book_path: String,
+    /// 0-based.
+    pub(crate) page: u32,
+    pub(crate) result: anyhow::Result<PathBuf>,
+}
+
+pub(crate) enum RenderDone {
+    Page(PageImageDone),
+    Thumbnail(ThumbnailDone),
+    Cover(CoverDone),
+    GridCover(CoverDone),
+    PageCount(PageCountDone),
+    ExportPage(ExportPageDone),
+}
+
+enum RenderJob {
+    Page(PageImageJob),
+    Thumbnail(ThumbnailJob),
+    Cover(CoverJob),
+    /// Covers for the library grid; replaces the list sent before.
+    GridCovers(Vec<CoverJob>),
+    PageCount(PageCountJob),
+    BookKinds(Vec<BookKindJob>),
+    Export(ExportJob),
+    RecheckPdfium,
+}
+
+/// Rasterizes pages on a background thread so slow (scanned) pages don't block key handling.
+///
+/// The worker owns its own `Engine`, so Pdfium is only ever touched from that thread. Page jobs
+/// queued behind a newer one are skipped: only the most recent request is rendered. Page counts
+/// and covers work the same way and wait for the page. An export renders one page at a time
+/// between other jobs, so the reader stays responsive while it runs. Thumbnails, then grid
+/// covers, then book kinds are handled in request order whenever nothing else is waiting.
+#[derive(Default)]
+pub(crate) struct PageRenderWorker {
+    jobs: Option<Sender<RenderJob>>,
+    results: Option<Receiver<RenderDone>>,
+    /// Called from the worker thread after each result.
+    wake: Option<Wake>,
+}
+
+impl PageRenderWorker {
+    /// Applies to the worker thread started next, which is the first one.
+    pub(crate) fn set_wake(&mut self, wake: Wake) {
+        self.wake = Some(wake);
+    }
+
+    pub(crate) fn request(&mut self, job: PageImageJob) {
+        self.send(RenderJob::Page(job));
+    }
+
+    pub(crate) fn request_thumbnail(&mut self, job: ThumbnailJob) {
+        self.send(RenderJob::Thumbnail(job));
+    }
+
+    pub(crate) fn request_cover(&mut self, job: CoverJob) {
+        self.send(RenderJob::Cover(job));
+    }
+
+    /// Renders these library grid covers, dropping any still queued from an earlier call.
+    pub(crate) fn request_grid_covers(&mut self, jobs: Vec<CoverJob>) {
+        self.send(RenderJob::GridCovers(jobs));
+    }
+
+    pub(crate) fn request_page_count(&mut self, job: PageCountJob) {
+        self.send(RenderJob::PageCount(job));
+    }
+
+    /// Tells these books' kinds after everything else; they are added to those still queued.
+    pub(crate) fn request_book_kinds(&mut self, jobs: Vec<BookKindJob>) {
+        self.send(RenderJob::BookKinds(jobs));
+    }
+
+    pub(crate) fn request_export(&mut self, job: ExportJob) {
+        self.send(RenderJob::Export(job));
+    }
+
+    /// Has the worker look for Pdfium again before its next render.
+    pub(crate) fn recheck_pdfium(&mut self) {
+        self.send(RenderJob::RecheckPdfium);
+    }
+
+    pub(crate) fn try_recv(&self) -> Option<RenderDone> {
+        self.results.as_ref()?.try_recv().ok()
+    }
+
+    fn send(&mut self, job: RenderJob) {
+        let job = match &self.jobs {
+            Some(jobs) => match jobs.send(job) {
+                Ok(()) => return,
+                Err(mpsc::SendError(job)) => job,
+            },
+            None => job,
+        };
+        // Not started yet, or the worker thread is gone: start a fresh one.
+        let _ = self.spawn().send(job);
+    }
+
+    fn spawn(&mut self) -> &Sender<RenderJob> {
+        let (job_tx, job_rx) = mpsc::channel::<RenderJob>();
+        let (done_tx, done_rx) = mpsc::channel::<RenderDone>();
+        let wake = self.wake.clone();
+        let _ = thread::Builder::new()
+            .name("bookshelf-page-render".to_string())
+            .spawn(move || {
+                let engine = Engine::new();
+                let mut queue = JobQueue::default();
+                loop {
+                    if queue.is_empty() {
+                        match job_rx.recv() {
+                            Ok(job) => queue.push(job),
+                            Err(_) => break,
+                        }
+                    }
+                    while let Ok(job) = job_rx.try_recv() {
+                        queue.push(job);
+                    }
+                    if std::mem::take(&mut queue.recheck_pdfium) {
+                        let _ = engine.recheck_pdfium();
+                        continue;
+                    }
+                    let done = if let Some(job) = queue.page.take() {
+                        RenderDone::Page(render_page_job(&engine, job))
+                    } else if let Some(job) = queue.page_count.take() {
+                        RenderDone::PageCount(count_pages_job(&engine, job))
+                    } else if let Some(job) = queue.cover.take() {
+                        RenderDone::Cover(render_cover_job(&engine, job, COVER_WIDTH_PX))
+                    } else if let Some(job) = &mut queue.export {
+                        let page = job.first;
+                        let done = export_page_job(&engine, job, page);
+                        if page >= job.last {
+                            queue.export = None;
+                        } else {
+                            job.first += 1;
+                        }
+                        RenderDone::ExportPage(done)
+                    } else if let Some(job) = queue.thumbnails.pop_front() {
+                        RenderDone::Thumbnail(render_thumbnail_job(&engine, job))
+                    } else if let Some(job) = queue.grid_covers.pop_front() {
+                        RenderDone::GridCover(render_cover_job(&engine, job, THUMBNAIL_WIDTH_PX))
+                    } else if let Some(job) = queue.book_kinds.pop_front() {
+                        match book_kind_job(&engine, job) {
+                            Some(done) => RenderDone::PageCount(done),
+                            None => continue,
+                        }
+                    } else {
+                        continue;
+                    };
+                    if done_tx.send(done).is_err() {
+                        break;
+                    }
+                    if let Some(wake) = &wake {
+                        wake();
+                    }
+                }
+            });
+        self.results = Some(done_rx);
+        self.jobs.insert(job_tx)
+    }
+}
+
+/// Jobs waiting on the worker thread.
+#[derive(Default)]
+struct JobQueue {
+    page: Option<PageImageJob>,
+    page_count: Option<PageCountJob>,
+    cover: Option<CoverJob>,
+    /// The export in progress; `first` is the next page to write.
+    export: Option<ExportJob>,
+    thumbnails: VecDeque<ThumbnailJob>,
+    grid_covers: VecDeque<CoverJob>,
+    book_kinds: VecDeque<BookKindJob>,
+    recheck_pdfium: bool,
+}
+
+impl JobQueue {
+    fn is_empty(&self) -> bool {
+        self.page.is_none()
+            && self.page_count.is_none()
+            && self.cover.is_none()
+            && self.export.is_none()
+            && self.thumbnails.is_empty()
+            && self.grid_covers.is_empty()
+            && self.book_kinds.is_empty()
+            && !self.recheck_pdfium
+    }
+
+    fn push(&mut self, job: RenderJob) {
+        match job {
+            RenderJob::Page(job) => self.page = Some(job),
+            RenderJob::PageCount(job) => self.page_count = Some(job),
+            RenderJob::Cover(job) => self.cover = Some(job),
+            RenderJob::GridCovers(jobs) => self.grid_covers = jobs.into(),
+            RenderJob::BookKinds(jobs) => self.book_kinds.extend(jobs),
+            RenderJob::Export(job) => self.export = Some(job),
+            RenderJob::RecheckPdfium => self.recheck_pdfium = true,
+            RenderJob::Thumbnail(job) => {
+                // Thumbnails for a book the reader has left are no longer wanted.
+                self.thumbnails
+                    .retain(|queued| queued.book.path == job.book.path);
+                self.thumbnails.push_back(job);
+            }
+        }
+    }
+}
+
+fn render_page_job(engine: &Engine, job: PageImageJob) -> PageImageDone {
+    let book_path = job.book.path.clone();
+    let key = job.key;
+    panic::catch_unwind(AssertUnwindSafe(|| render_job(engine, job))).unwrap_or_else(|_| {
+        PageImageDone {
+            book_path,
+            key,
+            render_width_px: 0,
+            rasterize_ms: 0,
+            image: Err(anyhow::anyhow!("page render panicked")),
+        }
+    })
+}
+
+fn render_thumbnail_job(engine: &Engine, job: ThumbnailJob) -> ThumbnailDone {
+    let image = panic::catch_unwind(AssertUnwindSafe(|| {
+        render_page_image(engine, &job.book, job.page, THUMBNAIL_WIDTH_PX)
+    }))
+    .unwrap_or_else(|_| Err(anyhow::anyhow!("thumbnail render panicked")));
+    ThumbnailDone {
+        book_path: job.book.path,
+        page: job.page,
+        image,
+    }
+}
+
+fn render_cover_job(engine: &Engine, job: CoverJob, width_px: u32) -> CoverDone {
+    let image = panic::catch_unwind(AssertUnwindSafe(|| {
+        render_page_image(engine, &job.book, 0, width_px)
+    }))
+    .unwrap_or_else(|_| Err(anyhow::anyhow!("cover render panicked")));
+    CoverDone {
+        book_path: job.book.path,
+        image,
+    }
+}
+
+fn export_page_job(engine: &Engine, job: &ExportJob, page: u32) -> ExportPageDone {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| export_page(engine, job, page)))
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("page render panicked")));
+    ExportPageDone {
+        book_path: job.book.path.clone(),
+        page,
+        result,
+    }
+}
+
+fn export_page(engine: &Engine, job: &ExportJob, page: u32) -> anyhow::Result<PathBuf> {
+    let (page_w_pt, _) = engine.page_size_points(&job.book, page)?;
+    let width_px = (page_w_pt * job.dpi as f32 / 72.0).round().max(1.0) as u32;
+    let image = render_page_image(engine, &job.book, page, width_px)?;
+    let path = bookshelf_core::decode_path(&job.book.path);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "page".to_string());
+    let out = job.dir.join(export_file_name(&stem, page + 1));
+    image.save_with_format(&out, image::ImageFormat::Png)?;
+    Ok(out)
+}
+
+fn count_pages_job(engine: &Engine, job: PageCountJob) -> PageCountDone {
+    let counted = panic::catch_unwind(AssertUnwindSafe(|| {
+        let pages = engine.page_count(&job.book)?;
+        anyhow::Ok((pages, engine.book_kind(&job.book).ok()))
+    }))
+    .unwrap_or_else(|_| Err(anyhow::anyhow!("page count panicked")));
+    PageCountDone {
+        book_path: job.book.path,
+        count: counted
+            .map_err(|err| err.to_string())
+            .map(|(pages, kind)| PageCount {
+                pages,
+                size_bytes: job.size_bytes,
+                modified_secs: job.modified_secs,
+                kind,
+            }),
+    }
+}
+
+/// Counts the book again when its file changed since `known`, or its kind is not known;
+/// `None` when nothing changed or the file is gone.
+fn book_kind_job(engine: &Engine, job: BookKindJob) -> Option<PageCountDone> {
+    let metadata = std::fs::metadata(bookshelf_core::decode_path(&job.book.path)).ok()?;
+    let modified_secs = file_modified_secs(&metadata);
+    let fresh = job
+        .known
+        .is_some_and(|known| known.kind.is_some() && known.is_fresh(metadata.len(), modified_secs));
+    (!fresh).then(|| {
+        count_pages_job(
+            engine,
+            PageCountJob {
+                book: job.book,
+                size_bytes: metadata.len(),
+                modified_secs,
+            },
+        )
+    })
+}
+
+/// Seconds since the Unix epoch, as page counts keep them; 0 when the filesystem has no times.
+pub(crate) fn file_modified_secs(metadata: &std::fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .and_then(|since| i64::try_from(since.as_secs()).ok())
+        .unwrap_or(0)
+}
+
+/// Pages shown in the thumbnail strip: up to `radius` on each side of `center`, shifted to stay
+/// within the book so the strip keeps its width near the first and last pages.
+pub(crate) fn thumbnail_window(center: u32, total: Option<u32>, radius: u32) -> (u32, u32) {
+    let span = radius.saturating_mul(2);
+    let mut first = center.saturating_sub(radius);
+    let mut last = first.saturating_add(span);
+    if let Some(total) = total.filter(|t| *t > 0) {
+        let max = total - 1;
+        if last > max {
+            last = max;
+            first = last.saturating_sub(span);
+        }
+    }
+    (first, last)
+}
+
+/// Returns the pages shown together with `page` in spread view as `(left, right)`.
+///
+/// With `cover_alone`, page 0 is shown by itself and spreads start on odd pages; otherwise they
+/// start on even pages. `right` is `None` past the last page.
+pub(crate) fn spread_pages(page: u32, total: Option<u32>, cover_alone: bool) -> (u32, Option<u32>) {
+    let left = if cover_alone {
+        if page == 0 {
+            return (0, None);
+        }
+        page - (1 - page % 2)
+    } else {
+        page - page % 2
+    };
+    let right = left + 1;
+    (left, total.is_none_or(|t| right < t).then_some(right))
+}
+
+fn render_job(engine: &Engine, job: PageImageJob) -> PageImageDone {
+    let start = Instant::now();
+    let key = job.key;
+    let (render_width_px, image) = match key.spread_page {
+        Some(right) => {
+            // Each half of the spread gets half the viewport and half the pixel budget. The
+            // pair is turned as a whole, so sideways halves split the viewport's height.
+            let (view_w, view_h) = if key.rotation.is_sideways() {
+                (key.viewport_px.1, key.viewport_px.0)
+            } else {
+                key.viewport_px
+            };
+            let half = PageImageKey {
+                viewport_px: ((view_w / 2).max(1), view_h),
+                max_render_pixels: key.max_render_pixels / 2,
+                rotation: PageRotation::Upright,
+                ..key
+            };
+            let (left_width, left) = render_fitted_page(engine, &job.book, &half, key.page);
+            let (right_width, right) = render_fitted_page(engine, &job.book, &half, right);
+            let image = left.and_then(|left| Ok(compose_spread(&left, &right?)));
+            (left_width + right_width, image)
+        }
+        None => render_fitted_page(engine, &job.book, &key, key.page),
+    };
+    let image = image.map(|image| rotate_page(image, key.rotation));
+    let image = if key.invert {
+        image.map(invert_luminance)
+    } else {
+        image
+    };
+    PageImageDone {
+        book_path: job.book.path,
+        key,
+        render_width_px,
+        rasterize_ms: start.elapsed().as_millis(),
+        image,
+    }
+}
+
+/// Renders `page` sized for the viewport. With auto-crop, the margins found on a probe render
+/// are cut away and the sizing treats the content box as the page, so the content fills the
+/// viewport. Returns the width the (cropped) page is rendered at.
+fn render_fitted_page(
+    engine: &Engine,
+    book: &Book,
+    key: &PageImageKey,
+    page: u32,
+) -> (u32, anyhow::Result<image::DynamicImage>) {
+    let (page_w_pt, page_h_pt) = engine.page_size_points(book, page).unwrap_or((1.0, 1.0));
+    let content = (key.auto_crop != AutoCrop::Off)
+        .then(|| render_page_image(engine, book, page, CROP_PROBE_WIDTH_PX).ok())
+        .flatten()
+        .and_then(|probe| content_box(&probe, key.auto_crop));
+    let Some(content) = content else {
+        let width = raster_width_px(key, page_w_pt, page_h_pt);
+        return (width, render_page_image(engine, book, page, width));
+    };
+
+    let content_key = PageImageKey {
+        max_render_pixels: (key.max_render_pixels as f64 * content.w * content.h) as u64,
+        ..*key
+    };
+    let width = raster_width_px(
+        &content_key,
+        page_w_pt * content.w as f32,
+        page_h_pt * content.h as f32,
+    );
+    let full_width = (f64::from(width) / content.w).round() as u32;
+    let image =
+        render_page_image(engine, book, page, full_width).map(|full| crop_to_box(&full, content));
+    (width, image)
+}
+
+/// Part of a page as fractions of its width and height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CropBox {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+/// Finds the content of a page by scanning for rows and columns that are all (or, with
+/// `Aggressive`, nearly all) near-white, and pads it slightly. `None` means keep the whole
+/// page: blank pages, pages whose content is a sliver (a lone page number or rule), and pages
+/// with no margin worth trimming.
+fn content_box(image: &image::DynamicImage, mode: AutoCrop) -> Option<CropBox> {
+    // (lightest luma counted as ink, share of a line that may be ink and still be margin,
+    // padding as a share of the page)
+    let (ink_below, noise, pad) = match mode {
+        AutoCrop::Off => return None,
+        AutoCrop::Light => (235u8, 0.0, 0.03),
+        AutoCrop::Aggressive => (200u8, 0.01, 0.015),
+    };
+    let luma = image.to_luma8();
+    let (width, height) = luma.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let mut row_ink = vec![0u32; height as usize];
+    let mut col_ink = vec![0u32; width as usize];
+    for (x, y, pixel) in luma.enumerate_pixels() {
+        if pixel.0[0] < ink_below {
+            row_ink[y as usize] += 1;
+            col_ink[x as usize] += 1;
+        }
+    }
+    let span = |ink: &[u32], across: u32| {
+        let limit = f64::from(across) * noise;
+        let is_content = |count: &u32| f64::from(*count) > limit;
+        let first = ink.iter().position(is_content)?;
+        let last = ink.iter().rposition(is_content)?;
+        let len = ink.len() as f64;
+        let start = (first as f64 / len - pad).max(0.0);
+        let end = ((last + 1) as f64 / len + pad).min(1.0);
+        Some((start, end - start))
+    };
+    let (x, w) = span(&col_ink, height)?;
+    let (y, h) = span(&row_ink, width)?;
+    if w < 0.1 || h < 0.1 || (w > 0.98 && h > 0.98) {
+        return None;
+    }
+    Some(CropBox { x, y, w, h })
+}
+
+fn crop_to_box(image: &image::DynamicImage, crop: CropBox) -> image::DynamicImage {
+    let (width, height) = (image.width(), image.height());
+    let to_px = |fraction: f64, size: u32| ((fraction * f64::from(size)).round() as u32).min(size);
+    let x = to_px(crop.x, width).min(width.saturating_sub(1));
+    let y = to_px(crop.y, height).min(height.saturating_sub(1));
+    let w = to_px(crop.w, width).clamp(1, width - x);
+    let h = to_px(crop.h, height).clamp(1, height - y);
+    image.crop_imm(x, y, w, h)
+}
+
+fn page_render_width_px(key: &PageImageKey, page_w_pt: f32, page_h_pt: f32) -> u32 {
+    const MAX_RENDER_WIDTH_PX: u32 = 8192;
+    let (viewport_w_px, viewport_h_px) = key.viewport_px;
+    let page_ratio = (page_w_pt as f64 / page_h_pt.max(1.0) as f64).clamp(0.05, 20.0);
+
+    let fit_h = (viewport_h_px as f64 * page_ratio).round().max(1.0) as u32;
+    let base_render_width_px = match key.fit {
+        ImageFit::Page if key.fit_page_to_frame => viewport_w_px.min(fit_h),
+        ImageFit::Page | ImageFit::Width => viewport_w_px,
+        ImageFit::Height => fit_h,
+    };
+
+    let render_width_px =
+        (u64::from(base_render_width_px).saturating_mul(u64::from(key.zoom_percent.max(1)))) / 100;
+    let render_width_px = render_width_px.clamp(1, i32::MAX as u64) as u32;
+
+    let max_width_by_pixels = ((key.max_render_pixels.max(1) as f64) * page_ratio)
+        .sqrt()
+        .floor()
+        .max(1.0) as u32;
+    render_width_px
+        .min(MAX_RENDER_WIDTH_PX)
+        .min(max_width_by_pixels)
+        .max(1)
+}
+
+/// Width to rasterize a page at so that, once turned by `key.rotation`, it has the size
+/// [`page_render_width_px`] gives an upright page.
+fn raster_width_px(key: &PageImageKey, page_w_pt: f32, page_h_pt: f32) -> u32 {
+    if !key.rotation.is_sideways() {
+        return page_render_width_px(key, page_w_pt, page_h_pt);
+    }
+    // A sideways page shows its height across the viewport.
+    let shown_width = page_render_width_px(key, page_h_pt, page_w_pt);
+    let ratio = f64::from(page_w_pt) / f64::from(page_h_pt.max(1.0));
+    ((f64::from(shown_width) * ratio).round() as u32).max(1)
+}
+
+fn rotate_page(image: image::DynamicImage, rotation: PageRotation) -> image::DynamicImage {
+    match rotation {
+        PageRotation::Upright => image,
+        PageRotation::Clockwise => image.rotate90(),
+        PageRotation::UpsideDown => image.rotate180(),
+        PageRotation::CounterClockwise => image.rotate270(),
+    }
+}
+
+fn render_page_image(
+    engine: &Engine,
+    book: &Book,
+    page_index: u32,
+    target_width_px: u32,
+) -> anyhow::Result<image::DynamicImage> {
+    let target_width_px = i32::try_from(target_width_px.clamp(1, i32::MAX as u32))
+        .unwrap_or(i32::MAX)
+        .max(1);
+    let bitmap = engine.render_page_bitmap_rgba(book, page_index, target_width_px, i32::MAX)?;
+    let image =
+        image::RgbaImage::from_raw(bitmap.width as u32, bitmap.height as u32, bitmap.pixels)
+            .ok_or_else(|| anyhow::anyhow!("invalid RGBA pixel buffer from pdfium"))?;
+    Ok(image::DynamicImage::ImageRgba8(image))
+}
+
+/// Flips light and dark while keeping hue: each pixel is shifted so its luma `y` becomes
+/// `255 - y`. White paper turns black, black text white, and colored figures keep their color.
+fn invert_luminance(image: image::DynamicImage) -> image::DynamicImage {
+    let mut rgba = image.into_rgba8();
+    for pixel in rgba.pixels_mut() {
+        let [r, g, b, a] = pixel.0;
+        let luma = (299 * i32::from(r) + 587 * i32::from(g) + 114 * i32::from(b)) / 1000;
+        let shift = 255 - 2 * luma;
+        let channel = |value: u8| (i32::from(value) + shift).clamp(0, 255) as u8;
+        pixel.0 = [channel(r), channel(g), channel(b), a];
+    }
+    image::DynamicImage::ImageRgba8(rgba)
+}
+
+/// Places two pages side by side, top-aligned on a white background.
+fn compose_spread(left: &image::DynamicImage, right: &image::DynamicImage) -> image::DynamicImage {
+    let width = left.width() + right.width();
+    let height = left.height().max(right.height());
+    let mut canvas = image::RgbaImage::from_pixel(width, height, image::Rgba([255, 255, 255, 255]));
+    image::imageops::replace(&mut canvas, &left.to_rgba8(), 0, 0);
+    image::imageops::replace(&mut canvas, &right.to_rgba8(), i64::from(left.width()), 0);
+    image::DynamicImage::ImageRgba8(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(zoom_percent: u16, fit_page_to_frame: bool) -> PageImageKey {
+        PageImageKey {
+            page: 0,
+            zoom_percent,
+            viewport_px: (1000, 800),
+            fit: ImageFit::Page,
+            fit_page_to_frame,
+            max_render_pixels: 100_000_000,
+            font_size: (10, 20),
+            spread_page: None,
+            invert: false,
+            auto_crop: AutoCrop::Off,
+            rotation: PageRotation::Upright,
+        }
+    }
+
+    #[test]
+    fn fit_to_frame_limits_width_by_page_ratio() {
+        // A4 portrait in an 800px tall viewport fits at ~565px wide.
+        assert_eq!(page_render_width_px(&key(100, true), 595.0, 842.0), 565);
+        assert_eq!(page_render_width_px(&key(100, false), 595.0, 842.0), 1000);
+    }
+
+    #[test]
+    fn fit_width_and_height_size_against_one_viewport_edge() {
+        let fit = |fit: ImageFit, zoom_percent: u16| PageImageKey {
+            fit,
+            ..key(zoom_percent, false)
+        };
+        // A4 portrait: width fills 1000px (and runs taller than the frame), height fills 800px.
+        assert_eq!(
+            page_render_width_px(&fit(ImageFit::Width, 100), 595.0, 842.0),
+            1000
+        );
+        assert_eq!(
+            page_render_width_px(&fit(ImageFit::Height, 100), 595.0, 842.0),
+            565
+        );
+        // A wide landscape page fit to height overflows the viewport width.
+        assert_eq!(
+            page_render_width_px(&fit(ImageFit::Height, 100), 842.0, 595.0),
+            1132
+        );
+        assert_eq!(
+            page_render_width_px(&fit(ImageFit::Width, 150), 595.0, 842.0),
+            1500
+        );
+    }
+
+    #[test]
+    fn sideways_pages_are_sized_for_the_turned_page() {
+        let sideways = PageImageKey {
+            rotation: PageRotation::Clockwise,
+            ..key(100, true)
+        };
+        // A landscape scan turned upright fits the 800px height like an A4 portrait page,
+        // so the raster is ~565px tall and as wide as the landscape page needs.
+        assert_eq!(raster_width_px(&sideways, 842.0, 595.0), 800);
+        let upright = raster_width_px(&key(100, true), 595.0, 842.0);
+        assert_eq!(upright, 565);
+
+        let page = image::DynamicImage::ImageRgba8(image::RgbaImage::new(800, 565));
+        let turned = rotate_page(page, PageRotation::Clockwise);
+        assert_eq!((turned.width(), turned.height()), (565, 800));
+        let turned = rotate_page(turned, PageRotation::UpsideDown);
+        assert_eq!((turned.width(), turned.height()), (565, 800));
+    }
+
+    #[test]
+    fn invert_luminance_flips_paper_and_keeps_hue() {
+        let pixels = [[255, 255, 255, 255], [0, 0, 0, 255], [200, 30, 30, 128]];
+        let mut page = image::RgbaImage::new(3, 1);
+        for (x, pixel) in pixels.into_iter().enumerate() {
+            page.put_pixel(x as u32, 0, image::Rgba(pixel));
+        }
+        let inverted = invert_luminance(image::DynamicImage::ImageRgba8(page)).into_rgba8();
+        assert_eq!(inverted.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(inverted.get_pixel(1, 0).0, [255, 255, 255, 255]);
+        // A dark red turns into a lighter red, alpha untouched.
+        let [r, g, b, a] = inverted.get_pixel(2, 0).0;
+        assert!(r > 200 && g > 30 && g == b && r > g, "{:?}", [r, g, b]);
+        assert_eq!(a, 128);
+    }
+
+    #[test]
+    fn zoom_scales_width_and_respects_pixel_budget() {
+        assert_eq!(page_render_width_px(&key(200, false), 595.0, 842.0), 2000);
+        let tight = PageImageKey {
+            max_render_pixels: 10_000,
+            ..key(200, false)
+        };
+        assert!(page_render_width_px(&tight, 595.0, 842.0) <= 100);
+    }
+
+    #[test]
+    fn spread_pages_keep_cover_alone_or_pair_from_zero() {
+        assert_eq!(spread_pages(0, Some(10), true), (0, None));
+        assert_eq!(spread_pages(1, Some(10), true), (1, Some(2)));
+        assert_eq!(spread_pages(2, Some(10), true), (1, Some(2)));
+        assert_eq!(spread_pages(9, Some(10), true), (9, None));
+        assert_eq!(spread_pages(0, Some(10), false), (0, Some(1)));
+        assert_eq!(spread_pages(3, Some(10), false), (2, Some(3)));
+        assert_eq!(spread_pages(4, None, false), (4, Some(5)));
+    }
+
+    #[test]
+    fn thumbnail_window_stays_within_the_book() {
+        assert_eq!(thumbnail_window(20, Some(100), 5), (15, 25));
+        assert_eq!(thumbnail_window(2, Some(100), 5), (0, 10));
+        assert_eq!(thumbnail_window(98, Some(100), 5), (89, 99));
+        assert_eq!(thumbnail_window(1, Some(3), 5), (0, 2));
+        assert_eq!(thumbnail_window(3, None, 5), (0, 10));
+    }
+
+    fn page_with(width: u32, height: u32, ink: &[(u32, u32, u8)]) -> image::DynamicImage {
+        let mut page = image::RgbaImage::from_pixel(width, height, image::Rgba([255; 4]));
+        for &(x, y, luma) in ink {
+            page.put_pixel(x, y, image::Rgba([luma, luma, luma, 255]));
+        }
+        image::DynamicImage::ImageRgba8(page)
+    }
+
+    #[test]
+    fn content_box_trims_margins_and_keeps_blank_pages_whole() {
+        let mut text = Vec::new();
+        for x in 20..80 {
+            text.push((x, 30, 0));
+            text.push((x, 59, 0));
+        }
+        let page = page_with(100, 100, &text);
+        let light = content_box(&page, AutoCrop::Light).expect("margins");
+        assert!((light.x - 0.17).abs() < 1e-9 && (light.w - 0.66).abs() < 1e-9);
+        assert!((light.y - 0.27).abs() < 1e-9 && (light.h - 0.36).abs() < 1e-9);
+        let cropped = crop_to_box(&page, light);
+        assert_eq!((cropped.width(), cropped.height()), (66, 36));
+        assert_eq!(content_box(&page, AutoCrop::Off), None);
+
+        assert_eq!(
+            content_box(&page_with(100, 100, &[]), AutoCrop::Light),
+            None
+        );
+        assert_eq!(
+            content_box(&page_with(100, 100, &[(50, 50, 0)]), AutoCrop::Light),
+            None
+        );
+        let full = page_with(100, 100, &[(0, 0, 0), (99, 99, 0)]);
+        assert_eq!(content_box(&full, AutoCrop::Light), None);
+    }
+
+    #[test]
+    fn aggressive_crop_ignores_scanner_shadow() {
+        // A light gray shadow down the left edge, plus text in the middle.
+        let mut ink: Vec<_> = (0..100).map(|y| (2, y, 220)).collect();
+        for x in 40..60 {
+            for y in 40..60 {
+                ink.push((x, y, 0));
+            }
+        }
+        let page = page_with(100, 100, &ink);
+        let light = content_box(&page, AutoCrop::Light).expect("light");
+        assert_eq!(light.x, 0.0);
+        let aggressive = content_box(&page, AutoCrop::Aggressive).expect("aggressive");
+        assert!((aggressive.x - 0.385).abs() < 1e-9, "{aggressive:?}");
+        assert!((aggressive.w - 0.23).abs() < 1e-9, "{aggressive:?}");
+    }
+
+    #[test]
+    fn job_queue_keeps_only_the_latest_cover_and_page_count() {
+        let book = |path: &str| Book {
+            path: path.to_string(),
+            title: path.to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: Default::default(),
+            archived: false,
+        };
+        let mut queue = JobQueue::default();
+        assert!(queue.is_empty());
+        queue.push(RenderJob::Cover(CoverJob { book: book("a") }));
+        queue.push(RenderJob::Thumbnail(ThumbnailJob {
+            book: book("a"),
+            page: 3,
+        }));
+        queue.push(RenderJob::Cover(CoverJob { book: book("b") }));
+        for path in ["a", "b"] {
+            queue.push(RenderJob::PageCount(PageCountJob {
+                book: book(path),
+                size_bytes: 1,
+                modified_secs: 0,
+            }));
+        }
+        assert_eq!(
+            queue.cover.take().map(|job| job.book.path).as_deref(),
+            Some("b")
+        );
+        assert_eq!(
+            queue.page_count.take().map(|job| job.book.path).as_deref(),
+            Some("b")
+        );
+        assert_eq!(queue.thumbnails.len(), 1);
+        queue.thumbnails.clear();
+        assert!(queue.is_empty());
+
+        // A new grid cover list replaces the old one.
+        queue.push(RenderJob::GridCovers(vec![
+            CoverJob { book: book("a") },
+            CoverJob { book: book("b") },
+        ]));
+        queue.push(RenderJob::GridCovers(vec![CoverJob { book: book("c") }]));
+        let queued: Vec<_> = queue
+            .grid_covers
+            .drain(..)
+            .map(|job| job.book.path)
+            .collect();
+        assert_eq!(queued, ["c"]);
+        assert!(queue.is_empty());
+
+        // Book kinds queue up instead.
+        for path in ["a", "b"] {
+            queue.push(RenderJob::BookKinds(vec![BookKindJob {
+                book: book(path),
+                known: None,
+            }]));
+        }
+        assert_eq!(queue.book_kinds.len(), 2);
+    }
+
+    #[test]
+    fn compose_spread_places_pages_side_by_side() {
+        let black = image::Rgba([0, 0, 0, 255]);
+        let left = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(3, 4, black));
+        let right = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(2, 2, black));
+        let spread = compose_spread(&left, &right).to_rgba8();
+        assert_eq!(spread.dimensions(), (5, 4));
+        assert_eq!(*spread.get_pixel(4, 1), black);
+        assert_eq!(*spread.get_pixel(4, 3), image::Rgba([255, 255, 255, 255]));
+    }
+}
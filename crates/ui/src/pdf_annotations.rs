@@ -0,0 +1,104 @@
+use bookshelf_engine::PdfAnnotation;
+
+/// The reader's list of annotations already in the PDF, on the current page or in the whole
+/// book.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PdfAnnotationsPanel {
+    pub(crate) open: bool,
+    pub(crate) selected: usize,
+    pub(crate) annotations: Vec<PdfAnnotation>,
+    /// 1-based page the annotations were read from; `None` for the whole book.
+    pub(crate) page: Option<u32>,
+    pub(crate) error: Option<String>,
+}
+
+impl PdfAnnotationsPanel {
+    pub(crate) fn show(
+        &mut self,
+        page: Option<u32>,
+        annotations: anyhow::Result<Vec<PdfAnnotation>>,
+    ) {
+        self.open = true;
+        self.selected = 0;
+        self.page = page;
+        (self.annotations, self.error) = match annotations {
+            Ok(annotations) => (annotations, None),
+            Err(err) => (Vec::new(), Some(err.to_string())),
+        };
+    }
+
+    /// Jumps to the selected annotation: closes the panel and returns its 0-based page.
+    pub(crate) fn jump(&mut self) -> Option<u32> {
+        let page = self.annotations.get(self.selected)?.page.saturating_sub(1);
+        self.open = false;
+        Some(page)
+    }
+
+    pub(crate) fn whole_book(&self) -> bool {
+        self.page.is_none()
+    }
+
+    /// The annotation as one list row; the page is shown when the list covers the book.
+    pub(crate) fn row(&self, annotation: &PdfAnnotation) -> String {
+        let contents = if annotation.contents.is_empty() {
+            "(no text)"
+        } else {
+            annotation.contents.as_str()
+        };
+        let row = format!("{}: {contents}", annotation.kind.label());
+        if self.whole_book() {
+            format!("{row}  [p{}]", annotation.page)
+        } else {
+            row
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bookshelf_engine::{LinkRect, PdfAnnotationKind};
+
+    use super::*;
+
+    fn annotation(page: u32, kind: PdfAnnotationKind, contents: &str) -> PdfAnnotation {
+        PdfAnnotation {
+            page,
+            kind,
+            contents: contents.to_string(),
+            rect: LinkRect {
+                left: 0.0,
+                bottom: 0.0,
+                right: 10.0,
+                top: 10.0,
+            },
+        }
+    }
+
+    #[test]
+    fn pdf_annotations_jump_to_their_page() {
+        let mut panel = PdfAnnotationsPanel::default();
+        panel.show(
+            None,
+            Ok(vec![
+                annotation(2, PdfAnnotationKind::Note, "Check this"),
+                annotation(9, PdfAnnotationKind::Highlight, ""),
+            ]),
+        );
+        assert_eq!(panel.row(&panel.annotations[0]), "note: Check this  [p2]");
+        assert_eq!(
+            panel.row(&panel.annotations[1]),
+            "highlight: (no text)  [p9]"
+        );
+        panel.selected = 1;
+        assert_eq!(panel.jump(), Some(8));
+        assert!(!panel.open);
+
+        panel.show(Some(4), Ok(vec![annotation(4, PdfAnnotationKind::Ink, "")]));
+        assert_eq!(panel.row(&panel.annotations[0]), "ink: (no text)");
+
+        panel.show(Some(1), Err(anyhow::anyhow!("broken")));
+        assert_eq!(panel.error.as_deref(), Some("broken"));
+        assert_eq!(panel.jump(), None);
+        assert!(panel.open);
+    }
+}
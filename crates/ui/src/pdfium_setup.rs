@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+/// The "Image rendering unavailable" panel: what Pdfium library to install and where it was
+/// looked for.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PdfiumSetupPanel {
+    pub(crate) open: bool,
+    /// Set once the panel has been shown this session. Later render failures only show the
+    /// text fallback; the image-mode toggle still opens the panel.
+    pub(crate) shown: bool,
+    library_name: String,
+    searched: Vec<PathBuf>,
+    /// First line of the load error.
+    error: String,
+    /// Outcome of the last re-check.
+    pub(crate) status: Option<String>,
+}
+
+impl PdfiumSetupPanel {
+    pub(crate) fn show(
+        &mut self,
+        error: &anyhow::Error,
+        library_name: String,
+        searched: Vec<PathBuf>,
+    ) {
+        self.open = true;
+        self.shown = true;
+        self.library_name = library_name;
+        self.searched = searched;
+        self.error = first_line(error);
+        self.status = None;
+    }
+
+    /// Records a failed re-check; the search list is read again in case the environment
+    /// changed.
+    pub(crate) fn still_missing(&mut self, error: &anyhow::Error, searched: Vec<PathBuf>) {
+        self.searched = searched;
+        self.error = first_line(error);
+        self.status = Some(format!("Still not found: {}", self.error));
+    }
+
+    /// Body text of the panel, one entry per line.
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            "Pages cannot be drawn as images because the Pdfium library did not load.".to_string(),
+            "Text mode keeps working.".to_string(),
+            String::new(),
+            format!("Expected library: {}", self.library_name),
+            "Searched:".to_string(),
+        ];
+        lines.extend(
+            self.searched
+                .iter()
+                .map(|path| format!("  {}", path.display())),
+        );
+        lines.push("  the system library search path".to_string());
+        lines.push(String::new());
+        lines.push(format!(
+            "Install Pdfium system-wide, or place {} in one of the folders above \
+             (or point BOOKSHELF_PDFIUM_DIR at its folder), then re-check.",
+            self.library_name
+        ));
+        lines.push(format!("Error: {}", self.error));
+        lines
+    }
+}
+
+fn first_line(error: &anyhow::Error) -> String {
+    error
+        .to_string()
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("unknown error")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panel_lists_searched_paths_and_library_name() {
+        let mut panel = PdfiumSetupPanel::default();
+        let error = anyhow::anyhow!("\nlibpdfium.so: cannot open shared object file\n\nmore");
+        panel.show(
+            &error,
+            "libpdfium.so".to_string(),
+            vec![PathBuf::from("/opt/app/libpdfium.so")],
+        );
+        assert!(panel.open && panel.shown);
+        let lines = panel.lines();
+        assert!(lines.contains(&"Expected library: libpdfium.so".to_string()));
+        assert!(lines.contains(&"  /opt/app/libpdfium.so".to_string()));
+        assert!(lines.contains(&"Error: libpdfium.so: cannot open shared object file".to_string()));
+
+        panel.still_missing(&anyhow::anyhow!("not again"), Vec::new());
+        assert_eq!(panel.status.as_deref(), Some("Still not found: not again"));
+        assert!(
+            !panel
+                .lines()
+                .contains(&"  /opt/app/libpdfium.so".to_string())
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+
+/// First line of a text page whose repeated headers or footers were cut.
+pub(crate) const FURNITURE_TRIMMED_MARKER: &str = "── page furniture trimmed ──";
+
+/// Lines of `text` with no styling; used for placeholders and error messages.
+pub(crate) fn plain_lines(text: &str) -> Vec<Line<'static>> {
+    text.lines()
+        .map(|line| Line::raw(line.to_string()))
+        .collect()
+}
+
+/// A header or footer line that trimming cut, shown struck through while cut lines are
+/// revealed.
+pub(crate) fn trimmed_furniture_line(line: &str) -> Line<'static> {
+    Line::styled(
+        line.to_string(),
+        Style::default().add_modifier(Modifier::DIM | Modifier::CROSSED_OUT),
+    )
+}
+
+pub(crate) fn furniture_marker_line() -> Line<'static> {
+    Line::styled(
+        FURNITURE_TRIMMED_MARKER,
+        Style::default().add_modifier(Modifier::DIM | Modifier::ITALIC),
+    )
+}
+
+/// Lines of a page that is already wrapped for the screen, with every match of `query` drawn
+/// in `match_style`. Matching ignores case and treats any run of whitespace, line breaks
+/// included, as one space, as the in-book search does; a match the wrap split across lines
+/// is styled on each of them. Also returns the index of the first line holding a match.
+pub(crate) fn styled_page_lines(
+    text: &str,
+    query: Option<&str>,
+    match_style: Style,
+) -> (Vec<Line<'static>>, Option<usize>) {
+    let lines: Vec<&str> = text.lines().collect();
+    let ranges = query.map_or_else(Vec::new, |query| match_ranges(&lines, query));
+    let first_match = ranges.first().map(|(line, _)| *line);
+
+    let mut styled = Vec::with_capacity(lines.len());
+    let mut ranges = ranges.into_iter().peekable();
+    for (idx, line) in lines.iter().enumerate() {
+        let mut spans = Vec::new();
+        let mut pos = 0;
+        while let Some((_, (start, end))) = ranges.next_if(|(range_line, _)| *range_line == idx) {
+            if start > pos {
+                spans.push(Span::raw(line[pos..start].to_string()));
+            }
+            spans.push(Span::styled(line[start..end].to_string(), match_style));
+            pos = end;
+        }
+        if pos < line.len() || spans.is_empty() {
+            spans.push(Span::raw(line[pos..].to_string()));
+        }
+        styled.push(Line::from(spans));
+    }
+    (styled, first_match)
+}
+
+/// Columns Shift+Left/Right move a text page wider than the screen.
+pub(crate) const TEXT_PAN_COLUMNS: u16 = 8;
+
+/// Lines of one screen a text page turn keeps from the previous screen.
+pub(crate) const TEXT_PAGE_OVERLAP: u16 = 2;
+
+/// Furthest a page of `lines` scrolls in a viewport `height` rows tall: the last line sits on
+/// the bottom row rather than scrolling out of view.
+pub(crate) fn max_text_scroll(lines: usize, height: u16) -> u16 {
+    u16::try_from(lines)
+        .unwrap_or(u16::MAX)
+        .saturating_sub(height.max(1))
+}
+
+/// Rows PageUp/PageDown move in a viewport `height` rows tall, for text spaced `spacing`
+/// tenths of a line apart: the overlap keeps the same lines of text at any spacing.
+pub(crate) fn text_page_step(height: u16, spacing: u16) -> u16 {
+    let overlap = TEXT_PAGE_OVERLAP.saturating_mul(spacing.max(10)) / 10;
+    height.saturating_sub(overlap).max(1)
+}
+
+/// `text` with blank rows between its lines for a spacing of `spacing` tenths of a line: 15
+/// adds a row after every second line, 20 after each. The last line gets none.
+pub(crate) fn space_lines(text: &str, spacing: u16) -> String {
+    let extra = usize::from(spacing.saturating_sub(10));
+    if extra == 0 {
+        return text.to_string();
+    }
+    let lines: Vec<&str> = text.lines().collect();
+    let mut out = Vec::with_capacity(lines.len() * (10 + extra) / 10);
+    for (idx, line) in lines.iter().enumerate() {
+        out.push(*line);
+        if idx + 1 < lines.len() {
+            let blanks = (idx + 1) * extra / 10 - idx * extra / 10;
+            out.extend(std::iter::repeat_n("", blanks));
+        }
+    }
+    out.join("\n")
+}
+
+/// How far down a page taller than its viewport is scrolled, for the page frame: a percentage,
+/// or "bottom" once the last line is in view. `None` when the page fits.
+pub(crate) fn text_scroll_label(scroll: u16, lines: usize, height: u16) -> Option<String> {
+    let max = max_text_scroll(lines, height);
+    if max == 0 {
+        return None;
+    }
+    if scroll >= max {
+        return Some("bottom".to_string());
+    }
+    Some(format!("{}%", u32::from(scroll) * 100 / u32::from(max)))
+}
+
+/// Line index and byte range a char of the flattened page came from.
+type CharSource = (usize, usize, usize);
+
+/// Byte ranges of `query` matches, per line, in order. Ranges never cover whitespace at the
+/// edge of a line.
+fn match_ranges(lines: &[&str], query: &str) -> Vec<(usize, (usize, usize))> {
+    let needle: Vec<char> = query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .flat_map(char::to_lowercase)
+        .collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    // The page as one whitespace-collapsed string; each char remembers where it came from, and
+    // a collapsed space has no source.
+    let mut flat: Vec<(char, Option<CharSource>)> = Vec::new();
+    for (line_idx, line) in lines.iter().enumerate() {
+        for (offset, ch) in line.char_indices() {
+            if ch.is_whitespace() {
+                if flat.last().is_some_and(|(last, _)| *last != ' ') {
+                    flat.push((' ', None));
+                }
+                continue;
+            }
+            let source = Some((line_idx, offset, offset + ch.len_utf8()));
+            for lower in ch.to_lowercase() {
+                flat.push((lower, source));
+            }
+        }
+        if flat.last().is_some_and(|(last, _)| *last != ' ') {
+            flat.push((' ', None));
+        }
+    }
+
+    let mut out: Vec<(usize, (usize, usize))> = Vec::new();
+    let mut start = 0;
+    while start + needle.len() <= flat.len() {
+        let window = &flat[start..start + needle.len()];
+        if !window.iter().map(|(ch, _)| *ch).eq(needle.iter().copied()) {
+            start += 1;
+            continue;
+        }
+        // One piece per line the match touches; spaces collapsed inside a line are covered.
+        let mut pieces: Vec<(usize, (usize, usize))> = Vec::new();
+        for (line_idx, from, to) in window.iter().filter_map(|(_, source)| *source) {
+            match pieces.last_mut() {
+                Some((line, (_, end))) if *line == line_idx => *end = to,
+                _ => pieces.push((line_idx, (from, to))),
+            }
+        }
+        out.extend(pieces);
+        start += needle.len();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marked(lines: &[Line<'static>], style: Style) -> Vec<String> {
+        lines
+            .iter()
+            .flat_map(|line| line.spans.iter())
+            .filter(|span| span.style == style)
+            .map(|span| span.content.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn highlights_matches_ignoring_case() {
+        let style = Style::default().add_modifier(Modifier::REVERSED);
+        let (lines, first) = styled_page_lines(
+            "Intro\nthe Borrow checker\nborrowing rules",
+            Some("borrow"),
+            style,
+        );
+        assert_eq!(first, Some(1));
+        assert_eq!(marked(&lines, style), vec!["Borrow", "borrow"]);
+        assert_eq!(lines[1].to_string(), "the Borrow checker");
+        assert_eq!(lines[2].spans.len(), 2);
+    }
+
+    #[test]
+    fn matches_that_wrap_are_styled_on_both_lines() {
+        let style = Style::default().add_modifier(Modifier::REVERSED);
+        let (lines, first) = styled_page_lines(
+            "the   borrow\n  checker runs",
+            Some("borrow  checker"),
+            style,
+        );
+        assert_eq!(first, Some(0));
+        assert_eq!(marked(&lines, style), vec!["borrow", "checker"]);
+        assert_eq!(lines[1].to_string(), "  checker runs");
+    }
+
+    #[test]
+    fn no_query_keeps_the_text_unstyled() {
+        let (lines, first) = styled_page_lines("one\n\ntwo", None, Style::default());
+        assert_eq!(first, None);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].to_string(), "");
+        let plain: Vec<String> = plain_lines("one\n\ntwo")
+            .iter()
+            .map(Line::to_string)
+            .collect();
+        assert_eq!(plain, vec!["one", "", "two"]);
+    }
+
+    #[test]
+    fn line_spacing_adds_blank_rows_between_lines() {
+        assert_eq!(space_lines("a\nb\nc", 10), "a\nb\nc");
+        assert_eq!(space_lines("a\nb\nc\nd\ne", 15), "a\nb\n\nc\nd\n\ne");
+        assert_eq!(space_lines("a\nb\nc", 20), "a\n\nb\n\nc");
+        assert_eq!(space_lines("only", 20), "only");
+        // Scrolling sees the inflated page: 30 lines double spaced fill 59 rows.
+        let rows = space_lines(&"line\n".repeat(30), 20).lines().count();
+        assert_eq!(rows, 59);
+        assert_eq!(max_text_scroll(rows, 20), 39);
+    }
+
+    #[test]
+    fn text_scroll_stops_with_the_last_line_on_screen() {
+        assert_eq!(max_text_scroll(50, 20), 30);
+        assert_eq!(max_text_scroll(10, 20), 0);
+        assert_eq!(max_text_scroll(5, 0), 4);
+        assert_eq!(text_page_step(20, 10), 18);
+        assert_eq!(text_page_step(2, 10), 1);
+        assert_eq!(text_page_step(20, 20), 16);
+
+        assert_eq!(text_scroll_label(0, 10, 20), None);
+        assert_eq!(text_scroll_label(0, 50, 20).as_deref(), Some("0%"));
+        assert_eq!(text_scroll_label(15, 50, 20).as_deref(), Some("50%"));
+        assert_eq!(text_scroll_label(30, 50, 20).as_deref(), Some("bottom"));
+    }
+}
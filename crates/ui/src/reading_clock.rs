@@ -0,0 +1,112 @@
+use std::time::{Duration, Instant};
+
+/// Time after the last key press that still counts as reading.
+pub(crate) const READING_IDLE_AFTER: Duration = Duration::from_secs(120);
+
+/// Accumulates active reading time for the open book.
+///
+/// Time between ticks counts when the clock was counting over that span and the reader pressed a
+/// key within `READING_IDLE_AFTER`; idle gaps beyond that are dropped.
+#[derive(Debug, Default)]
+pub(crate) struct ReadingClock {
+    book: Option<String>,
+    counting: bool,
+    last_tick: Option<Instant>,
+    last_input: Option<Instant>,
+    /// Counted time below a whole second, carried to the next tick.
+    carry: Duration,
+}
+
+impl ReadingClock {
+    /// Records a key press. Tick first so the gap before it is judged against the older press.
+    pub(crate) fn input(&mut self, now: Instant) {
+        self.last_input = Some(now);
+    }
+
+    /// Credits the time since the previous tick to the book read over it, then switches to
+    /// `book` and `counting` for the next span. Returns whole seconds to add for that book.
+    pub(crate) fn tick(
+        &mut self,
+        book: Option<&str>,
+        counting: bool,
+        now: Instant,
+    ) -> Option<(String, u64)> {
+        let mut credited = None;
+        if self.counting
+            && let (Some(path), Some(last_tick), Some(last_input)) =
+                (self.book.as_ref(), self.last_tick, self.last_input)
+        {
+            let end = now.min(last_input + READING_IDLE_AFTER);
+            if end > last_tick {
+                self.carry += end - last_tick;
+            }
+            let secs = self.carry.as_secs();
+            if secs > 0 {
+                self.carry -= Duration::from_secs(secs);
+                credited = Some((path.clone(), secs));
+            }
+        }
+
+        if self.book.as_deref() != book {
+            // Opening a book counts as activity; the sub-second remainder from the previous
+            // book is dropped.
+            self.book = book.map(str::to_string);
+            self.carry = Duration::ZERO;
+            self.last_input = Some(now);
+        }
+        self.counting = counting && self.book.is_some();
+        self.last_tick = Some(now);
+        credited
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_active_spans_and_drops_idle_gaps() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut clock = ReadingClock::default();
+
+        assert_eq!(clock.tick(Some("/a"), true, at(0)), None);
+        assert_eq!(
+            clock.tick(Some("/a"), true, at(30)),
+            Some(("/a".into(), 30))
+        );
+        // Nothing pressed since opening: only the idle window after it counts.
+        assert_eq!(
+            clock.tick(Some("/a"), true, at(600)),
+            Some(("/a".into(), 90))
+        );
+        clock.input(at(600));
+        assert_eq!(
+            clock.tick(Some("/a"), false, at(610)),
+            Some(("/a".into(), 10))
+        );
+        // Paused span (library overlay) is skipped.
+        assert_eq!(clock.tick(Some("/a"), true, at(650)), None);
+        assert_eq!(
+            clock.tick(Some("/b"), true, at(660)),
+            Some(("/a".into(), 10))
+        );
+        assert_eq!(clock.tick(None, false, at(665)), Some(("/b".into(), 5)));
+        assert_eq!(clock.tick(None, false, at(900)), None);
+    }
+
+    #[test]
+    fn carries_partial_seconds() {
+        let start = Instant::now();
+        let mut clock = ReadingClock::default();
+        clock.tick(Some("/a"), true, start);
+        let mut total = 0;
+        for step in 1..=10 {
+            let now = start + Duration::from_millis(250 * step);
+            total += clock
+                .tick(Some("/a"), true, now)
+                .map_or(0, |(_, secs)| secs);
+        }
+        assert_eq!(total, 2);
+    }
+}
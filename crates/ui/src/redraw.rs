@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+/// A resize is drawn once no further resize has arrived for this long, so dragging a window
+/// edge costs one frame rather than one per event.
+pub(crate) const RESIZE_DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Decides when the event loop draws a frame. Anything that changes what is on screen marks
+/// it dirty; a key handler that changed nothing says so with [`Redraw::ignore_key`].
+#[derive(Debug)]
+pub(crate) struct Redraw {
+    dirty: bool,
+    /// The last resize, while it is still settling.
+    resized_at: Option<Instant>,
+    /// Set by a key handler for a key that left the screen as it was.
+    key_ignored: bool,
+}
+
+impl Default for Redraw {
+    /// Dirty, for the first frame.
+    fn default() -> Self {
+        Self {
+            dirty: true,
+            resized_at: None,
+            key_ignored: false,
+        }
+    }
+}
+
+impl Redraw {
+    pub(crate) fn mark(&mut self) {
+        self.dirty = true;
+    }
+
+    pub(crate) fn resized(&mut self, now: Instant) {
+        self.dirty = true;
+        self.resized_at = Some(now);
+    }
+
+    /// Called before a key is handled.
+    pub(crate) fn begin_key(&mut self) {
+        self.key_ignored = false;
+    }
+
+    /// Tells the loop the key being handled changed nothing on screen.
+    pub(crate) fn ignore_key(&mut self) {
+        self.key_ignored = true;
+    }
+
+    /// Called after a key is handled; marks the frame dirty unless the handler ignored it.
+    pub(crate) fn end_key(&mut self) {
+        if !std::mem::take(&mut self.key_ignored) {
+            self.dirty = true;
+        }
+    }
+
+    /// Whether a frame should be drawn now.
+    pub(crate) fn due(&self, now: Instant) -> bool {
+        self.dirty
+            && self
+                .resized_at
+                .is_none_or(|at| now.duration_since(at) >= RESIZE_DEBOUNCE)
+    }
+
+    pub(crate) fn drew(&mut self) {
+        self.dirty = false;
+        self.resized_at = None;
+    }
+
+    /// `idle`, cut short so a settling resize is drawn on time.
+    pub(crate) fn poll_timeout(&self, now: Instant, idle: Duration) -> Duration {
+        match self.resized_at {
+            Some(at) if self.dirty => idle.min(RESIZE_DEBOUNCE.saturating_sub(now - at)),
+            _ => idle,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignored_keys_do_not_dirty_the_frame() {
+        let mut redraw = Redraw::default();
+        let now = Instant::now();
+        assert!(redraw.due(now));
+        redraw.drew();
+
+        redraw.begin_key();
+        redraw.ignore_key();
+        redraw.end_key();
+        assert!(!redraw.due(now));
+
+        redraw.begin_key();
+        redraw.end_key();
+        assert!(redraw.due(now));
+    }
+
+    #[test]
+    fn resize_storms_draw_once_they_settle() {
+        let mut redraw = Redraw::default();
+        let start = Instant::now();
+        redraw.drew();
+
+        for ms in [0, 10, 20, 30] {
+            let now = start + Duration::from_millis(ms);
+            redraw.resized(now);
+            assert!(!redraw.due(now));
+        }
+        let last = start + Duration::from_millis(30);
+        let idle = Duration::from_millis(250);
+        assert_eq!(
+            redraw.poll_timeout(last + Duration::from_millis(20), idle),
+            Duration::from_millis(30)
+        );
+        assert!(!redraw.due(last + Duration::from_millis(49)));
+        assert!(redraw.due(last + RESIZE_DEBOUNCE));
+        redraw.drew();
+        assert_eq!(redraw.poll_timeout(last + RESIZE_DEBOUNCE, idle), idle);
+    }
+}
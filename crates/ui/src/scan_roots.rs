@@ -0,0 +1,207 @@
+use std::path::Path;
+
+use bookshelf_application::resolve_root;
+use bookshelf_core::{Book, LibraryRoot, decode_path};
+
+use crate::text_input::TextInput;
+
+/// The Scan Paths panel: a working copy of the library roots, applied on Enter.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ScanPathPanel {
+    pub(crate) open: bool,
+    pub(crate) roots: Vec<LibraryRoot>,
+    /// One row per root, then the scan-scope row.
+    pub(crate) selected: usize,
+    /// Set while a path is typed in.
+    pub(crate) edit: Option<RootEdit>,
+    pub(crate) input: TextInput,
+    pub(crate) error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RootEdit {
+    Add,
+    /// Index into `roots`.
+    Change(usize),
+}
+
+impl ScanPathPanel {
+    pub(crate) fn open(&mut self, roots: &[LibraryRoot]) {
+        *self = Self {
+            open: true,
+            roots: roots.to_vec(),
+            ..Self::default()
+        };
+    }
+
+    pub(crate) fn scope_row(&self) -> usize {
+        self.roots.len()
+    }
+
+    fn selected_root(&self) -> Option<usize> {
+        (self.selected < self.roots.len()).then_some(self.selected)
+    }
+
+    pub(crate) fn start_add(&mut self) {
+        self.edit = Some(RootEdit::Add);
+        self.input.clear();
+        self.error = None;
+    }
+
+    pub(crate) fn start_change(&mut self) {
+        if let Some(idx) = self.selected_root() {
+            self.edit = Some(RootEdit::Change(idx));
+            self.input.set(self.roots[idx].path.clone());
+            self.error = None;
+        }
+    }
+
+    /// Saves the typed path. Errors are the messages shown in the panel.
+    pub(crate) fn finish_edit(&mut self) -> Result<(), String> {
+        let Some(edit) = self.edit else {
+            return Ok(());
+        };
+        let path = self.input.as_str().trim().to_string();
+        if path.is_empty() {
+            return Err("Enter a path".to_string());
+        }
+        let taken = self
+            .roots
+            .iter()
+            .enumerate()
+            .any(|(idx, root)| root.path == path && edit != RootEdit::Change(idx));
+        if taken {
+            return Err("Path is already listed".to_string());
+        }
+        match edit {
+            RootEdit::Add => {
+                self.roots.push(LibraryRoot::new(path));
+                self.selected = self.roots.len() - 1;
+            }
+            RootEdit::Change(idx) => self.roots[idx].path = path,
+        }
+        self.edit = None;
+        self.input.clear();
+        Ok(())
+    }
+
+    pub(crate) fn remove_selected(&mut self) {
+        if let Some(idx) = self.selected_root() {
+            self.roots.remove(idx);
+            self.selected = self.selected.min(self.scope_row());
+        }
+    }
+
+    pub(crate) fn toggle_selected(&mut self) {
+        if let Some(idx) = self.selected_root() {
+            self.roots[idx].enabled = !self.roots[idx].enabled;
+        }
+    }
+
+    /// The roots to save, or the message shown when none would be scanned.
+    pub(crate) fn applied_roots(&self) -> Result<Vec<LibraryRoot>, String> {
+        if !self.roots.iter().any(|root| root.enabled) {
+            return Err("Enable at least one path".to_string());
+        }
+        Ok(self.roots.clone())
+    }
+}
+
+/// Shown after a root: whether the folder exists and how many library books are under it.
+pub(crate) fn root_status(root: &str, cwd: &Path, books: &[Book]) -> String {
+    let dir = resolve_root(root, cwd);
+    if !dir.is_dir() {
+        return "not found".to_string();
+    }
+    let count = books
+        .iter()
+        .filter(|book| decode_path(&book.path).starts_with(&dir))
+        .count();
+    match count {
+        1 => "1 book".to_string(),
+        n => format!("{n} books"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bookshelf_core::{ReadingStatus, encode_path};
+
+    use super::*;
+
+    fn typed(panel: &mut ScanPathPanel, path: &str) -> Result<(), String> {
+        panel.input.set(path);
+        panel.finish_edit()
+    }
+
+    #[test]
+    fn roots_are_added_edited_toggled_and_removed() {
+        let mut panel = ScanPathPanel::default();
+        panel.open(&[LibraryRoot::new("/books")]);
+
+        panel.start_add();
+        assert_eq!(typed(&mut panel, "  "), Err("Enter a path".to_string()));
+        assert_eq!(
+            typed(&mut panel, "/books"),
+            Err("Path is already listed".to_string())
+        );
+        assert_eq!(typed(&mut panel, " /papers "), Ok(()));
+        assert_eq!(panel.selected, 1);
+
+        panel.start_change();
+        assert_eq!(panel.input.as_str(), "/papers");
+        // Saving a root under its own path is fine.
+        assert_eq!(typed(&mut panel, "/papers"), Ok(()));
+        panel.start_change();
+        assert_eq!(typed(&mut panel, "/articles"), Ok(()));
+
+        panel.selected = 0;
+        panel.toggle_selected();
+        panel.selected = panel.scope_row();
+        panel.toggle_selected();
+        panel.remove_selected();
+        assert_eq!(
+            panel.roots,
+            vec![
+                LibraryRoot {
+                    path: "/books".to_string(),
+                    enabled: false,
+                },
+                LibraryRoot::new("/articles"),
+            ]
+        );
+
+        panel.selected = 1;
+        panel.remove_selected();
+        assert_eq!(
+            panel.applied_roots(),
+            Err("Enable at least one path".to_string())
+        );
+    }
+
+    #[test]
+    fn status_counts_books_under_an_existing_root() {
+        let dir = std::env::temp_dir();
+        let book = |path: &Path| Book {
+            path: encode_path(path),
+            title: "t".to_string(),
+            last_opened: None,
+            favorite: false,
+            author: None,
+            status: ReadingStatus::Unread,
+            archived: false,
+        };
+        let books = vec![
+            book(&dir.join("a.pdf")),
+            book(Path::new("/elsewhere/b.pdf")),
+        ];
+        assert_eq!(
+            root_status(&dir.to_string_lossy(), Path::new("/"), &books),
+            "1 book"
+        );
+        assert_eq!(
+            root_status("no-such-bookshelf-root", &dir, &books),
+            "not found"
+        );
+    }
+}
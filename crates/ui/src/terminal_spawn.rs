@@ -0,0 +1,312 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+use bookshelf_application::{BOOT_READER_ENV_VARS, DB_PATH_ENV};
+
+use crate::external_viewer::{split_words, substitute_placeholders};
+
+/// Terminals tried in order when no graphics terminal is configured, as `(program, template)`.
+/// Each renders images in a way `ratatui-image` can draw to: kitty's protocol, or sixel for foot.
+pub(crate) const GRAPHICS_TERMINALS: &[(&str, &str)] = &[
+    ("kitty", "kitty --title bookshelf -- %e"),
+    ("wezterm", "wezterm start --always-new-process -- %e"),
+    ("ghostty", "ghostty --title=bookshelf -e %e"),
+    ("foot", "foot --title bookshelf %e"),
+];
+
+/// A terminal running a second bookshelf, started by the `k` reader fallback.
+pub(crate) struct SpawnedTerminal {
+    /// Program name, for the notice.
+    pub(crate) name: String,
+    pub(crate) child: Child,
+}
+
+/// Book the spawned reader opens.
+pub(crate) struct ReaderBootstrap<'a> {
+    pub(crate) book_path: &'a str,
+    pub(crate) page_index: u32,
+}
+
+/// Opens a graphics terminal running this executable, with the book when `reader` is set and
+/// on the library database at `db_path` when given. `template` is `Settings.graphics_terminal`;
+/// empty tries `GRAPHICS_TERMINALS` in order.
+pub(crate) fn spawn_graphics_terminal(
+    template: &str,
+    reader: Option<ReaderBootstrap<'_>>,
+    db_path: Option<&Path>,
+) -> anyhow::Result<SpawnedTerminal> {
+    let exe = std::env::current_exe()?;
+    let template = match template.trim() {
+        "" => detect_template(find_on_path).ok_or_else(|| {
+            let names: Vec<&str> = GRAPHICS_TERMINALS.iter().map(|(name, _)| *name).collect();
+            anyhow::anyhow!(
+                "no graphics terminal found (tried {}); install one or set Settings → Graphics terminal",
+                names.join(", ")
+            )
+        })?,
+        template => template,
+    };
+    let mut cmd = terminal_command(template, &exe, reader.as_ref())?;
+    let name = Path::new(cmd.get_program())
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    // If the terminal is launched from within tmux or other nested environments, various env vars
+    // can leak and confuse `ratatui-image` into emitting tmux passthrough wrappers. That results in
+    // raw `_G...` sequences/base64 being printed instead of images.
+    cmd.env_remove("TMUX");
+    cmd.env_remove("TERM_PROGRAM");
+    cmd.env_remove("TERM");
+    // A boot reader this process was started with must not reopen in the child.
+    for var in BOOT_READER_ENV_VARS {
+        cmd.env_remove(var);
+    }
+    if let Some(db_path) = db_path {
+        cmd.env(DB_PATH_ENV, db_path);
+    }
+
+    // Avoid having child inherit raw-mode stdin.
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    let child = cmd
+        .spawn()
+        .map_err(|err| anyhow::anyhow!("{name}: {err}"))?;
+    Ok(SpawnedTerminal { name, child })
+}
+
+/// The template of the first `GRAPHICS_TERMINALS` entry `find` locates.
+fn detect_template(find: impl Fn(&str) -> Option<PathBuf>) -> Option<&'static str> {
+    GRAPHICS_TERMINALS
+        .iter()
+        .find(|(name, _)| {
+            find(name)
+                .or_else(|| find(&format!("{name}.exe")))
+                .is_some()
+        })
+        .map(|(_, template)| *template)
+}
+
+/// Builds the terminal command from `template`. `%e` is the executable followed by the
+/// reader's arguments; a template without it gets them appended. `%f` and `%p` are the book
+/// and its 1-based page, empty without a book. Words split as for the external viewer.
+fn terminal_command(
+    template: &str,
+    exe: &Path,
+    reader: Option<&ReaderBootstrap<'_>>,
+) -> anyhow::Result<Command> {
+    let file = reader
+        .map(|reader| bookshelf_core::decode_path(reader.book_path))
+        .unwrap_or_default();
+    let page = reader
+        .map(|reader| (reader.page_index + 1).to_string())
+        .unwrap_or_default();
+    let mut exe_args = vec![exe.as_os_str().to_os_string()];
+    if let Some(reader) = reader {
+        exe_args.extend(reader_args(reader));
+    }
+
+    let words = split_words(template);
+    if words.first().is_none_or(|word| word == "%e") {
+        anyhow::bail!("graphics terminal command names no terminal");
+    }
+    let has_exe = words.iter().any(|word| word == "%e");
+    let mut args: Vec<OsString> = Vec::new();
+    for word in words {
+        if word == "%e" {
+            args.extend(exe_args.iter().cloned());
+        } else {
+            let word = substitute_placeholders(&word, &file.to_string_lossy(), &page);
+            if !word.is_empty() {
+                args.push(word.into());
+            }
+        }
+    }
+    if !has_exe {
+        args.extend(exe_args);
+    }
+    let mut command = Command::new(&args[0]);
+    command.args(&args[1..]);
+    Ok(command)
+}
+
+/// Arguments that open the book in the child's reader, in image mode.
+fn reader_args(reader: &ReaderBootstrap<'_>) -> Vec<OsString> {
+    vec![
+        "--open".into(),
+        bookshelf_core::decode_path(reader.book_path).into_os_string(),
+        "--page".into(),
+        (reader.page_index + 1).to_string().into(),
+        "--mode".into(),
+        "image".into(),
+    ]
+}
+
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    for dir in std::env::split_paths(&paths) {
+        if dir.as_os_str().is_empty() {
+            continue;
+        }
+        let candidate = dir.join(name);
+        if is_probably_executable(&candidate) {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
+fn is_probably_executable(path: &Path) -> bool {
+    if !path.is_file() {
+        return false;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        if let Ok(meta) = std::fs::metadata(path) {
+            return meta.permissions().mode() & 0o111 != 0;
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        return true;
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn parts(command: &Command) -> Vec<String> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|part| part.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn reader_is_passed_as_arguments() {
+        let args = reader_args(&ReaderBootstrap {
+            book_path: "/books/My Book.pdf",
+            page_index: 4,
+        });
+        assert_eq!(
+            args,
+            [
+                "--open",
+                "/books/My Book.pdf",
+                "--page",
+                "5",
+                "--mode",
+                "image"
+            ]
+            .map(OsString::from)
+            .to_vec()
+        );
+    }
+
+    #[test]
+    fn templates_place_the_executable_and_book() -> anyhow::Result<()> {
+        let exe = Path::new("/bin/bookshelf");
+        let reader = ReaderBootstrap {
+            book_path: "/books/a.pdf",
+            page_index: 1,
+        };
+
+        let command = terminal_command(GRAPHICS_TERMINALS[1].1, exe, Some(&reader))?;
+        assert_eq!(
+            parts(&command),
+            vec![
+                "wezterm",
+                "start",
+                "--always-new-process",
+                "--",
+                "/bin/bookshelf",
+                "--open",
+                "/books/a.pdf",
+                "--page",
+                "2",
+                "--mode",
+                "image",
+            ]
+        );
+
+        // Without `%e` the executable goes last; `%f`/`%p` are dropped without a book.
+        let command = terminal_command("alacritty --title \"%f p%p\" -e", exe, None)?;
+        assert_eq!(
+            parts(&command),
+            vec!["alacritty", "--title", " p", "-e", "/bin/bookshelf"]
+        );
+
+        // A book whose name holds a placeholder is passed through untouched.
+        let reader = ReaderBootstrap {
+            book_path: "/books/100%p.pdf",
+            page_index: 0,
+        };
+        let command = terminal_command("foot --title \"%f p%p\" %e", exe, Some(&reader))?;
+        assert_eq!(parts(&command)[2], "/books/100%p.pdf p1");
+        assert!(terminal_command("  ", exe, None).is_err());
+        assert!(terminal_command("%e", exe, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn detection_takes_the_first_terminal_found() {
+        let only =
+            |wanted: &'static str| move |name: &str| (name == wanted).then(|| PathBuf::from(name));
+        assert_eq!(
+            detect_template(only("kitty")),
+            Some(GRAPHICS_TERMINALS[0].1)
+        );
+        assert_eq!(
+            detect_template(only("ghostty.exe")),
+            Some(GRAPHICS_TERMINALS[2].1)
+        );
+        assert_eq!(detect_template(|_: &str| None), None);
+    }
+
+    #[test]
+    fn finds_kitty_on_path() {
+        let base =
+            std::env::temp_dir().join(format!("bookshelf-kitty-spawn-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(&base).unwrap();
+        let bin = base.join("kitty");
+        fs::write(&bin, b"#!/bin/sh\nexit 0\n").unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            let mut perms = fs::metadata(&bin).unwrap().permissions();
+            perms.set_mode(0o755);
+            fs::set_permissions(&bin, perms).unwrap();
+        }
+
+        let prev = std::env::var_os("PATH");
+        unsafe {
+            std::env::set_var("PATH", &base);
+        }
+        let found = find_on_path("kitty");
+        if let Some(prev) = prev {
+            unsafe {
+                std::env::set_var("PATH", prev);
+            }
+        } else {
+            unsafe {
+                std::env::remove_var("PATH");
+            }
+        }
+
+        assert_eq!(found.as_deref(), Some(bin.as_path()));
+        let _ = fs::remove_dir_all(&base);
+    }
+}
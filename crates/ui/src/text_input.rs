@@ -0,0 +1,333 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Text being typed into a panel, with a cursor that moves by grapheme cluster so emoji and
+/// combining marks are edited as the single character they show as.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct TextInput {
+    text: String,
+    /// Byte offset of the cursor; always on a grapheme boundary.
+    cursor: usize,
+}
+
+impl TextInput {
+    /// `text` with the cursor at its end.
+    pub(crate) fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        let cursor = text.len();
+        Self { text, cursor }
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text and puts the cursor at its end.
+    pub(crate) fn set(&mut self, text: impl Into<String>) {
+        *self = Self::new(text);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    pub(crate) fn insert(&mut self, ch: char) {
+        self.text.insert(self.cursor, ch);
+        self.cursor += ch.len_utf8();
+        // A combining mark joins the grapheme before it; keep the cursor on a boundary.
+        self.cursor = self.boundary_at_or_after(self.cursor);
+    }
+
+    /// Removes the grapheme before the cursor.
+    pub(crate) fn backspace(&mut self) {
+        let start = self.prev_boundary(self.cursor);
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Removes the grapheme under the cursor.
+    pub(crate) fn delete(&mut self) {
+        let end = self.next_boundary(self.cursor);
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    pub(crate) fn move_left(&mut self) {
+        self.cursor = self.prev_boundary(self.cursor);
+    }
+
+    pub(crate) fn move_right(&mut self) {
+        self.cursor = self.next_boundary(self.cursor);
+    }
+
+    /// Moves to the start of the cursor's line.
+    pub(crate) fn move_home(&mut self) {
+        self.cursor = self.text[..self.cursor]
+            .rfind('\n')
+            .map_or(0, |idx| idx + 1);
+    }
+
+    /// Moves to the end of the cursor's line.
+    pub(crate) fn move_end(&mut self) {
+        self.cursor = self.text[self.cursor..]
+            .find('\n')
+            .map_or(self.text.len(), |idx| self.cursor + idx);
+    }
+
+    /// Removes the word before the cursor and the whitespace between it and the cursor.
+    pub(crate) fn delete_word(&mut self) {
+        let before = &self.text[..self.cursor];
+        let mut start = self.cursor;
+        let mut in_word = false;
+        for (idx, grapheme) in before.grapheme_indices(true).rev() {
+            let blank = grapheme.chars().all(char::is_whitespace);
+            if blank && in_word {
+                break;
+            }
+            in_word |= !blank;
+            start = idx;
+        }
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Removes everything between the start of the cursor's line and the cursor.
+    pub(crate) fn delete_to_start(&mut self) {
+        let end = self.cursor;
+        self.move_home();
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    /// Applies an editing key: printable characters, Backspace/Delete, Left/Right/Home/End,
+    /// Ctrl+W (delete word) and Ctrl+U (delete to line start). Returns whether the key was one
+    /// of these.
+    pub(crate) fn handle_key(&mut self, key: KeyEvent) -> bool {
+        self.handle_key_accepting(key, |ch| !ch.is_control())
+    }
+
+    /// Like [`Self::handle_key`], but only characters passing `accept` are typed.
+    pub(crate) fn handle_key_accepting(
+        &mut self,
+        key: KeyEvent,
+        accept: impl Fn(char) -> bool,
+    ) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('w') if ctrl => self.delete_word(),
+            KeyCode::Char('u') if ctrl => self.delete_to_start(),
+            KeyCode::Char(ch)
+                if !ctrl && !key.modifiers.contains(KeyModifiers::ALT) && accept(ch) =>
+            {
+                self.insert(ch)
+            }
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => self.move_left(),
+            KeyCode::Right => self.move_right(),
+            KeyCode::Home => self.move_home(),
+            KeyCode::End => self.move_end(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// The text as spans with the cursor cell drawn reversed; a cursor at the end is a
+    /// reversed blank. Only for single-line inputs.
+    pub(crate) fn spans(&self, style: Style) -> Vec<Span<'static>> {
+        let cursor_style = style.add_modifier(Modifier::REVERSED);
+        let end = self.next_boundary(self.cursor);
+        let under = &self.text[self.cursor..end];
+        let mut spans = vec![Span::styled(self.text[..self.cursor].to_string(), style)];
+        if under.is_empty() || under == "\n" {
+            spans.push(Span::styled(" ", cursor_style));
+        } else {
+            spans.push(Span::styled(under.to_string(), cursor_style));
+        }
+        spans.push(Span::styled(self.text[end..].to_string(), style));
+        spans
+    }
+
+    /// `label` followed by the text, with the cursor drawn as in [`Self::spans`].
+    pub(crate) fn labeled_line(&self, label: Span<'static>) -> Line<'static> {
+        let mut spans = vec![label];
+        spans.extend(self.spans(Style::default()));
+        Line::from(spans)
+    }
+
+    /// One line per line of text, with the cursor drawn as in [`Self::spans`].
+    pub(crate) fn lines(&self, style: Style) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        let mut offset = 0;
+        for line in self.text.split('\n') {
+            let end = offset + line.len();
+            if (offset..=end).contains(&self.cursor) {
+                let local = Self {
+                    text: line.to_string(),
+                    cursor: self.cursor - offset,
+                };
+                lines.push(Line::from(local.spans(style)));
+            } else {
+                lines.push(Line::styled(line.to_string(), style));
+            }
+            offset = end + 1;
+        }
+        lines
+    }
+
+    fn prev_boundary(&self, from: usize) -> usize {
+        self.text[..from]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(idx, _)| idx)
+    }
+
+    fn next_boundary(&self, from: usize) -> usize {
+        self.text[from..]
+            .graphemes(true)
+            .next()
+            .map_or(from, |grapheme| from + grapheme.len())
+    }
+
+    fn boundary_at_or_after(&self, offset: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .map(|(idx, _)| idx)
+            .chain(std::iter::once(self.text.len()))
+            .find(|idx| *idx >= offset)
+            .unwrap_or(self.text.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(ch: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(ch), KeyModifiers::CONTROL)
+    }
+
+    /// Cursor position counted in graphemes from the start.
+    fn cursor(input: &TextInput) -> usize {
+        input.text[..input.cursor].graphemes(true).count()
+    }
+
+    fn typed(text: &str) -> TextInput {
+        let mut input = TextInput::default();
+        for ch in text.chars() {
+            input.handle_key(key(KeyCode::Char(ch)));
+        }
+        input
+    }
+
+    #[test]
+    fn edits_in_the_middle_of_the_text() {
+        let mut input = typed("helo");
+        input.handle_key(key(KeyCode::Left));
+        input.handle_key(key(KeyCode::Char('l')));
+        assert_eq!(input.as_str(), "hello");
+        assert_eq!(cursor(&input), 4);
+
+        input.handle_key(key(KeyCode::Home));
+        input.handle_key(key(KeyCode::Delete));
+        input.handle_key(key(KeyCode::Char('J')));
+        assert_eq!(input.as_str(), "Jello");
+        input.handle_key(key(KeyCode::End));
+        input.handle_key(key(KeyCode::Backspace));
+        assert_eq!(input.as_str(), "Jell");
+        assert_eq!(cursor(&input), 4);
+    }
+
+    #[test]
+    fn emoji_and_combining_marks_move_and_delete_as_one() {
+        // Family emoji (ZWJ sequence), flag (regional indicators) and e + combining acute.
+        let mut input = TextInput::new("a👨‍👩‍👧🇯🇵e\u{301}");
+        assert_eq!(cursor(&input), 4);
+        input.handle_key(key(KeyCode::Backspace));
+        assert_eq!(input.as_str(), "a👨‍👩‍👧🇯🇵");
+        input.handle_key(key(KeyCode::Left));
+        input.handle_key(key(KeyCode::Backspace));
+        assert_eq!(input.as_str(), "a🇯🇵");
+        assert_eq!(cursor(&input), 1);
+        input.handle_key(key(KeyCode::Right));
+        input.handle_key(key(KeyCode::Right));
+        assert_eq!(cursor(&input), 2);
+
+        // Typing a combining mark joins it to the grapheme before the cursor.
+        let mut input = typed("e");
+        input.handle_key(key(KeyCode::Char('\u{301}')));
+        assert_eq!(cursor(&input), 1);
+        input.handle_key(key(KeyCode::Backspace));
+        assert!(input.as_str().is_empty());
+    }
+
+    #[test]
+    fn cjk_text_edits_per_character() {
+        let mut input = typed("日本語の本");
+        input.handle_key(key(KeyCode::Left));
+        input.handle_key(key(KeyCode::Left));
+        input.handle_key(key(KeyCode::Backspace));
+        assert_eq!(input.as_str(), "日本の本");
+        input.handle_key(key(KeyCode::Char('語')));
+        assert_eq!(input.as_str(), "日本語の本");
+        assert_eq!(cursor(&input), 3);
+    }
+
+    #[test]
+    fn word_and_line_deletes() {
+        let mut input = typed("borrow  checker rules  ");
+        input.handle_key(ctrl('w'));
+        assert_eq!(input.as_str(), "borrow  checker ");
+        input.handle_key(key(KeyCode::Left));
+        input.handle_key(key(KeyCode::Left));
+        input.handle_key(ctrl('w'));
+        assert_eq!(input.as_str(), "borrow  r ");
+        input.handle_key(ctrl('u'));
+        assert_eq!(input.as_str(), "r ");
+        assert_eq!(cursor(&input), 0);
+
+        let mut note = TextInput::new("first line\nsecond line");
+        note.handle_key(ctrl('u'));
+        assert_eq!(note.as_str(), "first line\n");
+        note.insert('x');
+        note.handle_key(key(KeyCode::Left));
+        note.handle_key(key(KeyCode::Left));
+        note.handle_key(key(KeyCode::Home));
+        note.insert('>');
+        assert_eq!(note.as_str(), ">first line\nx");
+    }
+
+    #[test]
+    fn filtered_and_modified_keys_are_not_typed() {
+        let mut input = TextInput::default();
+        assert!(!input.handle_key_accepting(key(KeyCode::Char('x')), |ch| ch.is_ascii_digit()));
+        assert!(input.handle_key_accepting(key(KeyCode::Char('4')), |ch| ch.is_ascii_digit()));
+        assert!(!input.handle_key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::ALT)));
+        assert!(!input.handle_key(key(KeyCode::Enter)));
+        assert_eq!(input.as_str(), "4");
+    }
+
+    #[test]
+    fn draws_the_cursor_cell() {
+        let style = Style::default();
+        let mut input = TextInput::new("日本");
+        input.move_left();
+        let spans = input.spans(style);
+        assert_eq!(spans[0].content, "日");
+        assert_eq!(spans[1].content, "本");
+        assert!(spans[1].style.add_modifier.contains(Modifier::REVERSED));
+        input.move_end();
+        assert_eq!(input.spans(style)[1].content, " ");
+
+        let note = TextInput::new("one\ntwo");
+        let lines = note.lines(style);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].to_string(), "one");
+        assert_eq!(lines[1].to_string(), "two ");
+    }
+}
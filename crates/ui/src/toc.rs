@@ -0,0 +1,138 @@
+use bookshelf_core::{Bookmark, TocItem};
+use unicode_width::UnicodeWidthChar;
+
+/// Index of the entry the reader is in at `page` (1-based): the last entry in outline order
+/// that starts on or before it. Entries without a page are skipped.
+pub(crate) fn current_toc_index(items: &[TocItem], page: u32) -> Option<usize> {
+    items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| item.page.is_some_and(|start| start <= page))
+        .map(|(idx, _)| idx)
+        .next_back()
+}
+
+/// Bookmarks for the starts of outline entries at `max_depth` or above, labeled with their
+/// titles. Entries without a page in the book, and pages already bookmarked with the same
+/// label, are skipped.
+pub(crate) fn chapter_bookmarks(
+    items: &[TocItem],
+    max_depth: usize,
+    total_pages: Option<u32>,
+    existing: &[Bookmark],
+) -> Vec<Bookmark> {
+    let mut added: Vec<Bookmark> = Vec::new();
+    for item in items.iter().filter(|item| item.depth <= max_depth) {
+        let Some(page) = item
+            .page
+            .filter(|page| *page >= 1 && total_pages.is_none_or(|total| *page <= total))
+        else {
+            continue;
+        };
+        let label = item.title.trim();
+        let known = existing
+            .iter()
+            .chain(&added)
+            .any(|bookmark| bookmark.page == page && bookmark.label == label);
+        if !known {
+            added.push(Bookmark {
+                page,
+                label: label.to_string(),
+                view: None,
+            });
+        }
+    }
+    added
+}
+
+/// Cuts `text` to at most `max_width` columns, marking a cut with `…`.
+pub(crate) fn truncate_to_width(text: &str, max_width: usize) -> String {
+    let width: usize = text.chars().filter_map(UnicodeWidthChar::width).sum();
+    if width <= max_width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0usize;
+    for ch in text.chars() {
+        let w = ch.width().unwrap_or(0);
+        if used + w + 1 > max_width {
+            break;
+        }
+        out.push(ch);
+        used += w;
+    }
+    if max_width > 0 {
+        out.push('…');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(title: &str, page: Option<u32>, depth: usize) -> TocItem {
+        TocItem {
+            title: title.to_string(),
+            page,
+            depth,
+        }
+    }
+
+    #[test]
+    fn current_entry_is_the_last_one_started() {
+        let items = vec![
+            item("Preface", Some(3), 0),
+            item("Part I", None, 0),
+            item("1 Intro", Some(10), 1),
+            item("1.1 Scope", Some(10), 2),
+            item("2 Method", Some(25), 1),
+        ];
+        assert_eq!(current_toc_index(&items, 1), None);
+        assert_eq!(current_toc_index(&items, 3), Some(0));
+        assert_eq!(current_toc_index(&items, 12), Some(3));
+        assert_eq!(current_toc_index(&items, 99), Some(4));
+        assert_eq!(current_toc_index(&[], 5), None);
+    }
+
+    #[test]
+    fn chapter_starts_become_labeled_bookmarks_once() {
+        let items = vec![
+            item("Preface", Some(3), 0),
+            item("Part I", None, 0),
+            item(" 1 Intro ", Some(10), 1),
+            item("1.1 Scope", Some(10), 2),
+            item("2 Method", Some(25), 1),
+            item("Index", Some(900), 0),
+        ];
+        let existing = vec![Bookmark {
+            page: 25,
+            label: "2 Method".to_string(),
+            view: None,
+        }];
+        let pages = |bookmarks: Vec<Bookmark>| -> Vec<(u32, String)> {
+            bookmarks.into_iter().map(|b| (b.page, b.label)).collect()
+        };
+        assert_eq!(
+            pages(chapter_bookmarks(&items, 0, Some(400), &existing)),
+            vec![(3, "Preface".to_string())]
+        );
+        assert_eq!(
+            pages(chapter_bookmarks(&items, 1, Some(400), &existing)),
+            vec![(3, "Preface".to_string()), (10, "1 Intro".to_string())]
+        );
+        // Without a page count every entry with a page counts.
+        assert_eq!(chapter_bookmarks(&items, 2, None, &[]).len(), 5);
+        // A second run adds nothing.
+        let first = chapter_bookmarks(&items, 2, None, &[]);
+        assert!(chapter_bookmarks(&items, 2, None, &first).is_empty());
+    }
+
+    #[test]
+    fn truncates_by_display_width() {
+        assert_eq!(truncate_to_width("Chapter 1", 20), "Chapter 1");
+        assert_eq!(truncate_to_width("Chapter 1", 6), "Chapt…");
+        assert_eq!(truncate_to_width("第一章 总论", 5), "第一…");
+        assert_eq!(truncate_to_width("Chapter", 0), "");
+    }
+}